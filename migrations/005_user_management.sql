@@ -0,0 +1,5 @@
+-- User Management Migration
+
+-- Disabled users keep their history but can no longer log in
+ALTER TABLE users
+ADD COLUMN active BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,16 @@
+-- Conference Calls Migration
+
+-- Participants of Telnyx conferences created from an existing call
+CREATE TABLE conference_participants (
+    id BIGSERIAL PRIMARY KEY,
+    call_id BIGINT NOT NULL REFERENCES calls(id) ON DELETE CASCADE,
+    conference_id VARCHAR(255) NOT NULL,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    call_control_id VARCHAR(255) NOT NULL,
+    muted BOOLEAN NOT NULL DEFAULT FALSE,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    left_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_conference_participants_call ON conference_participants(call_id);
+CREATE INDEX idx_conference_participants_active ON conference_participants(call_id) WHERE left_at IS NULL;
@@ -0,0 +1,8 @@
+-- Supervisor Call Monitoring Migration
+
+-- listen / whisper / barge for supervisors monitoring a call; NULL for regular participants
+ALTER TABLE conference_participants
+ADD COLUMN monitor_mode VARCHAR(20);
+
+CREATE INDEX idx_conference_participants_monitoring ON conference_participants(monitor_mode)
+    WHERE monitor_mode IS NOT NULL AND left_at IS NULL;
@@ -0,0 +1,26 @@
+-- Call Dispositions Migration
+
+-- Configurable wrap-up codes agents pick after a call
+CREATE TABLE dispositions (
+    id BIGSERIAL PRIMARY KEY,
+    code VARCHAR(50) NOT NULL UNIQUE,
+    label VARCHAR(255) NOT NULL,
+    is_success BOOLEAN NOT NULL DEFAULT FALSE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE calls
+ADD COLUMN disposition_id BIGINT REFERENCES dispositions(id),
+ADD COLUMN wrap_up_notes TEXT;
+
+CREATE INDEX idx_calls_disposition ON calls(disposition_id);
+
+-- Default disposition codes
+INSERT INTO dispositions (code, label, is_success) VALUES
+    ('SALE', 'Sale', TRUE),
+    ('CALLBACK', 'Callback', FALSE),
+    ('NOT_INTERESTED', 'Not Interested', FALSE),
+    ('WRONG_NUMBER', 'Wrong Number', FALSE),
+    ('VOICEMAIL', 'Left Voicemail', FALSE),
+    ('DO_NOT_CALL', 'Do Not Call', FALSE);
@@ -0,0 +1,4 @@
+-- Hold Music Migration
+
+-- Per-campaign hold music (falls back to HOLD_MUSIC_URL when NULL)
+ALTER TABLE campaigns ADD COLUMN hold_music_url TEXT;
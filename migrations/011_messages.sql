@@ -0,0 +1,15 @@
+-- SMS Messages Migration
+
+CREATE TABLE messages (
+    id BIGSERIAL PRIMARY KEY,
+    lead_id BIGINT REFERENCES leads(id) ON DELETE SET NULL,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    direction call_direction NOT NULL,
+    from_number VARCHAR(50) NOT NULL,
+    to_number VARCHAR(50) NOT NULL,
+    body TEXT NOT NULL,
+    telnyx_message_id VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_messages_lead ON messages(lead_id, created_at);
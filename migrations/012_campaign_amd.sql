@@ -0,0 +1,9 @@
+-- Campaign Answering Machine Detection Migration
+
+CREATE TYPE amd_mode AS ENUM ('Disabled', 'Detect', 'DetectBeep', 'GreetingEnd');
+
+ALTER TABLE campaigns ADD COLUMN amd_mode amd_mode NOT NULL DEFAULT 'Detect';
+
+-- Speak a message to answering machines instead of hanging up
+ALTER TABLE campaigns ADD COLUMN leave_voicemail BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE campaigns ADD COLUMN voicemail_message TEXT;
@@ -0,0 +1,15 @@
+-- Lead Activity Timeline Migration
+
+CREATE TYPE lead_event_type AS ENUM ('StatusChange', 'Note', 'Call');
+
+CREATE TABLE lead_events (
+    id BIGSERIAL PRIMARY KEY,
+    lead_id BIGINT NOT NULL REFERENCES leads(id) ON DELETE CASCADE,
+    event_type lead_event_type NOT NULL,
+    description TEXT NOT NULL,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    call_id BIGINT REFERENCES calls(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_lead_events_lead ON lead_events(lead_id, created_at);
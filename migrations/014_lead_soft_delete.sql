@@ -0,0 +1,6 @@
+-- Lead Soft Delete Migration
+
+-- Deleted leads keep their row so call history stays intact
+ALTER TABLE leads ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_leads_active ON leads(created_at) WHERE deleted_at IS NULL;
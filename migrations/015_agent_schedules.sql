@@ -0,0 +1,16 @@
+-- Agent Schedules Migration
+
+-- Weekly shift windows. An agent with no rows is unrestricted.
+-- day_of_week: 0 = Monday .. 6 = Sunday, times are local to timezone.
+-- A window whose end_time is before start_time runs past midnight.
+CREATE TABLE agent_schedules (
+    id BIGSERIAL PRIMARY KEY,
+    agent_id BIGINT NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    day_of_week SMALLINT NOT NULL CHECK (day_of_week BETWEEN 0 AND 6),
+    start_time TIME NOT NULL,
+    end_time TIME NOT NULL,
+    timezone VARCHAR(64) NOT NULL DEFAULT 'UTC',
+    CHECK (start_time <> end_time)
+);
+
+CREATE INDEX idx_agent_schedules_agent ON agent_schedules(agent_id);
@@ -0,0 +1,8 @@
+-- Campaign Schedule Migration
+
+-- Optional start/end instants; the scheduler activates and stops campaigns when they pass
+ALTER TABLE campaigns ADD COLUMN scheduled_start_at TIMESTAMPTZ;
+ALTER TABLE campaigns ADD COLUMN scheduled_end_at TIMESTAMPTZ;
+
+CREATE INDEX idx_campaigns_scheduled ON campaigns(scheduled_start_at, scheduled_end_at)
+    WHERE scheduled_start_at IS NOT NULL OR scheduled_end_at IS NOT NULL;
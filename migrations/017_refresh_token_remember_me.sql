@@ -0,0 +1,4 @@
+-- Remember Me Migration
+
+-- Sessions started with "remember me" keep the long lifetime when their refresh token rotates
+ALTER TABLE refresh_tokens ADD COLUMN remember_me BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+-- Campaign Caller ID Pool Migration
+
+-- Numbers a campaign can present for local-presence dialing, matched to the lead's area code
+ALTER TABLE campaigns ADD COLUMN caller_id_pool TEXT[] NOT NULL DEFAULT '{}';
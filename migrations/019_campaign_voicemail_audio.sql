@@ -0,0 +1,4 @@
+-- Campaign Voicemail Audio Migration
+
+-- Pre-recorded voicemail played to answering machines after the beep
+ALTER TABLE campaigns ADD COLUMN voicemail_audio_url TEXT;
@@ -0,0 +1,12 @@
+-- Agent Status History Migration
+
+-- One row per agent status change, used for occupancy reporting
+CREATE TABLE agent_status_history (
+    id BIGSERIAL PRIMARY KEY,
+    agent_id BIGINT NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    from_status agent_status,
+    to_status agent_status NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_agent_status_history_agent ON agent_status_history(agent_id, changed_at);
@@ -0,0 +1,6 @@
+-- Greeting Templates Migration
+
+-- Spoken when a call is answered; supports {lead_name}, {first_name}, {agent_name},
+-- {company} and {campaign_name}. The campaign's template wins over the agent's.
+ALTER TABLE campaigns ADD COLUMN greeting_template TEXT;
+ALTER TABLE agents ADD COLUMN greeting_template TEXT;
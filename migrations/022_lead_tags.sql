@@ -0,0 +1,11 @@
+-- Lead Tags Migration
+
+-- Free-form labels such as "hot", "vip" or "spanish", stored lowercase
+CREATE TABLE lead_tags (
+    lead_id BIGINT NOT NULL REFERENCES leads(id) ON DELETE CASCADE,
+    tag VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (lead_id, tag)
+);
+
+CREATE INDEX idx_lead_tags_tag ON lead_tags(tag);
@@ -0,0 +1,13 @@
+-- Automation State Migration
+
+-- Dialing progress per campaign, so running campaigns resume after a restart
+CREATE TABLE campaign_automation_state (
+    campaign_id BIGINT PRIMARY KEY REFERENCES campaigns(id) ON DELETE CASCADE,
+    is_running BOOLEAN NOT NULL DEFAULT FALSE,
+    leads_processed INTEGER NOT NULL DEFAULT 0,
+    last_dial_at TIMESTAMPTZ,
+    error_message TEXT,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_campaign_automation_state_running ON campaign_automation_state(is_running) WHERE is_running;
@@ -0,0 +1,7 @@
+-- Session Metadata Migration
+
+-- Where each session was started from, so users can review and revoke them.
+-- session_started_at is carried over when a refresh token rotates.
+ALTER TABLE refresh_tokens ADD COLUMN user_agent TEXT;
+ALTER TABLE refresh_tokens ADD COLUMN ip_address VARCHAR(64);
+ALTER TABLE refresh_tokens ADD COLUMN session_started_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
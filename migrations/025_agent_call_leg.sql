@@ -0,0 +1,6 @@
+-- Agent Call Leg Migration
+
+-- Outbound calls for human agents dial the agent separately and bridge the two legs
+ALTER TABLE calls ADD COLUMN agent_call_control_id VARCHAR(255);
+
+CREATE INDEX idx_calls_agent_call_control_id ON calls(agent_call_control_id) WHERE agent_call_control_id IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Agent Call Limit Migration
+
+-- How many calls an agent may have in progress at once. Dialing counts the
+-- agent's unfinished call records against this limit.
+ALTER TABLE agents ADD COLUMN max_concurrent_calls INTEGER NOT NULL DEFAULT 1
+    CHECK (max_concurrent_calls >= 1);
@@ -0,0 +1,5 @@
+-- Call Failure Reason Migration
+
+-- Why a call could not be connected to an agent, e.g. 'transfer_failed'
+-- when Telnyx reports a failed transfer or bridge
+ALTER TABLE calls ADD COLUMN failure_reason TEXT;
@@ -0,0 +1,18 @@
+-- Lead Notes Migration
+
+-- One row per note so each keeps its author and time. Notes previously
+-- appended to leads.notes are carried over as a single unattributed note.
+CREATE TABLE lead_notes (
+    id BIGSERIAL PRIMARY KEY,
+    lead_id BIGINT NOT NULL REFERENCES leads(id) ON DELETE CASCADE,
+    author_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_lead_notes_lead ON lead_notes(lead_id, created_at);
+
+INSERT INTO lead_notes (lead_id, content, created_at)
+SELECT id, BTRIM(notes, E'\n '), updated_at
+FROM leads
+WHERE BTRIM(COALESCE(notes, ''), E'\n ') <> '';
@@ -0,0 +1,7 @@
+-- Call Sentiment Migration
+
+-- Caller sentiment from -1 (negative) to 1 (positive), scored from the AI
+-- call transcript after hangup
+ALTER TABLE calls ADD COLUMN sentiment REAL CHECK (sentiment BETWEEN -1 AND 1);
+
+CREATE INDEX idx_calls_sentiment ON calls(sentiment) WHERE sentiment IS NOT NULL;
@@ -0,0 +1,5 @@
+-- Transcript Redaction Migration
+
+-- Card numbers, SSNs and long digit runs are masked in stored AI call
+-- transcripts unless a campaign turns it off
+ALTER TABLE campaigns ADD COLUMN redact_transcripts BOOLEAN NOT NULL DEFAULT TRUE;
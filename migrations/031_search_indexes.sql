@@ -0,0 +1,12 @@
+-- Search Indexes Migration
+
+-- Trigram indexes let the global search's ILIKE '%...%' lookups use an
+-- index instead of scanning every lead
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- CONCAT_WS isn't immutable, so the full name is built with ||; searches
+-- must use the same expression to hit the index
+CREATE INDEX idx_leads_name_trgm ON leads USING gin ((COALESCE(first_name, '') || ' ' || COALESCE(last_name, '')) gin_trgm_ops);
+CREATE INDEX idx_leads_email_trgm ON leads USING gin (email gin_trgm_ops);
+CREATE INDEX idx_leads_company_trgm ON leads USING gin (company gin_trgm_ops);
+CREATE INDEX idx_leads_phone_digits_trgm ON leads USING gin (regexp_replace(phone, '[^0-9]', '', 'g') gin_trgm_ops);
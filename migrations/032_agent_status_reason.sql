@@ -0,0 +1,6 @@
+-- Agent Status Reason Migration
+
+-- Why an agent stepped away, when they set their own status
+CREATE TYPE status_reason AS ENUM ('Break', 'Lunch', 'Training');
+
+ALTER TABLE agent_status_history ADD COLUMN reason status_reason;
@@ -0,0 +1,10 @@
+-- Notification Preferences Migration
+
+-- Which notifications each user wants; users without a row get everything
+CREATE TABLE notification_preferences (
+    user_id BIGINT PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    email_on_assignment BOOLEAN NOT NULL DEFAULT TRUE,
+    email_daily_report BOOLEAN NOT NULL DEFAULT TRUE,
+    browser_call_alerts BOOLEAN NOT NULL DEFAULT TRUE,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,7 @@
+-- Verification Resends Migration
+
+-- Resending reuses the latest token, so sends are counted per token, and a
+-- token replaced by a newer one stops working
+ALTER TABLE verification_tokens
+ADD COLUMN send_count INTEGER NOT NULL DEFAULT 1,
+ADD COLUMN superseded_at TIMESTAMPTZ;
@@ -0,0 +1,15 @@
+-- Campaign AI Settings Migration
+
+-- AI script for every call in a campaign, taking precedence over the agent's settings
+CREATE TABLE campaign_ai_settings (
+    id BIGSERIAL PRIMARY KEY,
+    campaign_id BIGINT NOT NULL UNIQUE REFERENCES campaigns(id) ON DELETE CASCADE,
+    system_prompt TEXT NOT NULL,
+    greeting_message TEXT,
+    voice_id VARCHAR(100),
+    language VARCHAR(10) NOT NULL DEFAULT 'en-US',
+    max_response_tokens INT,
+    temperature FLOAT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
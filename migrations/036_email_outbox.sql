@@ -0,0 +1,22 @@
+-- Email Outbox Migration
+
+-- Emails waiting to be delivered; a background worker sends them and retries
+-- with backoff while the SMTP server is unavailable
+CREATE TYPE email_status AS ENUM ('Pending', 'Sent', 'Failed');
+
+CREATE TABLE email_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    to_email VARCHAR(255) NOT NULL,
+    to_name VARCHAR(255),
+    subject TEXT NOT NULL,
+    html_body TEXT NOT NULL,
+    text_body TEXT NOT NULL,
+    status email_status NOT NULL DEFAULT 'Pending',
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    sent_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_email_outbox_due ON email_outbox(next_attempt_at) WHERE status = 'Pending';
@@ -0,0 +1,24 @@
+-- IVR Menus Migration
+
+-- Menu played to callers on an inbound number before they are routed
+CREATE TYPE ivr_action AS ENUM ('Queue', 'Voicemail', 'Hangup');
+
+CREATE TABLE ivr_menus (
+    id BIGSERIAL PRIMARY KEY,
+    phone_number VARCHAR(50) NOT NULL UNIQUE,
+    prompt TEXT NOT NULL,
+    -- Times the menu is repeated after no input or an invalid digit
+    max_retries INT NOT NULL DEFAULT 2,
+    timeout_seconds INT NOT NULL DEFAULT 5,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE ivr_options (
+    menu_id BIGINT NOT NULL REFERENCES ivr_menus(id) ON DELETE CASCADE,
+    digit VARCHAR(1) NOT NULL,
+    action ivr_action NOT NULL,
+    -- Campaign whose agents take the call, for Queue
+    campaign_id BIGINT REFERENCES campaigns(id) ON DELETE SET NULL,
+    PRIMARY KEY (menu_id, digit)
+);
@@ -0,0 +1,18 @@
+-- Call Recording Controls Migration
+
+-- Agents can start, pause, resume and stop recording during a call
+CREATE TYPE recording_state AS ENUM ('Off', 'Recording', 'Paused');
+
+ALTER TABLE calls ADD COLUMN recording_state recording_state NOT NULL DEFAULT 'Off';
+
+-- Who changed the recording and when, e.g. pausing while a card number is read out
+CREATE TABLE call_recording_events (
+    id BIGSERIAL PRIMARY KEY,
+    call_id BIGINT NOT NULL REFERENCES calls(id) ON DELETE CASCADE,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    action VARCHAR(20) NOT NULL,
+    state recording_state NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_call_recording_events_call ON call_recording_events(call_id, created_at);
@@ -0,0 +1,9 @@
+-- Call AMD Outcome Migration
+
+-- What answering machine detection decided, kept for reporting. Timeout means
+-- detection took too long and the call went ahead as if a person answered.
+CREATE TYPE amd_outcome AS ENUM ('Human', 'Machine', 'Fax', 'Silence', 'NotSure', 'Timeout');
+
+ALTER TABLE calls ADD COLUMN amd_outcome amd_outcome;
+
+CREATE INDEX idx_calls_amd_outcome ON calls(amd_outcome) WHERE amd_outcome IS NOT NULL;
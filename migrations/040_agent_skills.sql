@@ -0,0 +1,20 @@
+-- Agent Skills Migration
+
+-- What an agent can handle, e.g. a language, matched against calls that need it
+CREATE TABLE agent_skills (
+    agent_id BIGINT NOT NULL REFERENCES agents(id) ON DELETE CASCADE,
+    skill VARCHAR(50) NOT NULL,
+    PRIMARY KEY (agent_id, skill)
+);
+
+CREATE INDEX idx_agent_skills_skill ON agent_skills(skill);
+
+-- Skill an agent needs to take the campaign's inbound calls
+ALTER TABLE campaigns ADD COLUMN required_skill VARCHAR(50);
+
+-- Inbound numbers whose callers need an agent with a particular skill
+CREATE TABLE inbound_numbers (
+    phone_number VARCHAR(50) PRIMARY KEY,
+    required_skill VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
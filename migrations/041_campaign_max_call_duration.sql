@@ -0,0 +1,4 @@
+-- Campaign Max Call Duration Migration
+
+-- Calls longer than this are hung up by the watchdog; NULL uses MAX_CALL_SECONDS
+ALTER TABLE campaigns ADD COLUMN max_call_seconds INT;
@@ -0,0 +1,11 @@
+-- Lead Number Info Migration
+
+-- Carrier details from the last number lookup for a lead's phone
+CREATE TABLE lead_number_info (
+    lead_id BIGINT PRIMARY KEY REFERENCES leads(id) ON DELETE CASCADE,
+    carrier VARCHAR(255),
+    -- mobile, landline, voip...; mobile numbers need consent before autodialing
+    line_type VARCHAR(50),
+    caller_name VARCHAR(255),
+    looked_up_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
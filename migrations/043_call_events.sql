@@ -0,0 +1,16 @@
+-- Call Event Log Migration
+
+-- Every Telnyx webhook processed for a call, and every action we took on it,
+-- so a misbehaving call can be reconstructed after the fact
+CREATE TYPE call_event_source AS ENUM ('Webhook', 'Action');
+
+CREATE TABLE call_events (
+    id BIGSERIAL PRIMARY KEY,
+    call_id BIGINT NOT NULL REFERENCES calls(id) ON DELETE CASCADE,
+    source call_event_source NOT NULL,
+    event_type VARCHAR(100) NOT NULL,
+    payload JSONB NOT NULL DEFAULT '{}',
+    processed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_call_events_call ON call_events(call_id, id);
@@ -0,0 +1,4 @@
+-- Agent Greeting Audio Migration
+
+-- Recorded greeting played instead of the text-to-speech template
+ALTER TABLE agents ADD COLUMN greeting_audio_url TEXT;
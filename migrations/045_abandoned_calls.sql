@@ -0,0 +1,18 @@
+-- Abandoned Call Callbacks Migration
+
+-- Inbound callers who hung up while queued, called back when an agent frees up
+CREATE TYPE abandoned_callback_status AS ENUM ('Pending', 'Dialed', 'Expired');
+
+CREATE TABLE abandoned_calls (
+    id BIGSERIAL PRIMARY KEY,
+    call_id BIGINT NOT NULL REFERENCES calls(id) ON DELETE CASCADE,
+    phone_number VARCHAR(50) NOT NULL,
+    lead_id BIGINT REFERENCES leads(id) ON DELETE SET NULL,
+    abandoned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    callback_due_by TIMESTAMPTZ NOT NULL,
+    status abandoned_callback_status NOT NULL DEFAULT 'Pending',
+    callback_call_id BIGINT REFERENCES calls(id) ON DELETE SET NULL,
+    dialed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_abandoned_calls_pending ON abandoned_calls(abandoned_at) WHERE status = 'Pending';
@@ -0,0 +1,13 @@
+-- Recording Channels Migration
+
+-- Dual-channel recordings keep each party on its own channel, which makes
+-- transcription and speaker separation much more reliable
+CREATE TYPE recording_channels AS ENUM ('Single', 'Dual');
+CREATE TYPE recording_format AS ENUM ('Mp3', 'Wav');
+
+ALTER TABLE campaigns
+    ADD COLUMN recording_channels recording_channels NOT NULL DEFAULT 'Dual',
+    ADD COLUMN recording_format recording_format NOT NULL DEFAULT 'Mp3';
+
+-- How a call's recording was made, so playback and transcription know the layout
+ALTER TABLE calls ADD COLUMN recording_channels recording_channels;
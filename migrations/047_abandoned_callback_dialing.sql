@@ -0,0 +1,5 @@
+-- Abandoned Callback Dialing Migration
+
+-- A callback claimed by one dialer pass, so a concurrent pass can't dial the
+-- same caller twice
+ALTER TYPE abandoned_callback_status ADD VALUE 'Dialing' BEFORE 'Dialed';
@@ -0,0 +1,29 @@
+-- Refresh Token Sessions Migration
+
+-- A session keeps one id while its refresh token rotates, so it can be
+-- revoked by that id whichever token is current. Existing chains take the
+-- id of the login that started them.
+CREATE SEQUENCE refresh_token_session_seq;
+
+ALTER TABLE refresh_tokens ADD COLUMN session_id BIGINT;
+
+WITH RECURSIVE chain AS (
+    SELECT id, id AS session_id
+    FROM refresh_tokens
+    WHERE id NOT IN (SELECT replaced_by FROM refresh_tokens WHERE replaced_by IS NOT NULL)
+    UNION ALL
+    SELECT t.replaced_by, chain.session_id
+    FROM refresh_tokens t
+    JOIN chain ON t.id = chain.id
+    WHERE t.replaced_by IS NOT NULL
+)
+UPDATE refresh_tokens SET session_id = chain.session_id
+FROM chain
+WHERE refresh_tokens.id = chain.id;
+
+SELECT setval('refresh_token_session_seq', COALESCE((SELECT MAX(id) FROM refresh_tokens), 0) + 1, false);
+
+ALTER TABLE refresh_tokens ALTER COLUMN session_id SET DEFAULT nextval('refresh_token_session_seq');
+ALTER TABLE refresh_tokens ALTER COLUMN session_id SET NOT NULL;
+
+CREATE INDEX idx_refresh_tokens_session ON refresh_tokens(session_id);
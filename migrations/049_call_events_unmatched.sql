@@ -0,0 +1,8 @@
+-- Unmatched Call Events Migration
+
+-- Webhooks are logged before their call is looked up, so events for calls we
+-- can't find are kept too. Those rows have no call, only the Telnyx leg id.
+ALTER TABLE call_events ALTER COLUMN call_id DROP NOT NULL;
+ALTER TABLE call_events ADD COLUMN call_control_id VARCHAR(255);
+
+CREATE INDEX idx_call_events_control_id ON call_events(call_control_id) WHERE call_control_id IS NOT NULL;
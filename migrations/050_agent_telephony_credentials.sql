@@ -0,0 +1,5 @@
+-- Agent Telephony Credentials Migration
+
+-- Each agent's browser phone logs in with their own Telnyx telephony
+-- credential, created the first time they ask for a login token
+ALTER TABLE agents ADD COLUMN telephony_credential_id VARCHAR(255);
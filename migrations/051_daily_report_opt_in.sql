@@ -0,0 +1,5 @@
+-- Daily Report Opt-In Migration
+
+-- The daily report only goes to supervisors who ask for it; users with no
+-- saved preferences no longer get it
+ALTER TABLE notification_preferences ALTER COLUMN email_daily_report SET DEFAULT FALSE;
@@ -0,0 +1,16 @@
+-- Lead Event Notes Migration
+
+-- Note events point at the note they record, so deleting the note removes
+-- its text from the timeline too. Existing note events are matched to their
+-- note by lead, author and text.
+ALTER TABLE lead_events ADD COLUMN note_id BIGINT REFERENCES lead_notes(id) ON DELETE CASCADE;
+
+UPDATE lead_events e
+SET note_id = n.id
+FROM lead_notes n
+WHERE e.event_type = 'Note'
+  AND n.lead_id = e.lead_id
+  AND n.content = e.description
+  AND n.author_id IS NOT DISTINCT FROM e.user_id;
+
+CREATE INDEX idx_lead_events_note ON lead_events(note_id) WHERE note_id IS NOT NULL;
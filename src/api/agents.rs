@@ -1,5 +1,9 @@
 use crate::api::{api_client, ApiError};
-use crate::models::{Agent, CreateAgentRequest, UpdateAgentStatusRequest, AgentStatus};
+use crate::models::{
+    Agent, AgentGreeting, AgentSchedule, AgentSkills, AgentStatus, CreateAgentRequest, InboundNumber,
+    SetInboundNumberRequest, SetMyStatusRequest, StatusReason, UpdateAgentGreetingRequest, UpdateAgentScheduleRequest,
+    UpdateAgentStatusRequest,
+};
 
 pub async fn get_all_agents() -> Result<Vec<Agent>, ApiError> {
     api_client().get("/api/agents").await
@@ -13,3 +17,44 @@ pub async fn update_agent_status(agent_id: i64, status: AgentStatus) -> Result<A
     let request = UpdateAgentStatusRequest { status };
     api_client().put(&format!("/api/agents/{}/status", agent_id), &request).await
 }
+
+pub async fn set_my_status(status: AgentStatus, reason: Option<StatusReason>) -> Result<Agent, ApiError> {
+    api_client().put("/api/agents/me/status", &SetMyStatusRequest { status, reason }).await
+}
+
+pub async fn get_schedule(agent_id: i64) -> Result<AgentSchedule, ApiError> {
+    api_client().get(&format!("/api/agents/{}/schedule", agent_id)).await
+}
+
+pub async fn update_schedule(agent_id: i64, request: UpdateAgentScheduleRequest) -> Result<AgentSchedule, ApiError> {
+    api_client().put(&format!("/api/agents/{}/schedule", agent_id), &request).await
+}
+
+pub async fn get_greeting(agent_id: i64) -> Result<AgentGreeting, ApiError> {
+    api_client().get(&format!("/api/agents/{}/greeting", agent_id)).await
+}
+
+pub async fn update_greeting(agent_id: i64, request: UpdateAgentGreetingRequest) -> Result<AgentGreeting, ApiError> {
+    api_client().put(&format!("/api/agents/{}/greeting", agent_id), &request).await
+}
+
+pub async fn get_inbound_numbers() -> Result<Vec<InboundNumber>, ApiError> {
+    api_client().get("/api/inbound-numbers").await
+}
+
+pub async fn set_inbound_number(phone_number: &str, required_skill: String) -> Result<InboundNumber, ApiError> {
+    let path = format!("/api/inbound-numbers/{}", phone_number.replace('+', "%2B"));
+    api_client().put(&path, &SetInboundNumberRequest { required_skill }).await
+}
+
+pub async fn remove_inbound_number(phone_number: &str) -> Result<(), ApiError> {
+    api_client().delete(&format!("/api/inbound-numbers/{}", phone_number.replace('+', "%2B"))).await
+}
+
+pub async fn get_skills(agent_id: i64) -> Result<AgentSkills, ApiError> {
+    api_client().get(&format!("/api/agents/{}/skills", agent_id)).await
+}
+
+pub async fn update_skills(agent_id: i64, skills: Vec<String>) -> Result<AgentSkills, ApiError> {
+    api_client().put(&format!("/api/agents/{}/skills", agent_id), &AgentSkills { skills }).await
+}
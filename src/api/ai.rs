@@ -2,7 +2,10 @@
 #![allow(dead_code)]
 
 use crate::api::{api_client, ApiError};
-use crate::models::{AiAgentSettings, GlobalAiConfig, UpsertAiSettingsRequest, PromptTemplate};
+use crate::models::{
+    AiAgentSettings, CampaignAiSettings, GlobalAiConfig, PromptTemplate, UpsertAiSettingsRequest,
+    UpsertCampaignAiSettingsRequest,
+};
 
 /// Get AI settings for an agent
 pub async fn get_settings(agent_id: i64) -> Result<Option<AiAgentSettings>, ApiError> {
@@ -24,6 +27,19 @@ pub async fn delete_settings(agent_id: i64) -> Result<(), ApiError> {
     api_client().delete(&format!("/api/ai/settings/{}", agent_id)).await
 }
 
+/// Get a campaign's AI settings, which override its agents'
+pub async fn get_campaign_settings(campaign_id: i64) -> Result<Option<CampaignAiSettings>, ApiError> {
+    api_client().get(&format!("/api/campaigns/{}/ai-settings", campaign_id)).await
+}
+
+/// Create or update a campaign's AI settings
+pub async fn upsert_campaign_settings(
+    campaign_id: i64,
+    request: UpsertCampaignAiSettingsRequest,
+) -> Result<CampaignAiSettings, ApiError> {
+    api_client().put(&format!("/api/campaigns/{}/ai-settings", campaign_id), &request).await
+}
+
 /// Get global AI configuration
 pub async fn get_global_config() -> Result<GlobalAiConfig, ApiError> {
     api_client().get("/api/ai/config").await
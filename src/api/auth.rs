@@ -4,27 +4,38 @@ use crate::models::{
     VerifyEmailRequest, VerifyEmailResponse, ResendVerificationRequest,
     ResendVerificationResponse, InviteUserRequest, InviteUserResponse,
     AcceptInvitationRequest, AcceptInvitationResponse, GetInvitationRequest,
-    InvitationDetails, UserRole,
+    InvitationDetails, UserRole, RefreshTokenRequest,
 };
 
-pub async fn login(username: &str, password: &str) -> Result<LoginResponse, ApiError> {
+pub async fn login(username: &str, password: &str, remember_me: bool) -> Result<LoginResponse, ApiError> {
     let request = LoginRequest {
         username: username.to_string(),
         password: password.to_string(),
+        remember_me,
     };
 
     let response: LoginResponse = api_client()
         .post("/api/auth/login", &request)
         .await?;
 
-    // Store the token for future requests
+    // Store the tokens for future requests
     api_client().set_token(Some(response.token.clone()));
+    api_client().set_refresh_token(Some(response.refresh_token.clone()));
 
     Ok(response)
 }
 
 pub async fn logout() {
+    // Revoke the refresh token server-side; local tokens are cleared regardless
+    if let Some(refresh_token) = api_client().get_refresh_token() {
+        let request = RefreshTokenRequest { refresh_token };
+        let _: Result<(), ApiError> = api_client()
+            .post_json_no_response("/api/auth/logout", &request)
+            .await;
+    }
+
     api_client().set_token(None);
+    api_client().set_refresh_token(None);
 }
 
 pub async fn register(username: &str, email: &str, password: &str) -> Result<RegisterResponse, ApiError> {
@@ -51,8 +62,9 @@ pub async fn verify_email(token: &str) -> Result<VerifyEmailResponse, ApiError>
         .post("/api/auth/verify-email", &request)
         .await?;
 
-    // Store the token for automatic login after verification
+    // Store the tokens for automatic login after verification
     api_client().set_token(Some(response.token.clone()));
+    api_client().set_refresh_token(Some(response.refresh_token.clone()));
 
     Ok(response)
 }
@@ -105,8 +117,9 @@ pub async fn accept_invitation(token: &str, username: &str, password: &str) -> R
         .post("/api/auth/register-invitation", &request)
         .await?;
 
-    // Store the token for automatic login after accepting invitation
+    // Store the tokens for automatic login after accepting invitation
     api_client().set_token(Some(response.token.clone()));
+    api_client().set_refresh_token(Some(response.refresh_token.clone()));
 
     Ok(response)
 }
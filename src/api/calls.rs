@@ -29,3 +29,79 @@ pub async fn dial_direct(phone_number: &str, agent_id: Option<i64>) -> Result<Di
 pub async fn get_call_status(call_id: i64) -> Result<Call, ApiError> {
     api_client().get(&format!("/api/calls/{}", call_id)).await
 }
+
+/// Recently abandoned inbound calls and their callbacks
+#[cfg(target_arch = "wasm32")]
+pub async fn get_abandoned_calls() -> Result<Vec<crate::models::AbandonedCall>, ApiError> {
+    api_client().get("/api/calls/abandoned").await
+}
+
+/// Webhooks and actions logged for a call, oldest first
+#[cfg(target_arch = "wasm32")]
+pub async fn get_call_events(call_id: i64) -> Result<Vec<crate::models::CallEvent>, ApiError> {
+    api_client().get(&format!("/api/calls/{}/events", call_id)).await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub async fn get_dispositions() -> Result<Vec<crate::models::Disposition>, ApiError> {
+    api_client().get("/api/dispositions").await
+}
+
+/// Record the wrap-up disposition after a call (returns the agent to Ready)
+#[cfg(target_arch = "wasm32")]
+pub async fn set_disposition(call_id: i64, disposition_id: i64, wrap_up_notes: Option<String>) -> Result<Call, ApiError> {
+    let request = crate::models::SetDispositionRequest {
+        disposition_id,
+        wrap_up_notes,
+    };
+    api_client().put(&format!("/api/calls/{}/disposition", call_id), &request).await
+}
+
+/// Start, pause, resume or stop recording the call the agent is on
+#[cfg(target_arch = "wasm32")]
+pub async fn control_recording(
+    call_id: i64,
+    action: crate::models::RecordingAction,
+) -> Result<crate::models::RecordingStatus, ApiError> {
+    api_client()
+        .post_empty(&format!("/api/calls/{}/recording/{}", call_id, action.as_str()))
+        .await
+}
+
+/// Inbound callers waiting for an agent
+pub async fn get_queue() -> Result<Vec<crate::models::QueuedCallInfo>, ApiError> {
+    api_client().get("/api/queue").await
+}
+
+/// Server event pushed over `/api/ws`
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
+#[serde(tag = "type", content = "data")]
+pub enum ServerEvent {
+    #[serde(rename = "queue.updated")]
+    QueueUpdated { calls: Vec<crate::models::QueuedCallInfo> },
+    #[serde(rename = "call.incoming")]
+    CallIncoming {
+        #[serde(rename = "agentId")]
+        agent_id: i64,
+        #[serde(rename = "userId")]
+        user_id: Option<i64>,
+        #[serde(rename = "callId")]
+        call_id: i64,
+        from: String,
+        #[serde(rename = "screenPop")]
+        screen_pop: crate::models::ScreenPop,
+    },
+    /// Events this client doesn't handle
+    #[serde(other)]
+    Other,
+}
+
+/// WebSocket URL for server events, authenticated with the current access token
+pub fn events_url() -> Option<String> {
+    let client = api_client();
+    let base = client
+        .base_url()
+        .replacen("https://", "wss://", 1)
+        .replacen("http://", "ws://", 1);
+    client.get_token().map(|token| format!("{}/api/ws?token={}", base, token))
+}
@@ -1,5 +1,8 @@
 use crate::api::{api_client, ApiError};
-use crate::models::{Campaign, CreateCampaignRequest, DialerStatus};
+use crate::models::{
+    AttachLeadsRequest, Campaign, CampaignLeadPage, CampaignProgress, CampaignProgressReport, CreateCampaignRequest,
+    DialerStatus, ScheduleCampaignRequest,
+};
 
 pub async fn get_all_campaigns() -> Result<Vec<Campaign>, ApiError> {
     api_client().get("/api/campaigns").await
@@ -13,6 +16,30 @@ pub async fn update_campaign(id: i64, request: CreateCampaignRequest) -> Result<
     api_client().put(&format!("/api/campaigns/{}", id), &request).await
 }
 
+pub async fn schedule_campaign(id: i64, request: ScheduleCampaignRequest) -> Result<Campaign, ApiError> {
+    api_client().post(&format!("/api/campaigns/{}/schedule", id), &request).await
+}
+
+pub async fn get_campaign_leads(id: i64, page: i64, per_page: i64) -> Result<CampaignLeadPage, ApiError> {
+    api_client()
+        .get(&format!("/api/campaigns/{}/leads?page={}&per_page={}", id, page, per_page))
+        .await
+}
+
+pub async fn attach_leads(id: i64, lead_ids: Vec<i64>) -> Result<CampaignProgress, ApiError> {
+    api_client()
+        .post(&format!("/api/campaigns/{}/leads", id), &AttachLeadsRequest { lead_ids })
+        .await
+}
+
+pub async fn detach_lead(id: i64, lead_id: i64) -> Result<(), ApiError> {
+    api_client().delete(&format!("/api/campaigns/{}/leads/{}", id, lead_id)).await
+}
+
+pub async fn get_progress(id: i64) -> Result<CampaignProgressReport, ApiError> {
+    api_client().get(&format!("/api/campaigns/{}/progress", id)).await
+}
+
 pub async fn start_dialer(campaign_id: i64) -> Result<DialerStatus, ApiError> {
     api_client().post_empty(&format!("/api/campaigns/{}/start", campaign_id)).await
 }
@@ -35,6 +35,7 @@ struct ApiClientInner {
     base_url: String,
     client: Client,
     token: RwLock<Option<String>>,
+    refresh_token: RwLock<Option<String>>,
 }
 
 impl ApiClient {
@@ -54,10 +55,15 @@ impl ApiClient {
                 base_url: base_url.trim_end_matches('/').to_string(),
                 client,
                 token: RwLock::new(None),
+                refresh_token: RwLock::new(None),
             }),
         }
     }
 
+    pub fn base_url(&self) -> &str {
+        &self.inner.base_url
+    }
+
     pub fn set_token(&self, token: Option<String>) {
         let mut guard = self.inner.token.write().unwrap();
         *guard = token;
@@ -67,90 +73,107 @@ impl ApiClient {
         self.inner.token.read().unwrap().clone()
     }
 
-    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
-        let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.get(&url);
+    pub fn set_refresh_token(&self, token: Option<String>) {
+        let mut guard = self.inner.refresh_token.write().unwrap();
+        *guard = token;
+    }
 
-        if let Some(token) = self.get_token() {
-            request = request.header("Authorization", format!("Bearer {}", token));
+    pub fn get_refresh_token(&self) -> Option<String> {
+        self.inner.refresh_token.read().unwrap().clone()
+    }
+
+    /// Exchange the stored refresh token for a new access token (rotating the refresh token)
+    pub async fn refresh_access_token(&self) -> Result<(), ApiError> {
+        let refresh_token = self.get_refresh_token().ok_or(ApiError::Unauthorized)?;
+        let url = format!("{}/api/auth/refresh", self.inner.base_url);
+        let body = crate::models::RefreshTokenRequest { refresh_token };
+
+        let response = self.inner.client.post(&url).json(&body).send().await?;
+        if !response.status().is_success() {
+            self.set_token(None);
+            self.set_refresh_token(None);
+            return Err(ApiError::Unauthorized);
         }
 
-        let response = request.send().await?;
-        self.handle_response(response).await
+        let tokens: crate::models::RefreshTokenResponse = response
+            .json()
+            .await
+            .map_err(|e| ApiError::Parse(e.to_string()))?;
+        self.set_token(Some(tokens.token));
+        self.set_refresh_token(Some(tokens.refresh_token));
+        Ok(())
     }
 
-    pub async fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
-        let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.post(&url).json(body);
-
+    /// Send a request with the bearer token attached. On 401 the access token is
+    /// refreshed once (if a refresh token is available) and the request retried.
+    async fn send(&self, build: impl Fn() -> reqwest::RequestBuilder) -> Result<reqwest::Response, ApiError> {
+        let mut request = build();
         if let Some(token) = self.get_token() {
             request = request.header("Authorization", format!("Bearer {}", token));
         }
 
         let response = request.send().await?;
-        self.handle_response(response).await
-    }
+        if response.status() != StatusCode::UNAUTHORIZED || self.get_refresh_token().is_none() {
+            return Ok(response);
+        }
 
-    pub async fn post_empty<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
-        let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.post(&url);
+        self.refresh_access_token().await?;
 
+        let mut request = build();
         if let Some(token) = self.get_token() {
             request = request.header("Authorization", format!("Bearer {}", token));
         }
+        Ok(request.send().await?)
+    }
 
-        let response = request.send().await?;
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.inner.base_url, path);
+        let response = self.send(|| self.inner.client.get(&url)).await?;
         self.handle_response(response).await
     }
 
-    pub async fn put<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
+    pub async fn post<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
         let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.put(&url).json(body);
+        let response = self.send(|| self.inner.client.post(&url).json(body)).await?;
+        self.handle_response(response).await
+    }
 
-        if let Some(token) = self.get_token() {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        }
+    pub async fn post_empty<T: DeserializeOwned>(&self, path: &str) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.inner.base_url, path);
+        let response = self.send(|| self.inner.client.post(&url)).await?;
+        self.handle_response(response).await
+    }
 
-        let response = request.send().await?;
+    pub async fn put<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
+        let url = format!("{}{}", self.inner.base_url, path);
+        let response = self.send(|| self.inner.client.put(&url).json(body)).await?;
         self.handle_response(response).await
     }
 
     #[allow(dead_code)]
     pub async fn patch<T: DeserializeOwned, B: Serialize>(&self, path: &str, body: &B) -> Result<T, ApiError> {
         let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.patch(&url).json(body);
-
-        if let Some(token) = self.get_token() {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        }
-
-        let response = request.send().await?;
+        let response = self.send(|| self.inner.client.patch(&url).json(body)).await?;
         self.handle_response(response).await
     }
 
     #[allow(dead_code)]
     pub async fn delete(&self, path: &str) -> Result<(), ApiError> {
         let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.delete(&url);
-
-        if let Some(token) = self.get_token() {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        }
-
-        let response = request.send().await?;
+        let response = self.send(|| self.inner.client.delete(&url)).await?;
         self.handle_empty_response(response).await
     }
 
     #[allow(dead_code)]
     pub async fn post_no_response(&self, path: &str) -> Result<(), ApiError> {
         let url = format!("{}{}", self.inner.base_url, path);
-        let mut request = self.inner.client.post(&url);
-
-        if let Some(token) = self.get_token() {
-            request = request.header("Authorization", format!("Bearer {}", token));
-        }
+        let response = self.send(|| self.inner.client.post(&url)).await?;
+        self.handle_empty_response(response).await
+    }
 
-        let response = request.send().await?;
+    pub async fn post_json_no_response<B: Serialize>(&self, path: &str, body: &B) -> Result<(), ApiError> {
+        let url = format!("{}{}", self.inner.base_url, path);
+        let response = self.send(|| self.inner.client.post(&url).json(body)).await?;
         self.handle_empty_response(response).await
     }
 
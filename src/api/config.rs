@@ -2,15 +2,19 @@
 
 #[cfg(target_arch = "wasm32")]
 use serde::{Deserialize, Serialize};
-#[cfg(target_arch = "wasm32")]
 use super::client::{api_client, ApiError};
+use crate::models::PhoneConfig;
 
 #[cfg(target_arch = "wasm32")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebRTCConfig {
-    pub sip_username: String,
-    pub sip_password: String,
+    /// Short-lived Telnyx token the SDK logs in with
+    pub login_token: String,
     pub caller_id: String,
+    /// WebSocket URL the SDK registers against; None uses the SDK default
+    #[serde(default)]
+    pub signaling_url: Option<String>,
+    pub expires_at: String,
 }
 
 /// Fetch WebRTC configuration from server
@@ -18,3 +22,8 @@ pub struct WebRTCConfig {
 pub async fn get_webrtc_config() -> Result<WebRTCConfig, ApiError> {
     api_client().get::<WebRTCConfig>("/api/config/webrtc").await
 }
+
+/// Fetch the server's phone number settings
+pub async fn get_phone_config() -> Result<PhoneConfig, ApiError> {
+    api_client().get::<PhoneConfig>("/api/config/phone").await
+}
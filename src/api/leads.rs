@@ -1,5 +1,8 @@
 use crate::api::{api_client, ApiError};
-use crate::models::{Lead, AddNoteRequest, UpdateStatusRequest, LeadNote};
+use crate::models::{
+    Lead, LeadDetail, AddNoteRequest, UpdateStatusRequest, LeadNote, LeadEvent, AssignmentStrategy, BulkAssignRequest,
+    LeadAssignment, AddTagRequest, LeadEnrichment,
+};
 
 pub async fn get_my_leads() -> Result<Vec<Lead>, ApiError> {
     api_client().get("/api/leads/my").await
@@ -9,6 +12,19 @@ pub async fn get_lead(id: i64) -> Result<Lead, ApiError> {
     api_client().get(&format!("/api/leads/{}", id)).await
 }
 
+/// A lead with its authored notes
+pub async fn get_lead_detail(id: i64) -> Result<LeadDetail, ApiError> {
+    api_client().get(&format!("/api/leads/{}", id)).await
+}
+
+pub async fn get_notes(lead_id: i64) -> Result<Vec<LeadNote>, ApiError> {
+    api_client().get(&format!("/api/leads/{}/notes", lead_id)).await
+}
+
+pub async fn delete_note(lead_id: i64, note_id: i64) -> Result<(), ApiError> {
+    api_client().delete(&format!("/api/leads/{}/notes/{}", lead_id, note_id)).await
+}
+
 pub async fn add_note(lead_id: i64, content: &str) -> Result<LeadNote, ApiError> {
     let request = AddNoteRequest {
         content: content.to_string(),
@@ -19,3 +35,41 @@ pub async fn add_note(lead_id: i64, content: &str) -> Result<LeadNote, ApiError>
 pub async fn update_status(lead_id: i64, request: UpdateStatusRequest) -> Result<Lead, ApiError> {
     api_client().put(&format!("/api/leads/{}/status", lead_id), &request).await
 }
+
+pub async fn get_timeline(lead_id: i64) -> Result<Vec<LeadEvent>, ApiError> {
+    api_client().get(&format!("/api/leads/{}/timeline", lead_id)).await
+}
+
+pub async fn assign_bulk(lead_ids: Vec<i64>, strategy: AssignmentStrategy) -> Result<Vec<LeadAssignment>, ApiError> {
+    let request = BulkAssignRequest { lead_ids, strategy };
+    api_client().post("/api/leads/assign-bulk", &request).await
+}
+
+pub async fn get_tags(lead_id: i64) -> Result<Vec<String>, ApiError> {
+    api_client().get(&format!("/api/leads/{}/tags", lead_id)).await
+}
+
+pub async fn add_tag(lead_id: i64, tag: &str) -> Result<Vec<String>, ApiError> {
+    let request = AddTagRequest { tag: tag.to_string() };
+    api_client().post(&format!("/api/leads/{}/tags", lead_id), &request).await
+}
+
+pub async fn remove_tag(lead_id: i64, tag: &str) -> Result<(), ApiError> {
+    api_client().delete(&format!("/api/leads/{}/tags/{}", lead_id, encode_path_segment(tag))).await
+}
+
+/// Percent-encode everything but unreserved characters so tags with spaces survive the path
+fn encode_path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Look up the lead's number and fill in what's missing
+pub async fn enrich_lead(lead_id: i64) -> Result<LeadEnrichment, ApiError> {
+    api_client().post_empty(&format!("/api/leads/{}/enrich", lead_id)).await
+}
@@ -7,6 +7,7 @@ pub mod campaigns;
 pub mod config;
 pub mod ai;
 pub mod sip;
+pub mod users;
 
 pub use client::*;
 #[cfg(target_arch = "wasm32")]
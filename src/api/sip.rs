@@ -9,6 +9,17 @@ pub struct SipStatus {
     pub registered: bool,
     pub trunk_host: Option<String>,
     pub caller_id: Option<String>,
+    #[serde(default)]
+    pub trunks: Vec<SipTrunkStatus>,
+}
+
+/// Registration state of one configured trunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SipTrunkStatus {
+    pub trunk_host: String,
+    pub caller_id: String,
+    pub status: String,
+    pub registered: bool,
 }
 
 impl SipStatus {
@@ -28,6 +39,15 @@ impl SipStatus {
     }
 }
 
+/// URL of the SIP event stream. EventSource can't send headers, so the
+/// access token goes in the query string.
+pub fn sip_events_url() -> Option<String> {
+    let client = api_client();
+    client
+        .get_token()
+        .map(|token| format!("{}/api/sip/events?token={}", client.base_url(), token))
+}
+
 /// Fetch SIP trunk status from server
 pub async fn get_sip_status() -> Result<SipStatus, ApiError> {
     api_client().get::<SipStatus>("/api/sip/status").await
@@ -0,0 +1,33 @@
+use crate::api::{api_client, ApiError};
+use crate::models::{
+    ManagedUser, NotificationPreferences, SetUserDisabledRequest, UpdateUserRoleRequest, UserListResponse, UserRole,
+};
+
+pub async fn list_users(page: i64, per_page: i64) -> Result<UserListResponse, ApiError> {
+    api_client()
+        .get(&format!("/api/users?page={}&per_page={}", page, per_page))
+        .await
+}
+
+pub async fn update_user_role(user_id: i64, role: UserRole) -> Result<ManagedUser, ApiError> {
+    let request = UpdateUserRoleRequest { role };
+    api_client().put(&format!("/api/users/{}/role", user_id), &request).await
+}
+
+pub async fn set_user_disabled(user_id: i64, disabled: bool) -> Result<ManagedUser, ApiError> {
+    let request = SetUserDisabledRequest { disabled };
+    api_client().put(&format!("/api/users/{}/disable", user_id), &request).await
+}
+
+pub async fn delete_user(user_id: i64) -> Result<(), ApiError> {
+    api_client().delete(&format!("/api/users/{}", user_id)).await
+}
+
+/// The signed-in user's notification preferences
+pub async fn get_my_notifications() -> Result<NotificationPreferences, ApiError> {
+    api_client().get("/api/users/me/notifications").await
+}
+
+pub async fn update_my_notifications(prefs: &NotificationPreferences) -> Result<NotificationPreferences, ApiError> {
+    api_client().put("/api/users/me/notifications", prefs).await
+}
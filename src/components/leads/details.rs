@@ -1,5 +1,5 @@
 use dioxus::prelude::*;
-use crate::models::{Lead, LeadStatus};
+use crate::models::{LeadDetail, LeadStatus};
 use crate::api;
 use crate::state::UI_STATE;
 use crate::components::common::LoadingSpinner;
@@ -17,7 +17,7 @@ pub fn LeadDetails() -> Element {
     }
 
     let lead_id = selected_id.unwrap();
-    let mut lead = use_signal(|| None::<Lead>);
+    let mut lead = use_signal(|| None::<LeadDetail>);
     let mut is_loading = use_signal(|| true);
     let mut new_note = use_signal(String::new);
     let mut is_adding_note = use_signal(|| false);
@@ -26,7 +26,7 @@ pub fn LeadDetails() -> Element {
     use_effect(move || {
         spawn(async move {
             is_loading.set(true);
-            if let Ok(data) = api::leads::get_lead(lead_id).await {
+            if let Ok(data) = api::leads::get_lead_detail(lead_id).await {
                 lead.set(Some(data));
             }
             is_loading.set(false);
@@ -44,7 +44,7 @@ pub fn LeadDetails() -> Element {
             if api::leads::add_note(lead_id, &content).await.is_ok() {
                 new_note.set(String::new());
                 // Refresh lead
-                if let Ok(data) = api::leads::get_lead(lead_id).await {
+                if let Ok(data) = api::leads::get_lead_detail(lead_id).await {
                     lead.set(Some(data));
                 }
             }
@@ -60,8 +60,8 @@ pub fn LeadDetails() -> Element {
         return rsx! { LoadingSpinner {} };
     }
 
-    let lead_data = match lead.read().as_ref() {
-        Some(l) => l.clone(),
+    let (lead_data, note_entries) = match lead.read().as_ref() {
+        Some(detail) => (detail.lead.clone(), detail.note_entries.clone()),
         None => return rsx! {
             div { class: "text-center text-red-500 p-4",
                 "Lead not found"
@@ -75,6 +75,15 @@ pub fn LeadDetails() -> Element {
         .unwrap_or_default();
     let last_call_str = lead_data.last_call_at
         .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string());
+    let note_entries: Vec<(i64, String, String, String)> = note_entries
+        .into_iter()
+        .map(|note| (
+            note.id,
+            note.author_name.unwrap_or_else(|| "Unknown".to_string()),
+            note.created_at.map(|dt| dt.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default(),
+            note.content,
+        ))
+        .collect();
 
     rsx! {
         div { class: "h-full flex flex-col bg-white",
@@ -163,16 +172,20 @@ pub fn LeadDetails() -> Element {
                     }
 
                     // Notes display
-                    if let Some(notes) = &lead_data.notes {
-                        if !notes.is_empty() {
-                            div { class: "bg-gray-50 rounded-lg p-3",
-                                p { class: "text-sm whitespace-pre-wrap", "{notes}" }
+                    if note_entries.is_empty() {
+                        p { class: "text-gray-500 text-sm", "No notes yet" }
+                    } else {
+                        div { class: "space-y-2",
+                            for (id, author, written_at, content) in note_entries {
+                                div { key: "{id}", class: "bg-gray-50 rounded-lg p-3",
+                                    div { class: "flex justify-between text-xs text-gray-500 mb-1",
+                                        span { "{author}" }
+                                        span { "{written_at}" }
+                                    }
+                                    p { class: "text-sm whitespace-pre-wrap", "{content}" }
+                                }
                             }
-                        } else {
-                            p { class: "text-gray-500 text-sm", "No notes yet" }
                         }
-                    } else {
-                        p { class: "text-gray-500 text-sm", "No notes yet" }
                     }
                 }
 
@@ -1,8 +1,197 @@
 use dioxus::prelude::*;
 use crate::state::CALL_STATE;
 
+/// Server event socket with the handler it calls
+#[cfg(target_arch = "wasm32")]
+type EventSocket = (
+    web_sys::WebSocket,
+    wasm_bindgen::closure::Closure<dyn FnMut(web_sys::MessageEvent)>,
+);
+
+/// SIP event stream with the handlers it calls
+#[cfg(target_arch = "wasm32")]
+struct SipStream {
+    source: web_sys::EventSource,
+    _on_message: wasm_bindgen::closure::Closure<dyn FnMut(web_sys::MessageEvent)>,
+    _on_error: wasm_bindgen::closure::Closure<dyn FnMut(wasm_bindgen::JsValue)>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl SipStream {
+    fn close(&self) {
+        self.source.set_onmessage(None);
+        self.source.set_onerror(None);
+        self.source.close();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+type SipStreamSlot = std::rc::Rc<std::cell::RefCell<Option<SipStream>>>;
+
+/// Wait before reopening a SIP event stream the server refused
+#[cfg(target_arch = "wasm32")]
+const SIP_EVENTS_RETRY_MS: u32 = 2000;
+
+/// Open the SIP event stream into `slot`, replacing any stream there. The
+/// browser retries dropped connections itself, but gives up once the server
+/// refuses one, which is what happens when the access token in the URL has
+/// expired. Then the token is refreshed and the stream opened again.
+#[cfg(target_arch = "wasm32")]
+fn open_sip_events(slot: SipStreamSlot) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+    use crate::api::sip::sip_events_url;
+    use crate::models::SipEvent;
+    use crate::state::{apply_sip_call_state, show_notification, NotificationType};
+
+    let Some(url) = sip_events_url() else {
+        return;
+    };
+    let Ok(source) = web_sys::EventSource::new(&url) else {
+        tracing::warn!("Failed to open SIP event stream");
+        return;
+    };
+
+    let on_message = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+        let Some(data) = event.data().as_string() else {
+            return;
+        };
+        match serde_json::from_str::<SipEvent>(&data) {
+            Ok(SipEvent::CallState { call_id, state, .. }) => apply_sip_call_state(&call_id, &state),
+            Ok(SipEvent::IncomingCall { from, .. }) => {
+                show_notification(&format!("Incoming call from {}", from), NotificationType::Info);
+            }
+            Ok(SipEvent::StateChanged { trunk, state }) => {
+                tracing::info!("SIP trunk {} is {}", trunk, state);
+            }
+            Ok(SipEvent::Error { trunk, message }) => {
+                tracing::warn!("SIP trunk {} error: {}", trunk, message);
+            }
+            Err(e) => tracing::warn!("Invalid SIP event: {}", e),
+        }
+    }) as Box<dyn FnMut(_)>);
+
+    // Weak, so the stream's own handler doesn't keep the slot alive
+    let weak_slot = std::rc::Rc::downgrade(&slot);
+    let on_error = Closure::wrap(Box::new(move |_: wasm_bindgen::JsValue| {
+        let Some(slot) = weak_slot.upgrade() else {
+            return;
+        };
+        let refused = slot
+            .borrow()
+            .as_ref()
+            .is_some_and(|stream| stream.source.ready_state() == web_sys::EventSource::CLOSED);
+        if !refused {
+            return;
+        }
+        wasm_bindgen_futures::spawn_local(async move {
+            gloo_timers::future::TimeoutFuture::new(SIP_EVENTS_RETRY_MS).await;
+            if let Err(e) = crate::api::api_client().refresh_access_token().await {
+                tracing::warn!("SIP event stream closed and the session couldn't be renewed: {}", e);
+                return;
+            }
+            // Unless the bar unmounted in the meantime
+            if slot.borrow().is_some() {
+                open_sip_events(slot);
+            }
+        });
+    }) as Box<dyn FnMut(_)>);
+
+    source.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    source.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+    let stream = SipStream { source, _on_message: on_message, _on_error: on_error };
+    if let Some(previous) = slot.borrow_mut().replace(stream) {
+        previous.close();
+    }
+}
+
 #[component]
 pub fn CallStatusBar() -> Element {
+    // Follow SIP call progress pushed by the server. The stream is closed
+    // when the bar unmounts.
+    #[cfg(target_arch = "wasm32")]
+    let sip_stream = use_hook(SipStreamSlot::default);
+    #[cfg(target_arch = "wasm32")]
+    use_drop({
+        let sip_stream = sip_stream.clone();
+        move || {
+            if let Some(stream) = sip_stream.borrow_mut().take() {
+                stream.close();
+            }
+        }
+    });
+    #[cfg(target_arch = "wasm32")]
+    use_effect({
+        let sip_stream = sip_stream.clone();
+        move || open_sip_events(sip_stream.clone())
+    });
+
+    // Incoming call toasts, unless turned off in the user's notification preferences
+    #[cfg(target_arch = "wasm32")]
+    let mut call_alerts = use_signal(|| true);
+    #[cfg(target_arch = "wasm32")]
+    use_effect(move || {
+        spawn(async move {
+            if let Ok(prefs) = crate::api::users::get_my_notifications().await {
+                call_alerts.set(prefs.browser_call_alerts);
+            }
+        });
+    });
+
+    // Pop up the caller's details when an inbound call is routed to us. The
+    // socket and its handler live until the bar unmounts, then the socket is closed.
+    #[cfg(target_arch = "wasm32")]
+    let event_socket = use_hook(|| std::rc::Rc::new(std::cell::RefCell::new(None::<EventSocket>)));
+    #[cfg(target_arch = "wasm32")]
+    use_drop({
+        let event_socket = event_socket.clone();
+        move || {
+            if let Some((socket, _callback)) = event_socket.borrow_mut().take() {
+                socket.set_onmessage(None);
+                let _ = socket.close();
+            }
+        }
+    });
+    #[cfg(target_arch = "wasm32")]
+    use_effect(move || {
+        use wasm_bindgen::{closure::Closure, JsCast};
+        use crate::api::calls::{events_url, ServerEvent};
+        use crate::state::{show_notification, show_screen_pop, NotificationType, AUTH_STATE};
+
+        let Some(url) = events_url() else {
+            return;
+        };
+        let Ok(socket) = web_sys::WebSocket::new(&url) else {
+            tracing::warn!("Failed to open server event socket");
+            return;
+        };
+
+        let callback = Closure::wrap(Box::new(move |event: web_sys::MessageEvent| {
+            let Some(data) = event.data().as_string() else {
+                return;
+            };
+            match serde_json::from_str::<ServerEvent>(&data) {
+                Ok(ServerEvent::CallIncoming { user_id, call_id, from, screen_pop, .. }) => {
+                    if user_id.is_none() || user_id != AUTH_STATE.peek().user_id() {
+                        return;
+                    }
+                    if *call_alerts.peek() {
+                        let caller = match &screen_pop.lead {
+                            Some(lead) => lead.full_name(),
+                            None => format!("New caller {}", from),
+                        };
+                        show_notification(&format!("Incoming call: {}", caller), NotificationType::Info);
+                    }
+                    show_screen_pop(call_id, from, screen_pop);
+                }
+                Ok(_) => {}
+                Err(e) => tracing::warn!("Invalid server event: {}", e),
+            }
+        }) as Box<dyn FnMut(_)>);
+
+        socket.set_onmessage(Some(callback.as_ref().unchecked_ref()));
+        *event_socket.borrow_mut() = Some((socket, callback));
+    });
+
     let call_state = CALL_STATE.read();
 
     // Show bar if dialing, ringing, or answered
@@ -36,6 +225,8 @@ pub fn CallStatusBar() -> Element {
         .or_else(|| call_state.dialed_number.clone())
         .unwrap_or_default();
 
+    let screen_pop = call_state.screen_pop.clone();
+
     // Yellow for dialing/ringing, green for connected
     let bar_color = if is_dialing || is_ringing { "bg-yellow-500" } else { "bg-green-600" };
 
@@ -51,6 +242,21 @@ pub fn CallStatusBar() -> Element {
                         div { class: "font-semibold", "{lead_name}" }
                         div { class: "text-sm opacity-90", "{phone}" }
                     }
+
+                    // Screen pop for inbound calls
+                    if let Some(pop) = screen_pop {
+                        div { class: "text-sm border-l border-white/40 pl-4 max-w-md",
+                            if pop.new_caller {
+                                div { class: "font-semibold", "New caller" }
+                            }
+                            if let Some(outcome) = pop.last_call_outcome {
+                                div { class: "opacity-90", "Last call: {outcome}" }
+                            }
+                            for note in pop.recent_notes {
+                                div { key: "{note.id}", class: "opacity-90 truncate", "{note.content}" }
+                            }
+                        }
+                    }
                 }
 
                 // Status and duration
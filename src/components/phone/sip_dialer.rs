@@ -3,6 +3,7 @@
 //! This component provides server-side SIP calling using DIDLogic or similar SIP trunks.
 
 use dioxus::prelude::*;
+use crate::models::{normalize_phone, DEFAULT_COUNTRY_CODE};
 use crate::state::{NotificationType, show_notification};
 
 /// Play DTMF tone for a digit
@@ -116,27 +117,6 @@ fn play_dtmf_tone(_digit: &str) {
     // Audio disabled - install alsa-lib-devel and build with --features desktop-audio
 }
 
-/// Format phone number to E.164
-fn format_e164(number: &str) -> String {
-    let cleaned: String = number.chars()
-        .filter(|c| c.is_ascii_digit() || *c == '+')
-        .collect();
-
-    if cleaned.starts_with('+') {
-        return cleaned;
-    }
-
-    if cleaned.len() == 10 {
-        return format!("+1{}", cleaned);
-    }
-
-    if cleaned.len() == 11 && cleaned.starts_with('1') {
-        return format!("+{}", cleaned);
-    }
-
-    format!("+{}", cleaned)
-}
-
 #[component]
 pub fn SipDialer() -> Element {
     let mut phone_number = use_signal(String::new);
@@ -145,6 +125,16 @@ pub fn SipDialer() -> Element {
     let mut is_in_call = use_signal(|| false);
     let mut call_id = use_signal(|| None::<String>);
     let mut call_state = use_signal(|| "idle".to_string());
+    // Numbers are checked the way the server will read them
+    let mut default_country = use_signal(|| DEFAULT_COUNTRY_CODE.to_string());
+
+    use_effect(move || {
+        spawn(async move {
+            if let Ok(config) = crate::api::config::get_phone_config().await {
+                default_country.set(config.default_country);
+            }
+        });
+    });
 
     // Fetch SIP status on mount and periodically
     use_effect(move || {
@@ -206,7 +196,10 @@ pub fn SipDialer() -> Element {
             return;
         }
 
-        let formatted = format_e164(&number);
+        let Some(formatted) = normalize_phone(&number, &default_country()) else {
+            show_notification("Please enter a valid phone number", NotificationType::Warning);
+            return;
+        };
         spawn(async move {
             call_state.set("dialing".to_string());
             is_in_call.set(true);
@@ -253,7 +246,23 @@ pub fn SipDialer() -> Element {
         });
     };
 
+    let copy_number = move |_| {
+        if let Some(formatted) = normalize_phone(&phone_number(), &default_country()) {
+            let _ = document::eval(&format!("navigator.clipboard.writeText({:?})", formatted));
+            show_notification(&format!("Copied {}", formatted), NotificationType::Success);
+        }
+    };
+
     // UI states
+    let normalized = normalize_phone(&phone_number(), &default_country());
+    let number_valid = normalized.is_some();
+    let input_border = if phone_number().is_empty() {
+        "border"
+    } else if number_valid {
+        "border-2 border-green-500"
+    } else {
+        "border-2 border-red-500"
+    };
     let status = sip_status();
     let registered = is_registered();
     let in_call = is_in_call();
@@ -277,7 +286,7 @@ pub fn SipDialer() -> Element {
             }
 
             // Display
-            div { class: "bg-white rounded-lg p-3 mb-3 text-center border",
+            div { class: "bg-white rounded-lg p-3 mb-3 text-center {input_border}",
                 input {
                     class: "text-xl font-mono w-full text-center bg-transparent outline-none",
                     r#type: "tel",
@@ -286,6 +295,16 @@ pub fn SipDialer() -> Element {
                     oninput: move |e| phone_number.set(e.value()),
                     disabled: in_call,
                 }
+                if let Some(formatted) = normalized.clone() {
+                    button {
+                        class: "text-xs text-green-700 font-mono hover:underline",
+                        onclick: copy_number,
+                        title: "Click to copy",
+                        "{formatted}"
+                    }
+                } else if !phone_number().is_empty() {
+                    div { class: "text-xs text-red-600", "Invalid phone number" }
+                }
                 if in_call {
                     div { class: "text-sm text-blue-600 mt-1 animate-pulse",
                         match current_call_state.as_str() {
@@ -335,7 +354,7 @@ pub fn SipDialer() -> Element {
                     }
                     button {
                         class: "bg-green-500 hover:bg-green-600 text-white rounded-full w-12 h-12 flex items-center justify-center transition-colors disabled:opacity-50",
-                        disabled: phone_number().is_empty() || !registered,
+                        disabled: !number_valid || !registered,
                         onclick: make_call,
                         title: "Call",
                         span { class: "text-xl", "\u{1F4DE}" }
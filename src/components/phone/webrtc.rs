@@ -1,6 +1,11 @@
 //! WebRTC integration with Telnyx
 //!
-//! This module provides Rust bindings for the Telnyx WebRTC JavaScript SDK.
+//! This module provides Rust bindings for the Telnyx WebRTC JavaScript SDK
+//! loaded in `index.html`. The SDK registers with Telnyx over a SIP-over-
+//! WebSocket connection using the short-lived login token from
+//! `/api/config/webrtc`.
+//! An empty signaling URL keeps the SDK default (`wss://rtc.telnyx.com`);
+//! set `WEBRTC_SIGNALING_URL` on the server to point it elsewhere.
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsValue;
@@ -8,7 +13,7 @@ use wasm_bindgen::JsValue;
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_name = initTelnyxWebRTC)]
-    pub async fn init_telnyx_webrtc(username: &str, password: &str) -> JsValue;
+    pub async fn init_telnyx_webrtc(login_token: &str, signaling_url: &str) -> JsValue;
 
     #[wasm_bindgen(js_name = makeWebRTCCall)]
     pub async fn make_webrtc_call(destination: &str, caller_id: &str) -> JsValue;
@@ -38,53 +43,4 @@ extern "C" {
     pub fn disconnect_telnyx();
 }
 
-/// WebRTC call state
-#[derive(Debug, Clone, PartialEq)]
-pub enum WebRTCCallState {
-    New,
-    Trying,
-    Ringing,
-    Active,
-    Held,
-    Hangup,
-    Destroy,
-    Unknown(String),
-}
-
-impl From<&str> for WebRTCCallState {
-    fn from(s: &str) -> Self {
-        match s {
-            "new" => WebRTCCallState::New,
-            "trying" => WebRTCCallState::Trying,
-            "ringing" => WebRTCCallState::Ringing,
-            "active" => WebRTCCallState::Active,
-            "held" => WebRTCCallState::Held,
-            "hangup" => WebRTCCallState::Hangup,
-            "destroy" => WebRTCCallState::Destroy,
-            _ => WebRTCCallState::Unknown(s.to_string()),
-        }
-    }
-}
-
-impl WebRTCCallState {
-    pub fn display_name(&self) -> &str {
-        match self {
-            WebRTCCallState::New => "Initiating...",
-            WebRTCCallState::Trying => "Dialing...",
-            WebRTCCallState::Ringing => "Ringing...",
-            WebRTCCallState::Active => "Connected",
-            WebRTCCallState::Held => "On Hold",
-            WebRTCCallState::Hangup => "Call Ended",
-            WebRTCCallState::Destroy => "Call Ended",
-            WebRTCCallState::Unknown(_) => "Unknown",
-        }
-    }
-
-    pub fn is_active(&self) -> bool {
-        matches!(self, WebRTCCallState::Active | WebRTCCallState::Held)
-    }
-
-    pub fn is_ringing(&self) -> bool {
-        matches!(self, WebRTCCallState::New | WebRTCCallState::Trying | WebRTCCallState::Ringing)
-    }
-}
+pub use crate::state::WebRTCCallState;
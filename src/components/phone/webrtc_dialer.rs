@@ -5,7 +5,7 @@
 use dioxus::prelude::*;
 use crate::state::{NotificationType, show_notification, WEBRTC_STATE};
 #[cfg(target_arch = "wasm32")]
-use crate::state::{set_webrtc_connecting, set_webrtc_connected, set_webrtc_error, set_webrtc_call_state, clear_webrtc_call};
+use crate::state::{set_webrtc_connecting, set_webrtc_connected, set_webrtc_error, set_webrtc_call_state, set_webrtc_muted, clear_webrtc_call, WebRTCCallState};
 
 #[cfg(target_arch = "wasm32")]
 use super::webrtc::{init_telnyx_webrtc, make_webrtc_call, hangup_webrtc_call, toggle_mute_webrtc, is_telnyx_ready};
@@ -110,16 +110,11 @@ pub fn WebRTCDialer() -> Element {
                 Ok(config) => {
                     caller_id.set(config.caller_id.clone());
 
-                    if config.sip_username.is_empty() || config.sip_password.is_empty() {
-                        set_webrtc_error("WebRTC not configured. Set TELNYX_SIP_USERNAME and TELNYX_SIP_PASSWORD".to_string());
-                        show_notification("WebRTC credentials not configured", NotificationType::Warning);
-                        return;
-                    }
-
                     set_webrtc_connecting();
                     show_notification("Connecting to Telnyx...", NotificationType::Info);
 
-                    let result = init_telnyx_webrtc(&config.sip_username, &config.sip_password).await;
+                    let signaling_url = config.signaling_url.as_deref().unwrap_or_default();
+                    let result = init_telnyx_webrtc(&config.login_token, signaling_url).await;
                     if result.is_truthy() {
                         set_webrtc_connected();
                         show_notification("Phone ready!", NotificationType::Success);
@@ -149,15 +144,16 @@ pub fn WebRTCDialer() -> Element {
                     .and_then(|v| v.as_string())
                 {
                     tracing::info!("WebRTC call state: {}", state);
-                    set_webrtc_call_state(state.clone());
+                    if !set_webrtc_call_state(&state) {
+                        return;
+                    }
 
-                    match state.as_str() {
-                        "active" => {
+                    match WebRTCCallState::from(state.as_str()) {
+                        WebRTCCallState::Active => {
                             show_notification("Call connected!", NotificationType::Success);
                         }
-                        "hangup" | "destroy" => {
+                        WebRTCCallState::Hangup | WebRTCCallState::Destroy => {
                             show_notification("Call ended", NotificationType::Info);
-                            clear_webrtc_call();
                         }
                         _ => {}
                     }
@@ -215,7 +211,7 @@ pub fn WebRTCDialer() -> Element {
                     }
 
                     show_notification(&format!("Calling {}...", formatted_number), NotificationType::Info);
-                    set_webrtc_call_state("trying".to_string());
+                    set_webrtc_call_state("trying");
 
                     let call_result = make_webrtc_call(&formatted_number, &cid).await;
                     if call_result.is_object() {
@@ -242,6 +238,7 @@ pub fn WebRTCDialer() -> Element {
         #[cfg(target_arch = "wasm32")]
         {
             let muted = toggle_mute_webrtc();
+            set_webrtc_muted(muted);
             if muted {
                 show_notification("Muted", NotificationType::Info);
             } else {
@@ -254,7 +251,12 @@ pub fn WebRTCDialer() -> Element {
     let is_connected = webrtc_state.is_connected;
     let is_connecting = webrtc_state.is_connecting;
     let is_in_call = webrtc_state.is_in_call;
-    let call_state = webrtc_state.call_state.clone().unwrap_or_default();
+    let is_muted = webrtc_state.is_muted;
+    let call_label = webrtc_state
+        .call_state
+        .as_ref()
+        .map(|state| state.display_name().to_string())
+        .unwrap_or_default();
 
     rsx! {
         div { class: "bg-gray-50 rounded-lg p-3 w-full",
@@ -280,15 +282,7 @@ pub fn WebRTCDialer() -> Element {
                     disabled: is_in_call,
                 }
                 if is_in_call {
-                    div { class: "text-sm text-blue-600 mt-1 animate-pulse",
-                        match call_state.as_str() {
-                            "trying" | "new" => "Dialing...",
-                            "ringing" => "Ringing...",
-                            "active" => "Connected",
-                            "held" => "On Hold",
-                            _ => &call_state,
-                        }
-                    }
+                    div { class: "text-sm text-blue-600 mt-1 animate-pulse", "{call_label}" }
                 }
             }
 
@@ -313,10 +307,14 @@ pub fn WebRTCDialer() -> Element {
                 if is_in_call {
                     // In-call controls
                     button {
-                        class: "bg-blue-500 hover:bg-blue-600 text-white rounded-full w-10 h-10 flex items-center justify-center transition-colors",
+                        class: if is_muted {
+                            "bg-yellow-500 hover:bg-yellow-600 text-white rounded-full w-10 h-10 flex items-center justify-center transition-colors"
+                        } else {
+                            "bg-blue-500 hover:bg-blue-600 text-white rounded-full w-10 h-10 flex items-center justify-center transition-colors"
+                        },
                         onclick: toggle_mute,
-                        title: "Mute",
-                        "\u{1F507}"
+                        title: if is_muted { "Unmute" } else { "Mute" },
+                        if is_muted { "\u{1F507}" } else { "\u{1F50A}" }
                     }
                     button {
                         class: "bg-red-500 hover:bg-red-600 text-white rounded-full w-12 h-12 flex items-center justify-center transition-colors",
@@ -1,5 +1,8 @@
 use dioxus::prelude::*;
-use crate::models::{Campaign, CampaignStatus, DialerMode, CreateCampaignRequest};
+use crate::models::{
+    normalize_skill, AmdMode, Campaign, CampaignStatus, DialerMode, CreateCampaignRequest, RecordingChannels,
+    RecordingFormat,
+};
 use crate::api;
 use crate::components::common::{LoadingSpinner, Card};
 
@@ -213,6 +216,18 @@ fn CreateCampaignModal(
             end_time: None,
             max_attempts: Some(3),
             retry_delay_minutes: Some(30),
+            hold_music_url: None,
+            amd_mode: AmdMode::default(),
+            leave_voicemail: false,
+            voicemail_message: None,
+            caller_id_pool: Vec::new(),
+            voicemail_audio_url: None,
+            greeting_template: None,
+            redact_transcripts: true,
+            required_skill: None,
+            max_call_seconds: None,
+            recording_channels: RecordingChannels::default(),
+            recording_format: RecordingFormat::default(),
         };
 
         spawn(async move {
@@ -301,31 +316,69 @@ fn CampaignSettingsModal(
     let mut dialer_mode = use_signal(|| campaign.dialer_mode.clone());
     let mut max_attempts = use_signal(|| campaign.max_attempts.unwrap_or(3).to_string());
     let mut retry_delay = use_signal(|| campaign.retry_delay_minutes.unwrap_or(30).to_string());
+    let mut max_call_seconds = use_signal(|| campaign.max_call_seconds.map(|s| s.to_string()).unwrap_or_default());
+    let mut hold_music_url = use_signal(|| campaign.hold_music_url.clone().unwrap_or_default());
+    let mut amd_mode = use_signal(|| campaign.amd_mode);
+    let mut recording_channels = use_signal(|| campaign.recording_channels);
+    let mut recording_format = use_signal(|| campaign.recording_format);
+    let mut leave_voicemail = use_signal(|| campaign.leave_voicemail);
+    let mut voicemail_message = use_signal(|| campaign.voicemail_message.clone().unwrap_or_default());
+    let mut voicemail_audio_url = use_signal(|| campaign.voicemail_audio_url.clone().unwrap_or_default());
+    let mut greeting_template = use_signal(|| campaign.greeting_template.clone().unwrap_or_default());
+    let mut redact_transcripts = use_signal(|| campaign.redact_transcripts);
+    let mut required_skill = use_signal(|| campaign.required_skill.clone().unwrap_or_default());
+    let mut caller_id = use_signal(|| campaign.caller_id.clone().unwrap_or_default());
+    let mut caller_id_pool = use_signal(|| campaign.caller_id_pool.join("\n"));
     let mut is_saving = use_signal(|| false);
     let campaign_id = campaign.id;
     let campaign_name = campaign.name.clone();
     let campaign_desc = campaign.description.clone();
-    let campaign_caller_id = campaign.caller_id.clone();
 
     let save_settings = move |_| {
         is_saving.set(true);
         let mode = dialer_mode();
         let attempts: i32 = max_attempts().parse().unwrap_or(3);
         let delay: i32 = retry_delay().parse().unwrap_or(30);
+        let call_limit: Option<i32> = max_call_seconds().trim().parse().ok().filter(|s: &i32| *s > 0);
         let name = campaign_name.clone();
         let desc = campaign_desc.clone();
-        let caller_id = campaign_caller_id.clone();
+        let caller_id = caller_id().trim().to_string();
+        let pool: Vec<String> = caller_id_pool()
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        let hold_music = hold_music_url().trim().to_string();
+        let amd = amd_mode();
+        let voicemail = leave_voicemail();
+        let voicemail_text = voicemail_message().trim().to_string();
+        let voicemail_audio = voicemail_audio_url().trim().to_string();
+        let greeting = greeting_template().trim().to_string();
+        let redact = redact_transcripts();
+        let skill = normalize_skill(&required_skill());
 
         spawn(async move {
             let request = CreateCampaignRequest {
                 name,
                 description: desc,
                 dialer_mode: mode,
-                caller_id,
+                caller_id: if caller_id.is_empty() { None } else { Some(caller_id) },
                 start_time: None,
                 end_time: None,
                 max_attempts: Some(attempts),
                 retry_delay_minutes: Some(delay),
+                hold_music_url: if hold_music.is_empty() { None } else { Some(hold_music) },
+                amd_mode: amd,
+                leave_voicemail: voicemail,
+                voicemail_message: if voicemail_text.is_empty() { None } else { Some(voicemail_text) },
+                caller_id_pool: pool,
+                voicemail_audio_url: if voicemail_audio.is_empty() { None } else { Some(voicemail_audio) },
+                greeting_template: if greeting.is_empty() { None } else { Some(greeting) },
+                redact_transcripts: redact,
+                required_skill: skill,
+                max_call_seconds: call_limit,
+                recording_channels: recording_channels(),
+                recording_format: recording_format(),
             };
 
             match api::campaigns::update_campaign(campaign_id, request).await {
@@ -410,6 +463,193 @@ fn CampaignSettingsModal(
                         p { class: "text-xs text-gray-500 mt-1", "Time between retry attempts" }
                     }
 
+                    // Max Call Length
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Max Call Length (seconds)" }
+                        input {
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                            r#type: "number",
+                            min: "60",
+                            placeholder: "Server default",
+                            value: "{max_call_seconds}",
+                            oninput: move |e| max_call_seconds.set(e.value()),
+                        }
+                        p { class: "text-xs text-gray-500 mt-1", "Calls running longer are hung up automatically" }
+                    }
+
+                    // Caller ID
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Caller ID" }
+                        input {
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                            r#type: "tel",
+                            placeholder: "+15551234567",
+                            value: "{caller_id}",
+                            oninput: move |e| caller_id.set(e.value()),
+                        }
+                        p { class: "text-xs text-gray-500 mt-1", "Leave empty to use the default caller ID" }
+                        textarea {
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg mt-2",
+                            rows: "3",
+                            placeholder: "Local presence numbers, one per line",
+                            value: "{caller_id_pool}",
+                            oninput: move |e| caller_id_pool.set(e.value()),
+                        }
+                        p { class: "text-xs text-gray-500 mt-1", "A number matching the lead's area code is used when available" }
+                    }
+
+                    // Hold Music
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Hold Music URL" }
+                        input {
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                            r#type: "url",
+                            placeholder: "https://example.com/hold.mp3",
+                            value: "{hold_music_url}",
+                            oninput: move |e| hold_music_url.set(e.value()),
+                        }
+                        p { class: "text-xs text-gray-500 mt-1", "Leave empty to use the default hold music" }
+                    }
+
+                    // Greeting
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Greeting" }
+                        textarea {
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                            rows: "2",
+                            placeholder: "Hi {{lead_name}}, this is {{agent_name}} calling about {{campaign_name}}.",
+                            value: "{greeting_template}",
+                            oninput: move |e| greeting_template.set(e.value()),
+                        }
+                        p { class: "text-xs text-gray-500 mt-1",
+                            "Placeholders: {{lead_name}}, {{first_name}}, {{agent_name}}, {{company}}, {{campaign_name}}"
+                        }
+                    }
+
+                    // Transcript Redaction
+                    div {
+                        label { class: "flex items-center gap-2 text-sm text-gray-700",
+                            input {
+                                r#type: "checkbox",
+                                checked: redact_transcripts(),
+                                onchange: move |e| redact_transcripts.set(e.checked()),
+                            }
+                            "Mask card numbers and SSNs in AI call transcripts"
+                        }
+                    }
+
+                    // Skill-based routing
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Required Skill" }
+                        input {
+                            r#type: "text",
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                            placeholder: "e.g. spanish",
+                            value: "{required_skill}",
+                            oninput: move |e| required_skill.set(e.value()),
+                        }
+                        p { class: "text-xs text-gray-500 mt-1", "Inbound calls for this campaign go to agents with this skill" }
+                    }
+
+                    // Call Recording
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Call Recording" }
+                        div { class: "grid grid-cols-2 gap-2",
+                            select {
+                                class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                                onchange: move |e| {
+                                    recording_channels.set(match e.value().as_str() {
+                                        "SINGLE" => RecordingChannels::Single,
+                                        _ => RecordingChannels::Dual,
+                                    });
+                                },
+                                for (value, channels) in [
+                                    ("DUAL", RecordingChannels::Dual),
+                                    ("SINGLE", RecordingChannels::Single),
+                                ] {
+                                    option {
+                                        value: "{value}",
+                                        selected: recording_channels() == channels,
+                                        "{channels.display_name()}"
+                                    }
+                                }
+                            }
+                            select {
+                                class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                                onchange: move |e| {
+                                    recording_format.set(match e.value().as_str() {
+                                        "WAV" => RecordingFormat::Wav,
+                                        _ => RecordingFormat::Mp3,
+                                    });
+                                },
+                                for (value, format) in [
+                                    ("MP3", RecordingFormat::Mp3),
+                                    ("WAV", RecordingFormat::Wav),
+                                ] {
+                                    option {
+                                        value: "{value}",
+                                        selected: recording_format() == format,
+                                        "{format.display_name()}"
+                                    }
+                                }
+                            }
+                        }
+                        p { class: "text-xs text-gray-500 mt-1", "Dual channel keeps each party separate for transcription" }
+                    }
+
+                    // Answering Machine Detection
+                    div {
+                        label { class: "block text-sm font-medium text-gray-700 mb-1", "Answering Machine Detection" }
+                        select {
+                            class: "w-full px-3 py-2 border border-gray-300 rounded-lg",
+                            onchange: move |e| {
+                                amd_mode.set(match e.value().as_str() {
+                                    "DISABLED" => AmdMode::Disabled,
+                                    "DETECT_BEEP" => AmdMode::DetectBeep,
+                                    "GREETING_END" => AmdMode::GreetingEnd,
+                                    _ => AmdMode::Detect,
+                                });
+                            },
+                            for (value, mode) in [
+                                ("DISABLED", AmdMode::Disabled),
+                                ("DETECT", AmdMode::Detect),
+                                ("DETECT_BEEP", AmdMode::DetectBeep),
+                                ("GREETING_END", AmdMode::GreetingEnd),
+                            ] {
+                                option {
+                                    value: "{value}",
+                                    selected: amd_mode() == mode,
+                                    "{mode.display_name()}"
+                                }
+                            }
+                        }
+                        label { class: "flex items-center gap-2 mt-2 text-sm text-gray-700",
+                            input {
+                                r#type: "checkbox",
+                                checked: leave_voicemail(),
+                                onchange: move |e| leave_voicemail.set(e.checked()),
+                            }
+                            "Leave a voicemail instead of hanging up on machines"
+                        }
+                        if leave_voicemail() {
+                            textarea {
+                                class: "w-full px-3 py-2 border border-gray-300 rounded-lg mt-2",
+                                rows: "3",
+                                placeholder: "Voicemail message",
+                                value: "{voicemail_message}",
+                                oninput: move |e| voicemail_message.set(e.value()),
+                            }
+                            input {
+                                class: "w-full px-3 py-2 border border-gray-300 rounded-lg mt-2",
+                                r#type: "url",
+                                placeholder: "https://example.com/voicemail.mp3",
+                                value: "{voicemail_audio_url}",
+                                oninput: move |e| voicemail_audio_url.set(e.value()),
+                            }
+                            p { class: "text-xs text-gray-500 mt-1", "A recording plays after the beep instead of the message above" }
+                        }
+                    }
+
                     // Campaign Status Info
                     div { class: "bg-gray-50 rounded-lg p-3",
                         div { class: "flex justify-between text-sm",
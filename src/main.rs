@@ -257,6 +257,7 @@ pub fn LoginPage() -> Element {
     let nav = use_navigator();
     let mut username = use_signal(String::new);
     let mut password = use_signal(String::new);
+    let mut remember_me = use_signal(|| false);
     let mut is_loading = use_signal(|| false);
     let mut error = use_signal(|| None::<String>);
     let mut is_unverified_error = use_signal(|| false);
@@ -280,7 +281,7 @@ pub fn LoginPage() -> Element {
         resend_success.set(None);
 
         spawn(async move {
-            match api::auth::login(&user, &pass).await {
+            match api::auth::login(&user, &pass, remember_me()).await {
                 Ok(response) => {
                     state::set_auth(response.user, response.token);
                     // Navigate to home after successful login
@@ -360,7 +361,7 @@ pub fn LoginPage() -> Element {
                         }
                     }
 
-                    div { class: "mb-6",
+                    div { class: "mb-4",
                         label { class: "block text-sm font-medium text-gray-700 mb-1", "Password" }
                         input {
                             class: "w-full px-4 py-3 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-blue-500",
@@ -371,6 +372,17 @@ pub fn LoginPage() -> Element {
                         }
                     }
 
+                    div { class: "mb-6 flex items-center",
+                        input {
+                            id: "remember-me",
+                            class: "h-4 w-4 text-blue-600 border-gray-300 rounded",
+                            r#type: "checkbox",
+                            checked: remember_me(),
+                            onchange: move |e| remember_me.set(e.checked()),
+                        }
+                        label { r#for: "remember-me", class: "ml-2 text-sm text-gray-700", "Remember me for 30 days" }
+                    }
+
                     button {
                         class: "w-full py-3 bg-blue-600 text-white rounded-lg hover:bg-blue-700 font-medium disabled:opacity-50",
                         r#type: "submit",
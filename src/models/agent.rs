@@ -1,5 +1,8 @@
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use super::FieldError;
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -63,6 +66,21 @@ impl AgentStatus {
         }
     }
 
+    /// Whether an agent or supervisor may move this status to `next`. An
+    /// agent on a call has to finish it before going on break or offline.
+    pub fn allows_change_to(&self, next: AgentStatus) -> bool {
+        !(*self == AgentStatus::OnCall && matches!(next, AgentStatus::Break | AgentStatus::Offline))
+    }
+
+    /// Status an agent moves to once they've dispositioned a call.
+    /// Only agents in wrap-up return to Ready; any other status is left alone.
+    pub fn after_disposition(&self) -> Option<AgentStatus> {
+        match self {
+            AgentStatus::AfterCall => Some(AgentStatus::Ready),
+            _ => None,
+        }
+    }
+
     pub fn color_class(&self) -> &str {
         match self {
             AgentStatus::Ready => "bg-green-500",
@@ -74,6 +92,17 @@ impl AgentStatus {
     }
 }
 
+/// Why an agent has stepped away, given when they change their own status
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "status_reason", rename_all = "PascalCase"))]
+pub enum StatusReason {
+    Break,
+    Lunch,
+    Training,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateAgentRequest {
     pub name: String,
@@ -84,11 +113,54 @@ pub struct CreateAgentRequest {
     pub extension: Option<String>,
 }
 
+impl CreateAgentRequest {
+    /// Check every field, collecting one error per invalid field
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "Name is required"));
+        }
+        if let Some(extension) = self.extension.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+            if !extension.chars().all(|c| c.is_ascii_digit()) {
+                errors.push(FieldError::new("extension", "Extension must contain only digits"));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateAgentStatusRequest {
     pub status: AgentStatus,
 }
 
+/// An agent changing their own status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetMyStatusRequest {
+    pub status: AgentStatus,
+    #[serde(default)]
+    pub reason: Option<StatusReason>,
+}
+
+impl SetMyStatusRequest {
+    /// On Call and After Call follow the agent's calls and can't be chosen;
+    /// a reason only goes with stepping away (Break or Offline)
+    pub fn validate(&self) -> Result<(), String> {
+        match self.status {
+            AgentStatus::OnCall | AgentStatus::AfterCall => {
+                Err(format!("{} is set by calls and can't be chosen", self.status.display_name()))
+            }
+            AgentStatus::Ready if self.reason.is_some() => Err("A reason can only be given when stepping away".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentStats {
     #[serde(rename = "agentId")]
@@ -103,4 +175,454 @@ pub struct AgentStats {
     pub total_talk_time: i32,
     #[serde(rename = "averageHandleTime")]
     pub average_handle_time: f64,
+    /// Mean caller sentiment over today's scored calls
+    #[serde(rename = "sentimentAvg", default)]
+    pub sentiment_avg: Option<f64>,
+}
+
+/// One weekly shift window in the schedule's timezone.
+/// A window whose end is before its start runs past midnight into the next day.
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ScheduleSlot {
+    /// 0 = Monday .. 6 = Sunday
+    #[serde(rename = "dayOfWeek")]
+    pub day_of_week: i16,
+    #[serde(rename = "startTime")]
+    pub start_time: NaiveTime,
+    #[serde(rename = "endTime")]
+    pub end_time: NaiveTime,
+}
+
+impl ScheduleSlot {
+    /// Whether a local weekday (0 = Monday) and time fall inside this window
+    fn contains(&self, day: i16, time: NaiveTime) -> bool {
+        if self.start_time < self.end_time {
+            day == self.day_of_week && time >= self.start_time && time < self.end_time
+        } else {
+            (day == self.day_of_week && time >= self.start_time)
+                || (day == (self.day_of_week + 1) % 7 && time < self.end_time)
+        }
+    }
+}
+
+/// Greeting for an agent's answered calls. A recorded greeting, when set, is
+/// played instead of speaking the template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentGreeting {
+    #[serde(rename = "greetingTemplate")]
+    pub greeting_template: Option<String>,
+    #[serde(rename = "greetingAudioUrl", default)]
+    pub greeting_audio_url: Option<String>,
+}
+
+/// Update to an agent's greeting. Leaving `greetingAudioUrl` out keeps the
+/// current recording; sending it as `null` removes it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateAgentGreetingRequest {
+    #[serde(rename = "greetingTemplate")]
+    pub greeting_template: Option<String>,
+    #[serde(
+        rename = "greetingAudioUrl",
+        default,
+        deserialize_with = "present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub greeting_audio_url: Option<Option<String>>,
+}
+
+/// Wrap a field that was present in the payload, so a `null` stays
+/// distinguishable from a missing field
+fn present<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// Audio formats Telnyx can play back on a call
+pub const GREETING_AUDIO_EXTENSIONS: [&str; 2] = ["mp3", "wav"];
+
+/// Whether `url` is an http(s) link to an MP3 or WAV file
+pub fn is_supported_greeting_audio(url: &str) -> bool {
+    let url = url.trim();
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return false;
+    }
+
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    path.rsplit_once('.')
+        .map(|(_, ext)| GREETING_AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Calls an agent may have in progress when no limit has been set
+pub const DEFAULT_MAX_CONCURRENT_CALLS: i32 = 1;
+
+/// Concurrent call limit for an agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentCallLimit {
+    #[serde(rename = "maxConcurrentCalls")]
+    pub max_concurrent_calls: i32,
+}
+
+impl AgentCallLimit {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        if self.max_concurrent_calls < 1 {
+            return Err(vec![FieldError::new("maxConcurrentCalls", "Limit must be at least 1")]);
+        }
+        Ok(())
+    }
+}
+
+/// Longest skill name accepted
+pub const MAX_SKILL_LEN: usize = 50;
+
+/// Canonical form of a skill: trimmed, lowercase, single-spaced. None if it is
+/// empty or too long.
+pub fn normalize_skill(skill: &str) -> Option<String> {
+    let skill = skill.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    (!skill.is_empty() && skill.chars().count() <= MAX_SKILL_LEN).then_some(skill)
+}
+
+/// Skills an agent has, such as the languages they speak
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentSkills {
+    pub skills: Vec<String>,
+}
+
+impl AgentSkills {
+    /// Normalized, sorted and without duplicates, or the fields that are invalid
+    pub fn normalized(&self) -> Result<Vec<String>, Vec<FieldError>> {
+        let mut skills = Vec::with_capacity(self.skills.len());
+        for skill in &self.skills {
+            match normalize_skill(skill) {
+                Some(skill) => skills.push(skill),
+                None => {
+                    return Err(vec![FieldError::new(
+                        "skills",
+                        &format!("Skills must be 1-{} characters", MAX_SKILL_LEN),
+                    )])
+                }
+            }
+        }
+        skills.sort();
+        skills.dedup();
+        Ok(skills)
+    }
+}
+
+/// Inbound number whose callers need an agent with `required_skill`
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct InboundNumber {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+    #[serde(rename = "requiredSkill")]
+    pub required_skill: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetInboundNumberRequest {
+    #[serde(rename = "requiredSkill")]
+    pub required_skill: String,
+}
+
+/// An agent's status alongside the calls they already have in progress
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CallCapacity {
+    pub status: AgentStatus,
+    pub max_concurrent_calls: i32,
+    pub active_calls: i64,
+}
+
+impl CallCapacity {
+    /// Whether another call may be assigned. Ready agents take calls up to
+    /// their limit; an agent already on a call only takes more when the limit
+    /// allows it. Any other status takes none.
+    pub fn can_take_call(&self) -> bool {
+        let below_limit = self.active_calls < i64::from(self.max_concurrent_calls);
+        match self.status {
+            AgentStatus::Ready => below_limit,
+            AgentStatus::OnCall => self.max_concurrent_calls > 1 && below_limit,
+            _ => false,
+        }
+    }
+}
+
+/// Weekly shift schedule for an agent. An empty schedule places no restriction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentSchedule {
+    #[serde(rename = "agentId")]
+    pub agent_id: i64,
+    /// IANA timezone name, e.g. "America/New_York"
+    pub timezone: String,
+    pub slots: Vec<ScheduleSlot>,
+}
+
+impl AgentSchedule {
+    /// Whether the agent is on shift at the given instant
+    pub fn is_on_shift(&self, at: DateTime<Utc>) -> bool {
+        if self.slots.is_empty() {
+            return true;
+        }
+
+        let tz: Tz = self.timezone.parse().unwrap_or(Tz::UTC);
+        let local = at.with_timezone(&tz);
+        let day = local.weekday().num_days_from_monday() as i16;
+        let time = local.time();
+
+        self.slots.iter().any(|slot| slot.contains(day, time))
+    }
+
+    /// Whether an agent may switch to `status` at the given instant.
+    /// Only going Ready is restricted to scheduled hours.
+    pub fn allows_status(&self, status: AgentStatus, at: DateTime<Utc>) -> bool {
+        status != AgentStatus::Ready || self.is_on_shift(at)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateAgentScheduleRequest {
+    pub timezone: String,
+    pub slots: Vec<ScheduleSlot>,
+}
+
+impl UpdateAgentScheduleRequest {
+    /// Check the timezone and every slot, returning a message for the first problem
+    pub fn validate(&self) -> Result<(), String> {
+        if self.timezone.parse::<Tz>().is_err() {
+            return Err(format!("Unknown timezone: {}", self.timezone));
+        }
+        for slot in &self.slots {
+            if !(0..=6).contains(&slot.day_of_week) {
+                return Err(format!("Invalid day of week: {}", slot.day_of_week));
+            }
+            if slot.start_time == slot.end_time {
+                return Err("Shift start and end times must differ".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_greeting_audio_format() {
+        assert!(is_supported_greeting_audio("https://cdn.example.com/greetings/maria.mp3"));
+        assert!(is_supported_greeting_audio("https://cdn.example.com/hello.WAV?sig=abc"));
+        assert!(!is_supported_greeting_audio("https://cdn.example.com/hello.ogg"));
+        assert!(!is_supported_greeting_audio("https://cdn.example.com/greetings"));
+        assert!(!is_supported_greeting_audio("ftp://cdn.example.com/hello.mp3"));
+    }
+
+    #[test]
+    fn test_greeting_update_tells_missing_audio_from_null() {
+        let req: UpdateAgentGreetingRequest = serde_json::from_str(r#"{"greetingTemplate":"Hi"}"#).unwrap();
+        assert_eq!(req.greeting_audio_url, None);
+
+        let req: UpdateAgentGreetingRequest =
+            serde_json::from_str(r#"{"greetingTemplate":"Hi","greetingAudioUrl":null}"#).unwrap();
+        assert_eq!(req.greeting_audio_url, Some(None));
+
+        let req: UpdateAgentGreetingRequest =
+            serde_json::from_str(r#"{"greetingTemplate":"Hi","greetingAudioUrl":"https://cdn.example.com/a.mp3"}"#)
+                .unwrap();
+        assert_eq!(req.greeting_audio_url, Some(Some("https://cdn.example.com/a.mp3".to_string())));
+    }
+
+    #[test]
+    fn test_agents_on_a_call_cannot_step_away() {
+        assert!(!AgentStatus::OnCall.allows_change_to(AgentStatus::Break));
+        assert!(!AgentStatus::OnCall.allows_change_to(AgentStatus::Offline));
+        assert!(AgentStatus::OnCall.allows_change_to(AgentStatus::AfterCall));
+        assert!(AgentStatus::Ready.allows_change_to(AgentStatus::Break));
+        assert!(AgentStatus::AfterCall.allows_change_to(AgentStatus::Offline));
+    }
+
+    #[test]
+    fn test_skills_are_normalized() {
+        let req = AgentSkills { skills: vec!["Spanish".into(), " spanish ".into(), "Tech  Support".into()] };
+        assert_eq!(req.normalized().unwrap(), vec!["spanish".to_string(), "tech support".to_string()]);
+
+        let req = AgentSkills { skills: vec!["  ".into()] };
+        assert!(req.normalized().is_err());
+    }
+
+    #[test]
+    fn test_agent_request_validation() {
+        let req = CreateAgentRequest {
+            name: "Grace".to_string(),
+            agent_type: AgentType::Human,
+            user_id: None,
+            extension: Some("1001".to_string()),
+        };
+        assert!(req.validate().is_ok());
+
+        let req = CreateAgentRequest { name: " ".to_string(), extension: Some("10a".to_string()), ..req };
+        assert_eq!(
+            req.validate().unwrap_err(),
+            vec![
+                FieldError::new("name", "Name is required"),
+                FieldError::new("extension", "Extension must contain only digits"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_disposition_returns_after_call_agent_to_ready() {
+        assert_eq!(AgentStatus::AfterCall.after_disposition(), Some(AgentStatus::Ready));
+    }
+
+    #[test]
+    fn test_disposition_leaves_other_statuses_alone() {
+        for status in [AgentStatus::Offline, AgentStatus::Ready, AgentStatus::OnCall, AgentStatus::Break] {
+            assert_eq!(status.after_disposition(), None);
+        }
+    }
+
+    #[test]
+    fn test_agent_on_call_takes_no_second_call_by_default() {
+        let capacity = CallCapacity {
+            status: AgentStatus::OnCall,
+            max_concurrent_calls: DEFAULT_MAX_CONCURRENT_CALLS,
+            active_calls: 1,
+        };
+        assert!(!capacity.can_take_call());
+
+        // A Ready agent with a lingering call record is still at the limit
+        assert!(!CallCapacity { status: AgentStatus::Ready, ..capacity }.can_take_call());
+        assert!(CallCapacity { status: AgentStatus::Ready, active_calls: 0, ..capacity }.can_take_call());
+
+        for status in [AgentStatus::Offline, AgentStatus::AfterCall, AgentStatus::Break] {
+            assert!(!CallCapacity { status, active_calls: 0, ..capacity }.can_take_call());
+        }
+    }
+
+    #[test]
+    fn test_raised_limit_allows_concurrent_calls() {
+        let capacity = CallCapacity {
+            status: AgentStatus::OnCall,
+            max_concurrent_calls: 2,
+            active_calls: 1,
+        };
+        assert!(capacity.can_take_call());
+        assert!(!CallCapacity { active_calls: 2, ..capacity }.can_take_call());
+
+        assert!(AgentCallLimit { max_concurrent_calls: 2 }.validate().is_ok());
+        assert!(AgentCallLimit { max_concurrent_calls: 0 }.validate().is_err());
+    }
+
+    fn weekday_schedule(timezone: &str) -> AgentSchedule {
+        // Monday-Friday, 9:00-17:00
+        AgentSchedule {
+            agent_id: 1,
+            timezone: timezone.to_string(),
+            slots: (0..5)
+                .map(|day| ScheduleSlot {
+                    day_of_week: day,
+                    start_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    end_time: NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                })
+                .collect(),
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_going_ready_outside_schedule_is_rejected() {
+        let schedule = weekday_schedule("UTC");
+
+        // Saturday, and a Monday evening
+        assert!(!schedule.allows_status(AgentStatus::Ready, utc("2024-06-08T10:00:00Z")));
+        assert!(!schedule.allows_status(AgentStatus::Ready, utc("2024-06-10T17:00:00Z")));
+
+        // Other statuses are never restricted
+        assert!(schedule.allows_status(AgentStatus::Break, utc("2024-06-08T10:00:00Z")));
+        assert!(schedule.allows_status(AgentStatus::Offline, utc("2024-06-08T10:00:00Z")));
+    }
+
+    #[test]
+    fn test_going_ready_inside_schedule_is_allowed() {
+        let schedule = weekday_schedule("UTC");
+        assert!(schedule.allows_status(AgentStatus::Ready, utc("2024-06-10T09:00:00Z")));
+        assert!(schedule.allows_status(AgentStatus::Ready, utc("2024-06-14T16:59:00Z")));
+    }
+
+    #[test]
+    fn test_schedule_uses_agent_timezone() {
+        let schedule = weekday_schedule("America/New_York");
+
+        // 14:00 UTC is 10:00 in New York (EDT)
+        assert!(schedule.is_on_shift(utc("2024-06-10T14:00:00Z")));
+        // 10:00 UTC is 06:00 in New York
+        assert!(!schedule.is_on_shift(utc("2024-06-10T10:00:00Z")));
+    }
+
+    #[test]
+    fn test_overnight_shift_spans_midnight() {
+        let schedule = AgentSchedule {
+            agent_id: 1,
+            timezone: "UTC".to_string(),
+            slots: vec![ScheduleSlot {
+                day_of_week: 6,
+                start_time: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+                end_time: NaiveTime::from_hms_opt(6, 0, 0).unwrap(),
+            }],
+        };
+
+        // Sunday 23:00 and the following Monday 05:00
+        assert!(schedule.is_on_shift(utc("2024-06-09T23:00:00Z")));
+        assert!(schedule.is_on_shift(utc("2024-06-10T05:00:00Z")));
+        assert!(!schedule.is_on_shift(utc("2024-06-10T07:00:00Z")));
+    }
+
+    #[test]
+    fn test_empty_schedule_is_unrestricted() {
+        let schedule = AgentSchedule { agent_id: 1, timezone: "UTC".to_string(), slots: vec![] };
+        assert!(schedule.allows_status(AgentStatus::Ready, utc("2024-06-08T03:00:00Z")));
+    }
+
+    #[test]
+    fn test_agent_goes_on_break_with_reason() {
+        let req: SetMyStatusRequest = serde_json::from_str(r#"{"status":"BREAK","reason":"LUNCH"}"#).unwrap();
+        assert_eq!(req.status, AgentStatus::Break);
+        assert_eq!(req.reason, Some(StatusReason::Lunch));
+        assert!(req.validate().is_ok());
+
+        // The reason is kept on the status history entry
+        let change = crate::models::AgentStatusChange {
+            from_status: Some(AgentStatus::Ready),
+            to_status: req.status,
+            changed_at: utc("2024-06-10T12:00:00Z"),
+            reason: req.reason,
+        };
+        let json = serde_json::to_value(&change).unwrap();
+        assert_eq!(json["reason"], "LUNCH");
+        assert_eq!(serde_json::from_value::<crate::models::AgentStatusChange>(json).unwrap(), change);
+    }
+
+    #[test]
+    fn test_on_call_cannot_be_chosen() {
+        let req = |status, reason| SetMyStatusRequest { status, reason };
+        assert!(req(AgentStatus::OnCall, None).validate().is_err());
+        assert!(req(AgentStatus::AfterCall, None).validate().is_err());
+        assert!(req(AgentStatus::Ready, Some(StatusReason::Training)).validate().is_err());
+        assert!(req(AgentStatus::Ready, None).validate().is_ok());
+        assert!(req(AgentStatus::Offline, Some(StatusReason::Training)).validate().is_ok());
+
+        // Older clients send no reason
+        let req: SetMyStatusRequest = serde_json::from_str(r#"{"status":"BREAK"}"#).unwrap();
+        assert_eq!(req.reason, None);
+    }
 }
@@ -24,6 +24,96 @@ pub struct AiAgentSettings {
     pub updated_at: Option<DateTime<Utc>>,
 }
 
+/// AI settings for every call in a campaign, overriding the agent's
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignAiSettings {
+    pub id: i64,
+    #[serde(rename = "campaignId")]
+    pub campaign_id: i64,
+    #[serde(rename = "systemPrompt")]
+    pub system_prompt: String,
+    #[serde(rename = "greetingMessage")]
+    pub greeting_message: Option<String>,
+    #[serde(rename = "voiceId")]
+    pub voice_id: Option<String>,
+    pub language: String,
+    #[serde(rename = "maxResponseTokens")]
+    pub max_response_tokens: Option<i32>,
+    pub temperature: Option<f64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Voice used when neither the campaign, the agent nor the global config sets one
+pub const DEFAULT_AI_VOICE: &str = "female";
+
+/// Response length used when neither the campaign nor the agent sets one
+pub const DEFAULT_MAX_RESPONSE_TOKENS: i32 = 150;
+
+/// Temperature used when neither the campaign nor the agent sets one
+pub const DEFAULT_TEMPERATURE: f64 = 0.7;
+
+/// Where the script for an AI call came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiSettingsSource {
+    Campaign,
+    Agent,
+}
+
+/// The settings an AI call runs with
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedAiSettings {
+    pub source: AiSettingsSource,
+    pub system_prompt: String,
+    pub greeting_message: Option<String>,
+    pub voice_id: String,
+    pub language: String,
+    pub max_response_tokens: i32,
+    pub temperature: f64,
+}
+
+impl ResolvedAiSettings {
+    /// Campaign settings take precedence over the agent's, which take
+    /// precedence over the global defaults. The script (prompt, greeting and
+    /// language) comes whole from the first that has one; voice and tuning
+    /// left blank fall through to the next, the voice ending at the global
+    /// config's `default_voice`. None when neither has a script.
+    pub fn resolve(
+        campaign: Option<&CampaignAiSettings>,
+        agent: Option<&AiAgentSettings>,
+        global: &GlobalAiConfig,
+    ) -> Option<Self> {
+        let (source, system_prompt, greeting_message, language) = match (campaign, agent) {
+            (Some(c), _) => (AiSettingsSource::Campaign, &c.system_prompt, &c.greeting_message, &c.language),
+            (None, Some(a)) => (AiSettingsSource::Agent, &a.system_prompt, &a.greeting_message, &a.language),
+            (None, None) => return None,
+        };
+
+        Some(Self {
+            source,
+            system_prompt: system_prompt.clone(),
+            greeting_message: greeting_message.clone(),
+            language: language.clone(),
+            voice_id: campaign
+                .and_then(|c| c.voice_id.clone())
+                .or_else(|| agent.and_then(|a| a.voice_id.clone()))
+                .or_else(|| Some(global.default_voice.trim()).filter(|v| !v.is_empty()).map(str::to_string))
+                .unwrap_or_else(|| DEFAULT_AI_VOICE.to_string()),
+            max_response_tokens: campaign
+                .and_then(|c| c.max_response_tokens)
+                .or_else(|| agent.and_then(|a| a.max_response_tokens))
+                .unwrap_or(DEFAULT_MAX_RESPONSE_TOKENS),
+            temperature: campaign
+                .and_then(|c| c.temperature)
+                .or_else(|| agent.and_then(|a| a.temperature))
+                .unwrap_or(DEFAULT_TEMPERATURE),
+        })
+    }
+}
+
 /// Global AI configuration (not agent-specific)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GlobalAiConfig {
@@ -73,6 +163,21 @@ pub struct UpsertAiSettingsRequest {
     pub temperature: Option<f64>,
 }
 
+/// Request to set a campaign's AI settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertCampaignAiSettingsRequest {
+    #[serde(rename = "systemPrompt")]
+    pub system_prompt: String,
+    #[serde(rename = "greetingMessage")]
+    pub greeting_message: Option<String>,
+    #[serde(rename = "voiceId")]
+    pub voice_id: Option<String>,
+    pub language: Option<String>,
+    #[serde(rename = "maxResponseTokens")]
+    pub max_response_tokens: Option<i32>,
+    pub temperature: Option<f64>,
+}
+
 /// Prompt template for AI agents
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PromptTemplate {
@@ -173,3 +278,83 @@ pub struct SttResponse {
     #[serde(rename = "isFinal")]
     pub is_final: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent_settings() -> AiAgentSettings {
+        AiAgentSettings {
+            id: 1,
+            agent_id: 7,
+            system_prompt: "You are a friendly sales assistant.".to_string(),
+            greeting_message: Some("Hi, it's Sam.".to_string()),
+            voice_id: Some("male".to_string()),
+            language: "en-US".to_string(),
+            max_response_tokens: Some(200),
+            temperature: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn campaign_settings() -> CampaignAiSettings {
+        CampaignAiSettings {
+            id: 2,
+            campaign_id: 3,
+            system_prompt: "You are calling about the spring renewal offer.".to_string(),
+            greeting_message: None,
+            voice_id: None,
+            language: "es-US".to_string(),
+            max_response_tokens: Some(120),
+            temperature: None,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_campaign_settings_override_agent() {
+        let resolved =
+            ResolvedAiSettings::resolve(Some(&campaign_settings()), Some(&agent_settings()), &GlobalAiConfig::default())
+                .unwrap();
+
+        assert_eq!(resolved.source, AiSettingsSource::Campaign);
+        assert_eq!(resolved.system_prompt, "You are calling about the spring renewal offer.");
+        assert_eq!(resolved.language, "es-US");
+        // The agent's greeting belongs to the agent's script, not the campaign's
+        assert_eq!(resolved.greeting_message, None);
+        assert_eq!(resolved.max_response_tokens, 120);
+        // Blank voice and tuning fall through to the agent, then the defaults
+        assert_eq!(resolved.voice_id, "male");
+        assert_eq!(resolved.temperature, DEFAULT_TEMPERATURE);
+    }
+
+    #[test]
+    fn test_agent_settings_without_campaign() {
+        let resolved = ResolvedAiSettings::resolve(None, Some(&agent_settings()), &GlobalAiConfig::default()).unwrap();
+
+        assert_eq!(resolved.source, AiSettingsSource::Agent);
+        assert_eq!(resolved.system_prompt, "You are a friendly sales assistant.");
+        assert_eq!(resolved.greeting_message.as_deref(), Some("Hi, it's Sam."));
+        assert_eq!(resolved.voice_id, "male");
+        assert_eq!(resolved.max_response_tokens, 200);
+        assert_eq!(resolved.temperature, DEFAULT_TEMPERATURE);
+
+        assert_eq!(ResolvedAiSettings::resolve(None, None, &GlobalAiConfig::default()), None);
+    }
+
+    #[test]
+    fn test_voice_falls_back_to_global_default() {
+        let agent = AiAgentSettings { voice_id: None, ..agent_settings() };
+        let global = GlobalAiConfig { default_voice: "nova".to_string(), ..GlobalAiConfig::default() };
+
+        let resolved = ResolvedAiSettings::resolve(Some(&campaign_settings()), Some(&agent), &global).unwrap();
+        assert_eq!(resolved.voice_id, "nova");
+
+        // A blank global voice leaves the built-in default
+        let global = GlobalAiConfig { default_voice: " ".to_string(), ..GlobalAiConfig::default() };
+        let resolved = ResolvedAiSettings::resolve(None, Some(&agent), &global).unwrap();
+        assert_eq!(resolved.voice_id, DEFAULT_AI_VOICE);
+    }
+}
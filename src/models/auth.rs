@@ -1,15 +1,63 @@
 use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
+    /// Keep the session for 30 days instead of JWT_EXPIRY_HOURS
+    #[serde(rename = "rememberMe", default)]
+    pub remember_me: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoginResponse {
     pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
     pub user: UserInfo,
+    /// When the access token expires and must be refreshed
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    /// When the session ends and the user must log in again
+    #[serde(rename = "refreshExpiresAt")]
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenRequest {
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefreshTokenResponse {
+    pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "refreshExpiresAt")]
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+/// An active login, as listed by `GET /api/auth/sessions`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionInfo {
+    /// Stays the same when the session's refresh token rotates
+    pub id: i64,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: DateTime<Utc>,
+    #[serde(rename = "lastUsedAt")]
+    pub last_used_at: DateTime<Utc>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(rename = "rememberMe")]
+    pub remember_me: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,6 +83,8 @@ pub struct VerifyEmailRequest {
 pub struct VerifyEmailResponse {
     pub message: String,
     pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
     pub user: UserInfo,
 }
 
@@ -73,6 +123,8 @@ pub struct AcceptInvitationRequest {
 pub struct AcceptInvitationResponse {
     pub message: String,
     pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
     pub user: UserInfo,
 }
 
@@ -102,6 +154,32 @@ pub struct UserInfo {
     pub last_name: Option<String>,
 }
 
+/// Which notifications a user receives. Everything but the daily report is on
+/// until they opt out; the report has to be asked for.
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NotificationPreferences {
+    /// Email when a lead is assigned to them
+    #[serde(rename = "emailOnAssignment")]
+    pub email_on_assignment: bool,
+    /// Email with the previous day's call totals (supervisors and admins, opt-in)
+    #[serde(rename = "emailDailyReport")]
+    pub email_daily_report: bool,
+    /// Toasts for incoming calls in the browser
+    #[serde(rename = "browserCallAlerts")]
+    pub browser_call_alerts: bool,
+}
+
+impl Default for NotificationPreferences {
+    fn default() -> Self {
+        Self {
+            email_on_assignment: true,
+            email_daily_report: false,
+            browser_call_alerts: true,
+        }
+    }
+}
+
 /// Full user struct for server-side use (includes password_hash)
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -115,6 +193,7 @@ pub struct User {
     #[serde(skip_serializing)]
     pub password_hash: String,
     pub email_verified: bool,
+    pub active: bool,
 }
 
 impl User {
@@ -128,6 +207,54 @@ impl User {
             last_name: self.last_name.clone(),
         }
     }
+
+    pub fn to_managed(&self) -> ManagedUser {
+        ManagedUser {
+            id: self.id,
+            username: self.username.clone(),
+            email: self.email.clone(),
+            role: self.role.clone(),
+            first_name: self.first_name.clone(),
+            last_name: self.last_name.clone(),
+            email_verified: self.email_verified,
+            active: self.active,
+        }
+    }
+}
+
+/// User as shown in the admin user management list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManagedUser {
+    pub id: i64,
+    pub username: String,
+    pub email: String,
+    pub role: UserRole,
+    #[serde(rename = "firstName")]
+    pub first_name: Option<String>,
+    #[serde(rename = "lastName")]
+    pub last_name: Option<String>,
+    #[serde(rename = "emailVerified")]
+    pub email_verified: bool,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserListResponse {
+    pub users: Vec<ManagedUser>,
+    pub total: i64,
+    pub page: i64,
+    #[serde(rename = "perPage")]
+    pub per_page: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateUserRoleRequest {
+    pub role: UserRole,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetUserDisabledRequest {
+    pub disabled: bool,
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
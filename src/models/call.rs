@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use super::{Lead, LeadNote};
+
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Call {
@@ -30,6 +32,16 @@ pub struct Call {
     pub disposition: Option<String>,
     #[serde(rename = "recordingUrl")]
     pub recording_url: Option<String>,
+    /// Channel layout of the recording, set once recording starts
+    #[serde(rename = "recordingChannels", default)]
+    pub recording_channels: Option<RecordingChannels>,
+    #[serde(rename = "dispositionId")]
+    pub disposition_id: Option<i64>,
+    #[serde(rename = "wrapUpNotes")]
+    pub wrap_up_notes: Option<String>,
+    /// Caller sentiment from -1 to 1, scored after an AI call ends
+    #[serde(default)]
+    pub sentiment: Option<f32>,
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
@@ -75,6 +87,166 @@ impl CallStatus {
     }
 }
 
+/// What answering machine detection decided about an answered call
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "amd_outcome", rename_all = "PascalCase"))]
+pub enum AmdOutcome {
+    Human,
+    Machine,
+    Fax,
+    Silence,
+    NotSure,
+    /// Detection didn't finish in time and the call went ahead as human
+    Timeout,
+}
+
+impl AmdOutcome {
+    /// Parse the `result` of a standard or premium detection event
+    pub fn from_result(result: &str) -> Option<Self> {
+        match result {
+            "human" | "human_residence" | "human_business" => Some(AmdOutcome::Human),
+            "machine" => Some(AmdOutcome::Machine),
+            "fax_detected" => Some(AmdOutcome::Fax),
+            "silence" => Some(AmdOutcome::Silence),
+            "not_sure" => Some(AmdOutcome::NotSure),
+            _ => None,
+        }
+    }
+
+    /// Whether nobody is there to talk to
+    pub fn is_machine(&self) -> bool {
+        matches!(self, AmdOutcome::Machine | AmdOutcome::Fax)
+    }
+}
+
+/// Whether a live call is being recorded
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "recording_state", rename_all = "PascalCase"))]
+pub enum RecordingState {
+    Off,
+    Recording,
+    Paused,
+}
+
+/// Whether both parties share one audio channel or each gets their own
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "recording_channels", rename_all = "PascalCase"))]
+pub enum RecordingChannels {
+    Single,
+    /// Caller and callee on separate channels, for transcription by speaker
+    #[default]
+    Dual,
+}
+
+impl RecordingChannels {
+    /// Value of Telnyx's `channels` recording parameter
+    pub fn telnyx_value(&self) -> &'static str {
+        match self {
+            RecordingChannels::Single => "single",
+            RecordingChannels::Dual => "dual",
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        match self {
+            RecordingChannels::Single => "Single channel",
+            RecordingChannels::Dual => "Dual channel (one per party)",
+        }
+    }
+}
+
+/// Audio format recordings are saved in
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "recording_format", rename_all = "PascalCase"))]
+pub enum RecordingFormat {
+    #[default]
+    Mp3,
+    Wav,
+}
+
+impl RecordingFormat {
+    /// Value of Telnyx's `format` recording parameter
+    pub fn telnyx_value(&self) -> &'static str {
+        match self {
+            RecordingFormat::Mp3 => "mp3",
+            RecordingFormat::Wav => "wav",
+        }
+    }
+
+    pub fn display_name(&self) -> &str {
+        match self {
+            RecordingFormat::Mp3 => "MP3",
+            RecordingFormat::Wav => "WAV",
+        }
+    }
+}
+
+/// How a call is recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RecordingSettings {
+    pub channels: RecordingChannels,
+    pub format: RecordingFormat,
+}
+
+impl RecordingSettings {
+    /// One channel, for recordings of a single speaker such as voicemail
+    pub fn single() -> Self {
+        Self { channels: RecordingChannels::Single, ..Self::default() }
+    }
+}
+
+/// A recording control an agent can use mid-call
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordingAction {
+    Start,
+    Pause,
+    Resume,
+    Stop,
+}
+
+impl RecordingAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RecordingAction::Start => "start",
+            RecordingAction::Pause => "pause",
+            RecordingAction::Resume => "resume",
+            RecordingAction::Stop => "stop",
+        }
+    }
+}
+
+impl RecordingState {
+    /// State after `action`, or why it can't be taken from this state
+    pub fn apply(self, action: RecordingAction) -> Result<RecordingState, String> {
+        match (self, action) {
+            (RecordingState::Off, RecordingAction::Start) => Ok(RecordingState::Recording),
+            (RecordingState::Recording, RecordingAction::Pause) => Ok(RecordingState::Paused),
+            (RecordingState::Paused, RecordingAction::Resume) => Ok(RecordingState::Recording),
+            (RecordingState::Recording | RecordingState::Paused, RecordingAction::Stop) => Ok(RecordingState::Off),
+            (RecordingState::Off, _) => Err("The call is not being recorded".to_string()),
+            (RecordingState::Recording, _) => Err("The call is already being recorded".to_string()),
+            (RecordingState::Paused, _) => Err("Recording is paused; resume or stop it".to_string()),
+        }
+    }
+}
+
+/// Recording state of a call after a control was used
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecordingStatus {
+    #[serde(rename = "callId")]
+    pub call_id: i64,
+    pub state: RecordingState,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialRequest {
     #[serde(rename = "leadId")]
@@ -101,3 +273,400 @@ pub struct TransferRequest {
     #[serde(rename = "targetNumber")]
     pub target_number: Option<String>,
 }
+
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConferenceParticipant {
+    pub id: i64,
+    #[serde(rename = "callId")]
+    pub call_id: i64,
+    #[serde(rename = "conferenceId")]
+    pub conference_id: String,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+    #[serde(rename = "callControlId")]
+    pub call_control_id: String,
+    pub muted: bool,
+    /// Set when a supervisor joined to monitor the call (listen, whisper or barge)
+    #[serde(rename = "monitorMode")]
+    pub monitor_mode: Option<String>,
+    #[serde(rename = "joinedAt")]
+    pub joined_at: Option<DateTime<Utc>>,
+    #[serde(rename = "leftAt")]
+    pub left_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinConferenceRequest {
+    /// Call leg of the participant joining (e.g. the supervisor's WebRTC leg)
+    #[serde(rename = "callControlId")]
+    pub call_control_id: String,
+    /// Join muted (listen-only)
+    #[serde(default)]
+    pub mute: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaveConferenceRequest {
+    #[serde(rename = "callControlId")]
+    pub call_control_id: String,
+}
+
+/// How a supervisor participates when monitoring a call
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitorMode {
+    /// Supervisor hears both sides, nobody hears the supervisor
+    Listen,
+    /// Supervisor is audible to the agent only
+    Whisper,
+    /// Supervisor is audible to everyone
+    Barge,
+}
+
+impl MonitorMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MonitorMode::Listen => "listen",
+            MonitorMode::Whisper => "whisper",
+            MonitorMode::Barge => "barge",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonitorCallRequest {
+    pub mode: MonitorMode,
+    /// The supervisor's call leg
+    #[serde(rename = "callControlId")]
+    pub call_control_id: String,
+    /// The agent's call leg - required for whisper so only the agent hears the supervisor
+    #[serde(rename = "agentCallControlId")]
+    pub agent_call_control_id: Option<String>,
+}
+
+/// Wrap-up code recorded after a call
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Disposition {
+    pub id: i64,
+    pub code: String,
+    pub label: String,
+    #[serde(rename = "isSuccess")]
+    pub is_success: bool,
+    pub active: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDispositionRequest {
+    pub code: String,
+    pub label: String,
+    #[serde(rename = "isSuccess", default)]
+    pub is_success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetDispositionRequest {
+    #[serde(rename = "dispositionId")]
+    pub disposition_id: i64,
+    #[serde(rename = "wrapUpNotes")]
+    pub wrap_up_notes: Option<String>,
+}
+
+/// Number of calls with a given disposition
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DispositionCount {
+    #[serde(rename = "dispositionId")]
+    pub disposition_id: i64,
+    pub code: String,
+    pub label: String,
+    pub count: i64,
+}
+
+/// An inbound caller waiting in the queue
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QueuedCallInfo {
+    #[serde(rename = "callId")]
+    pub call_id: i64,
+    #[serde(rename = "callControlId")]
+    pub call_control_id: String,
+    pub from: String,
+    #[serde(rename = "leadId")]
+    pub lead_id: Option<i64>,
+    /// 1-based position, 1 being next to be answered
+    pub position: usize,
+    #[serde(rename = "enqueuedAt")]
+    pub enqueued_at: DateTime<Utc>,
+    #[serde(rename = "waitSeconds")]
+    pub wait_seconds: i64,
+}
+
+/// Where a call event came from
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "call_event_source", rename_all = "PascalCase"))]
+pub enum CallEventSource {
+    /// A Telnyx webhook we processed
+    Webhook,
+    /// Something we asked Telnyx to do
+    Action,
+}
+
+/// Entry in a call's event log
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CallEvent {
+    pub id: i64,
+    /// Unset for webhooks about a call we have no record of
+    #[serde(rename = "callId")]
+    pub call_id: Option<i64>,
+    /// Telnyx leg a webhook was sent for
+    #[serde(rename = "callControlId")]
+    pub call_control_id: Option<String>,
+    pub source: CallEventSource,
+    /// Telnyx event type (e.g. `call.answered`) or our action (e.g. `hangup`)
+    #[serde(rename = "eventType")]
+    pub event_type: String,
+    pub payload: serde_json::Value,
+    #[serde(rename = "processedAt")]
+    pub processed_at: DateTime<Utc>,
+}
+
+/// Progress of the callback to an abandoned caller
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "abandoned_callback_status", rename_all = "PascalCase"))]
+pub enum CallbackStatus {
+    /// Waiting for a free agent
+    Pending,
+    /// Claimed for an agent and being dialed
+    Dialing,
+    Dialed,
+    /// No agent was free before the callback window closed
+    Expired,
+}
+
+/// An inbound caller who hung up while waiting in the queue
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AbandonedCall {
+    pub id: i64,
+    #[serde(rename = "callId")]
+    pub call_id: i64,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+    #[serde(rename = "leadId")]
+    pub lead_id: Option<i64>,
+    #[serde(rename = "abandonedAt")]
+    pub abandoned_at: DateTime<Utc>,
+    /// The callback expires if no agent is free by then
+    #[serde(rename = "callbackDueBy")]
+    pub callback_due_by: DateTime<Utc>,
+    pub status: CallbackStatus,
+    /// The outbound call placed as the callback
+    #[serde(rename = "callbackCallId")]
+    pub callback_call_id: Option<i64>,
+    #[serde(rename = "dialedAt")]
+    pub dialed_at: Option<DateTime<Utc>>,
+}
+
+/// Most recent notes shown when an inbound call pops up
+pub const SCREEN_POP_NOTES: usize = 3;
+
+/// Who is calling, shown to the agent an inbound call is routed to
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ScreenPop {
+    pub lead: Option<Lead>,
+    /// Newest first
+    #[serde(rename = "recentNotes")]
+    pub recent_notes: Vec<LeadNote>,
+    /// Disposition of the lead's previous call, or how it ended if it has none
+    #[serde(rename = "lastCallOutcome")]
+    pub last_call_outcome: Option<String>,
+    /// The number doesn't match any lead
+    #[serde(rename = "newCaller")]
+    pub new_caller: bool,
+}
+
+impl ScreenPop {
+    /// Screen pop for a caller whose number matched no lead
+    pub fn new_caller() -> Self {
+        Self { new_caller: true, ..Self::default() }
+    }
+
+    /// Screen pop for a matched lead. `notes` are oldest first and `calls`
+    /// newest first, as stored; the call being routed is skipped.
+    pub fn for_lead(lead: Lead, notes: Vec<LeadNote>, calls: &[Call], current_call_id: i64) -> Self {
+        let recent_notes = notes.into_iter().rev().take(SCREEN_POP_NOTES).collect();
+        let last_call_outcome = calls
+            .iter()
+            .find(|call| call.id != current_call_id)
+            .map(|call| call.disposition.clone().unwrap_or_else(|| call.status.display_name().to_string()));
+
+        Self {
+            lead: Some(lead),
+            recent_notes,
+            last_call_outcome,
+            new_caller: false,
+        }
+    }
+}
+
+/// SIP user agent event, streamed to the browser from `/api/sip/events`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SipEvent {
+    /// A trunk's registration state changed
+    StateChanged { trunk: usize, state: String },
+    /// A call arrived on a trunk
+    IncomingCall {
+        trunk: usize,
+        call_id: String,
+        from: String,
+        to: String,
+    },
+    /// A call moved to a new state
+    CallState {
+        trunk: usize,
+        call_id: String,
+        state: String,
+    },
+    /// The user agent reported an error
+    Error { trunk: usize, message: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LeadStatus;
+
+    #[test]
+    fn test_amd_outcome_from_standard_and_premium_results() {
+        assert_eq!(AmdOutcome::from_result("human"), Some(AmdOutcome::Human));
+        assert_eq!(AmdOutcome::from_result("human_business"), Some(AmdOutcome::Human));
+        assert_eq!(AmdOutcome::from_result("machine"), Some(AmdOutcome::Machine));
+        assert_eq!(AmdOutcome::from_result("fax_detected"), Some(AmdOutcome::Fax));
+        assert_eq!(AmdOutcome::from_result("not_sure"), Some(AmdOutcome::NotSure));
+        assert_eq!(AmdOutcome::from_result("beep_detected"), None);
+
+        assert!(AmdOutcome::Fax.is_machine());
+        assert!(!AmdOutcome::Silence.is_machine());
+        assert!(!AmdOutcome::Timeout.is_machine());
+    }
+
+    fn lead() -> Lead {
+        Lead {
+            id: 8,
+            first_name: Some("Maria".to_string()),
+            last_name: Some("Lopez".to_string()),
+            phone: "+15550100".to_string(),
+            email: None,
+            company: None,
+            status: LeadStatus::Contacted,
+            notes: None,
+            assigned_agent_id: None,
+            campaign_id: None,
+            call_attempts: 2,
+            last_call_at: None,
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    fn note(id: i64) -> LeadNote {
+        LeadNote {
+            id,
+            lead_id: 8,
+            author_id: Some(1),
+            author_name: Some("alice".to_string()),
+            content: format!("note {}", id),
+            created_at: None,
+        }
+    }
+
+    fn call(id: i64, status: CallStatus, disposition: Option<&str>) -> Call {
+        Call {
+            id,
+            call_control_id: None,
+            lead_id: Some(8),
+            agent_id: None,
+            campaign_id: None,
+            direction: CallDirection::Outbound,
+            status,
+            from_number: None,
+            to_number: None,
+            started_at: None,
+            answered_at: None,
+            ended_at: None,
+            duration_seconds: None,
+            disposition: disposition.map(str::to_string),
+            recording_url: None,
+            recording_channels: None,
+            disposition_id: None,
+            wrap_up_notes: None,
+            sentiment: None,
+        }
+    }
+
+    #[test]
+    fn test_screen_pop_for_matched_lead() {
+        let notes = (1..=5).map(note).collect();
+        // The inbound call itself is the newest call on the lead
+        let calls = [call(30, CallStatus::Ringing, None), call(21, CallStatus::Completed, Some("Callback requested"))];
+
+        let pop = ScreenPop::for_lead(lead(), notes, &calls, 30);
+        assert_eq!(pop.lead.as_ref().map(|l| l.id), Some(8));
+        assert_eq!(pop.recent_notes.iter().map(|n| n.id).collect::<Vec<_>>(), vec![5, 4, 3]);
+        assert_eq!(pop.last_call_outcome.as_deref(), Some("Callback requested"));
+        assert!(!pop.new_caller);
+
+        // Without a disposition, how the call ended is shown
+        let pop = ScreenPop::for_lead(lead(), Vec::new(), &[call(21, CallStatus::NoAnswer, None)], 30);
+        assert_eq!(pop.last_call_outcome.as_deref(), Some("No Answer"));
+
+        // A first-time call from a known lead has no previous outcome
+        let pop = ScreenPop::for_lead(lead(), Vec::new(), &calls[..1], 30);
+        assert_eq!(pop.last_call_outcome, None);
+    }
+
+    #[test]
+    fn test_screen_pop_for_unknown_number() {
+        let pop = ScreenPop::new_caller();
+        assert!(pop.new_caller);
+        assert!(pop.lead.is_none());
+        assert!(pop.recent_notes.is_empty());
+
+        let json = serde_json::to_value(&pop).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "lead": null, "recentNotes": [], "lastCallOutcome": null, "newCaller": true })
+        );
+    }
+
+    #[test]
+    fn test_recording_start_pause_resume_stop() {
+        let state = RecordingState::Off.apply(RecordingAction::Start).unwrap();
+        assert_eq!(state, RecordingState::Recording);
+        let state = state.apply(RecordingAction::Pause).unwrap();
+        assert_eq!(state, RecordingState::Paused);
+        let state = state.apply(RecordingAction::Resume).unwrap();
+        assert_eq!(state, RecordingState::Recording);
+
+        // Stopping works whether recording or paused
+        assert_eq!(state.apply(RecordingAction::Stop), Ok(RecordingState::Off));
+        assert_eq!(RecordingState::Paused.apply(RecordingAction::Stop), Ok(RecordingState::Off));
+    }
+
+    #[test]
+    fn test_recording_rejects_out_of_order_controls() {
+        assert!(RecordingState::Off.apply(RecordingAction::Pause).is_err());
+        assert!(RecordingState::Off.apply(RecordingAction::Stop).is_err());
+        assert!(RecordingState::Recording.apply(RecordingAction::Start).is_err());
+        assert!(RecordingState::Recording.apply(RecordingAction::Resume).is_err());
+        assert!(RecordingState::Paused.apply(RecordingAction::Pause).is_err());
+    }
+}
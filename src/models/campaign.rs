@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, NaiveTime, Utc};
+use super::phone::nanp_area_code;
+use super::{CallStatus, Lead, RecordingChannels, RecordingFormat, RecordingSettings};
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -30,6 +32,134 @@ pub struct Campaign {
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "holdMusicUrl")]
+    pub hold_music_url: Option<String>,
+    #[serde(rename = "amdMode")]
+    pub amd_mode: AmdMode,
+    #[serde(rename = "leaveVoicemail")]
+    pub leave_voicemail: bool,
+    #[serde(rename = "voicemailMessage")]
+    pub voicemail_message: Option<String>,
+    #[serde(rename = "scheduledStartAt", default)]
+    pub scheduled_start_at: Option<DateTime<Utc>>,
+    #[serde(rename = "scheduledEndAt", default)]
+    pub scheduled_end_at: Option<DateTime<Utc>>,
+    /// Numbers used for local-presence dialing, picked by the lead's area code
+    #[serde(rename = "callerIdPool", default)]
+    pub caller_id_pool: Vec<String>,
+    /// Pre-recorded message played to answering machines instead of text-to-speech
+    #[serde(rename = "voicemailAudioUrl", default)]
+    pub voicemail_audio_url: Option<String>,
+    /// Spoken when a call is answered, with placeholders like {lead_name}
+    #[serde(rename = "greetingTemplate", default)]
+    pub greeting_template: Option<String>,
+    /// Mask card numbers, SSNs and long digit runs in stored AI transcripts
+    #[serde(rename = "redactTranscripts", default = "redact_transcripts_default")]
+    pub redact_transcripts: bool,
+    /// Skill an agent needs to take the campaign's inbound calls
+    #[serde(rename = "requiredSkill", default)]
+    pub required_skill: Option<String>,
+    /// Longest a call may last before it is hung up; None uses `MAX_CALL_SECONDS`
+    #[serde(rename = "maxCallSeconds", default)]
+    pub max_call_seconds: Option<i32>,
+    #[serde(rename = "recordingChannels", default)]
+    pub recording_channels: RecordingChannels,
+    #[serde(rename = "recordingFormat", default)]
+    pub recording_format: RecordingFormat,
+    /// Lead counts computed from the campaign's leads; not stored on the row
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(skip))]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<CampaignProgress>,
+}
+
+/// Spoken when a campaign leaves voicemail without its own message
+pub const DEFAULT_VOICEMAIL_MESSAGE: &str =
+    "Hello, we're sorry we missed you. Please call us back at your earliest convenience. Thank you.";
+
+/// What to leave on an answering machine
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VoicemailDrop<'a> {
+    /// Play a pre-recorded audio file
+    Audio(&'a str),
+    /// Speak a message with text-to-speech
+    Message(&'a str),
+}
+
+impl Campaign {
+    /// Voicemail to leave when a machine answers, or None to hang up
+    pub fn voicemail_drop(&self) -> Option<VoicemailDrop<'_>> {
+        if !self.leave_voicemail {
+            return None;
+        }
+
+        let audio = self.voicemail_audio_url.as_deref().filter(|url| !url.trim().is_empty());
+        Some(match audio {
+            Some(url) => VoicemailDrop::Audio(url),
+            None => VoicemailDrop::Message(self.voicemail_message.as_deref().unwrap_or(DEFAULT_VOICEMAIL_MESSAGE)),
+        })
+    }
+
+    /// How the campaign's calls are recorded
+    pub fn recording_settings(&self) -> RecordingSettings {
+        RecordingSettings {
+            channels: self.recording_channels,
+            format: self.recording_format,
+        }
+    }
+
+    /// AMD mode to dial with
+    ///
+    /// A recorded voicemail drop has to start after the beep, otherwise the
+    /// beginning of the message is lost, so plain detection is upgraded to
+    /// waiting for the beep.
+    pub fn dial_amd_mode(&self) -> AmdMode {
+        match (self.voicemail_drop(), self.amd_mode) {
+            (Some(VoicemailDrop::Audio(_)), AmdMode::Detect) => AmdMode::DetectBeep,
+            (_, mode) => mode,
+        }
+    }
+
+    /// Caller ID to present when dialing `destination`
+    ///
+    /// A pool number in the destination's area code wins, then the campaign's
+    /// own caller ID, then `default`.
+    pub fn caller_id_for<'a>(&'a self, destination: &str, default: &'a str) -> &'a str {
+        let local = nanp_area_code(destination).and_then(|area| {
+            self.caller_id_pool
+                .iter()
+                .find(|number| nanp_area_code(number).as_deref() == Some(area.as_str()))
+        });
+
+        local
+            .map(String::as_str)
+            .or(self.caller_id.as_deref().filter(|id| !id.trim().is_empty()))
+            .unwrap_or(default)
+    }
+
+    /// Status the scheduler should move this campaign to at `now`, if any.
+    ///
+    /// A Draft or Paused campaign whose start has passed becomes Active (unless
+    /// its end has passed too). An Active campaign whose end has passed is
+    /// Completed once every lead has been dialed, otherwise Paused.
+    pub fn scheduled_transition(&self, now: DateTime<Utc>) -> Option<CampaignStatus> {
+        let ended = self.scheduled_end_at.is_some_and(|end| end <= now);
+
+        match self.status {
+            CampaignStatus::Draft | CampaignStatus::Paused => {
+                let started = self.scheduled_start_at.is_some_and(|start| start <= now);
+                (started && !ended).then_some(CampaignStatus::Active)
+            }
+            CampaignStatus::Active if ended => {
+                let total = self.total_leads.unwrap_or(0);
+                if total > 0 && self.dialed_leads.unwrap_or(0) >= total {
+                    Some(CampaignStatus::Completed)
+                } else {
+                    Some(CampaignStatus::Paused)
+                }
+            }
+            _ => None,
+        }
+    }
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
@@ -83,6 +213,47 @@ impl DialerMode {
     }
 }
 
+/// Answering machine detection used when dialing a campaign's leads
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "amd_mode", rename_all = "PascalCase"))]
+pub enum AmdMode {
+    Disabled,
+    #[default]
+    Detect,
+    /// Detect the machine and wait for the voicemail beep
+    DetectBeep,
+    /// Detect the machine and wait for its greeting to finish
+    GreetingEnd,
+}
+
+impl AmdMode {
+    /// Value of Telnyx's `answering_machine_detection` dial parameter
+    pub fn telnyx_value(&self) -> &'static str {
+        match self {
+            AmdMode::Disabled => "disabled",
+            AmdMode::Detect => "detect",
+            AmdMode::DetectBeep => "detect_beep",
+            AmdMode::GreetingEnd => "greeting_end",
+        }
+    }
+
+    /// Whether Telnyx sends `call.machine.greeting.ended` once the machine is ready to record
+    pub fn waits_for_greeting(&self) -> bool {
+        matches!(self, AmdMode::DetectBeep | AmdMode::GreetingEnd)
+    }
+
+    pub fn display_name(&self) -> &str {
+        match self {
+            AmdMode::Disabled => "Disabled",
+            AmdMode::Detect => "Detect",
+            AmdMode::DetectBeep => "Detect + wait for beep",
+            AmdMode::GreetingEnd => "Detect + wait for greeting end",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateCampaignRequest {
     pub name: String,
@@ -99,6 +270,153 @@ pub struct CreateCampaignRequest {
     pub max_attempts: Option<i32>,
     #[serde(rename = "retryDelayMinutes")]
     pub retry_delay_minutes: Option<i32>,
+    #[serde(rename = "holdMusicUrl", default)]
+    pub hold_music_url: Option<String>,
+    #[serde(rename = "amdMode", default)]
+    pub amd_mode: AmdMode,
+    #[serde(rename = "leaveVoicemail", default)]
+    pub leave_voicemail: bool,
+    #[serde(rename = "voicemailMessage", default)]
+    pub voicemail_message: Option<String>,
+    #[serde(rename = "callerIdPool", default)]
+    pub caller_id_pool: Vec<String>,
+    #[serde(rename = "voicemailAudioUrl", default)]
+    pub voicemail_audio_url: Option<String>,
+    #[serde(rename = "greetingTemplate", default)]
+    pub greeting_template: Option<String>,
+    #[serde(rename = "redactTranscripts", default = "redact_transcripts_default")]
+    pub redact_transcripts: bool,
+    /// Skill an agent needs to take the campaign's inbound calls
+    #[serde(rename = "requiredSkill", default)]
+    pub required_skill: Option<String>,
+    /// Longest a call may last before it is hung up; None uses `MAX_CALL_SECONDS`
+    #[serde(rename = "maxCallSeconds", default)]
+    pub max_call_seconds: Option<i32>,
+    #[serde(rename = "recordingChannels", default)]
+    pub recording_channels: RecordingChannels,
+    #[serde(rename = "recordingFormat", default)]
+    pub recording_format: RecordingFormat,
+}
+
+/// Redaction is on unless a campaign opts out
+fn redact_transcripts_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleCampaignRequest {
+    #[serde(rename = "scheduledStartAt")]
+    pub scheduled_start_at: Option<DateTime<Utc>>,
+    #[serde(rename = "scheduledEndAt")]
+    pub scheduled_end_at: Option<DateTime<Utc>>,
+}
+
+impl ScheduleCampaignRequest {
+    /// The end, when both are set, must come after the start
+    pub fn is_valid(&self) -> bool {
+        match (self.scheduled_start_at, self.scheduled_end_at) {
+            (Some(start), Some(end)) => end > start,
+            _ => true,
+        }
+    }
+}
+
+/// How many of a campaign's leads have been worked
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct CampaignProgress {
+    pub total: i64,
+    pub contacted: i64,
+    pub remaining: i64,
+}
+
+impl CampaignProgress {
+    pub fn from_counts(total: i64, contacted: i64) -> Self {
+        let contacted = contacted.clamp(0, total.max(0));
+        Self {
+            total,
+            contacted,
+            remaining: total - contacted,
+        }
+    }
+
+    /// Share of leads contacted, 0-100
+    pub fn percent_complete(&self) -> f64 {
+        if self.total <= 0 {
+            return 0.0;
+        }
+        self.contacted as f64 * 100.0 / self.total as f64
+    }
+
+    /// When the remaining leads will have been dialed at `dial_rate_per_hour`,
+    /// or None if nothing is being dialed
+    pub fn estimated_completion(&self, dial_rate_per_hour: f64, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.remaining <= 0 {
+            return Some(now);
+        }
+        if dial_rate_per_hour <= 0.0 || !dial_rate_per_hour.is_finite() {
+            return None;
+        }
+        let seconds = (self.remaining as f64 / dial_rate_per_hour * 3600.0).ceil() as i64;
+        Some(now + chrono::Duration::seconds(seconds))
+    }
+}
+
+/// A campaign's lead counts with its current pace
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CampaignProgressReport {
+    #[serde(flatten)]
+    pub progress: CampaignProgress,
+    /// Calls placed per hour over the recent window
+    #[serde(rename = "dialRatePerHour")]
+    pub dial_rate_per_hour: f64,
+    #[serde(rename = "estimatedCompletionAt")]
+    pub estimated_completion_at: Option<DateTime<Utc>>,
+}
+
+impl CampaignProgressReport {
+    pub fn new(progress: CampaignProgress, dial_rate_per_hour: f64, now: DateTime<Utc>) -> Self {
+        Self {
+            progress,
+            dial_rate_per_hour,
+            estimated_completion_at: progress.estimated_completion(dial_rate_per_hour, now),
+        }
+    }
+}
+
+/// A campaign lead with the outcome of its most recent call
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignLead {
+    #[cfg_attr(not(target_arch = "wasm32"), sqlx(flatten))]
+    #[serde(flatten)]
+    pub lead: Lead,
+    #[serde(rename = "lastCallStatus")]
+    pub last_call_status: Option<CallStatus>,
+    #[serde(rename = "lastDisposition")]
+    pub last_disposition: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignLeadPage {
+    pub leads: Vec<CampaignLead>,
+    pub total: i64,
+    pub page: i64,
+    #[serde(rename = "perPage")]
+    pub per_page: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttachLeadsRequest {
+    #[serde(rename = "leadIds")]
+    pub lead_ids: Vec<i64>,
+}
+
+impl AttachLeadsRequest {
+    /// Requested ids without duplicates, in the order given
+    pub fn unique_ids(&self) -> Vec<i64> {
+        let mut seen = std::collections::HashSet::new();
+        self.lead_ids.iter().copied().filter(|id| seen.insert(*id)).collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -114,3 +432,151 @@ pub struct DialerStatus {
     #[serde(rename = "failedCalls")]
     pub failed_calls: i32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn campaign(status: CampaignStatus) -> Campaign {
+        Campaign {
+            status,
+            total_leads: Some(10),
+            dialed_leads: Some(4),
+            ..crate::models::fixtures::campaign(1)
+        }
+    }
+
+    #[test]
+    fn test_progress_counts() {
+        let progress = CampaignProgress::from_counts(10, 4);
+        assert_eq!(progress, CampaignProgress { total: 10, contacted: 4, remaining: 6 });
+        assert_eq!(progress.percent_complete(), 40.0);
+
+        assert_eq!(CampaignProgress::from_counts(0, 0).percent_complete(), 0.0);
+        // Never more contacted than attached
+        assert_eq!(CampaignProgress::from_counts(3, 5).remaining, 0);
+    }
+
+    #[test]
+    fn test_eta_from_dial_rate() {
+        let now = Utc::now();
+        let progress = CampaignProgress::from_counts(100, 40);
+
+        // 60 leads left at 120 calls an hour is half an hour
+        let report = CampaignProgressReport::new(progress, 120.0, now);
+        assert_eq!(report.estimated_completion_at, Some(now + Duration::minutes(30)));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["remaining"], 60);
+        assert_eq!(json["dialRatePerHour"], 120.0);
+
+        // Not dialing, so no estimate; nothing left is done now
+        assert_eq!(progress.estimated_completion(0.0, now), None);
+        assert_eq!(CampaignProgress::from_counts(5, 5).estimated_completion(0.0, now), Some(now));
+    }
+
+    #[test]
+    fn test_progress_is_only_serialized_when_loaded() {
+        let mut c = campaign(CampaignStatus::Active);
+        assert!(serde_json::to_value(&c).unwrap().get("progress").is_none());
+
+        c.progress = Some(CampaignProgress::from_counts(10, 4));
+        assert_eq!(
+            serde_json::to_value(&c).unwrap()["progress"],
+            serde_json::json!({ "total": 10, "contacted": 4, "remaining": 6 })
+        );
+    }
+
+    #[test]
+    fn test_attach_request_drops_duplicate_ids() {
+        let req = AttachLeadsRequest { lead_ids: vec![5, 3, 5, 9, 3] };
+        assert_eq!(req.unique_ids(), vec![5, 3, 9]);
+    }
+
+    #[test]
+    fn test_past_scheduled_start_activates() {
+        let now = Utc::now();
+        let mut c = campaign(CampaignStatus::Draft);
+        c.scheduled_start_at = Some(now - Duration::minutes(1));
+
+        assert_eq!(c.scheduled_transition(now), Some(CampaignStatus::Active));
+    }
+
+    #[test]
+    fn test_future_scheduled_start_waits() {
+        let now = Utc::now();
+        let mut c = campaign(CampaignStatus::Draft);
+        c.scheduled_start_at = Some(now + Duration::hours(1));
+
+        assert_eq!(c.scheduled_transition(now), None);
+        assert_eq!(campaign(CampaignStatus::Draft).scheduled_transition(now), None);
+    }
+
+    #[test]
+    fn test_scheduled_end_pauses_or_completes() {
+        let now = Utc::now();
+        let mut c = campaign(CampaignStatus::Active);
+        c.scheduled_end_at = Some(now - Duration::minutes(1));
+        assert_eq!(c.scheduled_transition(now), Some(CampaignStatus::Paused));
+
+        c.dialed_leads = Some(10);
+        assert_eq!(c.scheduled_transition(now), Some(CampaignStatus::Completed));
+
+        // A window that has already closed never activates
+        let mut missed = campaign(CampaignStatus::Draft);
+        missed.scheduled_start_at = Some(now - Duration::hours(2));
+        missed.scheduled_end_at = Some(now - Duration::hours(1));
+        assert_eq!(missed.scheduled_transition(now), None);
+    }
+
+    #[test]
+    fn test_campaign_caller_id_overrides_default() {
+        let mut c = campaign(CampaignStatus::Active);
+        assert_eq!(c.caller_id_for("+14155550100", "+18005550000"), "+18005550000");
+
+        c.caller_id = Some("+12125550199".to_string());
+        assert_eq!(c.caller_id_for("+14155550100", "+18005550000"), "+12125550199");
+    }
+
+    #[test]
+    fn test_caller_id_pool_matches_area_code() {
+        let mut c = campaign(CampaignStatus::Active);
+        c.caller_id = Some("+12125550199".to_string());
+        c.caller_id_pool = vec!["+13105550111".to_string(), "+14155550122".to_string()];
+
+        assert_eq!(c.caller_id_for("(415) 555-0100", "+18005550000"), "+14155550122");
+        assert_eq!(c.caller_id_for("+13105550100", "+18005550000"), "+13105550111");
+        // No local match falls back to the campaign caller ID
+        assert_eq!(c.caller_id_for("+16175550100", "+18005550000"), "+12125550199");
+    }
+
+    #[test]
+    fn test_machine_on_voicemail_drop_campaign_plays_audio() {
+        let mut c = campaign(CampaignStatus::Active);
+        assert_eq!(c.voicemail_drop(), None);
+
+        c.leave_voicemail = true;
+        assert_eq!(c.voicemail_drop(), Some(VoicemailDrop::Message(DEFAULT_VOICEMAIL_MESSAGE)));
+        assert_eq!(c.dial_amd_mode(), AmdMode::Detect);
+
+        c.voicemail_audio_url = Some("https://example.com/vm.mp3".to_string());
+        assert_eq!(c.voicemail_drop(), Some(VoicemailDrop::Audio("https://example.com/vm.mp3")));
+        // Playback waits for the beep
+        assert_eq!(c.dial_amd_mode(), AmdMode::DetectBeep);
+        assert!(c.dial_amd_mode().waits_for_greeting());
+    }
+
+    #[test]
+    fn test_recording_settings_follow_campaign() {
+        let mut c = campaign(CampaignStatus::Active);
+        assert_eq!(c.recording_settings(), RecordingSettings::default());
+
+        c.recording_channels = RecordingChannels::Single;
+        c.recording_format = RecordingFormat::Wav;
+        assert_eq!(
+            c.recording_settings(),
+            RecordingSettings { channels: RecordingChannels::Single, format: RecordingFormat::Wav }
+        );
+    }
+}
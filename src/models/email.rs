@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "email_status", rename_all = "PascalCase"))]
+pub enum EmailStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+/// An email in the outbox, without its body
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EmailOutboxEntry {
+    pub id: i64,
+    #[serde(rename = "toEmail")]
+    pub to_email: String,
+    pub subject: String,
+    pub status: EmailStatus,
+    pub attempts: i32,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[serde(rename = "nextAttemptAt")]
+    pub next_attempt_at: DateTime<Utc>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "sentAt")]
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct EmailOutboxQuery {
+    pub status: Option<EmailStatus>,
+}
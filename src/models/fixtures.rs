@@ -0,0 +1,91 @@
+//! Records for tests, with optional fields left empty
+
+use super::*;
+
+/// A bridged outbound call with no lead, agent or campaign
+pub fn call(id: i64) -> Call {
+    Call {
+        id,
+        call_control_id: Some(format!("v3:call-{}", id)),
+        lead_id: None,
+        agent_id: None,
+        campaign_id: None,
+        direction: CallDirection::Outbound,
+        status: CallStatus::Bridged,
+        from_number: None,
+        to_number: None,
+        started_at: None,
+        answered_at: None,
+        ended_at: None,
+        duration_seconds: None,
+        disposition: None,
+        recording_url: None,
+        recording_channels: None,
+        disposition_id: None,
+        wrap_up_notes: None,
+        sentiment: None,
+    }
+}
+
+/// An active progressive campaign with default settings
+pub fn campaign(id: i64) -> Campaign {
+    Campaign {
+        id,
+        name: "Spring".to_string(),
+        description: None,
+        status: CampaignStatus::Active,
+        dialer_mode: DialerMode::Progressive,
+        caller_id: None,
+        start_time: None,
+        end_time: None,
+        max_attempts: None,
+        retry_delay_minutes: None,
+        total_leads: None,
+        dialed_leads: None,
+        connected_leads: None,
+        created_at: None,
+        updated_at: None,
+        hold_music_url: None,
+        amd_mode: AmdMode::default(),
+        leave_voicemail: false,
+        voicemail_message: None,
+        scheduled_start_at: None,
+        scheduled_end_at: None,
+        caller_id_pool: Vec::new(),
+        voicemail_audio_url: None,
+        greeting_template: None,
+        redact_transcripts: true,
+        required_skill: None,
+        max_call_seconds: None,
+        recording_channels: RecordingChannels::default(),
+        recording_format: RecordingFormat::default(),
+        progress: None,
+    }
+}
+
+/// A human agent who has been in `status` for `idle_minutes`
+pub fn agent(id: i64, status: AgentStatus, idle_minutes: i64) -> Agent {
+    Agent {
+        id,
+        name: format!("Agent {}", id),
+        extension: None,
+        user_id: None,
+        agent_type: AgentType::Human,
+        status,
+        sip_username: Some(format!("agent{}", id)),
+        current_call_id: None,
+        last_status_change: Some(chrono::Utc::now() - chrono::Duration::minutes(idle_minutes)),
+        created_at: None,
+    }
+}
+
+/// Claims for user 1 with `role`
+#[cfg(not(target_arch = "wasm32"))]
+pub fn claims(role: &str) -> crate::server::auth::Claims {
+    crate::server::auth::Claims {
+        sub: 1,
+        username: "alice".to_string(),
+        role: role.to_string(),
+        exp: 0,
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+/// Longest a caller is given to press a digit
+pub const MAX_IVR_TIMEOUT_SECONDS: i32 = 30;
+
+/// Most times a menu is repeated before the call is dropped
+pub const MAX_IVR_RETRIES: i32 = 5;
+
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "ivr_action", rename_all = "PascalCase"))]
+pub enum IvrAction {
+    /// Hand the caller to an agent, or the queue if nobody is free
+    Queue,
+    /// Record a message
+    Voicemail,
+    Hangup,
+}
+
+/// What pressing a digit does
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IvrOption {
+    pub digit: String,
+    pub action: IvrAction,
+    /// For `Queue`, the campaign whose agents take the call
+    #[serde(rename = "campaignId")]
+    pub campaign_id: Option<i64>,
+}
+
+/// Menu played to callers on an inbound number
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct IvrMenu {
+    pub id: i64,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+    /// e.g. "Press 1 for sales, or 2 to leave a message."
+    pub prompt: String,
+    pub options: Vec<IvrOption>,
+    #[serde(rename = "maxRetries")]
+    pub max_retries: i32,
+    #[serde(rename = "timeoutSeconds")]
+    pub timeout_seconds: i32,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "updatedAt")]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl IvrMenu {
+    /// The option for a pressed digit
+    pub fn option(&self, digit: &str) -> Option<&IvrOption> {
+        self.options.iter().find(|o| o.digit == digit)
+    }
+}
+
+/// Create or replace the menu on a number
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpsertIvrMenuRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: String,
+    pub prompt: String,
+    pub options: Vec<IvrOption>,
+    #[serde(rename = "maxRetries")]
+    pub max_retries: Option<i32>,
+    #[serde(rename = "timeoutSeconds")]
+    pub timeout_seconds: Option<i32>,
+}
+
+impl UpsertIvrMenuRequest {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.phone_number.trim().is_empty() {
+            return Err("Phone number is required".to_string());
+        }
+        if self.prompt.trim().is_empty() {
+            return Err("Prompt is required".to_string());
+        }
+        if self.options.is_empty() {
+            return Err("A menu needs at least one option".to_string());
+        }
+        for (i, option) in self.options.iter().enumerate() {
+            let mut keys = option.digit.chars();
+            let is_key = matches!((keys.next(), keys.next()), (Some(c), None) if c.is_ascii_digit() || c == '*' || c == '#');
+            if !is_key {
+                return Err(format!("'{}' is not a phone key", option.digit));
+            }
+            if self.options[..i].iter().any(|o| o.digit == option.digit) {
+                return Err(format!("Key {} is used twice", option.digit));
+            }
+        }
+        if self.max_retries.is_some_and(|n| !(0..=MAX_IVR_RETRIES).contains(&n)) {
+            return Err(format!("Retries must be between 0 and {}", MAX_IVR_RETRIES));
+        }
+        if self.timeout_seconds.is_some_and(|n| !(1..=MAX_IVR_TIMEOUT_SECONDS).contains(&n)) {
+            return Err(format!("Timeout must be between 1 and {} seconds", MAX_IVR_TIMEOUT_SECONDS));
+        }
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use super::{is_valid_email, normalize_phone, FieldError, NumberInfo};
+
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Lead {
@@ -26,6 +28,8 @@ pub struct Lead {
     pub created_at: Option<DateTime<Utc>>,
     #[serde(rename = "updatedAt")]
     pub updated_at: Option<DateTime<Utc>>,
+    #[serde(rename = "deletedAt", default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Lead {
@@ -36,6 +40,17 @@ impl Lead {
     }
 }
 
+/// A lead after looking up its number, with what the lookup found
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LeadEnrichment {
+    pub lead: Lead,
+    #[serde(rename = "numberInfo")]
+    pub number_info: NumberInfo,
+    /// Mobile numbers need prior consent before they're autodialed
+    #[serde(rename = "isMobile")]
+    pub is_mobile: bool,
+}
+
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
@@ -50,6 +65,11 @@ pub enum LeadStatus {
 }
 
 impl LeadStatus {
+    /// Leads flagged Do Not Call must not be called or texted
+    pub fn can_contact(&self) -> bool {
+        !matches!(self, LeadStatus::DoNotCall)
+    }
+
     pub fn display_name(&self) -> &str {
         match self {
             LeadStatus::New => "New",
@@ -74,18 +94,69 @@ impl LeadStatus {
 }
 
 #[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LeadNote {
     pub id: i64,
     #[serde(rename = "leadId")]
     pub lead_id: i64,
-    #[serde(rename = "agentId")]
-    pub agent_id: Option<i64>,
+    /// User who wrote the note; None for notes carried over from before authorship was tracked
+    #[serde(rename = "authorId")]
+    pub author_id: Option<i64>,
+    #[serde(rename = "authorName")]
+    pub author_name: Option<String>,
     pub content: String,
     #[serde(rename = "createdAt")]
     pub created_at: Option<DateTime<Utc>>,
 }
 
+/// A lead together with its notes, as shown on the lead detail view
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeadDetail {
+    #[serde(flatten)]
+    pub lead: Lead,
+    #[serde(rename = "noteEntries", default)]
+    pub note_entries: Vec<LeadNote>,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::Type))]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[cfg_attr(not(target_arch = "wasm32"), sqlx(type_name = "lead_event_type", rename_all = "PascalCase"))]
+pub enum LeadEventType {
+    StatusChange,
+    Note,
+    Call,
+}
+
+/// Entry in a lead's activity timeline
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LeadEvent {
+    pub id: i64,
+    #[serde(rename = "leadId")]
+    pub lead_id: i64,
+    #[serde(rename = "eventType")]
+    pub event_type: LeadEventType,
+    pub description: String,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+    #[serde(rename = "callId")]
+    pub call_id: Option<i64>,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl LeadEvent {
+    pub fn status_change_description(from: LeadStatus, to: LeadStatus) -> String {
+        format!("Status changed from {} to {}", from.display_name(), to.display_name())
+    }
+}
+
+/// Order events oldest first; events recorded in the same instant keep insertion order
+pub fn sort_timeline(events: &mut [LeadEvent]) {
+    events.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateLeadRequest {
     #[serde(rename = "firstName")]
@@ -98,6 +169,45 @@ pub struct CreateLeadRequest {
     pub title: Option<String>,
     #[serde(rename = "campaignId")]
     pub campaign_id: Option<i64>,
+    /// Opening note for a new lead, saved to its notes under the creator's
+    /// name. Ignored when updating; add notes to an existing lead instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+impl CreateLeadRequest {
+    /// The opening note without surrounding whitespace, if there is one
+    pub fn opening_note(&self) -> Option<&str> {
+        self.notes.as_deref().map(str::trim).filter(|note| !note.is_empty())
+    }
+
+    /// Check every field, collecting one error per invalid field. Phone
+    /// numbers without a calling code are read as `default_country` numbers.
+    pub fn validate(&self, default_country: &str) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.first_name.trim().is_empty() {
+            errors.push(FieldError::new("firstName", "First name is required"));
+        }
+        if self.last_name.trim().is_empty() {
+            errors.push(FieldError::new("lastName", "Last name is required"));
+        }
+        if self.phone.trim().is_empty() {
+            errors.push(FieldError::new("phone", "Phone number is required"));
+        } else if normalize_phone(&self.phone, default_country).is_none() {
+            errors.push(FieldError::new("phone", "Phone number is not valid"));
+        }
+        if let Some(email) = self.email.as_deref().map(str::trim).filter(|e| !e.is_empty()) {
+            if !is_valid_email(email) {
+                errors.push(FieldError::new("email", "Email address is not valid"));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,7 +215,339 @@ pub struct AddNoteRequest {
     pub content: String,
 }
 
+impl AddNoteRequest {
+    /// The note's text without surrounding whitespace; blank notes are rejected
+    pub fn validated_content(&self) -> Result<&str, Vec<FieldError>> {
+        match self.content.trim() {
+            "" => Err(vec![FieldError::new("content", "Note can't be empty")]),
+            content => Ok(content),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateStatusRequest {
     pub status: LeadStatus,
 }
+
+/// Status changes a lead may make without an admin override.
+///
+/// Moving to Do Not Call is always allowed so an opt-out is never blocked,
+/// and setting a lead's current status again is a no-op.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LeadStatusTransitions {
+    allowed: Vec<(LeadStatus, LeadStatus)>,
+}
+
+impl Default for LeadStatusTransitions {
+    fn default() -> Self {
+        use LeadStatus::*;
+        Self::new(vec![
+            (New, Contacted),
+            (New, Qualified),
+            (New, Lost),
+            (Contacted, Qualified),
+            (Contacted, Converted),
+            (Contacted, Lost),
+            (Qualified, Contacted),
+            (Qualified, Converted),
+            (Qualified, Lost),
+            // A lost lead can be worked again
+            (Lost, Contacted),
+        ])
+    }
+}
+
+impl LeadStatusTransitions {
+    pub fn new(allowed: Vec<(LeadStatus, LeadStatus)>) -> Self {
+        Self { allowed }
+    }
+
+    /// Parse a matrix written as `New>Contacted,Contacted>Qualified,...`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let status = |name: &str| match name.trim() {
+            "New" => Ok(LeadStatus::New),
+            "Contacted" => Ok(LeadStatus::Contacted),
+            "Qualified" => Ok(LeadStatus::Qualified),
+            "Converted" => Ok(LeadStatus::Converted),
+            "Lost" => Ok(LeadStatus::Lost),
+            "DoNotCall" => Ok(LeadStatus::DoNotCall),
+            other => Err(format!("Unknown lead status '{}'", other)),
+        };
+
+        let allowed = spec
+            .split(',')
+            .filter(|pair| !pair.trim().is_empty())
+            .map(|pair| {
+                let (from, to) = pair
+                    .split_once('>')
+                    .ok_or_else(|| format!("Expected FROM>TO, got '{}'", pair.trim()))?;
+                Ok((status(from)?, status(to)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Self::new(allowed))
+    }
+
+    pub fn allows(&self, from: LeadStatus, to: LeadStatus) -> bool {
+        from == to || to == LeadStatus::DoNotCall || self.allowed.contains(&(from, to))
+    }
+
+    /// Ok if `from` may move to `to`, otherwise why not
+    pub fn check(&self, from: LeadStatus, to: LeadStatus) -> Result<(), String> {
+        if self.allows(from, to) {
+            Ok(())
+        } else {
+            Err(format!("A lead can't move from {} to {}", from.display_name(), to.display_name()))
+        }
+    }
+}
+
+/// How bulk assignment spreads leads across agents
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AssignmentStrategy {
+    #[default]
+    RoundRobin,
+    LeastLoaded,
+    Random,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BulkAssignRequest {
+    #[serde(rename = "leadIds")]
+    pub lead_ids: Vec<i64>,
+    #[serde(default)]
+    pub strategy: AssignmentStrategy,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LeadAssignment {
+    #[serde(rename = "leadId")]
+    pub lead_id: i64,
+    #[serde(rename = "agentId")]
+    pub agent_id: i64,
+}
+
+/// Longest accepted tag
+pub const MAX_TAG_LEN: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddTagRequest {
+    pub tag: String,
+}
+
+/// Canonical form of a tag: trimmed, lowercase, single-spaced. None if the tag
+/// is empty, too long or contains a comma (commas separate tags in filters).
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    (!tag.is_empty() && tag.chars().count() <= MAX_TAG_LEN && !tag.contains(',')).then_some(tag)
+}
+
+/// Parse a comma-separated tag filter such as "hot, VIP" into distinct normalized tags
+pub fn parse_tag_filter(filter: &str) -> Vec<String> {
+    let mut tags: Vec<String> = filter.split(',').filter_map(normalize_tag).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// One lead in an export, in the documented column order
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LeadExportRow {
+    pub name: String,
+    pub phone: String,
+    pub email: Option<String>,
+    pub status: LeadStatus,
+    #[serde(rename = "assignedAgent")]
+    pub assigned_agent: Option<String>,
+    pub notes: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_valid_status_transitions() {
+        let transitions = LeadStatusTransitions::default();
+        assert!(transitions.allows(LeadStatus::New, LeadStatus::Contacted));
+        assert!(transitions.allows(LeadStatus::Contacted, LeadStatus::Converted));
+        assert!(transitions.allows(LeadStatus::Lost, LeadStatus::Contacted));
+        assert!(transitions.allows(LeadStatus::Converted, LeadStatus::DoNotCall));
+        assert!(transitions.allows(LeadStatus::Qualified, LeadStatus::Qualified));
+    }
+
+    #[test]
+    fn test_invalid_status_transitions() {
+        let transitions = LeadStatusTransitions::default();
+        assert!(!transitions.allows(LeadStatus::Converted, LeadStatus::New));
+        assert!(!transitions.allows(LeadStatus::Lost, LeadStatus::New));
+        assert!(!transitions.allows(LeadStatus::DoNotCall, LeadStatus::Contacted));
+        assert_eq!(
+            transitions.check(LeadStatus::Converted, LeadStatus::New),
+            Err("A lead can't move from Converted to New".to_string())
+        );
+    }
+
+    #[test]
+    fn test_status_transitions_parse() {
+        let transitions = LeadStatusTransitions::parse("New>Contacted, Converted>New").unwrap();
+        assert!(transitions.allows(LeadStatus::Converted, LeadStatus::New));
+        assert!(!transitions.allows(LeadStatus::New, LeadStatus::Qualified));
+
+        assert!(LeadStatusTransitions::parse("New>Closed").is_err());
+        assert!(LeadStatusTransitions::parse("New-Contacted").is_err());
+    }
+
+    #[test]
+    fn test_note_content_is_trimmed_and_required() {
+        let req = AddNoteRequest { content: "  Wants a demo on Friday \n".to_string() };
+        assert_eq!(req.validated_content(), Ok("Wants a demo on Friday"));
+
+        let blank = AddNoteRequest { content: " \n ".to_string() };
+        assert_eq!(blank.validated_content(), Err(vec![FieldError::new("content", "Note can't be empty")]));
+    }
+
+    #[test]
+    fn test_lead_detail_carries_authored_notes() {
+        let lead: Lead = serde_json::from_value(serde_json::json!({
+            "id": 7, "firstName": "Ada", "lastName": "Lovelace", "phone": "+15551234567",
+            "email": null, "company": null, "status": "NEW", "notes": null, "assignedAgentId": null,
+            "campaignId": null, "callAttempts": 0, "lastCallAt": null, "createdAt": null, "updatedAt": null
+        }))
+        .unwrap();
+        let note = LeadNote {
+            id: 1,
+            lead_id: 7,
+            author_id: Some(3),
+            author_name: Some("grace".to_string()),
+            content: "Wants a demo".to_string(),
+            created_at: Some("2024-06-10T14:00:00Z".parse().unwrap()),
+        };
+
+        let json = serde_json::to_value(LeadDetail { lead, note_entries: vec![note] }).unwrap();
+        assert_eq!(json["id"], 7);
+        assert_eq!(json["noteEntries"][0]["authorId"], 3);
+        assert_eq!(json["noteEntries"][0]["authorName"], "grace");
+        assert_eq!(json["noteEntries"][0]["createdAt"], "2024-06-10T14:00:00Z");
+
+        // Clients that only know about leads still read the detail as a lead
+        let as_lead: Lead = serde_json::from_value(json).unwrap();
+        assert_eq!(as_lead.id, 7);
+    }
+
+    fn event(id: i64, event_type: LeadEventType, description: &str, at: DateTime<Utc>) -> LeadEvent {
+        LeadEvent {
+            id,
+            lead_id: 1,
+            event_type,
+            description: description.to_string(),
+            user_id: None,
+            call_id: None,
+            created_at: Some(at),
+        }
+    }
+
+    fn lead_request() -> CreateLeadRequest {
+        CreateLeadRequest {
+            first_name: "Ada".to_string(),
+            last_name: "Lovelace".to_string(),
+            phone: "(555) 123-4567".to_string(),
+            email: Some("ada@example.com".to_string()),
+            company: None,
+            title: None,
+            campaign_id: None,
+            notes: None,
+        }
+    }
+
+    #[test]
+    fn test_opening_note_is_trimmed_and_optional() {
+        assert_eq!(lead_request().opening_note(), None);
+
+        let req = CreateLeadRequest { notes: Some("  \n ".to_string()), ..lead_request() };
+        assert_eq!(req.opening_note(), None);
+
+        let req = CreateLeadRequest { notes: Some(" Met at the expo\n".to_string()), ..lead_request() };
+        assert_eq!(req.opening_note(), Some("Met at the expo"));
+    }
+
+    #[test]
+    fn test_valid_lead_request_passes() {
+        assert!(lead_request().validate("US").is_ok());
+
+        // Email is optional
+        let req = CreateLeadRequest { email: Some("  ".to_string()), ..lead_request() };
+        assert!(req.validate("US").is_ok());
+    }
+
+    #[test]
+    fn test_empty_name_is_rejected() {
+        let req = CreateLeadRequest { first_name: "  ".to_string(), ..lead_request() };
+        assert_eq!(
+            req.validate("US").unwrap_err(),
+            vec![FieldError::new("firstName", "First name is required")]
+        );
+    }
+
+    #[test]
+    fn test_invalid_email_is_rejected() {
+        let req = CreateLeadRequest { email: Some("ada.example.com".to_string()), ..lead_request() };
+        assert_eq!(
+            req.validate("US").unwrap_err(),
+            vec![FieldError::new("email", "Email address is not valid")]
+        );
+    }
+
+    #[test]
+    fn test_unparseable_phone_is_rejected() {
+        let req = CreateLeadRequest { phone: "call me maybe".to_string(), ..lead_request() };
+        assert_eq!(
+            req.validate("US").unwrap_err(),
+            vec![FieldError::new("phone", "Phone number is not valid")]
+        );
+    }
+
+    #[test]
+    fn test_phone_is_checked_against_configured_country() {
+        // A UK mobile number written nationally
+        let req = CreateLeadRequest { phone: "07911 123456".to_string(), ..lead_request() };
+        assert!(req.validate("GB").is_ok());
+        assert!(req.validate("US").is_err());
+    }
+
+    #[test]
+    fn test_tags_are_normalized_when_added() {
+        assert_eq!(normalize_tag("  VIP "), Some("vip".to_string()));
+        assert_eq!(normalize_tag("Spanish   speaker"), Some("spanish speaker".to_string()));
+        assert_eq!(normalize_tag("   "), None);
+        assert_eq!(normalize_tag("hot,cold"), None);
+        assert_eq!(normalize_tag(&"x".repeat(MAX_TAG_LEN + 1)), None);
+    }
+
+    #[test]
+    fn test_tag_filter_requires_distinct_tags() {
+        // Duplicates are dropped so a lead needs each tag once to match all of them
+        assert_eq!(parse_tag_filter("hot, VIP,,hot"), vec!["hot".to_string(), "vip".to_string()]);
+        assert!(parse_tag_filter(" , ").is_empty());
+    }
+
+    #[test]
+    fn test_timeline_orders_status_change_and_note() {
+        let now = Utc::now();
+        let status = LeadEvent::status_change_description(LeadStatus::New, LeadStatus::Contacted);
+        let mut events = vec![
+            event(3, LeadEventType::Note, "Wants a follow-up next week", now),
+            event(1, LeadEventType::Call, "Outbound call placed", now - Duration::minutes(10)),
+            event(2, LeadEventType::StatusChange, &status, now),
+        ];
+
+        sort_timeline(&mut events);
+
+        let types: Vec<LeadEventType> = events.iter().map(|e| e.event_type).collect();
+        assert_eq!(types, vec![LeadEventType::Call, LeadEventType::StatusChange, LeadEventType::Note]);
+        assert_eq!(events[1].description, "Status changed from New to Contacted");
+    }
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+
+use super::CallDirection;
+
+/// An SMS sent to or received from a lead
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SmsMessage {
+    pub id: i64,
+    #[serde(rename = "leadId")]
+    pub lead_id: Option<i64>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<i64>,
+    pub direction: CallDirection,
+    #[serde(rename = "fromNumber")]
+    pub from_number: String,
+    #[serde(rename = "toNumber")]
+    pub to_number: String,
+    pub body: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendSmsRequest {
+    #[serde(rename = "leadId")]
+    pub lead_id: Option<i64>,
+    /// Destination number; defaults to the lead's phone when omitted
+    pub to: Option<String>,
+    pub text: String,
+}
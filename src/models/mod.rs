@@ -4,6 +4,15 @@ pub mod agent;
 pub mod campaign;
 pub mod auth;
 pub mod ai;
+pub mod stats;
+pub mod message;
+pub mod phone;
+pub mod validation;
+pub mod search;
+pub mod email;
+pub mod ivr;
+#[cfg(test)]
+pub mod fixtures;
 
 pub use lead::*;
 pub use call::*;
@@ -11,3 +20,10 @@ pub use agent::*;
 pub use campaign::*;
 pub use auth::*;
 pub use ai::*;
+pub use stats::*;
+pub use message::*;
+pub use phone::*;
+pub use validation::*;
+pub use search::*;
+pub use email::*;
+pub use ivr::*;
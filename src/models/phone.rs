@@ -0,0 +1,224 @@
+//! Phone number normalization shared by the dialer UI and the server
+
+use std::str::FromStr;
+
+use phonenumber::country;
+use serde::{Deserialize, Serialize};
+
+/// Country (ISO 3166 code) assumed when a number is entered without a calling code
+pub const DEFAULT_COUNTRY_CODE: &str = "US";
+
+/// Phone settings the client needs, from `GET /api/config/phone`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PhoneConfig {
+    /// Country numbers without a calling code are dialed in, as `DEFAULT_COUNTRY_CODE` on the server
+    #[serde(rename = "defaultCountry")]
+    pub default_country: String,
+}
+
+impl Default for PhoneConfig {
+    fn default() -> Self {
+        Self { default_country: DEFAULT_COUNTRY_CODE.to_string() }
+    }
+}
+
+/// E.164 allows at most 15 digits after the '+'
+const MAX_E164_DIGITS: usize = 15;
+
+/// Shortest number we accept, country code included
+const MIN_E164_DIGITS: usize = 8;
+
+/// Whether `code` names a country the number parser knows, e.g. "GB"
+pub fn is_known_country(code: &str) -> bool {
+    country::Id::from_str(&code.trim().to_uppercase()).is_ok()
+}
+
+/// Normalize user input to E.164 (`+` followed by digits)
+///
+/// Spaces, dashes, dots and parentheses are ignored. Numbers starting with `+`
+/// or the `00` international prefix are taken as already including a country
+/// code; anything else is parsed as a national number of `default_country`
+/// (an ISO 3166 code such as "US" or "GB"), including its trunk prefix rules.
+/// Returns None for input that can't be a phone number.
+pub fn normalize_phone(input: &str, default_country: &str) -> Option<String> {
+    let input = input.trim();
+    let (international, rest) = match input.strip_prefix('+') {
+        Some(rest) => (true, rest),
+        None => (false, input),
+    };
+
+    // The parser would read letters as a vanity number, so reject them first
+    let mut digits = String::with_capacity(rest.len());
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            ' ' | '-' | '.' | '(' | ')' => {}
+            _ => return None,
+        }
+    }
+    if digits.is_empty() {
+        return None;
+    }
+
+    let number = if international {
+        phonenumber::parse(None, format!("+{}", digits))
+    } else if let Some(stripped) = digits.strip_prefix("00") {
+        phonenumber::parse(None, format!("+{}", stripped))
+    } else {
+        let country = country::Id::from_str(&default_country.trim().to_uppercase()).ok()?;
+        phonenumber::parse(Some(country), &digits)
+    }
+    .ok()?;
+
+    let e164 = number.format().mode(phonenumber::Mode::E164).to_string();
+    let e164_digits = e164.strip_prefix('+')?;
+    if !(MIN_E164_DIGITS..=MAX_E164_DIGITS).contains(&e164_digits.len()) {
+        return None;
+    }
+    // North American Numbering Plan numbers are always 10 digits
+    if number.code().value() == 1 && e164_digits.len() != 11 {
+        return None;
+    }
+    Some(e164)
+}
+
+/// Carrier and caller name details for a phone number
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct NumberInfo {
+    pub carrier: Option<String>,
+    /// e.g. "mobile", "landline" or "voip"
+    #[serde(rename = "lineType")]
+    pub line_type: Option<String>,
+    /// Caller ID name (CNAM)
+    #[serde(rename = "callerName")]
+    pub caller_name: Option<String>,
+}
+
+impl NumberInfo {
+    /// Mobile numbers need prior consent for autodialed calls
+    pub fn is_mobile(&self) -> bool {
+        matches!(
+            self.line_type.as_deref().map(str::to_lowercase).as_deref(),
+            Some("mobile" | "wireless")
+        )
+    }
+
+    /// First and last name from the caller name, which carriers usually
+    /// give as "LAST,FIRST" or "FIRST LAST" in capitals
+    pub fn caller_names(&self) -> Option<(String, Option<String>)> {
+        let name = self.caller_name.as_deref()?.trim();
+        let (first, last) = match name.split_once(',') {
+            Some((last, first)) => (first.trim(), Some(last.trim())),
+            None => match name.split_once(char::is_whitespace) {
+                Some((first, last)) => (first, Some(last.trim())),
+                None => (name, None),
+            },
+        };
+        if first.is_empty() {
+            return None;
+        }
+        let last = last.filter(|l| !l.is_empty()).map(title_case);
+        Some((title_case(first), last))
+    }
+}
+
+fn title_case(name: &str) -> String {
+    name.split_whitespace()
+        .map(|word| {
+            let lower = word.to_lowercase();
+            let mut chars = lower.chars();
+            match chars.next() {
+                Some(c) => c.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Area code of a North American (+1) number, in any format `normalize_phone` accepts
+pub fn nanp_area_code(number: &str) -> Option<String> {
+    let e164 = normalize_phone(number, "US")?;
+    let national = e164.strip_prefix("+1")?;
+    (national.len() == 10).then(|| national[..3].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ten_digit_us_number() {
+        assert_eq!(normalize_phone("5551234567", "US").as_deref(), Some("+15551234567"));
+        assert_eq!(normalize_phone("(555) 123-4567", "US").as_deref(), Some("+15551234567"));
+        assert_eq!(normalize_phone("1-555-123-4567", "US").as_deref(), Some("+15551234567"));
+        assert_eq!(normalize_phone("+1 212 555 0100", "US").as_deref(), Some("+12125550100"));
+    }
+
+    #[test]
+    fn test_plus_prefixed_number_is_kept() {
+        assert_eq!(normalize_phone("+15551234567", "US").as_deref(), Some("+15551234567"));
+        assert_eq!(normalize_phone("+44 20 7946 0958", "US").as_deref(), Some("+442079460958"));
+        assert_eq!(normalize_phone("0044 20 7946 0958", "US").as_deref(), Some("+442079460958"));
+    }
+
+    #[test]
+    fn test_national_number_for_other_country() {
+        assert_eq!(normalize_phone("020 7946 0958", "GB").as_deref(), Some("+442079460958"));
+    }
+
+    #[test]
+    fn test_uk_numbers() {
+        assert_eq!(normalize_phone("07911 123456", "GB").as_deref(), Some("+447911123456"));
+        assert_eq!(normalize_phone("+44 7911 123456", "GB").as_deref(), Some("+447911123456"));
+        // A UK number dialed from a US deployment needs its calling code
+        assert_eq!(normalize_phone("+44 7911 123456", "US").as_deref(), Some("+447911123456"));
+    }
+
+    #[test]
+    fn test_german_numbers() {
+        assert_eq!(normalize_phone("030 12345678", "DE").as_deref(), Some("+493012345678"));
+        assert_eq!(normalize_phone("+49 30 12345678", "DE").as_deref(), Some("+493012345678"));
+        assert_eq!(normalize_phone("0151 23456789", "de").as_deref(), Some("+4915123456789"));
+        assert_eq!(normalize_phone("+49 151 23456789", "US").as_deref(), Some("+4915123456789"));
+    }
+
+    #[test]
+    fn test_unknown_default_country() {
+        assert!(is_known_country("gb"));
+        assert!(!is_known_country("XX"));
+        assert_eq!(normalize_phone("020 7946 0958", "XX"), None);
+        // Numbers with a calling code don't need the default
+        assert_eq!(normalize_phone("+442079460958", "XX").as_deref(), Some("+442079460958"));
+    }
+
+    #[test]
+    fn test_garbage_is_rejected() {
+        for input in ["", "abc", "555-CALL-NOW", "12345", "+", "+0123456789", "+1234567890123456", "555123456"] {
+            assert_eq!(normalize_phone(input, "US"), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn test_number_info_caller_names_and_line_type() {
+        let info = NumberInfo {
+            carrier: Some("T-Mobile USA".into()),
+            line_type: Some("mobile".into()),
+            caller_name: Some("SMITH,JOHN".into()),
+        };
+        assert!(info.is_mobile());
+        assert_eq!(info.caller_names(), Some(("John".to_string(), Some("Smith".to_string()))));
+
+        let info = NumberInfo { caller_name: Some("MARY ANN JONES".into()), ..Default::default() };
+        assert!(!info.is_mobile());
+        assert_eq!(info.caller_names(), Some(("Mary".to_string(), Some("Ann Jones".to_string()))));
+        assert_eq!(NumberInfo::default().caller_names(), None);
+    }
+
+    #[test]
+    fn test_nanp_area_code() {
+        assert_eq!(nanp_area_code("+1 (415) 555-0100").as_deref(), Some("415"));
+        assert_eq!(nanp_area_code("2125550100").as_deref(), Some("212"));
+        assert_eq!(nanp_area_code("+442079460958"), None);
+    }
+}
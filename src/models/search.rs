@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Agent, Campaign, Lead};
+
+/// Shorter queries return nothing rather than most of the database
+pub const MIN_SEARCH_LENGTH: usize = 2;
+
+/// Most results returned of each kind
+pub const MAX_SEARCH_RESULTS: i64 = 20;
+
+/// Fewest digits for a query to also be matched against phone numbers
+const MIN_PHONE_DIGITS: usize = 3;
+
+/// Results of the global search box; agents and campaigns are only searched for supervisors
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchResults {
+    pub leads: Vec<Lead>,
+    pub agents: Vec<Agent>,
+    pub campaigns: Vec<Campaign>,
+}
+
+/// A search box query, prepared for matching
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchTerm {
+    /// Lowercased query, matched anywhere in names, emails and companies
+    pub text: String,
+    /// Digits of the query when it looks like a phone number, matched
+    /// anywhere in the digits of stored numbers
+    pub phone_digits: Option<String>,
+}
+
+impl SearchTerm {
+    /// None when the query is too short to search
+    pub fn parse(query: &str) -> Option<Self> {
+        let text = query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        if text.chars().count() < MIN_SEARCH_LENGTH {
+            return None;
+        }
+
+        // "(555) 012-34" and "+1 555 01" should both find +1555012...
+        let looks_like_phone = text.chars().all(|c| c.is_ascii_digit() || " +-.()".contains(c));
+        let digits: String = text.chars().filter(char::is_ascii_digit).collect();
+        let phone_digits = (looks_like_phone && digits.len() >= MIN_PHONE_DIGITS).then_some(digits);
+
+        Some(Self { text, phone_digits })
+    }
+
+    /// ILIKE pattern for the text, with LIKE wildcards in the query escaped
+    pub fn like_pattern(&self) -> String {
+        format!("%{}%", escape_like(&self.text))
+    }
+
+    /// LIKE pattern for the digits of a phone number, if the query is one
+    pub fn phone_pattern(&self) -> Option<String> {
+        self.phone_digits.as_ref().map(|digits| format!("%{}%", digits))
+    }
+
+    /// How closely a lead matches, lower first: a name starting with the
+    /// query, then a phone number containing it, then any name, email or
+    /// company containing it. None if it doesn't match at all.
+    pub fn lead_rank(&self, lead: &Lead) -> Option<u8> {
+        let names = [lead.first_name.as_deref(), lead.last_name.as_deref(), Some(lead.full_name().as_str())]
+            .into_iter()
+            .flatten()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+
+        if names.iter().any(|name| name.starts_with(&self.text)) {
+            return Some(0);
+        }
+
+        if let Some(digits) = &self.phone_digits {
+            let phone: String = lead.phone.chars().filter(char::is_ascii_digit).collect();
+            if phone.contains(digits.as_str()) {
+                return Some(1);
+            }
+        }
+
+        let contains = |field: Option<&str>| field.is_some_and(|f| f.to_lowercase().contains(&self.text));
+        if names.iter().any(|name| name.contains(&self.text))
+            || contains(lead.email.as_deref())
+            || contains(lead.company.as_deref())
+        {
+            return Some(2);
+        }
+
+        None
+    }
+
+    /// Order leads by `lead_rank`, keeping the database order within a rank
+    pub fn rank_leads(&self, mut leads: Vec<Lead>) -> Vec<Lead> {
+        leads.sort_by_key(|lead| self.lead_rank(lead).unwrap_or(u8::MAX));
+        leads
+    }
+}
+
+/// Escape `\`, `%` and `_` so they match literally in a LIKE pattern
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LeadStatus;
+
+    fn lead() -> Lead {
+        Lead {
+            id: 3,
+            first_name: Some("Grace".to_string()),
+            last_name: Some("Hopper".to_string()),
+            phone: "+15550123456".to_string(),
+            email: Some("grace@navy.example".to_string()),
+            company: Some("Remington Rand".to_string()),
+            status: LeadStatus::New,
+            notes: None,
+            assigned_agent_id: None,
+            campaign_id: None,
+            call_attempts: 0,
+            last_call_at: None,
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_partial_phone_matches_lead() {
+        let term = SearchTerm::parse("(555) 012-3").unwrap();
+        assert_eq!(term.phone_digits.as_deref(), Some("5550123"));
+        assert_eq!(term.phone_pattern().as_deref(), Some("%5550123%"));
+        assert_eq!(term.lead_rank(&lead()), Some(1));
+
+        assert_eq!(SearchTerm::parse("+1 555 01").unwrap().lead_rank(&lead()), Some(1));
+        assert_eq!(SearchTerm::parse("555 999").unwrap().lead_rank(&lead()), None);
+    }
+
+    #[test]
+    fn test_company_substring_matches_lead() {
+        let term = SearchTerm::parse("  ington  ").unwrap();
+        assert_eq!(term.text, "ington");
+        assert_eq!(term.phone_digits, None);
+        assert_eq!(term.like_pattern(), "%ington%");
+        assert_eq!(term.lead_rank(&lead()), Some(2));
+
+        // Name prefixes rank above other matches
+        assert_eq!(SearchTerm::parse("Grace H").unwrap().lead_rank(&lead()), Some(0));
+        assert_eq!(SearchTerm::parse("Lovelace").unwrap().lead_rank(&lead()), None);
+
+        let company_match = Lead { id: 4, first_name: Some("Ada".to_string()), company: Some("Grace & Co".to_string()), ..lead() };
+        let ranked = SearchTerm::parse("grace").unwrap().rank_leads(vec![company_match, lead()]);
+        assert_eq!(ranked.iter().map(|l| l.id).collect::<Vec<_>>(), vec![3, 4]);
+    }
+
+    #[test]
+    fn test_short_queries_and_wildcards() {
+        assert_eq!(SearchTerm::parse(" a "), None);
+        assert_eq!(SearchTerm::parse("50%_off").unwrap().like_pattern(), "%50\\%\\_off%");
+        // Too few digits to be worth matching against phone numbers
+        assert_eq!(SearchTerm::parse("42").unwrap().phone_digits, None);
+    }
+}
@@ -0,0 +1,474 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeZone, Utc};
+
+use super::{AgentStats, AgentStatus, StatusReason};
+
+/// Bucket size for historical statistics
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Hour,
+    Day,
+}
+
+impl Granularity {
+    /// Field name accepted by PostgreSQL's `date_trunc`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Granularity::Hour => "hour",
+            Granularity::Day => "day",
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        match self {
+            Granularity::Hour => Duration::hours(1),
+            Granularity::Day => Duration::days(1),
+        }
+    }
+}
+
+/// Metric plotted by the historical statistics endpoint
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryMetric {
+    /// Number of calls started
+    Calls,
+    /// Number of calls that were answered
+    Answered,
+    /// Total talk time in seconds
+    TalkTime,
+}
+
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsBucket {
+    pub bucket: DateTime<Utc>,
+    pub value: i64,
+}
+
+/// Grouping for the statistics CSV export
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsGroupBy {
+    Agent,
+    Campaign,
+    Day,
+}
+
+impl StatsGroupBy {
+    /// Header of the first CSV column
+    pub fn label(&self) -> &'static str {
+        match self {
+            StatsGroupBy::Agent => "agent",
+            StatsGroupBy::Campaign => "campaign",
+            StatsGroupBy::Day => "day",
+        }
+    }
+}
+
+/// Aggregated call numbers for one agent, campaign or day
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatsSummaryRow {
+    #[serde(rename = "groupKey")]
+    pub group_key: String,
+    #[serde(rename = "totalCalls")]
+    pub total_calls: i64,
+    #[serde(rename = "answeredCalls")]
+    pub answered_calls: i64,
+    #[serde(rename = "avgTalkTime")]
+    pub avg_talk_time: f64,
+    pub conversions: i64,
+}
+
+impl StatsSummaryRow {
+    /// Percentage of answered calls that ended with a successful disposition
+    pub fn conversion_rate(&self) -> f64 {
+        if self.answered_calls > 0 {
+            self.conversions as f64 / self.answered_calls as f64 * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Time window for the agent leaderboard
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaderboardPeriod {
+    Today,
+    Week,
+    Month,
+}
+
+impl LeaderboardPeriod {
+    /// Start of the period containing `now` (UTC midnight, Monday of the week, or 1st of the month)
+    pub fn start(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.date_naive();
+        let first_day = match self {
+            LeaderboardPeriod::Today => today,
+            LeaderboardPeriod::Week => {
+                today - Duration::days(today.weekday().num_days_from_monday() as i64)
+            }
+            LeaderboardPeriod::Month => today.with_day(1).unwrap_or(today),
+        };
+        Utc.from_utc_datetime(&first_day.and_hms_opt(0, 0, 0).unwrap_or_default())
+    }
+}
+
+/// Metric agents are ranked by
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderboardMetric {
+    Calls,
+    Conversions,
+    TalkTime,
+}
+
+/// Per-agent totals used to build the leaderboard
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentPerformance {
+    pub agent_id: i64,
+    pub agent_name: String,
+    pub total_calls: i64,
+    pub answered_calls: i64,
+    pub missed_calls: i64,
+    pub total_talk_time: i64,
+    pub average_handle_time: f64,
+    pub conversions: i64,
+    pub sentiment_avg: Option<f64>,
+}
+
+impl AgentPerformance {
+    pub fn metric_value(&self, metric: LeaderboardMetric) -> i64 {
+        match metric {
+            LeaderboardMetric::Calls => self.total_calls,
+            LeaderboardMetric::Conversions => self.conversions,
+            LeaderboardMetric::TalkTime => self.total_talk_time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    #[serde(rename = "agentName")]
+    pub agent_name: String,
+    pub value: i64,
+    pub stats: AgentStats,
+}
+
+/// Sort agents by the chosen metric (highest first, ties broken by agent id) and assign ranks
+pub fn rank_leaderboard(mut agents: Vec<AgentPerformance>, metric: LeaderboardMetric) -> Vec<LeaderboardEntry> {
+    agents.sort_by(|a, b| {
+        b.metric_value(metric)
+            .cmp(&a.metric_value(metric))
+            .then(a.agent_id.cmp(&b.agent_id))
+    });
+
+    agents
+        .into_iter()
+        .enumerate()
+        .map(|(i, a)| LeaderboardEntry {
+            rank: i as u32 + 1,
+            value: a.metric_value(metric),
+            stats: AgentStats {
+                agent_id: a.agent_id,
+                total_calls: a.total_calls as i32,
+                answered_calls: a.answered_calls as i32,
+                missed_calls: a.missed_calls as i32,
+                total_talk_time: a.total_talk_time as i32,
+                average_handle_time: a.average_handle_time,
+                sentiment_avg: a.sentiment_avg,
+            },
+            agent_name: a.agent_name,
+        })
+        .collect()
+}
+
+/// A recorded agent status change
+#[cfg_attr(not(target_arch = "wasm32"), derive(sqlx::FromRow))]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentStatusChange {
+    #[serde(rename = "fromStatus")]
+    pub from_status: Option<AgentStatus>,
+    #[serde(rename = "toStatus")]
+    pub to_status: AgentStatus,
+    #[serde(rename = "changedAt")]
+    pub changed_at: DateTime<Utc>,
+    /// Reason the agent gave, when they changed their own status
+    #[serde(default)]
+    pub reason: Option<StatusReason>,
+}
+
+/// Time spent in one status
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatusDuration {
+    pub status: AgentStatus,
+    pub seconds: i64,
+}
+
+/// How an agent's time was split between statuses over a period
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentOccupancy {
+    #[serde(rename = "agentId")]
+    pub agent_id: i64,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub durations: Vec<StatusDuration>,
+    /// Share of available time spent handling calls (OnCall + AfterCall), 0-100
+    #[serde(rename = "occupancyPercent")]
+    pub occupancy_percent: f64,
+}
+
+impl AgentOccupancy {
+    pub fn seconds_in(&self, status: AgentStatus) -> i64 {
+        self.durations
+            .iter()
+            .find(|d| d.status == status)
+            .map(|d| d.seconds)
+            .unwrap_or(0)
+    }
+}
+
+/// Split `from..to` into time per status
+///
+/// `initial` is the status the agent was in at `from` (the last change before
+/// it), and `changes` are the changes inside the window in order. Time before
+/// the first known status is not counted. Occupancy is handling time (OnCall +
+/// AfterCall) over handling plus Ready time; breaks and Offline don't count.
+pub fn compute_occupancy(
+    agent_id: i64,
+    initial: Option<AgentStatus>,
+    changes: &[AgentStatusChange],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> AgentOccupancy {
+    const STATUSES: [AgentStatus; 5] = [
+        AgentStatus::Offline,
+        AgentStatus::Ready,
+        AgentStatus::OnCall,
+        AgentStatus::AfterCall,
+        AgentStatus::Break,
+    ];
+    let mut seconds = [0i64; STATUSES.len()];
+    let mut add = |status: Option<AgentStatus>, start: DateTime<Utc>, end: DateTime<Utc>| {
+        if let Some(i) = status.and_then(|s| STATUSES.iter().position(|x| *x == s)) {
+            seconds[i] += (end - start).num_seconds().max(0);
+        }
+    };
+
+    let mut current = initial;
+    let mut since = from;
+    for change in changes.iter().filter(|c| c.changed_at >= from && c.changed_at <= to) {
+        add(current, since, change.changed_at);
+        current = Some(change.to_status);
+        since = change.changed_at;
+    }
+    add(current, since, to);
+
+    let durations: Vec<StatusDuration> = STATUSES
+        .iter()
+        .zip(seconds)
+        .map(|(status, seconds)| StatusDuration { status: *status, seconds })
+        .collect();
+
+    let mut occupancy = AgentOccupancy {
+        agent_id,
+        from,
+        to,
+        durations,
+        occupancy_percent: 0.0,
+    };
+    let handling = occupancy.seconds_in(AgentStatus::OnCall) + occupancy.seconds_in(AgentStatus::AfterCall);
+    let available = handling + occupancy.seconds_in(AgentStatus::Ready);
+    if available > 0 {
+        occupancy.occupancy_percent = handling as f64 / available as f64 * 100.0;
+    }
+    occupancy
+}
+
+/// Fill gaps between `from` and `to` with zero-valued buckets so charts get a continuous series.
+/// `buckets` must already be truncated to the granularity (as returned by `date_trunc`).
+pub fn fill_missing_buckets(
+    buckets: Vec<StatsBucket>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    granularity: Granularity,
+) -> Vec<StatsBucket> {
+    let step = granularity.step();
+    let mut current = match from.duration_trunc(step) {
+        Ok(start) => start,
+        Err(_) => return buckets,
+    };
+
+    let mut filled = Vec::new();
+    let mut existing = buckets.into_iter().peekable();
+
+    while current <= to {
+        let value = match existing.peek() {
+            Some(b) if b.bucket == current => existing.next().map(|b| b.value).unwrap_or(0),
+            _ => 0,
+        };
+        filled.push(StatsBucket { bucket: current, value });
+        current += step;
+    }
+
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_daily_buckets_are_filled_and_summed() {
+        let day1 = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+        let day3 = Utc.with_ymd_and_hms(2026, 3, 3, 0, 0, 0).unwrap();
+
+        let buckets = vec![
+            StatsBucket { bucket: day1, value: 3 },
+            StatsBucket { bucket: day3, value: 2 },
+        ];
+
+        let from = Utc.with_ymd_and_hms(2026, 3, 1, 9, 30, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2026, 3, 3, 18, 0, 0).unwrap();
+        let filled = fill_missing_buckets(buckets, from, to, Granularity::Day);
+
+        let values: Vec<i64> = filled.iter().map(|b| b.value).collect();
+        assert_eq!(values, vec![3, 0, 2]);
+        assert_eq!(filled[0].bucket, day1);
+        assert_eq!(filled.iter().map(|b| b.value).sum::<i64>(), 5);
+    }
+
+    #[test]
+    fn test_hourly_buckets() {
+        let from = Utc.with_ymd_and_hms(2026, 3, 1, 10, 15, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let hour11 = Utc.with_ymd_and_hms(2026, 3, 1, 11, 0, 0).unwrap();
+
+        let filled = fill_missing_buckets(
+            vec![StatsBucket { bucket: hour11, value: 7 }],
+            from,
+            to,
+            Granularity::Hour,
+        );
+
+        let values: Vec<i64> = filled.iter().map(|b| b.value).collect();
+        assert_eq!(values, vec![0, 7, 0]);
+    }
+
+    #[test]
+    fn test_conversion_rate() {
+        let row = StatsSummaryRow {
+            group_key: "Alice".to_string(),
+            total_calls: 10,
+            answered_calls: 4,
+            avg_talk_time: 60.0,
+            conversions: 1,
+        };
+        assert_eq!(row.conversion_rate(), 25.0);
+
+        let empty = StatsSummaryRow { answered_calls: 0, conversions: 0, ..row };
+        assert_eq!(empty.conversion_rate(), 0.0);
+    }
+
+    fn performance(agent_id: i64, calls: i64, conversions: i64, talk_time: i64) -> AgentPerformance {
+        AgentPerformance {
+            agent_id,
+            agent_name: format!("Agent {}", agent_id),
+            total_calls: calls,
+            answered_calls: calls,
+            missed_calls: 0,
+            total_talk_time: talk_time,
+            average_handle_time: 0.0,
+            conversions,
+            sentiment_avg: None,
+        }
+    }
+
+    #[test]
+    fn test_leaderboard_ordering_and_ranks() {
+        let agents = vec![
+            performance(3, 5, 1, 300),
+            performance(1, 12, 2, 900),
+            performance(2, 5, 4, 1200),
+        ];
+
+        let by_calls = rank_leaderboard(agents.clone(), LeaderboardMetric::Calls);
+        let order: Vec<(u32, i64, i64)> = by_calls.iter().map(|e| (e.rank, e.stats.agent_id, e.value)).collect();
+        // Agents 2 and 3 tie on calls; the lower id ranks first
+        assert_eq!(order, vec![(1, 1, 12), (2, 2, 5), (3, 3, 5)]);
+
+        let by_conversions = rank_leaderboard(agents, LeaderboardMetric::Conversions);
+        let ids: Vec<i64> = by_conversions.iter().map(|e| e.stats.agent_id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_leaderboard_period_start() {
+        // Thursday
+        let now = Utc.with_ymd_and_hms(2026, 3, 12, 15, 30, 0).unwrap();
+
+        assert_eq!(LeaderboardPeriod::Today.start(now), Utc.with_ymd_and_hms(2026, 3, 12, 0, 0, 0).unwrap());
+        assert_eq!(LeaderboardPeriod::Week.start(now), Utc.with_ymd_and_hms(2026, 3, 9, 0, 0, 0).unwrap());
+        assert_eq!(LeaderboardPeriod::Month.start(now), Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_metric_query_values() {
+        let metric: HistoryMetric = serde_json::from_str("\"talk_time\"").unwrap();
+        assert_eq!(metric, HistoryMetric::TalkTime);
+        let granularity: Granularity = serde_json::from_str("\"hour\"").unwrap();
+        assert_eq!(granularity, Granularity::Hour);
+    }
+
+    #[test]
+    fn test_occupancy_from_status_transitions() {
+        let from = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let to = from + Duration::hours(1);
+        let change = |minutes: i64, from_status: AgentStatus, to_status: AgentStatus| AgentStatusChange {
+            from_status: Some(from_status),
+            to_status,
+            changed_at: from + Duration::minutes(minutes),
+            reason: None,
+        };
+        let changes = vec![
+            change(10, AgentStatus::Ready, AgentStatus::OnCall),
+            change(30, AgentStatus::OnCall, AgentStatus::AfterCall),
+            change(35, AgentStatus::AfterCall, AgentStatus::Ready),
+            change(50, AgentStatus::Ready, AgentStatus::Break),
+        ];
+
+        let occupancy = compute_occupancy(7, Some(AgentStatus::Ready), &changes, from, to);
+
+        assert_eq!(occupancy.seconds_in(AgentStatus::Ready), 25 * 60);
+        assert_eq!(occupancy.seconds_in(AgentStatus::OnCall), 20 * 60);
+        assert_eq!(occupancy.seconds_in(AgentStatus::AfterCall), 5 * 60);
+        assert_eq!(occupancy.seconds_in(AgentStatus::Break), 10 * 60);
+        assert_eq!(occupancy.seconds_in(AgentStatus::Offline), 0);
+        // 25 minutes handling out of 50 available
+        assert!((occupancy.occupancy_percent - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_occupancy_without_known_status_counts_from_first_change() {
+        let from = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+        let to = from + Duration::minutes(30);
+        let changes = vec![AgentStatusChange {
+            from_status: None,
+            to_status: AgentStatus::OnCall,
+            changed_at: from + Duration::minutes(20),
+            reason: None,
+        }];
+
+        let occupancy = compute_occupancy(7, None, &changes, from, to);
+
+        assert_eq!(occupancy.seconds_in(AgentStatus::OnCall), 10 * 60);
+        assert_eq!(occupancy.durations.iter().map(|d| d.seconds).sum::<i64>(), 10 * 60);
+        assert!((occupancy.occupancy_percent - 100.0).abs() < 1e-9);
+        assert_eq!(compute_occupancy(7, None, &[], from, to).occupancy_percent, 0.0);
+    }
+}
@@ -0,0 +1,48 @@
+//! Field-level validation of request payloads
+
+use serde::{Deserialize, Serialize};
+
+/// A problem with one field of a request
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Loose well-formedness check: one `@`, a non-empty local part and a dotted domain
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.contains('@')
+        && !email.chars().any(char::is_whitespace)
+        && domain.split('.').count() >= 2
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_email_shapes() {
+        assert!(is_valid_email("ada@example.com"));
+        assert!(is_valid_email("ada.lovelace+crm@mail.example.co.uk"));
+        assert!(!is_valid_email("ada"));
+        assert!(!is_valid_email("@example.com"));
+        assert!(!is_valid_email("ada@example"));
+        assert!(!is_valid_email("ada@example..com"));
+        assert!(!is_valid_email("ada@@example.com"));
+        assert!(!is_valid_email("ada lovelace@example.com"));
+    }
+}
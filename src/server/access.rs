@@ -0,0 +1,137 @@
+//! Record-level access checks
+//!
+//! Supervisors and admins can see every record. Agents are limited to the
+//! records assigned to their own agent profile, the same rule
+//! `can_access_call` applies to calls.
+
+use crate::models::{Call, Lead, LeadNote};
+
+use super::error::ApiError;
+use super::{auth, db, AppState};
+
+/// Whether a user with `claims` and agent profile `agent_id` may access `lead`
+pub fn lead_access_allowed(claims: &auth::Claims, agent_id: Option<i64>, lead: &Lead) -> bool {
+    if claims.is_supervisor_or_above() {
+        return true;
+    }
+    matches!((agent_id, lead.assigned_agent_id), (Some(mine), Some(assigned)) if mine == assigned)
+}
+
+/// Whether a user with `claims` and agent profile `agent_id` may act on `call`
+pub fn call_access_allowed(claims: &auth::Claims, agent_id: Option<i64>, call: &Call) -> bool {
+    if claims.is_supervisor_or_above() {
+        return true;
+    }
+    matches!((agent_id, call.agent_id), (Some(mine), Some(handling)) if mine == handling)
+}
+
+/// Notes can be deleted by their author; supervisors and admins can delete any note
+pub fn note_delete_allowed(claims: &auth::Claims, note: &LeadNote) -> bool {
+    claims.is_supervisor_or_above() || note.author_id == Some(claims.sub)
+}
+
+/// Load lead `id` and check the caller may access it: 404 if missing, 403 if not theirs
+pub async fn ensure_lead_access(state: &AppState, claims: &auth::Claims, id: i64) -> Result<Lead, ApiError> {
+    let lead = db::leads::get_by_id(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Lead"))?;
+
+    if claims.is_supervisor_or_above() {
+        return Ok(lead);
+    }
+
+    let agent = db::agents::get_by_user(&state.db, claims.sub).await?;
+    if lead_access_allowed(claims, agent.map(|a| a.id), &lead) {
+        Ok(lead)
+    } else {
+        Err(ApiError::forbidden())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::claims;
+    use crate::models::LeadStatus;
+
+    fn lead_assigned_to(agent_id: Option<i64>) -> Lead {
+        Lead {
+            id: 7,
+            first_name: Some("Ada".to_string()),
+            last_name: Some("Lovelace".to_string()),
+            phone: "+15551234567".to_string(),
+            email: None,
+            company: None,
+            status: LeadStatus::New,
+            notes: None,
+            assigned_agent_id: agent_id,
+            campaign_id: None,
+            call_attempts: 0,
+            last_call_at: None,
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn test_agent_can_access_own_lead() {
+        assert!(lead_access_allowed(&claims("Agent"), Some(3), &lead_assigned_to(Some(3))));
+    }
+
+    #[test]
+    fn test_agent_cannot_access_other_agents_lead() {
+        assert!(!lead_access_allowed(&claims("Agent"), Some(3), &lead_assigned_to(Some(4))));
+        assert!(!lead_access_allowed(&claims("Agent"), Some(3), &lead_assigned_to(None)));
+        // A user without an agent profile owns nothing
+        assert!(!lead_access_allowed(&claims("Agent"), None, &lead_assigned_to(None)));
+    }
+
+    #[test]
+    fn test_supervisor_can_access_any_lead() {
+        for role in ["Supervisor", "Admin"] {
+            assert!(lead_access_allowed(&claims(role), None, &lead_assigned_to(Some(4))));
+            assert!(lead_access_allowed(&claims(role), None, &lead_assigned_to(None)));
+        }
+    }
+
+    fn call_handled_by(agent_id: Option<i64>) -> Call {
+        Call { lead_id: Some(7), agent_id, ..crate::models::fixtures::call(11) }
+    }
+
+    #[test]
+    fn test_only_handling_agent_or_supervisor_controls_call() {
+        assert!(call_access_allowed(&claims("Agent"), Some(3), &call_handled_by(Some(3))));
+        assert!(!call_access_allowed(&claims("Agent"), Some(3), &call_handled_by(Some(4))));
+        assert!(!call_access_allowed(&claims("Agent"), Some(3), &call_handled_by(None)));
+        assert!(!call_access_allowed(&claims("Agent"), None, &call_handled_by(None)));
+
+        for role in ["Supervisor", "Admin"] {
+            assert!(call_access_allowed(&claims(role), None, &call_handled_by(Some(4))));
+        }
+    }
+
+    fn note_by(author_id: Option<i64>) -> LeadNote {
+        LeadNote {
+            id: 5,
+            lead_id: 7,
+            author_id,
+            author_name: None,
+            content: "Call back after lunch".to_string(),
+            created_at: None,
+        }
+    }
+
+    #[test]
+    fn test_only_author_or_supervisor_deletes_note() {
+        // claims() is user 1
+        assert!(note_delete_allowed(&claims("Agent"), &note_by(Some(1))));
+        assert!(!note_delete_allowed(&claims("Agent"), &note_by(Some(2))));
+        assert!(!note_delete_allowed(&claims("Agent"), &note_by(None)));
+
+        for role in ["Supervisor", "Admin"] {
+            assert!(note_delete_allowed(&claims(role), &note_by(Some(2))));
+            assert!(note_delete_allowed(&claims(role), &note_by(None)));
+        }
+    }
+}
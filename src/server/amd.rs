@@ -0,0 +1,153 @@
+//! Answering machine detection timeout
+//!
+//! With AMD on, an answered outbound call waits for Telnyx to decide whether
+//! a person or a machine picked up before greeting anyone. Detection can take
+//! a long time on ambiguous audio, so if no result arrives within
+//! `AMD_TIMEOUT_MS` the call goes ahead as if a person answered, rather than
+//! leaving them in dead air. A result that arrives after that is ignored.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::models::AmdOutcome;
+
+/// How long an answered call waits for a detection result by default
+pub const DEFAULT_AMD_TIMEOUT: Duration = Duration::from_millis(3500);
+
+/// `AMD_TIMEOUT_MS`, falling back to the default
+pub fn timeout_from_env() -> Duration {
+    std::env::var("AMD_TIMEOUT_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(DEFAULT_AMD_TIMEOUT)
+}
+
+/// What to do with a call once AMD has something to say about it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmdAction {
+    /// Treat it as a person: greet them and connect an agent
+    Greet,
+    /// Leave a voicemail or hang up
+    Machine,
+    /// Nothing yet, or nothing any more
+    Wait,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pending {
+    /// Answered, waiting on detection
+    Waiting,
+    /// Detection finished before the answer was handled
+    Detected(AmdOutcome),
+    /// Timed out; the call went ahead as human
+    TimedOut,
+}
+
+/// Calls whose AMD result hasn't been acted on, by call control id
+#[derive(Default)]
+pub struct PendingAmd {
+    calls: RwLock<HashMap<String, Pending>>,
+}
+
+impl PendingAmd {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A call was answered. Acts on a result that arrived first; otherwise
+    /// the call waits for detection or the timeout.
+    pub async fn answered(&self, call_control_id: &str) -> AmdAction {
+        let mut calls = self.calls.write().await;
+        match calls.remove(call_control_id) {
+            // The machine was already dealt with when the result came in
+            Some(Pending::Detected(outcome)) if outcome.is_machine() => AmdAction::Wait,
+            Some(Pending::Detected(_)) => AmdAction::Greet,
+            _ => {
+                calls.insert(call_control_id.to_string(), Pending::Waiting);
+                AmdAction::Wait
+            }
+        }
+    }
+
+    /// A detection result arrived. Returns what to do and whether the outcome
+    /// should be recorded; a late result keeps the recorded timeout.
+    pub async fn detected(&self, call_control_id: &str, outcome: AmdOutcome) -> (AmdAction, bool) {
+        let mut calls = self.calls.write().await;
+        let action = match calls.get(call_control_id) {
+            Some(Pending::TimedOut) => return (AmdAction::Wait, false),
+            Some(Pending::Waiting) => {
+                calls.remove(call_control_id);
+                if outcome.is_machine() { AmdAction::Machine } else { AmdAction::Greet }
+            }
+            _ => {
+                // Not answered yet; the answer handler greets a person
+                calls.insert(call_control_id.to_string(), Pending::Detected(outcome));
+                if outcome.is_machine() { AmdAction::Machine } else { AmdAction::Wait }
+            }
+        };
+        (action, true)
+    }
+
+    /// The timeout passed. Greet if the call was still waiting on detection.
+    pub async fn expire(&self, call_control_id: &str) -> AmdAction {
+        let mut calls = self.calls.write().await;
+        match calls.get_mut(call_control_id) {
+            Some(pending @ Pending::Waiting) => {
+                *pending = Pending::TimedOut;
+                AmdAction::Greet
+            }
+            _ => AmdAction::Wait,
+        }
+    }
+
+    /// Forget a call that has ended
+    pub async fn remove(&self, call_control_id: &str) {
+        self.calls.write().await.remove(call_control_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timeout_proceeds_with_human_greeting() {
+        let pending = PendingAmd::new();
+        assert_eq!(pending.answered("v3:call").await, AmdAction::Wait);
+
+        assert_eq!(pending.expire("v3:call").await, AmdAction::Greet);
+        // Only once
+        assert_eq!(pending.expire("v3:call").await, AmdAction::Wait);
+
+        // A late machine result doesn't hang up on the person we already greeted,
+        // and the timeout stays the recorded outcome
+        assert_eq!(pending.detected("v3:call", AmdOutcome::Machine).await, (AmdAction::Wait, false));
+    }
+
+    #[tokio::test]
+    async fn test_detection_before_timeout_is_recorded_and_acted_on() {
+        let pending = PendingAmd::new();
+
+        pending.answered("v3:human").await;
+        assert_eq!(pending.detected("v3:human", AmdOutcome::Human).await, (AmdAction::Greet, true));
+        assert_eq!(pending.expire("v3:human").await, AmdAction::Wait);
+
+        pending.answered("v3:machine").await;
+        assert_eq!(pending.detected("v3:machine", AmdOutcome::Machine).await, (AmdAction::Machine, true));
+        assert_eq!(pending.expire("v3:machine").await, AmdAction::Wait);
+    }
+
+    #[tokio::test]
+    async fn test_result_before_answer_is_picked_up() {
+        let pending = PendingAmd::new();
+
+        assert_eq!(pending.detected("v3:human", AmdOutcome::NotSure).await, (AmdAction::Wait, true));
+        assert_eq!(pending.answered("v3:human").await, AmdAction::Greet);
+
+        assert_eq!(pending.detected("v3:machine", AmdOutcome::Machine).await, (AmdAction::Machine, true));
+        assert_eq!(pending.answered("v3:machine").await, AmdAction::Wait);
+        assert_eq!(pending.expire("v3:machine").await, AmdAction::Wait);
+    }
+}
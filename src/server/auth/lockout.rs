@@ -0,0 +1,241 @@
+//! Brute-force protection for the login endpoint
+//!
+//! Failed login attempts are tracked per username in memory. Once a username
+//! accumulates too many failures inside the attempt window it is locked for a
+//! fixed period, during which every login attempt is rejected - even one with
+//! the correct password.
+
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use tokio::sync::RwLock;
+
+/// Lockout thresholds
+#[derive(Debug, Clone)]
+pub struct LockoutConfig {
+    /// Number of failures that triggers a lock
+    pub max_attempts: u32,
+    /// Window in which failures are counted
+    pub attempt_window: Duration,
+    /// How long the account stays locked
+    pub lockout_duration: Duration,
+}
+
+impl Default for LockoutConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            attempt_window: Duration::minutes(10),
+            lockout_duration: Duration::minutes(15),
+        }
+    }
+}
+
+impl LockoutConfig {
+    /// Load thresholds from environment variables, falling back to defaults
+    ///
+    /// - LOGIN_MAX_ATTEMPTS (default 5)
+    /// - LOGIN_ATTEMPT_WINDOW_MINUTES (default 10)
+    /// - LOGIN_LOCKOUT_MINUTES (default 15)
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        let max_attempts = std::env::var("LOGIN_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(defaults.max_attempts);
+
+        let attempt_window = std::env::var("LOGIN_ATTEMPT_WINDOW_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .map(Duration::minutes)
+            .unwrap_or(defaults.attempt_window);
+
+        let lockout_duration = std::env::var("LOGIN_LOCKOUT_MINUTES")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .map(Duration::minutes)
+            .unwrap_or(defaults.lockout_duration);
+
+        Self {
+            max_attempts,
+            attempt_window,
+            lockout_duration,
+        }
+    }
+}
+
+/// Failed attempts recorded for a single username
+#[derive(Debug, Clone, Default)]
+struct AttemptRecord {
+    failures: Vec<DateTime<Utc>>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl AttemptRecord {
+    /// Neither locked nor holding a failure that still counts
+    fn is_stale(&self, now: DateTime<Utc>, window_start: DateTime<Utc>) -> bool {
+        self.locked_until.is_none_or(|until| until <= now) && self.failures.iter().all(|at| *at <= window_start)
+    }
+}
+
+/// Tracks failed logins and locks usernames that exceed the threshold
+pub struct LoginLockout {
+    config: LockoutConfig,
+    attempts: RwLock<HashMap<String, AttemptRecord>>,
+}
+
+impl LoginLockout {
+    /// Create a new tracker with the given thresholds
+    pub fn new(config: LockoutConfig) -> Self {
+        Self {
+            config,
+            attempts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Usernames are matched case-insensitively so "Admin" and "admin" share a counter
+    fn key(username: &str) -> String {
+        username.trim().to_lowercase()
+    }
+
+    /// Returns the time the lock expires if the username is currently locked
+    pub async fn locked_until(&self, username: &str) -> Option<DateTime<Utc>> {
+        self.locked_until_at(username, Utc::now()).await
+    }
+
+    pub(crate) async fn locked_until_at(&self, username: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let attempts = self.attempts.read().await;
+        attempts
+            .get(&Self::key(username))
+            .and_then(|record| record.locked_until)
+            .filter(|until| *until > now)
+    }
+
+    /// Record a failed login. Returns the lock expiry if this failure locked the account.
+    pub async fn record_failure(&self, username: &str) -> Option<DateTime<Utc>> {
+        self.record_failure_at(username, Utc::now()).await
+    }
+
+    pub(crate) async fn record_failure_at(&self, username: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let window_start = now - self.config.attempt_window;
+        let mut attempts = self.attempts.write().await;
+
+        // Usernames that failed once and never came back would otherwise stay forever
+        attempts.retain(|_, record| !record.is_stale(now, window_start));
+        let record = attempts.entry(Self::key(username)).or_default();
+
+        // An expired lock starts a fresh count
+        if matches!(record.locked_until, Some(until) if until <= now) {
+            record.locked_until = None;
+            record.failures.clear();
+        }
+
+        record.failures.retain(|at| *at > window_start);
+        record.failures.push(now);
+
+        if record.failures.len() as u32 >= self.config.max_attempts {
+            let until = now + self.config.lockout_duration;
+            record.locked_until = Some(until);
+            record.failures.clear();
+            return Some(until);
+        }
+
+        None
+    }
+
+    /// Clear all recorded failures after a successful login
+    pub async fn reset(&self, username: &str) {
+        self.attempts.write().await.remove(&Self::key(username));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockout() -> LoginLockout {
+        LoginLockout::new(LockoutConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_five_failures_lock_account() {
+        let lockout = lockout();
+        let now = Utc::now();
+
+        for i in 0..4 {
+            let locked = lockout.record_failure_at("alice", now + Duration::seconds(i)).await;
+            assert!(locked.is_none());
+        }
+        assert!(lockout.locked_until_at("alice", now).await.is_none());
+
+        let locked = lockout.record_failure_at("alice", now + Duration::seconds(5)).await;
+        assert!(locked.is_some());
+        assert!(lockout.locked_until_at("alice", now + Duration::seconds(6)).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_locked_account_stays_locked_until_expiry() {
+        let lockout = lockout();
+        let now = Utc::now();
+
+        for i in 0..5 {
+            lockout.record_failure_at("Alice", now + Duration::seconds(i)).await;
+        }
+
+        // A 6th attempt is rejected regardless of the password, so the handler only
+        // needs the lock state. Usernames are case-insensitive.
+        assert!(lockout.locked_until_at("alice", now + Duration::minutes(14)).await.is_some());
+        assert!(lockout.locked_until_at("alice", now + Duration::minutes(16)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_success_resets_counter() {
+        let lockout = lockout();
+        let now = Utc::now();
+
+        for i in 0..4 {
+            lockout.record_failure_at("bob", now + Duration::seconds(i)).await;
+        }
+        lockout.reset("bob").await;
+
+        // Four more failures within the same window must not lock after the reset
+        for i in 0..4 {
+            let locked = lockout.record_failure_at("bob", now + Duration::seconds(10 + i)).await;
+            assert!(locked.is_none());
+        }
+        assert!(lockout.locked_until_at("bob", now + Duration::seconds(20)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failures_outside_window_are_ignored() {
+        let lockout = lockout();
+        let now = Utc::now();
+
+        for i in 0..4 {
+            lockout.record_failure_at("carol", now + Duration::seconds(i)).await;
+        }
+
+        let locked = lockout.record_failure_at("carol", now + Duration::minutes(11)).await;
+        assert!(locked.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_pruned() {
+        let lockout = lockout();
+        let now = Utc::now();
+
+        lockout.record_failure_at("dave", now).await;
+        for i in 0..5 {
+            lockout.record_failure_at("erin", now + Duration::seconds(i)).await;
+        }
+        assert_eq!(lockout.attempts.read().await.len(), 2);
+
+        // Dave's failure is outside the window, but Erin is still locked
+        lockout.record_failure_at("frank", now + Duration::minutes(11)).await;
+        let attempts = lockout.attempts.read().await;
+        assert!(!attempts.contains_key("dave"));
+        assert!(attempts.contains_key("erin"));
+        assert!(attempts.contains_key("frank"));
+    }
+}
@@ -1,8 +1,12 @@
 //! Authentication module with JWT
 
+pub mod lockout;
+pub mod password;
+pub mod sessions;
+
 use axum::{
     extract::{FromRequestParts, State},
-    http::{request::Parts, StatusCode},
+    http::{request::Parts, HeaderMap, StatusCode},
     Json,
     RequestPartsExt,
 };
@@ -10,13 +14,15 @@ use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
-use bcrypt::{hash, verify, DEFAULT_COST};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::models::{UserRole, LoginRequest, LoginResponse, RegisterRequest};
-use crate::server::{AppState, db};
+use crate::models::{
+    User, UserRole, LoginRequest, LoginResponse, RegisterRequest,
+    RefreshTokenRequest, RefreshTokenResponse,
+};
+use crate::server::{AppState, db, telemetry};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Claims {
@@ -46,6 +52,8 @@ pub struct VerifyEmailRequest {
 pub struct VerifyEmailResponse {
     pub message: String,
     pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
     pub user: crate::models::UserInfo,
 }
 
@@ -84,23 +92,160 @@ pub struct RegisterInvitationRequest {
 pub struct RegisterInvitationResponse {
     pub message: String,
     pub token: String,
+    #[serde(rename = "refreshToken")]
+    pub refresh_token: String,
     pub user: crate::models::UserInfo,
 }
 
-/// Hash a password using bcrypt
-pub fn hash_password(password: &str) -> Result<String, bcrypt::BcryptError> {
-    hash(password, DEFAULT_COST)
+/// Lifetime of access tokens
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+
+/// Lifetime of refresh tokens for "remember me" logins
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// Default session lifetime when JWT_EXPIRY_HOURS is unset
+pub const DEFAULT_SESSION_HOURS: i64 = 24;
+
+/// How long a login lasts before the user has to sign in again
+#[derive(Debug, Clone, Copy)]
+pub struct SessionConfig {
+    /// Refresh token lifetime for ordinary logins
+    pub session_lifetime: chrono::Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            session_lifetime: chrono::Duration::hours(DEFAULT_SESSION_HOURS),
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Load from JWT_EXPIRY_HOURS (default 24)
+    pub fn from_env() -> Self {
+        let hours = std::env::var("JWT_EXPIRY_HOURS")
+            .ok()
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .filter(|h| *h > 0)
+            .unwrap_or(DEFAULT_SESSION_HOURS);
+
+        Self {
+            session_lifetime: chrono::Duration::hours(hours),
+        }
+    }
+
+    /// Refresh token lifetime, extended to 30 days for "remember me"
+    pub fn refresh_lifetime(&self, remember_me: bool) -> chrono::Duration {
+        if remember_me {
+            chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS)
+        } else {
+            self.session_lifetime
+        }
+    }
+}
+
+/// Default minimum password length when PASSWORD_MIN_LENGTH is unset
+pub const DEFAULT_PASSWORD_MIN_LENGTH: usize = 8;
+
+/// Passwords that meet the length and character rules but are guessed first
+const COMMON_PASSWORDS: &[&str] = &[
+    "password1", "password12", "password123", "passw0rd", "p@ssw0rd", "abc12345", "abcd1234",
+    "qwerty12", "qwerty123", "qwerty1234", "1q2w3e4r", "1qaz2wsx", "zaq12wsx", "letmein1",
+    "welcome1", "welcome123", "iloveyou1", "monkey123", "dragon123", "trustno1", "sunshine1",
+    "football1", "baseball1", "admin123", "admin1234", "changeme1", "secret123", "test1234",
+];
+
+/// Rules every new password must meet
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub require_letter: bool,
+    pub require_digit: bool,
+    /// Both an uppercase and a lowercase letter
+    pub require_mixed_case: bool,
+    /// A character that is neither a letter nor a digit
+    pub require_symbol: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: DEFAULT_PASSWORD_MIN_LENGTH,
+            require_letter: true,
+            require_digit: true,
+            require_mixed_case: false,
+            require_symbol: false,
+        }
+    }
 }
 
-/// Verify a password against a hash
-pub fn verify_password(password: &str, hash: &str) -> Result<bool, bcrypt::BcryptError> {
-    verify(password, hash)
+impl PasswordPolicy {
+    /// Load from PASSWORD_MIN_LENGTH, PASSWORD_REQUIRE_MIXED_CASE and
+    /// PASSWORD_REQUIRE_SYMBOL; letters and digits are always required
+    pub fn from_env() -> Self {
+        let flag = |name: &str| std::env::var(name).map(|v| v.trim().eq_ignore_ascii_case("true")).unwrap_or(false);
+        Self {
+            min_length: std::env::var("PASSWORD_MIN_LENGTH")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(DEFAULT_PASSWORD_MIN_LENGTH),
+            require_mixed_case: flag("PASSWORD_REQUIRE_MIXED_CASE"),
+            require_symbol: flag("PASSWORD_REQUIRE_SYMBOL"),
+            ..Self::default()
+        }
+    }
+
+    /// Check a new password, giving every rule it breaks
+    pub fn validate_password(&self, password: &str) -> Result<(), String> {
+        let mut reasons = Vec::new();
+
+        if password.chars().count() < self.min_length {
+            reasons.push(format!("Password must be at least {} characters", self.min_length));
+        }
+        if self.require_letter && !password.chars().any(char::is_alphabetic) {
+            reasons.push("Password must contain at least one letter".to_string());
+        }
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            reasons.push("Password must contain at least one number".to_string());
+        }
+        if self.require_mixed_case
+            && !(password.chars().any(char::is_uppercase) && password.chars().any(char::is_lowercase))
+        {
+            reasons.push("Password must contain both uppercase and lowercase letters".to_string());
+        }
+        if self.require_symbol && !password.chars().any(|c| !c.is_alphanumeric()) {
+            reasons.push("Password must contain at least one symbol".to_string());
+        }
+        if COMMON_PASSWORDS.contains(&password.to_lowercase().as_str()) {
+            reasons.push("Password is too common".to_string());
+        }
+
+        if reasons.is_empty() {
+            Ok(())
+        } else {
+            Err(reasons.join(". "))
+        }
+    }
+
+    /// `validate_password` as a 422 response
+    fn check(&self, password: &str) -> Result<(), (StatusCode, Json<AuthError>)> {
+        self.validate_password(password)
+            .map_err(|message| (StatusCode::UNPROCESSABLE_ENTITY, Json(AuthError { message })))
+    }
 }
 
-/// Create a JWT token for a user
-pub fn create_token(user_id: i64, username: &str, role: &str, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
+/// Create a JWT token for a user, valid for `expires_in`
+pub fn create_token(
+    user_id: i64,
+    username: &str,
+    role: &str,
+    secret: &str,
+    expires_in: chrono::Duration,
+) -> Result<String, jsonwebtoken::errors::Error> {
     let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
+        .checked_add_signed(expires_in)
         .expect("valid timestamp")
         .timestamp() as usize;
 
@@ -118,17 +263,116 @@ pub fn create_token(user_id: i64, username: &str, role: &str, secret: &str) -> R
     )
 }
 
+/// Generate a new random refresh token
+///
+/// Returns the raw token (handed to the client) and its hash (stored in the database).
+pub fn generate_refresh_token() -> (String, String) {
+    let token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+    let token_hash = hash_refresh_token(&token);
+    (token, token_hash)
+}
+
+/// Hash a refresh token for storage/lookup (hex-encoded SHA-256)
+pub fn hash_refresh_token(token: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(token.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Tokens handed to a client at login
+struct IssuedTokens {
+    token: String,
+    refresh_token: String,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    refresh_expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Create a short-lived access token for a user
+fn access_token(
+    state: &AppState,
+    user: &User,
+) -> Result<(String, chrono::DateTime<chrono::Utc>), (StatusCode, Json<AuthError>)> {
+    let role_str = format!("{:?}", user.role);
+    let ttl = chrono::Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
+    let expires_at = chrono::Utc::now() + ttl;
+    let token = create_token(user.id, &user.username, &role_str, &state.jwt_secret, ttl)
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthError { message: "Token generation error".to_string() }),
+            )
+        })?;
+
+    Ok((token, expires_at))
+}
+
+/// Issue a short-lived access token and a persisted refresh token for a user
+async fn issue_tokens(
+    state: &AppState,
+    user: &User,
+    remember_me: bool,
+    headers: &HeaderMap,
+) -> Result<IssuedTokens, (StatusCode, Json<AuthError>)> {
+    let (token, expires_at) = access_token(state, user)?;
+
+    let (refresh_token, refresh_hash) = generate_refresh_token();
+    let refresh_expires_at = chrono::Utc::now() + state.session_config.refresh_lifetime(remember_me);
+    let client = sessions::client_info(headers);
+    db::refresh_tokens::create(&state.db, user.id, &refresh_hash, refresh_expires_at, remember_me, &client)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthError { message: "Failed to store refresh token".to_string() }),
+            )
+        })?;
+
+    Ok(IssuedTokens {
+        token,
+        refresh_token,
+        expires_at,
+        refresh_expires_at,
+    })
+}
+
 /// Validate a JWT token and extract claims
 pub fn validate_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    // No leeway: a token is rejected as soon as it expires
+    let mut validation = Validation::default();
+    validation.leeway = 0;
+
     let token_data = decode::<Claims>(
         token,
         &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::default(),
+        &validation,
     )?;
 
     Ok(token_data.claims)
 }
 
+impl Claims {
+    /// Parse the role stored in the token
+    pub fn user_role(&self) -> Option<UserRole> {
+        match self.role.as_str() {
+            "Admin" => Some(UserRole::Admin),
+            "Supervisor" => Some(UserRole::Supervisor),
+            "Agent" => Some(UserRole::Agent),
+            _ => None,
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.user_role() == Some(UserRole::Admin)
+    }
+
+    pub fn is_supervisor_or_above(&self) -> bool {
+        self.user_role().map(|r| r.is_supervisor_or_above()).unwrap_or(false)
+    }
+}
+
 /// JWT Auth extractor - extracts Claims from Authorization header
 impl FromRequestParts<Arc<AppState>> for Claims {
     type Rejection = (StatusCode, Json<AuthError>);
@@ -164,8 +408,15 @@ impl FromRequestParts<Arc<AppState>> for Claims {
 /// Login handler
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(req): Json<LoginRequest>,
 ) -> Result<Json<LoginResponse>, (StatusCode, Json<AuthError>)> {
+    // Reject attempts while the account is locked, even with the correct password
+    if let Some(until) = state.login_lockout.locked_until(&req.username).await {
+        telemetry::record_auth("login", "locked");
+        return Err(account_locked_error(until));
+    }
+
     // Find user by username
     let user = db::users::get_by_username(&state.db, &req.username)
         .await
@@ -174,16 +425,15 @@ pub async fn login(
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(AuthError { message: "Database error".to_string() }),
             )
-        })?
-        .ok_or_else(|| {
-            (
-                StatusCode::UNAUTHORIZED,
-                Json(AuthError { message: "Invalid credentials".to_string() }),
-            )
         })?;
 
+    let user = match user {
+        Some(user) => user,
+        None => return Err(invalid_credentials(&state, &req.username).await),
+    };
+
     // Verify password
-    let valid = verify_password(&req.password, &user.password_hash)
+    let valid = state.password_hasher.verify(&req.password, &user.password_hash)
         .map_err(|_| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -192,36 +442,191 @@ pub async fn login(
         })?;
 
     if !valid {
+        return Err(invalid_credentials(&state, &req.username).await);
+    }
+
+    state.login_lockout.reset(&req.username).await;
+
+    // Check account status and email verification before touching the account
+    ensure_can_login(&user)?;
+
+    // Upgrade hashes made with an older algorithm or cost while we have the plaintext
+    match state.password_hasher.rehash_if_outdated(&req.password, &user.password_hash) {
+        Ok(Some(new_hash)) => {
+            if let Err(e) = db::users::update_password(&state.db, user.id, &new_hash).await {
+                tracing::warn!("Failed to rehash password for user {}: {}", user.id, e);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to rehash password for user {}: {}", user.id, e),
+    }
+
+    // Create access and refresh tokens
+    let tokens = issue_tokens(&state, &user, req.remember_me, &headers).await?;
+    telemetry::record_auth("login", "success");
+
+    Ok(Json(LoginResponse {
+        token: tokens.token,
+        refresh_token: tokens.refresh_token,
+        user: user.to_info(),
+        expires_at: tokens.expires_at,
+        refresh_expires_at: tokens.refresh_expires_at,
+    }))
+}
+
+/// Refresh handler - exchanges a refresh token for a new access token, rotating the refresh token
+pub async fn refresh(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(req): Json<RefreshTokenRequest>,
+) -> Result<Json<RefreshTokenResponse>, (StatusCode, Json<AuthError>)> {
+    let token_hash = hash_refresh_token(&req.refresh_token);
+
+    let stored = db::refresh_tokens::get_by_hash(&state.db, &token_hash)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthError { message: "Database error".to_string() }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(AuthError { message: "Invalid refresh token".to_string() }),
+            )
+        })?;
+
+    if !stored.is_usable(chrono::Utc::now()) {
+        // A revoked token being presented again suggests it was stolen - end every session for the user
+        if stored.revoked_at.is_some() {
+            tracing::warn!("Revoked refresh token reused for user {}, revoking all sessions", stored.user_id);
+            let _ = db::refresh_tokens::revoke_all_for_user(&state.db, stored.user_id).await;
+        }
         return Err((
             StatusCode::UNAUTHORIZED,
-            Json(AuthError { message: "Invalid credentials".to_string() }),
+            Json(AuthError { message: "Refresh token has expired or been revoked".to_string() }),
         ));
     }
 
-    // Check email verification status (admins bypass this check)
-    if !user.email_verified && user.role != UserRole::Admin {
+    let user = db::users::get_by_id(&state.db, stored.user_id)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthError { message: "Database error".to_string() }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(AuthError { message: "Invalid refresh token".to_string() }),
+            )
+        })?;
+
+    if !user.active {
         return Err((
             StatusCode::FORBIDDEN,
-            Json(AuthError { message: "Please verify your email before logging in. Check your inbox for a verification link.".to_string() }),
+            Json(AuthError { message: "This account has been disabled. Contact an administrator.".to_string() }),
         ));
     }
 
-    // Create JWT token
-    let role_str = format!("{:?}", user.role);
-    let token = create_token(user.id, &user.username, &role_str, &state.jwt_secret)
+    // Rotate: the presented token is revoked and replaced by a new one
+    let (refresh_token, refresh_hash) = generate_refresh_token();
+    let refresh_expires_at = chrono::Utc::now() + state.session_config.refresh_lifetime(stored.remember_me);
+    let client = sessions::client_info(&headers);
+    db::refresh_tokens::rotate(&state.db, &stored, &refresh_hash, refresh_expires_at, &client)
+        .await
         .map_err(|_| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError { message: "Token generation error".to_string() }),
+                Json(AuthError { message: "Failed to rotate refresh token".to_string() }),
+            )
+        })?
+        .ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(AuthError { message: "Refresh token has expired or been revoked".to_string() }),
             )
         })?;
 
-    Ok(Json(LoginResponse {
+    let (token, expires_at) = access_token(&state, &user)?;
+
+    telemetry::record_auth("refresh", "success");
+    Ok(Json(RefreshTokenResponse {
         token,
-        user: user.to_info(),
+        refresh_token,
+        expires_at,
+        refresh_expires_at,
     }))
 }
 
+/// Logout handler - revokes the presented refresh token
+pub async fn logout(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<RefreshTokenRequest>,
+) -> Result<StatusCode, (StatusCode, Json<AuthError>)> {
+    let token_hash = hash_refresh_token(&req.refresh_token);
+
+    db::refresh_tokens::revoke(&state.db, &token_hash)
+        .await
+        .map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(AuthError { message: "Database error".to_string() }),
+            )
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Reject disabled accounts and unverified emails (admins bypass the email check)
+fn ensure_can_login(user: &User) -> Result<(), (StatusCode, Json<AuthError>)> {
+    if !user.active {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(AuthError { message: "This account has been disabled. Contact an administrator.".to_string() }),
+        ));
+    }
+
+    if !user.email_verified && user.role != UserRole::Admin {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(AuthError { message: "Please verify your email before logging in. Check your inbox for a verification link.".to_string() }),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Record a failed login and build the error response
+async fn invalid_credentials(state: &AppState, username: &str) -> (StatusCode, Json<AuthError>) {
+    if let Some(until) = state.login_lockout.record_failure(username).await {
+        tracing::warn!("Account '{}' locked after repeated failed logins", username);
+        telemetry::record_auth("login", "locked");
+        return account_locked_error(until);
+    }
+    telemetry::record_auth("login", "invalid_credentials");
+
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(AuthError { message: "Invalid credentials".to_string() }),
+    )
+}
+
+fn account_locked_error(until: chrono::DateTime<chrono::Utc>) -> (StatusCode, Json<AuthError>) {
+    let minutes = (until - chrono::Utc::now()).num_minutes().max(0) + 1;
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        Json(AuthError {
+            message: format!(
+                "Account temporarily locked due to too many failed login attempts. Try again in {} minute(s).",
+                minutes
+            ),
+        }),
+    )
+}
+
 /// Register handler
 pub async fn register(
     State(state): State<Arc<AppState>>,
@@ -243,8 +648,10 @@ pub async fn register(
         ));
     }
 
+    state.password_policy.check(&req.password)?;
+
     // Hash password
-    let password_hash = hash_password(&req.password)
+    let password_hash = state.password_hasher.hash(&req.password)
         .map_err(|_| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -288,6 +695,7 @@ pub async fn register(
             )
         })?;
 
+    telemetry::record_auth("register", "success");
     Ok(Json(RegisterResponse {
         message: "Registration successful. Please check your email to verify your account.".to_string(),
         email: user.email,
@@ -297,6 +705,7 @@ pub async fn register(
 /// Verify email handler
 pub async fn verify_email(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(req): Json<VerifyEmailRequest>,
 ) -> Result<Json<VerifyEmailResponse>, (StatusCode, Json<AuthError>)> {
     // Get verification token from database
@@ -361,23 +770,35 @@ pub async fn verify_email(
             )
         })?;
 
-    // Create JWT token for automatic login
-    let role_str = format!("{:?}", user.role);
-    let token = create_token(user.id, &user.username, &role_str, &state.jwt_secret)
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError { message: "Token generation error".to_string() }),
-            )
-        })?;
+    // Create tokens for automatic login
+    let IssuedTokens { token, refresh_token, .. } = issue_tokens(&state, &user, false, &headers).await?;
 
     Ok(Json(VerifyEmailResponse {
         message: "Email verified successfully".to_string(),
         token,
+        refresh_token,
         user: user.to_info(),
     }))
 }
 
+/// Verification emails allowed per address per hour
+const MAX_VERIFICATION_SENDS_PER_HOUR: i64 = 3;
+
+/// Pick the token for a resend: the recent still-valid one if there is one,
+/// or None to issue a new one. `recent_sends` counts every email sent in the
+/// last hour, so reusing a token doesn't get around the limit.
+fn resend_token(recent_sends: i64, resendable: Option<String>) -> Result<Option<String>, (StatusCode, Json<AuthError>)> {
+    if recent_sends >= MAX_VERIFICATION_SENDS_PER_HOUR {
+        return Err((
+            StatusCode::TOO_MANY_REQUESTS,
+            Json(AuthError {
+                message: "Too many verification emails sent. Please wait an hour before requesting another.".to_string()
+            }),
+        ));
+    }
+    Ok(resendable)
+}
+
 /// Resend verification email handler
 pub async fn resend_verification(
     State(state): State<Arc<AppState>>,
@@ -416,28 +837,42 @@ pub async fn resend_verification(
             )
         })?;
 
-    if recent_token_count >= 3 {
-        return Err((
-            StatusCode::TOO_MANY_REQUESTS,
-            Json(AuthError {
-                message: "Too many verification emails sent. Please wait an hour before requesting another.".to_string()
-            }),
-        ));
-    }
-
-    // Generate new verification token
-    let verification_token = uuid::Uuid::new_v4().to_string();
-
-    // Store verification token in database
-    db::users::create_verification_token(&state.db, user.id, &user.email, &verification_token)
+    let resendable = db::users::get_resendable_verification_token(&state.db, &req.email)
         .await
         .map_err(|_| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError { message: "Failed to create verification token".to_string() }),
+                Json(AuthError { message: "Database error".to_string() }),
             )
         })?;
 
+    // Send the link the user may already have open again, or a new one
+    let verification_token = match resend_token(recent_token_count, resendable)? {
+        Some(token) => {
+            db::users::record_verification_resend(&state.db, &token)
+                .await
+                .map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(AuthError { message: "Database error".to_string() }),
+                    )
+                })?;
+            token
+        }
+        None => {
+            let token = uuid::Uuid::new_v4().to_string();
+            db::users::create_verification_token(&state.db, user.id, &user.email, &token)
+                .await
+                .map_err(|_| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(AuthError { message: "Failed to create verification token".to_string() }),
+                    )
+                })?;
+            token
+        }
+    };
+
     // Send verification email
     state.email
         .send_verification_email(&user.email, Some(&user.username), &verification_token)
@@ -620,6 +1055,7 @@ pub async fn get_invitation_details(
 /// Register with invitation handler - allows users to register using an invitation token
 pub async fn register_invitation(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(req): Json<RegisterInvitationRequest>,
 ) -> Result<Json<RegisterInvitationResponse>, (StatusCode, Json<AuthError>)> {
     // Get invitation by token
@@ -656,8 +1092,10 @@ pub async fn register_invitation(
         ));
     }
 
+    state.password_policy.check(&req.password)?;
+
     // Hash password
-    let password_hash = hash_password(&req.password)
+    let password_hash = state.password_hasher.hash(&req.password)
         .map_err(|_| {
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -695,19 +1133,191 @@ pub async fn register_invitation(
             )
         })?;
 
-    // Create JWT token for automatic login
-    let role_str = format!("{:?}", role);
-    let token = create_token(user.id, &user.username, &role_str, &state.jwt_secret)
-        .map_err(|_| {
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(AuthError { message: "Token generation error".to_string() }),
-            )
-        })?;
+    // Create tokens for automatic login
+    let IssuedTokens { token, refresh_token, .. } = issue_tokens(&state, &user, false, &headers).await?;
 
     Ok(Json(RegisterInvitationResponse {
         message: "Registration successful".to_string(),
         token,
+        refresh_token,
         user: user.to_info(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::claims;
+    use db::refresh_tokens::RefreshToken;
+
+    const SECRET: &str = "test-secret";
+
+    fn user(role: UserRole, active: bool, email_verified: bool) -> User {
+        User {
+            id: 1,
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            role,
+            first_name: None,
+            last_name: None,
+            password_hash: String::new(),
+            email_verified,
+            active,
+        }
+    }
+
+    fn refresh_record(expires_in: chrono::Duration, revoked: bool) -> RefreshToken {
+        let now = chrono::Utc::now();
+        RefreshToken {
+            id: 1,
+            session_id: 1,
+            user_id: 7,
+            expires_at: now + expires_in,
+            revoked_at: if revoked { Some(now) } else { None },
+            created_at: now,
+            remember_me: false,
+            user_agent: None,
+            ip_address: None,
+            session_started_at: now,
+        }
+    }
+
+    #[test]
+    fn test_password_too_short() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(
+            policy.validate_password("ab12"),
+            Err("Password must be at least 8 characters".to_string())
+        );
+        assert!(policy.validate_password("tangerine42").is_ok());
+
+        let policy = PasswordPolicy { min_length: 12, ..Default::default() };
+        assert!(policy.validate_password("tangerine42").is_err());
+    }
+
+    #[test]
+    fn test_password_needs_each_char_class() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(
+            policy.validate_password("allletters"),
+            Err("Password must contain at least one number".to_string())
+        );
+
+        let strict = PasswordPolicy { require_mixed_case: true, require_symbol: true, ..Default::default() };
+        let reasons = strict.validate_password("tangerine42").unwrap_err();
+        assert!(reasons.contains("uppercase and lowercase"), "{}", reasons);
+        assert!(reasons.contains("symbol"), "{}", reasons);
+        assert!(strict.validate_password("Tangerine-42").is_ok());
+    }
+
+    #[test]
+    fn test_blocklisted_password_rejected() {
+        let policy = PasswordPolicy::default();
+        assert_eq!(policy.validate_password("Password123"), Err("Password is too common".to_string()));
+        assert!(policy.validate_password("qwerty123").is_err());
+    }
+
+    #[test]
+    fn test_create_token_uses_expiry() {
+        let token = create_token(1, "alice", "Agent", SECRET, chrono::Duration::minutes(15)).unwrap();
+        let claims = validate_token(&token, SECRET).unwrap();
+
+        let expected = (chrono::Utc::now() + chrono::Duration::minutes(15)).timestamp();
+        assert!((claims.exp as i64 - expected).abs() <= 2);
+        assert_eq!(claims.sub, 1);
+    }
+
+    #[test]
+    fn test_expired_access_token_rejected() {
+        // Past the default 60s validation leeway
+        let token = create_token(1, "alice", "Agent", SECRET, chrono::Duration::minutes(-5)).unwrap();
+        assert!(validate_token(&token, SECRET).is_err());
+    }
+
+    #[test]
+    fn test_token_just_past_expiry_rejected() {
+        let token = create_token(1, "alice", "Agent", SECRET, chrono::Duration::seconds(-1)).unwrap();
+        assert!(validate_token(&token, SECRET).is_err());
+
+        let token = create_token(1, "alice", "Agent", SECRET, chrono::Duration::seconds(30)).unwrap();
+        assert!(validate_token(&token, SECRET).is_ok());
+    }
+
+    #[test]
+    fn test_session_lifetime_short_vs_remember_me() {
+        let config = SessionConfig::default();
+        assert_eq!(config.refresh_lifetime(false), chrono::Duration::hours(24));
+        assert_eq!(config.refresh_lifetime(true), chrono::Duration::days(30));
+
+        let config = SessionConfig { session_lifetime: chrono::Duration::hours(8) };
+        assert_eq!(config.refresh_lifetime(false), chrono::Duration::hours(8));
+        assert_eq!(config.refresh_lifetime(true), chrono::Duration::days(30));
+    }
+
+    #[test]
+    fn test_refresh_token_hash_is_stable() {
+        let (token, token_hash) = generate_refresh_token();
+        assert_ne!(token, token_hash);
+        assert_eq!(token_hash.len(), 64);
+        assert_eq!(hash_refresh_token(&token), token_hash);
+
+        let (other, other_hash) = generate_refresh_token();
+        assert_ne!(token, other);
+        assert_ne!(token_hash, other_hash);
+    }
+
+    #[test]
+    fn test_rotated_refresh_token_is_not_usable() {
+        let now = chrono::Utc::now();
+        let current = refresh_record(chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS), false);
+        assert!(current.is_usable(now));
+
+        // Rotation and logout both set revoked_at on the old token
+        let rotated = refresh_record(chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS), true);
+        assert!(!rotated.is_usable(now));
+    }
+
+    #[test]
+    fn test_expired_refresh_token_is_not_usable() {
+        let expired = refresh_record(chrono::Duration::seconds(-1), false);
+        assert!(!expired.is_usable(chrono::Utc::now()));
+    }
+
+    #[test]
+    fn test_disabled_user_cannot_log_in() {
+        let (status, _) = ensure_can_login(&user(UserRole::Agent, false, true)).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+
+        // Admins don't bypass the disabled check
+        assert!(ensure_can_login(&user(UserRole::Admin, false, true)).is_err());
+        assert!(ensure_can_login(&user(UserRole::Agent, true, true)).is_ok());
+    }
+
+    #[test]
+    fn test_claims_role_checks() {
+        assert!(claims("Admin").is_admin());
+        assert!(claims("Supervisor").is_supervisor_or_above());
+        assert!(!claims("Supervisor").is_admin());
+        assert!(!claims("Agent").is_supervisor_or_above());
+        assert!(!claims("Unknown").is_supervisor_or_above());
+    }
+
+    #[test]
+    fn test_resends_within_window_reuse_token() {
+        // Registration sent the first email; the next two resends send the same link
+        let first = resend_token(1, Some("tok-1".to_string())).unwrap();
+        let second = resend_token(2, Some("tok-1".to_string())).unwrap();
+        assert_eq!(first.as_deref(), Some("tok-1"));
+        assert_eq!(first, second);
+
+        // Nothing reusable, so a new token is issued
+        assert_eq!(resend_token(0, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_resend_over_limit_is_rate_limited() {
+        let (status, _) = resend_token(MAX_VERIFICATION_SENDS_PER_HOUR, Some("tok-1".to_string())).unwrap_err();
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(resend_token(MAX_VERIFICATION_SENDS_PER_HOUR + 2, None).is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Password hashing
+//!
+//! New hashes use the configured algorithm (bcrypt or argon2id). Verification
+//! detects the algorithm from the stored hash, so accounts created before a
+//! switch keep working and are upgraded the next time they log in.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher as _, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Lowest accepted BCRYPT_COST
+pub const MIN_BCRYPT_COST: u32 = 10;
+/// Highest accepted BCRYPT_COST
+pub const MAX_BCRYPT_COST: u32 = 15;
+/// Cost used when BCRYPT_COST is unset or invalid
+pub const DEFAULT_BCRYPT_COST: u32 = 12;
+
+#[derive(Debug, Error)]
+pub enum PasswordError {
+    #[error("bcrypt error: {0}")]
+    Bcrypt(#[from] bcrypt::BcryptError),
+
+    #[error("argon2 error: {0}")]
+    Argon2(String),
+
+    #[error("Unrecognized password hash format")]
+    UnknownFormat,
+}
+
+/// Algorithm used for new password hashes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlgorithm {
+    #[default]
+    Bcrypt,
+    Argon2,
+}
+
+impl HashAlgorithm {
+    /// Detect the algorithm a stored hash was produced with
+    pub fn detect(hash: &str) -> Option<Self> {
+        if hash.starts_with("$argon2") {
+            Some(Self::Argon2)
+        } else if ["$2a$", "$2b$", "$2x$", "$2y$"].iter().any(|p| hash.starts_with(p)) {
+            Some(Self::Bcrypt)
+        } else {
+            None
+        }
+    }
+}
+
+/// Hashes and verifies passwords with the configured algorithm
+#[derive(Debug, Clone)]
+pub struct PasswordHasher {
+    algorithm: HashAlgorithm,
+    bcrypt_cost: u32,
+}
+
+impl Default for PasswordHasher {
+    fn default() -> Self {
+        Self {
+            algorithm: HashAlgorithm::Bcrypt,
+            bcrypt_cost: DEFAULT_BCRYPT_COST,
+        }
+    }
+}
+
+impl PasswordHasher {
+    /// Create a hasher. The bcrypt cost is clamped to the accepted range.
+    pub fn new(algorithm: HashAlgorithm, bcrypt_cost: u32) -> Self {
+        Self {
+            algorithm,
+            bcrypt_cost: bcrypt_cost.clamp(MIN_BCRYPT_COST, MAX_BCRYPT_COST),
+        }
+    }
+
+    /// Load settings from environment variables
+    ///
+    /// - PASSWORD_HASH: bcrypt (default) or argon2
+    /// - BCRYPT_COST: 10-15 (default 12)
+    pub fn from_env() -> Self {
+        let algorithm = match std::env::var("PASSWORD_HASH")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "argon2" | "argon2id" => HashAlgorithm::Argon2,
+            "" | "bcrypt" => HashAlgorithm::Bcrypt,
+            other => {
+                tracing::warn!("Unknown PASSWORD_HASH '{}', using bcrypt", other);
+                HashAlgorithm::Bcrypt
+            }
+        };
+
+        let bcrypt_cost = match std::env::var("BCRYPT_COST") {
+            Ok(value) => match value.trim().parse::<u32>() {
+                Ok(cost) if (MIN_BCRYPT_COST..=MAX_BCRYPT_COST).contains(&cost) => cost,
+                _ => {
+                    tracing::warn!(
+                        "BCRYPT_COST must be between {} and {}, using {}",
+                        MIN_BCRYPT_COST,
+                        MAX_BCRYPT_COST,
+                        DEFAULT_BCRYPT_COST
+                    );
+                    DEFAULT_BCRYPT_COST
+                }
+            },
+            Err(_) => DEFAULT_BCRYPT_COST,
+        };
+
+        Self { algorithm, bcrypt_cost }
+    }
+
+    /// Algorithm used for new hashes
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    fn argon2() -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::default())
+    }
+
+    /// Hash a password with the configured algorithm
+    pub fn hash(&self, password: &str) -> Result<String, PasswordError> {
+        match self.algorithm {
+            HashAlgorithm::Bcrypt => Ok(bcrypt::hash(password, self.bcrypt_cost)?),
+            HashAlgorithm::Argon2 => {
+                let salt = SaltString::encode_b64(&rand::random::<[u8; 16]>())
+                    .map_err(|e| PasswordError::Argon2(e.to_string()))?;
+                Self::argon2()
+                    .hash_password(password.as_bytes(), &salt)
+                    .map(|hash| hash.to_string())
+                    .map_err(|e| PasswordError::Argon2(e.to_string()))
+            }
+        }
+    }
+
+    /// Verify a password against a stored hash of either format
+    pub fn verify(&self, password: &str, hash: &str) -> Result<bool, PasswordError> {
+        match HashAlgorithm::detect(hash).ok_or(PasswordError::UnknownFormat)? {
+            HashAlgorithm::Bcrypt => Ok(bcrypt::verify(password, hash)?),
+            HashAlgorithm::Argon2 => {
+                let parsed = PasswordHash::new(hash).map_err(|e| PasswordError::Argon2(e.to_string()))?;
+                Ok(Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+            }
+        }
+    }
+
+    /// Whether a stored hash was made with an outdated algorithm or cost
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        match (self.algorithm, HashAlgorithm::detect(hash)) {
+            (HashAlgorithm::Bcrypt, Some(HashAlgorithm::Bcrypt)) => bcrypt::HashParts::from_str(hash)
+                .map(|parts| parts.get_cost() != self.bcrypt_cost)
+                .unwrap_or(true),
+            (HashAlgorithm::Argon2, Some(HashAlgorithm::Argon2)) => {
+                let Ok(parsed) = PasswordHash::new(hash) else {
+                    return true;
+                };
+                let current = Params::default();
+                parsed.algorithm != Algorithm::Argon2id.ident()
+                    || Params::try_from(&parsed)
+                        .map(|params| {
+                            params.m_cost() != current.m_cost()
+                                || params.t_cost() != current.t_cost()
+                                || params.p_cost() != current.p_cost()
+                        })
+                        .unwrap_or(true)
+            }
+            _ => true,
+        }
+    }
+
+    /// New hash for a password that just verified against `stored_hash`, if it is outdated
+    pub fn rehash_if_outdated(&self, password: &str, stored_hash: &str) -> Result<Option<String>, PasswordError> {
+        if !self.needs_rehash(stored_hash) {
+            return Ok(None);
+        }
+        self.hash(password).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bcrypt_hasher() -> PasswordHasher {
+        PasswordHasher::new(HashAlgorithm::Bcrypt, MIN_BCRYPT_COST)
+    }
+
+    fn argon2_hasher() -> PasswordHasher {
+        PasswordHasher::new(HashAlgorithm::Argon2, MIN_BCRYPT_COST)
+    }
+
+    #[test]
+    fn test_cost_is_clamped_to_range() {
+        assert_eq!(PasswordHasher::new(HashAlgorithm::Bcrypt, 4).bcrypt_cost, MIN_BCRYPT_COST);
+        assert_eq!(PasswordHasher::new(HashAlgorithm::Bcrypt, 20).bcrypt_cost, MAX_BCRYPT_COST);
+    }
+
+    #[test]
+    fn test_verifies_across_algorithms() {
+        let bcrypt_hash = bcrypt_hasher().hash("hunter22").unwrap();
+        let argon2_hash = argon2_hasher().hash("hunter22").unwrap();
+        assert_eq!(HashAlgorithm::detect(&bcrypt_hash), Some(HashAlgorithm::Bcrypt));
+        assert_eq!(HashAlgorithm::detect(&argon2_hash), Some(HashAlgorithm::Argon2));
+
+        // Either configuration verifies both formats
+        for hasher in [bcrypt_hasher(), argon2_hasher()] {
+            assert!(hasher.verify("hunter22", &bcrypt_hash).unwrap());
+            assert!(hasher.verify("hunter22", &argon2_hash).unwrap());
+            assert!(!hasher.verify("wrong", &bcrypt_hash).unwrap());
+            assert!(!hasher.verify("wrong", &argon2_hash).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_unknown_hash_format_is_an_error() {
+        assert!(matches!(
+            bcrypt_hasher().verify("hunter22", "plaintext"),
+            Err(PasswordError::UnknownFormat)
+        ));
+    }
+
+    #[test]
+    fn test_login_rehashes_outdated_bcrypt_to_argon2() {
+        let stored = bcrypt_hasher().hash("hunter22").unwrap();
+        let hasher = argon2_hasher();
+
+        assert!(hasher.verify("hunter22", &stored).unwrap());
+        let upgraded = hasher.rehash_if_outdated("hunter22", &stored).unwrap().unwrap();
+
+        assert_eq!(HashAlgorithm::detect(&upgraded), Some(HashAlgorithm::Argon2));
+        assert!(hasher.verify("hunter22", &upgraded).unwrap());
+        assert!(hasher.rehash_if_outdated("hunter22", &upgraded).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_login_rehashes_bcrypt_with_old_cost() {
+        let stored = bcrypt::hash("hunter22", 4).unwrap();
+        let hasher = bcrypt_hasher();
+
+        assert!(hasher.needs_rehash(&stored));
+        let upgraded = hasher.rehash_if_outdated("hunter22", &stored).unwrap().unwrap();
+        assert!(upgraded.starts_with("$2b$10$"));
+        assert!(!hasher.needs_rehash(&upgraded));
+    }
+}
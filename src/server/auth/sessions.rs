@@ -0,0 +1,180 @@
+//! Active sessions
+//!
+//! Every login creates a refresh token; rotation replaces it but keeps the
+//! session's id, start time and client details, so a session is the chain's
+//! current, unrevoked token. Revoking the session ends it the next time its
+//! access token expires.
+
+use std::sync::Arc;
+use axum::{extract::State, http::{HeaderMap, StatusCode}, Json};
+use serde::Deserialize;
+
+use crate::models::SessionInfo;
+use crate::server::{db, error::ApiError, rate_limit, AppState};
+use db::refresh_tokens::{ClientInfo, RefreshToken};
+use super::Claims;
+
+/// Longest user agent kept with a session
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Client details from the request headers
+pub fn client_info(headers: &HeaderMap) -> ClientInfo {
+    let user_agent = headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|ua| ua.trim().chars().take(MAX_USER_AGENT_LEN).collect::<String>())
+        .filter(|ua| !ua.is_empty());
+    let ip_address = Some(rate_limit::client_ip(headers, None)).filter(|ip| ip != "unknown");
+
+    ClientInfo { user_agent, ip_address }
+}
+
+impl From<RefreshToken> for SessionInfo {
+    fn from(token: RefreshToken) -> Self {
+        SessionInfo {
+            id: token.session_id,
+            user_agent: token.user_agent,
+            ip_address: token.ip_address,
+            created_at: token.session_started_at,
+            last_used_at: token.created_at,
+            expires_at: token.expires_at,
+            remember_me: token.remember_me,
+        }
+    }
+}
+
+/// Sessions among `tokens` that can still be refreshed
+fn active_sessions(tokens: Vec<RefreshToken>, now: chrono::DateTime<chrono::Utc>) -> Vec<SessionInfo> {
+    tokens
+        .into_iter()
+        .filter(|token| token.is_usable(now))
+        .map(SessionInfo::from)
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SessionsQuery {
+    /// Admins may manage another user's sessions
+    user_id: Option<i64>,
+}
+
+/// Whose sessions the request is about
+fn target_user(claims: &Claims, query: &SessionsQuery) -> Result<i64, ApiError> {
+    match query.user_id {
+        Some(user_id) if user_id != claims.sub && !claims.is_admin() => Err(ApiError::forbidden()),
+        Some(user_id) => Ok(user_id),
+        None => Ok(claims.sub),
+    }
+}
+
+/// `GET /api/auth/sessions`
+pub async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    axum::extract::Query(query): axum::extract::Query<SessionsQuery>,
+) -> Result<Json<Vec<SessionInfo>>, ApiError> {
+    let user_id = target_user(&claims, &query)?;
+    let tokens = db::refresh_tokens::get_active_for_user(&state.db, user_id).await?;
+    Ok(Json(active_sessions(tokens, chrono::Utc::now())))
+}
+
+/// `DELETE /api/auth/sessions/{id}` - users may end their own sessions, admins any
+pub async fn revoke_session(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    let token = db::refresh_tokens::get_active_by_session(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Session"))?;
+
+    if token.user_id != claims.sub && !claims.is_admin() {
+        // Don't reveal that another user's session exists
+        return Err(ApiError::not_found("Session"));
+    }
+
+    db::refresh_tokens::revoke_session(&state.db, id).await?;
+    tracing::info!("User {} revoked session {} of user {}", claims.sub, id, token.user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// `DELETE /api/auth/sessions` - log out everywhere
+pub async fn revoke_all_sessions(
+    State(state): State<Arc<AppState>>,
+    claims: Claims,
+    axum::extract::Query(query): axum::extract::Query<SessionsQuery>,
+) -> Result<StatusCode, ApiError> {
+    let user_id = target_user(&claims, &query)?;
+    let revoked = db::refresh_tokens::revoke_all_for_user(&state.db, user_id).await?;
+    tracing::info!("User {} revoked {} session(s) of user {}", claims.sub, revoked, user_id);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::claims;
+
+    fn token(id: i64) -> RefreshToken {
+        let now = chrono::Utc::now();
+        RefreshToken {
+            id,
+            session_id: id,
+            user_id: 7,
+            expires_at: now + chrono::Duration::days(1),
+            revoked_at: None,
+            created_at: now,
+            remember_me: false,
+            user_agent: Some("Mozilla/5.0".to_string()),
+            ip_address: Some("203.0.113.9".to_string()),
+            session_started_at: now - chrono::Duration::hours(3),
+        }
+    }
+
+    #[test]
+    fn test_sessions_exclude_rotated_and_expired_tokens() {
+        let now = chrono::Utc::now();
+        // Session 1 rotated from token 1 to token 4
+        let rotated = RefreshToken { revoked_at: Some(now), ..token(1) };
+        let current = RefreshToken { session_id: 1, ..token(4) };
+        let expired = RefreshToken { expires_at: now - chrono::Duration::minutes(1), ..token(2) };
+
+        let sessions = active_sessions(vec![current, rotated, expired, token(3)], now);
+        let ids: Vec<i64> = sessions.iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_session_keeps_id_and_start_time_across_rotation() {
+        let rotated = RefreshToken { session_id: 2, ..token(5) };
+        let session = SessionInfo::from(rotated.clone());
+
+        // The id a client revokes doesn't change when the token rotates
+        assert_eq!(session.id, 2);
+        assert_eq!(session.created_at, rotated.session_started_at);
+        assert_eq!(session.last_used_at, rotated.created_at);
+        assert_eq!(session.user_agent.as_deref(), Some("Mozilla/5.0"));
+    }
+
+    #[test]
+    fn test_client_info_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_info(&headers), ClientInfo::default());
+
+        headers.insert("user-agent", "Mozilla/5.0 (X11)".parse().unwrap());
+        headers.insert("x-forwarded-for", "198.51.100.4, 203.0.113.9".parse().unwrap());
+        let info = client_info(&headers);
+        assert_eq!(info.user_agent.as_deref(), Some("Mozilla/5.0 (X11)"));
+        assert_eq!(info.ip_address.as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_only_admins_manage_other_users_sessions() {
+        let own = SessionsQuery { user_id: None };
+        let other = SessionsQuery { user_id: Some(9) };
+
+        assert_eq!(target_user(&Claims { sub: 7, ..claims("Agent") }, &own).unwrap(), 7);
+        assert!(target_user(&Claims { sub: 7, ..claims("Agent") }, &other).is_err());
+        assert_eq!(target_user(&Claims { sub: 7, ..claims("Admin") }, &other).unwrap(), 9);
+    }
+}
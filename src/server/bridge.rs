@@ -0,0 +1,278 @@
+//! Agent leg bridging
+//!
+//! An outbound call placed for a human agent only rings the lead. Once the
+//! lead answers, a second call is dialed to the agent's SIP endpoint and,
+//! when the agent picks up, the two legs are bridged and the call becomes
+//! `Bridged`. The agent leg carries `agent_leg` in its client_state so its
+//! webhooks are routed here instead of being mistaken for the lead's.
+//!
+//! When Telnyx reports that a transfer or bridge failed, the agent is freed
+//! if they are still on that call, and an inbound caller who is still on the line goes back into the queue.
+//! Calls ended by the server, e.g. for running too long, are hung up on
+//! both legs.
+
+use chrono::Utc;
+
+use crate::models::{AmdMode, Call, CallDirection, CallStatus};
+use super::telnyx::{ClientState, TelnyxClient, TelnyxError};
+use super::{db, routing, AppState};
+
+/// Reasons stored on a call whose transfer or bridge failed
+pub const TRANSFER_FAILED: &str = "transfer_failed";
+pub const BRIDGE_FAILED: &str = "bridge_failed";
+
+/// Telnyx events reporting that a call could not be connected to its target
+pub fn is_connect_failure(event_type: &str) -> bool {
+    matches!(event_type, "call.transfer.failed" | "call.bridge.failed")
+}
+
+/// What to do after a failed transfer or bridge
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureRecovery {
+    /// Stored on the call as its failure reason
+    pub reason: &'static str,
+    /// Agent to return to `Ready`, if they are still on this call
+    pub release_agent: Option<i64>,
+    /// Put the caller back in the inbound queue
+    pub requeue: bool,
+}
+
+impl FailureRecovery {
+    pub fn for_call(event_type: &str, call: &Call) -> Self {
+        Self {
+            reason: if event_type == "call.bridge.failed" { BRIDGE_FAILED } else { TRANSFER_FAILED },
+            release_agent: call.agent_id,
+            requeue: call.direction == CallDirection::Inbound
+                && call.ended_at.is_none()
+                && call.call_control_id.is_some(),
+        }
+    }
+}
+
+/// Dial the agent assigned to `call` so they can be bridged to the lead
+///
+/// Returns false when the agent has no SIP endpoint to dial.
+pub async fn dial_agent_leg(state: &AppState, call: &Call) -> Result<bool, TelnyxError> {
+    let Some(agent_id) = call.agent_id else {
+        return Ok(false);
+    };
+    let agent = db::agents::get_by_id(&state.db, agent_id).await.ok().flatten();
+    let Some(uri) = agent.as_ref().and_then(routing::agent_sip_uri) else {
+        tracing::warn!("Agent {} has no SIP endpoint, call {} can't be bridged", agent_id, call.id);
+        return Ok(false);
+    };
+
+    let client_state = ClientState {
+        call_id: call.id,
+        agent_id: call.agent_id,
+        campaign_id: call.campaign_id,
+        agent_leg: true,
+    };
+    let from = call.from_number.as_deref().unwrap_or(&state.caller_id);
+    let response = state
+        .telnyx
+        .dial(&uri, from, Some(&state.webhook_url), AmdMode::Disabled, Some(&client_state))
+        .await?;
+
+    if let Err(e) = db::calls::set_agent_control_id(&state.db, call.id, &response.call_control_id).await {
+        tracing::error!("Failed to store agent leg for call {}: {}", call.id, e);
+    }
+    tracing::info!("Dialing agent {} for call {}", agent_id, call.id);
+    Ok(true)
+}
+
+/// Bridge the lead's leg to the agent leg that just answered
+///
+/// Returns false without touching Telnyx when the lead has already hung up.
+pub async fn bridge_on_agent_answer(
+    telnyx: &TelnyxClient,
+    call: &Call,
+    agent_call_control_id: &str,
+) -> Result<bool, TelnyxError> {
+    let lead_call_control_id = match (&call.call_control_id, call.ended_at) {
+        (Some(id), None) => id,
+        _ => return Ok(false),
+    };
+
+    telnyx.bridge(lead_call_control_id, agent_call_control_id).await?;
+    Ok(true)
+}
+
+/// Handle a webhook for an agent leg
+pub async fn handle_agent_leg_event(state: &AppState, event_type: &str, call_id: i64, agent_call_control_id: &str) {
+    let call = match db::calls::get_by_id(&state.db, call_id).await {
+        Ok(Some(call)) => call,
+        Ok(None) => {
+            tracing::warn!("Agent leg event for unknown call {}", call_id);
+            return;
+        }
+        Err(e) => {
+            tracing::error!("Failed to load call {} for agent leg: {}", call_id, e);
+            return;
+        }
+    };
+
+    match event_type {
+        "call.answered" => match bridge_on_agent_answer(&state.telnyx, &call, agent_call_control_id).await {
+            Ok(true) => {
+                let _ = db::calls::update_status(&state.db, call.id, CallStatus::Bridged).await;
+            }
+            Ok(false) => {
+                tracing::info!("Lead left call {} before the agent answered", call.id);
+                let _ = state.telnyx.hangup(agent_call_control_id).await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to bridge call {}: {:?}", call.id, e);
+                let _ = state.telnyx.hangup(agent_call_control_id).await;
+            }
+        },
+        event_type if is_connect_failure(event_type) => handle_connect_failure(state, event_type, &call).await,
+        // The agent hung up or never answered; don't leave the lead holding
+        "call.hangup" if call.ended_at.is_none() => {
+            if let Some(lead_call_control_id) = &call.call_control_id {
+                let _ = state.telnyx.hangup(lead_call_control_id).await;
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Recover from a failed transfer or bridge: record the reason, free the
+/// agent if this call still holds them and re-queue an inbound caller who is
+/// still waiting
+pub async fn handle_connect_failure(state: &AppState, event_type: &str, call: &Call) {
+    tracing::warn!("{} for call {} (agent {:?})", event_type, call.id, call.agent_id);
+
+    let recovery = FailureRecovery::for_call(event_type, call);
+    if let Err(e) = db::calls::set_failure_reason(&state.db, call.id, recovery.reason).await {
+        tracing::error!("Failed to record failure reason for call {}: {}", call.id, e);
+    }
+    // Whatever was ringing on the agent's side is no use now
+    hangup_agent_leg(state, call).await;
+
+    // An agent who already moved on to another call or status keeps it
+    if let Some(agent_id) = recovery.release_agent {
+        if let Err(e) = db::agents::release_claim(&state.db, agent_id, call.id).await {
+            tracing::error!("Failed to return agent {} to Ready: {}", agent_id, e);
+        }
+    }
+
+    if recovery.requeue {
+        let Some(call_control_id) = call.call_control_id.clone() else {
+            return;
+        };
+        let required_skill = routing::required_skill(state, call).await.unwrap_or_else(|e| {
+            tracing::warn!("Failed to load required skill for call {}: {}", call.id, e);
+            None
+        });
+        let position = state
+            .call_queue
+            .enqueue(routing::QueuedCall {
+                call_id: call.id,
+                call_control_id: call_control_id.clone(),
+                from: call.from_number.clone().unwrap_or_default(),
+                lead_id: call.lead_id,
+                required_skill,
+                enqueued_at: Utc::now(),
+            })
+            .await;
+        tracing::info!("Re-queued call {} at position {}", call.id, position);
+        routing::publish_queue(state).await;
+        let _ = state
+            .telnyx
+            .speak(&call_control_id, &routing::announcement(position), Some("female"))
+            .await;
+    }
+}
+
+/// Hang up the agent leg once the lead's leg has ended
+pub async fn hangup_agent_leg(state: &AppState, call: &Call) {
+    if let Ok(Some(agent_call_control_id)) = db::calls::get_agent_control_id(&state.db, call.id).await {
+        let _ = state.telnyx.hangup(&agent_call_control_id).await;
+    }
+}
+
+/// Hang up a call and its agent leg, storing `reason` as its disposition.
+/// A call still being dialed has no call control id to hang up yet; it is
+/// left alone so a later attempt can end it once it does.
+pub async fn end_call(state: &AppState, call: &Call, reason: &str) -> Result<(), String> {
+    let Some(call_control_id) = call.call_control_id.as_deref() else {
+        return Err("call has no call control id to hang up yet".to_string());
+    };
+
+    // Marked first so the hangup webhook sees why the call ended
+    db::calls::set_ended(&state.db, call.id, Some(reason))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    hangup_agent_leg(state, call).await;
+    state.telnyx.hangup(call_control_id).await.map_err(|e| format!("{:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::telnyx::stub::stub_telnyx;
+
+    fn call(ended: bool) -> Call {
+        Call {
+            call_control_id: Some("v3:lead-leg".to_string()),
+            lead_id: Some(1),
+            agent_id: Some(7),
+            status: CallStatus::Answered,
+            from_number: Some("+15557654321".to_string()),
+            to_number: Some("+15551234567".to_string()),
+            ended_at: ended.then(chrono::Utc::now),
+            ..crate::models::fixtures::call(42)
+        }
+    }
+
+    #[test]
+    fn test_bridge_failed_releases_agent() {
+        assert!(is_connect_failure("call.bridge.failed"));
+        assert!(is_connect_failure("call.transfer.failed"));
+        assert!(!is_connect_failure("call.bridged"));
+
+        // Outbound: the agent goes back to Ready, the lead isn't queued
+        assert_eq!(
+            FailureRecovery::for_call("call.bridge.failed", &call(false)),
+            FailureRecovery { reason: BRIDGE_FAILED, release_agent: Some(7), requeue: false }
+        );
+
+        // Nobody to free on a call without an agent
+        let unassigned = Call { agent_id: None, ..call(false) };
+        assert_eq!(FailureRecovery::for_call("call.bridge.failed", &unassigned).release_agent, None);
+    }
+
+    #[test]
+    fn test_failed_inbound_transfer_requeues_caller() {
+        let inbound = Call { direction: CallDirection::Inbound, ..call(false) };
+        assert_eq!(
+            FailureRecovery::for_call("call.transfer.failed", &inbound),
+            FailureRecovery { reason: TRANSFER_FAILED, release_agent: Some(7), requeue: true }
+        );
+
+        // A caller who already hung up isn't queued again
+        let gone = Call { direction: CallDirection::Inbound, ..call(true) };
+        assert!(!FailureRecovery::for_call("call.transfer.failed", &gone).requeue);
+    }
+
+    #[tokio::test]
+    async fn test_agent_answer_bridges_both_legs() {
+        let (url, request) = stub_telnyx().await;
+        let telnyx = TelnyxClient::new("key".to_string(), "conn".to_string()).with_base_url(url);
+
+        assert!(bridge_on_agent_answer(&telnyx, &call(false), "v3:agent-leg").await.unwrap());
+
+        let (request_line, body) = request.await.unwrap();
+        assert_eq!(request_line, "POST /calls/v3:lead-leg/actions/bridge HTTP/1.1");
+        assert_eq!(body, serde_json::json!({ "call_control_id": "v3:agent-leg" }));
+    }
+
+    #[tokio::test]
+    async fn test_no_bridge_after_lead_hung_up() {
+        // Nothing listens here; a request would fail the test
+        let telnyx = TelnyxClient::new("key".to_string(), "conn".to_string()).with_base_url("http://127.0.0.1:1");
+        assert!(!bridge_on_agent_answer(&telnyx, &call(true), "v3:agent-leg").await.unwrap());
+    }
+}
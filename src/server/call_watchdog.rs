@@ -0,0 +1,158 @@
+//! Maximum call duration
+//!
+//! A periodic sweep hangs up calls that have run longer than their limit, so
+//! a stuck call (e.g. an AI conversation going in circles) can't burn minutes
+//! indefinitely. The limit is the campaign's `max_call_seconds` when it sets
+//! one, otherwise `MAX_CALL_SECONDS`; with neither, calls run as long as they
+//! like. Calls are timed from when their record was created.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+
+use crate::models::Call;
+use super::{bridge, db, AppState};
+
+/// Reason stored on calls hung up for running too long
+pub const MAX_DURATION: &str = "max_duration";
+
+/// How often active calls are checked
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(15);
+
+/// `MAX_CALL_SECONDS`, or None when unset or zero
+pub fn max_call_seconds_from_env() -> Option<i64> {
+    std::env::var("MAX_CALL_SECONDS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|v| *v > 0)
+}
+
+/// Duration limits for active calls
+#[derive(Debug, Clone, Default)]
+pub struct CallLimits {
+    /// Applies to calls whose campaign sets no limit
+    pub default_seconds: Option<i64>,
+    /// Per-campaign limits, by campaign id
+    pub campaigns: HashMap<i64, i32>,
+}
+
+impl CallLimits {
+    pub fn limit_for(&self, call: &Call) -> Option<i64> {
+        call.campaign_id
+            .and_then(|id| self.campaigns.get(&id))
+            .filter(|seconds| **seconds > 0)
+            .map(|seconds| i64::from(*seconds))
+            .or(self.default_seconds)
+    }
+
+    /// Whether `call` has run past its limit at `now`
+    pub fn is_exceeded(&self, call: &Call, now: DateTime<Utc>) -> bool {
+        match (self.limit_for(call), call.started_at) {
+            (Some(limit), Some(started_at)) => (now - started_at).num_seconds() > limit,
+            _ => false,
+        }
+    }
+}
+
+/// The calls among `calls` that have run past their limit at `now`
+pub fn overdue<'a>(calls: &'a [Call], limits: &CallLimits, now: DateTime<Utc>) -> Vec<&'a Call> {
+    calls.iter().filter(|call| limits.is_exceeded(call, now)).collect()
+}
+
+/// Check every active call once
+pub async fn tick(state: &AppState, default_seconds: Option<i64>, now: DateTime<Utc>) -> Result<usize, sqlx::Error> {
+    let calls = db::calls::get_in_progress(&state.db).await?;
+    if calls.is_empty() {
+        return Ok(0);
+    }
+
+    let limits = CallLimits {
+        default_seconds,
+        campaigns: db::campaigns::get_max_call_seconds_all(&state.db).await?,
+    };
+
+    let mut ended = 0;
+    for call in overdue(&calls, &limits, now) {
+        match bridge::end_call(state, call, MAX_DURATION).await {
+            Ok(()) => {
+                tracing::warn!("Hung up call {} after exceeding {:?}s", call.id, limits.limit_for(call));
+                ended += 1;
+            }
+            Err(e) => tracing::error!("Failed to hang up over-long call {}: {}", call.id, e),
+        }
+    }
+    Ok(ended)
+}
+
+/// Run the watchdog in the background
+pub fn spawn(state: Arc<AppState>) -> JoinHandle<()> {
+    let default_seconds = max_call_seconds_from_env();
+    tokio::spawn(async move {
+        let mut ticker = interval(SWEEP_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = tick(&state, default_seconds, Utc::now()).await {
+                tracing::error!("Call duration sweep failed: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures;
+
+    fn call_started(id: i64, campaign_id: Option<i64>, seconds_ago: i64, now: DateTime<Utc>) -> Call {
+        Call {
+            campaign_id,
+            started_at: Some(now - chrono::Duration::seconds(seconds_ago)),
+            ..fixtures::call(id)
+        }
+    }
+
+    fn ids(calls: Vec<&Call>) -> Vec<i64> {
+        calls.iter().map(|call| call.id).collect()
+    }
+
+    #[test]
+    fn test_call_over_limit_is_hung_up() {
+        let now = Utc::now();
+        let limits = CallLimits { default_seconds: Some(600), ..Default::default() };
+        let calls = vec![call_started(1, None, 601, now), call_started(2, None, 599, now)];
+
+        assert_eq!(ids(overdue(&calls, &limits, now)), vec![1]);
+    }
+
+    #[test]
+    fn test_campaign_limit_overrides_default() {
+        let now = Utc::now();
+        let limits = CallLimits {
+            default_seconds: Some(600),
+            campaigns: HashMap::from([(3, 120), (4, 3600)]),
+        };
+        let calls = vec![
+            call_started(1, Some(3), 300, now),
+            call_started(2, Some(4), 900, now),
+            call_started(3, Some(5), 900, now),
+        ];
+
+        assert_eq!(ids(overdue(&calls, &limits, now)), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_no_limit_leaves_calls_alone() {
+        let now = Utc::now();
+        let calls = vec![
+            call_started(1, None, 86_400, now),
+            Call { started_at: None, ..fixtures::call(2) },
+        ];
+
+        assert!(overdue(&calls, &CallLimits::default(), now).is_empty());
+        // Calls with no start time are never timed out
+        let limits = CallLimits { default_seconds: Some(600), ..Default::default() };
+        assert_eq!(ids(overdue(&calls, &limits, now)), vec![1]);
+    }
+}
@@ -0,0 +1,286 @@
+//! Calling back abandoned callers
+//!
+//! An inbound caller who hangs up while waiting in the queue has abandoned the
+//! call. Their number is recorded with a deadline
+//! `ABANDONED_CALLBACK_WINDOW_MINUTES` after they hung up (60 by default, 0
+//! turns callbacks off), and whenever an agent is Ready the oldest pending
+//! callback is dialed and bridged to them like any outbound call. Callers
+//! still in the queue are served first; callbacks nobody was free to make
+//! before their deadline expire.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::{AbandonedCall, Agent, AmdMode, CallStatus};
+use super::routing::{self, CallQueue, QueuedCall};
+use super::{db, AppState};
+
+/// Minutes an abandoned caller is called back within when unset
+pub const DEFAULT_WINDOW_MINUTES: i64 = 60;
+
+/// `ABANDONED_CALLBACK_WINDOW_MINUTES`, or None when callbacks are off
+pub fn window_from_env() -> Option<Duration> {
+    let minutes = std::env::var("ABANDONED_CALLBACK_WINDOW_MINUTES")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .unwrap_or(DEFAULT_WINDOW_MINUTES);
+    (minutes > 0).then(|| Duration::minutes(minutes))
+}
+
+/// Take a caller who hung up out of the queue. Returns them with the deadline
+/// for calling them back, which is None when callbacks are off or there is no
+/// number to call. None when the call wasn't waiting in the queue.
+pub async fn leave_queue(
+    queue: &CallQueue,
+    call_control_id: &str,
+    window: Option<Duration>,
+    now: DateTime<Utc>,
+) -> Option<(QueuedCall, Option<DateTime<Utc>>)> {
+    let queued = queue.remove(call_control_id).await?;
+    let due_by = window.filter(|_| !queued.from.trim().is_empty()).map(|window| now + window);
+    Some((queued, due_by))
+}
+
+/// Record a queued caller who hung up so they are called back by `due_by`
+pub async fn record_abandoned(state: &AppState, queued: &QueuedCall, due_by: DateTime<Utc>) {
+    match db::abandoned_calls::create(&state.db, queued.call_id, &queued.from, queued.lead_id, due_by).await {
+        Ok(Some(_)) => tracing::info!("Call {} abandoned in the queue, callback scheduled", queued.call_id),
+        Ok(None) => {}
+        Err(e) => tracing::error!("Failed to record abandoned call {}: {}", queued.call_id, e),
+    }
+}
+
+/// What to do with one pending callback
+#[derive(Debug, Clone, PartialEq)]
+pub enum CallbackStep {
+    /// Nobody was free before the deadline
+    Expire { abandoned_id: i64 },
+    Dial { abandoned_id: i64, agent_id: i64 },
+}
+
+/// Pair `pending` callbacks, oldest first, with the longest idle of `agents`,
+/// one callback per agent. Callbacks past their deadline are expired and the
+/// rest wait for the next free agent.
+pub fn plan_callbacks(pending: &[AbandonedCall], agents: &[Agent], now: DateTime<Utc>) -> Vec<CallbackStep> {
+    let mut available = agents.to_vec();
+    let mut steps = Vec::new();
+
+    for abandoned in pending {
+        if now > abandoned.callback_due_by {
+            steps.push(CallbackStep::Expire { abandoned_id: abandoned.id });
+            continue;
+        }
+        let Some(agent_id) = routing::select_agent(&available).map(|a| a.id) else {
+            continue;
+        };
+        available.retain(|a| a.id != agent_id);
+        steps.push(CallbackStep::Dial { abandoned_id: abandoned.id, agent_id });
+    }
+    steps
+}
+
+/// Make whatever callbacks the agents free right now can take
+pub async fn dial_due(state: &AppState) -> Result<usize, sqlx::Error> {
+    // Callers still waiting on the line come first
+    if !state.call_queue.is_empty().await {
+        return Ok(0);
+    }
+
+    let pending = db::abandoned_calls::get_pending(&state.db).await?;
+    if pending.is_empty() {
+        return Ok(0);
+    }
+
+    let agents = db::agents::get_ready(&state.db).await?;
+    let agents = db::agent_schedules::filter_on_shift(&state.db, agents, Utc::now()).await?;
+
+    let mut dialed = 0;
+    for step in plan_callbacks(&pending, &agents, Utc::now()) {
+        match step {
+            CallbackStep::Expire { abandoned_id } => {
+                if let Err(e) = db::abandoned_calls::mark_expired(&state.db, abandoned_id).await {
+                    tracing::error!("Failed to expire callback {}: {}", abandoned_id, e);
+                }
+            }
+            CallbackStep::Dial { abandoned_id, agent_id } => {
+                let (Some(abandoned), Some(agent)) = (
+                    pending.iter().find(|a| a.id == abandoned_id),
+                    agents.iter().find(|a| a.id == agent_id),
+                ) else {
+                    continue;
+                };
+                match dial_back(state, abandoned, agent).await {
+                    Ok(Some(call_id)) => {
+                        tracing::info!("Calling back abandoned call {} as call {} for agent {}", abandoned.call_id, call_id, agent.id);
+                        dialed += 1;
+                    }
+                    Ok(None) => {}
+                    Err(e) => tracing::error!("Failed to call back abandoned call {}: {}", abandoned.call_id, e),
+                }
+            }
+        }
+    }
+    Ok(dialed)
+}
+
+/// Claim a callback and dial it for `agent`, returning the new call's id.
+/// None when another pass claimed the callback or the agent first, in which
+/// case the callback is left for the next pass.
+async fn dial_back(state: &AppState, abandoned: &AbandonedCall, agent: &Agent) -> Result<Option<i64>, String> {
+    let Some(abandoned) = db::abandoned_calls::claim(&state.db, abandoned.id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+
+    let result = dial_claimed(state, &abandoned, agent).await;
+    if !matches!(result, Ok(Some(_))) {
+        if let Err(e) = db::abandoned_calls::release(&state.db, abandoned.id).await {
+            tracing::error!("Failed to release callback {}: {}", abandoned.id, e);
+        }
+    }
+    result
+}
+
+async fn dial_claimed(state: &AppState, abandoned: &AbandonedCall, agent: &Agent) -> Result<Option<i64>, String> {
+    let Some(call) = db::calls::create_within_capacity(
+        &state.db,
+        abandoned.lead_id,
+        agent.id,
+        None,
+        &state.caller_id,
+        &abandoned.phone_number,
+    )
+    .await
+    .map_err(|e| e.to_string())?
+    else {
+        return Ok(None);
+    };
+
+    if db::agents::claim_ready(&state.db, agent.id, call.id)
+        .await
+        .map_err(|e| e.to_string())?
+        .is_none()
+    {
+        let _ = db::calls::update_status(&state.db, call.id, CallStatus::Failed).await;
+        let _ = db::calls::set_ended(&state.db, call.id, Some("agent_unavailable")).await;
+        return Ok(None);
+    }
+
+    // Marked before dialing so the callback can't be picked up again while the call rings
+    let dialed = match db::abandoned_calls::mark_dialed(&state.db, abandoned.id, call.id).await {
+        Ok(()) => super::place_telnyx_call(state, &call, &abandoned.phone_number, &state.caller_id, AmdMode::default())
+            .await
+            .map_err(|status| format!("dial failed with {}", status)),
+        Err(e) => {
+            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Failed).await;
+            Err(e.to_string())
+        }
+    };
+
+    if let Err(e) = dialed {
+        let _ = db::agents::release_claim(&state.db, agent.id, call.id).await;
+        return Err(e);
+    }
+    Ok(Some(call.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::agent;
+    use crate::models::{AgentStatus, CallbackStatus};
+
+    fn queued(call_id: i64, from: &str) -> QueuedCall {
+        QueuedCall {
+            call_id,
+            call_control_id: format!("v3:in-{}", call_id),
+            from: from.to_string(),
+            lead_id: Some(9),
+            required_skill: None,
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    fn abandoned(id: i64, due_by: DateTime<Utc>) -> AbandonedCall {
+        AbandonedCall {
+            id,
+            call_id: id + 10,
+            phone_number: "+15551234567".to_string(),
+            lead_id: None,
+            abandoned_at: due_by - Duration::minutes(DEFAULT_WINDOW_MINUTES),
+            callback_due_by: due_by,
+            status: CallbackStatus::Pending,
+            callback_call_id: None,
+            dialed_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caller_hanging_up_in_queue_is_due_a_callback() {
+        let now = Utc::now();
+        let queue = CallQueue::new();
+        queue.enqueue(queued(5, "+15551234567")).await;
+
+        let (caller, due_by) = leave_queue(&queue, "v3:in-5", Some(Duration::minutes(30)), now).await.unwrap();
+        assert_eq!(caller.call_id, 5);
+        assert_eq!(due_by, Some(now + Duration::minutes(30)));
+        assert!(queue.is_empty().await);
+
+        // Calls that weren't waiting aren't abandoned
+        assert!(leave_queue(&queue, "v3:in-5", Some(Duration::minutes(30)), now).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_no_callback_when_off_or_number_unknown() {
+        let now = Utc::now();
+        let queue = CallQueue::new();
+        queue.enqueue(queued(5, "+15551234567")).await;
+        queue.enqueue(queued(6, " ")).await;
+
+        let (_, due_by) = leave_queue(&queue, "v3:in-5", None, now).await.unwrap();
+        assert_eq!(due_by, None);
+        let (_, due_by) = leave_queue(&queue, "v3:in-6", Some(Duration::minutes(30)), now).await.unwrap();
+        assert_eq!(due_by, None);
+    }
+
+    #[test]
+    fn test_callbacks_go_to_longest_idle_agents_oldest_first() {
+        let now = Utc::now();
+        let pending = vec![
+            abandoned(1, now + Duration::minutes(5)),
+            abandoned(2, now + Duration::minutes(10)),
+            abandoned(3, now + Duration::minutes(20)),
+        ];
+        let agents = vec![
+            agent(7, AgentStatus::Ready, 2),
+            agent(8, AgentStatus::Ready, 30),
+            agent(9, AgentStatus::OnCall, 60),
+        ];
+
+        assert_eq!(
+            plan_callbacks(&pending, &agents, now),
+            vec![
+                CallbackStep::Dial { abandoned_id: 1, agent_id: 8 },
+                CallbackStep::Dial { abandoned_id: 2, agent_id: 7 },
+            ]
+        );
+        assert!(plan_callbacks(&pending, &[agent(9, AgentStatus::OnCall, 60)], now).is_empty());
+    }
+
+    #[test]
+    fn test_callback_expires_after_window() {
+        let now = Utc::now();
+        let pending = vec![abandoned(1, now - Duration::minutes(1)), abandoned(2, now + Duration::minutes(10))];
+
+        assert_eq!(
+            plan_callbacks(&pending, &[agent(7, AgentStatus::Ready, 2)], now),
+            vec![
+                CallbackStep::Expire { abandoned_id: 1 },
+                CallbackStep::Dial { abandoned_id: 2, agent_id: 7 },
+            ]
+        );
+        // Expired even with nobody free
+        assert_eq!(plan_callbacks(&pending[..1], &[], now), vec![CallbackStep::Expire { abandoned_id: 1 }]);
+    }
+}
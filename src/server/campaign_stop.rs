@@ -0,0 +1,191 @@
+//! Draining a stopped campaign's calls
+//!
+//! Stopping a campaign also ends the calls it placed that are still ringing
+//! or connected: each is marked ended with reason `campaign_stopped`, hung
+//! up, and the agent on it goes back to Ready unless they have already
+//! moved on to another call. Calls are drained
+//! concurrently, a bounded number at a time, so a large campaign doesn't
+//! flood the Telnyx API.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use async_trait::async_trait;
+use tokio::task::JoinSet;
+
+use crate::models::Call;
+use super::{bridge, db, AppState};
+
+/// Reason stored on calls ended because their campaign stopped
+pub const CAMPAIGN_STOPPED: &str = "campaign_stopped";
+
+/// Most hangups in flight at once
+pub const MAX_CONCURRENT_HANGUPS: usize = 8;
+
+/// What stopping a campaign did
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DrainSummary {
+    pub calls_ended: usize,
+    pub agents_reset: usize,
+}
+
+/// The side effects of ending a call, so draining can be tested without
+/// a database or Telnyx
+#[async_trait]
+pub trait CallDrain: Send + Sync + 'static {
+    async fn end_call(&self, call: &Call) -> Result<(), String>;
+    /// Return the agent to Ready if they are still on `call_id`; false if
+    /// they have moved on since
+    async fn reset_agent(&self, agent_id: i64, call_id: i64) -> Result<bool, String>;
+}
+
+#[async_trait]
+impl CallDrain for AppState {
+    async fn end_call(&self, call: &Call) -> Result<(), String> {
+        bridge::end_call(self, call, CAMPAIGN_STOPPED).await
+    }
+
+    async fn reset_agent(&self, agent_id: i64, call_id: i64) -> Result<bool, String> {
+        db::agents::release_claim(&self.db, agent_id, call_id).await.map_err(|e| e.to_string())
+    }
+}
+
+/// End `calls` concurrently, then return the agents still on them to Ready.
+/// Failures are logged and don't stop the rest of the drain.
+pub async fn drain_calls<D: CallDrain>(drain: Arc<D>, calls: Vec<Call>) -> DrainSummary {
+    let mut summary = DrainSummary::default();
+    let agents: HashSet<(i64, i64)> =
+        calls.iter().filter_map(|call| call.agent_id.map(|agent_id| (agent_id, call.id))).collect();
+
+    let mut tasks = JoinSet::new();
+    for call in calls {
+        if tasks.len() >= MAX_CONCURRENT_HANGUPS {
+            summary.calls_ended += finished(tasks.join_next().await);
+        }
+        let drain = drain.clone();
+        tasks.spawn(async move {
+            let result = drain.end_call(&call).await;
+            if let Err(e) = &result {
+                tracing::warn!("Failed to end call {} for stopped campaign: {}", call.id, e);
+            }
+            result.is_ok()
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        summary.calls_ended += finished(Some(result));
+    }
+
+    for (agent_id, call_id) in agents {
+        match drain.reset_agent(agent_id, call_id).await {
+            Ok(true) => summary.agents_reset += 1,
+            Ok(false) => {}
+            Err(e) => tracing::warn!("Failed to return agent {} to Ready: {}", agent_id, e),
+        }
+    }
+    summary
+}
+
+fn finished(result: Option<Result<bool, tokio::task::JoinError>>) -> usize {
+    usize::from(matches!(result, Some(Ok(true))))
+}
+
+/// Hang up everything a campaign still has in progress
+pub async fn drain_campaign(state: Arc<AppState>, campaign_id: i64) -> Result<DrainSummary, sqlx::Error> {
+    let calls = db::calls::get_in_progress_by_campaign(&state.db, campaign_id).await?;
+    if calls.is_empty() {
+        return Ok(DrainSummary::default());
+    }
+
+    let summary = drain_calls(state, calls).await;
+    tracing::info!(
+        "Campaign {} stopped: ended {} call(s), reset {} agent(s)",
+        campaign_id,
+        summary.calls_ended,
+        summary.agents_reset
+    );
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tokio::sync::Mutex;
+
+    #[derive(Default)]
+    struct MockDrain {
+        hangups: Mutex<Vec<i64>>,
+        reset: Mutex<Vec<i64>>,
+        /// Each agent's current call
+        on_call: Mutex<HashMap<i64, i64>>,
+    }
+
+    impl MockDrain {
+        fn with_agents_on(calls: &[(i64, i64)]) -> Self {
+            Self { on_call: Mutex::new(calls.iter().copied().collect()), ..Self::default() }
+        }
+    }
+
+    #[async_trait]
+    impl CallDrain for MockDrain {
+        async fn end_call(&self, call: &Call) -> Result<(), String> {
+            self.hangups.lock().await.push(call.id);
+            Ok(())
+        }
+
+        async fn reset_agent(&self, agent_id: i64, call_id: i64) -> Result<bool, String> {
+            let mut on_call = self.on_call.lock().await;
+            if on_call.get(&agent_id) != Some(&call_id) {
+                return Ok(false);
+            }
+            on_call.remove(&agent_id);
+            self.reset.lock().await.push(agent_id);
+            Ok(true)
+        }
+    }
+
+    fn active_call(id: i64, agent_id: i64) -> Call {
+        Call {
+            lead_id: Some(id * 10),
+            agent_id: Some(agent_id),
+            campaign_id: Some(3),
+            ..crate::models::fixtures::call(id)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stopping_campaign_hangs_up_calls_and_resets_agents() {
+        let drain = Arc::new(MockDrain::with_agents_on(&[(7, 1), (8, 2)]));
+
+        let summary = drain_calls(drain.clone(), vec![active_call(1, 7), active_call(2, 8)]).await;
+
+        assert_eq!(summary, DrainSummary { calls_ended: 2, agents_reset: 2 });
+        let mut hangups = drain.hangups.lock().await.clone();
+        hangups.sort();
+        assert_eq!(hangups, vec![1, 2]);
+        let mut reset = drain.reset.lock().await.clone();
+        reset.sort();
+        assert_eq!(reset, vec![7, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_drain_handles_more_calls_than_concurrency_limit() {
+        let drain = Arc::new(MockDrain::with_agents_on(&[(7, 20)]));
+        let calls = (1..=20).map(|id| active_call(id, 7)).collect();
+
+        let summary = drain_calls(drain.clone(), calls).await;
+
+        assert_eq!(summary, DrainSummary { calls_ended: 20, agents_reset: 1 });
+        assert_eq!(drain.hangups.lock().await.len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_agent_on_another_call_is_left_alone() {
+        // Agent 7 was on call 1 but has since picked up call 5 elsewhere
+        let drain = Arc::new(MockDrain::with_agents_on(&[(7, 5), (8, 2)]));
+
+        let summary = drain_calls(drain.clone(), vec![active_call(1, 7), active_call(2, 8)]).await;
+
+        assert_eq!(summary, DrainSummary { calls_ended: 2, agents_reset: 1 });
+        assert_eq!(*drain.reset.lock().await, vec![8]);
+    }
+}
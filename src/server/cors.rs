@@ -0,0 +1,119 @@
+//! CORS configuration
+//!
+//! `CORS_ALLOWED_ORIGINS` is a comma-separated list of origins allowed to call
+//! the API from a browser, e.g. `https://crm.example.com,https://admin.example.com`.
+//! Those origins may send credentials; any other origin gets no
+//! `Access-Control-Allow-Origin` header, so the browser blocks the response.
+//! When the variable is unset every origin is allowed without credentials.
+
+use axum::http::{HeaderValue, Method};
+use tower_http::cors::{AllowHeaders, Any, CorsLayer};
+
+const METHODS: [Method; 6] = [
+    Method::GET,
+    Method::POST,
+    Method::PUT,
+    Method::PATCH,
+    Method::DELETE,
+    Method::OPTIONS,
+];
+
+/// Parse the comma-separated origin list, skipping blanks and invalid values
+pub fn parse_origins(value: &str) -> Vec<HeaderValue> {
+    value
+        .split(',')
+        .map(|origin| origin.trim().trim_end_matches('/'))
+        .filter(|origin| !origin.is_empty())
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin '{}'", origin);
+                None
+            }
+        })
+        .collect()
+}
+
+/// CORS layer allowing exactly `origins`, or any origin when the list is empty
+pub fn cors_layer(origins: Vec<HeaderValue>) -> CorsLayer {
+    let layer = CorsLayer::new().allow_methods(METHODS);
+    if origins.is_empty() {
+        return layer.allow_origin(Any).allow_headers(Any);
+    }
+
+    // Credentials can't be combined with wildcard headers, so echo the requested ones
+    layer
+        .allow_origin(origins)
+        .allow_headers(AllowHeaders::mirror_request())
+        .allow_credentials(true)
+}
+
+/// Build the CORS layer from `CORS_ALLOWED_ORIGINS`
+pub fn cors_layer_from_env() -> CorsLayer {
+    let origins = parse_origins(&std::env::var("CORS_ALLOWED_ORIGINS").unwrap_or_default());
+    if origins.is_empty() {
+        tracing::warn!("CORS_ALLOWED_ORIGINS is not set; allowing requests from any origin");
+    }
+    cors_layer(origins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::{header, Request};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::Service;
+
+    fn app(origins: &str) -> Router {
+        Router::new()
+            .route("/api/leads", get(|| async { "[]" }))
+            .layer(cors_layer(parse_origins(origins)))
+    }
+
+    fn request_from(origin: &str) -> Request<Body> {
+        Request::builder()
+            .uri("/api/leads")
+            .header(header::ORIGIN, origin)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_parse_origins() {
+        let origins = parse_origins(" https://crm.example.com/, ,http://localhost:8080 ");
+        assert_eq!(
+            origins,
+            vec![
+                HeaderValue::from_static("https://crm.example.com"),
+                HeaderValue::from_static("http://localhost:8080"),
+            ]
+        );
+        assert!(parse_origins("").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origin_gets_header_and_credentials() {
+        let response = app("https://crm.example.com").call(request_from("https://crm.example.com")).await.unwrap();
+
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://crm.example.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_origin_gets_no_header() {
+        let response = app("https://crm.example.com").call(request_from("https://evil.example.net")).await.unwrap();
+
+        // Allow-Credentials is sent regardless, but without a matching
+        // Allow-Origin the browser still blocks the response
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_unset_allows_any_origin() {
+        let response = app("").call(request_from("https://anywhere.example")).await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+    }
+}
@@ -0,0 +1,113 @@
+//! Abandoned call database operations
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use crate::models::AbandonedCall;
+
+/// Record an abandoned call. Returns None when the number already has a
+/// pending callback, so a caller who tries several times is called back once.
+pub async fn create(
+    pool: &PgPool,
+    call_id: i64,
+    phone_number: &str,
+    lead_id: Option<i64>,
+    callback_due_by: DateTime<Utc>,
+) -> Result<Option<AbandonedCall>, sqlx::Error> {
+    sqlx::query_as::<_, AbandonedCall>(
+        r#"
+        INSERT INTO abandoned_calls (call_id, phone_number, lead_id, callback_due_by)
+        SELECT $1, $2, $3, $4
+        WHERE NOT EXISTS (
+            SELECT 1 FROM abandoned_calls WHERE phone_number = $2 AND status = 'Pending'
+        )
+        RETURNING id, call_id, phone_number, lead_id, abandoned_at, callback_due_by,
+                  status, callback_call_id, dialed_at
+        "#
+    )
+    .bind(call_id)
+    .bind(phone_number)
+    .bind(lead_id)
+    .bind(callback_due_by)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Callbacks still to be made, oldest abandoned first
+pub async fn get_pending(pool: &PgPool) -> Result<Vec<AbandonedCall>, sqlx::Error> {
+    sqlx::query_as::<_, AbandonedCall>(
+        r#"
+        SELECT id, call_id, phone_number, lead_id, abandoned_at, callback_due_by,
+               status, callback_call_id, dialed_at
+        FROM abandoned_calls
+        WHERE status = 'Pending'
+        ORDER BY abandoned_at, id
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Most recently abandoned calls first
+pub async fn get_recent(pool: &PgPool, limit: i64) -> Result<Vec<AbandonedCall>, sqlx::Error> {
+    sqlx::query_as::<_, AbandonedCall>(
+        r#"
+        SELECT id, call_id, phone_number, lead_id, abandoned_at, callback_due_by,
+               status, callback_call_id, dialed_at
+        FROM abandoned_calls
+        ORDER BY abandoned_at DESC, id DESC
+        LIMIT $1
+        "#
+    )
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// Claim a pending callback for dialing. None when it was already claimed,
+/// dialed or expired.
+pub async fn claim(pool: &PgPool, id: i64) -> Result<Option<AbandonedCall>, sqlx::Error> {
+    sqlx::query_as::<_, AbandonedCall>(
+        r#"
+        UPDATE abandoned_calls SET status = 'Dialing'
+        WHERE id = $1 AND status = 'Pending'
+        RETURNING id, call_id, phone_number, lead_id, abandoned_at, callback_due_by,
+                  status, callback_call_id, dialed_at
+        "#
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Put a claimed callback that couldn't be made back to Pending
+pub async fn release(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE abandoned_calls SET status = 'Pending', callback_call_id = NULL, dialed_at = NULL
+        WHERE id = $1 AND status IN ('Dialing', 'Dialed')
+        "#
+    )
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_dialed(pool: &PgPool, id: i64, callback_call_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE abandoned_calls SET status = 'Dialed', callback_call_id = $2, dialed_at = NOW() WHERE id = $1 AND status = 'Dialing'"
+    )
+    .bind(id)
+    .bind(callback_call_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn mark_expired(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE abandoned_calls SET status = 'Expired' WHERE id = $1 AND status = 'Pending'")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
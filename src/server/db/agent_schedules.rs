@@ -0,0 +1,119 @@
+//! Agent shift schedule database operations
+
+use chrono::{DateTime, NaiveTime, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use crate::models::{Agent, AgentSchedule, ScheduleSlot, UpdateAgentScheduleRequest};
+
+#[derive(sqlx::FromRow)]
+struct ScheduleRow {
+    agent_id: i64,
+    day_of_week: i16,
+    start_time: NaiveTime,
+    end_time: NaiveTime,
+    timezone: String,
+}
+
+/// Group slot rows into one schedule per agent
+fn group_rows(rows: Vec<ScheduleRow>) -> HashMap<i64, AgentSchedule> {
+    let mut schedules: HashMap<i64, AgentSchedule> = HashMap::new();
+    for row in rows {
+        schedules
+            .entry(row.agent_id)
+            .or_insert_with(|| AgentSchedule {
+                agent_id: row.agent_id,
+                timezone: row.timezone.clone(),
+                slots: Vec::new(),
+            })
+            .slots
+            .push(ScheduleSlot {
+                day_of_week: row.day_of_week,
+                start_time: row.start_time,
+                end_time: row.end_time,
+            });
+    }
+    schedules
+}
+
+/// An agent's schedule. Agents without one get an empty, unrestricted schedule.
+pub async fn get(pool: &PgPool, agent_id: i64) -> Result<AgentSchedule, sqlx::Error> {
+    let rows = sqlx::query_as::<_, ScheduleRow>(
+        r#"
+        SELECT agent_id, day_of_week, start_time, end_time, timezone
+        FROM agent_schedules
+        WHERE agent_id = $1
+        ORDER BY day_of_week, start_time
+        "#
+    )
+    .bind(agent_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(group_rows(rows).remove(&agent_id).unwrap_or(AgentSchedule {
+        agent_id,
+        timezone: "UTC".to_string(),
+        slots: Vec::new(),
+    }))
+}
+
+/// Replace an agent's schedule
+pub async fn replace(
+    pool: &PgPool,
+    agent_id: i64,
+    req: &UpdateAgentScheduleRequest,
+) -> Result<AgentSchedule, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM agent_schedules WHERE agent_id = $1")
+        .bind(agent_id)
+        .execute(&mut *tx)
+        .await?;
+
+    for slot in &req.slots {
+        sqlx::query(
+            r#"
+            INSERT INTO agent_schedules (agent_id, day_of_week, start_time, end_time, timezone)
+            VALUES ($1, $2, $3, $4, $5)
+            "#
+        )
+        .bind(agent_id)
+        .bind(slot.day_of_week)
+        .bind(slot.start_time)
+        .bind(slot.end_time)
+        .bind(&req.timezone)
+        .execute(&mut *tx)
+        .await?;
+    }
+
+    tx.commit().await?;
+    get(pool, agent_id).await
+}
+
+/// Drop agents that are outside their scheduled hours at `now`
+pub async fn filter_on_shift(
+    pool: &PgPool,
+    agents: Vec<Agent>,
+    now: DateTime<Utc>,
+) -> Result<Vec<Agent>, sqlx::Error> {
+    if agents.is_empty() {
+        return Ok(agents);
+    }
+
+    let ids: Vec<i64> = agents.iter().map(|a| a.id).collect();
+    let rows = sqlx::query_as::<_, ScheduleRow>(
+        r#"
+        SELECT agent_id, day_of_week, start_time, end_time, timezone
+        FROM agent_schedules
+        WHERE agent_id = ANY($1)
+        "#
+    )
+    .bind(&ids)
+    .fetch_all(pool)
+    .await?;
+
+    let schedules = group_rows(rows);
+    Ok(agents
+        .into_iter()
+        .filter(|a| schedules.get(&a.id).is_none_or(|s| s.is_on_shift(now)))
+        .collect())
+}
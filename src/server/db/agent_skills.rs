@@ -0,0 +1,92 @@
+//! Agent skill database operations
+
+use std::collections::{HashMap, HashSet};
+use sqlx::PgPool;
+use crate::models::InboundNumber;
+
+/// An agent's skills, alphabetically
+pub async fn get_for_agent(pool: &PgPool, agent_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT skill FROM agent_skills WHERE agent_id = $1 ORDER BY skill")
+        .bind(agent_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Replace an agent's skills. Returns the skills now set.
+pub async fn set_for_agent(pool: &PgPool, agent_id: i64, skills: &[String]) -> Result<Vec<String>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("DELETE FROM agent_skills WHERE agent_id = $1")
+        .bind(agent_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("INSERT INTO agent_skills (agent_id, skill) SELECT $1, UNNEST($2::text[]) ON CONFLICT DO NOTHING")
+        .bind(agent_id)
+        .bind(skills)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    get_for_agent(pool, agent_id).await
+}
+
+/// Skills of each of `agent_ids` that has any, keyed by agent id
+pub async fn get_for_agents(pool: &PgPool, agent_ids: &[i64]) -> Result<HashMap<i64, HashSet<String>>, sqlx::Error> {
+    if agent_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<(i64, String)> =
+        sqlx::query_as("SELECT agent_id, skill FROM agent_skills WHERE agent_id = ANY($1)")
+            .bind(agent_ids)
+            .fetch_all(pool)
+            .await?;
+
+    let mut skills: HashMap<i64, HashSet<String>> = HashMap::new();
+    for (agent_id, skill) in rows {
+        skills.entry(agent_id).or_default().insert(skill);
+    }
+    Ok(skills)
+}
+
+/// Skill required to answer calls to an inbound number, if it has one
+pub async fn required_for_number(pool: &PgPool, phone_number: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT required_skill FROM inbound_numbers WHERE phone_number = $1")
+        .bind(phone_number)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Inbound numbers that need a skill, by number
+pub async fn get_inbound_numbers(pool: &PgPool) -> Result<Vec<InboundNumber>, sqlx::Error> {
+    sqlx::query_as::<_, InboundNumber>(
+        "SELECT phone_number, required_skill, created_at FROM inbound_numbers ORDER BY phone_number",
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Set the skill calls to `phone_number` need, adding the number if it's new
+pub async fn set_inbound_number(pool: &PgPool, phone_number: &str, skill: &str) -> Result<InboundNumber, sqlx::Error> {
+    sqlx::query_as::<_, InboundNumber>(
+        r#"
+        INSERT INTO inbound_numbers (phone_number, required_skill)
+        VALUES ($1, $2)
+        ON CONFLICT (phone_number) DO UPDATE SET required_skill = EXCLUDED.required_skill
+        RETURNING phone_number, required_skill, created_at
+        "#
+    )
+    .bind(phone_number)
+    .bind(skill)
+    .fetch_one(pool)
+    .await
+}
+
+/// Stop requiring a skill for calls to `phone_number`. Returns whether it was set.
+pub async fn remove_inbound_number(pool: &PgPool, phone_number: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM inbound_numbers WHERE phone_number = $1")
+        .bind(phone_number)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
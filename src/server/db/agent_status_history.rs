@@ -0,0 +1,67 @@
+//! Agent status history for occupancy reporting
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgConnection, PgPool};
+use crate::models::{AgentStatus, AgentStatusChange, StatusReason};
+
+/// Record a status change (called inside the status update transaction)
+pub async fn record(
+    conn: &mut PgConnection,
+    agent_id: i64,
+    from_status: Option<AgentStatus>,
+    to_status: AgentStatus,
+    reason: Option<StatusReason>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO agent_status_history (agent_id, from_status, to_status, reason)
+        VALUES ($1, $2, $3, $4)
+        "#
+    )
+    .bind(agent_id)
+    .bind(from_status)
+    .bind(to_status)
+    .bind(reason)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Status the agent was in at `at`, from the last change before it
+pub async fn status_at(pool: &PgPool, agent_id: i64, at: DateTime<Utc>) -> Result<Option<AgentStatus>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT to_status
+        FROM agent_status_history
+        WHERE agent_id = $1 AND changed_at < $2
+        ORDER BY changed_at DESC, id DESC
+        LIMIT 1
+        "#
+    )
+    .bind(agent_id)
+    .bind(at)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Status changes between `from` and `to`, oldest first
+pub async fn get_between(
+    pool: &PgPool,
+    agent_id: i64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<AgentStatusChange>, sqlx::Error> {
+    sqlx::query_as::<_, AgentStatusChange>(
+        r#"
+        SELECT from_status, to_status, changed_at, reason
+        FROM agent_status_history
+        WHERE agent_id = $1 AND changed_at >= $2 AND changed_at <= $3
+        ORDER BY changed_at, id
+        "#
+    )
+    .bind(agent_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await
+}
@@ -1,7 +1,7 @@
 //! Agent database operations
 
-use sqlx::PgPool;
-use crate::models::{Agent, AgentStatus, CreateAgentRequest};
+use sqlx::{PgConnection, PgPool};
+use crate::models::{Agent, AgentStatus, CallCapacity, CreateAgentRequest, SearchTerm, StatusReason};
 
 pub async fn get_all(pool: &PgPool) -> Result<Vec<Agent>, sqlx::Error> {
     sqlx::query_as::<_, Agent>(
@@ -16,6 +16,24 @@ pub async fn get_all(pool: &PgPool) -> Result<Vec<Agent>, sqlx::Error> {
     .await
 }
 
+/// Agents whose name, extension or SIP username contains the search text
+pub async fn search(pool: &PgPool, term: &SearchTerm, limit: i64) -> Result<Vec<Agent>, sqlx::Error> {
+    sqlx::query_as::<_, Agent>(
+        r#"
+        SELECT id, name, extension, user_id, agent_type, status,
+               sip_username, current_call_id, last_status_change, created_at
+        FROM agents
+        WHERE name ILIKE $1 OR extension ILIKE $1 OR sip_username ILIKE $1
+        ORDER BY name
+        LIMIT $2
+        "#
+    )
+    .bind(term.like_pattern())
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<Agent>, sqlx::Error> {
     sqlx::query_as::<_, Agent>(
         r#"
@@ -92,8 +110,52 @@ pub async fn update(pool: &PgPool, id: i64, req: CreateAgentRequest) -> Result<A
     .await
 }
 
+/// Change an agent's status, recording the transition in agent_status_history
 pub async fn update_status(pool: &PgPool, id: i64, status: AgentStatus) -> Result<Agent, sqlx::Error> {
-    sqlx::query_as::<_, Agent>(
+    let mut tx = pool.begin().await?;
+    let previous = lock_status(&mut tx, id).await?;
+    let agent = write_status(&mut tx, id, previous, status, None).await?;
+    tx.commit().await?;
+    Ok(agent)
+}
+
+/// Change an agent's status at their own or a supervisor's request,
+/// recording why in the status history. A reason is recorded even if the
+/// status itself doesn't change (Break to Lunch). Returns None, changing
+/// nothing, when the current status doesn't allow it, e.g. going on Break
+/// in the middle of a call.
+pub async fn request_status(
+    pool: &PgPool,
+    id: i64,
+    status: AgentStatus,
+    reason: Option<StatusReason>,
+) -> Result<Option<Agent>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let previous = lock_status(&mut tx, id).await?;
+    if previous.is_some_and(|current| !current.allows_change_to(status)) {
+        return Ok(None);
+    }
+    let agent = write_status(&mut tx, id, previous, status, reason).await?;
+    tx.commit().await?;
+    Ok(Some(agent))
+}
+
+/// Current status, locked until the transaction ends
+async fn lock_status(conn: &mut PgConnection, id: i64) -> Result<Option<AgentStatus>, sqlx::Error> {
+    sqlx::query_scalar("SELECT status FROM agents WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(conn)
+        .await
+}
+
+async fn write_status(
+    conn: &mut PgConnection,
+    id: i64,
+    previous: Option<AgentStatus>,
+    status: AgentStatus,
+    reason: Option<StatusReason>,
+) -> Result<Agent, sqlx::Error> {
+    let agent = sqlx::query_as::<_, Agent>(
         r#"
         UPDATE agents
         SET status = $2, last_status_change = NOW()
@@ -104,8 +166,68 @@ pub async fn update_status(pool: &PgPool, id: i64, status: AgentStatus) -> Resul
     )
     .bind(id)
     .bind(status)
-    .fetch_one(pool)
-    .await
+    .fetch_one(&mut *conn)
+    .await?;
+
+    if previous != Some(status) || reason.is_some() {
+        super::agent_status_history::record(conn, id, previous, status, reason).await?;
+    }
+    Ok(agent)
+}
+
+/// Put a Ready agent on `call_id`. Returns None when the agent is no longer
+/// Ready, e.g. another call claimed them first, so two calls can never
+/// be given the same agent.
+pub async fn claim_ready(pool: &PgPool, id: i64, call_id: i64) -> Result<Option<Agent>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let agent = sqlx::query_as::<_, Agent>(
+        r#"
+        UPDATE agents
+        SET status = 'OnCall', current_call_id = $2, last_status_change = NOW()
+        WHERE id = $1 AND status = 'Ready'
+        RETURNING id, name, extension, user_id, agent_type, status,
+                  sip_username, current_call_id, last_status_change, created_at
+        "#
+    )
+    .bind(id)
+    .bind(call_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+
+    if agent.is_some() {
+        super::agent_status_history::record(&mut tx, id, Some(AgentStatus::Ready), AgentStatus::OnCall, None).await?;
+    }
+
+    tx.commit().await?;
+    Ok(agent)
+}
+
+/// Return an agent to Ready from `call_id`, a call that never connected or
+/// was ended under them. Agents who have since moved on to another call or
+/// status are left alone.
+pub async fn release_claim(pool: &PgPool, id: i64, call_id: i64) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(
+        r#"
+        UPDATE agents
+        SET status = 'Ready', current_call_id = NULL, last_status_change = NOW()
+        WHERE id = $1 AND current_call_id = $2 AND status = 'OnCall'
+        "#
+    )
+    .bind(id)
+    .bind(call_id)
+    .execute(&mut *tx)
+    .await?;
+
+    let released = result.rows_affected() > 0;
+    if released {
+        super::agent_status_history::record(&mut tx, id, Some(AgentStatus::OnCall), AgentStatus::Ready, None).await?;
+    }
+
+    tx.commit().await?;
+    Ok(released)
 }
 
 pub async fn set_current_call(pool: &PgPool, id: i64, call_id: Option<i64>) -> Result<(), sqlx::Error> {
@@ -117,6 +239,124 @@ pub async fn set_current_call(pool: &PgPool, id: i64, call_id: Option<i64>) -> R
     Ok(())
 }
 
+/// Greeting template spoken when this agent's calls are answered
+pub async fn get_greeting_template(pool: &PgPool, id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT greeting_template FROM agents WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map(Option::flatten)
+}
+
+pub async fn set_greeting_template(pool: &PgPool, id: i64, template: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE agents SET greeting_template = $2 WHERE id = $1")
+        .bind(id)
+        .bind(template)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Recorded greeting played when this agent's calls are answered
+pub async fn get_greeting_audio_url(pool: &PgPool, id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT greeting_audio_url FROM agents WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map(Option::flatten)
+}
+
+pub async fn set_greeting_audio_url(pool: &PgPool, id: i64, url: Option<&str>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE agents SET greeting_audio_url = $2 WHERE id = $1")
+        .bind(id)
+        .bind(url)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Telnyx telephony credential the agent's browser phone logs in with
+pub async fn get_telephony_credential_id(pool: &PgPool, id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, Option<String>>("SELECT telephony_credential_id FROM agents WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map(Option::flatten)
+}
+
+/// Store the agent's telephony credential unless they already have one,
+/// returning whichever is stored
+pub async fn set_telephony_credential_id(pool: &PgPool, id: i64, credential_id: &str) -> Result<String, sqlx::Error> {
+    sqlx::query_scalar::<_, String>(
+        r#"
+        UPDATE agents SET telephony_credential_id = COALESCE(telephony_credential_id, $2)
+        WHERE id = $1
+        RETURNING telephony_credential_id
+        "#
+    )
+    .bind(id)
+    .bind(credential_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Status, call limit and count of unfinished calls for an agent
+pub async fn get_call_capacity(pool: &PgPool, id: i64) -> Result<Option<CallCapacity>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    call_capacity(&mut conn, id).await
+}
+
+pub(super) async fn call_capacity(conn: &mut PgConnection, id: i64) -> Result<Option<CallCapacity>, sqlx::Error> {
+    sqlx::query_as::<_, CallCapacity>(
+        r"
+        SELECT a.status, a.max_concurrent_calls,
+               (SELECT COUNT(*) FROM calls c
+                WHERE c.agent_id = a.id AND c.ended_at IS NULL
+                  AND c.status IN ('Initiated', 'Ringing', 'Answered', 'Bridged')) AS active_calls
+        FROM agents a
+        WHERE a.id = $1
+        "
+    )
+    .bind(id)
+    .fetch_optional(conn)
+    .await
+}
+
+pub async fn get_max_concurrent_calls(pool: &PgPool, id: i64) -> Result<Option<i32>, sqlx::Error> {
+    sqlx::query_scalar::<_, i32>("SELECT max_concurrent_calls FROM agents WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+pub async fn set_max_concurrent_calls(pool: &PgPool, id: i64, limit: i32) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE agents SET max_concurrent_calls = $2 WHERE id = $1")
+        .bind(id)
+        .bind(limit)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Get agents assigned to a campaign who are Ready, or already on a call but
+/// allowed more than one. Callers still check each agent's capacity.
+pub async fn get_dialable_for_campaign(pool: &PgPool, campaign_id: i64) -> Result<Vec<Agent>, sqlx::Error> {
+    sqlx::query_as::<_, Agent>(
+        r"
+        SELECT a.id, a.name, a.extension, a.user_id, a.agent_type, a.status,
+               a.sip_username, a.current_call_id, a.last_status_change, a.created_at
+        FROM agents a
+        INNER JOIN campaign_agents ca ON a.id = ca.agent_id
+        WHERE ca.campaign_id = $1
+          AND (a.status = 'Ready' OR (a.status = 'OnCall' AND a.max_concurrent_calls > 1))
+        ORDER BY a.status = 'OnCall', a.last_status_change ASC
+        "
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await
+}
+
 /// Get agents that are ready and assigned to a campaign
 pub async fn get_ready_for_campaign(pool: &PgPool, campaign_id: i64) -> Result<Vec<Agent>, sqlx::Error> {
     sqlx::query_as::<_, Agent>(
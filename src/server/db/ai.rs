@@ -1,7 +1,7 @@
 //! AI settings database operations
 
 use sqlx::PgPool;
-use crate::models::AiAgentSettings;
+use crate::models::{AiAgentSettings, CampaignAiSettings, UpsertCampaignAiSettingsRequest};
 
 /// Get AI settings for an agent
 pub async fn get_settings(pool: &PgPool, agent_id: i64) -> Result<Option<AiAgentSettings>, sqlx::Error> {
@@ -86,6 +86,56 @@ pub async fn delete_settings(pool: &PgPool, agent_id: i64) -> Result<bool, sqlx:
     Ok(result.rows_affected() > 0)
 }
 
+// ============== Campaign Settings ==============
+
+/// Get AI settings for a campaign
+pub async fn get_campaign_settings(pool: &PgPool, campaign_id: i64) -> Result<Option<CampaignAiSettings>, sqlx::Error> {
+    sqlx::query_as::<_, CampaignAiSettings>(
+        r"
+        SELECT id, campaign_id, system_prompt, greeting_message, voice_id,
+               language, max_response_tokens, temperature, created_at, updated_at
+        FROM campaign_ai_settings
+        WHERE campaign_id = $1
+        "
+    )
+    .bind(campaign_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Create or update AI settings for a campaign
+pub async fn upsert_campaign_settings(
+    pool: &PgPool,
+    campaign_id: i64,
+    req: &UpsertCampaignAiSettingsRequest,
+) -> Result<CampaignAiSettings, sqlx::Error> {
+    sqlx::query_as::<_, CampaignAiSettings>(
+        r"
+        INSERT INTO campaign_ai_settings (campaign_id, system_prompt, greeting_message, voice_id, language, max_response_tokens, temperature, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, NOW())
+        ON CONFLICT (campaign_id) DO UPDATE SET
+            system_prompt = EXCLUDED.system_prompt,
+            greeting_message = EXCLUDED.greeting_message,
+            voice_id = EXCLUDED.voice_id,
+            language = EXCLUDED.language,
+            max_response_tokens = EXCLUDED.max_response_tokens,
+            temperature = EXCLUDED.temperature,
+            updated_at = NOW()
+        RETURNING id, campaign_id, system_prompt, greeting_message, voice_id,
+                  language, max_response_tokens, temperature, created_at, updated_at
+        "
+    )
+    .bind(campaign_id)
+    .bind(&req.system_prompt)
+    .bind(&req.greeting_message)
+    .bind(&req.voice_id)
+    .bind(req.language.as_deref().unwrap_or("en-US"))
+    .bind(req.max_response_tokens)
+    .bind(req.temperature)
+    .fetch_one(pool)
+    .await
+}
+
 // ============== Prompt Templates ==============
 
 use crate::models::PromptTemplate;
@@ -245,3 +295,26 @@ pub async fn update_global_config(pool: &PgPool, config: &GlobalAiConfig) -> Res
 
     Ok(())
 }
+
+// ============== Conversations ==============
+
+use crate::server::llm::Message;
+
+/// Store the turns of an AI call's conversation
+pub async fn save_conversation(pool: &PgPool, call_id: i64, turns: &[Message]) -> Result<(), sqlx::Error> {
+    let roles: Vec<&str> = turns.iter().map(|t| t.role.as_str()).collect();
+    let contents: Vec<&str> = turns.iter().map(|t| t.content.as_str()).collect();
+
+    sqlx::query(
+        r"
+        INSERT INTO ai_conversations (call_id, role, content)
+        SELECT $1, role, content FROM UNNEST($2::varchar[], $3::text[]) AS t(role, content)
+        "
+    )
+    .bind(call_id)
+    .bind(roles)
+    .bind(contents)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,58 @@
+//! Persisted campaign automation state
+
+use sqlx::PgPool;
+use crate::server::automation::CampaignState;
+
+/// Save a campaign's automation state, replacing any earlier row
+pub async fn save(pool: &PgPool, state: &CampaignState) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO campaign_automation_state (campaign_id, is_running, leads_processed, last_dial_at, error_message)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (campaign_id) DO UPDATE
+        SET is_running = EXCLUDED.is_running,
+            leads_processed = EXCLUDED.leads_processed,
+            last_dial_at = EXCLUDED.last_dial_at,
+            error_message = EXCLUDED.error_message,
+            updated_at = NOW()
+        "#
+    )
+    .bind(state.campaign_id)
+    .bind(state.is_running)
+    .bind(state.leads_processed)
+    .bind(state.last_dial_at)
+    .bind(&state.error_message)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Campaigns that were still dialing when the server last stopped
+pub async fn get_running(pool: &PgPool) -> Result<Vec<CampaignState>, sqlx::Error> {
+    sqlx::query_as::<_, CampaignState>(
+        r#"
+        SELECT campaign_id, is_running, 0 AS calls_in_progress, leads_processed, last_dial_at, error_message
+        FROM campaign_automation_state
+        WHERE is_running
+        ORDER BY campaign_id
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Mark a campaign's automation as running or stopped
+pub async fn set_running(pool: &PgPool, campaign_id: i64, is_running: bool) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE campaign_automation_state
+        SET is_running = $2, updated_at = NOW()
+        WHERE campaign_id = $1
+        "#
+    )
+    .bind(campaign_id)
+    .bind(is_running)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
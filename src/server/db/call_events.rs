@@ -0,0 +1,53 @@
+//! Call event log database operations
+
+use sqlx::PgPool;
+use crate::models::{CallEvent, CallEventSource};
+
+pub async fn record(
+    pool: &PgPool,
+    call_id: Option<i64>,
+    call_control_id: Option<&str>,
+    source: CallEventSource,
+    event_type: &str,
+    payload: &serde_json::Value,
+) -> Result<CallEvent, sqlx::Error> {
+    sqlx::query_as::<_, CallEvent>(
+        r#"
+        INSERT INTO call_events (call_id, call_control_id, source, event_type, payload)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, call_id, call_control_id, source, event_type, payload, processed_at
+        "#
+    )
+    .bind(call_id)
+    .bind(call_control_id)
+    .bind(source)
+    .bind(event_type)
+    .bind(payload)
+    .fetch_one(pool)
+    .await
+}
+
+/// Attach an event logged before its call was found
+pub async fn attach(pool: &PgPool, id: i64, call_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE call_events SET call_id = $2 WHERE id = $1")
+        .bind(id)
+        .bind(call_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// All events for a call, in the order they were processed
+pub async fn get_for_call(pool: &PgPool, call_id: i64) -> Result<Vec<CallEvent>, sqlx::Error> {
+    sqlx::query_as::<_, CallEvent>(
+        r#"
+        SELECT id, call_id, call_control_id, source, event_type, payload, processed_at
+        FROM call_events
+        WHERE call_id = $1
+        ORDER BY id
+        "#
+    )
+    .bind(call_id)
+    .fetch_all(pool)
+    .await
+}
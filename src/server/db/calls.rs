@@ -1,7 +1,7 @@
 //! Call database operations
 
-use sqlx::PgPool;
-use crate::models::{Call, CallStatus};
+use sqlx::{PgConnection, PgPool};
+use crate::models::{AmdOutcome, Call, CallStatus, RecordingAction, RecordingChannels, RecordingState};
 
 pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<Call>, sqlx::Error> {
     sqlx::query_as::<_, Call>(
@@ -9,7 +9,8 @@ pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<Call>, sqlx::Err
         SELECT id, call_control_id, lead_id, agent_id, campaign_id,
                direction, status, from_number, to_number,
                started_at, answered_at, ended_at,
-               duration_seconds, disposition, recording_url
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
         FROM calls
         WHERE id = $1
         "#
@@ -25,7 +26,8 @@ pub async fn get_by_control_id(pool: &PgPool, call_control_id: &str) -> Result<O
         SELECT id, call_control_id, lead_id, agent_id, campaign_id,
                direction, status, from_number, to_number,
                started_at, answered_at, ended_at,
-               duration_seconds, disposition, recording_url
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
         FROM calls
         WHERE call_control_id = $1
         "#
@@ -36,25 +38,26 @@ pub async fn get_by_control_id(pool: &PgPool, call_control_id: &str) -> Result<O
 }
 
 
-/// Create a call without a lead (direct dial)
-pub async fn create_direct(
+/// Create a record for a call received from outside
+pub async fn create_inbound(
     pool: &PgPool,
-    agent_id: Option<i64>,
+    lead_id: Option<i64>,
     call_control_id: &str,
     from_number: &str,
     to_number: &str,
 ) -> Result<Call, sqlx::Error> {
     sqlx::query_as::<_, Call>(
         r#"
-        INSERT INTO calls (agent_id, call_control_id, direction, status, from_number, to_number, started_at)
-        VALUES ($1, $2, 'Outbound', 'Initiated', $3, $4, NOW())
+        INSERT INTO calls (lead_id, call_control_id, direction, status, from_number, to_number, started_at)
+        VALUES ($1, $2, 'Inbound', 'Initiated', $3, $4, NOW())
         RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
                   direction, status, from_number, to_number,
                   started_at, answered_at, ended_at,
-                  duration_seconds, disposition, recording_url
+                  duration_seconds, disposition, recording_url, recording_channels,
+                  disposition_id, wrap_up_notes, sentiment
         "#
     )
-    .bind(agent_id)
+    .bind(lead_id)
     .bind(call_control_id)
     .bind(from_number)
     .bind(to_number)
@@ -62,6 +65,43 @@ pub async fn create_direct(
     .await
 }
 
+pub async fn assign_agent(pool: &PgPool, id: i64, agent_id: i64) -> Result<Call, sqlx::Error> {
+    sqlx::query_as::<_, Call>(
+        r#"
+        UPDATE calls SET agent_id = $2
+        WHERE id = $1
+        RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
+                  direction, status, from_number, to_number,
+                  started_at, answered_at, ended_at,
+                  duration_seconds, disposition, recording_url, recording_channels,
+                  disposition_id, wrap_up_notes, sentiment
+        "#
+    )
+    .bind(id)
+    .bind(agent_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Attach a call to a campaign, e.g. when an inbound caller picks one from an IVR menu
+pub async fn set_campaign(pool: &PgPool, id: i64, campaign_id: i64) -> Result<Call, sqlx::Error> {
+    sqlx::query_as::<_, Call>(
+        r#"
+        UPDATE calls SET campaign_id = $2
+        WHERE id = $1
+        RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
+                  direction, status, from_number, to_number,
+                  started_at, answered_at, ended_at,
+                  duration_seconds, disposition, recording_url, recording_channels,
+                  disposition_id, wrap_up_notes, sentiment
+        "#
+    )
+    .bind(id)
+    .bind(campaign_id)
+    .fetch_one(pool)
+    .await
+}
+
 pub async fn update_status(pool: &PgPool, id: i64, status: CallStatus) -> Result<Call, sqlx::Error> {
     sqlx::query_as::<_, Call>(
         r#"
@@ -71,7 +111,8 @@ pub async fn update_status(pool: &PgPool, id: i64, status: CallStatus) -> Result
         RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
                   direction, status, from_number, to_number,
                   started_at, answered_at, ended_at,
-                  duration_seconds, disposition, recording_url
+                  duration_seconds, disposition, recording_url, recording_channels,
+                  disposition_id, wrap_up_notes, sentiment
         "#
     )
     .bind(id)
@@ -88,6 +129,112 @@ pub async fn set_answered(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
+/// Flag a call as reaching voicemail while a message is left; `set_ended` keeps the reason
+pub async fn mark_voicemail(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET disposition = 'voicemail' WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Whether a call is being recorded
+pub async fn get_recording_state(pool: &PgPool, id: i64) -> Result<Option<RecordingState>, sqlx::Error> {
+    sqlx::query_scalar("SELECT recording_state FROM calls WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+/// Move a call's recording from `current` to `state` and log who did it.
+/// `channels` is the layout of a recording being started. Returns false,
+/// changing nothing, if someone else moved the recording off `current` first.
+pub async fn set_recording_state(
+    pool: &PgPool,
+    id: i64,
+    user_id: i64,
+    action: RecordingAction,
+    current: RecordingState,
+    state: RecordingState,
+    channels: Option<RecordingChannels>,
+) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE calls
+        SET recording_state = $3, recording_channels = COALESCE($4, recording_channels)
+        WHERE id = $1 AND recording_state = $2
+        "#,
+    )
+    .bind(id)
+    .bind(current)
+    .bind(state)
+    .bind(channels)
+    .execute(&mut *tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx::query("INSERT INTO call_recording_events (call_id, user_id, action, state) VALUES ($1, $2, $3, $4)")
+        .bind(id)
+        .bind(user_id)
+        .bind(action.as_str())
+        .bind(state)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Remember the channel layout a call's recording is being made with
+pub async fn set_recording_channels(pool: &PgPool, id: i64, channels: RecordingChannels) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET recording_channels = $2 WHERE id = $1")
+        .bind(id)
+        .bind(channels)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_recording_url(pool: &PgPool, id: i64, recording_url: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET recording_url = $2 WHERE id = $1")
+        .bind(id)
+        .bind(recording_url)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_failure_reason(pool: &PgPool, id: i64, reason: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET failure_reason = $2 WHERE id = $1")
+        .bind(id)
+        .bind(reason)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_amd_outcome(pool: &PgPool, id: i64, outcome: AmdOutcome) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET amd_outcome = $2 WHERE id = $1")
+        .bind(id)
+        .bind(outcome)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn set_sentiment(pool: &PgPool, id: i64, sentiment: f32) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET sentiment = $2 WHERE id = $1")
+        .bind(id)
+        .bind(sentiment.clamp(-1.0, 1.0))
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
 pub async fn set_ended(pool: &PgPool, id: i64, disposition: Option<&str>) -> Result<(), sqlx::Error> {
     sqlx::query(
         r#"
@@ -112,7 +259,8 @@ pub async fn get_active_by_agent(pool: &PgPool, agent_id: i64) -> Result<Option<
         SELECT id, call_control_id, lead_id, agent_id, campaign_id,
                direction, status, from_number, to_number,
                started_at, answered_at, ended_at,
-               duration_seconds, disposition, recording_url
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
         FROM calls
         WHERE agent_id = $1 AND status IN ('Initiated', 'Ringing', 'Answered', 'Bridged')
         ORDER BY started_at DESC
@@ -130,7 +278,8 @@ pub async fn get_by_lead(pool: &PgPool, lead_id: i64) -> Result<Vec<Call>, sqlx:
         SELECT id, call_control_id, lead_id, agent_id, campaign_id,
                direction, status, from_number, to_number,
                started_at, answered_at, ended_at,
-               duration_seconds, disposition, recording_url
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
         FROM calls
         WHERE lead_id = $1
         ORDER BY started_at DESC
@@ -141,13 +290,87 @@ pub async fn get_by_lead(pool: &PgPool, lead_id: i64) -> Result<Vec<Call>, sqlx:
     .await
 }
 
+/// Calls that have not ended yet
+pub async fn get_in_progress(pool: &PgPool) -> Result<Vec<Call>, sqlx::Error> {
+    sqlx::query_as::<_, Call>(
+        r#"
+        SELECT id, call_control_id, lead_id, agent_id, campaign_id,
+               direction, status, from_number, to_number,
+               started_at, answered_at, ended_at,
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
+        FROM calls
+        WHERE status IN ('Initiated', 'Ringing', 'Answered', 'Bridged') AND ended_at IS NULL
+        ORDER BY started_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// A campaign's calls that have not ended yet
+pub async fn get_in_progress_by_campaign(pool: &PgPool, campaign_id: i64) -> Result<Vec<Call>, sqlx::Error> {
+    sqlx::query_as::<_, Call>(
+        r#"
+        SELECT id, call_control_id, lead_id, agent_id, campaign_id,
+               direction, status, from_number, to_number,
+               started_at, answered_at, ended_at,
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
+        FROM calls
+        WHERE campaign_id = $1
+          AND status IN ('Initiated', 'Ringing', 'Answered', 'Bridged') AND ended_at IS NULL
+        ORDER BY started_at
+        "#
+    )
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Filters for the call search
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CallFilter {
+    pub agent_id: Option<i64>,
+    pub campaign_id: Option<i64>,
+    /// Only scored calls with sentiment strictly below this
+    pub sentiment_below: Option<f32>,
+    pub limit: i64,
+}
+
+/// Calls matching `filter`, newest first
+pub async fn search(pool: &PgPool, filter: &CallFilter) -> Result<Vec<Call>, sqlx::Error> {
+    sqlx::query_as::<_, Call>(
+        r#"
+        SELECT id, call_control_id, lead_id, agent_id, campaign_id,
+               direction, status, from_number, to_number,
+               started_at, answered_at, ended_at,
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
+        FROM calls
+        WHERE ($1::bigint IS NULL OR agent_id = $1)
+          AND ($2::bigint IS NULL OR campaign_id = $2)
+          AND ($3::real IS NULL OR sentiment < $3)
+        ORDER BY started_at DESC
+        LIMIT $4
+        "#
+    )
+    .bind(filter.agent_id)
+    .bind(filter.campaign_id)
+    .bind(filter.sentiment_below)
+    .bind(filter.limit)
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_recent(pool: &PgPool, limit: i64) -> Result<Vec<Call>, sqlx::Error> {
     sqlx::query_as::<_, Call>(
         r#"
         SELECT id, call_control_id, lead_id, agent_id, campaign_id,
                direction, status, from_number, to_number,
                started_at, answered_at, ended_at,
-               duration_seconds, disposition, recording_url
+               duration_seconds, disposition, recording_url, recording_channels,
+               disposition_id, wrap_up_notes, sentiment
         FROM calls
         ORDER BY started_at DESC
         LIMIT $1
@@ -158,38 +381,52 @@ pub async fn get_recent(pool: &PgPool, limit: i64) -> Result<Vec<Call>, sqlx::Er
     .await
 }
 
-/// Create a call with required lead and agent (legacy, for direct dial from UI)
-pub async fn create(
+/// Create an outbound call with optional lead, agent and campaign. Dials
+/// that claim an agent's capacity use `create_within_capacity` instead.
+pub async fn create_outbound(
     pool: &PgPool,
-    lead_id: i64,
-    agent_id: i64,
-    call_control_id: &str,
+    lead_id: Option<i64>,
+    agent_id: Option<i64>,
+    campaign_id: Option<i64>,
     from_number: &str,
     to_number: &str,
 ) -> Result<Call, sqlx::Error> {
-    sqlx::query_as::<_, Call>(
-        r#"
-        INSERT INTO calls (lead_id, agent_id, call_control_id, direction, status, from_number, to_number, started_at)
-        VALUES ($1, $2, $3, 'Outbound', 'Initiated', $4, $5, NOW())
-        RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
-                  direction, status, from_number, to_number,
-                  started_at, answered_at, ended_at,
-                  duration_seconds, disposition, recording_url
-        "#
-    )
-    .bind(lead_id)
-    .bind(agent_id)
-    .bind(call_control_id)
-    .bind(from_number)
-    .bind(to_number)
-    .fetch_one(pool)
-    .await
+    let mut conn = pool.acquire().await?;
+    insert_outbound(&mut conn, lead_id, agent_id, campaign_id, from_number, to_number).await
 }
 
-/// Create a call with optional lead, agent, and campaign (for automation)
-pub async fn create_for_automation(
+/// Create an outbound call for an agent, or None when the agent is unknown
+/// or can't take another call. The agent's row stays locked from counting
+/// their calls until the new one is stored, so two dials can't both take
+/// their last free slot.
+pub async fn create_within_capacity(
     pool: &PgPool,
     lead_id: Option<i64>,
+    agent_id: i64,
+    campaign_id: Option<i64>,
+    from_number: &str,
+    to_number: &str,
+) -> Result<Option<Call>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("SELECT id FROM agents WHERE id = $1 FOR UPDATE")
+        .bind(agent_id)
+        .execute(&mut *tx)
+        .await?;
+    // Counted after the lock is held, so calls stored by a dial that held it first are included
+    let capacity = super::agents::call_capacity(&mut tx, agent_id).await?;
+    if !capacity.is_some_and(|c| c.can_take_call()) {
+        return Ok(None);
+    }
+
+    let call = insert_outbound(&mut tx, lead_id, Some(agent_id), campaign_id, from_number, to_number).await?;
+    tx.commit().await?;
+    Ok(Some(call))
+}
+
+async fn insert_outbound(
+    conn: &mut PgConnection,
+    lead_id: Option<i64>,
     agent_id: Option<i64>,
     campaign_id: Option<i64>,
     from_number: &str,
@@ -202,7 +439,8 @@ pub async fn create_for_automation(
         RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
                   direction, status, from_number, to_number,
                   started_at, answered_at, ended_at,
-                  duration_seconds, disposition, recording_url
+                  duration_seconds, disposition, recording_url, recording_channels,
+                  disposition_id, wrap_up_notes, sentiment
         "
     )
     .bind(lead_id)
@@ -210,7 +448,7 @@ pub async fn create_for_automation(
     .bind(campaign_id)
     .bind(from_number)
     .bind(to_number)
-    .fetch_one(pool)
+    .fetch_one(conn)
     .await
 }
 
@@ -223,3 +461,48 @@ pub async fn set_control_id(pool: &PgPool, id: i64, call_control_id: &str) -> Re
         .await?;
     Ok(())
 }
+
+/// Store the control id of the leg dialed to the agent
+pub async fn set_agent_control_id(pool: &PgPool, id: i64, call_control_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE calls SET agent_call_control_id = $2 WHERE id = $1")
+        .bind(id)
+        .bind(call_control_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Control id of the agent leg, if one was dialed
+pub async fn get_agent_control_id(pool: &PgPool, id: i64) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar("SELECT agent_call_control_id FROM calls WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map(Option::flatten)
+}
+
+/// Record the agent's wrap-up disposition for a call
+pub async fn set_disposition(
+    pool: &PgPool,
+    id: i64,
+    disposition_id: i64,
+    wrap_up_notes: Option<&str>,
+) -> Result<Option<Call>, sqlx::Error> {
+    sqlx::query_as::<_, Call>(
+        r#"
+        UPDATE calls
+        SET disposition_id = $2, wrap_up_notes = $3
+        WHERE id = $1
+        RETURNING id, call_control_id, lead_id, agent_id, campaign_id,
+                  direction, status, from_number, to_number,
+                  started_at, answered_at, ended_at,
+                  duration_seconds, disposition, recording_url, recording_channels,
+                  disposition_id, wrap_up_notes, sentiment
+        "#
+    )
+    .bind(id)
+    .bind(disposition_id)
+    .bind(wrap_up_notes)
+    .fetch_optional(pool)
+    .await
+}
@@ -1,14 +1,23 @@
 //! Campaign database operations
 
-use sqlx::PgPool;
-use crate::models::{Campaign, CampaignStatus, CreateCampaignRequest};
+use std::collections::HashMap;
+use sqlx::{PgPool, Postgres, Transaction};
+use chrono::{DateTime, Utc};
+use crate::models::{
+    Campaign, CampaignLead, CampaignProgress, CampaignStatus, CreateCampaignRequest, ScheduleCampaignRequest,
+    SearchTerm,
+};
 
 pub async fn get_all(pool: &PgPool) -> Result<Vec<Campaign>, sqlx::Error> {
     sqlx::query_as::<_, Campaign>(
         r#"
         SELECT id, name, description, status, dialer_mode, caller_id,
                start_time, end_time, max_attempts, retry_delay_minutes,
-               total_leads, dialed_leads, connected_leads, created_at, updated_at
+               total_leads, dialed_leads, connected_leads, created_at, updated_at,
+               hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+               scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+               greeting_template, redact_transcripts, required_skill, max_call_seconds,
+               recording_channels, recording_format
         FROM campaigns
         ORDER BY created_at DESC
         "#
@@ -17,12 +26,39 @@ pub async fn get_all(pool: &PgPool) -> Result<Vec<Campaign>, sqlx::Error> {
     .await
 }
 
+/// Campaigns whose name or description contains the search text
+pub async fn search(pool: &PgPool, term: &SearchTerm, limit: i64) -> Result<Vec<Campaign>, sqlx::Error> {
+    sqlx::query_as::<_, Campaign>(
+        r#"
+        SELECT id, name, description, status, dialer_mode, caller_id,
+               start_time, end_time, max_attempts, retry_delay_minutes,
+               total_leads, dialed_leads, connected_leads, created_at, updated_at,
+               hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+               scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+               greeting_template, redact_transcripts, required_skill, max_call_seconds,
+               recording_channels, recording_format
+        FROM campaigns
+        WHERE name ILIKE $1 OR description ILIKE $1
+        ORDER BY name
+        LIMIT $2
+        "#
+    )
+    .bind(term.like_pattern())
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
 pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<Campaign>, sqlx::Error> {
     sqlx::query_as::<_, Campaign>(
         r#"
         SELECT id, name, description, status, dialer_mode, caller_id,
                start_time, end_time, max_attempts, retry_delay_minutes,
-               total_leads, dialed_leads, connected_leads, created_at, updated_at
+               total_leads, dialed_leads, connected_leads, created_at, updated_at,
+               hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+               scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+               greeting_template, redact_transcripts, required_skill, max_call_seconds,
+               recording_channels, recording_format
         FROM campaigns
         WHERE id = $1
         "#
@@ -37,7 +73,11 @@ pub async fn get_active(pool: &PgPool) -> Result<Vec<Campaign>, sqlx::Error> {
         r#"
         SELECT id, name, description, status, dialer_mode, caller_id,
                start_time, end_time, max_attempts, retry_delay_minutes,
-               total_leads, dialed_leads, connected_leads, created_at, updated_at
+               total_leads, dialed_leads, connected_leads, created_at, updated_at,
+               hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+               scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+               greeting_template, redact_transcripts, required_skill, max_call_seconds,
+               recording_channels, recording_format
         FROM campaigns
         WHERE status = 'Active'
         ORDER BY created_at DESC
@@ -50,11 +90,18 @@ pub async fn get_active(pool: &PgPool) -> Result<Vec<Campaign>, sqlx::Error> {
 pub async fn create(pool: &PgPool, req: CreateCampaignRequest) -> Result<Campaign, sqlx::Error> {
     sqlx::query_as::<_, Campaign>(
         r#"
-        INSERT INTO campaigns (name, description, dialer_mode, caller_id, max_attempts, retry_delay_minutes, status)
-        VALUES ($1, $2, $3, $4, $5, $6, 'Draft')
+        INSERT INTO campaigns (name, description, dialer_mode, caller_id, max_attempts, retry_delay_minutes,
+                               hold_music_url, amd_mode, leave_voicemail, voicemail_message, caller_id_pool,
+                               voicemail_audio_url, greeting_template, redact_transcripts, required_skill, max_call_seconds,
+                               recording_channels, recording_format, status)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, 'Draft')
         RETURNING id, name, description, status, dialer_mode, caller_id,
                   start_time, end_time, max_attempts, retry_delay_minutes,
-                  total_leads, dialed_leads, connected_leads, created_at, updated_at
+                  total_leads, dialed_leads, connected_leads, created_at, updated_at,
+                  hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+                  scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+                  greeting_template, redact_transcripts, required_skill, max_call_seconds,
+                  recording_channels, recording_format
         "#
     )
     .bind(&req.name)
@@ -63,6 +110,18 @@ pub async fn create(pool: &PgPool, req: CreateCampaignRequest) -> Result<Campaig
     .bind(&req.caller_id)
     .bind(req.max_attempts.unwrap_or(3))
     .bind(req.retry_delay_minutes.unwrap_or(30))
+    .bind(&req.hold_music_url)
+    .bind(req.amd_mode)
+    .bind(req.leave_voicemail)
+    .bind(&req.voicemail_message)
+    .bind(&req.caller_id_pool)
+    .bind(&req.voicemail_audio_url)
+    .bind(&req.greeting_template)
+    .bind(req.redact_transcripts)
+    .bind(&req.required_skill)
+    .bind(req.max_call_seconds)
+    .bind(req.recording_channels)
+    .bind(req.recording_format)
     .fetch_one(pool)
     .await
 }
@@ -73,11 +132,18 @@ pub async fn update(pool: &PgPool, id: i64, req: CreateCampaignRequest) -> Resul
         UPDATE campaigns
         SET name = $2, description = $3, dialer_mode = $4,
             caller_id = $5, max_attempts = $6, retry_delay_minutes = $7,
-            updated_at = NOW()
+            hold_music_url = $8, amd_mode = $9, leave_voicemail = $10,
+            voicemail_message = $11, caller_id_pool = $12, voicemail_audio_url = $13,
+            greeting_template = $14, redact_transcripts = $15, required_skill = $16,
+            max_call_seconds = $17, recording_channels = $18, recording_format = $19, updated_at = NOW()
         WHERE id = $1
         RETURNING id, name, description, status, dialer_mode, caller_id,
                   start_time, end_time, max_attempts, retry_delay_minutes,
-                  total_leads, dialed_leads, connected_leads, created_at, updated_at
+                  total_leads, dialed_leads, connected_leads, created_at, updated_at,
+                  hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+                  scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+                  greeting_template, redact_transcripts, required_skill, max_call_seconds,
+                  recording_channels, recording_format
         "#
     )
     .bind(id)
@@ -87,6 +153,18 @@ pub async fn update(pool: &PgPool, id: i64, req: CreateCampaignRequest) -> Resul
     .bind(&req.caller_id)
     .bind(req.max_attempts.unwrap_or(3))
     .bind(req.retry_delay_minutes.unwrap_or(30))
+    .bind(&req.hold_music_url)
+    .bind(req.amd_mode)
+    .bind(req.leave_voicemail)
+    .bind(&req.voicemail_message)
+    .bind(&req.caller_id_pool)
+    .bind(&req.voicemail_audio_url)
+    .bind(&req.greeting_template)
+    .bind(req.redact_transcripts)
+    .bind(&req.required_skill)
+    .bind(req.max_call_seconds)
+    .bind(req.recording_channels)
+    .bind(req.recording_format)
     .fetch_one(pool)
     .await
 }
@@ -99,7 +177,11 @@ pub async fn update_status(pool: &PgPool, id: i64, status: CampaignStatus) -> Re
         WHERE id = $1
         RETURNING id, name, description, status, dialer_mode, caller_id,
                   start_time, end_time, max_attempts, retry_delay_minutes,
-                  total_leads, dialed_leads, connected_leads, created_at, updated_at
+                  total_leads, dialed_leads, connected_leads, created_at, updated_at,
+                  hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+                  scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+                  greeting_template, redact_transcripts, required_skill, max_call_seconds,
+                  recording_channels, recording_format
         "#
     )
     .bind(id)
@@ -123,3 +205,273 @@ pub async fn increment_connected(pool: &PgPool, id: i64) -> Result<(), sqlx::Err
         .await?;
     Ok(())
 }
+
+/// Campaigns with a pending scheduled start or end at or before `now`
+pub async fn get_schedule_due(pool: &PgPool, now: DateTime<Utc>) -> Result<Vec<Campaign>, sqlx::Error> {
+    sqlx::query_as::<_, Campaign>(
+        r#"
+        SELECT id, name, description, status, dialer_mode, caller_id,
+               start_time, end_time, max_attempts, retry_delay_minutes,
+               total_leads, dialed_leads, connected_leads, created_at, updated_at,
+               hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+               scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+               greeting_template, redact_transcripts, required_skill, max_call_seconds,
+               recording_channels, recording_format
+        FROM campaigns
+        WHERE scheduled_start_at <= $1 OR scheduled_end_at <= $1
+        ORDER BY id
+        "#
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await
+}
+
+/// Forget schedule times at or before `now` on campaigns that passed them
+/// without a transition, so `get_schedule_due` stops returning them. Times
+/// rescheduled into the future since they were read are kept.
+pub async fn clear_passed_schedule(pool: &PgPool, ids: &[i64], now: DateTime<Utc>) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE campaigns
+        SET scheduled_start_at = CASE WHEN scheduled_start_at <= $2 THEN NULL ELSE scheduled_start_at END,
+            scheduled_end_at = CASE WHEN scheduled_end_at <= $2 THEN NULL ELSE scheduled_end_at END,
+            updated_at = NOW()
+        WHERE id = ANY($1)
+        "#
+    )
+    .bind(ids)
+    .bind(now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn set_schedule(
+    pool: &PgPool,
+    id: i64,
+    req: &ScheduleCampaignRequest,
+) -> Result<Option<Campaign>, sqlx::Error> {
+    sqlx::query_as::<_, Campaign>(
+        r#"
+        UPDATE campaigns
+        SET scheduled_start_at = $2, scheduled_end_at = $3, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, name, description, status, dialer_mode, caller_id,
+                  start_time, end_time, max_attempts, retry_delay_minutes,
+                  total_leads, dialed_leads, connected_leads, created_at, updated_at,
+                  hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+                  scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+                  greeting_template, redact_transcripts, required_skill, max_call_seconds,
+                  recording_channels, recording_format
+        "#
+    )
+    .bind(id)
+    .bind(req.scheduled_start_at)
+    .bind(req.scheduled_end_at)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Apply a scheduler transition. The schedule field that triggered it is
+/// cleared so a manual status change afterwards isn't overridden.
+pub async fn apply_scheduled_status(
+    pool: &PgPool,
+    id: i64,
+    status: CampaignStatus,
+) -> Result<Campaign, sqlx::Error> {
+    sqlx::query_as::<_, Campaign>(
+        r#"
+        UPDATE campaigns
+        SET status = $2,
+            scheduled_start_at = CASE WHEN $2 = 'Active'::campaign_status THEN NULL ELSE scheduled_start_at END,
+            scheduled_end_at = CASE WHEN $2 = 'Active'::campaign_status THEN scheduled_end_at ELSE NULL END,
+            updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, name, description, status, dialer_mode, caller_id,
+                  start_time, end_time, max_attempts, retry_delay_minutes,
+                  total_leads, dialed_leads, connected_leads, created_at, updated_at,
+                  hold_music_url, amd_mode, leave_voicemail, voicemail_message,
+                  scheduled_start_at, scheduled_end_at, caller_id_pool, voicemail_audio_url,
+                  greeting_template, redact_transcripts, required_skill, max_call_seconds,
+                  recording_channels, recording_format
+        "#
+    )
+    .bind(id)
+    .bind(status)
+    .fetch_one(pool)
+    .await
+}
+
+/// A page of the campaign's leads, each with the outcome of its latest call
+pub async fn get_leads(
+    pool: &PgPool,
+    campaign_id: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<CampaignLead>, sqlx::Error> {
+    sqlx::query_as::<_, CampaignLead>(
+        r#"
+        SELECT l.id, l.first_name, l.last_name, l.phone, l.email, l.company,
+               l.status, l.notes, l.assigned_agent_id, l.campaign_id,
+               l.call_attempts, l.last_call_at, l.created_at, l.updated_at, l.deleted_at,
+               last_call.status AS last_call_status, last_call.disposition AS last_disposition
+        FROM leads l
+        LEFT JOIN LATERAL (
+            SELECT c.status, c.disposition
+            FROM calls c
+            WHERE c.lead_id = l.id
+            ORDER BY c.started_at DESC NULLS LAST, c.id DESC
+            LIMIT 1
+        ) last_call ON TRUE
+        WHERE l.campaign_id = $1 AND l.deleted_at IS NULL
+        ORDER BY l.id
+        LIMIT $2 OFFSET $3
+        "#
+    )
+    .bind(campaign_id)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+/// Lead counts for one campaign. A lead counts as contacted once it has been
+/// dialed or its status has moved past New.
+pub async fn get_progress(pool: &PgPool, campaign_id: i64) -> Result<CampaignProgress, sqlx::Error> {
+    let (total, contacted): (i64, i64) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*),
+               COUNT(*) FILTER (WHERE call_attempts > 0 OR status <> 'New')
+        FROM leads
+        WHERE campaign_id = $1 AND deleted_at IS NULL
+        "#
+    )
+    .bind(campaign_id)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(CampaignProgress::from_counts(total, contacted))
+}
+
+/// Call duration limits of campaigns that set one, keyed by campaign id
+pub async fn get_max_call_seconds_all(pool: &PgPool) -> Result<HashMap<i64, i32>, sqlx::Error> {
+    let rows: Vec<(i64, i32)> =
+        sqlx::query_as("SELECT id, max_call_seconds FROM campaigns WHERE max_call_seconds IS NOT NULL")
+            .fetch_all(pool)
+            .await?;
+    Ok(rows.into_iter().collect())
+}
+
+/// Lead counts for every campaign that has leads, keyed by campaign id
+pub async fn get_progress_all(pool: &PgPool) -> Result<HashMap<i64, CampaignProgress>, sqlx::Error> {
+    let rows: Vec<(i64, i64, i64)> = sqlx::query_as(
+        r#"
+        SELECT campaign_id,
+               COUNT(*),
+               COUNT(*) FILTER (WHERE call_attempts > 0 OR status <> 'New')
+        FROM leads
+        WHERE campaign_id IS NOT NULL AND deleted_at IS NULL
+        GROUP BY campaign_id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, total, contacted)| (id, CampaignProgress::from_counts(total, contacted)))
+        .collect())
+}
+
+/// Moves leads into a campaign, returning the campaign each moved lead was in before
+const ATTACH_LEADS: &str = r#"
+    UPDATE leads l
+    SET campaign_id = $1, updated_at = NOW()
+    FROM leads prev
+    WHERE l.id = prev.id AND l.id = ANY($2) AND l.deleted_at IS NULL
+    RETURNING prev.campaign_id
+"#;
+
+const DETACH_LEAD: &str = r#"
+    UPDATE leads SET campaign_id = NULL, updated_at = NOW()
+    WHERE id = $1 AND campaign_id = $2
+"#;
+
+const REFRESH_TOTAL_LEADS: &str = r#"
+    UPDATE campaigns c
+    SET total_leads = (
+        SELECT COUNT(*) FROM leads l WHERE l.campaign_id = c.id AND l.deleted_at IS NULL
+    )
+    WHERE c.id = ANY($1)
+"#;
+
+/// Move leads into the campaign, returning how many were attached.
+/// Leads already in another campaign are moved; deleted leads are skipped.
+pub async fn attach_leads(pool: &PgPool, campaign_id: i64, lead_ids: &[i64]) -> Result<u64, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let previous: Vec<Option<i64>> = sqlx::query_scalar(ATTACH_LEADS)
+        .bind(campaign_id)
+        .bind(lead_ids)
+        .fetch_all(&mut *tx)
+        .await?;
+
+    refresh_total_leads(&mut tx, &affected_campaigns(campaign_id, &previous)).await?;
+    tx.commit().await?;
+
+    Ok(previous.len() as u64)
+}
+
+/// Remove a lead from the campaign. Returns false if it wasn't part of it.
+pub async fn detach_lead(pool: &PgPool, campaign_id: i64, lead_id: i64) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let result = sqlx::query(DETACH_LEAD)
+        .bind(lead_id)
+        .bind(campaign_id)
+        .execute(&mut *tx)
+        .await?;
+
+    refresh_total_leads(&mut tx, &[campaign_id]).await?;
+    tx.commit().await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// The campaign leads were attached to and every campaign they left
+fn affected_campaigns(campaign_id: i64, previous: &[Option<i64>]) -> Vec<i64> {
+    let mut ids: Vec<i64> = previous.iter().flatten().copied().chain([campaign_id]).collect();
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Bring the stored `total_leads` counter of `campaign_ids` in line with the leads table
+async fn refresh_total_leads(tx: &mut Transaction<'_, Postgres>, campaign_ids: &[i64]) -> Result<(), sqlx::Error> {
+    sqlx::query(REFRESH_TOTAL_LEADS)
+        .bind(campaign_ids)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attach_refreshes_source_and_target_campaigns() {
+        assert_eq!(affected_campaigns(5, &[Some(2), None, Some(2), Some(5), Some(9)]), vec![2, 5, 9]);
+        assert_eq!(affected_campaigns(5, &[None, None]), vec![5]);
+        assert_eq!(affected_campaigns(5, &[]), vec![5]);
+    }
+
+    #[test]
+    fn test_lead_count_refresh_is_scoped() {
+        assert!(REFRESH_TOTAL_LEADS.contains("WHERE c.id = ANY($1)"));
+        assert!(ATTACH_LEADS.contains("l.deleted_at IS NULL"));
+        assert!(ATTACH_LEADS.contains("RETURNING prev.campaign_id"));
+        assert!(DETACH_LEAD.contains("campaign_id = $2"));
+    }
+}
@@ -0,0 +1,100 @@
+//! Conference participant database operations
+
+use sqlx::PgPool;
+use crate::models::{ConferenceParticipant, MonitorMode};
+
+/// Get the Telnyx conference id already created for a call, if any participant is still in it
+pub async fn get_active_conference_id(pool: &PgPool, call_id: i64) -> Result<Option<String>, sqlx::Error> {
+    let result: Option<(String,)> = sqlx::query_as(
+        r#"
+        SELECT conference_id
+        FROM conference_participants
+        WHERE call_id = $1 AND left_at IS NULL
+        ORDER BY joined_at
+        LIMIT 1
+        "#
+    )
+    .bind(call_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(result.map(|r| r.0))
+}
+
+pub async fn get_active_participants(pool: &PgPool, call_id: i64) -> Result<Vec<ConferenceParticipant>, sqlx::Error> {
+    sqlx::query_as::<_, ConferenceParticipant>(
+        r#"
+        SELECT id, call_id, conference_id, user_id, call_control_id, muted, monitor_mode, joined_at, left_at
+        FROM conference_participants
+        WHERE call_id = $1 AND left_at IS NULL
+        ORDER BY joined_at
+        "#
+    )
+    .bind(call_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn add_participant(
+    pool: &PgPool,
+    call_id: i64,
+    conference_id: &str,
+    user_id: Option<i64>,
+    call_control_id: &str,
+    muted: bool,
+    monitor_mode: Option<MonitorMode>,
+) -> Result<ConferenceParticipant, sqlx::Error> {
+    sqlx::query_as::<_, ConferenceParticipant>(
+        r#"
+        INSERT INTO conference_participants (call_id, conference_id, user_id, call_control_id, muted, monitor_mode)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, call_id, conference_id, user_id, call_control_id, muted, monitor_mode, joined_at, left_at
+        "#
+    )
+    .bind(call_id)
+    .bind(conference_id)
+    .bind(user_id)
+    .bind(call_control_id)
+    .bind(muted)
+    .bind(monitor_mode.map(|m| m.as_str()))
+    .fetch_one(pool)
+    .await
+}
+
+/// Mark a participant as having left. Returns false if they weren't in the conference.
+pub async fn remove_participant(pool: &PgPool, call_id: i64, call_control_id: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        r#"
+        UPDATE conference_participants
+        SET left_at = NOW()
+        WHERE call_id = $1 AND call_control_id = $2 AND left_at IS NULL
+        "#
+    )
+    .bind(call_id)
+    .bind(call_control_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Mark every participant as having left (e.g. when the call ends)
+pub async fn end_conference(pool: &PgPool, call_id: i64) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE conference_participants SET left_at = NOW() WHERE call_id = $1 AND left_at IS NULL")
+        .bind(call_id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Supervisors currently monitoring any call
+pub async fn get_active_monitoring(pool: &PgPool) -> Result<Vec<ConferenceParticipant>, sqlx::Error> {
+    sqlx::query_as::<_, ConferenceParticipant>(
+        r#"
+        SELECT id, call_id, conference_id, user_id, call_control_id, muted, monitor_mode, joined_at, left_at
+        FROM conference_participants
+        WHERE monitor_mode IS NOT NULL AND left_at IS NULL
+        ORDER BY joined_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,54 @@
+//! Disposition (wrap-up code) database operations
+
+use sqlx::PgPool;
+use crate::models::{CreateDispositionRequest, Disposition};
+
+pub async fn get_active(pool: &PgPool) -> Result<Vec<Disposition>, sqlx::Error> {
+    sqlx::query_as::<_, Disposition>(
+        r#"
+        SELECT id, code, label, is_success, active
+        FROM dispositions
+        WHERE active = TRUE
+        ORDER BY label
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<Disposition>, sqlx::Error> {
+    sqlx::query_as::<_, Disposition>(
+        r#"
+        SELECT id, code, label, is_success, active
+        FROM dispositions
+        WHERE id = $1
+        "#
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn create(pool: &PgPool, req: CreateDispositionRequest) -> Result<Disposition, sqlx::Error> {
+    sqlx::query_as::<_, Disposition>(
+        r#"
+        INSERT INTO dispositions (code, label, is_success)
+        VALUES ($1, $2, $3)
+        RETURNING id, code, label, is_success, active
+        "#
+    )
+    .bind(req.code.trim().to_uppercase())
+    .bind(&req.label)
+    .bind(req.is_success)
+    .fetch_one(pool)
+    .await
+}
+
+/// Dispositions are deactivated rather than deleted so historical calls keep their code
+pub async fn deactivate(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE dispositions SET active = FALSE WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
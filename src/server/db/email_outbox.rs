@@ -0,0 +1,134 @@
+//! Email outbox database operations
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::{EmailOutboxEntry, EmailStatus};
+use crate::server::email::{DeliveryOutcome, OutgoingEmail};
+
+/// How long a claimed email is held before another worker may retry it,
+/// in case the worker sending it dies mid-delivery
+const CLAIM_LEASE_SECONDS: i32 = 300;
+
+/// An email claimed for delivery
+#[derive(Debug, Clone)]
+pub struct QueuedEmail {
+    pub id: i64,
+    /// Attempts so far, including the one it was claimed for
+    pub attempts: i32,
+    pub email: OutgoingEmail,
+}
+
+#[derive(sqlx::FromRow)]
+struct QueuedEmailRow {
+    id: i64,
+    attempts: i32,
+    to_email: String,
+    to_name: Option<String>,
+    subject: String,
+    html_body: String,
+    text_body: String,
+}
+
+impl From<QueuedEmailRow> for QueuedEmail {
+    fn from(row: QueuedEmailRow) -> Self {
+        QueuedEmail {
+            id: row.id,
+            attempts: row.attempts,
+            email: OutgoingEmail {
+                to_email: row.to_email,
+                to_name: row.to_name,
+                subject: row.subject,
+                html_body: row.html_body,
+                text_body: row.text_body,
+            },
+        }
+    }
+}
+
+/// Add an email to the outbox, due immediately
+pub async fn enqueue(pool: &PgPool, email: &OutgoingEmail) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO email_outbox (to_email, to_name, subject, html_body, text_body)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#
+    )
+    .bind(&email.to_email)
+    .bind(&email.to_name)
+    .bind(&email.subject)
+    .bind(&email.html_body)
+    .bind(&email.text_body)
+    .fetch_one(pool)
+    .await
+}
+
+/// Claim up to `limit` due emails, counting the attempt about to be made
+pub async fn claim_due(pool: &PgPool, limit: i64) -> Result<Vec<QueuedEmail>, sqlx::Error> {
+    sqlx::query_as::<_, QueuedEmailRow>(
+        r#"
+        UPDATE email_outbox
+        SET attempts = attempts + 1,
+            next_attempt_at = NOW() + make_interval(secs => $2)
+        WHERE id IN (
+            SELECT id FROM email_outbox
+            WHERE status = 'Pending' AND next_attempt_at <= NOW()
+            ORDER BY next_attempt_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING id, attempts, to_email, to_name, subject, html_body, text_body
+        "#
+    )
+    .bind(limit)
+    .bind(CLAIM_LEASE_SECONDS)
+    .fetch_all(pool)
+    .await
+    .map(|rows| rows.into_iter().map(QueuedEmail::from).collect())
+}
+
+/// Record the result of a delivery attempt
+pub async fn record_outcome(pool: &PgPool, id: i64, outcome: &DeliveryOutcome) -> Result<(), sqlx::Error> {
+    let (status, next_attempt_at, error): (EmailStatus, Option<DateTime<Utc>>, Option<&str>) = match outcome {
+        DeliveryOutcome::Sent => (EmailStatus::Sent, None, None),
+        DeliveryOutcome::Retry { next_attempt_at, error } => (EmailStatus::Pending, Some(*next_attempt_at), Some(error)),
+        DeliveryOutcome::Failed { error } => (EmailStatus::Failed, None, Some(error)),
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE email_outbox
+        SET status = $2,
+            next_attempt_at = COALESCE($3, next_attempt_at),
+            last_error = COALESCE($4, last_error),
+            sent_at = CASE WHEN $2 = 'Sent'::email_status THEN NOW() ELSE sent_at END
+        WHERE id = $1
+        "#
+    )
+    .bind(id)
+    .bind(status)
+    .bind(next_attempt_at)
+    .bind(error)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Most recent outbox entries, optionally only those with `status`
+pub async fn list(pool: &PgPool, status: Option<EmailStatus>, limit: i64) -> Result<Vec<EmailOutboxEntry>, sqlx::Error> {
+    sqlx::query_as::<_, EmailOutboxEntry>(
+        r#"
+        SELECT id, to_email, subject, status, attempts, last_error,
+               next_attempt_at, created_at, sent_at
+        FROM email_outbox
+        WHERE $1::email_status IS NULL OR status = $1
+        ORDER BY created_at DESC
+        LIMIT $2
+        "#
+    )
+    .bind(status)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
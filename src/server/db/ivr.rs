@@ -0,0 +1,150 @@
+//! IVR menu database operations
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::{IvrAction, IvrMenu, IvrOption, UpsertIvrMenuRequest};
+
+/// Default times a menu is repeated
+const DEFAULT_MAX_RETRIES: i32 = 2;
+
+/// Default seconds a caller has to press a key
+const DEFAULT_TIMEOUT_SECONDS: i32 = 5;
+
+/// Internal row type for IvrMenu, without its options
+#[derive(sqlx::FromRow)]
+struct IvrMenuRow {
+    id: i64,
+    phone_number: String,
+    prompt: String,
+    max_retries: i32,
+    timeout_seconds: i32,
+    created_at: Option<DateTime<Utc>>,
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl IvrMenuRow {
+    fn with_options(self, options: Vec<IvrOption>) -> IvrMenu {
+        IvrMenu {
+            id: self.id,
+            phone_number: self.phone_number,
+            prompt: self.prompt,
+            options,
+            max_retries: self.max_retries,
+            timeout_seconds: self.timeout_seconds,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+async fn get_options(pool: &PgPool, menu_id: i64) -> Result<Vec<IvrOption>, sqlx::Error> {
+    sqlx::query_as::<_, IvrOption>(
+        "SELECT digit, action, campaign_id FROM ivr_options WHERE menu_id = $1 ORDER BY digit"
+    )
+    .bind(menu_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// The menu callers to `phone_number` hear, if it has one
+pub async fn get_by_number(pool: &PgPool, phone_number: &str) -> Result<Option<IvrMenu>, sqlx::Error> {
+    let row = sqlx::query_as::<_, IvrMenuRow>(
+        r#"
+        SELECT id, phone_number, prompt, max_retries, timeout_seconds, created_at, updated_at
+        FROM ivr_menus
+        WHERE phone_number = $1
+        "#
+    )
+    .bind(phone_number)
+    .fetch_optional(pool)
+    .await?;
+
+    match row {
+        Some(row) => {
+            let options = get_options(pool, row.id).await?;
+            Ok(Some(row.with_options(options)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Every menu, by number
+pub async fn get_all(pool: &PgPool) -> Result<Vec<IvrMenu>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, IvrMenuRow>(
+        r#"
+        SELECT id, phone_number, prompt, max_retries, timeout_seconds, created_at, updated_at
+        FROM ivr_menus
+        ORDER BY phone_number
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut menus = Vec::with_capacity(rows.len());
+    for row in rows {
+        let options = get_options(pool, row.id).await?;
+        menus.push(row.with_options(options));
+    }
+    Ok(menus)
+}
+
+/// Create the menu on a number, or replace the one already there
+pub async fn upsert(pool: &PgPool, req: &UpsertIvrMenuRequest) -> Result<IvrMenu, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let row = sqlx::query_as::<_, IvrMenuRow>(
+        r#"
+        INSERT INTO ivr_menus (phone_number, prompt, max_retries, timeout_seconds)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (phone_number) DO UPDATE SET
+            prompt = EXCLUDED.prompt,
+            max_retries = EXCLUDED.max_retries,
+            timeout_seconds = EXCLUDED.timeout_seconds,
+            updated_at = NOW()
+        RETURNING id, phone_number, prompt, max_retries, timeout_seconds, created_at, updated_at
+        "#
+    )
+    .bind(req.phone_number.trim())
+    .bind(req.prompt.trim())
+    .bind(req.max_retries.unwrap_or(DEFAULT_MAX_RETRIES))
+    .bind(req.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("DELETE FROM ivr_options WHERE menu_id = $1")
+        .bind(row.id)
+        .execute(&mut *tx)
+        .await?;
+
+    let digits: Vec<&str> = req.options.iter().map(|o| o.digit.as_str()).collect();
+    let actions: Vec<IvrAction> = req.options.iter().map(|o| o.action).collect();
+    let campaign_ids: Vec<Option<i64>> = req.options.iter().map(|o| o.campaign_id).collect();
+    sqlx::query(
+        r#"
+        INSERT INTO ivr_options (menu_id, digit, action, campaign_id)
+        SELECT $1, digit, action, campaign_id
+        FROM UNNEST($2::varchar[], $3::ivr_action[], $4::bigint[]) AS t(digit, action, campaign_id)
+        "#
+    )
+    .bind(row.id)
+    .bind(digits)
+    .bind(actions)
+    .bind(campaign_ids)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let options = get_options(pool, row.id).await?;
+    Ok(row.with_options(options))
+}
+
+/// Delete a menu. Returns false if there was none.
+pub async fn delete(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM ivr_menus WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,61 @@
+//! Lead activity timeline database operations
+
+use sqlx::PgPool;
+use crate::models::{LeadEvent, LeadEventType, LeadNote};
+
+pub async fn record(
+    pool: &PgPool,
+    lead_id: i64,
+    event_type: LeadEventType,
+    description: &str,
+    user_id: Option<i64>,
+    call_id: Option<i64>,
+) -> Result<LeadEvent, sqlx::Error> {
+    sqlx::query_as::<_, LeadEvent>(
+        r#"
+        INSERT INTO lead_events (lead_id, event_type, description, user_id, call_id)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, lead_id, event_type, description, user_id, call_id, created_at
+        "#
+    )
+    .bind(lead_id)
+    .bind(event_type)
+    .bind(description)
+    .bind(user_id)
+    .bind(call_id)
+    .fetch_one(pool)
+    .await
+}
+
+/// Timeline entry for a new note. It is tied to the note, so deleting the
+/// note removes the entry along with it.
+pub async fn record_note(pool: &PgPool, note: &LeadNote) -> Result<LeadEvent, sqlx::Error> {
+    sqlx::query_as::<_, LeadEvent>(
+        r#"
+        INSERT INTO lead_events (lead_id, event_type, description, user_id, note_id)
+        VALUES ($1, 'Note', $2, $3, $4)
+        RETURNING id, lead_id, event_type, description, user_id, call_id, created_at
+        "#
+    )
+    .bind(note.lead_id)
+    .bind(&note.content)
+    .bind(note.author_id)
+    .bind(note.id)
+    .fetch_one(pool)
+    .await
+}
+
+/// All events for a lead, oldest first
+pub async fn get_timeline(pool: &PgPool, lead_id: i64) -> Result<Vec<LeadEvent>, sqlx::Error> {
+    sqlx::query_as::<_, LeadEvent>(
+        r#"
+        SELECT id, lead_id, event_type, description, user_id, call_id, created_at
+        FROM lead_events
+        WHERE lead_id = $1
+        ORDER BY created_at, id
+        "#
+    )
+    .bind(lead_id)
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,64 @@
+//! Lead notes database operations
+
+use sqlx::PgPool;
+use crate::models::LeadNote;
+
+pub async fn create(pool: &PgPool, lead_id: i64, author_id: i64, content: &str) -> Result<LeadNote, sqlx::Error> {
+    sqlx::query_as::<_, LeadNote>(
+        r#"
+        WITH note AS (
+            INSERT INTO lead_notes (lead_id, author_id, content)
+            VALUES ($1, $2, $3)
+            RETURNING id, lead_id, author_id, content, created_at
+        )
+        SELECT note.id, note.lead_id, note.author_id, u.username AS author_name, note.content, note.created_at
+        FROM note
+        LEFT JOIN users u ON u.id = note.author_id
+        "#
+    )
+    .bind(lead_id)
+    .bind(author_id)
+    .bind(content)
+    .fetch_one(pool)
+    .await
+}
+
+/// All notes for a lead, oldest first
+pub async fn get_for_lead(pool: &PgPool, lead_id: i64) -> Result<Vec<LeadNote>, sqlx::Error> {
+    sqlx::query_as::<_, LeadNote>(
+        r#"
+        SELECT n.id, n.lead_id, n.author_id, u.username AS author_name, n.content, n.created_at
+        FROM lead_notes n
+        LEFT JOIN users u ON u.id = n.author_id
+        WHERE n.lead_id = $1
+        ORDER BY n.created_at, n.id
+        "#
+    )
+    .bind(lead_id)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn get_by_id(pool: &PgPool, lead_id: i64, id: i64) -> Result<Option<LeadNote>, sqlx::Error> {
+    sqlx::query_as::<_, LeadNote>(
+        r#"
+        SELECT n.id, n.lead_id, n.author_id, u.username AS author_name, n.content, n.created_at
+        FROM lead_notes n
+        LEFT JOIN users u ON u.id = n.author_id
+        WHERE n.lead_id = $1 AND n.id = $2
+        "#
+    )
+    .bind(lead_id)
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Delete a note; its timeline entry goes with it (`lead_events.note_id` cascades)
+pub async fn delete(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM lead_notes WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
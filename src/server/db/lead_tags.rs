@@ -0,0 +1,33 @@
+//! Lead tag database operations
+
+use sqlx::PgPool;
+
+/// Tags on a lead, alphabetically
+pub async fn get_for_lead(pool: &PgPool, lead_id: i64) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query_scalar::<_, String>("SELECT tag FROM lead_tags WHERE lead_id = $1 ORDER BY tag")
+        .bind(lead_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Tag a lead. Adding a tag it already has is a no-op. Returns the lead's tags.
+pub async fn add(pool: &PgPool, lead_id: i64, tag: &str) -> Result<Vec<String>, sqlx::Error> {
+    sqlx::query("INSERT INTO lead_tags (lead_id, tag) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(lead_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+
+    get_for_lead(pool, lead_id).await
+}
+
+/// Remove a tag from a lead. Returns false if the lead didn't have it.
+pub async fn remove(pool: &PgPool, lead_id: i64, tag: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM lead_tags WHERE lead_id = $1 AND tag = $2")
+        .bind(lead_id)
+        .bind(tag)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
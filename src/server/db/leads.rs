@@ -1,15 +1,20 @@
 //! Lead database operations
 
+use std::collections::HashSet;
+use futures::stream::BoxStream;
 use sqlx::PgPool;
-use crate::models::{Lead, LeadStatus, CreateLeadRequest};
+use crate::models::{
+    AssignmentStrategy, CreateLeadRequest, Lead, LeadAssignment, LeadExportRow, LeadStatus, NumberInfo, SearchTerm,
+};
 
 pub async fn get_all(pool: &PgPool) -> Result<Vec<Lead>, sqlx::Error> {
     sqlx::query_as::<_, Lead>(
         r#"
         SELECT id, first_name, last_name, phone, email, company,
                status, notes, assigned_agent_id, campaign_id,
-               call_attempts, last_call_at, created_at, updated_at
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
         FROM leads
+        WHERE deleted_at IS NULL
         ORDER BY created_at DESC
         "#
     )
@@ -17,17 +22,187 @@ pub async fn get_all(pool: &PgPool) -> Result<Vec<Lead>, sqlx::Error> {
     .await
 }
 
+/// Filters shared by the lead list and export
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LeadFilter {
+    pub status: Option<LeadStatus>,
+    /// Normalized, distinct tags; leads must carry every one of them
+    pub tags: Vec<String>,
+    pub include_deleted: bool,
+}
+
+/// Leads matching `filter`, newest first
+pub async fn get_filtered(pool: &PgPool, filter: &LeadFilter) -> Result<Vec<Lead>, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(
+        r#"
+        SELECT id, first_name, last_name, phone, email, company,
+               status, notes, assigned_agent_id, campaign_id,
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
+        FROM leads l
+        WHERE ($1 OR deleted_at IS NULL)
+          AND ($2::lead_status IS NULL OR status = $2)
+          AND (SELECT COUNT(*) FROM lead_tags t WHERE t.lead_id = l.id AND t.tag = ANY($3)) = cardinality($3)
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(filter.include_deleted)
+    .bind(filter.status)
+    .bind(&filter.tags)
+    .fetch_all(pool)
+    .await
+}
+
+const STREAM_EXPORT: &str = r#"
+    SELECT TRIM(CONCAT_WS(' ', l.first_name, l.last_name)) AS name,
+           l.phone, l.email, l.status, a.name AS assigned_agent,
+           (SELECT STRING_AGG(n.content, E'\n' ORDER BY n.created_at, n.id)
+            FROM lead_notes n WHERE n.lead_id = l.id) AS notes
+    FROM leads l
+    LEFT JOIN agents a ON a.id = l.assigned_agent_id
+    WHERE ($1 OR l.deleted_at IS NULL)
+      AND ($2::lead_status IS NULL OR l.status = $2)
+      AND (SELECT COUNT(*) FROM lead_tags t WHERE t.lead_id = l.id AND t.tag = ANY($3)) = cardinality($3)
+    ORDER BY l.created_at DESC
+"#;
+
+/// Stream export rows for every lead matching `filter` without loading them
+/// all at once. A lead's notes are exported oldest first, one per line.
+pub fn stream_export<'a>(
+    pool: &'a PgPool,
+    filter: &'a LeadFilter,
+) -> BoxStream<'a, Result<LeadExportRow, sqlx::Error>> {
+    sqlx::query_as::<_, LeadExportRow>(STREAM_EXPORT)
+        .bind(filter.include_deleted)
+        .bind(filter.status)
+        .bind(&filter.tags)
+        .fetch(pool)
+}
+
+/// Leads whose name, email or company contains the search text, or whose
+/// phone digits contain the query's, newest first. `assigned_agent_id`
+/// limits the search to one agent's leads.
+pub async fn search(
+    pool: &PgPool,
+    term: &SearchTerm,
+    assigned_agent_id: Option<i64>,
+    limit: i64,
+) -> Result<Vec<Lead>, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(
+        r#"
+        SELECT id, first_name, last_name, phone, email, company,
+               status, notes, assigned_agent_id, campaign_id,
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
+        FROM leads
+        WHERE deleted_at IS NULL
+          AND ($3::bigint IS NULL OR assigned_agent_id = $3)
+          AND ((COALESCE(first_name, '') || ' ' || COALESCE(last_name, '')) ILIKE $1
+               OR email ILIKE $1
+               OR company ILIKE $1
+               OR ($2::text IS NOT NULL AND regexp_replace(phone, '[^0-9]', '', 'g') LIKE $2))
+        ORDER BY updated_at DESC NULLS LAST
+        LIMIT $4
+        "#
+    )
+    .bind(term.like_pattern())
+    .bind(term.phone_pattern())
+    .bind(assigned_agent_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await
+}
+
+/// All leads including soft-deleted ones (admin view)
+pub async fn get_all_including_deleted(pool: &PgPool) -> Result<Vec<Lead>, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(
+        r#"
+        SELECT id, first_name, last_name, phone, email, company,
+               status, notes, assigned_agent_id, campaign_id,
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
+        FROM leads
+        ORDER BY created_at DESC
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
+
+const GET_BY_ID: &str = r#"
+    SELECT id, first_name, last_name, phone, email, company,
+           status, notes, assigned_agent_id, campaign_id,
+           call_attempts, last_call_at, created_at, updated_at, deleted_at
+    FROM leads
+    WHERE id = $1 AND deleted_at IS NULL
+"#;
+
+/// A lead that hasn't been deleted
 pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<Lead>, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(GET_BY_ID)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+}
+
+const FILL_MISSING_NAME: &str = r#"
+    UPDATE leads
+    SET first_name = COALESCE(NULLIF(first_name, ''), $2),
+        last_name = COALESCE(NULLIF(last_name, ''), $3),
+        updated_at = NOW()
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, first_name, last_name, phone, email, company,
+              status, notes, assigned_agent_id, campaign_id,
+              call_attempts, last_call_at, created_at, updated_at, deleted_at
+"#;
+
+/// Fill in a lead's name where it is blank, leaving names already set alone
+pub async fn fill_missing_name(
+    pool: &PgPool,
+    id: i64,
+    first_name: &str,
+    last_name: Option<&str>,
+) -> Result<Lead, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(FILL_MISSING_NAME)
+        .bind(id)
+        .bind(first_name)
+        .bind(last_name)
+        .fetch_one(pool)
+        .await
+}
+
+/// Store the result of looking up a lead's number, replacing any earlier one
+pub async fn save_number_info(pool: &PgPool, lead_id: i64, info: &NumberInfo) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO lead_number_info (lead_id, carrier, line_type, caller_name, looked_up_at)
+        VALUES ($1, $2, $3, $4, NOW())
+        ON CONFLICT (lead_id) DO UPDATE
+        SET carrier = EXCLUDED.carrier, line_type = EXCLUDED.line_type,
+            caller_name = EXCLUDED.caller_name, looked_up_at = NOW()
+        "#
+    )
+    .bind(lead_id)
+    .bind(&info.carrier)
+    .bind(&info.line_type)
+    .bind(&info.caller_name)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Find a lead by phone number, ignoring formatting characters
+pub async fn get_by_phone(pool: &PgPool, phone: &str) -> Result<Option<Lead>, sqlx::Error> {
     sqlx::query_as::<_, Lead>(
         r#"
         SELECT id, first_name, last_name, phone, email, company,
                status, notes, assigned_agent_id, campaign_id,
-               call_attempts, last_call_at, created_at, updated_at
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
         FROM leads
-        WHERE id = $1
+        WHERE regexp_replace(phone, '[^0-9]', '', 'g') = regexp_replace($1, '[^0-9]', '', 'g')
+          AND deleted_at IS NULL
+        ORDER BY updated_at DESC
+        LIMIT 1
         "#
     )
-    .bind(id)
+    .bind(phone)
     .fetch_optional(pool)
     .await
 }
@@ -37,9 +212,9 @@ pub async fn get_by_agent(pool: &PgPool, agent_id: i64) -> Result<Vec<Lead>, sql
         r#"
         SELECT id, first_name, last_name, phone, email, company,
                status, notes, assigned_agent_id, campaign_id,
-               call_attempts, last_call_at, created_at, updated_at
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
         FROM leads
-        WHERE assigned_agent_id = $1
+        WHERE assigned_agent_id = $1 AND deleted_at IS NULL
         ORDER BY created_at DESC
         "#
     )
@@ -53,9 +228,9 @@ pub async fn get_by_campaign(pool: &PgPool, campaign_id: i64) -> Result<Vec<Lead
         r#"
         SELECT id, first_name, last_name, phone, email, company,
                status, notes, assigned_agent_id, campaign_id,
-               call_attempts, last_call_at, created_at, updated_at
+               call_attempts, last_call_at, created_at, updated_at, deleted_at
         FROM leads
-        WHERE campaign_id = $1
+        WHERE campaign_id = $1 AND deleted_at IS NULL
         ORDER BY created_at DESC
         "#
     )
@@ -64,119 +239,282 @@ pub async fn get_by_campaign(pool: &PgPool, campaign_id: i64) -> Result<Vec<Lead
     .await
 }
 
-pub async fn create(pool: &PgPool, req: CreateLeadRequest) -> Result<Lead, sqlx::Error> {
-    sqlx::query_as::<_, Lead>(
-        r#"
+const CREATE: &str = r#"
+    WITH lead AS (
         INSERT INTO leads (first_name, last_name, phone, email, company, campaign_id, status)
         VALUES ($1, $2, $3, $4, $5, $6, 'New')
         RETURNING id, first_name, last_name, phone, email, company,
                   status, notes, assigned_agent_id, campaign_id,
-                  call_attempts, last_call_at, created_at, updated_at
-        "#
+                  call_attempts, last_call_at, created_at, updated_at, deleted_at
+    ), opening_note AS (
+        INSERT INTO lead_notes (lead_id, author_id, content)
+        SELECT id, $7, $8 FROM lead WHERE $8::text IS NOT NULL
     )
-    .bind(&req.first_name)
-    .bind(&req.last_name)
-    .bind(&req.phone)
-    .bind(&req.email)
-    .bind(&req.company)
-    .bind(req.campaign_id)
-    .fetch_one(pool)
-    .await
+    SELECT * FROM lead
+"#;
+
+/// Create a lead, with its opening note (if any) written by `author_id`
+pub async fn create(pool: &PgPool, req: CreateLeadRequest, author_id: i64) -> Result<Lead, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(CREATE)
+        .bind(&req.first_name)
+        .bind(&req.last_name)
+        .bind(&req.phone)
+        .bind(&req.email)
+        .bind(&req.company)
+        .bind(req.campaign_id)
+        .bind(author_id)
+        .bind(req.opening_note())
+        .fetch_one(pool)
+        .await
 }
 
+const UPDATE: &str = r#"
+    UPDATE leads
+    SET first_name = $2, last_name = $3, phone = $4, email = $5, company = $6, updated_at = NOW()
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, first_name, last_name, phone, email, company,
+              status, notes, assigned_agent_id, campaign_id,
+              call_attempts, last_call_at, created_at, updated_at, deleted_at
+"#;
+
 pub async fn update(pool: &PgPool, id: i64, req: CreateLeadRequest) -> Result<Lead, sqlx::Error> {
+    sqlx::query_as::<_, Lead>(UPDATE)
+        .bind(id)
+        .bind(&req.first_name)
+        .bind(&req.last_name)
+        .bind(&req.phone)
+        .bind(&req.email)
+        .bind(&req.company)
+        .fetch_one(pool)
+        .await
+}
+
+/// Soft-delete a lead. Returns false if it doesn't exist or is already deleted.
+pub async fn delete(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("UPDATE leads SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Undo a soft delete
+pub async fn restore(pool: &PgPool, id: i64) -> Result<Option<Lead>, sqlx::Error> {
     sqlx::query_as::<_, Lead>(
         r#"
         UPDATE leads
-        SET first_name = $2, last_name = $3, phone = $4, email = $5, company = $6, updated_at = NOW()
-        WHERE id = $1
+        SET deleted_at = NULL, updated_at = NOW()
+        WHERE id = $1 AND deleted_at IS NOT NULL
         RETURNING id, first_name, last_name, phone, email, company,
                   status, notes, assigned_agent_id, campaign_id,
-                  call_attempts, last_call_at, created_at, updated_at
+                  call_attempts, last_call_at, created_at, updated_at, deleted_at
         "#
     )
     .bind(id)
-    .bind(&req.first_name)
-    .bind(&req.last_name)
-    .bind(&req.phone)
-    .bind(&req.email)
-    .bind(&req.company)
-    .fetch_one(pool)
+    .fetch_optional(pool)
     .await
 }
 
-pub async fn delete(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
-    sqlx::query("DELETE FROM leads WHERE id = $1")
+/// Permanently remove a lead
+pub async fn purge(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM leads WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
-    Ok(())
+    Ok(result.rows_affected() > 0)
 }
 
+const UPDATE_STATUS: &str = r#"
+    UPDATE leads
+    SET status = $2, updated_at = NOW()
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, first_name, last_name, phone, email, company,
+              status, notes, assigned_agent_id, campaign_id,
+              call_attempts, last_call_at, created_at, updated_at, deleted_at
+"#;
+
 pub async fn update_status(pool: &PgPool, id: i64, status: LeadStatus) -> Result<Lead, sqlx::Error> {
-    sqlx::query_as::<_, Lead>(
-        r#"
-        UPDATE leads
-        SET status = $2, updated_at = NOW()
-        WHERE id = $1
-        RETURNING id, first_name, last_name, phone, email, company,
-                  status, notes, assigned_agent_id, campaign_id,
-                  call_attempts, last_call_at, created_at, updated_at
-        "#
-    )
-    .bind(id)
-    .bind(status)
-    .fetch_one(pool)
-    .await
+    sqlx::query_as::<_, Lead>(UPDATE_STATUS)
+        .bind(id)
+        .bind(status)
+        .fetch_one(pool)
+        .await
 }
 
+const ASSIGN: &str = r#"
+    UPDATE leads
+    SET assigned_agent_id = $2, updated_at = NOW()
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, first_name, last_name, phone, email, company,
+              status, notes, assigned_agent_id, campaign_id,
+              call_attempts, last_call_at, created_at, updated_at, deleted_at
+"#;
+
 pub async fn assign(pool: &PgPool, id: i64, agent_id: i64) -> Result<Lead, sqlx::Error> {
-    sqlx::query_as::<_, Lead>(
-        r#"
-        UPDATE leads
-        SET assigned_agent_id = $2, updated_at = NOW()
-        WHERE id = $1
-        RETURNING id, first_name, last_name, phone, email, company,
-                  status, notes, assigned_agent_id, campaign_id,
-                  call_attempts, last_call_at, created_at, updated_at
-        "#
-    )
-    .bind(id)
-    .bind(agent_id)
-    .fetch_one(pool)
-    .await
+    sqlx::query_as::<_, Lead>(ASSIGN)
+        .bind(id)
+        .bind(agent_id)
+        .fetch_one(pool)
+        .await
 }
 
-pub async fn add_note(pool: &PgPool, lead_id: i64, content: &str) -> Result<Lead, sqlx::Error> {
-    // Append note to existing notes
-    sqlx::query_as::<_, Lead>(
+/// Distribute leads across agents
+///
+/// `agents` pairs each agent id with the number of open leads it already
+/// holds. Round robin and random ignore the load; least loaded hands each lead
+/// to the agent with the fewest open leads at that point, lowest id first.
+pub fn plan_assignments(
+    lead_ids: &[i64],
+    agents: &[(i64, i64)],
+    strategy: AssignmentStrategy,
+) -> Vec<LeadAssignment> {
+    if agents.is_empty() {
+        return Vec::new();
+    }
+
+    let mut loads: Vec<(i64, i64)> = agents.to_vec();
+    loads.sort_by_key(|(agent_id, _)| *agent_id);
+
+    lead_ids
+        .iter()
+        .enumerate()
+        .map(|(i, &lead_id)| {
+            let slot = match strategy {
+                AssignmentStrategy::RoundRobin => i % loads.len(),
+                AssignmentStrategy::Random => rand::random::<usize>() % loads.len(),
+                AssignmentStrategy::LeastLoaded => loads
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, (agent_id, load))| (*load, *agent_id))
+                    .map(|(slot, _)| slot)
+                    .unwrap_or(0),
+            };
+            loads[slot].1 += 1;
+            LeadAssignment { lead_id, agent_id: loads[slot].0 }
+        })
+        .collect()
+}
+
+/// Assign leads across active human agents in one transaction. Returns the
+/// assignments made, leaving out leads that don't exist or are deleted, or
+/// None when there is no agent to assign to.
+pub async fn assign_bulk(
+    pool: &PgPool,
+    lead_ids: &[i64],
+    strategy: AssignmentStrategy,
+) -> Result<Option<Vec<LeadAssignment>>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    // Open leads: assigned, not deleted and not in a final status
+    let agents: Vec<(i64, i64)> = sqlx::query_as(
         r#"
-        UPDATE leads
-        SET notes = COALESCE(notes, '') || E'\n' || $2, updated_at = NOW()
-        WHERE id = $1
-        RETURNING id, first_name, last_name, phone, email, company,
-                  status, notes, assigned_agent_id, campaign_id,
-                  call_attempts, last_call_at, created_at, updated_at
+        SELECT a.id, COUNT(l.id)
+        FROM agents a
+        LEFT JOIN leads l ON l.assigned_agent_id = a.id
+            AND l.deleted_at IS NULL
+            AND l.status NOT IN ('Converted', 'Lost', 'DoNotCall')
+        WHERE a.agent_type = 'Human' AND a.status <> 'Offline'
+        GROUP BY a.id
+        ORDER BY a.id
         "#
     )
-    .bind(lead_id)
-    .bind(content)
-    .fetch_one(pool)
-    .await
+    .fetch_all(&mut *tx)
+    .await?;
+
+    if agents.is_empty() {
+        return Ok(None);
+    }
+
+    let mut seen = HashSet::new();
+    let lead_ids: Vec<i64> = lead_ids.iter().copied().filter(|id| seen.insert(*id)).collect();
+
+    let mut assigned = Vec::with_capacity(lead_ids.len());
+    for assignment in plan_assignments(&lead_ids, &agents, strategy) {
+        let result = sqlx::query(
+            "UPDATE leads SET assigned_agent_id = $2, updated_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(assignment.lead_id)
+        .bind(assignment.agent_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() > 0 {
+            assigned.push(assignment);
+        }
+    }
+
+    tx.commit().await?;
+    Ok(Some(assigned))
 }
 
+const INCREMENT_CALL_ATTEMPTS: &str = r#"
+    UPDATE leads
+    SET call_attempts = call_attempts + 1, last_call_at = NOW(), updated_at = NOW()
+    WHERE id = $1 AND deleted_at IS NULL
+    RETURNING id, first_name, last_name, phone, email, company,
+              status, notes, assigned_agent_id, campaign_id,
+              call_attempts, last_call_at, created_at, updated_at, deleted_at
+"#;
+
 pub async fn increment_call_attempts(pool: &PgPool, id: i64) -> Result<Lead, sqlx::Error> {
-    sqlx::query_as::<_, Lead>(
-        r#"
-        UPDATE leads
-        SET call_attempts = call_attempts + 1, last_call_at = NOW(), updated_at = NOW()
-        WHERE id = $1
-        RETURNING id, first_name, last_name, phone, email, company,
-                  status, notes, assigned_agent_id, campaign_id,
-                  call_attempts, last_call_at, created_at, updated_at
-        "#
-    )
-    .bind(id)
-    .fetch_one(pool)
-    .await
+    sqlx::query_as::<_, Lead>(INCREMENT_CALL_ATTEMPTS)
+        .bind(id)
+        .fetch_one(pool)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_single_lead_queries_skip_deleted_leads() {
+        for query in [GET_BY_ID, FILL_MISSING_NAME, UPDATE, UPDATE_STATUS, ASSIGN, INCREMENT_CALL_ATTEMPTS] {
+            assert!(query.contains("WHERE id = $1 AND deleted_at IS NULL"), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_notes_live_in_lead_notes() {
+        assert!(STREAM_EXPORT.contains("FROM lead_notes n WHERE n.lead_id = l.id"));
+        assert!(!STREAM_EXPORT.contains("l.notes"));
+        assert!(CREATE.contains("INSERT INTO lead_notes (lead_id, author_id, content)"));
+    }
+
+    fn counts(assignments: &[LeadAssignment]) -> HashMap<i64, usize> {
+        let mut counts = HashMap::new();
+        for a in assignments {
+            *counts.entry(a.agent_id).or_default() += 1;
+        }
+        counts
+    }
+
+    #[test]
+    fn test_round_robin_spreads_six_leads_over_three_agents() {
+        let leads = [1, 2, 3, 4, 5, 6];
+        let agents = [(30, 9), (10, 0), (20, 4)];
+        let assignments = plan_assignments(&leads, &agents, AssignmentStrategy::RoundRobin);
+
+        let order: Vec<i64> = assignments.iter().map(|a| a.agent_id).collect();
+        assert_eq!(order, vec![10, 20, 30, 10, 20, 30]);
+        assert!(counts(&assignments).values().all(|&n| n == 2));
+    }
+
+    #[test]
+    fn test_least_loaded_picks_agent_with_fewest_open_leads() {
+        let agents = [(1, 5), (2, 1), (3, 3)];
+        let assignments = plan_assignments(&[100], &agents, AssignmentStrategy::LeastLoaded);
+        assert_eq!(assignments, vec![LeadAssignment { lead_id: 100, agent_id: 2 }]);
+
+        // Agent 2 fills up to agent 3's load, then they alternate
+        let assignments = plan_assignments(&[1, 2, 3, 4], &agents, AssignmentStrategy::LeastLoaded);
+        let order: Vec<i64> = assignments.iter().map(|a| a.agent_id).collect();
+        assert_eq!(order, vec![2, 2, 2, 3]);
+    }
+
+    #[test]
+    fn test_no_agents_assigns_nothing() {
+        assert!(plan_assignments(&[1, 2], &[], AssignmentStrategy::Random).is_empty());
+        assert_eq!(plan_assignments(&[1, 2], &[(7, 0)], AssignmentStrategy::Random).len(), 2);
+    }
 }
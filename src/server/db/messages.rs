@@ -0,0 +1,49 @@
+//! SMS message database operations
+
+use sqlx::PgPool;
+use crate::models::{CallDirection, SmsMessage};
+
+/// A message to store, sent or received
+#[derive(Debug, Clone)]
+pub struct NewMessage<'a> {
+    pub lead_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub direction: CallDirection,
+    pub from_number: &'a str,
+    pub to_number: &'a str,
+    pub body: &'a str,
+    pub telnyx_message_id: Option<&'a str>,
+}
+
+pub async fn create(pool: &PgPool, message: &NewMessage<'_>) -> Result<SmsMessage, sqlx::Error> {
+    sqlx::query_as::<_, SmsMessage>(
+        r#"
+        INSERT INTO messages (lead_id, user_id, direction, from_number, to_number, body, telnyx_message_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, lead_id, user_id, direction, from_number, to_number, body, created_at
+        "#
+    )
+    .bind(message.lead_id)
+    .bind(message.user_id)
+    .bind(message.direction.clone())
+    .bind(message.from_number)
+    .bind(message.to_number)
+    .bind(message.body)
+    .bind(message.telnyx_message_id)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_by_lead(pool: &PgPool, lead_id: i64) -> Result<Vec<SmsMessage>, sqlx::Error> {
+    sqlx::query_as::<_, SmsMessage>(
+        r#"
+        SELECT id, lead_id, user_id, direction, from_number, to_number, body, created_at
+        FROM messages
+        WHERE lead_id = $1
+        ORDER BY created_at
+        "#
+    )
+    .bind(lead_id)
+    .fetch_all(pool)
+    .await
+}
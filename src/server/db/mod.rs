@@ -8,6 +8,23 @@ pub mod users;
 pub mod stats;
 pub mod ai;
 pub mod invitations;
+pub mod refresh_tokens;
+pub mod conferences;
+pub mod dispositions;
+pub mod webhook_events;
+pub mod messages;
+pub mod lead_events;
+pub mod agent_schedules;
+pub mod agent_status_history;
+pub mod lead_tags;
+pub mod automation_state;
+pub mod lead_notes;
+pub mod notification_preferences;
+pub mod email_outbox;
+pub mod ivr;
+pub mod agent_skills;
+pub mod call_events;
+pub mod abandoned_calls;
 
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use std::time::Duration;
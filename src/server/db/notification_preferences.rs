@@ -0,0 +1,63 @@
+//! Notification preference database operations
+
+use sqlx::PgPool;
+use crate::models::{NotificationPreferences, User};
+
+/// A user's preferences, or the defaults if they never changed them
+pub async fn get(pool: &PgPool, user_id: i64) -> Result<NotificationPreferences, sqlx::Error> {
+    let prefs = sqlx::query_as::<_, NotificationPreferences>(
+        r#"
+        SELECT email_on_assignment, email_daily_report, browser_call_alerts
+        FROM notification_preferences
+        WHERE user_id = $1
+        "#
+    )
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(prefs.unwrap_or_default())
+}
+
+/// Replace a user's preferences
+pub async fn upsert(
+    pool: &PgPool,
+    user_id: i64,
+    prefs: &NotificationPreferences,
+) -> Result<NotificationPreferences, sqlx::Error> {
+    sqlx::query_as::<_, NotificationPreferences>(
+        r#"
+        INSERT INTO notification_preferences (user_id, email_on_assignment, email_daily_report, browser_call_alerts)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id) DO UPDATE
+        SET email_on_assignment = EXCLUDED.email_on_assignment,
+            email_daily_report = EXCLUDED.email_daily_report,
+            browser_call_alerts = EXCLUDED.browser_call_alerts,
+            updated_at = NOW()
+        RETURNING email_on_assignment, email_daily_report, browser_call_alerts
+        "#
+    )
+    .bind(user_id)
+    .bind(prefs.email_on_assignment)
+    .bind(prefs.email_daily_report)
+    .bind(prefs.browser_call_alerts)
+    .fetch_one(pool)
+    .await
+}
+
+/// Active supervisors and admins who haven't turned off the daily report
+pub async fn get_daily_report_recipients(pool: &PgPool) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        SELECT u.id, u.username, u.email, u.role, u.first_name, u.last_name,
+               u.password_hash, u.email_verified, u.active
+        FROM users u
+        LEFT JOIN notification_preferences np ON np.user_id = u.id
+        WHERE u.active AND u.role IN ('Admin', 'Supervisor')
+          AND COALESCE(np.email_daily_report, FALSE)
+        ORDER BY u.id
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
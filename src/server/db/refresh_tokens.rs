@@ -0,0 +1,193 @@
+//! Refresh token database operations
+
+use sqlx::PgPool;
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct RefreshToken {
+    pub id: i64,
+    /// Shared by every token in a chain of rotations
+    pub session_id: i64,
+    pub user_id: i64,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Session was started with "remember me"
+    pub remember_me: bool,
+    /// Client that last used the session
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    /// When the login that started this chain of rotated tokens happened
+    pub session_started_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl RefreshToken {
+    /// A refresh token can be exchanged only if it hasn't been revoked (or rotated) and hasn't expired
+    pub fn is_usable(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        self.revoked_at.is_none() && self.expires_at > now
+    }
+}
+
+/// Client details recorded with a session
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+pub async fn create(
+    pool: &PgPool,
+    user_id: i64,
+    token_hash: &str,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    remember_me: bool,
+    client: &ClientInfo,
+) -> Result<RefreshToken, sqlx::Error> {
+    sqlx::query_as::<_, RefreshToken>(
+        r#"
+        INSERT INTO refresh_tokens (user_id, token_hash, expires_at, remember_me, user_agent, ip_address)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, session_id, user_id, expires_at, revoked_at, created_at, remember_me,
+                  user_agent, ip_address, session_started_at
+        "#
+    )
+    .bind(user_id)
+    .bind(token_hash)
+    .bind(expires_at)
+    .bind(remember_me)
+    .bind(&client.user_agent)
+    .bind(&client.ip_address)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn get_by_hash(pool: &PgPool, token_hash: &str) -> Result<Option<RefreshToken>, sqlx::Error> {
+    sqlx::query_as::<_, RefreshToken>(
+        r#"
+        SELECT id, session_id, user_id, expires_at, revoked_at, created_at, remember_me,
+               user_agent, ip_address, session_started_at
+        FROM refresh_tokens
+        WHERE token_hash = $1
+        "#
+    )
+    .bind(token_hash)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Replace a refresh token with a new one, revoking the old token in the same transaction
+///
+/// The new token continues the old one's session, recording the client that refreshed it.
+pub async fn rotate(
+    pool: &PgPool,
+    old: &RefreshToken,
+    new_token_hash: &str,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    client: &ClientInfo,
+) -> Result<Option<RefreshToken>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    let new_token = sqlx::query_as::<_, RefreshToken>(
+        r#"
+        INSERT INTO refresh_tokens (user_id, token_hash, expires_at, remember_me, user_agent, ip_address,
+                                    session_started_at, session_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id, session_id, user_id, expires_at, revoked_at, created_at, remember_me,
+                  user_agent, ip_address, session_started_at
+        "#
+    )
+    .bind(old.user_id)
+    .bind(new_token_hash)
+    .bind(expires_at)
+    .bind(old.remember_me)
+    .bind(client.user_agent.as_ref().or(old.user_agent.as_ref()))
+    .bind(client.ip_address.as_ref().or(old.ip_address.as_ref()))
+    .bind(old.session_started_at)
+    .bind(old.session_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Only one concurrent rotation of the same token may win
+    let revoked = sqlx::query(
+        r#"
+        UPDATE refresh_tokens
+        SET revoked_at = NOW(), replaced_by = $2
+        WHERE id = $1 AND revoked_at IS NULL
+        "#
+    )
+    .bind(old.id)
+    .bind(new_token.id)
+    .execute(&mut *tx)
+    .await?;
+
+    if revoked.rows_affected() == 0 {
+        tx.rollback().await?;
+        return Ok(None);
+    }
+
+    tx.commit().await?;
+    Ok(Some(new_token))
+}
+
+pub async fn revoke(pool: &PgPool, token_hash: &str) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE token_hash = $1 AND revoked_at IS NULL"
+    )
+    .bind(token_hash)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn revoke_all_for_user(pool: &PgPool, user_id: i64) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE user_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(user_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Sessions a user can still refresh, most recently used first
+pub async fn get_active_for_user(pool: &PgPool, user_id: i64) -> Result<Vec<RefreshToken>, sqlx::Error> {
+    sqlx::query_as::<_, RefreshToken>(
+        r#"
+        SELECT id, session_id, user_id, expires_at, revoked_at, created_at, remember_me,
+               user_agent, ip_address, session_started_at
+        FROM refresh_tokens
+        WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY created_at DESC
+        "#
+    )
+    .bind(user_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Current token of a session, if it can still be refreshed
+pub async fn get_active_by_session(pool: &PgPool, session_id: i64) -> Result<Option<RefreshToken>, sqlx::Error> {
+    sqlx::query_as::<_, RefreshToken>(
+        r#"
+        SELECT id, session_id, user_id, expires_at, revoked_at, created_at, remember_me,
+               user_agent, ip_address, session_started_at
+        FROM refresh_tokens
+        WHERE session_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Revoke every token of a session, whichever one is current
+pub async fn revoke_session(pool: &PgPool, session_id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query(
+        "UPDATE refresh_tokens SET revoked_at = NOW() WHERE session_id = $1 AND revoked_at IS NULL"
+    )
+    .bind(session_id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
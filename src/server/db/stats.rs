@@ -1,15 +1,25 @@
 //! Statistics database operations
 
 use sqlx::PgPool;
-use crate::models::AgentStats;
+use crate::models::{
+    AgentPerformance, AgentStats, DispositionCount, Granularity, HistoryMetric, StatsBucket, StatsGroupBy,
+    StatsSummaryRow,
+};
+use crate::server::sentiment;
 
-pub async fn get_realtime(pool: &PgPool) -> Result<serde_json::Value, sqlx::Error> {
-    // Get active calls count
+/// Calls that are currently in progress
+pub async fn count_active_calls(pool: &PgPool) -> Result<i64, sqlx::Error> {
     let active_calls: (i64,) = sqlx::query_as(
         "SELECT COUNT(*) FROM calls WHERE status IN ('Initiated', 'Ringing', 'Answered', 'Bridged')"
     )
     .fetch_one(pool)
     .await?;
+    Ok(active_calls.0)
+}
+
+pub async fn get_realtime(pool: &PgPool) -> Result<serde_json::Value, sqlx::Error> {
+    // Get active calls count
+    let active_calls = count_active_calls(pool).await?;
 
     // Get ready agents count
     let ready_agents: (i64,) = sqlx::query_as(
@@ -33,7 +43,7 @@ pub async fn get_realtime(pool: &PgPool) -> Result<serde_json::Value, sqlx::Erro
     .await?;
 
     Ok(serde_json::json!({
-        "active_calls": active_calls.0,
+        "active_calls": active_calls,
         "ready_agents": ready_agents.0,
         "calls_today": calls_today.0,
         "avg_handle_time": avg_handle_time.0.unwrap_or(0.0)
@@ -57,6 +67,16 @@ pub async fn get_agent_stats(pool: &PgPool, agent_id: i64) -> Result<AgentStats,
     .fetch_one(pool)
     .await?;
 
+    let sentiments: Vec<f32> = sqlx::query_scalar(
+        r#"
+        SELECT sentiment FROM calls
+        WHERE agent_id = $1 AND DATE(started_at) = CURRENT_DATE AND sentiment IS NOT NULL
+        "#
+    )
+    .bind(agent_id)
+    .fetch_all(pool)
+    .await?;
+
     Ok(AgentStats {
         agent_id,
         total_calls: stats.0 as i32,
@@ -64,6 +84,7 @@ pub async fn get_agent_stats(pool: &PgPool, agent_id: i64) -> Result<AgentStats,
         missed_calls: stats.2 as i32,
         total_talk_time: stats.3 as i32,
         average_handle_time: stats.4,
+        sentiment_avg: sentiment::average(&sentiments),
     })
 }
 
@@ -93,3 +114,156 @@ pub async fn get_campaign_stats(pool: &PgPool, campaign_id: i64) -> Result<serde
         }
     }))
 }
+
+/// Window the campaign dial rate is measured over
+pub const DIAL_RATE_WINDOW_MINUTES: i64 = 30;
+
+/// Calls a campaign placed per hour over the last `DIAL_RATE_WINDOW_MINUTES`
+pub async fn get_dial_rate(pool: &PgPool, campaign_id: i64) -> Result<f64, sqlx::Error> {
+    let (calls,): (i64,) = sqlx::query_as(
+        r#"
+        SELECT COUNT(*)
+        FROM calls
+        WHERE campaign_id = $1
+          AND direction = 'Outbound'
+          AND started_at >= NOW() - make_interval(mins => $2::int)
+        "#
+    )
+    .bind(campaign_id)
+    .bind(DIAL_RATE_WINDOW_MINUTES as i32)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(calls as f64 * 60.0 / DIAL_RATE_WINDOW_MINUTES as f64)
+}
+
+/// Count dispositioned calls per code, optionally scoped to an agent and/or campaign
+pub async fn get_disposition_counts(
+    pool: &PgPool,
+    agent_id: Option<i64>,
+    campaign_id: Option<i64>,
+) -> Result<Vec<DispositionCount>, sqlx::Error> {
+    sqlx::query_as::<_, DispositionCount>(
+        r#"
+        SELECT d.id AS disposition_id, d.code, d.label, COUNT(c.id) AS count
+        FROM dispositions d
+        JOIN calls c ON c.disposition_id = d.id
+        WHERE ($1::bigint IS NULL OR c.agent_id = $1)
+          AND ($2::bigint IS NULL OR c.campaign_id = $2)
+        GROUP BY d.id, d.code, d.label
+        ORDER BY count DESC, d.label
+        "#
+    )
+    .bind(agent_id)
+    .bind(campaign_id)
+    .fetch_all(pool)
+    .await
+}
+
+/// Bucketed time series of a call metric between `from` and `to`
+pub async fn get_history(
+    pool: &PgPool,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    granularity: Granularity,
+    metric: HistoryMetric,
+    agent_id: Option<i64>,
+    campaign_id: Option<i64>,
+) -> Result<Vec<StatsBucket>, sqlx::Error> {
+    let value_expr = match metric {
+        HistoryMetric::Calls => "COUNT(*)",
+        HistoryMetric::Answered => "COUNT(*) FILTER (WHERE answered_at IS NOT NULL)",
+        HistoryMetric::TalkTime => "COALESCE(SUM(duration_seconds), 0)",
+    };
+
+    let query = format!(
+        r#"
+        SELECT date_trunc($1, started_at) AS bucket, ({})::bigint AS value
+        FROM calls
+        WHERE started_at >= $2 AND started_at <= $3
+          AND ($4::bigint IS NULL OR agent_id = $4)
+          AND ($5::bigint IS NULL OR campaign_id = $5)
+        GROUP BY bucket
+        ORDER BY bucket
+        "#,
+        value_expr
+    );
+
+    sqlx::query_as::<_, StatsBucket>(&query)
+        .bind(granularity.as_str())
+        .bind(from)
+        .bind(to)
+        .bind(agent_id)
+        .bind(campaign_id)
+        .fetch_all(pool)
+        .await
+}
+
+/// Call totals grouped by agent, campaign or day between `from` and `to`
+pub async fn get_summary(
+    pool: &PgPool,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    group_by: StatsGroupBy,
+) -> Result<Vec<StatsSummaryRow>, sqlx::Error> {
+    // Agents and campaigns are grouped by id, so two with the same name stay apart
+    let (group_expr, group_columns) = match group_by {
+        StatsGroupBy::Agent => ("COALESCE(a.name, 'Unassigned')", "a.id, a.name"),
+        StatsGroupBy::Campaign => ("COALESCE(cp.name, 'No campaign')", "cp.id, cp.name"),
+        StatsGroupBy::Day => ("to_char(date_trunc('day', c.started_at), 'YYYY-MM-DD')", "group_key"),
+    };
+
+    let query = format!(
+        r#"
+        SELECT
+            {} AS group_key,
+            COUNT(*) AS total_calls,
+            COUNT(*) FILTER (WHERE c.status = 'Completed' AND c.answered_at IS NOT NULL) AS answered_calls,
+            COALESCE(AVG(c.duration_seconds) FILTER (WHERE c.duration_seconds > 0), 0)::float8 AS avg_talk_time,
+            COUNT(*) FILTER (WHERE d.is_success) AS conversions
+        FROM calls c
+        LEFT JOIN agents a ON a.id = c.agent_id
+        LEFT JOIN campaigns cp ON cp.id = c.campaign_id
+        LEFT JOIN dispositions d ON d.id = c.disposition_id
+        WHERE c.started_at >= $1 AND c.started_at <= $2
+        GROUP BY {}
+        ORDER BY group_key
+        "#,
+        group_expr, group_columns
+    );
+
+    sqlx::query_as::<_, StatsSummaryRow>(&query)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+}
+
+/// Per-agent totals for calls started since `since`, used by the leaderboard
+pub async fn get_agent_performance(
+    pool: &PgPool,
+    since: chrono::DateTime<chrono::Utc>,
+) -> Result<Vec<AgentPerformance>, sqlx::Error> {
+    sqlx::query_as::<_, AgentPerformance>(
+        r#"
+        SELECT
+            a.id AS agent_id,
+            a.name AS agent_name,
+            COUNT(c.id) AS total_calls,
+            COUNT(c.id) FILTER (WHERE c.status = 'Completed' AND c.answered_at IS NOT NULL) AS answered_calls,
+            COUNT(c.id) FILTER (WHERE c.status IN ('NoAnswer', 'Busy', 'Failed')) AS missed_calls,
+            COALESCE(SUM(c.duration_seconds), 0)::bigint AS total_talk_time,
+            COALESCE(AVG(c.duration_seconds) FILTER (WHERE c.duration_seconds > 0), 0)::float8 AS average_handle_time,
+            COUNT(c.id) FILTER (WHERE d.is_success) AS conversions,
+            AVG(c.sentiment)::float8 AS sentiment_avg
+        FROM agents a
+        JOIN calls c ON c.agent_id = a.id AND c.started_at >= $1
+        LEFT JOIN dispositions d ON d.id = c.disposition_id
+        GROUP BY a.id, a.name
+        ORDER BY a.id
+        "#
+    )
+    .bind(since)
+    .fetch_all(pool)
+    .await
+}
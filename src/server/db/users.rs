@@ -6,7 +6,7 @@ use crate::models::{User, UserRole};
 pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<User>, sqlx::Error> {
     sqlx::query_as::<_, User>(
         r#"
-        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified
+        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified, active
         FROM users
         WHERE id = $1
         "#
@@ -19,7 +19,7 @@ pub async fn get_by_id(pool: &PgPool, id: i64) -> Result<Option<User>, sqlx::Err
 pub async fn get_by_username(pool: &PgPool, username: &str) -> Result<Option<User>, sqlx::Error> {
     sqlx::query_as::<_, User>(
         r#"
-        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified
+        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified, active
         FROM users
         WHERE username = $1
         "#
@@ -40,7 +40,7 @@ pub async fn create(
         r#"
         INSERT INTO users (username, email, password_hash, role)
         VALUES ($1, $2, $3, $4)
-        RETURNING id, username, email, role, first_name, last_name, password_hash, email_verified
+        RETURNING id, username, email, role, first_name, last_name, password_hash, email_verified, active
         "#
     )
     .bind(username)
@@ -63,7 +63,7 @@ pub async fn update_password(pool: &PgPool, id: i64, password_hash: &str) -> Res
 pub async fn get_by_email(pool: &PgPool, email: &str) -> Result<Option<User>, sqlx::Error> {
     sqlx::query_as::<_, User>(
         r#"
-        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified
+        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified, active
         FROM users
         WHERE email = $1
         "#
@@ -83,12 +83,27 @@ pub async fn check_email_verified(pool: &PgPool, user_id: i64) -> Result<bool, s
     Ok(result.0)
 }
 
+/// Store a new verification token. Links from the user's older, unused
+/// tokens stop working.
 pub async fn create_verification_token(
     pool: &PgPool,
     user_id: i64,
     email: &str,
     token: &str,
 ) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        r#"
+        UPDATE verification_tokens
+        SET superseded_at = NOW()
+        WHERE user_id = $1 AND used_at IS NULL AND superseded_at IS NULL
+        "#
+    )
+    .bind(user_id)
+    .execute(&mut *tx)
+    .await?;
+
     sqlx::query(
         r#"
         INSERT INTO verification_tokens (token, user_id, email, expires_at)
@@ -98,8 +113,39 @@ pub async fn create_verification_token(
     .bind(token)
     .bind(user_id)
     .bind(email)
-    .execute(pool)
+    .execute(&mut *tx)
     .await?;
+
+    tx.commit().await
+}
+
+/// The latest token for an email that was created in the last hour and can
+/// still be used, to send again instead of issuing a new one
+pub async fn get_resendable_verification_token(pool: &PgPool, email: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT token
+        FROM verification_tokens
+        WHERE email = $1
+          AND created_at > NOW() - INTERVAL '1 hour'
+          AND expires_at > NOW()
+          AND used_at IS NULL
+          AND superseded_at IS NULL
+        ORDER BY created_at DESC
+        LIMIT 1
+        "#
+    )
+    .bind(email)
+    .fetch_optional(pool)
+    .await
+}
+
+/// Count another email sent with an existing token
+pub async fn record_verification_resend(pool: &PgPool, token: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE verification_tokens SET send_count = send_count + 1 WHERE token = $1")
+        .bind(token)
+        .execute(pool)
+        .await?;
     Ok(())
 }
 
@@ -109,12 +155,13 @@ struct VerificationToken {
     email: String,
     expires_at: chrono::DateTime<chrono::Utc>,
     used_at: Option<chrono::DateTime<chrono::Utc>>,
+    superseded_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 pub async fn get_verification_token(pool: &PgPool, token: &str) -> Result<Option<(i64, String, bool)>, sqlx::Error> {
     let result: Option<VerificationToken> = sqlx::query_as(
         r#"
-        SELECT user_id, email, expires_at, used_at
+        SELECT user_id, email, expires_at, used_at, superseded_at
         FROM verification_tokens
         WHERE token = $1
         "#
@@ -124,7 +171,7 @@ pub async fn get_verification_token(pool: &PgPool, token: &str) -> Result<Option
     .await?;
 
     Ok(result.map(|vt| {
-        let is_valid = vt.used_at.is_none() && vt.expires_at > chrono::Utc::now();
+        let is_valid = vt.used_at.is_none() && vt.superseded_at.is_none() && vt.expires_at > chrono::Utc::now();
         (vt.user_id, vt.email, is_valid)
     }))
 }
@@ -149,12 +196,12 @@ pub async fn verify_email(pool: &PgPool, user_id: i64) -> Result<(), sqlx::Error
     Ok(())
 }
 
-/// Count verification tokens created in the last hour for an email
-/// Used for rate limiting resend verification requests
+/// Count verification emails sent in the last hour for an email, including
+/// resends of the same token. Used for rate limiting resend verification requests
 pub async fn count_recent_verification_tokens(pool: &PgPool, email: &str) -> Result<i64, sqlx::Error> {
     let result: (i64,) = sqlx::query_as(
         r#"
-        SELECT COUNT(*)
+        SELECT COALESCE(SUM(send_count), 0)::bigint
         FROM verification_tokens
         WHERE email = $1 AND created_at > NOW() - INTERVAL '1 hour'
         "#
@@ -164,3 +211,62 @@ pub async fn count_recent_verification_tokens(pool: &PgPool, email: &str) -> Res
     .await?;
     Ok(result.0)
 }
+
+/// List users ordered by id, one page at a time
+pub async fn list(pool: &PgPool, limit: i64, offset: i64) -> Result<Vec<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        SELECT id, username, email, role, first_name, last_name, password_hash, email_verified, active
+        FROM users
+        ORDER BY id
+        LIMIT $1 OFFSET $2
+        "#
+    )
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await
+}
+
+pub async fn count(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    let result: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+        .fetch_one(pool)
+        .await?;
+    Ok(result.0)
+}
+
+pub async fn update_role(pool: &PgPool, id: i64, role: UserRole) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users SET role = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, username, email, role, first_name, last_name, password_hash, email_verified, active
+        "#
+    )
+    .bind(id)
+    .bind(role)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn set_active(pool: &PgPool, id: i64, active: bool) -> Result<Option<User>, sqlx::Error> {
+    sqlx::query_as::<_, User>(
+        r#"
+        UPDATE users SET active = $2, updated_at = NOW()
+        WHERE id = $1
+        RETURNING id, username, email, role, first_name, last_name, password_hash, email_verified, active
+        "#
+    )
+    .bind(id)
+    .bind(active)
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn delete(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,107 @@
+//! Webhook idempotency tracking
+//!
+//! Telnyx delivers webhooks at least once, so the same event id can arrive
+//! more than once. Each event id is recorded before it is handled and repeat
+//! deliveries are skipped.
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+/// Storage for the ids of webhook events that have already been handled
+#[async_trait]
+pub trait ProcessedEventStore {
+    /// Record the event id. Returns `false` if it had already been recorded.
+    async fn mark_processed(&self, event_id: &str, event_type: &str) -> Result<bool, sqlx::Error>;
+}
+
+#[async_trait]
+impl ProcessedEventStore for PgPool {
+    async fn mark_processed(&self, event_id: &str, event_type: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO processed_webhook_events (event_id, event_type)
+            VALUES ($1, $2)
+            ON CONFLICT (event_id) DO NOTHING
+            "#
+        )
+        .bind(event_id)
+        .bind(event_type)
+        .execute(self)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
+
+/// Whether an event should be handled. Events without an id are always handled, and a
+/// storage error fails open so a database hiccup never drops a webhook.
+pub async fn is_first_delivery<S: ProcessedEventStore + ?Sized>(
+    store: &S,
+    event_id: Option<&str>,
+    event_type: &str,
+) -> bool {
+    let Some(event_id) = event_id else {
+        return true;
+    };
+
+    match store.mark_processed(event_id, event_type).await {
+        Ok(first) => first,
+        Err(e) => {
+            tracing::warn!("Failed to record webhook event {}: {}", event_id, e);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        seen: Mutex<HashSet<String>>,
+    }
+
+    #[async_trait]
+    impl ProcessedEventStore for MemoryStore {
+        async fn mark_processed(&self, event_id: &str, _event_type: &str) -> Result<bool, sqlx::Error> {
+            Ok(self.seen.lock().await.insert(event_id.to_string()))
+        }
+    }
+
+    struct FailingStore;
+
+    #[async_trait]
+    impl ProcessedEventStore for FailingStore {
+        async fn mark_processed(&self, _event_id: &str, _event_type: &str) -> Result<bool, sqlx::Error> {
+            Err(sqlx::Error::PoolTimedOut)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_event_is_noop() {
+        let store = MemoryStore::default();
+        let status_updates = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            if is_first_delivery(&store, Some("evt-1"), "call.hangup").await {
+                status_updates.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+
+        assert_eq!(status_updates.load(Ordering::SeqCst), 1);
+        assert!(is_first_delivery(&store, Some("evt-2"), "call.hangup").await);
+    }
+
+    #[tokio::test]
+    async fn test_events_without_id_or_store_errors_are_processed() {
+        let store = MemoryStore::default();
+        assert!(is_first_delivery(&store, None, "call.answered").await);
+        assert!(is_first_delivery(&store, None, "call.answered").await);
+
+        assert!(is_first_delivery(&FailingStore, Some("evt-1"), "call.hangup").await);
+    }
+}
@@ -3,6 +3,10 @@
 //! This module provides email functionality using SMTP via the lettre crate.
 //! It supports sending HTML emails for user registration verification and
 //! team invitations.
+//!
+//! With an outbox configured, emails are queued in the database and a
+//! background worker delivers them, retrying with backoff while the SMTP
+//! server is unavailable, so a brief outage doesn't lose a verification email.
 
 use lettre::{
     message::{header::ContentType, Mailbox, Message},
@@ -12,7 +16,28 @@ use lettre::{
     },
     AsyncSmtpTransport, AsyncTransport, Tokio1Executor,
 };
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
 use thiserror::Error;
+use tokio::task::JoinHandle;
+
+use crate::models::StatsSummaryRow;
+use super::db;
+
+/// Delivery attempts before a queued email is marked failed
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+
+/// Wait before the first retry; doubles with each further attempt
+const RETRY_BASE_DELAY_SECONDS: i64 = 30;
+
+/// Longest wait between retries
+const RETRY_MAX_DELAY_SECONDS: i64 = 3600;
+
+/// How often the outbox worker looks for due emails
+const OUTBOX_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Emails claimed per outbox poll
+const OUTBOX_BATCH_SIZE: i64 = 20;
 
 /// Email service for sending verification and invitation emails
 #[derive(Clone)]
@@ -21,6 +46,46 @@ pub struct EmailService {
     from_email: Mailbox,
     from_name: String,
     app_url: String,
+    /// Queue emails here for the outbox worker instead of sending them inline
+    outbox: Option<PgPool>,
+}
+
+/// A rendered email, ready to send or queue
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutgoingEmail {
+    pub to_email: String,
+    pub to_name: Option<String>,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+/// What to do with a queued email after a delivery attempt
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeliveryOutcome {
+    Sent,
+    Retry { next_attempt_at: DateTime<Utc>, error: String },
+    Failed { error: String },
+}
+
+/// Wait before retrying an email that has failed `attempts` times
+pub fn retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    Duration::seconds((RETRY_BASE_DELAY_SECONDS << doublings).min(RETRY_MAX_DELAY_SECONDS))
+}
+
+/// Outcome of the `attempts`-th delivery attempt. Send failures are retried
+/// until the attempts run out; an email that can't be built or addressed
+/// never will be, so it fails straight away.
+pub fn delivery_outcome(result: &Result<(), EmailError>, attempts: i32, now: DateTime<Utc>) -> DeliveryOutcome {
+    match result {
+        Ok(()) => DeliveryOutcome::Sent,
+        Err(e @ EmailError::SendFailed(_)) if attempts < MAX_DELIVERY_ATTEMPTS => DeliveryOutcome::Retry {
+            next_attempt_at: now + retry_delay(attempts),
+            error: e.to_string(),
+        },
+        Err(e) => DeliveryOutcome::Failed { error: e.to_string() },
+    }
 }
 
 /// Errors that can occur when sending emails
@@ -37,6 +102,61 @@ pub enum EmailError {
 
     #[error("SMTP configuration error: {0}")]
     ConfigError(String),
+
+    #[error("Failed to queue email: {0}")]
+    Queue(String),
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpEncryption {
+    /// TLS required on connect (typically port 465)
+    Tls,
+    /// Plain connection upgraded with STARTTLS (typically port 587)
+    StartTls,
+    /// No encryption - local development only
+    None,
+}
+
+impl SmtpEncryption {
+    /// Parse an `SMTP_ENCRYPTION` value. `none` is only accepted when `allow_insecure` is set.
+    pub fn parse(value: &str, allow_insecure: bool) -> Result<Self, EmailError> {
+        match value.trim().to_lowercase().as_str() {
+            "" | "tls" => Ok(SmtpEncryption::Tls),
+            "starttls" => Ok(SmtpEncryption::StartTls),
+            "none" if allow_insecure => Ok(SmtpEncryption::None),
+            "none" => Err(EmailError::ConfigError(
+                "SMTP_ENCRYPTION=none requires ALLOW_INSECURE_SMTP=true".to_string(),
+            )),
+            other => Err(EmailError::ConfigError(format!(
+                "Invalid SMTP_ENCRYPTION '{}': expected starttls, tls or none",
+                other
+            ))),
+        }
+    }
+
+    /// Read `SMTP_ENCRYPTION` (default `tls`) and `ALLOW_INSECURE_SMTP`
+    pub fn from_env() -> Result<Self, EmailError> {
+        let value = std::env::var("SMTP_ENCRYPTION").unwrap_or_default();
+        let allow_insecure = std::env::var("ALLOW_INSECURE_SMTP")
+            .map(|v| v.eq_ignore_ascii_case("true") || v == "1")
+            .unwrap_or(false);
+        Self::parse(&value, allow_insecure)
+    }
+}
+
+/// SMTP server and sender settings
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    pub from_email: String,
+    pub from_name: String,
+    /// Base URL for links in emails
+    pub app_url: String,
+    pub encryption: SmtpEncryption,
 }
 
 impl EmailService {
@@ -50,79 +170,105 @@ impl EmailService {
     /// - SMTP_FROM_EMAIL: From email address
     /// - SMTP_FROM_NAME: From name (optional, defaults to "VoIP CRM")
     /// - APP_URL: Base URL for the application (for generating links)
+    /// - SMTP_ENCRYPTION: tls (default), starttls, or none (requires ALLOW_INSECURE_SMTP=true)
     pub fn from_env() -> Result<Self, EmailError> {
-        let smtp_host = std::env::var("SMTP_HOST")
+        let host = std::env::var("SMTP_HOST")
             .map_err(|_| EmailError::ConfigError("SMTP_HOST not set".to_string()))?;
 
-        let smtp_port = std::env::var("SMTP_PORT")
+        let port = std::env::var("SMTP_PORT")
             .map_err(|_| EmailError::ConfigError("SMTP_PORT not set".to_string()))?
             .parse::<u16>()
             .map_err(|_| EmailError::ConfigError("SMTP_PORT must be a valid port number".to_string()))?;
 
-        let smtp_username = std::env::var("SMTP_USERNAME")
+        let username = std::env::var("SMTP_USERNAME")
             .map_err(|_| EmailError::ConfigError("SMTP_USERNAME not set".to_string()))?;
 
-        let smtp_password = std::env::var("SMTP_PASSWORD")
+        let password = std::env::var("SMTP_PASSWORD")
             .map_err(|_| EmailError::ConfigError("SMTP_PASSWORD not set".to_string()))?;
 
-        let smtp_from_email = std::env::var("SMTP_FROM_EMAIL")
+        let from_email = std::env::var("SMTP_FROM_EMAIL")
             .map_err(|_| EmailError::ConfigError("SMTP_FROM_EMAIL not set".to_string()))?;
 
-        let smtp_from_name = std::env::var("SMTP_FROM_NAME")
+        let from_name = std::env::var("SMTP_FROM_NAME")
             .unwrap_or_else(|_| "VoIP CRM".to_string());
 
         let app_url = std::env::var("APP_URL")
             .map_err(|_| EmailError::ConfigError("APP_URL not set".to_string()))?;
 
-        Self::new(
-            &smtp_host,
-            smtp_port,
-            &smtp_username,
-            &smtp_password,
-            &smtp_from_email,
-            &smtp_from_name,
-            &app_url,
-        )
+        let encryption = SmtpEncryption::from_env()?;
+
+        Self::new(&SmtpConfig { host, port, username, password, from_email, from_name, app_url, encryption })
     }
 
     /// Create a new email service with explicit configuration
-    pub fn new(
-        smtp_host: &str,
-        smtp_port: u16,
-        smtp_username: &str,
-        smtp_password: &str,
-        from_email: &str,
-        from_name: &str,
-        app_url: &str,
-    ) -> Result<Self, EmailError> {
+    pub fn new(config: &SmtpConfig) -> Result<Self, EmailError> {
+        let smtp_host = config.host.as_str();
+        let smtp_port = config.port;
+
         // Parse the from email address
-        let from_mailbox: Mailbox = format!("{} <{}>", from_name, from_email)
+        let from_mailbox: Mailbox = format!("{} <{}>", config.from_name, config.from_email)
             .parse()
             .map_err(|e| EmailError::InvalidAddress(format!("Invalid from address: {}", e)))?;
 
-        // Configure TLS
-        let tls_parameters = TlsParameters::builder(smtp_host.to_string())
-            .build()
-            .map_err(|e| EmailError::ConfigError(format!("Failed to build TLS parameters: {}", e)))?;
+        let credentials = Credentials::new(config.username.clone(), config.password.clone());
 
-        // Build SMTP transport
-        let credentials = Credentials::new(smtp_username.to_string(), smtp_password.to_string());
+        // Build SMTP transport for the configured encryption mode
+        let mailer = match config.encryption {
+            SmtpEncryption::Tls => {
+                let tls_parameters = TlsParameters::builder(smtp_host.to_string())
+                    .build()
+                    .map_err(|e| EmailError::ConfigError(format!("Failed to build TLS parameters: {}", e)))?;
 
-        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)
-            .map_err(|e| EmailError::ConfigError(format!("Failed to create SMTP transport: {}", e)))?
-            .port(smtp_port)
-            .credentials(credentials)
-            .tls(Tls::Required(tls_parameters))
-            .build();
+                AsyncSmtpTransport::<Tokio1Executor>::relay(smtp_host)
+                    .map_err(|e| EmailError::ConfigError(format!("Failed to create SMTP transport: {}", e)))?
+                    .port(smtp_port)
+                    .credentials(credentials)
+                    .tls(Tls::Required(tls_parameters))
+                    .build()
+            }
+            SmtpEncryption::StartTls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)
+                    .map_err(|e| EmailError::ConfigError(format!("Failed to create SMTP transport: {}", e)))?
+                    .port(smtp_port)
+                    .credentials(credentials)
+                    .build()
+            }
+            SmtpEncryption::None => {
+                let builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(smtp_host)
+                    .port(smtp_port);
+                // Local dev servers (e.g. MailHog) usually don't require auth
+                if config.username.is_empty() {
+                    builder.build()
+                } else {
+                    builder.credentials(credentials).build()
+                }
+            }
+        };
 
         Ok(Self {
             mailer,
             from_email: from_mailbox,
-            from_name: from_name.to_string(),
-            app_url: app_url.trim_end_matches('/').to_string(),
+            from_name: config.from_name.clone(),
+            app_url: config.app_url.trim_end_matches('/').to_string(),
+            outbox: None,
         })
     }
 
+    /// Queue emails in the database outbox rather than sending them inline
+    pub fn with_outbox(mut self, pool: PgPool) -> Self {
+        self.outbox = Some(pool);
+        self
+    }
+
+    /// Open a connection to the SMTP server to check it is reachable and accepts our credentials
+    pub async fn check_connection(&self) -> Result<(), EmailError> {
+        match self.mailer.test_connection().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(EmailError::SendFailed("SMTP server rejected the connection".to_string())),
+            Err(e) => Err(EmailError::SendFailed(e.to_string())),
+        }
+    }
+
     /// Send a verification email to a new user
     ///
     /// # Arguments
@@ -143,8 +289,14 @@ impl EmailService {
         let html_body = self.build_verification_email_html(display_name, &verification_url);
         let text_body = self.build_verification_email_text(display_name, &verification_url);
 
-        self.send_email(to_email, to_name, subject, &html_body, &text_body)
-            .await
+        self.deliver(OutgoingEmail {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(str::to_string),
+            subject: subject.to_string(),
+            html_body,
+            text_body,
+        })
+        .await
     }
 
     /// Send an invitation email to a new team member
@@ -168,19 +320,122 @@ impl EmailService {
         let html_body = self.build_invitation_email_html(inviter_name, role, &invitation_url);
         let text_body = self.build_invitation_email_text(inviter_name, role, &invitation_url);
 
-        self.send_email(to_email, None, &subject, &html_body, &text_body)
-            .await
+        self.deliver(OutgoingEmail {
+            to_email: to_email.to_string(),
+            to_name: None,
+            subject,
+            html_body,
+            text_body,
+        })
+        .await
     }
 
-    /// Internal method to send an email with both HTML and plain text versions
-    async fn send_email(
+    /// Tell an agent a lead was assigned to them
+    pub async fn send_lead_assigned_email(
+        &self,
+        to_email: &str,
+        to_name: Option<&str>,
+        lead_id: i64,
+        lead_name: &str,
+    ) -> Result<(), EmailError> {
+        let lead_url = format!("{}/leads/{}", self.app_url, lead_id);
+        let subject = format!("New lead assigned: {}", lead_name);
+
+        let html_body = format!(
+            r#"<p>Hi {},</p>
+<p><strong>{}</strong> has been assigned to you.</p>
+<p><a href="{}">Open the lead</a></p>"#,
+            to_name.unwrap_or("there"),
+            lead_name,
+            lead_url
+        );
+        let text_body = format!(
+            "Hi {},\n\n{} has been assigned to you.\n\nOpen the lead: {}",
+            to_name.unwrap_or("there"),
+            lead_name,
+            lead_url
+        );
+
+        self.deliver(OutgoingEmail {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(str::to_string),
+            subject,
+            html_body,
+            text_body,
+        })
+        .await
+    }
+
+    /// Tell an agent several leads were assigned to them at once, in one email
+    pub async fn send_leads_assigned_email(
         &self,
         to_email: &str,
         to_name: Option<&str>,
-        subject: &str,
-        html_body: &str,
-        text_body: &str,
+        leads: &[(i64, String)],
     ) -> Result<(), EmailError> {
+        let subject = format!("{} new leads assigned", leads.len());
+        let html_body = leads_assigned_html(&self.app_url, to_name, leads);
+        let text_body = leads_assigned_text(&self.app_url, to_name, leads);
+
+        self.deliver(OutgoingEmail {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(str::to_string),
+            subject,
+            html_body,
+            text_body,
+        })
+        .await
+    }
+
+    /// Send the previous day's call totals, one row per agent
+    pub async fn send_daily_report_email(
+        &self,
+        to_email: &str,
+        to_name: Option<&str>,
+        day: chrono::NaiveDate,
+        rows: &[StatsSummaryRow],
+    ) -> Result<(), EmailError> {
+        let subject = format!("Daily call report for {}", day);
+        let html_body = daily_report_html(day, rows);
+        let text_body = daily_report_text(day, rows);
+
+        self.deliver(OutgoingEmail {
+            to_email: to_email.to_string(),
+            to_name: to_name.map(str::to_string),
+            subject,
+            html_body,
+            text_body,
+        })
+        .await
+    }
+
+    /// Queue the email in the outbox if there is one, otherwise send it now
+    async fn deliver(&self, email: OutgoingEmail) -> Result<(), EmailError> {
+        match &self.outbox {
+            Some(pool) => {
+                let id = db::email_outbox::enqueue(pool, &email)
+                    .await
+                    .map_err(|e| EmailError::Queue(e.to_string()))?;
+                tracing::debug!("Queued email {} to {}", id, email.to_email);
+                Ok(())
+            }
+            None => self.send_email(&email).await,
+        }
+    }
+
+    /// Make the `attempts`-th attempt to deliver a queued email
+    pub async fn attempt(&self, email: &OutgoingEmail, attempts: i32, now: DateTime<Utc>) -> DeliveryOutcome {
+        let result = self.send_email(email).await;
+        if let Err(e) = &result {
+            tracing::warn!("Delivery attempt {} to {} failed: {}", attempts, email.to_email, e);
+        }
+        delivery_outcome(&result, attempts, now)
+    }
+
+    /// Internal method to send an email with both HTML and plain text versions
+    async fn send_email(&self, email: &OutgoingEmail) -> Result<(), EmailError> {
+        let OutgoingEmail { to_email, to_name, subject, html_body, .. } = email;
+
         // Parse the recipient email address
         let to_mailbox: Mailbox = if let Some(name) = to_name {
             format!("{} <{}>", name, to_email)
@@ -194,9 +449,9 @@ impl EmailService {
         let email = Message::builder()
             .from(self.from_email.clone())
             .to(to_mailbox)
-            .subject(subject)
+            .subject(subject.as_str())
             .header(ContentType::TEXT_HTML)
-            .body(html_body.to_string())
+            .body(html_body.clone())
             .map_err(|e| EmailError::MessageBuild(e.to_string()))?;
 
         // Send the email
@@ -447,9 +702,110 @@ VoIP CRM
     }
 }
 
+/// Attempt every due email in the outbox once, returning how many were sent
+pub async fn deliver_due(service: &EmailService, pool: &PgPool) -> Result<usize, sqlx::Error> {
+    let due = db::email_outbox::claim_due(pool, OUTBOX_BATCH_SIZE).await?;
+
+    let mut sent = 0;
+    for queued in due {
+        let outcome = service.attempt(&queued.email, queued.attempts, Utc::now()).await;
+        if outcome == DeliveryOutcome::Sent {
+            sent += 1;
+        } else if let DeliveryOutcome::Failed { error } = &outcome {
+            tracing::error!("Giving up on email {} to {}: {}", queued.id, queued.email.to_email, error);
+        }
+        db::email_outbox::record_outcome(pool, queued.id, &outcome).await?;
+    }
+    Ok(sent)
+}
+
+/// Deliver queued emails in the background
+pub fn spawn_outbox_worker(service: EmailService, pool: PgPool) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match deliver_due(&service, &pool).await {
+                Ok(0) => {}
+                Ok(sent) => tracing::info!("Delivered {} queued email(s)", sent),
+                Err(e) => tracing::error!("Email outbox error: {}", e),
+            }
+            tokio::time::sleep(OUTBOX_POLL_INTERVAL).await;
+        }
+    })
+}
+
+/// HTML list of leads just assigned to an agent, each linked
+fn leads_assigned_html(app_url: &str, to_name: Option<&str>, leads: &[(i64, String)]) -> String {
+    let items: String = leads
+        .iter()
+        .map(|(id, name)| format!("<li><a href=\"{}/leads/{}\">{}</a></li>\n", app_url, id, name))
+        .collect();
+
+    format!(
+        "<p>Hi {},</p>\n<p>{} leads have been assigned to you:</p>\n<ul>\n{}</ul>",
+        to_name.unwrap_or("there"),
+        leads.len(),
+        items
+    )
+}
+
+/// Plain text version of `leads_assigned_html`
+fn leads_assigned_text(app_url: &str, to_name: Option<&str>, leads: &[(i64, String)]) -> String {
+    let mut text = format!("Hi {},\n\n{} leads have been assigned to you:\n\n", to_name.unwrap_or("there"), leads.len());
+    for (id, name) in leads {
+        text.push_str(&format!("{}: {}/leads/{}\n", name, app_url, id));
+    }
+    text
+}
+
+/// HTML table of a day's call totals
+fn daily_report_html(day: chrono::NaiveDate, rows: &[StatsSummaryRow]) -> String {
+    if rows.is_empty() {
+        return format!("<p>No calls were made on {}.</p>", day);
+    }
+
+    let body: String = rows
+        .iter()
+        .map(|row| {
+            format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.0}s</td><td>{}</td></tr>",
+                row.group_key, row.total_calls, row.answered_calls, row.avg_talk_time, row.conversions
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<h2>Calls on {}</h2>
+<table cellpadding="6">
+<tr><th align="left">Agent</th><th>Calls</th><th>Answered</th><th>Avg talk time</th><th>Conversions</th></tr>
+{}
+</table>"#,
+        day, body
+    )
+}
+
+/// Plain text version of `daily_report_html`
+fn daily_report_text(day: chrono::NaiveDate, rows: &[StatsSummaryRow]) -> String {
+    if rows.is_empty() {
+        return format!("No calls were made on {}.", day);
+    }
+
+    let mut text = format!("Calls on {}\n\n", day);
+    for row in rows {
+        text.push_str(&format!(
+            "{}: {} calls, {} answered, {:.0}s average talk time, {} conversions\n",
+            row.group_key, row.total_calls, row.answered_calls, row.avg_talk_time, row.conversions
+        ));
+    }
+    text
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
 
     // Test the HTML building functions directly without creating an SMTP transport.
     // We duplicate the function logic here since the methods on EmailService require
@@ -508,4 +864,191 @@ mod tests {
         assert!(html.contains("Agent"));
         assert!(html.contains("xyz789"));
     }
+
+    #[test]
+    fn test_daily_report_lists_each_agent() {
+        let day = chrono::NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let rows = vec![StatsSummaryRow {
+            group_key: "Alice".to_string(),
+            total_calls: 12,
+            answered_calls: 9,
+            avg_talk_time: 95.4,
+            conversions: 2,
+        }];
+
+        let text = daily_report_text(day, &rows);
+        assert!(text.contains("Calls on 2024-03-04"));
+        assert!(text.contains("Alice: 12 calls, 9 answered, 95s average talk time, 2 conversions"));
+        assert!(daily_report_html(day, &rows).contains("<td>Alice</td><td>12</td>"));
+
+        assert_eq!(daily_report_text(day, &[]), "No calls were made on 2024-03-04.");
+    }
+
+    #[test]
+    fn test_leads_assigned_lists_every_lead() {
+        let leads = vec![(3, "Ada Lovelace".to_string()), (8, "Alan Turing".to_string())];
+
+        let text = leads_assigned_text("https://crm.example.com", Some("Alice"), &leads);
+        assert!(text.starts_with("Hi Alice,\n\n2 leads have been assigned to you:"));
+        assert!(text.contains("Ada Lovelace: https://crm.example.com/leads/3\n"));
+        assert!(text.contains("Alan Turing: https://crm.example.com/leads/8\n"));
+
+        let html = leads_assigned_html("https://crm.example.com", None, &leads);
+        assert!(html.contains("Hi there,"));
+        assert!(html.contains(r#"<a href="https://crm.example.com/leads/8">Alan Turing</a>"#));
+    }
+
+    #[test]
+    fn test_parse_smtp_encryption() {
+        assert_eq!(SmtpEncryption::parse("tls", false).unwrap(), SmtpEncryption::Tls);
+        assert_eq!(SmtpEncryption::parse("", false).unwrap(), SmtpEncryption::Tls);
+        assert_eq!(SmtpEncryption::parse("STARTTLS", false).unwrap(), SmtpEncryption::StartTls);
+        assert_eq!(SmtpEncryption::parse("none", true).unwrap(), SmtpEncryption::None);
+        assert!(SmtpEncryption::parse("ssl3", true).is_err());
+    }
+
+    #[test]
+    fn test_insecure_smtp_requires_flag() {
+        assert!(matches!(
+            SmtpEncryption::parse("none", false),
+            Err(EmailError::ConfigError(_))
+        ));
+    }
+
+    fn outgoing() -> OutgoingEmail {
+        OutgoingEmail {
+            to_email: "alice@example.com".to_string(),
+            to_name: Some("Alice".to_string()),
+            subject: "Verify Your Email - VoIP CRM".to_string(),
+            html_body: "<p>Verify</p>".to_string(),
+            text_body: "Verify".to_string(),
+        }
+    }
+
+    /// SMTP server that turns away the first `busy` connections with a 421
+    /// and accepts mail on the rest, counting the messages delivered
+    fn smtp_config(port: u16, encryption: SmtpEncryption) -> SmtpConfig {
+        SmtpConfig {
+            host: "smtp.example.com".to_string(),
+            port,
+            username: "user".to_string(),
+            password: "password".to_string(),
+            from_email: "noreply@example.com".to_string(),
+            from_name: "VoIP CRM".to_string(),
+            app_url: "https://example.com/".to_string(),
+            encryption,
+        }
+    }
+
+    async fn mock_smtp(busy: usize) -> (u16, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let delivered = Arc::new(AtomicUsize::new(0));
+
+        let counter = delivered.clone();
+        tokio::spawn(async move {
+            let mut connections = 0;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                connections += 1;
+                if connections <= busy {
+                    let _ = socket.write_all(b"421 mock busy, try again later\r\n").await;
+                    continue;
+                }
+
+                let counter = counter.clone();
+                tokio::spawn(async move {
+                    let (read, mut write) = socket.split();
+                    let mut lines = BufReader::new(read);
+                    let _ = write.write_all(b"220 mock ESMTP\r\n").await;
+
+                    let mut in_data = false;
+                    let mut line = String::new();
+                    while lines.read_line(&mut line).await.unwrap_or(0) > 0 {
+                        let reply: &[u8] = if in_data {
+                            if line == ".\r\n" {
+                                in_data = false;
+                                counter.fetch_add(1, Ordering::SeqCst);
+                                b"250 queued\r\n"
+                            } else {
+                                b""
+                            }
+                        } else {
+                            match line.get(..4).map(str::to_ascii_uppercase).as_deref() {
+                                Some("EHLO") => b"250 mock\r\n",
+                                Some("DATA") => {
+                                    in_data = true;
+                                    b"354 end with .\r\n"
+                                }
+                                Some("QUIT") => {
+                                    let _ = write.write_all(b"221 bye\r\n").await;
+                                    break;
+                                }
+                                _ => b"250 OK\r\n",
+                            }
+                        };
+                        let _ = write.write_all(reply).await;
+                        line.clear();
+                    }
+                });
+            }
+        });
+
+        (port, delivered)
+    }
+
+    #[tokio::test]
+    async fn test_transient_failure_is_retried_then_sent() {
+        let (port, delivered) = mock_smtp(1).await;
+        let service = EmailService::new(&SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            username: String::new(),
+            password: String::new(),
+            app_url: "https://example.com".to_string(),
+            ..smtp_config(port, SmtpEncryption::None)
+        })
+        .unwrap();
+        let now = Utc::now();
+
+        // The server is busy: the email stays queued for a retry
+        match service.attempt(&outgoing(), 1, now).await {
+            DeliveryOutcome::Retry { next_attempt_at, .. } => {
+                assert_eq!(next_attempt_at, now + Duration::seconds(30));
+            }
+            other => panic!("expected a retry, got {:?}", other),
+        }
+        assert_eq!(delivered.load(Ordering::SeqCst), 0);
+
+        // The retry goes through and the email is marked sent
+        assert_eq!(service.attempt(&outgoing(), 2, now).await, DeliveryOutcome::Sent);
+        assert_eq!(delivered.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retries_back_off_then_give_up() {
+        let now = Utc::now();
+        let busy = Err(EmailError::SendFailed("421 busy".to_string()));
+
+        assert_eq!(retry_delay(1), Duration::seconds(30));
+        assert_eq!(retry_delay(3), Duration::seconds(120));
+        assert_eq!(retry_delay(20), Duration::seconds(3600));
+
+        assert!(matches!(delivery_outcome(&busy, MAX_DELIVERY_ATTEMPTS - 1, now), DeliveryOutcome::Retry { .. }));
+        assert!(matches!(delivery_outcome(&busy, MAX_DELIVERY_ATTEMPTS, now), DeliveryOutcome::Failed { .. }));
+
+        // A bad address won't get better with time
+        let invalid = Err(EmailError::InvalidAddress("nope".to_string()));
+        assert!(matches!(delivery_outcome(&invalid, 1, now), DeliveryOutcome::Failed { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_service_builds_in_each_mode() {
+        for (port, encryption) in [
+            (465, SmtpEncryption::Tls),
+            (587, SmtpEncryption::StartTls),
+            (1025, SmtpEncryption::None),
+        ] {
+            let service = EmailService::new(&smtp_config(port, encryption));
+            assert!(service.is_ok(), "failed to build transport for {:?}", encryption);
+        }
+    }
 }
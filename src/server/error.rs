@@ -0,0 +1,200 @@
+//! API error responses
+//!
+//! Handlers return `ApiError` so clients can tell a missing record from a bad
+//! request, a conflict or a server failure. Every variant renders as a JSON
+//! body of the form `{"error": "..."}`; payloads that fail field validation
+//! also list the offending fields under `"fields"`.
+
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+
+use crate::models::FieldError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiError {
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    Validation(String),
+
+    #[error("Validation failed")]
+    Unprocessable(Vec<FieldError>),
+
+    #[error("{0}")]
+    Conflict(String),
+
+    #[error("{0}")]
+    Unauthorized(String),
+
+    #[error("{0}")]
+    Forbidden(String),
+
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl ApiError {
+    pub fn not_found(what: &str) -> Self {
+        ApiError::NotFound(format!("{} not found", what))
+    }
+
+    pub fn forbidden() -> Self {
+        ApiError::Forbidden("You don't have permission to do that".to_string())
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unprocessable(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        if matches!(err, sqlx::Error::RowNotFound) {
+            return ApiError::NotFound("Record not found".to_string());
+        }
+
+        if let Some(db_err) = err.as_database_error() {
+            if db_err.is_unique_violation() {
+                return ApiError::Conflict("A record with these details already exists".to_string());
+            }
+            if db_err.is_foreign_key_violation() {
+                return ApiError::Conflict("The record is referenced by other records".to_string());
+            }
+        }
+
+        // Details stay in the log; clients get a generic message
+        tracing::error!("Database error: {}", err);
+        ApiError::Internal("Internal server error".to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = match &self {
+            ApiError::Unprocessable(fields) => json!({ "error": self.to_string(), "fields": fields }),
+            _ => json!({ "error": self.to_string() }),
+        };
+        (self.status_code(), Json(body)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use sqlx::error::{DatabaseError, ErrorKind};
+
+    /// Stand-in for the error Postgres returns on a duplicate insert
+    #[derive(Debug)]
+    struct UniqueViolation;
+
+    impl std::fmt::Display for UniqueViolation {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "duplicate key value violates unique constraint \"agents_sip_username_key\"")
+        }
+    }
+
+    impl std::error::Error for UniqueViolation {}
+
+    impl DatabaseError for UniqueViolation {
+        fn message(&self) -> &str {
+            "duplicate key value violates unique constraint \"agents_sip_username_key\""
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed("23505"))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::UniqueViolation
+        }
+    }
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_create_returns_409() {
+        let err = ApiError::from(sqlx::Error::Database(Box::new(UniqueViolation)));
+        let response = err.into_response();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            body_json(response).await,
+            json!({ "error": "A record with these details already exists" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_id_returns_404_with_json_body() {
+        let response = ApiError::from(sqlx::Error::RowNotFound).into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await, json!({ "error": "Record not found" }));
+
+        let response = ApiError::not_found("Lead").into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await, json!({ "error": "Lead not found" }));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_lead_returns_422_with_fields() {
+        let req = crate::models::CreateLeadRequest {
+            first_name: String::new(),
+            last_name: "Lovelace".to_string(),
+            phone: "not a number".to_string(),
+            email: Some("ada@".to_string()),
+            company: None,
+            title: None,
+            campaign_id: None,
+            notes: None,
+        };
+        let response = ApiError::Unprocessable(req.validate("US").unwrap_err()).into_response();
+
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            body_json(response).await,
+            json!({
+                "error": "Validation failed",
+                "fields": [
+                    { "field": "firstName", "message": "First name is required" },
+                    { "field": "phone", "message": "Phone number is not valid" },
+                    { "field": "email", "message": "Email address is not valid" },
+                ]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_database_errors_hide_details() {
+        let response = ApiError::from(sqlx::Error::PoolTimedOut).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(body_json(response).await, json!({ "error": "Internal server error" }));
+    }
+}
@@ -0,0 +1,309 @@
+//! Real-time events pushed to the browser
+//!
+//! Handlers publish `ServerEvent`s on the shared `EventBus`. Every connected
+//! client on `GET /api/ws` receives them as JSON text frames. Browsers can't set
+//! headers on a WebSocket, so the access token is passed as `?token=`.
+//!
+//! SIP user agent events are forwarded onto a second bus and streamed as
+//! Server-Sent Events on `GET /api/sip/events`, authenticated the same way.
+
+use std::convert::Infallible;
+
+use std::sync::Arc;
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Response,
+    },
+};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
+
+use crate::models::{QueuedCallInfo, ScreenPop, SipEvent};
+use super::{auth, sip, AppState};
+
+/// Events buffered per client before slow clients start missing some
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Event sent to connected clients
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", content = "data")]
+pub enum ServerEvent {
+    /// The inbound call queue changed
+    #[serde(rename = "queue.updated")]
+    QueueUpdated { calls: Vec<QueuedCallInfo> },
+    /// An inbound call was routed to an agent. Only the agent's own login
+    /// receives it, since the screen pop carries the lead's details.
+    #[serde(rename = "call.incoming")]
+    CallIncoming {
+        #[serde(rename = "agentId")]
+        agent_id: i64,
+        /// The agent's login, if they have one
+        #[serde(rename = "userId")]
+        user_id: Option<i64>,
+        #[serde(rename = "callId")]
+        call_id: i64,
+        from: String,
+        #[serde(rename = "screenPop")]
+        screen_pop: ScreenPop,
+    },
+    /// A lead was assigned to an agent. Only the agent's own login receives it.
+    #[serde(rename = "lead.assigned")]
+    LeadAssigned {
+        #[serde(rename = "agentId")]
+        agent_id: i64,
+        #[serde(rename = "userId")]
+        user_id: i64,
+        #[serde(rename = "leadId")]
+        lead_id: i64,
+        #[serde(rename = "leadName")]
+        lead_name: String,
+        phone: String,
+    },
+}
+
+impl ServerEvent {
+    /// Whether the client logged in as `user_id` should receive this event
+    pub fn is_for(&self, user_id: i64) -> bool {
+        match self {
+            ServerEvent::LeadAssigned { user_id: recipient, .. } => *recipient == user_id,
+            ServerEvent::CallIncoming { user_id: recipient, .. } => *recipient == Some(user_id),
+            ServerEvent::QueueUpdated { .. } => true,
+        }
+    }
+}
+
+impl SipEvent {
+    /// Convert an event from the user agent of trunk `trunk`
+    pub fn from_agent(trunk: usize, event: &sip::AgentEvent) -> Self {
+        match event {
+            sip::AgentEvent::StateChanged(state) => SipEvent::StateChanged {
+                trunk,
+                state: super::sip_state_name(*state).to_string(),
+            },
+            sip::AgentEvent::IncomingCall { call_id, from, to } => SipEvent::IncomingCall {
+                trunk,
+                call_id: call_id.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            },
+            sip::AgentEvent::CallStateChanged { call_id, state } => SipEvent::CallState {
+                trunk,
+                call_id: call_id.clone(),
+                state: state.to_string().to_lowercase(),
+            },
+            sip::AgentEvent::Error(message) => SipEvent::Error {
+                trunk,
+                message: message.clone(),
+            },
+        }
+    }
+}
+
+/// Fan-out of server events to every connected client
+#[derive(Clone)]
+pub struct EventBus<T = ServerEvent> {
+    tx: broadcast::Sender<T>,
+}
+
+impl<T: Clone> Default for EventBus<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> EventBus<T> {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self { tx }
+    }
+
+    /// Send an event to all subscribers. Having none is not an error.
+    pub fn publish(&self, event: T) {
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<T> {
+        self.tx.subscribe()
+    }
+}
+
+/// Forward a trunk's user agent events onto the SIP event bus until the agent is dropped
+pub fn forward_sip_events(trunk: usize, mut events: mpsc::Receiver<sip::AgentEvent>, bus: EventBus<SipEvent>) {
+    tokio::spawn(async move {
+        while let Some(event) = events.recv().await {
+            bus.publish(SipEvent::from_agent(trunk, &event));
+        }
+    });
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WsQuery {
+    token: String,
+}
+
+/// Upgrade to a WebSocket that streams server events
+pub async fn ws_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WsQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let claims = auth::validate_token(&query.token, &state.jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    let events = state.events.subscribe();
+    Ok(ws.on_upgrade(move |socket| stream_events(socket, events, claims.sub)))
+}
+
+/// Stream SIP user agent events as Server-Sent Events
+pub async fn sip_events_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    auth::validate_token(&query.token, &state.jwt_secret).map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    Ok(sse_stream(state.sip_events.subscribe()))
+}
+
+fn sse_stream<T: Serialize + Clone + Send + 'static>(
+    events: broadcast::Receiver<T>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures::stream::unfold(events, |mut events| async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => {
+                    let Ok(frame) = Event::default().json_data(&event) else { continue };
+                    return Some((Ok(frame), events));
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("SSE client lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn stream_events(mut socket: WebSocket, mut events: broadcast::Receiver<ServerEvent>, user_id: i64) {
+    loop {
+        tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) if !event.is_for(user_id) => {}
+                Ok(event) => {
+                    let Ok(json) = serde_json::to_string(&event) else { continue };
+                    if socket.send(Message::Text(json.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("WebSocket client lagged, skipped {} events", skipped);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_published_events_reach_subscribers() {
+        let bus = EventBus::new();
+        let mut rx = bus.subscribe();
+
+        bus.publish(ServerEvent::QueueUpdated { calls: Vec::new() });
+
+        assert_eq!(rx.recv().await.unwrap(), ServerEvent::QueueUpdated { calls: Vec::new() });
+    }
+
+    #[test]
+    fn test_event_json_shape() {
+        let json = serde_json::to_value(ServerEvent::QueueUpdated { calls: Vec::new() }).unwrap();
+        assert_eq!(json, serde_json::json!({ "type": "queue.updated", "data": { "calls": [] } }));
+    }
+
+    #[test]
+    fn test_call_incoming_json_shape() {
+        let event = ServerEvent::CallIncoming {
+            agent_id: 4,
+            user_id: Some(9),
+            call_id: 31,
+            from: "+15550100".to_string(),
+            screen_pop: ScreenPop::new_caller(),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "call.incoming");
+        assert_eq!(json["data"]["agentId"], 4);
+        assert_eq!(json["data"]["userId"], 9);
+        assert_eq!(json["data"]["screenPop"]["newCaller"], true);
+
+        let parsed: ServerEvent = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, event);
+    }
+
+    #[test]
+    fn test_call_incoming_only_goes_to_the_agent() {
+        let event = |user_id| ServerEvent::CallIncoming {
+            agent_id: 4,
+            user_id,
+            call_id: 31,
+            from: "+15550100".to_string(),
+            screen_pop: ScreenPop::new_caller(),
+        };
+        assert!(event(Some(9)).is_for(9));
+        assert!(!event(Some(9)).is_for(10));
+        // An agent without a login has no browser to pop up on
+        assert!(!event(None).is_for(9));
+    }
+
+    #[test]
+    fn test_lead_assigned_only_goes_to_the_agent() {
+        let event = ServerEvent::LeadAssigned {
+            agent_id: 4,
+            user_id: 9,
+            lead_id: 12,
+            lead_name: "Ada Lovelace".to_string(),
+            phone: "+15550100".to_string(),
+        };
+        assert!(event.is_for(9));
+        assert!(!event.is_for(10));
+        assert!(ServerEvent::QueueUpdated { calls: Vec::new() }.is_for(10));
+
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "lead.assigned");
+        assert_eq!(json["data"]["leadId"], 12);
+    }
+
+    #[tokio::test]
+    async fn test_agent_state_change_becomes_sse_frame() {
+        use axum::response::IntoResponse;
+
+        let bus = EventBus::<SipEvent>::new();
+        let (agent_tx, agent_rx) = mpsc::channel(8);
+        let response = sse_stream(bus.subscribe()).into_response();
+        forward_sip_events(1, agent_rx, bus);
+
+        agent_tx.send(sip::AgentEvent::StateChanged(sip::AgentState::Registered)).await.unwrap();
+        // Dropping the agent ends the forwarder, which closes the bus and the stream
+        drop(agent_tx);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(
+            std::str::from_utf8(&body).unwrap(),
+            "data: {\"type\":\"state_changed\",\"trunk\":1,\"state\":\"registered\"}\n\n"
+        );
+    }
+}
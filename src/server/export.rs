@@ -0,0 +1,326 @@
+//! CSV export helpers
+
+use std::borrow::Cow;
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::models::{LeadExportRow, StatsGroupBy, StatsSummaryRow};
+
+/// Render grouped statistics as CSV. An empty result set yields just the header row.
+pub fn stats_summary_csv(group_by: StatsGroupBy, rows: &[StatsSummaryRow]) -> Result<String, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+
+    writer.write_record([
+        group_by.label(),
+        "total_calls",
+        "answered_calls",
+        "avg_talk_time_seconds",
+        "conversions",
+        "conversion_rate",
+    ])?;
+
+    for row in rows {
+        writer.write_record([
+            spreadsheet_safe(&row.group_key).into_owned(),
+            row.total_calls.to_string(),
+            row.answered_calls.to_string(),
+            format!("{:.1}", row.avg_talk_time),
+            row.conversions.to_string(),
+            format!("{:.1}", row.conversion_rate()),
+        ])?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Columns of a lead CSV export, in order
+pub const LEAD_EXPORT_COLUMNS: [&str; 6] = ["name", "phone", "email", "status", "assigned_agent", "notes"];
+
+/// Output format for `GET /api/leads/export`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeadExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl LeadExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            LeadExportFormat::Csv => "text/csv; charset=utf-8",
+            LeadExportFormat::Json => "application/json",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            LeadExportFormat::Csv => "csv",
+            LeadExportFormat::Json => "json",
+        }
+    }
+}
+
+/// Encodes lead rows one at a time so an export never holds the whole result set
+pub struct LeadExportEncoder {
+    format: LeadExportFormat,
+    rows_written: usize,
+}
+
+impl LeadExportEncoder {
+    pub fn new(format: LeadExportFormat) -> Self {
+        Self { format, rows_written: 0 }
+    }
+
+    /// Bytes written before the first row
+    pub fn header(&self) -> Result<Vec<u8>, csv::Error> {
+        match self.format {
+            LeadExportFormat::Csv => csv_record(LEAD_EXPORT_COLUMNS),
+            LeadExportFormat::Json => Ok(b"[".to_vec()),
+        }
+    }
+
+    pub fn row(&mut self, row: &LeadExportRow) -> Result<Vec<u8>, csv::Error> {
+        let bytes = match self.format {
+            LeadExportFormat::Csv => csv_record([
+                row.name.as_str(),
+                row.phone.as_str(),
+                row.email.as_deref().unwrap_or(""),
+                row.status.display_name(),
+                row.assigned_agent.as_deref().unwrap_or(""),
+                row.notes.as_deref().unwrap_or(""),
+            ])?,
+            LeadExportFormat::Json => {
+                let mut bytes = if self.rows_written == 0 { Vec::new() } else { b",".to_vec() };
+                serde_json::to_writer(&mut bytes, row).map_err(std::io::Error::from)?;
+                bytes
+            }
+        };
+        self.rows_written += 1;
+        Ok(bytes)
+    }
+
+    /// Bytes written after the last row
+    pub fn footer(&self) -> Vec<u8> {
+        match self.format {
+            LeadExportFormat::Csv => Vec::new(),
+            LeadExportFormat::Json => b"]".to_vec(),
+        }
+    }
+}
+
+/// A cell a spreadsheet will show as text. Values starting with `=`, `+`,
+/// `-` or `@` would otherwise be run as formulas when the export is opened,
+/// so they get a leading `'`.
+pub fn spreadsheet_safe(cell: &str) -> Cow<'_, str> {
+    if cell.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        Cow::Owned(format!("'{}", cell))
+    } else {
+        Cow::Borrowed(cell)
+    }
+}
+
+fn csv_record<'a>(fields: impl IntoIterator<Item = &'a str>) -> Result<Vec<u8>, csv::Error> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    let cells: Vec<Cow<str>> = fields.into_iter().map(spreadsheet_safe).collect();
+    writer.write_record(cells.iter().map(|cell| cell.as_ref()))?;
+    writer.into_inner().map_err(|e| e.into_error().into())
+}
+
+/// Encode `rows` and send each chunk to `tx`, which backs the response body
+///
+/// A failed row or database error is sent as an error so the client sees a
+/// broken download instead of a silently truncated file. Sending stops early
+/// when the client goes away.
+pub async fn send_leads<S>(rows: S, format: LeadExportFormat, tx: mpsc::Sender<Result<Vec<u8>, std::io::Error>>)
+where
+    S: Stream<Item = Result<LeadExportRow, sqlx::Error>>,
+{
+    let mut encoder = LeadExportEncoder::new(format);
+    let mut rows = std::pin::pin!(rows);
+
+    let header = encoder.header().map_err(std::io::Error::other);
+    if tx.send(header).await.is_err() {
+        return;
+    }
+
+    while let Some(row) = rows.next().await {
+        let chunk = match row {
+            Ok(row) => encoder.row(&row).map_err(std::io::Error::other),
+            Err(e) => {
+                tracing::error!("Lead export failed: {}", e);
+                Err(std::io::Error::other("lead export failed"))
+            }
+        };
+        let failed = chunk.is_err();
+        if tx.send(chunk).await.is_err() || failed {
+            return;
+        }
+    }
+
+    let _ = tx.send(Ok(encoder.footer())).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &str) -> Vec<csv::StringRecord> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .from_reader(data.as_bytes())
+            .records()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_empty_export_has_header_only() {
+        let data = stats_summary_csv(StatsGroupBy::Campaign, &[]).unwrap();
+        let records = parse(&data);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0][0], "campaign");
+        assert_eq!(records[0].len(), 6);
+    }
+
+    #[test]
+    fn test_export_row_values() {
+        let rows = vec![StatsSummaryRow {
+            group_key: "Smith, Jane".to_string(),
+            total_calls: 20,
+            answered_calls: 8,
+            avg_talk_time: 95.0,
+            conversions: 2,
+        }];
+
+        let data = stats_summary_csv(StatsGroupBy::Agent, &rows).unwrap();
+        let records = parse(&data);
+
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.len() == 6));
+        // Names containing commas must survive quoting
+        assert_eq!(&records[1][0], "Smith, Jane");
+        assert_eq!(&records[1][1], "20");
+        assert_eq!(&records[1][3], "95.0");
+        assert_eq!(&records[1][5], "25.0");
+    }
+
+    #[test]
+    fn test_export_group_keys_cannot_run_formulas() {
+        let rows = vec![StatsSummaryRow {
+            group_key: "=HYPERLINK(\"http://evil\")".to_string(),
+            total_calls: 1,
+            answered_calls: 0,
+            avg_talk_time: 0.0,
+            conversions: 0,
+        }];
+
+        let records = parse(&stats_summary_csv(StatsGroupBy::Campaign, &rows).unwrap());
+        assert_eq!(&records[1][0], "'=HYPERLINK(\"http://evil\")");
+    }
+
+    fn lead_row(name: &str, status: crate::models::LeadStatus) -> LeadExportRow {
+        LeadExportRow {
+            name: name.to_string(),
+            phone: "+15551234567".to_string(),
+            email: None,
+            status,
+            assigned_agent: Some("Grace Hopper".to_string()),
+            notes: Some("Called twice, \"busy\"".to_string()),
+        }
+    }
+
+    async fn export(rows: Vec<LeadExportRow>, format: LeadExportFormat) -> String {
+        let (tx, mut rx) = mpsc::channel(4);
+        let send = send_leads(futures::stream::iter(rows.into_iter().map(Ok)), format, tx);
+        let collect = async {
+            let mut out = Vec::new();
+            while let Some(chunk) = rx.recv().await {
+                out.extend(chunk.unwrap());
+            }
+            out
+        };
+        let ((), out) = tokio::join!(send, collect);
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lead_csv_header_matches_documented_columns() {
+        let data = export(Vec::new(), LeadExportFormat::Csv).await;
+        let records = parse(&data);
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].iter().collect::<Vec<_>>(), LEAD_EXPORT_COLUMNS);
+    }
+
+    #[tokio::test]
+    async fn test_lead_csv_rows() {
+        use crate::models::LeadStatus;
+
+        let rows = vec![lead_row("Ada Lovelace", LeadStatus::Qualified), lead_row("Alan Turing", LeadStatus::DoNotCall)];
+        let records = parse(&export(rows, LeadExportFormat::Csv).await);
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(&records[1][0], "Ada Lovelace");
+        assert_eq!(&records[1][2], "");
+        assert_eq!(&records[1][3], "Qualified");
+        assert_eq!(&records[1][4], "Grace Hopper");
+        assert_eq!(&records[1][5], "Called twice, \"busy\"");
+        assert_eq!(&records[2][3], "Do Not Call");
+    }
+
+    #[test]
+    fn test_formula_cells_are_neutralised() {
+        assert_eq!(spreadsheet_safe("=HYPERLINK(\"http://evil\")"), "'=HYPERLINK(\"http://evil\")");
+        assert_eq!(spreadsheet_safe("+15551234567"), "'+15551234567");
+        assert_eq!(spreadsheet_safe("-2+3"), "'-2+3");
+        assert_eq!(spreadsheet_safe("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(spreadsheet_safe("Ada Lovelace"), "Ada Lovelace");
+        assert_eq!(spreadsheet_safe(""), "");
+    }
+
+    #[tokio::test]
+    async fn test_lead_csv_cells_cannot_run_formulas() {
+        use crate::models::LeadStatus;
+
+        let rows = vec![LeadExportRow {
+            notes: Some("=cmd|' /C calc'!A0".to_string()),
+            ..lead_row("@SUM(1+1)", LeadStatus::New)
+        }];
+        let records = parse(&export(rows.clone(), LeadExportFormat::Csv).await);
+
+        assert_eq!(&records[1][0], "'@SUM(1+1)");
+        assert_eq!(&records[1][5], "'=cmd|' /C calc'!A0");
+
+        // JSON isn't opened by spreadsheets, so it keeps the raw values
+        let parsed: Vec<LeadExportRow> = serde_json::from_str(&export(rows.clone(), LeadExportFormat::Json).await).unwrap();
+        assert_eq!(parsed, rows);
+    }
+
+    #[tokio::test]
+    async fn test_lead_json_is_a_single_array() {
+        use crate::models::LeadStatus;
+
+        let data = export(Vec::new(), LeadExportFormat::Json).await;
+        assert_eq!(data, "[]");
+
+        let rows = vec![lead_row("Ada Lovelace", LeadStatus::New), lead_row("Alan Turing", LeadStatus::Lost)];
+        let parsed: Vec<LeadExportRow> = serde_json::from_str(&export(rows.clone(), LeadExportFormat::Json).await).unwrap();
+        assert_eq!(parsed, rows);
+    }
+
+    #[tokio::test]
+    async fn test_database_error_breaks_the_download() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let rows = futures::stream::iter(vec![Err(sqlx::Error::PoolTimedOut)]);
+        send_leads(rows, LeadExportFormat::Csv, tx).await;
+
+        assert!(rx.recv().await.unwrap().is_ok());
+        assert!(rx.recv().await.unwrap().is_err());
+        // No footer after a failure
+        assert!(rx.recv().await.is_none());
+    }
+}
@@ -0,0 +1,271 @@
+//! Claude API client for AI-powered call handling
+//!
+//! This module provides integration with the Anthropic Claude API
+//! for generating AI agent responses during calls.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{sse_text_stream, LlmClient, LlmError, LlmResponse, Message, TextStream};
+
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5-20250514";
+
+/// Claude API client
+#[derive(Clone)]
+pub struct ClaudeClient {
+    client: Client,
+    api_key: String,
+    model: Arc<RwLock<String>>,
+    base_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ClaudeApiRequest {
+    model: String,
+    max_tokens: i32,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeApiResponse {
+    id: String,
+    content: Vec<ContentBlock>,
+    model: String,
+    stop_reason: Option<String>,
+    usage: Usage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    content_type: String,
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    input_tokens: i32,
+    output_tokens: i32,
+}
+
+/// A streamed event; only text deltas carry reply text
+#[derive(Debug, Deserialize)]
+struct StreamEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    delta: Option<StreamDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDelta {
+    text: Option<String>,
+}
+
+impl ClaudeClient {
+    /// Create a new Claude client
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model: Arc::new(RwLock::new(DEFAULT_MODEL.to_string())),
+            base_url: "https://api.anthropic.com".to_string(),
+        }
+    }
+
+    /// Use another model than the default
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = Arc::new(RwLock::new(model));
+        self
+    }
+
+    /// Send requests to another API host, e.g. a local stub in tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Set the model to use
+    pub async fn set_model(&self, model: String) {
+        *self.model.write().await = model;
+    }
+
+    async fn post(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+        stream: bool,
+    ) -> Result<reqwest::Response, LlmError> {
+        let model = self.model.read().await.clone();
+
+        let request = ClaudeApiRequest {
+            model,
+            max_tokens,
+            messages,
+            system: system_prompt.map(|s| s.to_string()),
+            temperature,
+            stream,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| LlmError::NetworkError(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(LlmError::from_response(status.as_u16(), body));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmClient for ClaudeClient {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    async fn complete(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+    ) -> Result<LlmResponse, LlmError> {
+        let response = self.post(system_prompt, messages, max_tokens, temperature, false).await?;
+        let body = response
+            .text()
+            .await
+            .map_err(|e| LlmError::NetworkError(e.to_string()))?;
+
+        let api_response: ClaudeApiResponse = serde_json::from_str(&body)
+            .map_err(|e| LlmError::ParseError(e.to_string()))?;
+
+        // Extract text from content blocks
+        let text = api_response
+            .content
+            .iter()
+            .filter_map(|block| {
+                if block.content_type == "text" {
+                    block.text.clone()
+                } else {
+                    None
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("");
+
+        Ok(LlmResponse {
+            text,
+            input_tokens: api_response.usage.input_tokens,
+            output_tokens: api_response.usage.output_tokens,
+            stop_reason: api_response.stop_reason,
+        })
+    }
+
+    async fn stream(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+    ) -> Result<TextStream, LlmError> {
+        let response = self.post(system_prompt, messages, max_tokens, temperature, true).await?;
+        Ok(sse_text_stream(response, |data| {
+            let event: StreamEvent = serde_json::from_str(data).ok()?;
+            if event.event_type != "content_block_delta" {
+                return None;
+            }
+            event.delta?.text
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::telnyx::stub::stub_http;
+    use futures::StreamExt;
+
+    fn message(content: &str) -> Message {
+        Message {
+            role: "user".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_message_creation() {
+        let msg = message("Hello");
+        assert_eq!(msg.role, "user");
+        assert_eq!(msg.content, "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_complete_against_messages_api() {
+        let reply = r#"{"id":"msg_1","model":"claude-test","stop_reason":"end_turn",
+            "content":[{"type":"text","text":"Hi Maria, "},{"type":"text","text":"how are you?"}],
+            "usage":{"input_tokens":12,"output_tokens":6}}"#;
+        let (url, request) = stub_http("application/json", reply).await;
+        let client: Arc<dyn LlmClient> =
+            Arc::new(ClaudeClient::new("sk-ant".to_string()).with_model("claude-test".to_string()).with_base_url(url));
+
+        let response = client.complete(Some("Be brief"), vec![message("Hello")], 50, Some(0.5)).await.unwrap();
+        assert_eq!(response.text, "Hi Maria, how are you?");
+        assert_eq!((response.input_tokens, response.output_tokens), (12, 6));
+        assert_eq!(response.stop_reason.as_deref(), Some("end_turn"));
+
+        let (request_line, body) = request.await.unwrap();
+        assert!(request_line.starts_with("POST /v1/messages "), "{}", request_line);
+        assert_eq!(body["model"], "claude-test");
+        assert_eq!(body["system"], "Be brief");
+        assert_eq!(body["messages"][0]["content"], "Hello");
+        assert!(body.get("stream").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_text_deltas() {
+        let reply = concat!(
+            "event: message_start\n",
+            "data: {\"type\":\"message_start\",\"message\":{}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Sure, \"}}\n\n",
+            "event: content_block_delta\n",
+            "data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Tuesday works.\"}}\n\n",
+            "event: message_stop\n",
+            "data: {\"type\":\"message_stop\"}\n\n",
+        );
+        let (url, request) = stub_http("text/event-stream", reply).await;
+        let client = ClaudeClient::new("sk-ant".to_string()).with_base_url(url);
+
+        let stream = client.stream(None, vec![message("Is Tuesday ok?")], 50, None).await.unwrap();
+        let pieces: Vec<String> = stream.map(|piece| piece.unwrap()).collect().await;
+        assert_eq!(pieces, vec!["Sure, ", "Tuesday works."]);
+
+        let (_, body) = request.await.unwrap();
+        assert_eq!(body["stream"], true);
+    }
+}
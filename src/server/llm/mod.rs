@@ -0,0 +1,391 @@
+//! Language model backends
+//!
+//! AI calls are answered by whichever chat model is configured here. Claude
+//! is the default; OpenAI's chat completions API can be used instead. Both
+//! sit behind `LlmClient`, so call handling does not depend on either one.
+//!
+//! - LLM_PROVIDER: anthropic (default) or openai
+//! - ANTHROPIC_API_KEY: required for anthropic
+//! - OPENAI_API_KEY: required for openai
+//! - LLM_MODEL: model to use instead of the provider's default
+
+pub mod claude;
+pub mod openai;
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use async_trait::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+pub use claude::ClaudeClient;
+pub use openai::OpenAiClient;
+
+/// One turn of a conversation
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+/// A completed reply
+#[derive(Debug, Clone)]
+pub struct LlmResponse {
+    pub text: String,
+    pub input_tokens: i32,
+    pub output_tokens: i32,
+    pub stop_reason: Option<String>,
+}
+
+/// Reply text as it is generated
+pub type TextStream = BoxStream<'static, Result<String, LlmError>>;
+
+/// Errors that can occur when calling a model API
+#[derive(Debug, thiserror::Error)]
+#[allow(clippy::enum_variant_names)]
+pub enum LlmError {
+    #[error("Network error: {0}")]
+    NetworkError(String),
+
+    #[error("API error ({status}): {message}")]
+    ApiError {
+        status: u16,
+        message: String,
+        error_type: String,
+    },
+
+    #[error("Parse error: {0}")]
+    ParseError(String),
+}
+
+/// Error body shared by the Anthropic and OpenAI APIs
+#[derive(Debug, Deserialize)]
+struct ApiErrorBody {
+    error: ApiErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorDetail {
+    message: String,
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+}
+
+impl LlmError {
+    /// Error for a non-success response, using the API's message when the body has one
+    fn from_response(status: u16, body: String) -> Self {
+        match serde_json::from_str::<ApiErrorBody>(&body) {
+            Ok(error) => LlmError::ApiError {
+                status,
+                message: error.error.message,
+                error_type: error.error.error_type.unwrap_or_else(|| "unknown".to_string()),
+            },
+            Err(_) => LlmError::ApiError {
+                status,
+                message: body,
+                error_type: "unknown".to_string(),
+            },
+        }
+    }
+}
+
+/// A chat model that AI calls can talk to
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Whether an API key has been configured
+    fn is_configured(&self) -> bool;
+
+    /// Send a conversation and wait for the whole reply
+    async fn complete(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+    ) -> Result<LlmResponse, LlmError>;
+
+    /// Send a conversation and receive the reply in pieces as it is generated
+    async fn stream(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+    ) -> Result<TextStream, LlmError>;
+
+    /// Short summary of a call transcript for the lead's notes
+    async fn summarize(&self, transcript: &str) -> Result<String, LlmError> {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: format!(
+                "Summarize this phone call transcript in two or three sentences for a CRM note. \
+                 Mention anything the caller asked to follow up on.\n\n{}",
+                transcript
+            ),
+        }];
+
+        let response = self.complete(None, messages, 200, Some(0.3)).await?;
+        Ok(response.text.trim().to_string())
+    }
+
+    /// Generate a response for a call conversation
+    async fn generate_call_response(
+        &self,
+        system_prompt: &str,
+        conversation_history: Vec<Message>,
+        user_speech: &str,
+        max_tokens: i32,
+    ) -> Result<String, LlmError> {
+        let mut messages = conversation_history;
+        messages.push(Message {
+            role: "user".to_string(),
+            content: user_speech.to_string(),
+        });
+
+        let response = self
+            .complete(Some(system_prompt), messages, max_tokens, Some(0.7))
+            .await?;
+
+        Ok(response.text)
+    }
+
+    /// Generate a greeting for an AI agent call
+    async fn generate_greeting(
+        &self,
+        system_prompt: &str,
+        lead_name: Option<&str>,
+        campaign_context: Option<&str>,
+    ) -> Result<String, LlmError> {
+        let greeting_prompt = format!(
+            "Generate a natural, friendly greeting to start a phone call.{}{}",
+            lead_name
+                .map(|n| format!(" The person's name is {}.", n))
+                .unwrap_or_default(),
+            campaign_context
+                .map(|c| format!(" Context: {}", c))
+                .unwrap_or_default()
+        );
+
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: greeting_prompt,
+        }];
+
+        let response = self
+            .complete(Some(system_prompt), messages, 100, Some(0.8))
+            .await?;
+
+        Ok(response.text)
+    }
+
+    /// Ask the model how the caller felt over the course of a transcript
+    async fn score_sentiment(&self, transcript: &str) -> Result<f32, LlmError> {
+        let messages = vec![Message {
+            role: "user".to_string(),
+            content: format!(
+                "Rate the caller's overall sentiment in this phone call transcript on a scale \
+                 from -1 (very negative) to 1 (very positive). Reply with the number only.\n\n{}",
+                transcript
+            ),
+        }];
+
+        let response = self.complete(None, messages, 10, Some(0.0)).await?;
+        parse_score(&response.text)
+            .ok_or_else(|| LlmError::ParseError(format!("Not a score: {}", response.text)))
+    }
+}
+
+/// Parse the number the model was asked to reply with, clamped to -1..1
+fn parse_score(reply: &str) -> Option<f32> {
+    let number = reply
+        .split(|c: char| !(c.is_ascii_digit() || c == '.' || c == '-'))
+        .find(|token| token.parse::<f32>().is_ok())?;
+    Some(number.parse::<f32>().ok()?.clamp(-1.0, 1.0))
+}
+
+/// Which backend to use, read from the environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LlmConfig {
+    pub provider: String,
+    pub anthropic_api_key: Option<String>,
+    pub openai_api_key: Option<String>,
+    pub model: Option<String>,
+}
+
+impl LlmConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        Self {
+            provider: var("LLM_PROVIDER").unwrap_or_default().to_lowercase(),
+            anthropic_api_key: var("ANTHROPIC_API_KEY"),
+            openai_api_key: var("OPENAI_API_KEY"),
+            model: var("LLM_MODEL"),
+        }
+    }
+
+    /// Build the configured client. Unknown providers fall back to Claude.
+    pub fn client(&self) -> Arc<dyn LlmClient> {
+        match self.provider.as_str() {
+            "" | "anthropic" => {}
+            "openai" => {
+                if self.openai_api_key.is_none() {
+                    tracing::warn!("LLM_PROVIDER is openai but OPENAI_API_KEY is not set, AI calls will fail");
+                }
+                let client = OpenAiClient::new(self.openai_api_key.clone().unwrap_or_default());
+                return Arc::new(match &self.model {
+                    Some(model) => client.with_model(model.clone()),
+                    None => client,
+                });
+            }
+            other => tracing::warn!("Unknown LLM_PROVIDER '{}', using anthropic", other),
+        }
+
+        let client = ClaudeClient::new(self.anthropic_api_key.clone().unwrap_or_default());
+        Arc::new(match &self.model {
+            Some(model) => client.with_model(model.clone()),
+            None => client,
+        })
+    }
+}
+
+/// Collects the `data:` payloads of a server-sent events body, which may
+/// arrive split across chunks at any byte
+#[derive(Default)]
+struct SseLines {
+    buffer: Vec<u8>,
+}
+
+impl SseLines {
+    /// Add a chunk and return the payloads of the lines it completed
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut payloads = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(data) = line.trim_end().strip_prefix("data:") {
+                payloads.push(data.trim_start().to_string());
+            }
+        }
+        payloads
+    }
+}
+
+/// Turn a streaming response into reply text. `delta` pulls the text out of
+/// one event's data, or returns None for events that carry none. A `[DONE]`
+/// event or the end of the body finishes the stream.
+fn sse_text_stream(response: reqwest::Response, delta: fn(&str) -> Option<String>) -> TextStream {
+    struct State {
+        response: reqwest::Response,
+        lines: SseLines,
+        pending: VecDeque<String>,
+        done: bool,
+    }
+
+    let state = State {
+        response,
+        lines: SseLines::default(),
+        pending: VecDeque::new(),
+        done: false,
+    };
+
+    futures::stream::unfold(state, move |mut state| async move {
+        loop {
+            if let Some(text) = state.pending.pop_front() {
+                return Some((Ok(text), state));
+            }
+            if state.done {
+                return None;
+            }
+
+            match state.response.chunk().await {
+                Ok(Some(chunk)) => {
+                    for data in state.lines.push(&chunk) {
+                        if data == "[DONE]" {
+                            state.done = true;
+                            break;
+                        }
+                        state.pending.extend(delta(&data));
+                    }
+                }
+                Ok(None) => state.done = true,
+                Err(e) => {
+                    state.done = true;
+                    return Some((Err(LlmError::NetworkError(e.to_string())), state));
+                }
+            }
+        }
+    })
+    .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(provider: &str) -> LlmConfig {
+        LlmConfig {
+            provider: provider.to_string(),
+            anthropic_api_key: Some("sk-ant".to_string()),
+            openai_api_key: Some("sk-openai".to_string()),
+            model: None,
+        }
+    }
+
+    #[test]
+    fn test_provider_selection() {
+        assert_eq!(config("").client().name(), "anthropic");
+        assert_eq!(config("anthropic").client().name(), "anthropic");
+        assert_eq!(config("openai").client().name(), "openai");
+        assert_eq!(config("mistral").client().name(), "anthropic");
+
+        // The key for the chosen provider is the one that matters
+        let openai = LlmConfig { anthropic_api_key: None, ..config("openai") };
+        assert!(openai.client().is_configured());
+        let missing = LlmConfig { openai_api_key: None, ..config("openai") };
+        assert!(!missing.client().is_configured());
+    }
+
+    #[test]
+    fn test_parse_score() {
+        assert_eq!(parse_score("-0.6"), Some(-0.6));
+        assert_eq!(parse_score("Score: 0.8"), Some(0.8));
+        assert_eq!(parse_score("5"), Some(1.0));
+        assert_eq!(parse_score("neutral"), None);
+    }
+
+    #[test]
+    fn test_api_error_keeps_provider_message() {
+        let body = r#"{"type":"error","error":{"type":"invalid_request_error","message":"max_tokens: too large"}}"#;
+        match LlmError::from_response(400, body.to_string()) {
+            LlmError::ApiError { status, message, error_type } => {
+                assert_eq!(status, 400);
+                assert_eq!(message, "max_tokens: too large");
+                assert_eq!(error_type, "invalid_request_error");
+            }
+            other => panic!("expected an API error, got {}", other),
+        }
+
+        // Bodies that are not JSON are passed through as the message
+        let err = LlmError::from_response(502, "Bad Gateway".to_string());
+        assert_eq!(err.to_string(), "API error (502): Bad Gateway");
+    }
+
+    #[test]
+    fn test_sse_lines_split_across_chunks() {
+        let mut lines = SseLines::default();
+        assert!(lines.push(b"event: delta\r\nda").is_empty());
+        assert_eq!(lines.push(b"ta: {\"a\":1}\r\n\r\ndata: [DO"), vec!["{\"a\":1}".to_string()]);
+        assert_eq!(lines.push(b"NE]\n"), vec!["[DONE]".to_string()]);
+
+        // Multi-byte characters split between chunks survive
+        let bytes = "data: caf\u{e9}\n".as_bytes();
+        let (first, second) = bytes.split_at(bytes.len() - 2);
+        assert!(lines.push(first).is_empty());
+        assert_eq!(lines.push(second), vec!["caf\u{e9}".to_string()]);
+    }
+}
@@ -0,0 +1,248 @@
+//! OpenAI chat completions client
+//!
+//! An alternative to Claude for AI calls, selected with LLM_PROVIDER=openai.
+//! The system prompt is sent as the first message, as the API expects.
+
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::{sse_text_stream, LlmClient, LlmError, LlmResponse, Message, TextStream};
+
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// OpenAI API client
+#[derive(Clone)]
+pub struct OpenAiClient {
+    client: Client,
+    api_key: String,
+    model: String,
+    base_url: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    max_tokens: i32,
+    messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Usage {
+    prompt_tokens: i32,
+    completion_tokens: i32,
+}
+
+/// A streamed chunk; the reply text is in the first choice's delta
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    choices: Vec<StreamChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+impl OpenAiClient {
+    pub fn new(api_key: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model: DEFAULT_MODEL.to_string(),
+            base_url: "https://api.openai.com".to_string(),
+        }
+    }
+
+    /// Use another model than the default
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Send requests to another API host, e.g. a local stub in tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    async fn post(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+        stream: bool,
+    ) -> Result<reqwest::Response, LlmError> {
+        let system = system_prompt.map(|prompt| Message {
+            role: "system".to_string(),
+            content: prompt.to_string(),
+        });
+
+        let request = ChatRequest {
+            model: self.model.clone(),
+            max_tokens,
+            messages: system.into_iter().chain(messages).collect(),
+            temperature,
+            stream,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| LlmError::NetworkError(e.to_string()))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(LlmError::from_response(status.as_u16(), body));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiClient {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn is_configured(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    async fn complete(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+    ) -> Result<LlmResponse, LlmError> {
+        let response = self.post(system_prompt, messages, max_tokens, temperature, false).await?;
+        let body = response
+            .text()
+            .await
+            .map_err(|e| LlmError::NetworkError(e.to_string()))?;
+
+        let chat: ChatResponse = serde_json::from_str(&body)
+            .map_err(|e| LlmError::ParseError(e.to_string()))?;
+        let choice = chat
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LlmError::ParseError("Response has no choices".to_string()))?;
+        let (input_tokens, output_tokens) = chat
+            .usage
+            .map(|usage| (usage.prompt_tokens, usage.completion_tokens))
+            .unwrap_or_default();
+
+        Ok(LlmResponse {
+            text: choice.message.content.unwrap_or_default(),
+            input_tokens,
+            output_tokens,
+            stop_reason: choice.finish_reason,
+        })
+    }
+
+    async fn stream(
+        &self,
+        system_prompt: Option<&str>,
+        messages: Vec<Message>,
+        max_tokens: i32,
+        temperature: Option<f64>,
+    ) -> Result<TextStream, LlmError> {
+        let response = self.post(system_prompt, messages, max_tokens, temperature, true).await?;
+        Ok(sse_text_stream(response, |data| {
+            let chunk: StreamChunk = serde_json::from_str(data).ok()?;
+            chunk.choices.into_iter().next()?.delta.content
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::telnyx::stub::stub_http;
+    use futures::StreamExt;
+    use std::sync::Arc;
+
+    fn message(content: &str) -> Message {
+        Message {
+            role: "user".to_string(),
+            content: content.to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_against_chat_completions() {
+        let reply = r#"{"id":"chatcmpl-1","object":"chat.completion",
+            "choices":[{"index":0,"message":{"role":"assistant","content":"Hi Maria, how are you?"},"finish_reason":"stop"}],
+            "usage":{"prompt_tokens":14,"completion_tokens":7,"total_tokens":21}}"#;
+        let (url, request) = stub_http("application/json", reply).await;
+        let client: Arc<dyn LlmClient> =
+            Arc::new(OpenAiClient::new("sk-openai".to_string()).with_model("gpt-test".to_string()).with_base_url(url));
+
+        let response = client.complete(Some("Be brief"), vec![message("Hello")], 50, Some(0.5)).await.unwrap();
+        assert_eq!(response.text, "Hi Maria, how are you?");
+        assert_eq!((response.input_tokens, response.output_tokens), (14, 7));
+        assert_eq!(response.stop_reason.as_deref(), Some("stop"));
+
+        // The system prompt goes first, as a message
+        let (request_line, body) = request.await.unwrap();
+        assert!(request_line.starts_with("POST /v1/chat/completions "), "{}", request_line);
+        assert_eq!(body["model"], "gpt-test");
+        assert_eq!(body["messages"][0], serde_json::json!({ "role": "system", "content": "Be brief" }));
+        assert_eq!(body["messages"][1], serde_json::json!({ "role": "user", "content": "Hello" }));
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_deltas_until_done() {
+        let reply = concat!(
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Sure, \"}}]}\n\n",
+            "data: {\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Tuesday works.\"}}]}\n\n",
+            "data: [DONE]\n\n",
+        );
+        let (url, request) = stub_http("text/event-stream", reply).await;
+        let client = OpenAiClient::new("sk-openai".to_string()).with_base_url(url);
+
+        let stream = client.stream(None, vec![message("Is Tuesday ok?")], 50, None).await.unwrap();
+        let pieces: Vec<String> = stream.map(|piece| piece.unwrap()).collect().await;
+        assert_eq!(pieces, vec!["Sure, ", "Tuesday works."]);
+
+        let (_, body) = request.await.unwrap();
+        assert_eq!(body["stream"], true);
+        assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+    }
+}
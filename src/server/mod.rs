@@ -12,25 +12,48 @@ pub mod db;
 pub mod telnyx;
 pub mod sip;
 pub mod auth;
-pub mod claude;
+pub mod llm;
 pub mod automation;
 pub mod ai_call_handler;
 pub mod email;
+pub mod export;
+pub mod routing;
+pub mod rate_limit;
+pub mod shutdown;
+pub mod scheduler;
+pub mod events;
+pub mod health;
+pub mod error;
+pub mod telemetry;
+pub mod cors;
+pub mod access;
+pub mod bridge;
+pub mod campaign_stop;
+pub mod call_watchdog;
+pub mod amd;
+pub mod tts;
+pub mod sentiment;
+pub mod request_id;
+pub mod notifications;
+pub mod webrtc;
+pub mod call_log;
+pub mod callbacks;
 
 use axum::{
     routing::{get, post, put},
     Router,
     extract::State,
     http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use sqlx::PgPool;
 use std::sync::Arc;
-use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
 use tower_http::trace::TraceLayer;
+use tracing::Instrument;
 
 use crate::models::*;
+use error::ApiError;
 use serde::{Deserialize, Serialize};
 
 /// Application state shared across all routes
@@ -38,58 +61,129 @@ use serde::{Deserialize, Serialize};
 pub struct AppState {
     pub db: PgPool,
     pub telnyx: telnyx::TelnyxClient,
-    pub claude: claude::ClaudeClient,
+    pub llm: Arc<dyn llm::LlmClient>,
     pub automation: Arc<automation::AutomationManager>,
     pub ai_handler: Arc<ai_call_handler::AiCallHandler>,
     pub email: email::EmailService,
     pub jwt_secret: String,
     pub caller_id: String,
+    /// Callback URL given to Telnyx on dial, ending in `/{env}` when WEBHOOK_ENV is set
     pub webhook_url: String,
-    pub sip_username: String,
-    pub sip_password: String,
-    /// Optional SIP User Agent for direct SIP trunk calls
-    pub sip_agent: Option<Arc<tokio::sync::RwLock<sip::SipUserAgent>>>,
+    /// This server's environment name; webhooks on another env's path are rejected
+    pub webhook_env: Option<String>,
+    /// Browser phone credentials and signaling
+    pub webrtc: webrtc::WebRtcConfig,
+    /// Default hold music played when a call is put on hold
+    pub hold_music_url: Option<String>,
+    /// SIP trunks for direct SIP calls, if any are configured
+    pub sip_trunks: Option<Arc<sip::SipTrunks>>,
+    /// Failed login tracking for brute-force protection
+    pub login_lockout: Arc<auth::lockout::LoginLockout>,
+    pub password_hasher: auth::password::PasswordHasher,
+    pub session_config: auth::SessionConfig,
+    /// Rules for new passwords
+    pub password_policy: auth::PasswordPolicy,
+    /// Country assumed for phone numbers dialed without a calling code
+    pub default_country: String,
+    /// Lead status changes allowed without an admin override
+    pub lead_transitions: LeadStatusTransitions,
+    /// Route calls needing a skill to any free agent when no skilled one is
+    pub skill_fallback: bool,
+    /// How long after hanging up a queued caller is called back; None turns callbacks off
+    pub callback_window: Option<chrono::Duration>,
+    /// Inbound calls waiting for a free agent
+    pub call_queue: Arc<routing::CallQueue>,
+    /// Inbound callers choosing from an IVR menu
+    pub ivr_sessions: Arc<routing::IvrSessions>,
+    /// Answered calls waiting on answering machine detection
+    pub pending_amd: Arc<amd::PendingAmd>,
+    /// How long to wait for AMD before treating the call as human
+    pub amd_timeout: std::time::Duration,
+    /// Real-time events for connected clients
+    pub events: events::EventBus,
+    /// SIP user agent events for `/api/sip/events`
+    pub sip_events: events::EventBus<SipEvent>,
+    /// Prometheus recorder handle rendered by `/metrics`
+    pub metrics: metrics_exporter_prometheus::PrometheusHandle,
+    /// Bearer token scrapers must present; None disables `/metrics`
+    pub metrics_token: Option<String>,
+    /// Last dependency probe, shared by `/api/health/ready` requests
+    pub readiness: Arc<health::ReadinessCache>,
 }
 
 /// Create the Axum router with all API routes
 pub fn create_router(state: AppState) -> Router {
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([
-            Method::GET,
-            Method::POST,
-            Method::PUT,
-            Method::PATCH,
-            Method::DELETE,
-            Method::OPTIONS,
-        ])
-        .allow_headers(Any);
+    let cors = cors::cors_layer_from_env();
+
+    // Per-IP limits; each layer's bucket is shared by the routes it covers
+    let auth_limit = rate_limit::RateLimitLayer::new(
+        rate_limit::RateLimitConfig::per_minute_from_env("RATE_LIMIT_AUTH_PER_MINUTE", 10),
+    );
+    let dial_limit = rate_limit::RateLimitLayer::new(
+        rate_limit::RateLimitConfig::per_minute_from_env("RATE_LIMIT_DIAL_PER_MINUTE", 30),
+    );
+    let webrtc_limit = rate_limit::RateLimitLayer::new(
+        rate_limit::RateLimitConfig::per_minute_from_env("RATE_LIMIT_WEBRTC_PER_MINUTE", 10),
+    );
 
     Router::new()
         // Health check
         .route("/api/health", get(health_check))
+        .route("/api/health/ready", get(health::readiness_handler))
+        .route("/metrics", get(telemetry::metrics_handler))
 
         // Auth routes
-        .route("/api/auth/login", post(auth::login))
-        .route("/api/auth/register", post(auth::register))
-        .route("/api/auth/verify-email", post(auth::verify_email))
-        .route("/api/auth/resend-verification", post(auth::resend_verification))
+        .route("/api/auth/login", post(auth::login).layer(auth_limit.clone()))
+        .route("/api/auth/refresh", post(auth::refresh))
+        .route("/api/auth/logout", post(auth::logout))
+        .route("/api/auth/sessions", get(auth::sessions::list_sessions).delete(auth::sessions::revoke_all_sessions))
+        .route("/api/auth/sessions/{id}", axum::routing::delete(auth::sessions::revoke_session))
+        .route("/api/auth/register", post(auth::register).layer(auth_limit.clone()))
+        .route("/api/auth/verify-email", post(auth::verify_email).layer(auth_limit.clone()))
+        .route("/api/auth/resend-verification", post(auth::resend_verification).layer(auth_limit.clone()))
         .route("/api/auth/invite", post(auth::invite_user))
-        .route("/api/auth/invitation-details", post(auth::get_invitation_details))
-        .route("/api/auth/register-invitation", post(auth::register_invitation))
+        .route("/api/auth/invitation-details", post(auth::get_invitation_details).layer(auth_limit.clone()))
+        .route("/api/auth/register-invitation", post(auth::register_invitation).layer(auth_limit))
+
+        // User management routes (supervisor/admin)
+        .route("/api/users", get(list_users))
+        .route("/api/users/me/notifications", get(get_my_notifications).put(update_my_notifications))
+        .route("/api/admin/email-outbox", get(get_email_outbox))
+        .route("/api/users/{id}", axum::routing::delete(delete_user))
+        .route("/api/users/{id}/role", put(update_user_role))
+        .route("/api/users/{id}/disable", put(set_user_disabled))
 
         // Lead routes
         .route("/api/leads", get(get_leads).post(create_lead))
         .route("/api/leads/my", get(get_my_leads))
+        .route("/api/leads/export", get(export_leads))
         .route("/api/leads/{id}", get(get_lead).put(update_lead).delete(delete_lead))
-        .route("/api/leads/{id}/notes", post(add_lead_note))
+        .route("/api/leads/{id}/notes", get(get_lead_notes).post(add_lead_note))
+        .route("/api/leads/{id}/notes/{note_id}", axum::routing::delete(delete_lead_note))
         .route("/api/leads/{id}/status", put(update_lead_status))
         .route("/api/leads/{id}/assign", put(assign_lead))
+        .route("/api/leads/assign-bulk", post(assign_leads_bulk))
+        .route("/api/leads/{id}/messages", get(get_lead_messages))
+        .route("/api/leads/{id}/timeline", get(get_lead_timeline))
+        .route("/api/leads/{id}/restore", post(restore_lead))
+        .route("/api/leads/{id}/enrich", post(enrich_lead))
+        .route("/api/leads/{id}/tags", get(get_lead_tags).post(add_lead_tag))
+        .route("/api/leads/{id}/tags/{tag}", axum::routing::delete(remove_lead_tag))
 
         // Agent routes
         .route("/api/agents", get(get_agents).post(create_agent))
         .route("/api/agents/{id}", get(get_agent).put(update_agent))
+        .route("/api/agents/me/status", put(set_my_status))
         .route("/api/agents/{id}/status", put(update_agent_status))
+        .route("/api/agents/{id}/schedule", get(get_agent_schedule).put(update_agent_schedule))
+        .route("/api/agents/{id}/greeting", get(get_agent_greeting).put(update_agent_greeting))
+        .route("/api/agents/{id}/call-limit", get(get_agent_call_limit).put(update_agent_call_limit))
+        .route("/api/agents/{id}/skills", get(get_agent_skills).put(update_agent_skills))
+        .route("/api/inbound-numbers", get(get_inbound_numbers))
+        .route(
+            "/api/inbound-numbers/{phone}",
+            axum::routing::put(set_inbound_number).delete(remove_inbound_number),
+        )
 
         // Campaign routes
         .route("/api/campaigns", get(get_campaigns).post(create_campaign))
@@ -97,31 +191,72 @@ pub fn create_router(state: AppState) -> Router {
         .route("/api/campaigns/{id}/start", post(start_campaign))
         .route("/api/campaigns/{id}/pause", post(pause_campaign))
         .route("/api/campaigns/{id}/stop", post(stop_campaign))
+        .route("/api/campaigns/{id}/schedule", post(schedule_campaign))
+        .route("/api/campaigns/{id}/ai-settings", get(get_campaign_ai_settings).put(upsert_campaign_ai_settings))
+        .route("/api/campaigns/{id}/leads", get(get_campaign_leads).post(attach_campaign_leads))
+        .route("/api/campaigns/{id}/leads/{lead_id}", axum::routing::delete(detach_campaign_lead))
+        .route("/api/campaigns/{id}/progress", get(get_campaign_progress))
 
         // Call routes (Telnyx integration)
-        .route("/api/calls/dial", post(dial_call))
-        .route("/api/calls/direct", post(direct_dial))
+        .route("/api/calls", get(search_calls))
+        .route("/api/search", get(search))
+        .route("/api/calls/dial", post(dial_call).layer(dial_limit.clone()))
+        .route("/api/calls/direct", post(direct_dial).layer(dial_limit.clone()))
+        .route("/api/calls/abandoned", get(get_abandoned_calls))
         .route("/api/calls/{id}/hangup", post(hangup_call))
         .route("/api/calls/{id}/transfer", post(transfer_call))
         .route("/api/calls/{id}/hold", post(hold_call))
         .route("/api/calls/{id}/unhold", post(unhold_call))
+        .route("/api/calls/{id}/recording/start", post(start_call_recording))
+        .route("/api/calls/{id}/recording/pause", post(pause_call_recording))
+        .route("/api/calls/{id}/recording/resume", post(resume_call_recording))
+        .route("/api/calls/{id}/recording/stop", post(stop_call_recording))
         .route("/api/calls/{id}", get(get_call))
-
+        .route("/api/calls/{id}/events", get(get_call_events))
+        .route("/api/calls/{id}/conference", get(get_conference_participants))
+        .route("/api/calls/{id}/conference/join", post(join_conference))
+        .route("/api/calls/{id}/conference/leave", post(leave_conference))
+        .route("/api/calls/{id}/monitor", post(monitor_call))
+        .route("/api/calls/{id}/disposition", put(set_call_disposition))
+        .route("/api/dispositions", get(get_dispositions).post(create_disposition))
+        .route("/api/dispositions/{id}", axum::routing::delete(delete_disposition))
+        .route("/api/calls/monitoring", get(get_monitoring_sessions))
+        .route("/api/queue", get(get_call_queue))
+        .route("/api/ivr-menus", get(get_ivr_menus).put(upsert_ivr_menu))
+        .route("/api/ivr-menus/{id}", axum::routing::delete(delete_ivr_menu))
+
+        // Real-time events
+        .route("/api/ws", get(events::ws_handler))
+
+        // SMS
+        .route("/api/sms/send", post(send_sms))
         // Telnyx webhooks
         .route("/api/webhooks/telnyx", post(handle_telnyx_webhook))
+        .route("/api/webhooks/telnyx/{env}", post(handle_telnyx_env_webhook))
+        .route("/api/tts/{token}", get(tts::serve_clip))
 
         // Statistics
         .route("/api/stats/realtime", get(get_realtime_stats))
         .route("/api/statistics/realtime", get(get_realtime_stats))
         .route("/api/stats/agent/{id}", get(get_agent_stats))
+        .route("/api/stats/agent/{id}/occupancy", get(get_agent_occupancy))
+        .route("/api/stats/dispositions", get(get_disposition_stats))
+        .route("/api/stats/history", get(get_stats_history))
+        .route("/api/stats/export", get(export_stats_csv))
+        .route("/api/stats/leaderboard", get(get_leaderboard))
 
         // WebRTC config
-        .route("/api/config/webrtc", get(get_webrtc_config))
+        .route("/api/config/webrtc", get(get_webrtc_config).layer(webrtc_limit))
+        .route("/api/config/phone", get(get_phone_config))
 
         // SIP trunk routes
         .route("/api/sip/status", get(get_sip_status))
-        .route("/api/sip/dial", post(sip_dial))
+        .route("/api/sip/dial", post(sip_dial).layer(dial_limit))
         .route("/api/sip/hangup", post(sip_hangup))
+        .route("/api/sip/events", get(events::sip_events_handler))
+        .route("/api/sip/calls/{id}/quality", get(get_sip_call_quality))
+        .route("/api/sip/calls/{id}/hold", post(sip_hold))
+        .route("/api/sip/calls/{id}/unhold", post(sip_unhold))
 
         // AI Settings routes
         .route("/api/ai/settings", get(get_all_ai_settings))
@@ -135,8 +270,11 @@ pub fn create_router(state: AppState) -> Router {
         .route("/api/campaigns/{id}/automation/stop", post(stop_campaign_automation))
         .route("/api/campaigns/{id}/automation/status", get(get_automation_status))
 
+        .route_layer(axum::middleware::from_fn(telemetry::track_http))
         .layer(cors)
-        .layer(TraceLayer::new_for_http())
+        .layer(request_id::propagate_layer())
+        .layer(TraceLayer::new_for_http().make_span_with(request_id::make_span))
+        .layer(request_id::set_layer())
         .with_state(Arc::new(state))
 }
 
@@ -146,22 +284,17 @@ async fn health_check() -> &'static str {
 }
 
 // WebRTC config
-#[derive(serde::Serialize)]
-struct WebRTCConfig {
-    sip_username: String,
-    sip_password: String,
-    caller_id: String,
-}
-
 async fn get_webrtc_config(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-) -> Json<WebRTCConfig> {
-    Json(WebRTCConfig {
-        sip_username: state.sip_username.clone(),
-        sip_password: state.sip_password.clone(),
-        caller_id: state.caller_id.clone(),
-    })
+) -> Result<Json<webrtc::WebRtcCredentials>, ApiError> {
+    Ok(Json(webrtc::issue(&state, claims.sub, chrono::Utc::now()).await?))
+}
+
+/// Country the server reads numbers without a calling code as, so the
+/// dialer validates them the same way
+async fn get_phone_config(State(state): State<Arc<AppState>>, _claims: auth::Claims) -> Json<PhoneConfig> {
+    Json(PhoneConfig { default_country: state.default_country.clone() })
 }
 
 // ============== SIP Trunk Routes ==============
@@ -172,39 +305,63 @@ struct SipStatusResponse {
     registered: bool,
     trunk_host: Option<String>,
     caller_id: Option<String>,
+    trunks: Vec<SipTrunkStatus>,
+}
+
+#[derive(Debug, Serialize)]
+struct SipTrunkStatus {
+    trunk_host: String,
+    caller_id: String,
+    status: String,
+    registered: bool,
+}
+
+fn sip_state_name(state: sip::AgentState) -> &'static str {
+    match state {
+        sip::AgentState::Disconnected => "disconnected",
+        sip::AgentState::Connecting => "connecting",
+        sip::AgentState::Registering => "registering",
+        sip::AgentState::Registered => "registered",
+        sip::AgentState::Failed => "failed",
+    }
 }
 
 async fn get_sip_status(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
 ) -> Json<SipStatusResponse> {
-    if let Some(ref sip_agent) = state.sip_agent {
-        let agent = sip_agent.read().await;
-        let agent_state = agent.state().await;
-        let config = agent.config();
-
-        let status = match agent_state {
-            sip::AgentState::Disconnected => "disconnected",
-            sip::AgentState::Connecting => "connecting",
-            sip::AgentState::Registering => "registering",
-            sip::AgentState::Registered => "registered",
-            sip::AgentState::Failed => "failed",
-        };
-
-        Json(SipStatusResponse {
-            status: status.to_string(),
-            registered: agent_state == sip::AgentState::Registered,
-            trunk_host: Some(config.trunk_host.clone()),
-            caller_id: Some(config.caller_id.clone()),
-        })
-    } else {
-        Json(SipStatusResponse {
+    let Some(ref sip_trunks) = state.sip_trunks else {
+        return Json(SipStatusResponse {
             status: "not_configured".to_string(),
             registered: false,
             trunk_host: None,
             caller_id: None,
-        })
+            trunks: Vec::new(),
+        });
+    };
+
+    let mut trunks = Vec::new();
+    for agent in sip_trunks.agents() {
+        let agent = agent.read().await;
+        let agent_state = agent.state().await;
+        let config = agent.config();
+        trunks.push(SipTrunkStatus {
+            trunk_host: config.trunk_host.clone(),
+            caller_id: config.caller_id.clone(),
+            status: sip_state_name(agent_state).to_string(),
+            registered: agent_state == sip::AgentState::Registered,
+        });
     }
+
+    // Report the first registered trunk, or the primary one if none are
+    let primary = trunks.iter().find(|t| t.registered).or(trunks.first());
+    Json(SipStatusResponse {
+        status: primary.map(|t| t.status.clone()).unwrap_or_else(|| "not_configured".to_string()),
+        registered: primary.is_some_and(|t| t.registered),
+        trunk_host: primary.map(|t| t.trunk_host.clone()),
+        caller_id: primary.map(|t| t.caller_id.clone()),
+        trunks,
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -221,14 +378,12 @@ struct SipDialResponse {
 
 async fn sip_dial(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     Json(req): Json<SipDialRequest>,
 ) -> Json<SipDialResponse> {
-    if let Some(ref sip_agent) = state.sip_agent {
-        let agent = sip_agent.read().await;
-
+    if let Some(ref sip_trunks) = state.sip_trunks {
         // Check if registered
-        if !agent.is_registered().await {
+        if !sip_trunks.any_registered().await {
             return Json(SipDialResponse {
                 success: false,
                 call_id: None,
@@ -236,18 +391,17 @@ async fn sip_dial(
             });
         }
 
-        // Format phone number (ensure E.164 format)
-        let phone = if req.phone_number.starts_with('+') {
-            req.phone_number.clone()
-        } else if req.phone_number.len() == 10 {
-            format!("+1{}", req.phone_number)
-        } else {
-            req.phone_number.clone()
+        let Some(phone) = normalize_phone(&req.phone_number, &state.default_country) else {
+            return Json(SipDialResponse {
+                success: false,
+                call_id: None,
+                error: Some("Invalid phone number".to_string()),
+            });
         };
 
-        match agent.dial(&phone).await {
-            Ok(call_id) => {
-                tracing::info!("SIP call initiated: {} -> {}", call_id, phone);
+        match sip_trunks.dial(&phone).await {
+            Ok((trunk, call_id)) => {
+                tracing::info!("SIP call initiated on trunk {}: {} -> {}", trunk, call_id, phone);
                 Json(SipDialResponse {
                     success: true,
                     call_id: Some(call_id),
@@ -285,13 +439,11 @@ struct SipHangupResponse {
 
 async fn sip_hangup(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     Json(req): Json<SipHangupRequest>,
 ) -> Json<SipHangupResponse> {
-    if let Some(ref sip_agent) = state.sip_agent {
-        let agent = sip_agent.read().await;
-
-        match agent.hangup(&req.call_id).await {
+    if let Some(ref sip_trunks) = state.sip_trunks {
+        match sip_trunks.hangup(&req.call_id).await {
             Ok(()) => {
                 tracing::info!("SIP call hung up: {}", req.call_id);
                 Json(SipHangupResponse {
@@ -315,584 +467,2727 @@ async fn sip_hangup(
     }
 }
 
-// ============== Lead Routes ==============
+/// RTCP quality metrics for an active SIP call. `null` until the remote
+/// party sends its first receiver report.
+async fn get_sip_call_quality(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(call_id): axum::extract::Path<String>,
+) -> Result<Json<Option<sip::CallQualityMetrics>>, StatusCode> {
+    let sip_trunks = state.sip_trunks.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    match sip_trunks.call_quality(&call_id).await {
+        Ok(metrics) => Ok(Json(metrics)),
+        Err(sip::SipError::CallNotFound(_)) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            tracing::error!("Failed to read call quality: {:?}", e);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
 
-async fn get_leads(
+/// Put an active SIP call on hold
+async fn sip_hold(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
-) -> Result<Json<Vec<Lead>>, StatusCode> {
-    db::leads::get_all(&state.db)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    _claims: auth::Claims,
+    axum::extract::Path(call_id): axum::extract::Path<String>,
+) -> Response {
+    let Some(sip_trunks) = state.sip_trunks.as_ref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    sip_hold_response(sip_trunks.hold(&call_id).await)
 }
 
-async fn get_my_leads(
+/// Take a held SIP call off hold
+async fn sip_unhold(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
-) -> Result<Json<Vec<Lead>>, StatusCode> {
-    // Get the agent for this user
-    let agent = db::agents::get_by_user(&state.db, claims.sub)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    _claims: auth::Claims,
+    axum::extract::Path(call_id): axum::extract::Path<String>,
+) -> Response {
+    let Some(sip_trunks) = state.sip_trunks.as_ref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    sip_hold_response(sip_trunks.unhold(&call_id).await)
+}
 
-    match agent {
-        Some(a) => {
-            // Return leads assigned to this agent
-            db::leads::get_by_agent(&state.db, a.id)
-                .await
-                .map(Json)
-                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-        }
-        None => {
-            // User has no agent - return empty list
-            Ok(Json(vec![]))
+/// 409 with the reason when the call can't be held or resumed right now
+fn sip_hold_response(result: Result<(), sip::SipError>) -> Response {
+    match result {
+        Ok(()) => StatusCode::OK.into_response(),
+        Err(sip::SipError::CallNotFound(_)) => StatusCode::NOT_FOUND.into_response(),
+        Err(sip::SipError::InvalidState(reason)) => ApiError::Conflict(reason).into_response(),
+        Err(e) => {
+            tracing::error!("SIP hold/unhold failed: {:?}", e);
+            StatusCode::BAD_GATEWAY.into_response()
         }
     }
 }
 
-async fn get_lead(
+// ============== User Management Routes ==============
+
+#[derive(Debug, Deserialize)]
+struct UserListQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+async fn list_users(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Lead>, StatusCode> {
-    db::leads::get_by_id(&state.db, id)
+    axum::extract::Query(query): axum::extract::Query<UserListQuery>,
+) -> Result<Json<UserListResponse>, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(25).clamp(1, 100);
+
+    let users = db::users::list(&state.db, per_page, (page - 1) * per_page)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let total = db::users::count(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(UserListResponse {
+        users: users.iter().map(|u| u.to_managed()).collect(),
+        total,
+        page,
+        per_page,
+    }))
 }
 
-async fn create_lead(
-    State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
-    Json(req): Json<CreateLeadRequest>,
-) -> Result<Json<Lead>, StatusCode> {
-    db::leads::create(&state.db, req)
+/// Load the target user and make sure the caller may manage them.
+/// Only admins may manage admin accounts, and nobody may manage themselves here.
+async fn get_manageable_user(
+    state: &AppState,
+    claims: &auth::Claims,
+    id: i64,
+) -> Result<User, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if claims.sub == id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let user = db::users::get_by_id(&state.db, id)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if user.role == UserRole::Admin && !claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    Ok(user)
 }
 
-async fn update_lead(
+async fn update_user_role(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<CreateLeadRequest>,
-) -> Result<Json<Lead>, StatusCode> {
-    db::leads::update(&state.db, id, req)
+    Json(req): Json<UpdateUserRoleRequest>,
+) -> Result<Json<ManagedUser>, StatusCode> {
+    get_manageable_user(&state, &claims, id).await?;
+
+    if req.role == UserRole::Admin && !claims.is_admin() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let user = db::users::update_role(&state.db, id, req.role)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(user.to_managed()))
 }
 
-async fn delete_lead(
+async fn set_user_disabled(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    db::leads::delete(&state.db, id)
+    Json(req): Json<SetUserDisabledRequest>,
+) -> Result<Json<ManagedUser>, StatusCode> {
+    get_manageable_user(&state, &claims, id).await?;
+
+    let user = db::users::set_active(&state.db, id, !req.disabled)
         .await
-        .map(|_| StatusCode::NO_CONTENT)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // End existing sessions so the user can't keep refreshing
+    if req.disabled {
+        if let Err(e) = db::refresh_tokens::revoke_all_for_user(&state.db, id).await {
+            tracing::error!("Failed to revoke sessions for disabled user {}: {}", id, e);
+        }
+    }
+
+    Ok(Json(user.to_managed()))
 }
 
-async fn add_lead_note(
+async fn delete_user(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<AddNoteRequest>,
-) -> Result<Json<Lead>, StatusCode> {
-    db::leads::add_note(&state.db, id, &req.content)
+) -> Result<StatusCode, StatusCode> {
+    get_manageable_user(&state, &claims, id).await?;
+
+    db::users::delete(&state.db, id)
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|e| {
+            // Users still referenced by agents or invitations can't be removed; disable them instead
+            if e.as_database_error().and_then(|d| d.code()).as_deref() == Some("23503") {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    Ok(StatusCode::NO_CONTENT)
 }
 
-async fn update_lead_status(
+// ============== Lead Routes ==============
+
+#[derive(Debug, Default, Deserialize)]
+struct LeadListQuery {
+    #[serde(default)]
+    include_deleted: bool,
+    /// Comma-separated tags; only leads with all of them are returned
+    tags: Option<String>,
+    status: Option<LeadStatus>,
+}
+
+impl LeadListQuery {
+    fn filter(&self) -> db::leads::LeadFilter {
+        db::leads::LeadFilter {
+            status: self.status,
+            tags: self.tags.as_deref().map(parse_tag_filter).unwrap_or_default(),
+            include_deleted: self.include_deleted,
+        }
+    }
+}
+
+async fn get_leads(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<UpdateStatusRequest>,
-) -> Result<Json<Lead>, StatusCode> {
-    db::leads::update_status(&state.db, id, req.status)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    axum::extract::Query(query): axum::extract::Query<LeadListQuery>,
+) -> Result<Json<Vec<Lead>>, ApiError> {
+    if query.include_deleted && !claims.is_admin() {
+        return Err(ApiError::forbidden());
+    }
+
+    Ok(Json(db::leads::get_filtered(&state.db, &query.filter()).await?))
 }
 
 #[derive(Debug, Deserialize)]
-struct AssignLeadRequest {
-    #[serde(rename = "agentId")]
-    agent_id: i64,
+struct LeadExportParams {
+    #[serde(default)]
+    format: export::LeadExportFormat,
 }
 
-async fn assign_lead(
+/// Download every lead matching the list filters as CSV or JSON (supervisors only)
+///
+/// Rows are streamed from the database into the response body, so large
+/// exports don't have to fit in memory.
+async fn export_leads(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<AssignLeadRequest>,
-) -> Result<Json<Lead>, StatusCode> {
-    db::leads::assign(&state.db, id, req.agent_id)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-}
+    axum::extract::Query(query): axum::extract::Query<LeadListQuery>,
+    axum::extract::Query(params): axum::extract::Query<LeadExportParams>,
+) -> Result<impl axum::response::IntoResponse, ApiError> {
+    if !claims.is_supervisor_or_above() || (query.include_deleted && !claims.is_admin()) {
+        return Err(ApiError::forbidden());
+    }
 
-// ============== Agent Routes ==============
+    let filter = query.filter();
+    let format = params.format;
+    let db = state.db.clone();
+    let (tx, rx) = tokio::sync::mpsc::channel(32);
+    tokio::spawn(async move {
+        export::send_leads(db::leads::stream_export(&db, &filter), format, tx).await;
+    });
+
+    let filename = format!(
+        "attachment; filename=\"leads-{}.{}\"",
+        chrono::Utc::now().format("%Y%m%d"),
+        format.extension()
+    );
 
-async fn get_agents(
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, format.content_type().to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, filename),
+        ],
+        axum::body::Body::from_stream(tokio_stream::wrappers::ReceiverStream::new(rx)),
+    ))
+}
+
+async fn get_my_leads(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-) -> Result<Json<Vec<Agent>>, StatusCode> {
-    db::agents::get_all(&state.db)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+) -> Result<Json<Vec<Lead>>, ApiError> {
+    // Get the agent for this user
+    let agent = db::agents::get_by_user(&state.db, claims.sub).await?;
+
+    match agent {
+        Some(a) => {
+            // Return leads assigned to this agent
+            Ok(Json(db::leads::get_by_agent(&state.db, a.id).await?))
+        }
+        None => {
+            // User has no agent - return empty list
+            Ok(Json(vec![]))
+        }
+    }
 }
 
-async fn get_agent(
+async fn get_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Agent>, StatusCode> {
-    db::agents::get_by_id(&state.db, id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+) -> Result<Json<LeadDetail>, ApiError> {
+    let lead = access::ensure_lead_access(&state, &claims, id).await?;
+    let note_entries = db::lead_notes::get_for_lead(&state.db, id).await?;
+    Ok(Json(LeadDetail { lead, note_entries }))
 }
 
-async fn create_agent(
+async fn create_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    Json(req): Json<CreateAgentRequest>,
-) -> Result<Json<Agent>, StatusCode> {
-    db::agents::create(&state.db, req)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    Json(req): Json<CreateLeadRequest>,
+) -> Result<Json<Lead>, ApiError> {
+    req.validate(&state.default_country).map_err(ApiError::Unprocessable)?;
+    Ok(Json(db::leads::create(&state.db, req, claims.sub).await?))
 }
 
-async fn update_agent(
+async fn update_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<CreateAgentRequest>,
-) -> Result<Json<Agent>, StatusCode> {
-    db::agents::update(&state.db, id, req)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    Json(req): Json<CreateLeadRequest>,
+) -> Result<Json<Lead>, ApiError> {
+    req.validate(&state.default_country).map_err(ApiError::Unprocessable)?;
+    access::ensure_lead_access(&state, &claims, id).await?;
+    Ok(Json(db::leads::update(&state.db, id, req).await?))
 }
 
-async fn update_agent_status(
+#[derive(Debug, Default, Deserialize)]
+struct DeleteLeadQuery {
+    #[serde(default)]
+    purge: bool,
+}
+
+/// Soft-delete a lead; admins can pass `?purge=true` to remove it permanently
+async fn delete_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<UpdateAgentStatusRequest>,
-) -> Result<Json<Agent>, StatusCode> {
-    db::agents::update_status(&state.db, id, req.status)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
-}
+    axum::extract::Query(query): axum::extract::Query<DeleteLeadQuery>,
+) -> Result<StatusCode, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
 
-// ============== Campaign Routes ==============
+    let deleted = if query.purge {
+        if !claims.is_admin() {
+            return Err(ApiError::forbidden());
+        }
+        // Leads still referenced by calls can't be removed permanently (409)
+        db::leads::purge(&state.db, id).await?
+    } else {
+        db::leads::delete(&state.db, id).await?
+    };
 
-async fn get_campaigns(
+    if deleted {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found("Lead"))
+    }
+}
+
+async fn restore_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-) -> Result<Json<Vec<Campaign>>, StatusCode> {
-    db::campaigns::get_all(&state.db)
-        .await
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Lead>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    db::leads::restore(&state.db, id)
+        .await?
         .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .ok_or_else(|| ApiError::not_found("Lead"))
 }
 
-async fn get_campaign(
+/// Look up the lead's number with Telnyx, fill in a missing name from the
+/// caller name and store the carrier details
+async fn enrich_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Campaign>, StatusCode> {
-    db::campaigns::get_by_id(&state.db, id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+) -> Result<Json<LeadEnrichment>, ApiError> {
+    let mut lead = access::ensure_lead_access(&state, &claims, id).await?;
+    let phone = normalize_phone(&lead.phone, &state.default_country)
+        .ok_or_else(|| ApiError::Validation("Lead has no valid phone number".to_string()))?;
+
+    let number_info = state.telnyx.lookup_number(&phone).await.map_err(|e| {
+        tracing::error!("Number lookup failed for lead {}: {}", id, e);
+        ApiError::Internal("Number lookup failed".to_string())
+    })?;
+    db::leads::save_number_info(&state.db, id, &number_info).await?;
+
+    let name_missing = lead.first_name.as_deref().is_none_or(str::is_empty)
+        || lead.last_name.as_deref().is_none_or(str::is_empty);
+    if let (true, Some((first, last))) = (name_missing, number_info.caller_names()) {
+        lead = db::leads::fill_missing_name(&state.db, id, &first, last.as_deref()).await?;
+    }
+
+    Ok(Json(LeadEnrichment {
+        lead,
+        is_mobile: number_info.is_mobile(),
+        number_info,
+    }))
 }
 
-async fn create_campaign(
+async fn add_lead_note(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    Json(req): Json<CreateCampaignRequest>,
-) -> Result<Json<Campaign>, StatusCode> {
-    db::campaigns::create(&state.db, req)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<AddNoteRequest>,
+) -> Result<Json<LeadNote>, ApiError> {
+    let content = req.validated_content().map_err(ApiError::Unprocessable)?;
+    access::ensure_lead_access(&state, &claims, id).await?;
+
+    let note = db::lead_notes::create(&state.db, id, claims.sub, content).await?;
+
+    // Best-effort like record_lead_event
+    if let Err(e) = db::lead_events::record_note(&state.db, &note).await {
+        tracing::warn!("Failed to record lead event for lead {}: {}", id, e);
+    }
+
+    Ok(Json(note))
 }
 
-async fn update_campaign(
+async fn get_lead_notes(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<CreateCampaignRequest>,
-) -> Result<Json<Campaign>, StatusCode> {
-    db::campaigns::update(&state.db, id, req)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+) -> Result<Json<Vec<LeadNote>>, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
+    Ok(Json(db::lead_notes::get_for_lead(&state.db, id).await?))
 }
 
-async fn start_campaign(
+async fn delete_lead_note(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path((id, note_id)): axum::extract::Path<(i64, i64)>,
+) -> Result<StatusCode, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
+    let note = db::lead_notes::get_by_id(&state.db, id, note_id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Note"))?;
+    if !access::note_delete_allowed(&claims, &note) {
+        return Err(ApiError::forbidden());
+    }
+
+    db::lead_notes::delete(&state.db, note.id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UpdateStatusQuery {
+    #[serde(default)]
+    force: bool,
+}
+
+/// Change a lead's status. Moves the transition matrix doesn't allow are
+/// rejected with 422 unless an admin passes `?force=true`.
+async fn update_lead_status(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Campaign>, StatusCode> {
-    db::campaigns::update_status(&state.db, id, CampaignStatus::Active)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+    axum::extract::Query(query): axum::extract::Query<UpdateStatusQuery>,
+    Json(req): Json<UpdateStatusRequest>,
+) -> Result<Json<Lead>, ApiError> {
+    let previous = access::ensure_lead_access(&state, &claims, id).await?;
+
+    if query.force {
+        if !claims.is_admin() {
+            return Err(ApiError::forbidden());
+        }
+    } else if let Err(message) = state.lead_transitions.check(previous.status, req.status) {
+        return Err(ApiError::Unprocessable(vec![FieldError::new("status", &message)]));
+    }
+
+    let lead = db::leads::update_status(&state.db, id, req.status).await?;
+
+    if previous.status != lead.status {
+        let mut description = LeadEvent::status_change_description(previous.status, lead.status);
+        if !state.lead_transitions.allows(previous.status, lead.status) {
+            description.push_str(" (admin override)");
+        }
+        record_lead_event(&state, id, LeadEventType::StatusChange, &description, Some(claims.sub), None).await;
+    }
+
+    Ok(Json(lead))
 }
 
-async fn pause_campaign(
+async fn get_lead_timeline(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Campaign>, StatusCode> {
-    db::campaigns::update_status(&state.db, id, CampaignStatus::Paused)
-        .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+) -> Result<Json<Vec<LeadEvent>>, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
+    Ok(Json(db::lead_events::get_timeline(&state.db, id).await?))
 }
 
-async fn stop_campaign(
+/// Timeline writes are best-effort; a failure must not fail the action being recorded
+async fn record_lead_event(
+    state: &AppState,
+    lead_id: i64,
+    event_type: LeadEventType,
+    description: &str,
+    user_id: Option<i64>,
+    call_id: Option<i64>,
+) {
+    if let Err(e) = db::lead_events::record(&state.db, lead_id, event_type, description, user_id, call_id).await {
+        tracing::warn!("Failed to record lead event for lead {}: {}", lead_id, e);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct AssignLeadRequest {
+    #[serde(rename = "agentId")]
+    agent_id: i64,
+}
+
+async fn assign_lead(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Campaign>, StatusCode> {
-    db::campaigns::update_status(&state.db, id, CampaignStatus::Completed)
-        .await
+    Json(req): Json<AssignLeadRequest>,
+) -> Result<Json<Lead>, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
+    let lead = db::leads::assign(&state.db, id, req.agent_id).await?;
+
+    let notified = lead.clone();
+    tokio::spawn(async move { notifications::notify_lead_assigned(&state, &notified).await });
+
+    Ok(Json(lead))
+}
+
+async fn assign_leads_bulk(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    Json(req): Json<BulkAssignRequest>,
+) -> Result<Json<Vec<LeadAssignment>>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    if req.lead_ids.is_empty() {
+        return Err(ApiError::Validation("No leads to assign".to_string()));
+    }
+
+    let assignments = db::leads::assign_bulk(&state.db, &req.lead_ids, req.strategy)
+        .await?
+        .ok_or_else(|| ApiError::Validation("No agents are available to assign leads to".to_string()))?;
+
+    // One email per agent, however many leads they were given
+    let per_agent = notifications::leads_by_agent(&assignments);
+    tokio::spawn(async move {
+        for (agent_id, lead_ids) in per_agent {
+            let mut leads = Vec::with_capacity(lead_ids.len());
+            for lead_id in lead_ids {
+                if let Ok(Some(lead)) = db::leads::get_by_id(&state.db, lead_id).await {
+                    leads.push(lead);
+                }
+            }
+            notifications::notify_leads_assigned(&state, agent_id, &leads).await;
+        }
+    });
+
+    Ok(Json(assignments))
+}
+
+async fn get_lead_tags(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
+    Ok(Json(db::lead_tags::get_for_lead(&state.db, id).await?))
+}
+
+async fn add_lead_tag(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<AddTagRequest>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    let tag = normalize_tag(&req.tag)
+        .ok_or_else(|| ApiError::Validation(format!("Tags must be 1-{} characters without commas", MAX_TAG_LEN)))?;
+
+    access::ensure_lead_access(&state, &claims, id).await?;
+
+    Ok(Json(db::lead_tags::add(&state.db, id, &tag).await?))
+}
+
+async fn remove_lead_tag(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path((id, tag)): axum::extract::Path<(i64, String)>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    access::ensure_lead_access(&state, &claims, id).await?;
+    let removed = match normalize_tag(&tag) {
+        Some(tag) => db::lead_tags::remove(&state.db, id, &tag).await?,
+        None => false,
+    };
+    if !removed {
+        return Err(ApiError::not_found("Tag"));
+    }
+
+    Ok(Json(db::lead_tags::get_for_lead(&state.db, id).await?))
+}
+
+// ============== Agent Routes ==============
+
+async fn get_agents(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+) -> Result<Json<Vec<Agent>>, ApiError> {
+    Ok(Json(db::agents::get_all(&state.db).await?))
+}
+
+async fn get_agent(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Agent>, ApiError> {
+    db::agents::get_by_id(&state.db, id)
+        .await?
         .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .ok_or_else(|| ApiError::not_found("Agent"))
 }
 
-// ============== Call Routes ==============
+async fn create_agent(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    Json(req): Json<CreateAgentRequest>,
+) -> Result<Json<Agent>, ApiError> {
+    req.validate().map_err(ApiError::Unprocessable)?;
+    Ok(Json(db::agents::create(&state.db, req).await?))
+}
 
-async fn dial_call(
+async fn update_agent(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<CreateAgentRequest>,
+) -> Result<Json<Agent>, ApiError> {
+    req.validate().map_err(ApiError::Unprocessable)?;
+    Ok(Json(db::agents::update(&state.db, id, req).await?))
+}
+
+async fn update_agent_status(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<UpdateAgentStatusRequest>,
+) -> Result<Json<Agent>, ApiError> {
+    Ok(Json(apply_agent_status(&state, id, req.status, None).await?))
+}
+
+/// Set the signed-in agent's own status, e.g. going on break with a reason
+async fn set_my_status(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    Json(req): Json<DialRequest>,
-) -> Result<Json<DialResponse>, StatusCode> {
-    // Get lead phone number
-    let lead = db::leads::get_by_id(&state.db, req.lead_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+    Json(req): Json<SetMyStatusRequest>,
+) -> Result<Json<Agent>, ApiError> {
+    req.validate().map_err(ApiError::Validation)?;
 
-    // Initiate call via Telnyx
-    let dial_result = state.telnyx.dial(
-        &lead.phone,
-        &state.caller_id,
-        Some(&state.webhook_url),
-    )
-        .await
-        .map_err(|e| {
-            tracing::error!("Telnyx dial error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
+    let agent = db::agents::get_by_user(&state.db, claims.sub)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Agent"))?;
+
+    Ok(Json(apply_agent_status(&state, agent.id, req.status, req.reason).await?))
+}
+
+/// Change an agent's status within their shift rules and hand a newly
+/// available agent the next queued call
+async fn apply_agent_status(
+    state: &Arc<AppState>,
+    id: i64,
+    status: AgentStatus,
+    reason: Option<StatusReason>,
+) -> Result<Agent, ApiError> {
+    // Agents can only go Ready during their scheduled shift
+    let schedule = db::agent_schedules::get(&state.db, id).await?;
+    if !schedule.allows_status(status, chrono::Utc::now()) {
+        return Err(ApiError::Forbidden("Agents can only go Ready during their scheduled shift".to_string()));
+    }
+
+    let agent = db::agents::request_status(&state.db, id, status, reason)
+        .await?
+        .ok_or_else(|| {
+            ApiError::Conflict(format!("Agents on a call can't go {} until it ends", status.display_name()))
         })?;
 
-    // Create call record
-    let call = db::calls::create(
-        &state.db,
-        req.lead_id,
-        req.agent_id,
-        &dial_result.call_control_id,
-        &state.caller_id,
-        &lead.phone,
-    )
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    // A newly available agent takes the longest-waiting queued call, or
+    // else calls back someone who gave up waiting
+    if agent.status == AgentStatus::Ready {
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = routing::dispatch_queued_calls(&state).await {
+                tracing::error!("Failed to dispatch queued calls: {}", e);
+            }
+            if let Err(e) = callbacks::dial_due(&state).await {
+                tracing::error!("Failed to dial abandoned call callbacks: {}", e);
+            }
+        });
+    }
 
-    // Update agent status to OnCall
-    let _ = db::agents::update_status(&state.db, req.agent_id, AgentStatus::OnCall).await;
+    Ok(agent)
+}
 
-    Ok(Json(DialResponse {
-        call_id: call.id,
-        call_control_id: dial_result.call_control_id,
-        status: "initiated".to_string(),
-    }))
+async fn get_agent_schedule(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<AgentSchedule>, ApiError> {
+    Ok(Json(db::agent_schedules::get(&state.db, id).await?))
 }
 
-#[derive(Debug, Deserialize)]
-struct DirectDialRequest {
-    #[serde(rename = "phoneNumber")]
-    phone_number: String,
-    #[serde(rename = "agentId")]
-    agent_id: Option<i64>,
+async fn update_agent_schedule(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<UpdateAgentScheduleRequest>,
+) -> Result<Json<AgentSchedule>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    req.validate().map_err(ApiError::Validation)?;
+
+    if db::agents::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Agent"));
+    }
+
+    Ok(Json(db::agent_schedules::replace(&state.db, id, &req).await?))
 }
 
-/// Direct dial a phone number without a lead
-async fn direct_dial(
+async fn get_agent_greeting(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<AgentGreeting>, ApiError> {
+    let greeting_template = db::agents::get_greeting_template(&state.db, id).await?;
+    let greeting_audio_url = db::agents::get_greeting_audio_url(&state.db, id).await?;
+    Ok(Json(AgentGreeting { greeting_template, greeting_audio_url }))
+}
+
+async fn update_agent_greeting(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
-    Json(req): Json<DirectDialRequest>,
-) -> Result<Json<DialResponse>, StatusCode> {
-    // Initiate call via Telnyx
-    let dial_result = state.telnyx.dial(
-        &req.phone_number,
-        &state.caller_id,
-        Some(&state.webhook_url),
-    )
-        .await
-        .map_err(|e| {
-            tracing::error!("Telnyx dial error: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        })?;
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<UpdateAgentGreetingRequest>,
+) -> Result<Json<AgentGreeting>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
 
-    // Create call record without lead
-    let call = db::calls::create_direct(
-        &state.db,
-        req.agent_id,
-        &dial_result.call_control_id,
-        &state.caller_id,
-        &req.phone_number,
-    )
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if db::agents::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Agent"));
+    }
 
-    // Update agent status to OnCall if agent provided
-    if let Some(agent_id) = req.agent_id {
-        let _ = db::agents::update_status(&state.db, agent_id, AgentStatus::OnCall).await;
+    let audio_url = req
+        .greeting_audio_url
+        .as_ref()
+        .map(|url| url.as_deref().map(str::trim).filter(|u| !u.is_empty()));
+    if audio_url.flatten().is_some_and(|url| !is_supported_greeting_audio(url)) {
+        return Err(ApiError::Unprocessable(vec![FieldError::new(
+            "greetingAudioUrl",
+            "Greeting audio must be an http(s) link to an MP3 or WAV file",
+        )]));
     }
 
-    Ok(Json(DialResponse {
-        call_id: call.id,
-        call_control_id: dial_result.call_control_id,
-        status: "initiated".to_string(),
-    }))
+    let template = req.greeting_template.as_deref().map(str::trim).filter(|t| !t.is_empty());
+    db::agents::set_greeting_template(&state.db, id, template).await?;
+    let greeting_audio_url = match audio_url {
+        Some(url) => {
+            db::agents::set_greeting_audio_url(&state.db, id, url).await?;
+            url.map(str::to_string)
+        }
+        None => db::agents::get_greeting_audio_url(&state.db, id).await?,
+    };
+
+    Ok(Json(AgentGreeting { greeting_template: template.map(str::to_string), greeting_audio_url }))
 }
 
-async fn hangup_call(
+async fn get_agent_call_limit(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<AgentCallLimit>, ApiError> {
+    let max_concurrent_calls = db::agents::get_max_concurrent_calls(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Agent"))?;
+    Ok(Json(AgentCallLimit { max_concurrent_calls }))
+}
+
+async fn update_agent_call_limit(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    let call = db::calls::get_by_id(&state.db, id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+    Json(req): Json<AgentCallLimit>,
+) -> Result<Json<AgentCallLimit>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    req.validate().map_err(ApiError::Unprocessable)?;
 
-    if let Some(call_control_id) = &call.call_control_id {
-        state.telnyx.hangup(call_control_id)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if db::agents::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Agent"));
+    }
+    db::agents::set_max_concurrent_calls(&state.db, id, req.max_concurrent_calls).await?;
+
+    Ok(Json(req))
+}
+
+async fn get_agent_skills(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<AgentSkills>, ApiError> {
+    if db::agents::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Agent"));
+    }
+    let skills = db::agent_skills::get_for_agent(&state.db, id).await?;
+    Ok(Json(AgentSkills { skills }))
+}
+
+async fn update_agent_skills(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<AgentSkills>,
+) -> Result<Json<AgentSkills>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    let skills = req.normalized().map_err(ApiError::Unprocessable)?;
+
+    if db::agents::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Agent"));
+    }
+    let skills = db::agent_skills::set_for_agent(&state.db, id, &skills).await?;
+    Ok(Json(AgentSkills { skills }))
+}
+
+async fn get_inbound_numbers(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+) -> Result<Json<Vec<InboundNumber>>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    Ok(Json(db::agent_skills::get_inbound_numbers(&state.db).await?))
+}
+
+/// Require a skill for calls to an inbound number
+async fn set_inbound_number(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(phone): axum::extract::Path<String>,
+    Json(req): Json<SetInboundNumberRequest>,
+) -> Result<Json<InboundNumber>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    let mut errors = Vec::new();
+    let phone = normalize_phone(&phone, &state.default_country);
+    if phone.is_none() {
+        errors.push(FieldError::new("phoneNumber", "Invalid phone number"));
+    }
+    let skill = normalize_skill(&req.required_skill);
+    if skill.is_none() {
+        errors.push(FieldError::new("requiredSkill", &format!("Skills must be 1-{} characters", MAX_SKILL_LEN)));
+    }
+    let (Some(phone), Some(skill)) = (phone, skill) else {
+        return Err(ApiError::Unprocessable(errors));
+    };
+
+    Ok(Json(db::agent_skills::set_inbound_number(&state.db, &phone, &skill).await?))
+}
+
+async fn remove_inbound_number(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(phone): axum::extract::Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    let phone = normalize_phone(&phone, &state.default_country).ok_or_else(|| ApiError::not_found("Inbound number"))?;
+    if !db::agent_skills::remove_inbound_number(&state.db, &phone).await? {
+        return Err(ApiError::not_found("Inbound number"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// ============== Campaign Routes ==============
+
+async fn get_campaigns(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+) -> Result<Json<Vec<Campaign>>, StatusCode> {
+    let mut campaigns = db::campaigns::get_all(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let progress = db::campaigns::get_progress_all(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    for campaign in &mut campaigns {
+        campaign.progress = Some(progress.get(&campaign.id).copied().unwrap_or_default());
+    }
+
+    Ok(Json(campaigns))
+}
+
+async fn get_campaign(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Campaign>, StatusCode> {
+    let mut campaign = db::campaigns::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    campaign.progress = Some(
+        db::campaigns::get_progress(&state.db, id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    );
+
+    Ok(Json(campaign))
+}
+
+#[derive(Debug, Deserialize)]
+struct CampaignLeadsQuery {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+async fn get_campaign_leads(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    axum::extract::Query(query): axum::extract::Query<CampaignLeadsQuery>,
+) -> Result<Json<CampaignLeadPage>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(25).clamp(1, 100);
+
+    if db::campaigns::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Campaign"));
+    }
+
+    let leads = db::campaigns::get_leads(&state.db, id, per_page, (page - 1) * per_page).await?;
+    let total = db::campaigns::get_progress(&state.db, id).await?.total;
+
+    Ok(Json(CampaignLeadPage {
+        leads,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+async fn attach_campaign_leads(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<AttachLeadsRequest>,
+) -> Result<Json<CampaignProgress>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    let lead_ids = req.unique_ids();
+    if lead_ids.is_empty() {
+        return Err(ApiError::Validation("No leads to attach".to_string()));
+    }
+    if db::campaigns::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Campaign"));
+    }
+
+    db::campaigns::attach_leads(&state.db, id, &lead_ids).await?;
+    Ok(Json(db::campaigns::get_progress(&state.db, id).await?))
+}
+
+async fn detach_campaign_lead(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path((id, lead_id)): axum::extract::Path<(i64, i64)>,
+) -> Result<Json<CampaignProgress>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    if !db::campaigns::detach_lead(&state.db, id, lead_id).await? {
+        return Err(ApiError::not_found("Campaign lead"));
+    }
+    Ok(Json(db::campaigns::get_progress(&state.db, id).await?))
+}
+
+/// Lead counts, the current dial rate and when the campaign should finish.
+/// Supervisors and admins only.
+async fn get_campaign_progress(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<CampaignProgressReport>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    if db::campaigns::get_by_id(&state.db, id).await?.is_none() {
+        return Err(ApiError::not_found("Campaign"));
+    }
+
+    let progress = db::campaigns::get_progress(&state.db, id).await?;
+    let dial_rate = db::stats::get_dial_rate(&state.db, id).await?;
+    Ok(Json(CampaignProgressReport::new(progress, dial_rate, chrono::Utc::now())))
+}
+
+async fn create_campaign(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    Json(mut req): Json<CreateCampaignRequest>,
+) -> Result<Json<Campaign>, StatusCode> {
+    req.required_skill = req.required_skill.as_deref().and_then(normalize_skill);
+    db::campaigns::create(&state.db, req)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn update_campaign(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(mut req): Json<CreateCampaignRequest>,
+) -> Result<Json<Campaign>, StatusCode> {
+    req.required_skill = req.required_skill.as_deref().and_then(normalize_skill);
+    db::campaigns::update(&state.db, id, req)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn start_campaign(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Campaign>, StatusCode> {
+    db::campaigns::update_status(&state.db, id, CampaignStatus::Active)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn pause_campaign(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Campaign>, StatusCode> {
+    db::campaigns::update_status(&state.db, id, CampaignStatus::Paused)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Complete a campaign, stopping its automation and hanging up its calls in progress
+async fn stop_campaign(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Campaign>, StatusCode> {
+    let campaign = db::campaigns::update_status(&state.db, id, CampaignStatus::Completed)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Not running is fine; there may still be calls to drain
+    let _ = state.automation.stop_campaign(id).await;
+    campaign_stop::drain_campaign(state.clone(), id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(campaign))
+}
+
+async fn schedule_campaign(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<ScheduleCampaignRequest>,
+) -> Result<Json<Campaign>, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if !req.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    db::campaigns::set_schedule(&state.db, id, &req)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+// ============== Call Routes ==============
+
+async fn dial_call(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    Json(req): Json<DialRequest>,
+) -> Result<Json<DialResponse>, StatusCode> {
+    let capacity = db::agents::get_call_capacity(&state.db, req.agent_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    check_agent_capacity(capacity)?;
+
+    // Get lead phone number
+    let lead = db::leads::get_by_id(&state.db, req.lead_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let phone = normalize_phone(&lead.phone, &state.default_country).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let campaign = match lead.campaign_id {
+        Some(campaign_id) => db::campaigns::get_by_id(&state.db, campaign_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        None => None,
+    };
+    let amd_mode = campaign.as_ref().map(|c| c.dial_amd_mode()).unwrap_or_default();
+    let caller_id = campaign
+        .as_ref()
+        .map(|c| c.caller_id_for(&phone, &state.caller_id))
+        .unwrap_or(state.caller_id.as_str());
+
+    // Create the call record first so its id can travel in client_state.
+    // Capacity is checked again as it's stored, in case another dial took
+    // the agent's last slot since the check above.
+    let call = db::calls::create_within_capacity(
+        &state.db,
+        Some(req.lead_id),
+        req.agent_id,
+        lead.campaign_id,
+        caller_id,
+        &phone,
+    )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::CONFLICT)?;
+
+    let dial_result = place_telnyx_call(&state, &call, &phone, caller_id, amd_mode).await?;
+
+    // Update agent status to OnCall
+    let _ = db::agents::update_status(&state.db, req.agent_id, AgentStatus::OnCall).await;
+
+    record_lead_event(
+        &state,
+        req.lead_id,
+        LeadEventType::Call,
+        &format!("Outbound call placed to {}", phone),
+        Some(claims.sub),
+        Some(call.id),
+    ).await;
+
+    Ok(Json(DialResponse {
+        call_id: call.id,
+        call_control_id: dial_result.call_control_id,
+        status: "initiated".to_string(),
+    }))
+}
+
+/// 404 for an unknown agent, 409 when the agent can't take another call
+fn check_agent_capacity(capacity: Option<CallCapacity>) -> Result<(), StatusCode> {
+    match capacity {
+        None => Err(StatusCode::NOT_FOUND),
+        Some(capacity) if !capacity.can_take_call() => Err(StatusCode::CONFLICT),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Dial a call that already has a record, tagging it with its ids in
+/// `client_state`. A failed dial marks the record failed.
+async fn place_telnyx_call(
+    state: &AppState,
+    call: &Call,
+    to: &str,
+    caller_id: &str,
+    amd_mode: AmdMode,
+) -> Result<telnyx::DialResponse, StatusCode> {
+    let client_state = telnyx::ClientState {
+        call_id: call.id,
+        agent_id: call.agent_id,
+        campaign_id: call.campaign_id,
+        agent_leg: false,
+    };
+
+    let dial_started = std::time::Instant::now();
+    let dial_result = state.telnyx.dial(
+        to,
+        caller_id,
+        Some(&state.webhook_url),
+        amd_mode,
+        Some(&client_state),
+    )
+        .await;
+    telemetry::record_dial(dial_started.elapsed(), dial_result.is_ok());
+
+    match dial_result {
+        Ok(response) => {
+            db::calls::set_control_id(&state.db, call.id, &response.call_control_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            call_log::dial(&state.db, call.id, to, caller_id, &response.call_control_id).await;
+            Ok(response)
+        }
+        Err(e) => {
+            tracing::error!("Telnyx dial error: {:?}", e);
+            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Failed).await;
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DirectDialRequest {
+    #[serde(rename = "phoneNumber")]
+    phone_number: String,
+    #[serde(rename = "agentId")]
+    agent_id: Option<i64>,
+    /// Campaign whose dialing settings (e.g. answering machine detection) apply
+    #[serde(rename = "campaignId")]
+    campaign_id: Option<i64>,
+}
+
+/// Direct dial a phone number without a lead
+async fn direct_dial(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    Json(req): Json<DirectDialRequest>,
+) -> Result<Json<DialResponse>, StatusCode> {
+    let phone_number = normalize_phone(&req.phone_number, &state.default_country)
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let campaign = match req.campaign_id {
+        Some(campaign_id) => Some(
+            db::campaigns::get_by_id(&state.db, campaign_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::NOT_FOUND)?,
+        ),
+        None => None,
+    };
+    let amd_mode = campaign.as_ref().map(|c| c.dial_amd_mode()).unwrap_or_default();
+    let caller_id = campaign
+        .as_ref()
+        .map(|c| c.caller_id_for(&phone_number, &state.caller_id))
+        .unwrap_or(state.caller_id.as_str());
+
+    // Create call record without lead. A dial for an agent takes one of
+    // their call slots, checked as the record is stored like `dial_call`.
+    let call = match req.agent_id {
+        Some(agent_id) => {
+            let capacity = db::agents::get_call_capacity(&state.db, agent_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            check_agent_capacity(capacity)?;
+
+            db::calls::create_within_capacity(&state.db, None, agent_id, req.campaign_id, caller_id, &phone_number)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::CONFLICT)?
+        }
+        None => db::calls::create_outbound(&state.db, None, None, req.campaign_id, caller_id, &phone_number)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+    };
+
+    let dial_result = place_telnyx_call(&state, &call, &phone_number, caller_id, amd_mode).await?;
+
+    // Update agent status to OnCall if agent provided
+    if let Some(agent_id) = req.agent_id {
+        let _ = db::agents::update_status(&state.db, agent_id, AgentStatus::OnCall).await;
+    }
+
+    Ok(Json(DialResponse {
+        call_id: call.id,
+        call_control_id: dial_result.call_control_id,
+        status: "initiated".to_string(),
+    }))
+}
+
+async fn hangup_call(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<StatusCode, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Some(call_control_id) = &call.call_control_id {
+        state.telnyx.hangup(call_control_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        call_log::action(
+            &state.db,
+            id,
+            call_log::HANGUP,
+            serde_json::json!({ "call_control_id": call_control_id, "user_id": claims.sub }),
+        ).await;
+    }
+
+    db::calls::set_ended(&state.db, id, Some("hangup"))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Update agent status back to Ready
+    if let Some(agent_id) = call.agent_id {
+        let _ = db::agents::update_status(&state.db, agent_id, AgentStatus::AfterCall).await;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn transfer_call(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<TransferRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let (Some(call_control_id), Some(target)) = (&call.call_control_id, &req.target_number) {
+        state.telnyx.transfer(call_control_id, target)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        call_log::action(
+            &state.db,
+            id,
+            call_log::TRANSFER,
+            serde_json::json!({ "call_control_id": call_control_id, "to": target, "user_id": claims.sub }),
+        ).await;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn hold_call(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<StatusCode, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let campaign = match call.campaign_id {
+        Some(campaign_id) => db::campaigns::get_by_id(&state.db, campaign_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        None => None,
+    };
+    let hold_music = telnyx::resolve_hold_music(
+        campaign.as_ref().and_then(|c| c.hold_music_url.as_deref()),
+        state.hold_music_url.as_deref(),
+    );
+
+    if let Some(call_control_id) = &call.call_control_id {
+        state.telnyx.hold(call_control_id, hold_music)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+async fn unhold_call(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<StatusCode, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Some(call_control_id) = &call.call_control_id {
+        state.telnyx.unhold(call_control_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    Ok(StatusCode::OK)
+}
+
+/// Most calls a search returns
+const MAX_CALL_SEARCH_RESULTS: i64 = 500;
+
+#[derive(Debug, Default, Deserialize)]
+struct CallSearchQuery {
+    agent_id: Option<i64>,
+    campaign_id: Option<i64>,
+    /// Only calls whose sentiment score is below this, e.g. -0.3 for negative calls
+    sentiment_below: Option<f32>,
+    limit: Option<i64>,
+}
+
+impl CallSearchQuery {
+    fn filter(&self) -> db::calls::CallFilter {
+        db::calls::CallFilter {
+            agent_id: self.agent_id,
+            campaign_id: self.campaign_id,
+            sentiment_below: self.sentiment_below,
+            limit: self.limit.unwrap_or(100).clamp(1, MAX_CALL_SEARCH_RESULTS),
+        }
+    }
+}
+
+async fn search_calls(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<CallSearchQuery>,
+) -> Result<Json<Vec<Call>>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    Ok(Json(db::calls::search(&state.db, &query.filter()).await?))
+}
+
+async fn get_call(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Call>, StatusCode> {
+    db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Webhooks processed and actions taken for a call, oldest first
+async fn get_call_events(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Vec<CallEvent>>, ApiError> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Call"))?;
+
+    if !can_access_call(&state, &claims, &call)
+        .await
+        .map_err(|_| ApiError::Internal("Failed to look up agent".to_string()))?
+    {
+        return Err(ApiError::forbidden());
+    }
+
+    Ok(Json(db::call_events::get_for_call(&state.db, id).await?))
+}
+
+/// Supervisors and admins may act on any call; agents only on their own
+async fn can_access_call(state: &AppState, claims: &auth::Claims, call: &Call) -> Result<bool, StatusCode> {
+    if claims.is_supervisor_or_above() {
+        return Ok(true);
+    }
+
+    let agent = db::agents::get_by_user(&state.db, claims.sub)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(access::call_access_allowed(claims, agent.map(|a| a.id), call))
+}
+
+/// Apply a recording control to a live call the caller is handling
+async fn control_recording(
+    state: &AppState,
+    claims: &auth::Claims,
+    id: i64,
+    action: RecordingAction,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await?
+        .ok_or_else(|| ApiError::not_found("Call"))?;
+
+    if !can_access_call(state, claims, &call)
+        .await
+        .map_err(|_| ApiError::Internal("Failed to look up agent".to_string()))?
+    {
+        return Err(ApiError::forbidden());
+    }
+    if !call.status.is_active() {
+        return Err(ApiError::Conflict("The call has ended".to_string()));
+    }
+    // Only Telnyx calls can be recorded until the SIP recorder lands
+    let Some(call_control_id) = call.call_control_id.as_deref() else {
+        return Err(ApiError::Validation("Recording is not available for this call".to_string()));
+    };
+
+    let current = db::calls::get_recording_state(&state.db, id)
+        .await?
+        .unwrap_or(RecordingState::Off);
+    let next = current.apply(action).map_err(ApiError::Conflict)?;
+
+    let result = match action {
+        RecordingAction::Start => {
+            let campaign = campaign_for_call(state, &call).await;
+            start_recording(&state.telnyx, call_control_id, campaign.as_ref())
+                .await
+                .map(|settings| Some(settings.channels))
+        }
+        RecordingAction::Pause => state.telnyx.pause_recording(call_control_id).await.map(|_| None),
+        RecordingAction::Resume => state.telnyx.resume_recording(call_control_id).await.map(|_| None),
+        RecordingAction::Stop => state.telnyx.stop_recording(call_control_id).await.map(|_| None),
+    };
+    let channels =
+        result.map_err(|e| ApiError::Internal(format!("Failed to {} recording: {:?}", action.as_str(), e)))?;
+
+    if !db::calls::set_recording_state(&state.db, id, claims.sub, action, current, next, channels).await? {
+        return Err(ApiError::Conflict("The recording was changed by someone else".to_string()));
+    }
+
+    Ok(Json(RecordingStatus { call_id: id, state: next }))
+}
+
+/// Start recording with the campaign's channel and format settings, or the
+/// defaults for calls outside a campaign
+async fn start_recording(
+    telnyx: &telnyx::TelnyxClient,
+    call_control_id: &str,
+    campaign: Option<&Campaign>,
+) -> Result<RecordingSettings, telnyx::TelnyxError> {
+    let settings = campaign.map(|c| c.recording_settings()).unwrap_or_default();
+    telnyx.start_recording(call_control_id, settings).await?;
+    Ok(settings)
+}
+
+async fn start_call_recording(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    control_recording(&state, &claims, id, RecordingAction::Start).await
+}
+
+async fn pause_call_recording(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    control_recording(&state, &claims, id, RecordingAction::Pause).await
+}
+
+async fn resume_call_recording(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    control_recording(&state, &claims, id, RecordingAction::Resume).await
+}
+
+async fn stop_call_recording(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<RecordingStatus>, ApiError> {
+    control_recording(&state, &claims, id, RecordingAction::Stop).await
+}
+
+async fn get_conference_participants(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<Json<Vec<ConferenceParticipant>>, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !can_access_call(&state, &claims, &call).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    db::conferences::get_active_participants(&state.db, id)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Get the call's conference id, creating the conference from the call leg on first use
+async fn ensure_conference(state: &AppState, call_id: i64, call_control_id: &str) -> Result<String, StatusCode> {
+    let existing = db::conferences::get_active_conference_id(&state.db, call_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some(conference_id) = existing {
+        return Ok(conference_id);
+    }
+
+    let conference_id = state.telnyx
+        .create_conference(call_control_id, &format!("call-{}", call_id))
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to create conference for call {}: {}", call_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // The original call leg is the first participant
+    db::conferences::add_participant(&state.db, call_id, &conference_id, None, call_control_id, false, None)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(conference_id)
+}
+
+/// Join a call leg to the call's conference, creating the conference from the call on first join
+async fn join_conference(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<JoinConferenceRequest>,
+) -> Result<Json<ConferenceParticipant>, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !can_access_call(&state, &claims, &call).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if !call.status.is_active() {
+        return Err(StatusCode::CONFLICT);
+    }
+    let call_control_id = call.call_control_id.clone().ok_or(StatusCode::BAD_REQUEST)?;
+
+    let conference_id = ensure_conference(&state, id, &call_control_id).await?;
+
+    state.telnyx
+        .join_conference(&req.call_control_id, &conference_id, req.mute)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to join conference {}: {}", conference_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    db::conferences::add_participant(
+        &state.db,
+        id,
+        &conference_id,
+        Some(claims.sub),
+        &req.call_control_id,
+        req.mute,
+        None,
+    )
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn leave_conference(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<LeaveConferenceRequest>,
+) -> Result<StatusCode, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !can_access_call(&state, &claims, &call).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let conference_id = db::conferences::get_active_conference_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    state.telnyx
+        .leave_conference(&req.call_control_id, &conference_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to leave conference {}: {}", conference_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let removed = db::conferences::remove_participant(&state.db, id, &req.call_control_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if removed {
+        Ok(StatusCode::OK)
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Supervisor joins a call to listen, whisper to the agent, or barge in
+async fn monitor_call(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<MonitorCallRequest>,
+) -> Result<Json<ConferenceParticipant>, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !call.status.is_active() {
+        return Err(StatusCode::CONFLICT);
+    }
+    let call_control_id = call.call_control_id.clone().ok_or(StatusCode::BAD_REQUEST)?;
+
+    let supervisor_mode = match req.mode {
+        MonitorMode::Listen => telnyx::SupervisorMode::Monitor,
+        MonitorMode::Whisper => {
+            let agent_leg = req.agent_call_control_id.as_deref().ok_or(StatusCode::BAD_REQUEST)?;
+            telnyx::SupervisorMode::Whisper(agent_leg)
+        }
+        MonitorMode::Barge => telnyx::SupervisorMode::Barge,
+    };
+
+    let conference_id = ensure_conference(&state, id, &call_control_id).await?;
+
+    state.telnyx
+        .join_conference_as_supervisor(&req.call_control_id, &conference_id, supervisor_mode)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to join conference {} for monitoring: {}", conference_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    tracing::info!("User {} monitoring call {} in {} mode", claims.sub, id, req.mode.as_str());
+
+    db::conferences::add_participant(
+        &state.db,
+        id,
+        &conference_id,
+        Some(claims.sub),
+        &req.call_control_id,
+        req.mode == MonitorMode::Listen,
+        Some(req.mode),
+    )
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn get_monitoring_sessions(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+) -> Result<Json<Vec<ConferenceParticipant>>, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    db::conferences::get_active_monitoring(&state.db)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Inbound callers currently waiting for an agent
+async fn get_call_queue(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+) -> Result<Json<Vec<QueuedCallInfo>>, StatusCode> {
+    Ok(Json(state.call_queue.snapshot(chrono::Utc::now()).await))
+}
+
+/// Recently abandoned inbound calls and their callbacks
+async fn get_abandoned_calls(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+) -> Result<Json<Vec<AbandonedCall>>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+
+    Ok(Json(db::abandoned_calls::get_recent(&state.db, 100).await?))
+}
+
+/// IVR menus on inbound numbers
+async fn get_ivr_menus(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+) -> Result<Json<Vec<IvrMenu>>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    Ok(Json(db::ivr::get_all(&state.db).await?))
+}
+
+/// Set the menu on an inbound number, replacing any it had
+async fn upsert_ivr_menu(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    Json(req): Json<UpsertIvrMenuRequest>,
+) -> Result<Json<IvrMenu>, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    req.validate().map_err(ApiError::Validation)?;
+    Ok(Json(db::ivr::upsert(&state.db, &req).await?))
+}
+
+async fn delete_ivr_menu(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<StatusCode, ApiError> {
+    if !claims.is_supervisor_or_above() {
+        return Err(ApiError::forbidden());
+    }
+    if !db::ivr::delete(&state.db, id).await? {
+        return Err(ApiError::not_found("IVR menu"));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Record the wrap-up disposition for a call and release the agent from AfterCall
+async fn set_call_disposition(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+    Json(req): Json<SetDispositionRequest>,
+) -> Result<Json<Call>, StatusCode> {
+    let call = db::calls::get_by_id(&state.db, id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if !can_access_call(&state, &claims, &call).await? {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let disposition = db::dispositions::get_by_id(&state.db, req.disposition_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    if !disposition.active {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let call = db::calls::set_disposition(&state.db, id, disposition.id, req.wrap_up_notes.as_deref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    if let Some(agent_id) = call.agent_id {
+        let agent = db::agents::get_by_id(&state.db, agent_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if let Some(next) = agent.and_then(|a| a.status.after_disposition()) {
+            db::agents::update_status(&state.db, agent_id, next)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        }
+    }
+
+    Ok(Json(call))
+}
+
+async fn get_dispositions(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+) -> Result<Json<Vec<Disposition>>, StatusCode> {
+    db::dispositions::get_active(&state.db)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn create_disposition(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    Json(req): Json<CreateDispositionRequest>,
+) -> Result<Json<Disposition>, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if req.code.trim().is_empty() || req.label.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    db::dispositions::create(&state.db, req)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn delete_disposition(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Path(id): axum::extract::Path<i64>,
+) -> Result<StatusCode, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    match db::dispositions::deactivate(&state.db, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(_) => Err(StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+// ============== Webhook Handler ==============
+
+/// Telnyx webhook on the plain path, used by servers without `WEBHOOK_ENV`.
+/// A server with an environment only listens on its own path.
+async fn handle_telnyx_webhook(
+    State(state): State<Arc<AppState>>,
+    Json(raw): Json<serde_json::Value>,
+) -> StatusCode {
+    if let Err(status) = check_webhook_env(state.webhook_env.as_deref(), None) {
+        let event_type = raw["data"]["event_type"].as_str().unwrap_or("unknown");
+        tracing::warn!("Rejected Telnyx webhook {} on the path without an environment", event_type);
+        return status;
+    }
+    process_telnyx_webhook(state, raw).await
+}
+
+async fn process_telnyx_webhook(state: Arc<AppState>, raw: serde_json::Value) -> StatusCode {
+    // Parsed from the raw body so the original can be kept in the call's event log
+    let event: telnyx::TelnyxWebhookEvent = match serde_json::from_value(raw.clone()) {
+        Ok(event) => event,
+        Err(e) => {
+            tracing::warn!("Malformed Telnyx webhook: {}", e);
+            return StatusCode::UNPROCESSABLE_ENTITY;
+        }
+    };
+    tracing::info!("Received Telnyx webhook: {}", event.event_type());
+    let _timer = telemetry::WebhookTimer::start(event.event_type());
+
+    // Telnyx retries deliveries; skip events we've already handled
+    if !db::webhook_events::is_first_delivery(&state.db, event.event_id(), event.event_type()).await {
+        tracing::debug!("Skipping duplicate webhook event {:?}", event.event_id());
+        return StatusCode::OK;
+    }
+
+    if event.event_type() == "message.received" {
+        handle_inbound_sms(&state, &event.data.payload).await;
+        return StatusCode::OK;
+    }
+
+    let call_control_id = match event.call_control_id() {
+        Some(id) => id.to_string(),
+        None => return StatusCode::OK,
+    };
+
+    let span = tracing::info_span!("call", call_control_id = %call_control_id);
+    handle_call_event(state, event, raw, call_control_id).instrument(span).await
+}
+
+/// Telnyx webhook sent to an environment's own path. Several environments can
+/// share one Telnyx app; each dials with its own path, so events for another
+/// environment's calls are turned away here instead of acted on.
+async fn handle_telnyx_env_webhook(
+    State(state): State<Arc<AppState>>,
+    axum::extract::Path(env): axum::extract::Path<String>,
+    Json(raw): Json<serde_json::Value>,
+) -> StatusCode {
+    if let Err(status) = check_webhook_env(state.webhook_env.as_deref(), Some(&env)) {
+        let event_type = raw["data"]["event_type"].as_str().unwrap_or("unknown");
+        tracing::warn!("Rejected Telnyx webhook {} for environment '{}'", event_type, env);
+        return status;
+    }
+    process_telnyx_webhook(state, raw).await
+}
+
+/// A webhook path's environment (None for the plain path) must be the one
+/// this server runs as
+fn check_webhook_env(configured: Option<&str>, requested: Option<&str>) -> Result<(), StatusCode> {
+    if configured == requested {
+        Ok(())
+    } else {
+        Err(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Webhook URL for dialing from an environment: WEBHOOK_URL with `/{env}` appended
+fn env_webhook_url(base: &str, env: Option<&str>) -> String {
+    match env {
+        Some(env) if !base.is_empty() => format!("{}/{}", base.trim_end_matches('/'), env),
+        _ => base.to_string(),
+    }
+}
+
+/// Act on an event for one call leg. Runs in a span carrying the leg's
+/// call_control_id, so every log line for the call can be found by it.
+async fn handle_call_event(
+    state: Arc<AppState>,
+    event: telnyx::TelnyxWebhookEvent,
+    raw: serde_json::Value,
+    call_control_id: String,
+) -> StatusCode {
+    // Events for the leg dialed to an agent belong to the lead's call record
+    let agent_leg_call_id = event.client_state().filter(|cs| cs.agent_leg).map(|cs| cs.call_id);
+
+    // Logged before the call is looked up, so events for calls we have no
+    // record of are kept too
+    let log_entry = call_log::webhook(&state.db, agent_leg_call_id, &raw).await;
+
+    // New inbound call: answer it and pick an agent
+    if event.event_type() == "call.initiated" && event.data.payload.direction.as_deref() == Some("incoming") {
+        handle_inbound_call(&state, &call_control_id, &event.data.payload).await;
+        // The call record is created while handling the event
+        if let Ok(Some(call)) = db::calls::get_by_control_id(&state.db, &call_control_id).await {
+            call_log::attach(&state.db, log_entry, call.id).await;
+        }
+        return StatusCode::OK;
+    }
+
+    if let Some(call_id) = agent_leg_call_id {
+        bridge::handle_agent_leg_event(&state, event.event_type(), call_id, &call_control_id).await;
+        return StatusCode::OK;
+    }
+
+    let call = match resolve_webhook_call(&state, &event, &call_control_id).await {
+        Some(c) => c,
+        None => return StatusCode::OK,
+    };
+    call_log::attach(&state.db, log_entry, call.id).await;
+
+    // Handle different event types
+    match event.event_type() {
+        event_type if bridge::is_connect_failure(event_type) => {
+            bridge::handle_connect_failure(&state, event_type, &call).await;
+        }
+        "call.answered" if call.direction == CallDirection::Inbound => {
+            telemetry::record_call_status("answered");
+            let _ = db::calls::set_answered(&state.db, call.id).await;
+            if !routing::start_ivr(&state, &call_control_id).await {
+                connect_inbound_call(&state, &call, &call_control_id).await;
+            }
+        }
+        "call.gather.ended" => {
+            let payload = &event.data.payload;
+            if let Some(input) = routing::gather_input(payload.status.as_deref(), payload.digits.as_deref()) {
+                routing::handle_ivr_input(&state, &call_control_id, input).await;
+            }
+        }
+        "call.initiated" => {
+            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Initiated).await;
+        }
+        "call.ringing" => {
+            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Ringing).await;
+        }
+        "call.answered" => {
+            telemetry::record_call_status("answered");
+            let _ = db::calls::set_answered(&state.db, call.id).await;
+
+            let amd_mode = campaign_for_call(&state, &call).await.map(|c| c.dial_amd_mode()).unwrap_or_default();
+            if amd_mode == AmdMode::Disabled
+                || state.pending_amd.answered(&call_control_id).await == amd::AmdAction::Greet
+            {
+                greet_answered_call(&state, &call, &call_control_id).await;
+            } else {
+                // Go ahead as human if detection takes too long
+                let state = state.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(state.amd_timeout).await;
+                    if state.pending_amd.expire(&call_control_id).await == amd::AmdAction::Greet {
+                        tracing::info!("AMD timed out for call {}, continuing as human", call.id);
+                        let _ = db::calls::set_amd_outcome(&state.db, call.id, AmdOutcome::Timeout).await;
+                        greet_answered_call(&state, &call, &call_control_id).await;
+                    }
+                });
+            }
+        }
+        "call.bridged" => {
+            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Bridged).await;
+        }
+        "call.hangup" => {
+            telemetry::record_call_status("completed");
+
+            // End AI session if active and score it in the background
+            if let Some(session) = state.ai_handler.end_session(&call_control_id).await {
+                let ai_handler = state.ai_handler.clone();
+                tokio::spawn(async move { ai_handler.finish_call(session).await });
+            }
+
+            let reason = match call.disposition.as_deref() {
+                Some("voicemail") => "voicemail",
+                Some(campaign_stop::CAMPAIGN_STOPPED) => campaign_stop::CAMPAIGN_STOPPED,
+                Some(call_watchdog::MAX_DURATION) => call_watchdog::MAX_DURATION,
+                _ => "hangup",
+            };
+            let _ = db::calls::set_ended(&state.db, call.id, Some(reason)).await;
+            let _ = db::conferences::end_conference(&state.db, call.id).await;
+            bridge::hangup_agent_leg(&state, &call).await;
+            if let Some((queued, due_by)) =
+                callbacks::leave_queue(&state.call_queue, &call_control_id, state.callback_window, chrono::Utc::now()).await
+            {
+                routing::publish_queue(&state).await;
+                if let Some(due_by) = due_by {
+                    callbacks::record_abandoned(&state, &queued, due_by).await;
+                }
+            }
+            state.ivr_sessions.remove(&call_control_id).await;
+            state.pending_amd.remove(&call_control_id).await;
+            // Agents on a stopped campaign's calls go straight back to Ready
+            if let Some(agent_id) = call.agent_id.filter(|_| reason != campaign_stop::CAMPAIGN_STOPPED) {
+                let _ = db::agents::update_status(&state.db, agent_id, AgentStatus::AfterCall).await;
+            }
+        }
+        "call.machine.detection.ended" | "call.machine.premium.detection.ended" => {
+            let Some(outcome) = event.data.payload.result.as_deref().and_then(AmdOutcome::from_result) else {
+                return StatusCode::OK;
+            };
+
+            let (action, record) = state.pending_amd.detected(&call_control_id, outcome).await;
+            if record {
+                let _ = db::calls::set_amd_outcome(&state.db, call.id, outcome).await;
+            }
+            match action {
+                amd::AmdAction::Greet => greet_answered_call(&state, &call, &call_control_id).await,
+                amd::AmdAction::Machine => handle_machine_answer(&state, &call, &call_control_id).await,
+                amd::AmdAction::Wait => {}
+            }
+        }
+        "call.machine.greeting.ended" | "call.machine.premium.greeting.ended"
+            if call.disposition.as_deref() == Some("voicemail") =>
+        {
+            if let Some(campaign) = campaign_for_call(&state, &call).await.filter(|c| c.leave_voicemail) {
+                leave_voicemail(&state, &campaign, &call_control_id).await;
+            }
+        }
+        "call.recording.saved" => {
+            if let Some(url) = event.data.payload.recording_url.as_deref() {
+                if let Err(e) = db::calls::set_recording_url(&state.db, call.id, url).await {
+                    tracing::error!("Failed to save recording URL for call {}: {}", call.id, e);
+                }
+            }
+        }
+        // Voicemail delivered
+        "call.speak.ended" | "call.playback.ended"
+            if call.disposition.as_deref() == Some("voicemail") && call.ended_at.is_none() =>
+        {
+            let _ = state.telnyx.hangup(&call_control_id).await;
+        }
+        _ => {}
+    }
+
+    StatusCode::OK
+}
+
+/// A person answered an outbound call: start the AI agent, or greet them
+/// while the human agent's phone rings
+async fn greet_answered_call(state: &AppState, call: &Call, call_control_id: &str) {
+    // Check if this is an AI agent call
+    if let Some(agent_id) = call.agent_id {
+        if state.ai_handler.is_ai_agent(agent_id).await {
+            // Start AI session
+            if let Err(e) = state.ai_handler.start_session(
+                call.id,
+                call_control_id,
+                agent_id,
+                call.lead_id,
+                call.campaign_id,
+            ).await {
+                tracing::error!("Failed to start AI session: {}", e);
+                // Fall back to default greeting
+                play_answered_greeting(state, call, call_control_id, "Hello, please hold while we connect you.").await;
+            }
+        } else {
+            // Non-AI call - greet the lead while the agent's phone rings, then bridge them
+            play_answered_greeting(
+                state,
+                call,
+                call_control_id,
+                "Hello, this is a call from the VoIP CRM system. Please hold while we connect you.",
+            ).await;
+
+            if let Err(e) = bridge::dial_agent_leg(state, call).await {
+                tracing::error!("Failed to dial agent for call {}: {:?}", call.id, e);
+            }
+        }
+    } else {
+        // No agent assigned - play the configured or default greeting
+        play_answered_greeting(state, call, call_control_id, "Hello, please hold while we connect you to an agent.").await;
+    }
+}
+
+/// An answering machine picked up: leave a voicemail if the campaign wants one, otherwise hang up
+async fn handle_machine_answer(state: &AppState, call: &Call, call_control_id: &str) {
+    let campaign = campaign_for_call(state, call).await;
+
+    match campaign.filter(|c| c.leave_voicemail) {
+        Some(campaign) => {
+            let _ = db::calls::mark_voicemail(&state.db, call.id).await;
+            // Modes that wait for the greeting leave it once call.machine.greeting.ended arrives
+            if !campaign.dial_amd_mode().waits_for_greeting() {
+                leave_voicemail(state, &campaign, call_control_id).await;
+            }
+        }
+        None => {
+            let _ = state.telnyx.hangup(call_control_id).await;
+            let _ = db::calls::set_ended(&state.db, call.id, Some("voicemail")).await;
+        }
+    }
+}
+
+/// Campaign a call belongs to, either directly or through its lead
+async fn campaign_for_call(state: &AppState, call: &Call) -> Option<Campaign> {
+    let campaign_id = match (call.campaign_id, call.lead_id) {
+        (Some(id), _) => id,
+        (None, Some(lead_id)) => db::leads::get_by_id(&state.db, lead_id).await.ok().flatten()?.campaign_id?,
+        (None, None) => return None,
+    };
+
+    db::campaigns::get_by_id(&state.db, campaign_id).await.ok().flatten()
+}
+
+async fn leave_voicemail(state: &AppState, campaign: &Campaign, call_control_id: &str) {
+    let result = match campaign.voicemail_drop() {
+        Some(VoicemailDrop::Audio(url)) => state.telnyx.play_audio(call_control_id, url).await,
+        Some(VoicemailDrop::Message(message)) => state.telnyx.speak(call_control_id, message, Some("female")).await,
+        None => return,
+    };
+
+    if let Err(e) = result {
+        tracing::error!("Failed to leave voicemail: {:?}", e);
+        let _ = state.telnyx.hangup(call_control_id).await;
+    }
+}
+
+/// Greet an answered call with the campaign's greeting, the agent's
+/// recorded greeting, or the agent's or default greeting spoken, in that order
+async fn play_answered_greeting(state: &AppState, call: &Call, call_control_id: &str, default: &str) {
+    let (text, from_campaign) = answered_greeting(state, call, default).await;
+    let audio_url = match call.agent_id {
+        Some(agent_id) if !from_campaign => {
+            db::agents::get_greeting_audio_url(&state.db, agent_id).await.ok().flatten()
+        }
+        _ => None,
+    };
+
+    let greeting = telnyx::Greeting::for_call(from_campaign.then_some(text.as_str()), audio_url.as_deref(), &text);
+    if let Err(e) = state.telnyx.greet(call_control_id, greeting).await {
+        tracing::warn!("Failed to greet call {}: {:?}", call.id, e);
+    }
+}
+
+/// Greeting for an answered call: the campaign's template, then the agent's,
+/// then `default`, rendered with the lead, agent and campaign names. The flag
+/// says whether it came from the campaign.
+async fn answered_greeting(state: &AppState, call: &Call, default: &str) -> (String, bool) {
+    let lead = match call.lead_id {
+        Some(lead_id) => db::leads::get_by_id(&state.db, lead_id).await.ok().flatten(),
+        None => None,
+    };
+    let agent = match call.agent_id {
+        Some(agent_id) => db::agents::get_by_id(&state.db, agent_id).await.ok().flatten(),
+        None => None,
+    };
+    let agent_template = match &agent {
+        Some(agent) => db::agents::get_greeting_template(&state.db, agent.id).await.ok().flatten(),
+        None => None,
+    };
+    let campaign = campaign_for_call(state, call).await;
+
+    let mut context = ai_call_handler::GreetingContext::for_lead(lead.as_ref());
+    context.agent_name = agent.map(|a| a.name);
+    context.campaign_name = campaign.as_ref().map(|c| c.name.clone());
+
+    let campaign_template = campaign.and_then(|c| c.greeting_template).filter(|t| !t.trim().is_empty());
+    let from_campaign = campaign_template.is_some();
+    let template = campaign_template.or(agent_template.filter(|t| !t.trim().is_empty()));
+    (ai_call_handler::render_greeting(template.as_deref().unwrap_or(default), &context), from_campaign)
+}
+
+/// Find the call a webhook is about, by the id in `client_state` when present
+/// and otherwise by call control id
+async fn resolve_webhook_call(
+    state: &AppState,
+    event: &telnyx::TelnyxWebhookEvent,
+    call_control_id: &str,
+) -> Option<Call> {
+    if let Some(telnyx::CallLookup::ById(id)) = event.call_lookup() {
+        match db::calls::get_by_id(&state.db, id).await {
+            // The control id may not be stored yet if the webhook beat the dial response
+            Ok(Some(call)) if call.call_control_id.as_deref().is_none_or(|c| c == call_control_id) => {
+                return Some(call);
+            }
+            Ok(_) => tracing::debug!("client_state call {} doesn't match {}", id, call_control_id),
+            Err(e) => tracing::warn!("Failed to load call {} from client_state: {}", id, e),
+        }
+    }
+
+    db::calls::get_by_control_id(&state.db, call_control_id).await.ok().flatten()
+}
+
+async fn handle_inbound_call(state: &AppState, call_control_id: &str, payload: &telnyx::WebhookPayload) {
+    let from = payload.from.as_deref().unwrap_or_default();
+    let to = payload.to.as_deref().unwrap_or_default();
+
+    let menu = match db::ivr::get_by_number(&state.db, to).await {
+        Ok(menu) => menu,
+        Err(e) => {
+            tracing::warn!("Failed to load IVR menu for {}: {}", to, e);
+            None
+        }
+    };
+
+    // With a menu, the caller picks where to go once the call is answered
+    let routed = match &menu {
+        Some(_) => routing::record_inbound_call(state, call_control_id, from, to).await.map(|(call, _)| call),
+        None => routing::route_inbound_call(state, call_control_id, from, to).await.map(|(call, _)| call),
+    };
+
+    let client_state = match routed {
+        Ok(call) => {
+            if let Some(menu) = menu {
+                state.ivr_sessions.insert(call_control_id, routing::IvrSession::new(call.id, menu)).await;
+            }
+            Some(telnyx::ClientState {
+                call_id: call.id,
+                agent_id: call.agent_id,
+                campaign_id: call.campaign_id,
+                agent_leg: false,
+            })
+        }
+        Err(e) => {
+            tracing::error!("Failed to route inbound call from {}: {}", from, e);
+            None
+        }
+    };
+
+    if let Err(e) = state.telnyx.answer(call_control_id, client_state.as_ref()).await {
+        tracing::error!("Failed to answer inbound call: {:?}", e);
+    }
+}
+
+/// Once an inbound call is answered, hand it to the reserved agent or tell the caller to hold
+async fn connect_inbound_call(state: &AppState, call: &Call, call_control_id: &str) {
+    let agent = match call.agent_id {
+        Some(agent_id) => db::agents::get_by_id(&state.db, agent_id).await.ok().flatten(),
+        None => None,
+    };
+
+    match agent.as_ref().and_then(routing::agent_sip_uri) {
+        Some(uri) => {
+            if let Err(e) = state.telnyx.transfer(call_control_id, &uri).await {
+                tracing::error!("Failed to connect inbound call {} to agent: {:?}", call.id, e);
+            }
+        }
+        None => {
+            let _ = state.telnyx.speak(
+                call_control_id,
+                "Thank you for calling. All of our agents are currently busy. Please hold and the next available agent will be with you shortly.",
+                Some("female")
+            ).await;
+        }
+    }
+}
+
+/// Store an inbound SMS, attaching it to the lead whose phone matches the sender
+async fn handle_inbound_sms(state: &AppState, payload: &telnyx::WebhookPayload) {
+    let (Some(from), Some(text)) = (payload.from.as_deref(), payload.text.as_deref()) else {
+        return;
+    };
+    let to = payload.to.as_deref().unwrap_or(&state.caller_id);
+
+    let lead_id = db::leads::get_by_phone(&state.db, from).await.ok().flatten().map(|l| l.id);
+
+    let message = db::messages::NewMessage {
+        lead_id,
+        user_id: None,
+        direction: CallDirection::Inbound,
+        from_number: from,
+        to_number: to,
+        body: text,
+        telnyx_message_id: None,
+    };
+    if let Err(e) = db::messages::create(&state.db, &message).await {
+        tracing::error!("Failed to store inbound SMS from {}: {}", from, e);
+    }
+}
+
+// ============== Notification Preference Routes ==============
+
+async fn get_my_notifications(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+) -> Result<Json<NotificationPreferences>, ApiError> {
+    Ok(Json(db::notification_preferences::get(&state.db, claims.sub).await?))
+}
+
+async fn update_my_notifications(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    Json(prefs): Json<NotificationPreferences>,
+) -> Result<Json<NotificationPreferences>, ApiError> {
+    Ok(Json(db::notification_preferences::upsert(&state.db, claims.sub, &prefs).await?))
+}
+
+// ============== Email Outbox Routes ==============
+
+/// Most outbox entries returned at once
+const EMAIL_OUTBOX_LIMIT: i64 = 200;
+
+async fn get_email_outbox(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<EmailOutboxQuery>,
+) -> Result<Json<Vec<EmailOutboxEntry>>, ApiError> {
+    if !claims.is_admin() {
+        return Err(ApiError::forbidden());
+    }
+    Ok(Json(db::email_outbox::list(&state.db, query.status, EMAIL_OUTBOX_LIMIT).await?))
+}
+
+// ============== Search Routes ==============
+
+#[derive(Debug, Default, Deserialize)]
+struct SearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+/// The global search box: leads, plus agents and campaigns for supervisors
+///
+/// Agents only find leads assigned to them, the same rule as opening a lead.
+/// Queries shorter than two characters return nothing.
+async fn search(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<SearchQuery>,
+) -> Result<Json<SearchResults>, ApiError> {
+    let Some(term) = SearchTerm::parse(&query.q) else {
+        return Ok(Json(SearchResults::default()));
+    };
+
+    if !claims.is_supervisor_or_above() {
+        let Some(agent) = db::agents::get_by_user(&state.db, claims.sub).await? else {
+            return Ok(Json(SearchResults::default()));
+        };
+        let leads = db::leads::search(&state.db, &term, Some(agent.id), MAX_SEARCH_RESULTS).await?;
+        return Ok(Json(SearchResults {
+            leads: term.rank_leads(leads),
+            ..SearchResults::default()
+        }));
+    }
+
+    let (leads, agents, campaigns) = tokio::try_join!(
+        db::leads::search(&state.db, &term, None, MAX_SEARCH_RESULTS),
+        db::agents::search(&state.db, &term, MAX_SEARCH_RESULTS),
+        db::campaigns::search(&state.db, &term, MAX_SEARCH_RESULTS),
+    )?;
+
+    Ok(Json(SearchResults {
+        leads: term.rank_leads(leads),
+        agents,
+        campaigns,
+    }))
+}
+
+// ============== SMS Routes ==============
+
+async fn send_sms(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    Json(req): Json<SendSmsRequest>,
+) -> Result<Json<SmsMessage>, StatusCode> {
+    if req.text.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let lead = match (req.lead_id, req.to.as_deref()) {
+        (Some(lead_id), _) => Some(
+            db::leads::get_by_id(&state.db, lead_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::NOT_FOUND)?,
+        ),
+        (None, Some(to)) => db::leads::get_by_phone(&state.db, to)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    };
+
+    // Honor Do Not Call for texts as well as calls
+    if lead.as_ref().is_some_and(|l| !l.status.can_contact()) {
+        return Err(StatusCode::FORBIDDEN);
     }
 
-    db::calls::set_ended(&state.db, id, Some("hangup"))
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let to = match (&req.to, &lead) {
+        (Some(to), _) => to.clone(),
+        (None, Some(lead)) => lead.phone.clone(),
+        (None, None) => return Err(StatusCode::BAD_REQUEST),
+    };
 
-    // Update agent status back to Ready
-    if let Some(agent_id) = call.agent_id {
-        let _ = db::agents::update_status(&state.db, agent_id, AgentStatus::AfterCall).await;
-    }
+    let message_id = state.telnyx.send_sms(&to, &state.caller_id, &req.text)
+        .await
+        .map_err(|e| {
+            tracing::error!("Telnyx SMS error: {:?}", e);
+            StatusCode::BAD_GATEWAY
+        })?;
 
-    Ok(StatusCode::OK)
+    let message = db::messages::NewMessage {
+        lead_id: lead.map(|l| l.id),
+        user_id: Some(claims.sub),
+        direction: CallDirection::Outbound,
+        from_number: &state.caller_id,
+        to_number: &to,
+        body: &req.text,
+        telnyx_message_id: Some(&message_id),
+    };
+    db::messages::create(&state.db, &message)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-async fn transfer_call(
+async fn get_lead_messages(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-    Json(req): Json<TransferRequest>,
-) -> Result<StatusCode, StatusCode> {
-    let call = db::calls::get_by_id(&state.db, id)
+) -> Result<Json<Vec<SmsMessage>>, StatusCode> {
+    db::messages::get_by_lead(&state.db, id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
 
-    if let (Some(call_control_id), Some(target)) = (&call.call_control_id, &req.target_number) {
-        state.telnyx.transfer(call_control_id, target)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    }
+// ============== Stats Routes ==============
 
-    Ok(StatusCode::OK)
+async fn get_realtime_stats(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut stats = db::stats::get_realtime(&state.db)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    stats["queue_depth"] = serde_json::json!(state.call_queue.len().await);
+    Ok(Json(stats))
 }
 
-async fn hold_call(
+async fn get_agent_stats(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    let call = db::calls::get_by_id(&state.db, id)
+) -> Result<Json<AgentStats>, StatusCode> {
+    db::stats::get_agent_stats(&state.db, id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
-
-    if let Some(call_control_id) = &call.call_control_id {
-        state.telnyx.hold(call_control_id, None)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    }
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
 
-    Ok(StatusCode::OK)
+#[derive(Debug, Deserialize)]
+struct OccupancyQuery {
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
 }
 
-async fn unhold_call(
+/// Time spent in each status and occupancy (defaults to the last 24 hours)
+async fn get_agent_occupancy(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<StatusCode, StatusCode> {
-    let call = db::calls::get_by_id(&state.db, id)
+    axum::extract::Query(query): axum::extract::Query<OccupancyQuery>,
+) -> Result<Json<AgentOccupancy>, StatusCode> {
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::days(1));
+    if from > to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let initial = db::agent_status_history::status_at(&state.db, id, from)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let changes = db::agent_status_history::get_between(&state.db, id, from, to)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    if let Some(call_control_id) = &call.call_control_id {
-        state.telnyx.unhold(call_control_id)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    }
+    Ok(Json(compute_occupancy(id, initial, &changes, from, to)))
+}
 
-    Ok(StatusCode::OK)
+#[derive(Debug, Deserialize)]
+struct DispositionStatsQuery {
+    agent_id: Option<i64>,
+    campaign_id: Option<i64>,
 }
 
-async fn get_call(
+async fn get_disposition_stats(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
-    axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<Call>, StatusCode> {
-    db::calls::get_by_id(&state.db, id)
+    _claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<DispositionStatsQuery>,
+) -> Result<Json<Vec<DispositionCount>>, StatusCode> {
+    db::stats::get_disposition_counts(&state.db, query.agent_id, query.campaign_id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
         .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-// ============== Webhook Handler ==============
+#[derive(Debug, Deserialize)]
+struct StatsHistoryQuery {
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    granularity: Option<Granularity>,
+    metric: Option<HistoryMetric>,
+    agent_id: Option<i64>,
+    campaign_id: Option<i64>,
+}
 
-async fn handle_telnyx_webhook(
+/// Bucketed call metrics for trend charts (defaults to daily call counts over the last 7 days)
+async fn get_stats_history(
     State(state): State<Arc<AppState>>,
-    Json(event): Json<telnyx::TelnyxWebhookEvent>,
-) -> StatusCode {
-    tracing::info!("Received Telnyx webhook: {}", event.event_type());
+    _claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<StatsHistoryQuery>,
+) -> Result<Json<Vec<StatsBucket>>, StatusCode> {
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::days(7));
+    let granularity = query.granularity.unwrap_or(Granularity::Day);
+    let metric = query.metric.unwrap_or(HistoryMetric::Calls);
+
+    if from > to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-    let call_control_id = match event.call_control_id() {
-        Some(id) => id.to_string(),
-        None => return StatusCode::OK,
+    // Keep the number of buckets bounded
+    let max_range = match granularity {
+        Granularity::Hour => chrono::Duration::days(31),
+        Granularity::Day => chrono::Duration::days(366),
     };
+    if to - from > max_range {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-    // Find call by control ID
-    let call = match db::calls::get_by_control_id(&state.db, &call_control_id).await {
-        Ok(Some(c)) => c,
-        _ => return StatusCode::OK,
-    };
+    let buckets = db::stats::get_history(
+        &state.db,
+        from,
+        to,
+        granularity,
+        metric,
+        query.agent_id,
+        query.campaign_id,
+    )
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to load stats history: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
 
-    // Handle different event types
-    match event.event_type() {
-        "call.initiated" => {
-            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Initiated).await;
-        }
-        "call.ringing" => {
-            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Ringing).await;
-        }
-        "call.answered" => {
-            let _ = db::calls::set_answered(&state.db, call.id).await;
+    Ok(Json(fill_missing_buckets(buckets, from, to, granularity)))
+}
 
-            // Check if this is an AI agent call
-            if let Some(agent_id) = call.agent_id {
-                if state.ai_handler.is_ai_agent(agent_id).await {
-                    // Start AI session
-                    if let Err(e) = state.ai_handler.start_session(
-                        call.id,
-                        &call_control_id,
-                        agent_id,
-                        call.lead_id,
-                        call.campaign_id,
-                    ).await {
-                        tracing::error!("Failed to start AI session: {}", e);
-                        // Fall back to default greeting
-                        let _ = state.telnyx.speak(
-                            &call_control_id,
-                            "Hello, please hold while we connect you.",
-                            Some("female")
-                        ).await;
-                    }
-                } else {
-                    // Non-AI call - play standard greeting
-                    let _ = state.telnyx.speak(
-                        &call_control_id,
-                        "Hello, this is a call from the VoIP CRM system. Please hold while we connect you.",
-                        Some("female")
-                    ).await;
-                }
-            } else {
-                // No agent assigned - play default greeting
-                let _ = state.telnyx.speak(
-                    &call_control_id,
-                    "Hello, please hold while we connect you to an agent.",
-                    Some("female")
-                ).await;
-            }
-        }
-        "call.bridged" => {
-            let _ = db::calls::update_status(&state.db, call.id, CallStatus::Bridged).await;
-        }
-        "call.hangup" => {
-            // End AI session if active
-            let _ = state.ai_handler.end_session(&call_control_id).await;
+#[derive(Debug, Deserialize)]
+struct StatsExportQuery {
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    group_by: Option<StatsGroupBy>,
+}
 
-            let _ = db::calls::set_ended(&state.db, call.id, Some("hangup")).await;
-            if let Some(agent_id) = call.agent_id {
-                let _ = db::agents::update_status(&state.db, agent_id, AgentStatus::AfterCall).await;
-            }
-        }
-        "call.machine.detection.ended" => {
-            // Check if answering machine and hang up if so
-            if let Some(result) = &event.data.payload.result {
-                if result == "machine" {
-                    let _ = state.telnyx.hangup(&call_control_id).await;
-                    let _ = db::calls::set_ended(&state.db, call.id, Some("voicemail")).await;
-                }
-            }
-        }
-        _ => {}
+/// Download grouped call statistics as a CSV attachment (supervisors only)
+async fn export_stats_csv(
+    State(state): State<Arc<AppState>>,
+    claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<StatsExportQuery>,
+) -> Result<impl axum::response::IntoResponse, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
     }
 
-    StatusCode::OK
-}
+    let to = query.to.unwrap_or_else(chrono::Utc::now);
+    let from = query.from.unwrap_or(to - chrono::Duration::days(30));
+    let group_by = query.group_by.unwrap_or(StatsGroupBy::Agent);
 
-// ============== Stats Routes ==============
+    if from > to {
+        return Err(StatusCode::BAD_REQUEST);
+    }
 
-async fn get_realtime_stats(
-    State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let stats = db::stats::get_realtime(&state.db)
+    let rows = db::stats::get_summary(&state.db, from, to, group_by)
         .await
+        .map_err(|e| {
+            tracing::error!("Failed to load stats summary: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    let body = export::stats_summary_csv(group_by, &rows)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    Ok(Json(stats))
+
+    let filename = format!(
+        "attachment; filename=\"stats-{}-{}-{}.csv\"",
+        group_by.label(),
+        from.format("%Y%m%d"),
+        to.format("%Y%m%d")
+    );
+
+    Ok((
+        [
+            (axum::http::header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (axum::http::header::CONTENT_DISPOSITION, filename),
+        ],
+        body,
+    ))
 }
 
-async fn get_agent_stats(
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    period: Option<LeaderboardPeriod>,
+    metric: Option<LeaderboardMetric>,
+}
+
+/// Agents ranked by calls, conversions or talk time for the current day, week or month
+async fn get_leaderboard(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
-    axum::extract::Path(id): axum::extract::Path<i64>,
-) -> Result<Json<AgentStats>, StatusCode> {
-    db::stats::get_agent_stats(&state.db, id)
+    _claims: auth::Claims,
+    axum::extract::Query(query): axum::extract::Query<LeaderboardQuery>,
+) -> Result<Json<Vec<LeaderboardEntry>>, StatusCode> {
+    let period = query.period.unwrap_or(LeaderboardPeriod::Today);
+    let metric = query.metric.unwrap_or(LeaderboardMetric::Calls);
+
+    let agents = db::stats::get_agent_performance(&state.db, period.start(chrono::Utc::now()))
         .await
-        .map(Json)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rank_leaderboard(agents, metric)))
 }
 
 // ============== AI Settings Routes ==============
 
 async fn get_all_ai_settings(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
 ) -> Result<Json<Vec<AiAgentSettings>>, StatusCode> {
     db::ai::get_all_settings(&state.db)
         .await
@@ -902,7 +3197,7 @@ async fn get_all_ai_settings(
 
 async fn get_ai_settings(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(agent_id): axum::extract::Path<i64>,
 ) -> Result<Json<Option<AiAgentSettings>>, StatusCode> {
     db::ai::get_settings(&state.db, agent_id)
@@ -913,7 +3208,7 @@ async fn get_ai_settings(
 
 async fn upsert_ai_settings(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(agent_id): axum::extract::Path<i64>,
     Json(req): Json<UpsertAiSettingsRequest>,
 ) -> Result<Json<AiAgentSettings>, StatusCode> {
@@ -932,9 +3227,44 @@ async fn upsert_ai_settings(
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
 }
 
-async fn delete_ai_settings(
+async fn get_campaign_ai_settings(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
+    axum::extract::Path(campaign_id): axum::extract::Path<i64>,
+) -> Result<Json<Option<CampaignAiSettings>>, StatusCode> {
+    db::ai::get_campaign_settings(&state.db, campaign_id)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn upsert_campaign_ai_settings(
     State(state): State<Arc<AppState>>,
     claims: auth::Claims,
+    axum::extract::Path(campaign_id): axum::extract::Path<i64>,
+    Json(req): Json<UpsertCampaignAiSettingsRequest>,
+) -> Result<Json<CampaignAiSettings>, StatusCode> {
+    if !claims.is_supervisor_or_above() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if req.system_prompt.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    db::campaigns::get_by_id(&state.db, campaign_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    db::ai::upsert_campaign_settings(&state.db, campaign_id, &req)
+        .await
+        .map(Json)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn delete_ai_settings(
+    State(state): State<Arc<AppState>>,
+    _claims: auth::Claims,
     axum::extract::Path(agent_id): axum::extract::Path<i64>,
 ) -> Result<StatusCode, StatusCode> {
     db::ai::delete_settings(&state.db, agent_id)
@@ -945,7 +3275,7 @@ async fn delete_ai_settings(
 
 async fn get_global_ai_config(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
 ) -> Result<Json<GlobalAiConfig>, StatusCode> {
     db::ai::get_global_config(&state.db)
         .await
@@ -958,7 +3288,7 @@ async fn get_global_ai_config(
 
 async fn update_global_ai_config(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     Json(config): Json<GlobalAiConfig>,
 ) -> Result<Json<GlobalAiConfig>, StatusCode> {
     db::ai::update_global_config(&state.db, &config)
@@ -972,7 +3302,7 @@ async fn update_global_ai_config(
 
 async fn get_prompt_templates(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
 ) -> Result<Json<Vec<PromptTemplate>>, StatusCode> {
     db::ai::get_all_templates(&state.db)
         .await
@@ -985,7 +3315,7 @@ async fn get_prompt_templates(
 
 async fn create_prompt_template(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     Json(template): Json<PromptTemplate>,
 ) -> Result<Json<PromptTemplate>, StatusCode> {
     db::ai::create_template(&state.db, &template)
@@ -999,7 +3329,7 @@ async fn create_prompt_template(
 
 async fn get_prompt_template(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<String>,
 ) -> Result<Json<PromptTemplate>, StatusCode> {
     db::ai::get_template(&state.db, &id)
@@ -1014,7 +3344,7 @@ async fn get_prompt_template(
 
 async fn update_prompt_template(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<String>,
     Json(template): Json<PromptTemplate>,
 ) -> Result<Json<PromptTemplate>, StatusCode> {
@@ -1029,7 +3359,7 @@ async fn update_prompt_template(
 
 async fn delete_prompt_template(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(id): axum::extract::Path<String>,
 ) -> Result<StatusCode, StatusCode> {
     db::ai::delete_template(&state.db, &id)
@@ -1053,7 +3383,7 @@ struct AutomationStatus {
 
 async fn start_campaign_automation(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(campaign_id): axum::extract::Path<i64>,
 ) -> Result<StatusCode, StatusCode> {
     // Start the campaign automation
@@ -1075,7 +3405,7 @@ async fn start_campaign_automation(
 
 async fn stop_campaign_automation(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(campaign_id): axum::extract::Path<i64>,
 ) -> Result<StatusCode, StatusCode> {
     state.automation.stop_campaign(campaign_id)
@@ -1085,13 +3415,17 @@ async fn stop_campaign_automation(
             StatusCode::NOT_FOUND
         })?;
 
+    campaign_stop::drain_campaign(state.clone(), campaign_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     tracing::info!("Stopped automation for campaign {}", campaign_id);
     Ok(StatusCode::OK)
 }
 
 async fn get_automation_status(
     State(state): State<Arc<AppState>>,
-    claims: auth::Claims,
+    _claims: auth::Claims,
     axum::extract::Path(campaign_id): axum::extract::Path<i64>,
 ) -> Json<AutomationStatus> {
     if let Some(status) = state.automation.get_status(campaign_id).await {
@@ -1126,13 +3460,34 @@ pub async fn run_server(database_url: &str, port: u16) -> anyhow::Result<()> {
     let telnyx_connection_id = std::env::var("TELNYX_CONNECTION_ID").unwrap_or_default();
     let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string());
     let caller_id = std::env::var("TELNYX_CALLER_ID").unwrap_or_default();
-    let webhook_url = std::env::var("WEBHOOK_URL").unwrap_or_default();
-    let sip_username = std::env::var("TELNYX_SIP_USERNAME").unwrap_or_default();
-    let sip_password = std::env::var("TELNYX_SIP_PASSWORD").unwrap_or_default();
-    let anthropic_api_key = std::env::var("ANTHROPIC_API_KEY").unwrap_or_default();
+    let webhook_env = std::env::var("WEBHOOK_ENV")
+        .ok()
+        .map(|env| env.trim().trim_matches('/').to_string())
+        .filter(|env| !env.is_empty());
+    let webhook_url = env_webhook_url(&std::env::var("WEBHOOK_URL").unwrap_or_default(), webhook_env.as_deref());
+    let hold_music_url = std::env::var("HOLD_MUSIC_URL").ok().filter(|url| !url.is_empty());
+    let default_country = match std::env::var("DEFAULT_COUNTRY_CODE") {
+        Ok(code) if is_known_country(&code) => code.trim().to_uppercase(),
+        Ok(code) if !code.trim().is_empty() => {
+            tracing::warn!("Ignoring unknown DEFAULT_COUNTRY_CODE {:?}", code);
+            DEFAULT_COUNTRY_CODE.to_string()
+        }
+        _ => DEFAULT_COUNTRY_CODE.to_string(),
+    };
+    let skill_fallback = std::env::var("SKILL_ROUTING_FALLBACK")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+        .unwrap_or(false);
+    let lead_transitions = match std::env::var("LEAD_STATUS_TRANSITIONS") {
+        Ok(spec) if !spec.trim().is_empty() => LeadStatusTransitions::parse(&spec).unwrap_or_else(|e| {
+            tracing::warn!("Ignoring LEAD_STATUS_TRANSITIONS: {}", e);
+            LeadStatusTransitions::default()
+        }),
+        _ => LeadStatusTransitions::default(),
+    };
 
     let telnyx = telnyx::TelnyxClient::new(telnyx_api_key, telnyx_connection_id);
-    let claude = claude::ClaudeClient::new(anthropic_api_key);
+    let llm = llm::LlmConfig::from_env().client();
+    tracing::info!("AI calls use the {} language model backend", llm.name());
     let automation_manager = automation::AutomationManager::new(
         pool.clone(),
         telnyx.clone(),
@@ -1141,65 +3496,248 @@ pub async fn run_server(database_url: &str, port: u16) -> anyhow::Result<()> {
     );
     let ai_handler = ai_call_handler::AiCallHandler::new(
         pool.clone(),
-        claude.clone(),
+        llm.clone(),
         telnyx.clone(),
-    );
+    )
+    .with_tts(tts::TtsConfig::from_env().player());
 
     // Initialize email service
     let email = email::EmailService::from_env()
         .unwrap_or_else(|e| {
             tracing::warn!("Email service not configured: {}. Email features will be disabled.", e);
             // Return a dummy service that will fail gracefully
-            email::EmailService::new(
-                "localhost",
-                587,
-                "noreply",
-                "password",
-                "noreply@localhost",
-                "VoIP CRM",
-                "http://localhost:3000",
-            ).expect("Failed to create fallback email service")
-        });
+            email::EmailService::new(&email::SmtpConfig {
+                host: "localhost".to_string(),
+                port: 587,
+                username: "noreply".to_string(),
+                password: "password".to_string(),
+                from_email: "noreply@localhost".to_string(),
+                from_name: "VoIP CRM".to_string(),
+                app_url: "http://localhost:3000".to_string(),
+                encryption: email::SmtpEncryption::Tls,
+            }).expect("Failed to create fallback email service")
+        })
+        .with_outbox(pool.clone());
 
-    // Optionally initialize SIP User Agent for direct trunk calls
-    let sip_agent = if let Some(sip_config) = sip::SipConfig::from_env() {
-        tracing::info!("SIP trunk configured: {}:{}", sip_config.trunk_host, sip_config.trunk_port);
-        let (agent, _event_rx) = sip::SipUserAgent::new(sip_config);
-        // Register with SIP trunk in background
-        let agent = Arc::new(tokio::sync::RwLock::new(agent));
-        let agent_clone = agent.clone();
-        tokio::spawn(async move {
-            if let Err(e) = agent_clone.read().await.register().await {
-                tracing::error!("SIP registration failed: {}", e);
-            }
-        });
-        Some(agent)
-    } else {
+    // Optionally initialize SIP User Agents for direct trunk calls
+    let metrics = telemetry::install_recorder()?;
+    let metrics_token = telemetry::token_from_env();
+    if metrics_token.is_none() {
+        tracing::info!("METRICS_TOKEN not set, /metrics is disabled");
+    }
+
+    let trunk_configs = sip::SipConfig::trunks_from_env();
+    let sip_events = events::EventBus::new();
+    let sip_trunks = if trunk_configs.is_empty() {
         tracing::info!("SIP trunk not configured, using Telnyx only");
         None
+    } else {
+        let mut agents = Vec::new();
+        for (trunk, sip_config) in trunk_configs.into_iter().enumerate() {
+            tracing::info!("SIP trunk configured: {}:{}", sip_config.trunk_host, sip_config.trunk_port);
+            let (agent, event_rx) = sip::SipUserAgent::new(sip_config);
+            events::forward_sip_events(trunk, event_rx, sip_events.clone());
+            // Register with SIP trunk in background
+            let agent = Arc::new(tokio::sync::RwLock::new(agent));
+            let agent_clone = agent.clone();
+            tokio::spawn(async move {
+                if let Err(e) = agent_clone.read().await.register().await {
+                    tracing::error!("SIP registration failed: {}", e);
+                }
+            });
+            agents.push(agent);
+        }
+        Some(Arc::new(sip::SipTrunks::new(agents, sip::TrunkStrategy::from_env())))
     };
 
     let state = AppState {
         db: pool,
         telnyx,
-        claude,
+        llm,
         automation: Arc::new(automation_manager),
         ai_handler: Arc::new(ai_handler),
         email,
         jwt_secret,
         caller_id,
         webhook_url,
-        sip_username,
-        sip_password,
-        sip_agent,
+        webhook_env,
+        webrtc: webrtc::WebRtcConfig::from_env(),
+        hold_music_url,
+        sip_trunks,
+        login_lockout: Arc::new(auth::lockout::LoginLockout::new(auth::lockout::LockoutConfig::from_env())),
+        password_hasher: auth::password::PasswordHasher::from_env(),
+        session_config: auth::SessionConfig::from_env(),
+        password_policy: auth::PasswordPolicy::from_env(),
+        default_country,
+        lead_transitions,
+        skill_fallback,
+        callback_window: callbacks::window_from_env(),
+        call_queue: Arc::new(routing::CallQueue::new()),
+        ivr_sessions: Arc::new(routing::IvrSessions::new()),
+        pending_amd: Arc::new(amd::PendingAmd::new()),
+        amd_timeout: amd::timeout_from_env(),
+        events: events::EventBus::new(),
+        sip_events,
+        metrics,
+        metrics_token,
+        readiness: Arc::new(health::ReadinessCache::new(health::READINESS_CACHE_TTL)),
     };
 
+    let mut shutdown_hooks: Vec<Box<dyn shutdown::ShutdownHook>> = vec![
+        Box::new(shutdown::AutomationHook(state.automation.clone())),
+        Box::new(shutdown::TelnyxCallsHook {
+            db: state.db.clone(),
+            telnyx: state.telnyx.clone(),
+        }),
+    ];
+    if let Some(trunks) = &state.sip_trunks {
+        for agent in trunks.agents() {
+            shutdown_hooks.push(Box::new(shutdown::SipAgentHook(agent.clone())));
+        }
+    }
+
+    // Pick up campaigns that were dialing when the server last stopped
+    match state.automation.resume_persisted().await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!("Resumed automation for {} campaign(s)", n),
+        Err(e) => tracing::error!("Failed to resume campaign automation: {}", e),
+    }
+
+    scheduler::spawn(state.db.clone(), state.automation.clone());
+    routing::spawn_queue_worker(Arc::new(state.clone()));
+    call_watchdog::spawn(Arc::new(state.clone()));
+    notifications::spawn_daily_reports(Arc::new(state.clone()));
+    email::spawn_outbox_worker(state.email.clone(), state.db.clone());
+
     let app = create_router(state);
 
     let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
     tracing::info!("Server running on http://0.0.0.0:{}", port);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
+        .with_graceful_shutdown(shutdown::graceful(shutdown::signal(), shutdown_hooks))
+        .await?;
+
+    tracing::info!("Server stopped");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lead_query(uri: &str) -> LeadListQuery {
+        let uri: axum::http::Uri = uri.parse().unwrap();
+        axum::extract::Query::<LeadListQuery>::try_from_uri(&uri).unwrap().0
+    }
+
+    #[test]
+    fn test_export_uses_list_filters() {
+        let filter = lead_query("/api/leads/export?format=csv&status=QUALIFIED&tags=VIP,%20hot").filter();
+        assert_eq!(
+            filter,
+            db::leads::LeadFilter {
+                status: Some(LeadStatus::Qualified),
+                tags: vec!["hot".to_string(), "vip".to_string()],
+                include_deleted: false,
+            }
+        );
+
+        assert_eq!(lead_query("/api/leads/export").filter(), db::leads::LeadFilter::default());
+    }
+
+    #[test]
+    fn test_webhook_env_path() {
+        // Another environment's webhook is turned away, ours is processed
+        assert_eq!(check_webhook_env(Some("staging"), Some("production")), Err(StatusCode::NOT_FOUND));
+        assert_eq!(check_webhook_env(Some("staging"), Some("staging")), Ok(()));
+        // A server with an environment doesn't take the plain path either
+        assert_eq!(check_webhook_env(Some("staging"), None), Err(StatusCode::NOT_FOUND));
+        // A server without an environment only takes the plain path
+        assert_eq!(check_webhook_env(None, Some("staging")), Err(StatusCode::NOT_FOUND));
+        assert_eq!(check_webhook_env(None, None), Ok(()));
+
+        assert_eq!(
+            env_webhook_url("https://crm.example.com/api/webhooks/telnyx/", Some("staging")),
+            "https://crm.example.com/api/webhooks/telnyx/staging"
+        );
+        assert_eq!(env_webhook_url("https://crm.example.com/api/webhooks/telnyx", None), "https://crm.example.com/api/webhooks/telnyx");
+    }
+
+    #[test]
+    fn test_call_search_filters_by_sentiment() {
+        let uri: axum::http::Uri = "/api/calls?sentiment_below=-0.3&agent_id=4".parse().unwrap();
+        let query = axum::extract::Query::<CallSearchQuery>::try_from_uri(&uri).unwrap().0;
+        assert_eq!(
+            query.filter(),
+            db::calls::CallFilter {
+                agent_id: Some(4),
+                campaign_id: None,
+                sentiment_below: Some(-0.3),
+                limit: 100,
+            }
+        );
+
+        let uri: axum::http::Uri = "/api/calls?limit=100000".parse().unwrap();
+        let query = axum::extract::Query::<CallSearchQuery>::try_from_uri(&uri).unwrap().0;
+        assert_eq!(query.filter().limit, MAX_CALL_SEARCH_RESULTS);
+    }
+
+    #[test]
+    fn test_dial_to_agent_on_call_is_rejected() {
+        let on_call = CallCapacity {
+            status: AgentStatus::OnCall,
+            max_concurrent_calls: DEFAULT_MAX_CONCURRENT_CALLS,
+            active_calls: 1,
+        };
+        assert_eq!(check_agent_capacity(Some(on_call)), Err(StatusCode::CONFLICT));
+        assert_eq!(
+            check_agent_capacity(Some(CallCapacity { status: AgentStatus::Break, active_calls: 0, ..on_call })),
+            Err(StatusCode::CONFLICT)
+        );
+        assert_eq!(check_agent_capacity(None), Err(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn test_second_dial_within_raised_limit_is_allowed() {
+        let ready = CallCapacity {
+            status: AgentStatus::Ready,
+            max_concurrent_calls: 2,
+            active_calls: 0,
+        };
+        assert_eq!(check_agent_capacity(Some(ready)), Ok(()));
+
+        // After the first dial the agent is on a call with one in progress
+        let on_call = CallCapacity { status: AgentStatus::OnCall, active_calls: 1, ..ready };
+        assert_eq!(check_agent_capacity(Some(on_call)), Ok(()));
+
+        let full = CallCapacity { active_calls: 2, ..on_call };
+        assert_eq!(check_agent_capacity(Some(full)), Err(StatusCode::CONFLICT));
+    }
+
+    #[tokio::test]
+    async fn test_recording_starts_with_campaign_settings() {
+        use telnyx::stub::stub_telnyx;
+
+        let campaign = Campaign {
+            recording_channels: RecordingChannels::Single,
+            recording_format: RecordingFormat::Wav,
+            ..crate::models::fixtures::campaign(1)
+        };
+        let (url, request) = stub_telnyx().await;
+        let client = telnyx::TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+        let settings = start_recording(&client, "v3:lead", Some(&campaign)).await.unwrap();
+
+        assert_eq!(settings.channels, RecordingChannels::Single);
+        let (_, body) = request.await.unwrap();
+        assert_eq!(body, serde_json::json!({ "channels": "single", "format": "wav" }));
+
+        // Calls outside a campaign use the defaults
+        let (url, request) = stub_telnyx().await;
+        let client = telnyx::TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+        assert_eq!(start_recording(&client, "v3:lead", None).await.unwrap(), RecordingSettings::default());
+        let (_, body) = request.await.unwrap();
+        assert_eq!(body, serde_json::json!({ "channels": "dual", "format": "mp3" }));
+    }
+}
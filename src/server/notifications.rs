@@ -0,0 +1,257 @@
+//! Email notifications
+//!
+//! Agents are told when leads are assigned to them, with a `lead.assigned`
+//! WebSocket event per lead and one email per batch, and supervisors who ask
+//! for it get a summary of the previous day's calls each morning. Emails
+//! respect the recipient's `NotificationPreferences`.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use tokio::task::JoinHandle;
+
+use crate::models::{Lead, LeadAssignment, NotificationPreferences, StatsGroupBy, User};
+use super::events::ServerEvent;
+use super::{db, AppState};
+
+/// Daily reports go out at this hour, UTC
+pub const DAILY_REPORT_HOUR: u32 = 7;
+
+/// Where to send an assignment email, if the user wants one
+pub fn assignment_recipient<'a>(user: &'a User, prefs: &NotificationPreferences) -> Option<&'a str> {
+    let email = user.email.trim();
+    (user.active && prefs.email_on_assignment && !email.is_empty()).then_some(email)
+}
+
+/// Real-time notice for the agent's login that `lead` is now theirs
+pub fn lead_assigned_event(lead: &Lead, agent_id: i64, user: &User) -> ServerEvent {
+    ServerEvent::LeadAssigned {
+        agent_id,
+        user_id: user.id,
+        lead_id: lead.id,
+        lead_name: lead.full_name(),
+        phone: lead.phone.clone(),
+    }
+}
+
+/// Lead ids from a bulk assignment, grouped by the agent they went to
+pub fn leads_by_agent(assignments: &[LeadAssignment]) -> BTreeMap<i64, Vec<i64>> {
+    let mut grouped: BTreeMap<i64, Vec<i64>> = BTreeMap::new();
+    for assignment in assignments {
+        grouped.entry(assignment.agent_id).or_default().push(assignment.lead_id);
+    }
+    grouped
+}
+
+/// Tell the agent a lead was just assigned to them: a WebSocket event, and an
+/// email if they want one. Failures are logged, not returned, so they never
+/// fail the assignment itself.
+pub async fn notify_lead_assigned(state: &AppState, lead: &Lead) {
+    let Some(agent_id) = lead.assigned_agent_id else {
+        return;
+    };
+    notify_leads_assigned(state, agent_id, std::slice::from_ref(lead)).await;
+}
+
+/// Tell an agent about leads just assigned to them: a WebSocket event for
+/// each lead, but a single email for the lot, so a bulk assignment doesn't
+/// flood their inbox.
+pub async fn notify_leads_assigned(state: &AppState, agent_id: i64, leads: &[Lead]) {
+    if leads.is_empty() {
+        return;
+    }
+
+    let user = match agent_user(state, agent_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to look up agent {} for assignment email: {}", agent_id, e);
+            return;
+        }
+    };
+
+    for lead in leads {
+        state.events.publish(lead_assigned_event(lead, agent_id, &user));
+    }
+
+    let prefs = match db::notification_preferences::get(&state.db, user.id).await {
+        Ok(prefs) => prefs,
+        Err(e) => {
+            tracing::warn!("Failed to load notification preferences for user {}: {}", user.id, e);
+            return;
+        }
+    };
+    let Some(to_email) = assignment_recipient(&user, &prefs) else {
+        return;
+    };
+
+    let to_name = user.first_name.as_deref();
+    let result = match leads {
+        [lead] => state.email.send_lead_assigned_email(to_email, to_name, lead.id, &lead.full_name()).await,
+        _ => {
+            let summary: Vec<(i64, String)> = leads.iter().map(|lead| (lead.id, lead.full_name())).collect();
+            state.email.send_leads_assigned_email(to_email, to_name, &summary).await
+        }
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to send assignment email for agent {}: {}", agent_id, e);
+    }
+}
+
+/// The login behind an agent, if it has one
+async fn agent_user(state: &AppState, agent_id: i64) -> Result<Option<User>, sqlx::Error> {
+    let Some(user_id) = db::agents::get_by_id(&state.db, agent_id).await?.and_then(|agent| agent.user_id) else {
+        return Ok(None);
+    };
+    db::users::get_by_id(&state.db, user_id).await
+}
+
+/// Next time the daily report is due after `now`
+pub fn next_report_at(now: DateTime<Utc>) -> DateTime<Utc> {
+    let report_time = NaiveTime::from_hms_opt(DAILY_REPORT_HOUR, 0, 0).unwrap_or(NaiveTime::MIN);
+    let today = now.date_naive().and_time(report_time).and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+/// Email yesterday's per-agent totals to everyone who wants the report
+pub async fn send_daily_reports(state: &AppState, now: DateTime<Utc>) -> Result<usize, sqlx::Error> {
+    let day = now.date_naive() - Duration::days(1);
+    let from = day.and_time(NaiveTime::MIN).and_utc();
+    let to = from + Duration::days(1) - Duration::microseconds(1);
+
+    let rows = db::stats::get_summary(&state.db, from, to, StatsGroupBy::Agent).await?;
+    let recipients = db::notification_preferences::get_daily_report_recipients(&state.db).await?;
+
+    let mut sent = 0;
+    for user in &recipients {
+        match state
+            .email
+            .send_daily_report_email(&user.email, user.first_name.as_deref(), day, &rows)
+            .await
+        {
+            Ok(()) => sent += 1,
+            Err(e) => tracing::warn!("Failed to send daily report to user {}: {}", user.id, e),
+        }
+    }
+    Ok(sent)
+}
+
+/// Send the daily report every morning in the background
+pub fn spawn_daily_reports(state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now();
+            let wait = (next_report_at(now) - now).to_std().unwrap_or_default();
+            tokio::time::sleep(wait).await;
+
+            match send_daily_reports(&state, Utc::now()).await {
+                Ok(sent) => tracing::info!("Sent {} daily report(s)", sent),
+                Err(e) => tracing::error!("Failed to send daily reports: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::models::UserRole;
+
+    fn user() -> User {
+        User {
+            id: 5,
+            username: "alice".to_string(),
+            email: "alice@example.com".to_string(),
+            role: UserRole::Agent,
+            first_name: Some("Alice".to_string()),
+            last_name: None,
+            password_hash: String::new(),
+            email_verified: true,
+            active: true,
+        }
+    }
+
+    #[test]
+    fn test_no_assignment_email_when_disabled() {
+        let prefs = NotificationPreferences { email_on_assignment: false, ..Default::default() };
+        assert_eq!(assignment_recipient(&user(), &prefs), None);
+
+        // The other preferences don't matter
+        let prefs = NotificationPreferences { email_daily_report: false, browser_call_alerts: false, ..Default::default() };
+        assert_eq!(assignment_recipient(&user(), &prefs), Some("alice@example.com"));
+    }
+
+    #[test]
+    fn test_bulk_assignments_grouped_per_agent() {
+        let assignments = [
+            LeadAssignment { lead_id: 1, agent_id: 4 },
+            LeadAssignment { lead_id: 2, agent_id: 7 },
+            LeadAssignment { lead_id: 3, agent_id: 4 },
+        ];
+
+        let grouped = leads_by_agent(&assignments);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[&4], vec![1, 3]);
+        assert_eq!(grouped[&7], vec![2]);
+    }
+
+    #[test]
+    fn test_daily_report_is_opt_in() {
+        assert!(!NotificationPreferences::default().email_daily_report);
+    }
+
+    #[test]
+    fn test_assignment_email_defaults_on() {
+        assert_eq!(assignment_recipient(&user(), &NotificationPreferences::default()), Some("alice@example.com"));
+
+        let disabled = User { active: false, ..user() };
+        assert_eq!(assignment_recipient(&disabled, &NotificationPreferences::default()), None);
+    }
+
+    #[tokio::test]
+    async fn test_assignment_event_goes_to_assigned_agent() {
+        let bus = crate::server::events::EventBus::new();
+        let mut rx = bus.subscribe();
+        let lead = Lead {
+            id: 12,
+            first_name: Some("Ada".to_string()),
+            last_name: Some("Lovelace".to_string()),
+            phone: "+15550100".to_string(),
+            email: None,
+            company: None,
+            status: crate::models::LeadStatus::New,
+            notes: None,
+            assigned_agent_id: Some(4),
+            campaign_id: None,
+            call_attempts: 0,
+            last_call_at: None,
+            created_at: None,
+            updated_at: None,
+            deleted_at: None,
+        };
+
+        bus.publish(lead_assigned_event(&lead, 4, &user()));
+
+        let event = rx.recv().await.unwrap();
+        assert!(event.is_for(5));
+        assert!(!event.is_for(6));
+        assert!(matches!(
+            event,
+            ServerEvent::LeadAssigned { agent_id: 4, user_id: 5, lead_id: 12, ref lead_name, .. } if lead_name == "Ada Lovelace"
+        ));
+    }
+
+    #[test]
+    fn test_next_report_at() {
+        let early = Utc.with_ymd_and_hms(2024, 3, 4, 6, 30, 0).unwrap();
+        assert_eq!(next_report_at(early), Utc.with_ymd_and_hms(2024, 3, 4, 7, 0, 0).unwrap());
+
+        let on_time = Utc.with_ymd_and_hms(2024, 3, 4, 7, 0, 0).unwrap();
+        assert_eq!(next_report_at(on_time), Utc.with_ymd_and_hms(2024, 3, 5, 7, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,260 @@
+//! Per-IP rate limiting for sensitive endpoints
+//!
+//! `RateLimitLayer` is a tower layer backed by a token bucket per client IP.
+//! Each bucket holds up to `capacity` tokens and refills continuously over
+//! `period`; a request without a token is rejected with 429 and a
+//! `Retry-After` header.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tower::{Layer, Service};
+
+/// Requests allowed per period
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitConfig {
+    pub capacity: u32,
+    pub period: Duration,
+}
+
+impl RateLimitConfig {
+    pub fn per_minute(capacity: u32) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            period: Duration::from_secs(60),
+        }
+    }
+
+    /// Read a per-minute limit from an environment variable, falling back to `default`
+    pub fn per_minute_from_env(var: &str, default: u32) -> Self {
+        let capacity = std::env::var(var)
+            .ok()
+            .and_then(|v| v.parse::<u32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(default);
+        Self::per_minute(capacity)
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.capacity as f64 / self.period.as_secs_f64()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token buckets keyed by client
+pub struct TokenBucketLimiter {
+    config: RateLimitConfig,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl TokenBucketLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Take a token for `key`. Returns how long to wait if none is available.
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    pub(crate) fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        let capacity = self.config.capacity as f64;
+        let rate = self.config.refill_per_second();
+
+        // Drop buckets that have been full for a while so the map doesn't grow forever
+        if buckets.len() > 10_000 {
+            buckets.retain(|_, b| now.duration_since(b.last_refill) < self.config.period);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            last_refill: now,
+        });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+}
+
+/// Client IP from `X-Forwarded-For`, `X-Real-IP` or the peer address
+///
+/// Only the right-most `X-Forwarded-For` hop is trusted: it is the one our
+/// proxy appended, while earlier hops are whatever the client sent.
+pub fn client_ip(headers: &HeaderMap, peer: Option<SocketAddr>) -> String {
+    headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit(',').next())
+        .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+        .or_else(|| peer.map(|addr| addr.ip().to_string()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn too_many_requests(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+    let mut response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too many requests. Please try again later.",
+    )
+        .into_response();
+
+    if let Ok(value) = HeaderValue::from_str(&seconds.to_string()) {
+        response.headers_mut().insert(header::RETRY_AFTER, value);
+    }
+    response
+}
+
+/// Tower layer that rate limits requests per client IP.
+/// Clones share the same buckets, so one layer can cover several routes.
+#[derive(Clone)]
+pub struct RateLimitLayer {
+    limiter: Arc<TokenBucketLimiter>,
+}
+
+impl RateLimitLayer {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            limiter: Arc::new(TokenBucketLimiter::new(config)),
+        }
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limiter: self.limiter.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limiter: Arc<TokenBucketLimiter>,
+}
+
+impl<S> Service<Request<Body>> for RateLimit<S>
+where
+    S: Service<Request<Body>, Response = Response> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let peer = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|info| info.0);
+        let ip = client_ip(req.headers(), peer);
+
+        match self.limiter.check(&ip) {
+            Ok(()) => Box::pin(self.inner.call(req)),
+            Err(retry_after) => {
+                tracing::warn!("Rate limit exceeded for {} on {}", ip, req.uri().path());
+                Box::pin(async move { Ok(too_many_requests(retry_after)) })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::routing::post;
+    use axum::Router;
+
+    fn login_request(ip: &str) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/api/auth/login")
+            .header("x-forwarded-for", ip)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_eleventh_login_in_a_minute_is_rejected() {
+        let mut app: Router = Router::new().route(
+            "/api/auth/login",
+            post(|| async { "ok" }).layer(RateLimitLayer::new(RateLimitConfig::per_minute(10))),
+        );
+
+        for _ in 0..10 {
+            let response = app.call(login_request("203.0.113.7")).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        let response = app.call(login_request("203.0.113.7")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+
+        // Other clients have their own bucket
+        let response = app.call(login_request("198.51.100.1")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_bucket_refills_over_time() {
+        let limiter = TokenBucketLimiter::new(RateLimitConfig::per_minute(10));
+        let start = Instant::now();
+
+        for _ in 0..10 {
+            assert!(limiter.check_at("ip", start).is_ok());
+        }
+        let retry_after = limiter.check_at("ip", start).unwrap_err();
+        assert!(retry_after <= Duration::from_secs(6));
+
+        // One token comes back every 6 seconds
+        assert!(limiter.check_at("ip", start + Duration::from_secs(6)).is_ok());
+        assert!(limiter.check_at("ip", start + Duration::from_secs(6)).is_err());
+    }
+
+    #[test]
+    fn test_client_ip_uses_last_forwarded_hop() {
+        let peer: SocketAddr = "192.0.2.10:54321".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert_eq!(client_ip(&headers, None), "unknown");
+        assert_eq!(client_ip(&headers, Some(peer)), "192.0.2.10");
+
+        headers.insert("x-real-ip", HeaderValue::from_static("10.0.0.2"));
+        assert_eq!(client_ip(&headers, Some(peer)), "10.0.0.2");
+
+        // A client-supplied first hop can't pick the bucket
+        headers.insert("x-forwarded-for", HeaderValue::from_static("203.0.113.7, 198.51.100.4"));
+        assert_eq!(client_ip(&headers, Some(peer)), "198.51.100.4");
+    }
+}
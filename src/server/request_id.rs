@@ -0,0 +1,77 @@
+//! Request ids
+//!
+//! Every request gets an `x-request-id`: the caller's own if it sent one,
+//! otherwise a fresh UUID. The id is recorded on the request's tracing span,
+//! so all log lines for a request share it, and echoed on the response so a
+//! client report can be matched to the server logs.
+
+use axum::http::{HeaderName, Request};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+use tracing::Span;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Assigns an id to requests that arrive without one
+pub fn set_layer() -> SetRequestIdLayer<MakeRequestUuid> {
+    SetRequestIdLayer::new(HeaderName::from_static(REQUEST_ID_HEADER), MakeRequestUuid)
+}
+
+/// Copies the request's id onto its response
+pub fn propagate_layer() -> PropagateRequestIdLayer {
+    PropagateRequestIdLayer::new(HeaderName::from_static(REQUEST_ID_HEADER))
+}
+
+/// Span for one request, carrying its id
+pub fn make_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id = %request_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::Service;
+    use tower_http::trace::TraceLayer;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/api/health", get(|| async { "OK" }))
+            .layer(propagate_layer())
+            .layer(TraceLayer::new_for_http().make_span_with(make_span))
+            .layer(set_layer())
+    }
+
+    #[tokio::test]
+    async fn test_response_echoes_generated_request_id() {
+        let request = Request::builder().uri("/api/health").body(Body::empty()).unwrap();
+        let response = app().call(request).await.unwrap();
+
+        let id = response.headers()[REQUEST_ID_HEADER].to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(id).is_ok(), "not a UUID: {}", id);
+    }
+
+    #[tokio::test]
+    async fn test_caller_request_id_is_kept() {
+        let request = Request::builder()
+            .uri("/api/health")
+            .header(REQUEST_ID_HEADER, "trace-abc-123")
+            .body(Body::empty())
+            .unwrap();
+        let response = app().call(request).await.unwrap();
+
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "trace-abc-123");
+    }
+}
@@ -0,0 +1,896 @@
+//! Inbound call routing
+//!
+//! Incoming calls are offered to the `Ready` agent that has been idle the
+//! longest. When nobody is available the call waits in a FIFO queue until an
+//! agent frees up. A background worker hands queued calls to agents as they
+//! become available and periodically tells waiting callers their position.
+//!
+//! A call to a campaign or inbound number with a required skill (e.g. a
+//! language) only goes to agents with that skill. With
+//! `SKILL_ROUTING_FALLBACK` on, it goes to any available agent when no skilled
+//! one is free; otherwise it waits for a skilled agent.
+//!
+//! Callers who hang up while queued are called back later; see `callbacks`.
+//!
+//! Numbers with an IVR menu answer first and ask the caller to press a key;
+//! the chosen option queues the call for a campaign's agents, takes a
+//! voicemail or hangs up.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+
+use crate::models::{
+    Agent, AgentStatus, Call, IvrAction, IvrMenu, IvrOption, Lead, QueuedCallInfo, RecordingSettings, ScreenPop,
+};
+use super::events::ServerEvent;
+use super::{db, AppState};
+
+/// How often the queue worker dispatches calls and announces positions
+pub const QUEUE_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(45);
+
+/// A caller waiting for an agent
+#[derive(Debug, Clone)]
+pub struct QueuedCall {
+    pub call_id: i64,
+    pub call_control_id: String,
+    pub from: String,
+    pub lead_id: Option<i64>,
+    /// Skill the agent taking the call needs
+    pub required_skill: Option<String>,
+    pub enqueued_at: DateTime<Utc>,
+}
+
+/// FIFO queue of inbound calls waiting for an agent
+#[derive(Default)]
+pub struct CallQueue {
+    calls: RwLock<VecDeque<QueuedCall>>,
+}
+
+impl CallQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a call to the back of the queue, returning its 1-based position
+    pub async fn enqueue(&self, call: QueuedCall) -> usize {
+        let mut calls = self.calls.write().await;
+        calls.push_back(call);
+        calls.len()
+    }
+
+    /// Take the call that has been waiting the longest
+    pub async fn dequeue(&self) -> Option<QueuedCall> {
+        self.calls.write().await.pop_front()
+    }
+
+    /// Take the longest waiting call that `accept` takes
+    pub async fn dequeue_where(&self, accept: impl Fn(&QueuedCall) -> bool) -> Option<QueuedCall> {
+        let mut calls = self.calls.write().await;
+        let index = calls.iter().position(accept)?;
+        calls.remove(index)
+    }
+
+    /// Put back a call that was taken but couldn't be connected, in the
+    /// place its wait time gives it
+    pub async fn requeue(&self, call: QueuedCall) {
+        let mut calls = self.calls.write().await;
+        let index = calls
+            .iter()
+            .position(|c| c.enqueued_at > call.enqueued_at)
+            .unwrap_or(calls.len());
+        calls.insert(index, call);
+    }
+
+    /// Drop a call from the queue (e.g. the caller hung up)
+    pub async fn remove(&self, call_control_id: &str) -> Option<QueuedCall> {
+        let mut calls = self.calls.write().await;
+        let index = calls.iter().position(|c| c.call_control_id == call_control_id)?;
+        calls.remove(index)
+    }
+
+    /// 1-based position of a queued call
+    pub async fn position(&self, call_control_id: &str) -> Option<usize> {
+        let calls = self.calls.read().await;
+        calls.iter().position(|c| c.call_control_id == call_control_id).map(|i| i + 1)
+    }
+
+    /// Queued calls in order with their position and time waited at `now`
+    pub async fn snapshot(&self, now: DateTime<Utc>) -> Vec<QueuedCallInfo> {
+        let calls = self.calls.read().await;
+        calls
+            .iter()
+            .enumerate()
+            .map(|(i, c)| QueuedCallInfo {
+                call_id: c.call_id,
+                call_control_id: c.call_control_id.clone(),
+                from: c.from.clone(),
+                lead_id: c.lead_id,
+                position: i + 1,
+                enqueued_at: c.enqueued_at,
+                wait_seconds: (now - c.enqueued_at).num_seconds().max(0),
+            })
+            .collect()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.calls.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.calls.read().await.is_empty()
+    }
+}
+
+/// Outcome of routing an inbound call
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoutingDecision {
+    /// Connect the caller to this agent
+    Agent { agent_id: i64 },
+    /// No agent available; the call is waiting at this queue position
+    Queued { position: usize },
+}
+
+/// Skills of each agent, by agent id
+pub type AgentSkillMap = HashMap<i64, HashSet<String>>;
+
+/// Pick the `Ready` agent that has been idle the longest. Agents that never
+/// changed status count as idle the longest; ties go to the lowest id.
+pub fn select_agent(agents: &[Agent]) -> Option<&Agent> {
+    longest_idle(agents.iter())
+}
+
+fn longest_idle<'a>(agents: impl Iterator<Item = &'a Agent>) -> Option<&'a Agent> {
+    agents
+        .filter(|a| a.status == AgentStatus::Ready)
+        .min_by(|a, b| {
+            a.last_status_change
+                .cmp(&b.last_status_change)
+                .then(a.id.cmp(&b.id))
+        })
+}
+
+/// Pick the longest idle agent with `required` skill. Without a skilled agent
+/// free, any agent is picked only when `fallback` is set.
+pub fn select_skilled_agent<'a>(
+    agents: &'a [Agent],
+    skills: &AgentSkillMap,
+    required: Option<&str>,
+    fallback: bool,
+) -> Option<&'a Agent> {
+    let Some(required) = required else {
+        return select_agent(agents);
+    };
+
+    let skilled = longest_idle(
+        agents
+            .iter()
+            .filter(|a| skills.get(&a.id).is_some_and(|s| s.contains(required))),
+    );
+    match skilled {
+        Some(agent) => Some(agent),
+        None if fallback => select_agent(agents),
+        None => None,
+    }
+}
+
+/// Decide where a call goes given the currently available agents, queueing it if nobody is free
+pub async fn route_to_available(
+    queue: &CallQueue,
+    agents: &[Agent],
+    skills: &AgentSkillMap,
+    fallback: bool,
+    call: QueuedCall,
+) -> RoutingDecision {
+    match select_skilled_agent(agents, skills, call.required_skill.as_deref(), fallback) {
+        Some(agent) => RoutingDecision::Agent { agent_id: agent.id },
+        None => RoutingDecision::Queued {
+            position: queue.enqueue(call).await,
+        },
+    }
+}
+
+/// SIP URI the caller is transferred to when connecting with an agent
+pub fn agent_sip_uri(agent: &Agent) -> Option<String> {
+    agent
+        .sip_username
+        .as_deref()
+        .filter(|u| !u.is_empty())
+        .map(|u| format!("sip:{}@sip.telnyx.com", u))
+}
+
+/// Route a new inbound call: record it, attach the caller's lead if their
+/// number is known, and either reserve an idle agent or queue the call.
+pub async fn route_inbound_call(
+    state: &AppState,
+    call_control_id: &str,
+    from: &str,
+    to: &str,
+) -> Result<(Call, RoutingDecision), sqlx::Error> {
+    let (call, lead) = record_inbound_call(state, call_control_id, from, to).await?;
+
+    let agents = db::agents::get_ready(&state.db).await?;
+    offer_call(state, call, call_control_id, from, lead, agents).await
+}
+
+/// Skill an agent needs to take `call`: its campaign's, or else its inbound number's
+pub async fn required_skill(state: &AppState, call: &Call) -> Result<Option<String>, sqlx::Error> {
+    if let Some(campaign_id) = call.campaign_id {
+        let campaign = db::campaigns::get_by_id(&state.db, campaign_id).await?;
+        return Ok(campaign.and_then(|c| c.required_skill));
+    }
+    match call.to_number.as_deref() {
+        Some(to) => db::agent_skills::required_for_number(&state.db, to).await,
+        None => Ok(None),
+    }
+}
+
+/// Record a new inbound call, with the caller's lead if their number is known
+pub async fn record_inbound_call(
+    state: &AppState,
+    call_control_id: &str,
+    from: &str,
+    to: &str,
+) -> Result<(Call, Option<Lead>), sqlx::Error> {
+    let lead = db::leads::get_by_phone(&state.db, from).await?;
+    let call = db::calls::create_inbound(&state.db, lead.as_ref().map(|l| l.id), call_control_id, from, to).await?;
+    Ok((call, lead))
+}
+
+/// Reserve the longest idle of `agents` who is on shift for an inbound call, or queue it
+async fn offer_call(
+    state: &AppState,
+    call: Call,
+    call_control_id: &str,
+    from: &str,
+    lead: Option<Lead>,
+    agents: Vec<Agent>,
+) -> Result<(Call, RoutingDecision), sqlx::Error> {
+    let mut agents = db::agent_schedules::filter_on_shift(&state.db, agents, Utc::now()).await?;
+    let agent_ids: Vec<i64> = agents.iter().map(|a| a.id).collect();
+    let skills = db::agent_skills::get_for_agents(&state.db, &agent_ids).await?;
+    let queued = QueuedCall {
+        call_id: call.id,
+        call_control_id: call_control_id.to_string(),
+        from: from.to_string(),
+        lead_id: call.lead_id,
+        required_skill: required_skill(state, &call).await?,
+        enqueued_at: Utc::now(),
+    };
+
+    // Agents were read before they were claimed, so one may already have
+    // been taken by a concurrent call; skip them and offer the next
+    let decision = loop {
+        let decision = route_to_available(&state.call_queue, &agents, &skills, state.skill_fallback, queued.clone()).await;
+        let RoutingDecision::Agent { agent_id } = decision else {
+            break decision;
+        };
+        if db::agents::claim_ready(&state.db, agent_id, call.id).await?.is_some() {
+            break decision;
+        }
+        agents.retain(|a| a.id != agent_id);
+    };
+
+    let call = match decision {
+        RoutingDecision::Agent { agent_id } => {
+            let call = db::calls::assign_agent(&state.db, call.id, agent_id).await?;
+            if let Some(agent) = agents.iter().find(|a| a.id == agent_id) {
+                publish_call_incoming(state, agent, call.id, from, lead).await;
+            }
+            call
+        }
+        RoutingDecision::Queued { position } => {
+            tracing::info!("No agent available for inbound call {}, queued at position {}", call.id, position);
+            publish_queue(state).await;
+            call
+        }
+    };
+
+    Ok((call, decision))
+}
+
+/// What a waiting caller hears at a given position
+pub fn announcement(position: usize) -> String {
+    if position == 1 {
+        "You are next in line. An agent will be with you shortly.".to_string()
+    } else {
+        format!("You are number {} in the queue. Please stay on the line.", position)
+    }
+}
+
+/// Tell connected clients the queue changed
+pub async fn publish_queue(state: &AppState) {
+    let calls = state.call_queue.snapshot(Utc::now()).await;
+    state.events.publish(ServerEvent::QueueUpdated { calls });
+}
+
+/// Tell the agent a call was routed to who is calling
+pub async fn publish_call_incoming(state: &AppState, agent: &Agent, call_id: i64, from: &str, lead: Option<Lead>) {
+    let screen_pop = match screen_pop(state, call_id, lead).await {
+        Ok(screen_pop) => screen_pop,
+        Err(e) => {
+            tracing::warn!("Failed to load screen pop for call {}: {}", call_id, e);
+            ScreenPop::new_caller()
+        }
+    };
+
+    state.events.publish(ServerEvent::CallIncoming {
+        agent_id: agent.id,
+        user_id: agent.user_id,
+        call_id,
+        from: from.to_string(),
+        screen_pop,
+    });
+}
+
+/// The caller's lead with its latest notes and previous call, or a new caller
+async fn screen_pop(state: &AppState, call_id: i64, lead: Option<Lead>) -> Result<ScreenPop, sqlx::Error> {
+    let Some(lead) = lead else {
+        return Ok(ScreenPop::new_caller());
+    };
+
+    let (notes, calls) = tokio::try_join!(
+        db::lead_notes::get_for_lead(&state.db, lead.id),
+        db::calls::get_by_lead(&state.db, lead.id),
+    )?;
+    Ok(ScreenPop::for_lead(lead, notes, &calls, call_id))
+}
+
+/// Hand queued calls to available agents, oldest call first. Returns how many were connected.
+pub async fn dispatch_queued_calls(state: &AppState) -> Result<usize, sqlx::Error> {
+    let mut dispatched = 0;
+
+    while !state.call_queue.is_empty().await {
+        let agents = db::agents::get_ready(&state.db).await?;
+        let agents = db::agent_schedules::filter_on_shift(&state.db, agents, Utc::now()).await?;
+        let agent_ids: Vec<i64> = agents.iter().map(|a| a.id).collect();
+        let skills = db::agent_skills::get_for_agents(&state.db, &agent_ids).await?;
+        let pick = |call: &QueuedCall| {
+            select_skilled_agent(&agents, &skills, call.required_skill.as_deref(), state.skill_fallback)
+        };
+
+        // The oldest call someone free can take; skilled calls may wait behind
+        let Some(queued) = state.call_queue.dequeue_where(|call| pick(call).is_some()).await else {
+            break;
+        };
+        let Some(agent) = pick(&queued).cloned() else {
+            state.call_queue.requeue(queued).await;
+            break;
+        };
+
+        // Someone else may have taken the agent since they were read
+        match db::agents::claim_ready(&state.db, agent.id, queued.call_id).await {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                state.call_queue.requeue(queued).await;
+                continue;
+            }
+            Err(e) => {
+                state.call_queue.requeue(queued).await;
+                return Err(e);
+            }
+        }
+
+        // A caller who can't be connected keeps their place and the agent is freed again
+        if let Err(e) = connect_queued_call(state, &queued, &agent).await {
+            tracing::error!("Failed to connect queued call {} to agent {}: {}", queued.call_id, agent.id, e);
+            release_agent(state, agent.id, queued.call_id).await;
+            state.call_queue.requeue(queued).await;
+            break;
+        }
+        dispatched += 1;
+    }
+
+    if dispatched > 0 {
+        publish_queue(state).await;
+    }
+    Ok(dispatched)
+}
+
+/// Transfer a queued caller to the agent claimed for them
+async fn connect_queued_call(state: &AppState, queued: &QueuedCall, agent: &Agent) -> Result<(), String> {
+    let uri = agent_sip_uri(agent).ok_or_else(|| format!("agent {} has no SIP username", agent.id))?;
+
+    db::calls::assign_agent(&state.db, queued.call_id, agent.id)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    state.telnyx.transfer(&queued.call_control_id, &uri).await.map_err(|e| format!("{:?}", e))?;
+
+    let lead = match queued.lead_id {
+        Some(lead_id) => db::leads::get_by_id(&state.db, lead_id).await.ok().flatten(),
+        None => None,
+    };
+    publish_call_incoming(state, agent, queued.call_id, &queued.from, lead).await;
+    Ok(())
+}
+
+/// Put an agent claimed for a call that never connected back to Ready
+async fn release_agent(state: &AppState, agent_id: i64, call_id: i64) {
+    if let Err(e) = db::agents::release_claim(&state.db, agent_id, call_id).await {
+        tracing::error!("Failed to free agent {}: {}", agent_id, e);
+    }
+}
+
+/// Tell every waiting caller their position
+pub async fn announce_positions(state: &AppState) {
+    for call in state.call_queue.snapshot(Utc::now()).await {
+        let text = announcement(call.position);
+        if let Err(e) = state.telnyx.speak(&call.call_control_id, &text, Some("female")).await {
+            tracing::warn!("Failed to announce queue position for call {}: {:?}", call.call_id, e);
+        }
+    }
+}
+
+/// Said before the menu is repeated when the caller pressed nothing
+pub const IVR_NO_INPUT_PROMPT: &str = "Sorry, we didn't get your selection.";
+
+/// Said before the menu is repeated after a key with no option
+pub const IVR_INVALID_PROMPT: &str = "Sorry, that is not a valid option.";
+
+/// Said before recording a voicemail
+pub const IVR_VOICEMAIL_PROMPT: &str = "Please leave your message after the tone, then hang up.";
+
+/// What the caller did when asked to press a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IvrInput<'a> {
+    Digit(&'a str),
+    /// The gather timed out
+    NoInput,
+}
+
+/// What an IVR menu does next
+#[derive(Debug, Clone, PartialEq)]
+pub enum IvrStep {
+    /// Speak this and wait for a key
+    Prompt(String),
+    /// The caller picked an option
+    Route(IvrOption),
+    /// Out of retries
+    Hangup,
+}
+
+/// A caller working through an IVR menu
+#[derive(Debug, Clone)]
+pub struct IvrSession {
+    pub call_id: i64,
+    pub menu: IvrMenu,
+    /// No-input and invalid attempts so far
+    pub failures: i32,
+}
+
+impl IvrSession {
+    pub fn new(call_id: i64, menu: IvrMenu) -> Self {
+        Self { call_id, menu, failures: 0 }
+    }
+
+    /// The first step, once the call is answered
+    pub fn start(&self) -> IvrStep {
+        IvrStep::Prompt(self.menu.prompt.clone())
+    }
+
+    /// Advance on the caller's input. No input and unknown keys repeat the
+    /// menu with an apology until `max_retries` repeats are used up.
+    pub fn handle(&mut self, input: IvrInput) -> IvrStep {
+        let apology = match input {
+            IvrInput::Digit(digit) => match self.menu.option(digit) {
+                Some(option) => return IvrStep::Route(option.clone()),
+                None => IVR_INVALID_PROMPT,
+            },
+            IvrInput::NoInput => IVR_NO_INPUT_PROMPT,
+        };
+
+        self.failures += 1;
+        if self.failures > self.menu.max_retries {
+            return IvrStep::Hangup;
+        }
+        IvrStep::Prompt(format!("{} {}", apology, self.menu.prompt))
+    }
+}
+
+/// Callers currently in an IVR menu, by call control id
+#[derive(Default)]
+pub struct IvrSessions {
+    sessions: RwLock<HashMap<String, IvrSession>>,
+}
+
+impl IvrSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn insert(&self, call_control_id: &str, session: IvrSession) {
+        self.sessions.write().await.insert(call_control_id.to_string(), session);
+    }
+
+    pub async fn remove(&self, call_control_id: &str) -> Option<IvrSession> {
+        self.sessions.write().await.remove(call_control_id)
+    }
+
+    /// The first step of a call's menu, if it is in one
+    pub async fn start(&self, call_control_id: &str) -> Option<(IvrStep, std::time::Duration)> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(call_control_id)?;
+        Some((session.start(), ivr_timeout(&session.menu)))
+    }
+
+    /// Advance a call's menu. The session ends once the menu is done with the call.
+    pub async fn handle(&self, call_control_id: &str, input: IvrInput<'_>) -> Option<(IvrStep, i64, std::time::Duration)> {
+        let mut sessions = self.sessions.write().await;
+        let session = sessions.get_mut(call_control_id)?;
+        let step = session.handle(input);
+        let (call_id, timeout) = (session.call_id, ivr_timeout(&session.menu));
+        if !matches!(step, IvrStep::Prompt(_)) {
+            sessions.remove(call_control_id);
+        }
+        Some((step, call_id, timeout))
+    }
+}
+
+fn ivr_timeout(menu: &IvrMenu) -> std::time::Duration {
+    std::time::Duration::from_secs(menu.timeout_seconds.max(1) as u64)
+}
+
+/// Input from a `call.gather.ended` webhook; None when the gather ended
+/// because the call did
+pub fn gather_input<'a>(status: Option<&str>, digits: Option<&'a str>) -> Option<IvrInput<'a>> {
+    match (status, digits.filter(|d| !d.is_empty())) {
+        (Some("call_hangup" | "cancelled"), _) => None,
+        (_, Some(digits)) => Some(IvrInput::Digit(digits)),
+        (_, None) => Some(IvrInput::NoInput),
+    }
+}
+
+/// Play the menu to an answered call, if its number has one. Returns false
+/// for calls not in a menu.
+pub async fn start_ivr(state: &AppState, call_control_id: &str) -> bool {
+    let Some((step, timeout)) = state.ivr_sessions.start(call_control_id).await else {
+        return false;
+    };
+    run_ivr_step(state, call_control_id, None, step, timeout).await;
+    true
+}
+
+/// Act on the key a caller pressed, or didn't
+pub async fn handle_ivr_input(state: &AppState, call_control_id: &str, input: IvrInput<'_>) {
+    if let Some((step, call_id, timeout)) = state.ivr_sessions.handle(call_control_id, input).await {
+        run_ivr_step(state, call_control_id, Some(call_id), step, timeout).await;
+    }
+}
+
+async fn run_ivr_step(
+    state: &AppState,
+    call_control_id: &str,
+    call_id: Option<i64>,
+    step: IvrStep,
+    timeout: std::time::Duration,
+) {
+    let result = match step {
+        IvrStep::Prompt(prompt) => state.telnyx.gather_digit(call_control_id, &prompt, timeout).await,
+        IvrStep::Hangup => state.telnyx.hangup(call_control_id).await,
+        IvrStep::Route(option) => match option.action {
+            IvrAction::Queue => {
+                if let Some(call_id) = call_id {
+                    if let Err(e) = route_ivr_choice(state, call_id, call_control_id, option.campaign_id).await {
+                        tracing::error!("Failed to route IVR call {}: {}", call_id, e);
+                    }
+                }
+                Ok(())
+            }
+            IvrAction::Voicemail => {
+                let settings = RecordingSettings::single();
+                let result = match state.telnyx.speak(call_control_id, IVR_VOICEMAIL_PROMPT, Some("female")).await {
+                    Ok(()) => state.telnyx.start_recording(call_control_id, settings).await,
+                    Err(e) => Err(e),
+                };
+                if let (Ok(()), Some(call_id)) = (&result, call_id) {
+                    let _ = db::calls::set_recording_channels(&state.db, call_id, settings.channels).await;
+                }
+                result
+            }
+            IvrAction::Hangup => state.telnyx.hangup(call_control_id).await,
+        },
+    };
+
+    if let Err(e) = result {
+        tracing::error!("IVR action failed for {}: {:?}", call_control_id, e);
+    }
+}
+
+/// Route a call whose caller picked a queue option, to the option's
+/// campaign agents when it names a campaign
+async fn route_ivr_choice(
+    state: &AppState,
+    call_id: i64,
+    call_control_id: &str,
+    campaign_id: Option<i64>,
+) -> Result<(), sqlx::Error> {
+    let Some(mut call) = db::calls::get_by_id(&state.db, call_id).await? else {
+        return Ok(());
+    };
+    let agents = match campaign_id {
+        Some(campaign_id) => {
+            call = db::calls::set_campaign(&state.db, call.id, campaign_id).await?;
+            db::agents::get_ready_for_campaign(&state.db, campaign_id).await?
+        }
+        None => db::agents::get_ready(&state.db).await?,
+    };
+    let lead = match call.lead_id {
+        Some(lead_id) => db::leads::get_by_id(&state.db, lead_id).await?,
+        None => None,
+    };
+
+    let from = call.from_number.clone().unwrap_or_default();
+    let (call, _) = offer_call(state, call, call_control_id, &from, lead, agents).await?;
+    super::connect_inbound_call(state, &call, call_control_id).await;
+    Ok(())
+}
+
+/// Run the queue worker in the background
+pub fn spawn_queue_worker(state: Arc<AppState>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(QUEUE_ANNOUNCE_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = dispatch_queued_calls(&state).await {
+                tracing::error!("Failed to dispatch queued calls: {}", e);
+            }
+            if let Err(e) = super::callbacks::dial_due(&state).await {
+                tracing::error!("Failed to dial abandoned call callbacks: {}", e);
+            }
+            announce_positions(&state).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures::agent;
+    use chrono::Duration;
+
+    fn menu() -> IvrMenu {
+        IvrMenu {
+            id: 1,
+            phone_number: "+15550100".to_string(),
+            prompt: "Press 1 for sales or 2 to leave a message.".to_string(),
+            options: vec![
+                IvrOption { digit: "1".to_string(), action: IvrAction::Queue, campaign_id: Some(4) },
+                IvrOption { digit: "2".to_string(), action: IvrAction::Voicemail, campaign_id: None },
+            ],
+            max_retries: 2,
+            timeout_seconds: 5,
+            created_at: None,
+            updated_at: None,
+        }
+    }
+
+    fn queued(call_id: i64) -> QueuedCall {
+        QueuedCall {
+            call_id,
+            call_control_id: format!("v3:inbound-{}", call_id),
+            from: "+15551234567".to_string(),
+            lead_id: None,
+            required_skill: None,
+            enqueued_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_routes_to_longest_idle_agent() {
+        let queue = CallQueue::new();
+        let agents = vec![
+            agent(1, AgentStatus::Ready, 2),
+            agent(2, AgentStatus::Ready, 30),
+            agent(3, AgentStatus::OnCall, 60),
+        ];
+
+        let decision = route_to_available(&queue, &agents, &AgentSkillMap::new(), false, queued(10)).await;
+
+        assert_eq!(decision, RoutingDecision::Agent { agent_id: 2 });
+        assert!(queue.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_queues_when_all_agents_busy() {
+        let queue = CallQueue::new();
+        let agents = vec![
+            agent(1, AgentStatus::OnCall, 5),
+            agent(2, AgentStatus::AfterCall, 5),
+        ];
+
+        let skills = AgentSkillMap::new();
+        assert_eq!(
+            route_to_available(&queue, &agents, &skills, false, queued(10)).await,
+            RoutingDecision::Queued { position: 1 }
+        );
+        assert_eq!(
+            route_to_available(&queue, &agents, &skills, false, queued(11)).await,
+            RoutingDecision::Queued { position: 2 }
+        );
+        assert_eq!(queue.len().await, 2);
+
+        // Hung-up callers leave the queue; the rest keep FIFO order
+        assert!(queue.remove("v3:inbound-10").await.is_some());
+        assert_eq!(queue.dequeue().await.map(|c| c.call_id), Some(11));
+        assert!(queue.dequeue().await.is_none());
+    }
+
+    fn spanish_call(call_id: i64) -> QueuedCall {
+        QueuedCall { required_skill: Some("spanish".to_string()), ..queued(call_id) }
+    }
+
+    fn skills(agent_skills: &[(i64, &str)]) -> AgentSkillMap {
+        let mut map = AgentSkillMap::new();
+        for (agent_id, skill) in agent_skills {
+            map.entry(*agent_id).or_default().insert(skill.to_string());
+        }
+        map
+    }
+
+    #[tokio::test]
+    async fn test_spanish_call_routes_to_spanish_agent() {
+        let queue = CallQueue::new();
+        // Agent 1 has been idle longer but doesn't speak Spanish
+        let agents = vec![agent(1, AgentStatus::Ready, 30), agent(2, AgentStatus::Ready, 2)];
+        let skills = skills(&[(2, "spanish"), (1, "french")]);
+
+        let decision = route_to_available(&queue, &agents, &skills, false, spanish_call(10)).await;
+        assert_eq!(decision, RoutingDecision::Agent { agent_id: 2 });
+
+        // Calls without a required skill still go to the longest idle agent
+        let decision = route_to_available(&queue, &agents, &skills, false, queued(11)).await;
+        assert_eq!(decision, RoutingDecision::Agent { agent_id: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_skilled_call_waits_unless_fallback_is_on() {
+        let queue = CallQueue::new();
+        let agents = vec![agent(1, AgentStatus::Ready, 30), agent(2, AgentStatus::OnCall, 2)];
+        let skills = skills(&[(2, "spanish")]);
+
+        // The only Spanish speaker is busy
+        let decision = route_to_available(&queue, &agents, &skills, false, spanish_call(10)).await;
+        assert_eq!(decision, RoutingDecision::Queued { position: 1 });
+
+        let decision = route_to_available(&queue, &agents, &skills, true, spanish_call(11)).await;
+        assert_eq!(decision, RoutingDecision::Agent { agent_id: 1 });
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_where_skips_calls_nobody_can_take() {
+        let queue = CallQueue::new();
+        queue.enqueue(spanish_call(1)).await;
+        queue.enqueue(queued(2)).await;
+
+        let taken = queue.dequeue_where(|call| call.required_skill.is_none()).await;
+        assert_eq!(taken.map(|c| c.call_id), Some(2));
+        assert_eq!(queue.position("v3:inbound-1").await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_requeued_call_keeps_its_place() {
+        let queue = CallQueue::new();
+        let now = Utc::now();
+        for id in 1..=3 {
+            queue.enqueue(QueuedCall { enqueued_at: now + Duration::seconds(id), ..queued(id) }).await;
+        }
+
+        let taken = queue.dequeue_where(|call| call.call_id == 2).await.unwrap();
+        queue.requeue(taken).await;
+        assert_eq!(queue.position("v3:inbound-2").await, Some(2));
+
+        let taken = queue.dequeue().await.unwrap();
+        queue.requeue(taken).await;
+        assert_eq!(queue.position("v3:inbound-1").await, Some(1));
+        assert_eq!(queue.len().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_order_and_positions() {
+        let queue = CallQueue::new();
+        for id in 1..=3 {
+            queue.enqueue(queued(id)).await;
+        }
+
+        assert_eq!(queue.position("v3:inbound-3").await, Some(3));
+        assert_eq!(queue.dequeue().await.map(|c| c.call_id), Some(1));
+
+        // Everyone behind moves up
+        assert_eq!(queue.position("v3:inbound-2").await, Some(1));
+        assert_eq!(queue.position("v3:inbound-3").await, Some(2));
+        assert_eq!(queue.position("v3:inbound-1").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_reports_position_and_wait() {
+        let queue = CallQueue::new();
+        let now = Utc::now();
+        let mut first = queued(1);
+        first.enqueued_at = now - Duration::seconds(90);
+        queue.enqueue(first).await;
+        queue.enqueue(queued(2)).await;
+
+        let snapshot = queue.snapshot(now).await;
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!((snapshot[0].call_id, snapshot[0].position, snapshot[0].wait_seconds), (1, 1, 90));
+        assert_eq!((snapshot[1].call_id, snapshot[1].position), (2, 2));
+    }
+
+    #[test]
+    fn test_announcement_mentions_position() {
+        assert!(announcement(1).contains("next in line"));
+        assert!(announcement(4).contains("number 4"));
+    }
+
+    #[test]
+    fn test_agent_sip_uri() {
+        let mut a = agent(1, AgentStatus::Ready, 0);
+        assert_eq!(agent_sip_uri(&a).as_deref(), Some("sip:agent1@sip.telnyx.com"));
+
+        a.sip_username = None;
+        assert!(agent_sip_uri(&a).is_none());
+    }
+
+    #[test]
+    fn test_ivr_digit_routes_to_option() {
+        let mut session = IvrSession::new(9, menu());
+        assert_eq!(session.start(), IvrStep::Prompt("Press 1 for sales or 2 to leave a message.".to_string()));
+
+        match session.handle(IvrInput::Digit("1")) {
+            IvrStep::Route(option) => {
+                assert_eq!(option.action, IvrAction::Queue);
+                assert_eq!(option.campaign_id, Some(4));
+            }
+            other => panic!("expected a route, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_ivr_invalid_digit_and_timeout_retry_then_hang_up() {
+        let mut session = IvrSession::new(9, menu());
+
+        assert_eq!(
+            session.handle(IvrInput::Digit("7")),
+            IvrStep::Prompt("Sorry, that is not a valid option. Press 1 for sales or 2 to leave a message.".to_string())
+        );
+        assert_eq!(
+            session.handle(IvrInput::NoInput),
+            IvrStep::Prompt("Sorry, we didn't get your selection. Press 1 for sales or 2 to leave a message.".to_string())
+        );
+        // Two retries used up: the third miss ends the call
+        assert_eq!(session.handle(IvrInput::NoInput), IvrStep::Hangup);
+    }
+
+    #[test]
+    fn test_ivr_valid_digit_after_retry() {
+        let mut session = IvrSession::new(9, menu());
+        assert!(matches!(session.handle(IvrInput::NoInput), IvrStep::Prompt(_)));
+        assert!(matches!(
+            session.handle(IvrInput::Digit("2")),
+            IvrStep::Route(IvrOption { action: IvrAction::Voicemail, .. })
+        ));
+    }
+
+    #[test]
+    fn test_gather_input_from_webhook() {
+        assert_eq!(gather_input(Some("valid"), Some("1")), Some(IvrInput::Digit("1")));
+        assert_eq!(gather_input(Some("invalid"), Some("9")), Some(IvrInput::Digit("9")));
+        assert_eq!(gather_input(Some("timeout"), Some("")), Some(IvrInput::NoInput));
+        assert_eq!(gather_input(Some("timeout"), None), Some(IvrInput::NoInput));
+        assert_eq!(gather_input(Some("call_hangup"), None), None);
+    }
+
+    #[tokio::test]
+    async fn test_ivr_session_ends_once_routed() {
+        let sessions = IvrSessions::new();
+        sessions.insert("v3:ivr", IvrSession::new(9, menu())).await;
+
+        assert!(matches!(sessions.handle("v3:ivr", IvrInput::NoInput).await, Some((IvrStep::Prompt(_), 9, _))));
+        assert!(matches!(sessions.handle("v3:ivr", IvrInput::Digit("1")).await, Some((IvrStep::Route(_), 9, _))));
+        assert!(sessions.handle("v3:ivr", IvrInput::Digit("1")).await.is_none());
+    }
+}
@@ -0,0 +1,110 @@
+//! Campaign scheduler
+//!
+//! Every tick, campaigns whose scheduled start has passed are activated and
+//! handed to the automation manager, and campaigns whose scheduled end has
+//! passed are stopped. See `Campaign::scheduled_transition` for the rules.
+//! Schedule times that pass without a transition, such as a window that
+//! closed before the campaign was ever activated, are cleared so the same
+//! campaigns aren't picked up again on every tick.
+
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, Duration};
+
+use crate::models::{Campaign, CampaignStatus};
+use super::automation::AutomationManager;
+use super::db;
+
+/// How often schedules are checked
+pub const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Status changes due at `now`
+pub fn due_transitions(campaigns: &[Campaign], now: DateTime<Utc>) -> Vec<(i64, CampaignStatus)> {
+    campaigns
+        .iter()
+        .filter_map(|c| c.scheduled_transition(now).map(|status| (c.id, status)))
+        .collect()
+}
+
+/// Campaigns picked up as due that have no transition to make at `now`
+pub fn missed_windows(campaigns: &[Campaign], now: DateTime<Utc>) -> Vec<i64> {
+    campaigns
+        .iter()
+        .filter(|c| c.scheduled_transition(now).is_none())
+        .map(|c| c.id)
+        .collect()
+}
+
+/// Apply every transition due at `now`. Returns how many campaigns changed.
+pub async fn tick(db: &PgPool, automation: &AutomationManager, now: DateTime<Utc>) -> Result<usize, sqlx::Error> {
+    let campaigns = db::campaigns::get_schedule_due(db, now).await?;
+    let transitions = due_transitions(&campaigns, now);
+
+    for (campaign_id, status) in &transitions {
+        db::campaigns::apply_scheduled_status(db, *campaign_id, status.clone()).await?;
+        tracing::info!("Campaign {} moved to {} by schedule", campaign_id, status.display_name());
+
+        if *status == CampaignStatus::Active {
+            if let Err(e) = automation.start_campaign(*campaign_id).await {
+                tracing::error!("Failed to start automation for scheduled campaign {}: {}", campaign_id, e);
+            }
+        } else {
+            // Not running is fine - the dialer may never have been started
+            let _ = automation.stop_campaign(*campaign_id).await;
+        }
+    }
+
+    let missed = missed_windows(&campaigns, now);
+    if !missed.is_empty() {
+        db::campaigns::clear_passed_schedule(db, &missed, now).await?;
+        tracing::info!("Cleared passed schedule times for campaign(s) {:?}", missed);
+    }
+
+    Ok(transitions.len())
+}
+
+/// Run the scheduler in the background
+pub fn spawn(db: PgPool, automation: Arc<AutomationManager>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = interval(TICK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = tick(&db, &automation, Utc::now()).await {
+                tracing::error!("Campaign scheduler tick failed: {}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::fixtures;
+
+    #[test]
+    fn test_missed_windows_are_separated_from_due_transitions() {
+        let now = Utc::now();
+        let due = Campaign {
+            status: CampaignStatus::Draft,
+            scheduled_start_at: Some(now - chrono::Duration::minutes(1)),
+            ..fixtures::campaign(1)
+        };
+        let closed = Campaign {
+            status: CampaignStatus::Draft,
+            scheduled_start_at: Some(now - chrono::Duration::hours(2)),
+            scheduled_end_at: Some(now - chrono::Duration::hours(1)),
+            ..fixtures::campaign(2)
+        };
+        let finished = Campaign {
+            status: CampaignStatus::Completed,
+            scheduled_end_at: Some(now - chrono::Duration::hours(1)),
+            ..fixtures::campaign(3)
+        };
+        let campaigns = [due, closed, finished];
+
+        assert_eq!(due_transitions(&campaigns, now), vec![(1, CampaignStatus::Active)]);
+        assert_eq!(missed_windows(&campaigns, now), vec![2, 3]);
+    }
+}
@@ -0,0 +1,140 @@
+//! Call sentiment scoring
+//!
+//! After an AI call ends, what the caller said is scored from -1 (negative)
+//! to 1 (positive) so supervisors can find calls that went badly. The
+//! configured language model scores the transcript when it has an API key;
+//! otherwise, or if the request fails, a small word lexicon gives a rough score.
+
+use super::llm::{LlmClient, Message};
+
+/// Words that pull the score down
+const NEGATIVE_WORDS: &[&str] = &[
+    "angry", "annoyed", "annoying", "awful", "bad", "cancel", "complain", "complaint", "disappointed",
+    "frustrated", "frustrating", "hate", "horrible", "lawyer", "never", "problem", "refund", "ridiculous",
+    "rude", "scam", "stop", "terrible", "unacceptable", "unhappy", "upset", "useless", "waste", "worst",
+];
+
+/// Words that push the score up
+const POSITIVE_WORDS: &[&str] = &[
+    "amazing", "appreciate", "awesome", "definitely", "excellent", "fantastic", "glad", "good", "great",
+    "happy", "helpful", "interested", "love", "nice", "perfect", "pleased", "sounds", "sure", "thank",
+    "thanks", "wonderful", "yes",
+];
+
+/// Words that flip the word after them ("not happy")
+const NEGATIONS: &[&str] = &["not", "no", "don't", "didn't", "isn't", "wasn't", "never", "can't", "won't"];
+
+/// Rough sentiment from word counts, -1 to 1. Text with no scored words is neutral.
+pub fn lexicon_score(text: &str) -> f32 {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '\''))
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    let (mut positive, mut negative) = (0u32, 0u32);
+    for (i, word) in words.iter().enumerate() {
+        let negated = i > 0 && NEGATIONS.contains(&words[i - 1].as_str());
+        let polarity = if POSITIVE_WORDS.contains(&word.as_str()) {
+            1
+        } else if NEGATIVE_WORDS.contains(&word.as_str()) {
+            -1
+        } else {
+            continue;
+        };
+
+        if (polarity > 0) != negated {
+            positive += 1;
+        } else {
+            negative += 1;
+        }
+    }
+
+    let total = positive + negative;
+    if total == 0 {
+        return 0.0;
+    }
+    (positive as f32 - negative as f32) / total as f32
+}
+
+/// Transcript with each turn labelled by speaker
+pub fn transcript(conversation: &[Message]) -> String {
+    conversation
+        .iter()
+        .map(|turn| {
+            let speaker = if turn.role == "user" { "Caller" } else { "Agent" };
+            format!("{}: {}", speaker, turn.content)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Score a conversation with the model, falling back to the lexicon over what the caller said
+pub async fn score(llm: &dyn LlmClient, conversation: &[Message]) -> f32 {
+    if llm.is_configured() {
+        match llm.score_sentiment(&transcript(conversation)).await {
+            Ok(score) => return score,
+            Err(e) => tracing::warn!("Sentiment scoring failed, using lexicon: {}", e),
+        }
+    }
+
+    let caller_text = conversation
+        .iter()
+        .filter(|turn| turn.role == "user")
+        .map(|turn| turn.content.as_str())
+        .collect::<Vec<_>>()
+        .join(" ");
+    lexicon_score(&caller_text)
+}
+
+/// Mean of the scored calls, or None when no call has a score
+pub fn average(scores: &[f32]) -> Option<f64> {
+    if scores.is_empty() {
+        return None;
+    }
+    Some(scores.iter().map(|s| f64::from(*s)).sum::<f64>() / scores.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lexicon_scores_negative_text_low() {
+        let score = lexicon_score("This is ridiculous. I'm frustrated and I want a refund, stop calling me!");
+        assert!(score <= -0.9, "score was {}", score);
+
+        // Negation flips a positive word
+        assert!(lexicon_score("I'm not happy with this at all") < 0.0);
+    }
+
+    #[test]
+    fn test_lexicon_scores_positive_text_high() {
+        let positive = lexicon_score("Yes, that sounds great, thanks so much for the help!");
+        let negative = lexicon_score("This is terrible and I'm upset");
+        assert!(positive >= 0.9, "score was {}", positive);
+        assert!(positive > negative);
+        assert_eq!(lexicon_score("The meeting is on Tuesday"), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_unconfigured_model_scores_caller_turns_only() {
+        let conversation = vec![
+            Message { role: "assistant".to_string(), content: "Great to hear, thanks!".to_string() },
+            Message { role: "user".to_string(), content: "This is a waste of my time, stop calling".to_string() },
+        ];
+        assert_eq!(score(&crate::server::llm::ClaudeClient::new(String::new()), &conversation).await, -1.0);
+        assert_eq!(
+            transcript(&conversation),
+            "Agent: Great to hear, thanks!\nCaller: This is a waste of my time, stop calling"
+        );
+    }
+
+    #[test]
+    fn test_average_ignores_unscored_calls() {
+        assert_eq!(average(&[]), None);
+        assert_eq!(average(&[0.5, -0.5, 1.0]), Some(1.0 / 3.0));
+        let avg = average(&[-0.8, -0.4]).unwrap();
+        assert!((avg - -0.6).abs() < 1e-6);
+    }
+}
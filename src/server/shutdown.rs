@@ -0,0 +1,171 @@
+//! Graceful shutdown
+//!
+//! On SIGINT/SIGTERM the server stops dialing, hangs up calls that are still
+//! in progress and unregisters from the SIP trunk before axum stops accepting
+//! connections.
+
+use std::future::Future;
+use std::sync::Arc;
+use async_trait::async_trait;
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+
+use super::{automation::AutomationManager, db, sip::SipUserAgent, telnyx::TelnyxClient};
+
+/// Cleanup work run once a shutdown signal arrives
+#[async_trait]
+pub trait ShutdownHook: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn shutdown(&self);
+}
+
+/// Stops campaign automation so no new calls are placed
+pub struct AutomationHook(pub Arc<AutomationManager>);
+
+#[async_trait]
+impl ShutdownHook for AutomationHook {
+    fn name(&self) -> &'static str {
+        "campaign automation"
+    }
+
+    async fn shutdown(&self) {
+        self.0.shutdown().await;
+    }
+}
+
+/// Hangs up Telnyx calls that haven't ended yet
+pub struct TelnyxCallsHook {
+    pub db: PgPool,
+    pub telnyx: TelnyxClient,
+}
+
+#[async_trait]
+impl ShutdownHook for TelnyxCallsHook {
+    fn name(&self) -> &'static str {
+        "telnyx calls"
+    }
+
+    async fn shutdown(&self) {
+        let calls = match db::calls::get_in_progress(&self.db).await {
+            Ok(calls) => calls,
+            Err(e) => {
+                tracing::error!("Failed to load in-progress calls: {}", e);
+                return;
+            }
+        };
+
+        for call in calls {
+            if let Some(call_control_id) = &call.call_control_id {
+                if let Err(e) = self.telnyx.hangup(call_control_id).await {
+                    tracing::warn!("Failed to hang up call {}: {:?}", call.id, e);
+                }
+            }
+            let _ = db::calls::set_ended(&self.db, call.id, Some("shutdown")).await;
+        }
+    }
+}
+
+/// Hangs up SIP calls and unregisters from the trunk
+pub struct SipAgentHook(pub Arc<RwLock<SipUserAgent>>);
+
+#[async_trait]
+impl ShutdownHook for SipAgentHook {
+    fn name(&self) -> &'static str {
+        "sip user agent"
+    }
+
+    async fn shutdown(&self) {
+        self.0.read().await.shutdown().await;
+    }
+}
+
+/// Resolves when the process receives Ctrl-C or SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Wait for `signal`, then run every hook in order. Pass to `axum::serve(..).with_graceful_shutdown`.
+pub async fn graceful<F>(signal: F, hooks: Vec<Box<dyn ShutdownHook>>)
+where
+    F: Future<Output = ()>,
+{
+    signal.await;
+    tracing::info!("Shutdown signal received, cleaning up");
+
+    for hook in hooks {
+        tracing::info!("Shutting down {}", hook.name());
+        hook.shutdown().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockAgent {
+        unregistered: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl ShutdownHook for MockAgent {
+        fn name(&self) -> &'static str {
+            "mock agent"
+        }
+
+        async fn shutdown(&self) {
+            self.unregistered.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_runs_hooks_after_signal() {
+        let unregistered = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+
+        let handle = tokio::spawn(graceful(
+            async move {
+                let _ = rx.await;
+            },
+            vec![Box::new(MockAgent { unregistered: unregistered.clone() })],
+        ));
+
+        // Nothing happens before the signal
+        tokio::task::yield_now().await;
+        assert_eq!(unregistered.load(Ordering::SeqCst), 0);
+
+        tx.send(()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(1), handle)
+            .await
+            .expect("shutdown future should resolve")
+            .unwrap();
+
+        assert_eq!(unregistered.load(Ordering::SeqCst), 1);
+    }
+}
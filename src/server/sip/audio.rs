@@ -0,0 +1,119 @@
+//! Audio files played into calls
+//!
+//! Call audio is 8kHz mono PCM decoded from G.711. Files such as hold music
+//! are read from WAV and brought to that rate by linear resampling.
+
+use super::SipError;
+
+/// Sample rate of decoded G.711 audio
+pub const G711_SAMPLE_RATE: u32 = 8000;
+
+/// Resample mono PCM with linear interpolation
+pub fn resample(pcm: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || pcm.is_empty() || from_rate == 0 {
+        return pcm.to_vec();
+    }
+
+    let out_len = (pcm.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let step = from_rate as f64 / to_rate as f64;
+    (0..out_len)
+        .map(|i| {
+            let pos = i as f64 * step;
+            let index = pos as usize;
+            let frac = pos - index as f64;
+            let a = pcm[index] as f64;
+            let b = *pcm.get(index + 1).unwrap_or(&pcm[index]) as f64;
+            (a + (b - a) * frac).round() as i16
+        })
+        .collect()
+}
+
+/// Read a 16-bit PCM WAV file as mono samples and its sample rate.
+/// Stereo is mixed down.
+pub fn decode_wav(bytes: &[u8]) -> Result<(Vec<i16>, u32), SipError> {
+    let invalid = |why: &str| SipError::Codec(format!("Invalid WAV file: {}", why));
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err(invalid("missing RIFF/WAVE header"));
+    }
+
+    let mut format = None;
+    let mut offset = 12;
+    while offset + 8 <= bytes.len() {
+        let id = &bytes[offset..offset + 4];
+        let len = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into().unwrap()) as usize;
+        let body = bytes.get(offset + 8..offset + 8 + len).ok_or_else(|| invalid("truncated chunk"))?;
+
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                let u16_at = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]);
+                let rate = u32::from_le_bytes(body[4..8].try_into().unwrap());
+                format = Some((u16_at(0), u16_at(2), rate, u16_at(14)));
+            }
+            b"data" => {
+                let (encoding, channels, rate, bits) = format.ok_or_else(|| invalid("data before fmt chunk"))?;
+                if encoding != 1 || bits != 16 || !(1..=2).contains(&channels) {
+                    return Err(invalid("only 16-bit PCM mono or stereo is supported"));
+                }
+                let samples: Vec<i16> = body.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+                let mono = if channels == 2 {
+                    samples.chunks_exact(2).map(|lr| ((lr[0] as i32 + lr[1] as i32) / 2) as i16).collect()
+                } else {
+                    samples
+                };
+                return Ok((mono, rate));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length
+        offset += 8 + len + (len & 1);
+    }
+    Err(invalid("no data chunk"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 16-bit mono PCM WAV file
+    fn encode_wav(samples: &[i16], sample_rate: u32) -> Vec<u8> {
+        let data_len = (samples.len() * 2) as u32;
+        let mut out = Vec::with_capacity(44 + data_len as usize);
+
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&1u16.to_le_bytes()); // mono
+        out.extend_from_slice(&sample_rate.to_le_bytes());
+        out.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+        out.extend_from_slice(&2u16.to_le_bytes()); // block align
+        out.extend_from_slice(&16u16.to_le_bytes()); // bits per sample
+
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            out.extend_from_slice(&sample.to_le_bytes());
+        }
+        out
+    }
+
+    #[test]
+    fn test_resample_to_16khz_interpolates() {
+        // Twice as many samples, with the midpoints interpolated
+        let resampled = resample(&[0, 1000, 2000, -2000], 8000, 16000);
+        assert_eq!(resampled, vec![0, 500, 1000, 1500, 2000, 0, -2000, -2000]);
+        assert_eq!(resample(&[0, 1000], G711_SAMPLE_RATE, G711_SAMPLE_RATE), vec![0, 1000]);
+    }
+
+    #[test]
+    fn test_decode_wav_round_trip() {
+        let pcm = [0i16, 1000, -1000, 32767];
+        assert_eq!(decode_wav(&encode_wav(&pcm, 16000)).unwrap(), (pcm.to_vec(), 16000));
+
+        assert!(matches!(decode_wav(b"RIFF\0\0\0\0WAVE"), Err(SipError::Codec(_))));
+        assert!(decode_wav(b"not a wav file").is_err());
+    }
+}
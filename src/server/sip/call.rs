@@ -5,8 +5,11 @@
 use std::sync::Arc;
 use tokio::sync::{mpsc, RwLock};
 use chrono::{DateTime, Utc};
+use ftth_rsipstack::dialog::client_dialog::ClientInviteDialog;
+use ftth_rsipstack::rsip;
 
-use super::rtp::{RtpSession, AudioFrame};
+use super::rtp::{RtpSession, AudioFrame, CallQualityMetrics};
+use super::sdp::{self, MediaDirection};
 use super::SipError;
 
 /// Call direction
@@ -82,14 +85,22 @@ pub struct SipCall {
     state: RwLock<CallState>,
     /// RTP session for audio
     rtp_session: Option<Arc<RtpSession>>,
+    /// Port reserved from the agent's allocator, taken once when the call ends
+    rtp_port: std::sync::Mutex<Option<u16>>,
     /// Call start time
     pub started_at: DateTime<Utc>,
     /// Call connect time (when answered)
     connected_at: RwLock<Option<DateTime<Utc>>>,
     /// Call end time
     ended_at: RwLock<Option<DateTime<Utc>>>,
+    /// Final SIP response status for a rejected outbound call
+    final_status: RwLock<Option<u16>>,
     /// Event sender
     event_tx: mpsc::Sender<CallEvent>,
+    /// Last SDP we offered, the base for hold and resume re-offers
+    local_sdp: std::sync::Mutex<Option<String>>,
+    /// Confirmed INVITE dialog of an outbound call, used for re-INVITEs
+    dialog: std::sync::Mutex<Option<ClientInviteDialog>>,
     /// Dialog state (for rsipstack integration)
     #[allow(dead_code)]
     dialog_id: Option<String>,
@@ -112,10 +123,14 @@ impl SipCall {
             local_party,
             state: RwLock::new(CallState::Trying),
             rtp_session: None,
+            rtp_port: std::sync::Mutex::new(None),
             started_at: Utc::now(),
             connected_at: RwLock::new(None),
             ended_at: RwLock::new(None),
+            final_status: RwLock::new(None),
             event_tx,
+            local_sdp: std::sync::Mutex::new(None),
+            dialog: std::sync::Mutex::new(None),
             dialog_id: None,
         }
     }
@@ -136,10 +151,14 @@ impl SipCall {
             local_party,
             state: RwLock::new(CallState::Ringing),
             rtp_session: None,
+            rtp_port: std::sync::Mutex::new(None),
             started_at: Utc::now(),
             connected_at: RwLock::new(None),
             ended_at: RwLock::new(None),
+            final_status: RwLock::new(None),
             event_tx,
+            local_sdp: std::sync::Mutex::new(None),
+            dialog: std::sync::Mutex::new(None),
             dialog_id: None,
         }
     }
@@ -149,6 +168,16 @@ impl SipCall {
         &self.call_id
     }
 
+    /// Final SIP response status, if the call was rejected
+    pub async fn final_status(&self) -> Option<u16> {
+        *self.final_status.read().await
+    }
+
+    /// Record the final SIP response status of a rejected call
+    pub async fn set_final_status(&self, status: u16) {
+        *self.final_status.write().await = Some(status);
+    }
+
     /// Get current state
     pub async fn state(&self) -> CallState {
         *self.state.read().await
@@ -182,11 +211,70 @@ impl SipCall {
         self.rtp_session = Some(session);
     }
 
+    /// Record the port reserved for this call's RTP session
+    pub fn set_rtp_port(&self, port: u16) {
+        *self.rtp_port.lock().unwrap() = Some(port);
+    }
+
+    /// Take the reserved RTP port so it is released exactly once
+    pub fn take_rtp_port(&self) -> Option<u16> {
+        self.rtp_port.lock().unwrap().take()
+    }
+
     /// Get the RTP session
     pub fn rtp_session(&self) -> Option<&Arc<RtpSession>> {
         self.rtp_session.as_ref()
     }
 
+    /// Latest RTCP quality metrics for this call's audio, if any have been reported
+    pub async fn quality(&self) -> Option<CallQualityMetrics> {
+        match &self.rtp_session {
+            Some(rtp) => rtp.quality().await,
+            None => None,
+        }
+    }
+
+    /// Record the SDP offered for this call
+    pub fn set_local_sdp(&self, sdp: String) {
+        *self.local_sdp.lock().unwrap() = Some(sdp);
+    }
+
+    /// Keep the confirmed dialog so the call can be re-INVITEd
+    pub fn set_dialog(&self, dialog: ClientInviteDialog) {
+        *self.dialog.lock().unwrap() = Some(dialog);
+    }
+
+    /// Re-offer the session with the audio stream set to `direction`
+    pub async fn reinvite(&self, direction: MediaDirection) -> Result<(), SipError> {
+        let dialog = self
+            .dialog
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| SipError::InvalidState("Call has no confirmed dialog".to_string()))?;
+        let offer = {
+            let mut local_sdp = self.local_sdp.lock().unwrap();
+            let current = local_sdp
+                .as_deref()
+                .ok_or_else(|| SipError::InvalidState("Call has no SDP offer".to_string()))?;
+            let offer = sdp::reoffer(current, direction);
+            *local_sdp = Some(offer.clone());
+            offer
+        };
+
+        let headers = vec![rsip::Header::ContentType("application/sdp".into())];
+        let response = dialog
+            .reinvite(Some(headers), Some(offer.into_bytes()))
+            .await
+            .map_err(|e| SipError::CallFailed(format!("re-INVITE failed: {:?}", e)))?;
+
+        match response.map(|r| r.status_code.code()) {
+            Some(status) if (200..300).contains(&status) => Ok(()),
+            Some(status) => Err(SipError::Rejected(status)),
+            None => Err(SipError::Timeout("No response to re-INVITE".to_string())),
+        }
+    }
+
     /// Check if call is active (can send/receive audio)
     pub async fn is_active(&self) -> bool {
         matches!(self.state().await, CallState::Active | CallState::Held)
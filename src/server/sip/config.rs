@@ -8,8 +8,11 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum SipTransport {
     #[default]
+    #[serde(alias = "UDP", alias = "udp")]
     Udp,
+    #[serde(alias = "TCP", alias = "tcp")]
     Tcp,
+    #[serde(alias = "TLS", alias = "tls")]
     Tls,
 }
 
@@ -71,6 +74,18 @@ impl SipCodec {
 }
 
 
+/// One trunk in SIP_TRUNKS. Unset fields come from the `SIP_*` variables.
+#[derive(Debug, Clone, Deserialize)]
+struct TrunkEntry {
+    host: String,
+    port: Option<u16>,
+    username: Option<String>,
+    password: Option<String>,
+    caller_id: Option<String>,
+    domain: Option<String>,
+    transport: Option<SipTransport>,
+}
+
 /// SIP trunk configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SipConfig {
@@ -113,6 +128,12 @@ pub struct SipConfig {
     /// Enable STUN for NAT traversal
     pub stun_server: Option<String>,
 
+    /// Inbound RTP jitter buffer depth in milliseconds
+    pub jitter_buffer_ms: u32,
+
+    /// WAV file streamed to callers on hold; silence if None
+    pub hold_music_wav: Option<String>,
+
     /// User agent string
     pub user_agent: String,
 }
@@ -133,6 +154,8 @@ impl Default for SipConfig {
             rtp_port_end: 30000,
             register_expires: 3600,
             stun_server: None,
+            jitter_buffer_ms: 60,
+            hold_music_wav: None,
             user_agent: "VoIP-CRM/1.0 (Rust)".to_string(),
         }
     }
@@ -151,6 +174,21 @@ impl SipConfig {
             .and_then(|p| p.parse().ok())
             .unwrap_or(5060);
 
+        let domain = std::env::var("SIP_DOMAIN").unwrap_or_else(|_| trunk_host.clone());
+
+        Some(Self {
+            trunk_host,
+            trunk_port,
+            username,
+            password,
+            caller_id,
+            domain,
+            ..Self::shared_from_env()
+        })
+    }
+
+    /// Settings shared by every trunk (transport, codec, RTP, STUN)
+    fn shared_from_env() -> Self {
         let transport = match std::env::var("SIP_TRANSPORT")
             .unwrap_or_default()
             .to_uppercase()
@@ -161,8 +199,6 @@ impl SipConfig {
             _ => SipTransport::Udp,
         };
 
-        let domain = std::env::var("SIP_DOMAIN").unwrap_or_else(|_| trunk_host.clone());
-
         let codec = match std::env::var("SIP_CODEC")
             .unwrap_or_default()
             .to_uppercase()
@@ -172,13 +208,10 @@ impl SipConfig {
             _ => SipCodec::Pcmu,
         };
 
-        Some(Self {
-            trunk_host,
-            trunk_port,
-            username,
-            password,
-            caller_id,
-            domain,
+        Self {
+            username: std::env::var("SIP_USERNAME").unwrap_or_default(),
+            password: std::env::var("SIP_PASSWORD").unwrap_or_default(),
+            caller_id: std::env::var("SIP_CALLER_ID").unwrap_or_default(),
             transport,
             codec,
             local_ip: std::env::var("SIP_LOCAL_IP").ok(),
@@ -195,8 +228,55 @@ impl SipConfig {
                 .and_then(|p| p.parse().ok())
                 .unwrap_or(3600),
             stun_server: std::env::var("SIP_STUN_SERVER").ok(),
-            user_agent: "VoIP-CRM/1.0 (Rust)".to_string(),
-        })
+            jitter_buffer_ms: std::env::var("SIP_JITTER_BUFFER_MS")
+                .ok()
+                .and_then(|p| p.parse().ok())
+                .unwrap_or(60),
+            hold_music_wav: std::env::var("SIP_HOLD_MUSIC_WAV").ok().filter(|path| !path.is_empty()),
+            ..Self::default()
+        }
+    }
+
+    /// Load every configured trunk
+    ///
+    /// `SIP_TRUNKS` holds a JSON array of trunks in priority order. Fields left
+    /// out of an entry fall back to the single-trunk `SIP_*` variables. Without
+    /// `SIP_TRUNKS` the single trunk from `from_env` is used.
+    pub fn trunks_from_env() -> Vec<Self> {
+        let Ok(json) = std::env::var("SIP_TRUNKS") else {
+            return Self::from_env().into_iter().collect();
+        };
+
+        match Self::parse_trunks(&json, &Self::shared_from_env()) {
+            Ok(trunks) => trunks,
+            Err(e) => {
+                tracing::error!("Invalid SIP_TRUNKS: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Build trunk configs from a SIP_TRUNKS JSON array on top of `base`
+    pub fn parse_trunks(json: &str, base: &SipConfig) -> Result<Vec<Self>, String> {
+        let entries: Vec<TrunkEntry> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        entries
+            .into_iter()
+            .map(|entry| {
+                let transport = entry.transport.unwrap_or(base.transport);
+                let config = Self {
+                    trunk_port: entry.port.unwrap_or_else(|| transport.default_port()),
+                    domain: entry.domain.unwrap_or_else(|| entry.host.clone()),
+                    trunk_host: entry.host,
+                    username: entry.username.unwrap_or_else(|| base.username.clone()),
+                    password: entry.password.unwrap_or_else(|| base.password.clone()),
+                    caller_id: entry.caller_id.unwrap_or_else(|| base.caller_id.clone()),
+                    transport,
+                    ..base.clone()
+                };
+                config.validate().map(|_| config)
+            })
+            .collect()
     }
 
     /// Validate configuration
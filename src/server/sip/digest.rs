@@ -0,0 +1,151 @@
+//! Digest authentication for requests the trunk challenges
+//!
+//! Registration is authenticated by rsipstack, but some trunks also challenge
+//! each INVITE with a 401 or 407. The answer is the same digest, sent back in
+//! an Authorization or Proxy-Authorization header.
+
+use ftth_rsipstack::dialog::authenticate::Credential;
+use ftth_rsipstack::rsip::{
+    self,
+    prelude::*,
+    services::DigestGenerator,
+    typed::{Authorization, ProxyAuthorization, WwwAuthenticate},
+};
+
+use super::SipError;
+
+/// Whether a response status is an authentication challenge
+pub fn is_challenge(status: u16) -> bool {
+    matches!(status, 401 | 407)
+}
+
+/// Header answering the challenge in `response` for a request of `method` to
+/// `uri`: Authorization for a 401, Proxy-Authorization for a 407
+pub fn challenge_response(
+    response: &rsip::Response,
+    method: &rsip::Method,
+    uri: &rsip::Uri,
+    credential: &Credential,
+) -> Result<rsip::Header, SipError> {
+    let (challenge, proxy) = response
+        .headers
+        .iter()
+        .find_map(|header| match header {
+            rsip::Header::WwwAuthenticate(h) => h.typed().ok().map(|c| (c, false)),
+            rsip::Header::ProxyAuthenticate(h) => h.typed().ok().map(|c| (c.0, true)),
+            _ => None,
+        })
+        .ok_or_else(|| SipError::CallFailed("Challenge has no authenticate header".to_string()))?;
+
+    let authorization = authorization(&challenge, method, uri, credential);
+    Ok(if proxy {
+        ProxyAuthorization(authorization).into()
+    } else {
+        authorization.into()
+    })
+}
+
+fn authorization(
+    challenge: &WwwAuthenticate,
+    method: &rsip::Method,
+    uri: &rsip::Uri,
+    credential: &Credential,
+) -> Authorization {
+    let qop = match challenge.qop {
+        Some(rsip::headers::auth::Qop::Auth) => Some(rsip::headers::auth::AuthQop::Auth {
+            cnonce: uuid::Uuid::new_v4().simple().to_string(),
+            nc: 1,
+        }),
+        _ => None,
+    };
+
+    let response = DigestGenerator {
+        username: &credential.username,
+        password: &credential.password,
+        // A challenge without `algorithm=` means MD5 (RFC 2617 section 3.2.1)
+        algorithm: challenge.algorithm.unwrap_or(rsip::headers::auth::Algorithm::Md5),
+        nonce: &challenge.nonce,
+        method,
+        qop: qop.as_ref(),
+        uri,
+        realm: &challenge.realm,
+    }
+    .compute();
+
+    Authorization {
+        scheme: challenge.scheme.clone(),
+        username: credential.username.clone(),
+        realm: challenge.realm.clone(),
+        nonce: challenge.nonce.clone(),
+        uri: uri.clone(),
+        response,
+        algorithm: challenge.algorithm,
+        opaque: challenge.opaque.clone(),
+        qop,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn credential() -> Credential {
+        Credential {
+            username: "alice".to_string(),
+            password: "secret".to_string(),
+            realm: None,
+        }
+    }
+
+    fn challenge(status: u16, header: &str) -> rsip::Response {
+        let text = format!(
+            "SIP/2.0 {} Challenge\r\n\
+             Via: SIP/2.0/UDP 127.0.0.1:5060;branch=z9hG4bK1\r\n\
+             From: <sip:alice@127.0.0.1>;tag=a\r\n\
+             To: <sip:15550100@127.0.0.1>;tag=b\r\n\
+             Call-ID: c1\r\n\
+             CSeq: 1 INVITE\r\n\
+             {}: Digest realm=\"mock\", nonce=\"abc123\"\r\n\
+             Content-Length: 0\r\n\r\n",
+            status, header
+        );
+        rsip::Response::try_from(text.as_str()).unwrap()
+    }
+
+    #[test]
+    fn test_proxy_challenge_gets_proxy_authorization() {
+        let uri = rsip::Uri::try_from("sip:15550100@127.0.0.1").unwrap();
+        let header = challenge_response(
+            &challenge(407, "Proxy-Authenticate"),
+            &rsip::Method::Invite,
+            &uri,
+            &credential(),
+        )
+        .unwrap();
+
+        let text = header.to_string();
+        assert!(text.starts_with("Proxy-Authorization: Digest "), "{}", text);
+        assert!(text.contains("username=\"alice\""));
+        assert!(text.contains("realm=\"mock\""));
+        // MD5(MD5(alice:mock:secret):abc123:MD5(INVITE:sip:15550100@127.0.0.1))
+        assert!(text.contains("response=\"37af730949c1ee134a86dc4c72cc6ebf\""), "{}", text);
+    }
+
+    #[test]
+    fn test_unauthorized_gets_authorization() {
+        let uri = rsip::Uri::try_from("sip:15550100@127.0.0.1").unwrap();
+        let header = challenge_response(
+            &challenge(401, "WWW-Authenticate"),
+            &rsip::Method::Invite,
+            &uri,
+            &credential(),
+        )
+        .unwrap();
+
+        assert!(matches!(header, rsip::Header::Authorization(_)));
+        let text = header.to_string();
+        assert!(text.starts_with("Authorization: Digest "), "{}", text);
+        assert!(text.contains("response=\"37af730949c1ee134a86dc4c72cc6ebf\""), "{}", text);
+        assert!(is_challenge(401) && is_challenge(407) && !is_challenge(403));
+    }
+}
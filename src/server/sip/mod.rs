@@ -10,11 +10,17 @@
 mod config;
 mod codec;
 mod rtp;
+mod stun;
 mod user_agent;
 mod call;
+mod trunks;
+mod audio;
+mod digest;
+mod sdp;
 
 pub use config::SipConfig;
-pub use user_agent::{SipUserAgent, AgentState};
+pub use user_agent::{SipUserAgent, AgentState, AgentEvent};
+pub use trunks::{SipTrunks, TrunkStrategy};
 
 // Public API re-exports for external use
 #[allow(unused_imports)]
@@ -23,6 +29,7 @@ pub use call::{SipCall, CallState, CallDirection};
 pub use codec::G711Codec;
 #[allow(unused_imports)]
 pub use rtp::RtpSession;
+pub use rtp::CallQualityMetrics;
 
 use thiserror::Error;
 
@@ -59,6 +66,21 @@ pub enum SipError {
     #[error("Call not found: {0}")]
     CallNotFound(String),
 
+    #[error("Call rejected with SIP {0}")]
+    Rejected(u16),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+impl SipError {
+    /// Whether another trunk might succeed where this one failed: the trunk
+    /// is unreachable, unregistered, timed out or answered with a 5xx.
+    pub fn is_trunk_failure(&self) -> bool {
+        match self {
+            SipError::Rejected(status) => (500..600).contains(status),
+            SipError::Timeout(_) | SipError::Transport(_) | SipError::NotRegistered | SipError::Io(_) => true,
+            _ => false,
+        }
+    }
+}
@@ -1,16 +1,24 @@
 //! RTP (Real-time Transport Protocol) Session Handler
 //!
 //! Handles RTP audio streaming for SIP calls.
-//! Implements RFC 3550 for RTP packet format.
+//! Implements RFC 3550 for RTP packet format, plus the RTCP sender and
+//! receiver reports used to measure call quality.
 
+use std::collections::{BTreeMap, HashSet};
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::net::UdpSocket;
 use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
 use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 
 use super::codec::G711Codec;
 use super::config::SipCodec;
+use super::stun::StunClient;
 use super::SipError;
 
 /// RTP packet header (12 bytes minimum)
@@ -152,6 +160,231 @@ impl RtpPacket {
     }
 }
 
+/// RTCP sender report packet type
+const RTCP_SR: u8 = 200;
+
+/// RTCP receiver report packet type
+const RTCP_RR: u8 = 201;
+
+/// Size of one reception report block
+const REPORT_BLOCK_LEN: usize = 24;
+
+/// Seconds between the NTP epoch (1900) and the Unix epoch
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// RTP clock rate of G.711
+pub const CLOCK_RATE: u32 = 8000;
+
+/// Interval between RTCP sender reports
+pub const RTCP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Reception report block, as carried in RTCP SR and RR packets
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportBlock {
+    /// Source the report is about
+    pub ssrc: u32,
+    /// Fraction of packets lost since the previous report, out of 256
+    pub fraction_lost: u8,
+    /// Total packets lost since the call started
+    pub cumulative_lost: i32,
+    /// Extended highest sequence number received
+    pub highest_sequence: u32,
+    /// Interarrival jitter in RTP timestamp units
+    pub jitter: u32,
+    /// Middle 32 bits of the NTP timestamp of the last SR received
+    pub last_sr: u32,
+    /// Delay since that SR, in 1/65536 seconds
+    pub delay_since_last_sr: u32,
+}
+
+impl ReportBlock {
+    fn from_bytes(data: &[u8]) -> Self {
+        let word = |i: usize| u32::from_be_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]]);
+        // 24-bit signed cumulative loss, sign-extended
+        let cumulative_lost = ((word(4) << 8) as i32) >> 8;
+
+        Self {
+            ssrc: word(0),
+            fraction_lost: data[4],
+            cumulative_lost,
+            highest_sequence: word(8),
+            jitter: word(12),
+            last_sr: word(16),
+            delay_since_last_sr: word(20),
+        }
+    }
+
+    fn write(&self, buf: &mut BytesMut) {
+        buf.put_u32(self.ssrc);
+        buf.put_u32(((self.fraction_lost as u32) << 24) | (self.cumulative_lost as u32 & 0x00FF_FFFF));
+        buf.put_u32(self.highest_sequence);
+        buf.put_u32(self.jitter);
+        buf.put_u32(self.last_sr);
+        buf.put_u32(self.delay_since_last_sr);
+    }
+}
+
+/// RTCP packets we send and understand
+#[derive(Debug, Clone, PartialEq)]
+pub enum RtcpPacket {
+    SenderReport {
+        ssrc: u32,
+        ntp_timestamp: u64,
+        rtp_timestamp: u32,
+        packet_count: u32,
+        octet_count: u32,
+        reports: Vec<ReportBlock>,
+    },
+    ReceiverReport {
+        ssrc: u32,
+        reports: Vec<ReportBlock>,
+    },
+}
+
+impl RtcpPacket {
+    /// Reception report blocks carried by this packet
+    pub fn reports(&self) -> &[ReportBlock] {
+        match self {
+            RtcpPacket::SenderReport { reports, .. } | RtcpPacket::ReceiverReport { reports, .. } => reports,
+        }
+    }
+
+    /// Serialize packet to bytes
+    pub fn to_bytes(&self) -> Bytes {
+        let (packet_type, reports) = match self {
+            RtcpPacket::SenderReport { reports, .. } => (RTCP_SR, reports),
+            RtcpPacket::ReceiverReport { reports, .. } => (RTCP_RR, reports),
+        };
+        let mut buf = BytesMut::with_capacity(28 + reports.len() * REPORT_BLOCK_LEN);
+
+        // V(2) P(1) RC(5), then PT; length is filled in once the body is written
+        buf.put_u8((2 << 6) | (reports.len() as u8 & 0x1F));
+        buf.put_u8(packet_type);
+        buf.put_u16(0);
+
+        match self {
+            RtcpPacket::SenderReport {
+                ssrc,
+                ntp_timestamp,
+                rtp_timestamp,
+                packet_count,
+                octet_count,
+                ..
+            } => {
+                buf.put_u32(*ssrc);
+                buf.put_u64(*ntp_timestamp);
+                buf.put_u32(*rtp_timestamp);
+                buf.put_u32(*packet_count);
+                buf.put_u32(*octet_count);
+            }
+            RtcpPacket::ReceiverReport { ssrc, .. } => buf.put_u32(*ssrc),
+        }
+        for report in reports {
+            report.write(&mut buf);
+        }
+
+        // Length in 32-bit words minus one
+        let words = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&words.to_be_bytes());
+        buf.freeze()
+    }
+
+    /// Parse a compound RTCP packet, skipping packet types other than SR and RR
+    pub fn parse_compound(mut data: &[u8]) -> Result<Vec<Self>, SipError> {
+        let mut packets = Vec::new();
+
+        while !data.is_empty() {
+            if data.len() < 4 {
+                return Err(SipError::Rtp("RTCP packet too short".to_string()));
+            }
+            let version = data[0] >> 6;
+            if version != 2 {
+                return Err(SipError::Rtp(format!("Invalid RTCP version: {}", version)));
+            }
+
+            let report_count = (data[0] & 0x1F) as usize;
+            let packet_type = data[1];
+            let len = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+            if data.len() < len {
+                return Err(SipError::Rtp("Truncated RTCP packet".to_string()));
+            }
+            let packet = &data[..len];
+            data = &data[len..];
+
+            let word = |i: usize| u32::from_be_bytes([packet[i], packet[i + 1], packet[i + 2], packet[i + 3]]);
+            let reports_at = |offset: usize| -> Result<Vec<ReportBlock>, SipError> {
+                if packet.len() < offset + report_count * REPORT_BLOCK_LEN {
+                    return Err(SipError::Rtp("RTCP report blocks truncated".to_string()));
+                }
+                Ok((0..report_count)
+                    .map(|i| ReportBlock::from_bytes(&packet[offset + i * REPORT_BLOCK_LEN..]))
+                    .collect())
+            };
+
+            match packet_type {
+                RTCP_SR if packet.len() >= 28 => packets.push(RtcpPacket::SenderReport {
+                    ssrc: word(4),
+                    ntp_timestamp: ((word(8) as u64) << 32) | word(12) as u64,
+                    rtp_timestamp: word(16),
+                    packet_count: word(20),
+                    octet_count: word(24),
+                    reports: reports_at(28)?,
+                }),
+                RTCP_RR if packet.len() >= 8 => packets.push(RtcpPacket::ReceiverReport {
+                    ssrc: word(4),
+                    reports: reports_at(8)?,
+                }),
+                RTCP_SR | RTCP_RR => return Err(SipError::Rtp("RTCP report too short".to_string())),
+                _ => {}
+            }
+        }
+
+        Ok(packets)
+    }
+}
+
+/// Current wall-clock time as a 64-bit NTP timestamp
+pub fn ntp_now() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() + NTP_UNIX_OFFSET;
+    let fraction = ((since_epoch.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    (seconds << 32) | fraction
+}
+
+/// Quality of our outbound audio as reported back by the remote party
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct CallQualityMetrics {
+    /// Packets lost in the last reporting interval, as a percentage
+    pub packet_loss_percent: f64,
+    /// Packets lost since the call started
+    pub cumulative_lost: i32,
+    /// Interarrival jitter in milliseconds
+    pub jitter_ms: f64,
+    /// Round-trip time, once the remote party has received one of our SRs
+    pub round_trip_ms: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CallQualityMetrics {
+    /// Compute metrics from a report block that arrived at `arrival` (NTP time)
+    pub fn from_report(block: &ReportBlock, clock_rate: u32, arrival: u64) -> Self {
+        // RTT = arrival - LSR - DLSR, all in the 1/65536s units of the middle NTP bits
+        let round_trip_ms = (block.last_sr != 0).then(|| {
+            let arrival = (arrival >> 16) as u32;
+            let rtt = arrival.wrapping_sub(block.last_sr).wrapping_sub(block.delay_since_last_sr);
+            rtt as f64 * 1000.0 / 65536.0
+        });
+
+        Self {
+            packet_loss_percent: block.fraction_lost as f64 * 100.0 / 256.0,
+            cumulative_lost: block.cumulative_lost,
+            jitter_ms: block.jitter as f64 * 1000.0 / clock_rate as f64,
+            round_trip_ms,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
 /// Audio frame received from remote party
 #[derive(Debug, Clone)]
 pub struct AudioFrame {
@@ -163,18 +396,195 @@ pub struct AudioFrame {
     pub sequence: u16,
 }
 
+/// Duration of one RTP audio frame
+pub const FRAME_DURATION: Duration = Duration::from_millis(20);
+
+/// Default jitter buffer depth
+pub const DEFAULT_JITTER_DEPTH: Duration = Duration::from_millis(60);
+
+/// Samples in one 20ms frame at 8kHz
+const FRAME_SAMPLES: usize = 160;
+
+/// Upper bound for adaptive growth, as a multiple of the configured depth
+const MAX_DEPTH_FACTOR: usize = 4;
+
+/// On-time frames released before the target depth shrinks back by one frame
+const SHRINK_AFTER_FRAMES: u32 = 500;
+
+/// Adaptive jitter buffer for inbound audio
+///
+/// Frames are held by RTP sequence number until the buffer reaches its target
+/// depth, then released one per 20ms tick in order. A single missing frame is
+/// concealed with silence; longer gaps skip ahead to the next frame we have.
+/// The target depth grows when packets arrive too late to be played and
+/// slowly shrinks back to the configured depth once the network settles.
+pub struct JitterBuffer {
+    /// Buffered frames keyed by extended (wrap-free) sequence number
+    frames: BTreeMap<u64, AudioFrame>,
+    /// Highest extended sequence seen, used to unwrap 16-bit sequences
+    highest: Option<u64>,
+    /// Next extended sequence to release
+    next: Option<u64>,
+    /// Configured depth in frames
+    min_depth: usize,
+    /// Current target depth in frames
+    target_depth: usize,
+    /// Whether the buffer has filled to target depth and is releasing frames
+    playing: bool,
+    /// Frames released on time since the last adjustment
+    on_time: u32,
+}
+
+impl JitterBuffer {
+    /// Create a buffer holding `depth` of audio before playout starts
+    pub fn new(depth: Duration) -> Self {
+        let depth_frames = (depth.as_millis() / FRAME_DURATION.as_millis()).max(1) as usize;
+        Self {
+            frames: BTreeMap::new(),
+            highest: None,
+            next: None,
+            min_depth: depth_frames,
+            target_depth: depth_frames,
+            playing: false,
+            on_time: 0,
+        }
+    }
+
+    /// Current target depth in frames
+    pub fn target_depth(&self) -> usize {
+        self.target_depth
+    }
+
+    /// Number of frames currently buffered
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Whether the buffer holds no frames
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Map a 16-bit sequence number onto a monotonically increasing counter
+    fn extend_sequence(&mut self, sequence: u16) -> u64 {
+        let Some(highest) = self.highest else {
+            // Start well above zero so early reordered packets don't underflow
+            let extended = (1u64 << 16) + sequence as u64;
+            self.highest = Some(extended);
+            return extended;
+        };
+
+        let delta = sequence.wrapping_sub(highest as u16) as i16 as i64;
+        let extended = (highest as i64 + delta).max(0) as u64;
+        if extended > highest {
+            self.highest = Some(extended);
+        }
+        extended
+    }
+
+    /// Add a received frame. Returns false if it was dropped as late or duplicate.
+    pub fn push(&mut self, frame: AudioFrame) -> bool {
+        let extended = self.extend_sequence(frame.sequence);
+
+        if self.next.is_some_and(|next| extended < next) {
+            // Arrived after its slot was played - buffer more to absorb this jitter
+            self.target_depth = (self.target_depth + 1).min(self.min_depth * MAX_DEPTH_FACTOR);
+            self.on_time = 0;
+            return false;
+        }
+        if self.frames.contains_key(&extended) {
+            return false;
+        }
+
+        self.frames.insert(extended, frame);
+
+        // Bound memory if the consumer stalls
+        while self.frames.len() > self.min_depth * MAX_DEPTH_FACTOR * 2 {
+            self.frames.pop_first();
+        }
+
+        true
+    }
+
+    /// Release the next frame for this 20ms tick
+    ///
+    /// Returns None while the buffer is filling or after it runs dry.
+    pub fn pop(&mut self) -> Option<AudioFrame> {
+        if !self.playing {
+            if self.frames.len() < self.target_depth {
+                return None;
+            }
+            self.playing = true;
+            self.next = self.frames.keys().next().copied();
+        }
+
+        let Some(&first) = self.frames.keys().next() else {
+            // Underrun: refill to target depth before playing again
+            self.playing = false;
+            return None;
+        };
+        let next = self.next.unwrap_or(first);
+
+        let frame = if let Some(frame) = self.frames.remove(&next) {
+            self.next = Some(next + 1);
+            self.note_on_time();
+            frame
+        } else if first == next + 1 {
+            // Exactly one packet lost - conceal with silence
+            self.next = Some(next + 1);
+            let following = &self.frames[&first];
+            let samples = following.samples.len().max(1);
+            AudioFrame {
+                samples: vec![0i16; samples],
+                timestamp: following.timestamp.wrapping_sub(samples as u32),
+                sequence: following.sequence.wrapping_sub(1),
+            }
+        } else {
+            // Longer gap - resync on the next frame we have
+            let frame = self.frames.remove(&first).expect("first key exists");
+            self.next = Some(first + 1);
+            frame
+        };
+
+        Some(frame)
+    }
+
+    fn note_on_time(&mut self) {
+        self.on_time += 1;
+        if self.on_time >= SHRINK_AFTER_FRAMES && self.target_depth > self.min_depth {
+            self.target_depth -= 1;
+            self.on_time = 0;
+        }
+    }
+}
+
+impl Default for JitterBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_JITTER_DEPTH)
+    }
+}
+
 /// RTP Session for a SIP call
 pub struct RtpSession {
     /// Local UDP socket for RTP
     socket: Arc<UdpSocket>,
+    /// RTCP socket on the port after RTP, if it could be bound
+    rtcp_socket: Option<Arc<UdpSocket>>,
     /// Remote RTP endpoint
-    remote_addr: RwLock<Option<SocketAddr>>,
+    remote_addr: Arc<RwLock<Option<SocketAddr>>>,
     /// SSRC for outgoing packets
     ssrc: u32,
     /// Current sequence number
     sequence: RwLock<u16>,
     /// Current timestamp
-    timestamp: RwLock<u32>,
+    timestamp: Arc<RwLock<u32>>,
+    /// Packets and payload octets sent, for sender reports
+    packets_sent: Arc<AtomicU32>,
+    octets_sent: Arc<AtomicU32>,
+    /// Latest quality reported by the remote party
+    quality: Arc<RwLock<Option<CallQualityMetrics>>>,
+    /// Stops the RTCP tasks
+    rtcp_cancel: CancellationToken,
     /// Audio codec
     codec: G711Codec,
     /// Payload type
@@ -183,6 +593,8 @@ pub struct RtpSession {
     audio_tx: mpsc::Sender<AudioFrame>,
     /// Receiver for audio frames
     audio_rx: RwLock<Option<mpsc::Receiver<AudioFrame>>>,
+    /// Inbound jitter buffer depth
+    jitter_depth: Duration,
     /// Running flag
     running: RwLock<bool>,
 }
@@ -199,20 +611,42 @@ impl RtpSession {
 
         let (audio_tx, audio_rx) = mpsc::channel(100);
 
+        // RTCP uses the next port up; calls still work without it, just unmeasured
+        let rtp_port = socket.local_addr().map(|a| a.port()).unwrap_or(0);
+        let rtcp_socket = match UdpSocket::bind(format!("0.0.0.0:{}", rtp_port.wrapping_add(1))).await {
+            Ok(socket) => Some(Arc::new(socket)),
+            Err(e) => {
+                tracing::debug!("RTCP disabled, could not bind port {}: {}", rtp_port.wrapping_add(1), e);
+                None
+            }
+        };
+
         Ok(Self {
             socket: Arc::new(socket),
-            remote_addr: RwLock::new(None),
+            rtcp_socket,
+            remote_addr: Arc::new(RwLock::new(None)),
             ssrc,
             sequence: RwLock::new(rand::random::<u16>()),
-            timestamp: RwLock::new(rand::random::<u32>()),
+            timestamp: Arc::new(RwLock::new(rand::random::<u32>())),
+            packets_sent: Arc::new(AtomicU32::new(0)),
+            octets_sent: Arc::new(AtomicU32::new(0)),
+            quality: Arc::new(RwLock::new(None)),
+            rtcp_cancel: CancellationToken::new(),
             codec,
             payload_type,
             audio_tx,
             audio_rx: RwLock::new(Some(audio_rx)),
+            jitter_depth: DEFAULT_JITTER_DEPTH,
             running: RwLock::new(false),
         })
     }
 
+    /// Set the inbound jitter buffer depth (default 60ms)
+    pub fn with_jitter_depth(mut self, depth: Duration) -> Self {
+        self.jitter_depth = depth;
+        self
+    }
+
     /// Try to bind to a port, trying multiple ports if necessary
     async fn try_bind_port(start_port: u16, max_attempts: u16) -> Result<UdpSocket, SipError> {
         let mut port = start_port;
@@ -247,11 +681,23 @@ impl RtpSession {
         self.socket.local_addr().map(|a| a.port()).unwrap_or(0)
     }
 
+    /// Discover the public address of this session's socket via STUN
+    ///
+    /// Must be called before `start`, which takes over the socket's receive side.
+    pub async fn discover_public_addr(&self, stun: &StunClient) -> Result<SocketAddr, SipError> {
+        stun.map_socket(&self.socket).await
+    }
+
     /// Set the remote RTP endpoint
     pub async fn set_remote(&self, addr: SocketAddr) {
         *self.remote_addr.write().await = Some(addr);
     }
 
+    /// Latest call quality reported by the remote party over RTCP
+    pub async fn quality(&self) -> Option<CallQualityMetrics> {
+        self.quality.read().await.clone()
+    }
+
     /// Take the audio receiver (can only be called once)
     pub async fn take_audio_receiver(&self) -> Option<mpsc::Receiver<AudioFrame>> {
         self.audio_rx.write().await.take()
@@ -265,9 +711,11 @@ impl RtpSession {
         let audio_tx = self.audio_tx.clone();
         let codec_type = self.payload_type;
         let _running = Arc::new(*self.running.read().await);
+        let jitter = Arc::new(Mutex::new(JitterBuffer::new(self.jitter_depth)));
 
-        // Spawn receiver task
-        tokio::spawn(async move {
+        // Spawn receiver task - decodes packets into the jitter buffer
+        let receive_jitter = jitter.clone();
+        let receiver = tokio::spawn(async move {
             let codec = if codec_type == 0 {
                 G711Codec::pcmu()
             } else {
@@ -289,9 +737,7 @@ impl RtpSession {
                                 sequence: packet.header.sequence,
                             };
 
-                            if audio_tx.send(frame).await.is_err() {
-                                break;
-                            }
+                            receive_jitter.lock().unwrap().push(frame);
                         }
                     }
                     Err(e) => {
@@ -302,12 +748,111 @@ impl RtpSession {
             }
         });
 
+        // Spawn playout task - releases one frame per 20ms tick
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(FRAME_DURATION);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+            loop {
+                ticker.tick().await;
+                if receiver.is_finished() {
+                    break;
+                }
+
+                let frame = jitter.lock().unwrap().pop();
+                if let Some(frame) = frame {
+                    if audio_tx.send(frame).await.is_err() {
+                        receiver.abort();
+                        break;
+                    }
+                }
+            }
+        });
+
+        self.start_rtcp();
+
         Ok(())
     }
 
+    /// Send periodic sender reports and record the receiver reports that come back
+    fn start_rtcp(&self) {
+        let Some(socket) = self.rtcp_socket.clone() else {
+            return;
+        };
+        let ssrc = self.ssrc;
+
+        // Receiver task - turns report blocks about our stream into metrics
+        let receive_socket = socket.clone();
+        let receive_cancel = self.rtcp_cancel.clone();
+        let quality = self.quality.clone();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+
+            loop {
+                let len = tokio::select! {
+                    _ = receive_cancel.cancelled() => break,
+                    result = receive_socket.recv_from(&mut buf) => match result {
+                        Ok((len, _addr)) => len,
+                        Err(e) => {
+                            tracing::error!("RTCP receive error: {}", e);
+                            break;
+                        }
+                    },
+                };
+
+                let arrival = ntp_now();
+                let Ok(packets) = RtcpPacket::parse_compound(&buf[..len]) else {
+                    continue;
+                };
+                if let Some(block) = packets.iter().flat_map(|p| p.reports()).find(|b| b.ssrc == ssrc) {
+                    *quality.write().await = Some(CallQualityMetrics::from_report(block, CLOCK_RATE, arrival));
+                }
+            }
+        });
+
+        // Sender task - one SR per interval once the remote endpoint is known
+        let cancel = self.rtcp_cancel.clone();
+        let remote_addr = self.remote_addr.clone();
+        let timestamp = self.timestamp.clone();
+        let packets_sent = self.packets_sent.clone();
+        let octets_sent = self.octets_sent.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(RTCP_INTERVAL);
+
+            loop {
+                tokio::select! {
+                    _ = cancel.cancelled() => break,
+                    _ = ticker.tick() => {}
+                }
+
+                let Some(remote) = *remote_addr.read().await else {
+                    continue;
+                };
+                let report = RtcpPacket::SenderReport {
+                    ssrc,
+                    ntp_timestamp: ntp_now(),
+                    rtp_timestamp: *timestamp.read().await,
+                    packet_count: packets_sent.load(Ordering::Relaxed),
+                    octet_count: octets_sent.load(Ordering::Relaxed),
+                    reports: Vec::new(),
+                };
+                let rtcp_remote = SocketAddr::new(remote.ip(), remote.port().wrapping_add(1));
+                if let Err(e) = socket.send_to(&report.to_bytes(), rtcp_remote).await {
+                    tracing::warn!("Failed to send RTCP sender report: {}", e);
+                }
+            }
+        });
+    }
+
     /// Stop the RTP session
     pub async fn stop(&self) {
         *self.running.write().await = false;
+        self.rtcp_cancel.cancel();
+    }
+
+    fn record_sent(&self, payload_len: usize) {
+        self.packets_sent.fetch_add(1, Ordering::Relaxed);
+        self.octets_sent.fetch_add(payload_len as u32, Ordering::Relaxed);
     }
 
     /// Send audio samples
@@ -334,11 +879,13 @@ impl RtpSession {
         };
 
         // Build packet
+        let payload_len = payload.len();
         let header = RtpHeader::new(self.payload_type, sequence, timestamp, self.ssrc);
         let packet = RtpPacket::new(header, payload);
 
         // Send
         self.socket.send_to(&packet.to_bytes(), remote_addr).await?;
+        self.record_sent(payload_len);
 
         Ok(())
     }
@@ -366,49 +913,276 @@ impl RtpSession {
         let packet = RtpPacket::new(header, Bytes::copy_from_slice(encoded));
 
         self.socket.send_to(&packet.to_bytes(), remote_addr).await?;
+        self.record_sent(encoded.len());
 
         Ok(())
     }
 
     /// Generate silence (160 samples = 20ms at 8kHz)
     pub fn silence_frame() -> Vec<i16> {
-        vec![0i16; 160]
+        vec![0i16; FRAME_SAMPLES]
     }
 }
 
 /// RTP port allocator
+///
+/// Hands out even ports for RTP whose odd neighbour (used for RTCP) is also in
+/// the range. A port stays reserved until it is released when its call ends.
 pub struct RtpPortAllocator {
     start: u16,
     end: u16,
-    current: RwLock<u16>,
+    state: Mutex<PortState>,
+}
+
+struct PortState {
+    /// Where the search for a free port starts, so ports are reused round-robin
+    next: u16,
+    in_use: HashSet<u16>,
 }
 
 impl RtpPortAllocator {
     pub fn new(start: u16, end: u16) -> Self {
+        let first = start.saturating_add(start % 2);
         Self {
             start,
             end,
-            current: RwLock::new(start),
+            state: Mutex::new(PortState {
+                next: first,
+                in_use: HashSet::new(),
+            }),
         }
     }
 
-    /// Allocate next available port (even number for RTP, odd for RTCP)
-    pub async fn allocate(&self) -> u16 {
-        let mut current = self.current.write().await;
-        let port = *current;
+    /// First usable (even) port
+    fn first_port(&self) -> u16 {
+        self.start.saturating_add(self.start % 2)
+    }
 
-        // Increment by 2 (RTP uses even ports, RTCP uses odd)
-        *current = if *current + 2 >= self.end {
-            self.start
-        } else {
-            *current + 2
+    /// Number of RTP/RTCP port pairs in the range
+    pub fn capacity(&self) -> usize {
+        let first = self.first_port() as u32;
+        let end = self.end as u32;
+        if first + 1 > end {
+            return 0;
+        }
+        ((end - 1 - first) / 2 + 1) as usize
+    }
+
+    /// Reserve the next free port (even number for RTP, odd for RTCP)
+    pub fn allocate(&self) -> Result<u16, SipError> {
+        let mut state = self.state.lock().unwrap();
+        let first = self.first_port();
+
+        for _ in 0..self.capacity() {
+            let port = state.next;
+            state.next = if port as u32 + 3 > self.end as u32 { first } else { port + 2 };
+            if state.in_use.insert(port) {
+                return Ok(port);
+            }
+        }
+
+        Err(SipError::Rtp(format!("No free RTP ports in range {}-{}", self.start, self.end)))
+    }
+
+    /// Return a port to the pool
+    pub fn release(&self, port: u16) {
+        self.state.lock().unwrap().in_use.remove(&port);
+    }
+
+    /// Number of ports currently reserved
+    pub fn in_use(&self) -> usize {
+        self.state.lock().unwrap().in_use.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(sequence: u16) -> AudioFrame {
+        AudioFrame {
+            samples: vec![sequence as i16; FRAME_SAMPLES],
+            timestamp: sequence as u32 * FRAME_SAMPLES as u32,
+            sequence,
+        }
+    }
+
+    fn drain(buffer: &mut JitterBuffer) -> Vec<AudioFrame> {
+        std::iter::from_fn(|| buffer.pop()).collect()
+    }
+
+    #[test]
+    fn test_waits_for_target_depth() {
+        let mut buffer = JitterBuffer::default();
+        assert_eq!(buffer.target_depth(), 3);
+
+        buffer.push(frame(10));
+        buffer.push(frame(11));
+        assert!(buffer.pop().is_none());
+
+        buffer.push(frame(12));
+        assert_eq!(buffer.pop().unwrap().sequence, 10);
+    }
+
+    #[test]
+    fn test_reorders_out_of_order_packets() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        for sequence in [101, 100, 103, 102, 104] {
+            assert!(buffer.push(frame(sequence)));
+        }
+
+        let sequences: Vec<u16> = drain(&mut buffer).iter().map(|f| f.sequence).collect();
+        assert_eq!(sequences, vec![100, 101, 102, 103, 104]);
+    }
+
+    #[test]
+    fn test_conceals_single_lost_packet_with_silence() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        for sequence in [200, 202, 201, 204, 205] {
+            buffer.push(frame(sequence));
+        }
+
+        let output = drain(&mut buffer);
+        let sequences: Vec<u16> = output.iter().map(|f| f.sequence).collect();
+        assert_eq!(sequences, vec![200, 201, 202, 203, 204, 205]);
+
+        let concealed = &output[3];
+        assert_eq!(concealed.samples, vec![0i16; FRAME_SAMPLES]);
+        assert_eq!(concealed.timestamp, 203 * FRAME_SAMPLES as u32);
+    }
+
+    #[test]
+    fn test_reorders_across_sequence_wrap() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        for sequence in [65534, 0, 65535, 1] {
+            buffer.push(frame(sequence));
+        }
+
+        let sequences: Vec<u16> = drain(&mut buffer).iter().map(|f| f.sequence).collect();
+        assert_eq!(sequences, vec![65534, 65535, 0, 1]);
+    }
+
+    /// RR from SSRC 0x11223344 about our stream 0xAABBCCDD: 64/256 lost in the
+    /// interval, 1000 lost overall, jitter 160 (20ms), LSR 0x00010000, DLSR 0x8000 (0.5s)
+    const CANNED_RR: [u8; 32] = [
+        0x81, 201, 0x00, 0x07, // V=2, RC=1, PT=RR, length 7
+        0x11, 0x22, 0x33, 0x44, // reporter SSRC
+        0xAA, 0xBB, 0xCC, 0xDD, // reportee SSRC
+        0x40, 0x00, 0x03, 0xE8, // fraction lost, cumulative lost
+        0x00, 0x01, 0x13, 0x88, // extended highest sequence
+        0x00, 0x00, 0x00, 0xA0, // jitter
+        0x00, 0x01, 0x00, 0x00, // LSR
+        0x00, 0x00, 0x80, 0x00, // DLSR
+    ];
+
+    #[test]
+    fn test_parses_canned_receiver_report() {
+        let packets = RtcpPacket::parse_compound(&CANNED_RR).unwrap();
+        assert_eq!(
+            packets,
+            vec![RtcpPacket::ReceiverReport {
+                ssrc: 0x1122_3344,
+                reports: vec![ReportBlock {
+                    ssrc: 0xAABB_CCDD,
+                    fraction_lost: 64,
+                    cumulative_lost: 1000,
+                    highest_sequence: 70_536,
+                    jitter: 160,
+                    last_sr: 0x0001_0000,
+                    delay_since_last_sr: 0x8000,
+                }],
+            }]
+        );
+    }
+
+    #[test]
+    fn test_receiver_report_becomes_quality_metrics() {
+        let packets = RtcpPacket::parse_compound(&CANNED_RR).unwrap();
+        let block = &packets[0].reports()[0];
+
+        // Arrived 0.75s after the SR it echoes (middle 32 bits 0x0001_C000)
+        let arrival = 0x0001_C000u64 << 16;
+        let metrics = CallQualityMetrics::from_report(block, CLOCK_RATE, arrival);
+
+        assert_eq!(metrics.packet_loss_percent, 25.0);
+        assert_eq!(metrics.cumulative_lost, 1000);
+        assert_eq!(metrics.jitter_ms, 20.0);
+        assert_eq!(metrics.round_trip_ms, Some(250.0));
+    }
+
+    #[test]
+    fn test_report_without_sender_report_has_no_round_trip() {
+        let block = ReportBlock {
+            ssrc: 1,
+            fraction_lost: 0,
+            cumulative_lost: -2,
+            highest_sequence: 10,
+            jitter: 0,
+            last_sr: 0,
+            delay_since_last_sr: 0,
         };
+        let metrics = CallQualityMetrics::from_report(&block, CLOCK_RATE, ntp_now());
+        assert_eq!(metrics.round_trip_ms, None);
+        assert_eq!(metrics.cumulative_lost, -2);
+    }
 
-        // Ensure even port
-        if port % 2 != 0 {
-            port + 1
-        } else {
-            port
+    #[test]
+    fn test_sender_report_round_trips() {
+        let report = RtcpPacket::SenderReport {
+            ssrc: 42,
+            ntp_timestamp: ntp_now(),
+            rtp_timestamp: 8000,
+            packet_count: 50,
+            octet_count: 8000,
+            reports: Vec::new(),
+        };
+        let bytes = report.to_bytes();
+
+        assert_eq!(bytes.len(), 28);
+        assert_eq!(RtcpPacket::parse_compound(&bytes).unwrap(), vec![report]);
+    }
+
+    #[test]
+    fn test_late_packet_is_dropped_and_grows_depth() {
+        let mut buffer = JitterBuffer::new(Duration::from_millis(60));
+        for sequence in [1, 2, 3] {
+            buffer.push(frame(sequence));
+        }
+        assert_eq!(buffer.pop().unwrap().sequence, 1);
+
+        assert!(!buffer.push(frame(1)));
+        assert_eq!(buffer.target_depth(), 4);
+    }
+
+    #[test]
+    fn test_port_allocator_exhausts_and_reuses_released_ports() {
+        let ports = RtpPortAllocator::new(10001, 10008);
+        assert_eq!(ports.capacity(), 3);
+
+        let mut allocated: Vec<u16> = (0..3).map(|_| ports.allocate().unwrap()).collect();
+        allocated.sort();
+        assert_eq!(allocated, vec![10002, 10004, 10006]);
+        assert!(ports.allocate().is_err());
+        assert_eq!(ports.in_use(), 3);
+
+        ports.release(10004);
+        assert_eq!(ports.allocate().unwrap(), 10004);
+        assert!(ports.allocate().is_err());
+    }
+
+    #[test]
+    fn test_port_allocator_never_hands_out_duplicates() {
+        let ports = RtpPortAllocator::new(20000, 20010);
+        let first = ports.allocate().unwrap();
+        let second = ports.allocate().unwrap();
+        ports.release(first);
+
+        // Round-robin continues past the released port before reusing it
+        let mut seen = vec![second];
+        while let Ok(port) = ports.allocate() {
+            assert!(!seen.contains(&port), "port {} handed out twice", port);
+            seen.push(port);
         }
+        assert_eq!(seen.len(), ports.capacity());
     }
 }
@@ -0,0 +1,126 @@
+//! SDP re-offers for hold and resume
+//!
+//! A call is held by sending a re-INVITE whose SDP marks the audio stream
+//! `sendonly` (we keep sending, e.g. hold music) or `inactive` (silence both
+//! ways), and resumed with `sendrecv`. Each new offer for the session must
+//! carry a higher version in its `o=` line (RFC 3264 section 8).
+
+/// Direction attribute of the audio stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaDirection {
+    SendRecv,
+    SendOnly,
+    RecvOnly,
+    Inactive,
+}
+
+impl MediaDirection {
+    pub fn attribute(&self) -> &'static str {
+        match self {
+            MediaDirection::SendRecv => "a=sendrecv",
+            MediaDirection::SendOnly => "a=sendonly",
+            MediaDirection::RecvOnly => "a=recvonly",
+            MediaDirection::Inactive => "a=inactive",
+        }
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        match line {
+            "a=sendrecv" => Some(MediaDirection::SendRecv),
+            "a=sendonly" => Some(MediaDirection::SendOnly),
+            "a=recvonly" => Some(MediaDirection::RecvOnly),
+            "a=inactive" => Some(MediaDirection::Inactive),
+            _ => None,
+        }
+    }
+}
+
+/// Direction of the first stream that sets one; sendrecv if none does
+pub fn direction(sdp: &str) -> MediaDirection {
+    sdp.lines()
+        .find_map(|line| MediaDirection::from_line(line.trim()))
+        .unwrap_or(MediaDirection::SendRecv)
+}
+
+/// The next offer for a session: `sdp` with its version bumped and its
+/// direction set to `direction`
+pub fn reoffer(sdp: &str, direction: MediaDirection) -> String {
+    let mut direction_set = false;
+    let mut out = String::with_capacity(sdp.len() + 16);
+
+    for line in sdp.lines() {
+        if line.starts_with("o=") {
+            out.push_str(&bump_version(line));
+        } else if MediaDirection::from_line(line).is_some() {
+            out.push_str(direction.attribute());
+            direction_set = true;
+        } else {
+            out.push_str(line);
+        }
+        out.push_str("\r\n");
+    }
+
+    if !direction_set {
+        out.push_str(direction.attribute());
+        out.push_str("\r\n");
+    }
+    out
+}
+
+/// `o=<user> <session id> <version> ...` with the version incremented
+fn bump_version(origin: &str) -> String {
+    let mut fields: Vec<String> = origin.split(' ').map(str::to_string).collect();
+    if let Some(version) = fields.get_mut(2) {
+        if let Ok(current) = version.parse::<u64>() {
+            *version = (current + 1).to_string();
+        }
+    }
+    fields.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFER: &str = "v=0\r\n\
+                         o=- 12345 1 IN IP4 10.0.0.5\r\n\
+                         s=VoIP CRM Call\r\n\
+                         c=IN IP4 10.0.0.5\r\n\
+                         t=0 0\r\n\
+                         m=audio 40000 RTP/AVP 0\r\n\
+                         a=rtpmap:0 PCMU/8000\r\n\
+                         a=ptime:20\r\n\
+                         a=sendrecv\r\n";
+
+    #[test]
+    fn test_hold_and_unhold_flip_direction() {
+        let held = reoffer(OFFER, MediaDirection::SendOnly);
+        assert_eq!(direction(&held), MediaDirection::SendOnly);
+        assert!(held.contains("a=sendonly\r\n"));
+        assert!(!held.contains("a=sendrecv"));
+        assert!(held.contains("o=- 12345 2 IN IP4 10.0.0.5\r\n"));
+
+        let resumed = reoffer(&held, MediaDirection::SendRecv);
+        assert_eq!(direction(&resumed), MediaDirection::SendRecv);
+        assert!(!resumed.contains("a=sendonly"));
+        assert!(resumed.contains("o=- 12345 3 IN IP4 10.0.0.5\r\n"));
+
+        // Everything else is left alone
+        assert!(resumed.contains("m=audio 40000 RTP/AVP 0\r\n"));
+        assert_eq!(resumed.lines().count(), OFFER.lines().count());
+    }
+
+    #[test]
+    fn test_hold_without_music_is_inactive() {
+        let held = reoffer(OFFER, MediaDirection::Inactive);
+        assert_eq!(direction(&held), MediaDirection::Inactive);
+        assert_eq!(held.matches("a=inactive").count(), 1);
+    }
+
+    #[test]
+    fn test_direction_added_when_missing() {
+        let bare = OFFER.replace("a=sendrecv\r\n", "");
+        assert_eq!(direction(&bare), MediaDirection::SendRecv);
+        assert!(reoffer(&bare, MediaDirection::SendOnly).ends_with("a=sendonly\r\n"));
+    }
+}
@@ -0,0 +1,341 @@
+//! STUN client for NAT traversal
+//!
+//! Sends RFC 5389 Binding Requests to discover the public address a NAT maps
+//! our RTP socket to, so the SDP we advertise is reachable from the trunk.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::RwLock;
+
+use super::SipError;
+
+/// STUN magic cookie (RFC 5389 section 6)
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+/// Binding Request message type
+const BINDING_REQUEST: u16 = 0x0001;
+/// Binding Success Response message type
+const BINDING_SUCCESS: u16 = 0x0101;
+/// MAPPED-ADDRESS attribute (RFC 3489 servers)
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+/// XOR-MAPPED-ADDRESS attribute
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+/// Default STUN port when the server has none
+const DEFAULT_STUN_PORT: u16 = 3478;
+/// STUN header length
+const HEADER_LEN: usize = 20;
+
+/// Build a Binding Request with the given transaction ID
+pub fn build_binding_request(transaction_id: &[u8; 12]) -> [u8; HEADER_LEN] {
+    let mut packet = [0u8; HEADER_LEN];
+    packet[0..2].copy_from_slice(&BINDING_REQUEST.to_be_bytes());
+    // Message length is zero - no attributes
+    packet[4..8].copy_from_slice(&MAGIC_COOKIE.to_be_bytes());
+    packet[8..20].copy_from_slice(transaction_id);
+    packet
+}
+
+/// Parse a Binding Success Response and return the mapped address
+///
+/// XOR-MAPPED-ADDRESS is preferred; MAPPED-ADDRESS is accepted from older servers.
+pub fn parse_binding_response(data: &[u8], transaction_id: &[u8; 12]) -> Result<SocketAddr, SipError> {
+    if data.len() < HEADER_LEN {
+        return Err(SipError::Transport("STUN response too short".to_string()));
+    }
+
+    let message_type = u16::from_be_bytes([data[0], data[1]]);
+    let length = u16::from_be_bytes([data[2], data[3]]) as usize;
+    let cookie = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+
+    if message_type != BINDING_SUCCESS {
+        return Err(SipError::Transport(format!("Unexpected STUN message type 0x{:04x}", message_type)));
+    }
+    if cookie != MAGIC_COOKIE || &data[8..20] != transaction_id {
+        return Err(SipError::Transport("STUN transaction mismatch".to_string()));
+    }
+    if data.len() < HEADER_LEN + length {
+        return Err(SipError::Transport("STUN response truncated".to_string()));
+    }
+
+    let mut mapped = None;
+    let mut offset = HEADER_LEN;
+    let end = HEADER_LEN + length;
+
+    while offset + 4 <= end {
+        let attr_type = u16::from_be_bytes([data[offset], data[offset + 1]]);
+        let attr_len = u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize;
+        let value_start = offset + 4;
+        let value_end = value_start + attr_len;
+        if value_end > end {
+            break;
+        }
+        let value = &data[value_start..value_end];
+
+        match attr_type {
+            ATTR_XOR_MAPPED_ADDRESS => {
+                if let Some(addr) = parse_address(value, Some(transaction_id)) {
+                    return Ok(addr);
+                }
+            }
+            ATTR_MAPPED_ADDRESS => {
+                mapped = mapped.or_else(|| parse_address(value, None));
+            }
+            _ => {}
+        }
+
+        // Attributes are padded to a multiple of 4 bytes
+        offset = value_start + attr_len.div_ceil(4) * 4;
+    }
+
+    mapped.ok_or_else(|| SipError::Transport("STUN response has no mapped address".to_string()))
+}
+
+/// Decode a (XOR-)MAPPED-ADDRESS value. The transaction ID is given for the XOR variant.
+fn parse_address(value: &[u8], xor_transaction_id: Option<&[u8; 12]>) -> Option<SocketAddr> {
+    if value.len() < 4 {
+        return None;
+    }
+
+    let family = value[1];
+    let mut port = u16::from_be_bytes([value[2], value[3]]);
+    let cookie = MAGIC_COOKIE.to_be_bytes();
+
+    if xor_transaction_id.is_some() {
+        port ^= (MAGIC_COOKIE >> 16) as u16;
+    }
+
+    let ip = match family {
+        0x01 if value.len() >= 8 => {
+            let mut octets = [value[4], value[5], value[6], value[7]];
+            if xor_transaction_id.is_some() {
+                for (octet, key) in octets.iter_mut().zip(cookie.iter()) {
+                    *octet ^= key;
+                }
+            }
+            IpAddr::V4(Ipv4Addr::from(octets))
+        }
+        0x02 if value.len() >= 20 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&value[4..20]);
+            if let Some(transaction_id) = xor_transaction_id {
+                let key: Vec<u8> = cookie.iter().chain(transaction_id.iter()).copied().collect();
+                for (octet, key) in octets.iter_mut().zip(key.iter()) {
+                    *octet ^= key;
+                }
+            }
+            IpAddr::V6(Ipv6Addr::from(octets))
+        }
+        _ => return None,
+    };
+
+    Some(SocketAddr::new(ip, port))
+}
+
+/// Cached public address from the last successful binding
+#[derive(Debug, Clone, Copy)]
+struct CachedBinding {
+    addr: SocketAddr,
+    discovered_at: Instant,
+}
+
+/// STUN client with a cached binding
+pub struct StunClient {
+    /// STUN server as host[:port], optionally prefixed with "stun:"
+    server: String,
+    /// How long a binding is trusted before it is refreshed
+    refresh_interval: Duration,
+    /// Per-request timeout
+    timeout: Duration,
+    /// Last discovered binding
+    cached: RwLock<Option<CachedBinding>>,
+}
+
+impl StunClient {
+    /// Create a client for the given server
+    pub fn new(server: impl Into<String>) -> Self {
+        Self {
+            server: server.into(),
+            refresh_interval: Duration::from_secs(300),
+            timeout: Duration::from_secs(2),
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Set how long a cached binding is reused
+    pub fn with_refresh_interval(mut self, interval: Duration) -> Self {
+        self.refresh_interval = interval;
+        self
+    }
+
+    /// Set the per-request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Resolve the configured server to a socket address
+    async fn server_addr(&self) -> Result<SocketAddr, SipError> {
+        let host = self.server.trim().trim_start_matches("stun:");
+        let target = if host.rsplit_once(':').is_some_and(|(_, port)| port.parse::<u16>().is_ok()) {
+            host.to_string()
+        } else {
+            format!("{}:{}", host, DEFAULT_STUN_PORT)
+        };
+
+        let addr = tokio::net::lookup_host(&target)
+            .await?
+            .find(|addr| addr.is_ipv4())
+            .ok_or_else(|| SipError::Transport(format!("Could not resolve STUN server {}", target)));
+        addr
+    }
+
+    /// Discover the public address the NAT maps `socket` to
+    ///
+    /// The socket must not have a receive loop running yet, since the response
+    /// arrives on it. A successful result refreshes the cached binding.
+    pub async fn map_socket(&self, socket: &UdpSocket) -> Result<SocketAddr, SipError> {
+        let server = self.server_addr().await?;
+        let transaction_id: [u8; 12] = rand::random();
+        let request = build_binding_request(&transaction_id);
+
+        socket.send_to(&request, server).await?;
+
+        let mut buf = [0u8; 512];
+        let deadline = tokio::time::Instant::now() + self.timeout;
+        let addr = loop {
+            let (len, from) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf))
+                .await
+                .map_err(|_| SipError::Timeout(format!("No STUN response from {}", server)))??;
+
+            // Ignore stray packets (early RTP, other servers)
+            if from != server {
+                continue;
+            }
+            match parse_binding_response(&buf[..len], &transaction_id) {
+                Ok(addr) => break addr,
+                Err(e) => tracing::debug!("Ignoring STUN packet from {}: {}", from, e),
+            }
+        };
+
+        *self.cached.write().await = Some(CachedBinding {
+            addr,
+            discovered_at: Instant::now(),
+        });
+
+        tracing::debug!("STUN mapped address: {}", addr);
+        Ok(addr)
+    }
+
+    /// Public IP from the cached binding, refreshed when older than the refresh interval
+    pub async fn public_ip(&self) -> Result<IpAddr, SipError> {
+        if let Some(binding) = *self.cached.read().await {
+            if binding.discovered_at.elapsed() < self.refresh_interval {
+                return Ok(binding.addr.ip());
+            }
+        }
+
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        Ok(self.map_socket(&socket).await?.ip())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::net::SocketAddrV4;
+
+    /// Build a Binding Success Response carrying an XOR-MAPPED-ADDRESS
+    fn xor_mapped_response(transaction_id: &[u8], addr: SocketAddrV4) -> Vec<u8> {
+        let cookie = MAGIC_COOKIE.to_be_bytes();
+        let port = addr.port() ^ (MAGIC_COOKIE >> 16) as u16;
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&BINDING_SUCCESS.to_be_bytes());
+        packet.extend_from_slice(&12u16.to_be_bytes());
+        packet.extend_from_slice(&cookie);
+        packet.extend_from_slice(transaction_id);
+        packet.extend_from_slice(&ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+        packet.extend_from_slice(&8u16.to_be_bytes());
+        packet.extend_from_slice(&[0x00, 0x01]);
+        packet.extend_from_slice(&port.to_be_bytes());
+        for (octet, key) in addr.ip().octets().iter().zip(cookie.iter()) {
+            packet.push(octet ^ key);
+        }
+        packet
+    }
+
+    /// Spawn a mock STUN server that answers every request with `mapped`
+    pub(crate) async fn mock_stun_server(mapped: SocketAddrV4) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                if len < HEADER_LEN {
+                    continue;
+                }
+                let response = xor_mapped_response(&buf[8..20], mapped);
+                let _ = socket.send_to(&response, from).await;
+            }
+        });
+
+        addr
+    }
+
+    #[test]
+    fn test_binding_request_layout() {
+        let transaction_id = [7u8; 12];
+        let packet = build_binding_request(&transaction_id);
+
+        assert_eq!(&packet[0..2], &[0x00, 0x01]);
+        assert_eq!(&packet[2..4], &[0x00, 0x00]);
+        assert_eq!(&packet[4..8], &[0x21, 0x12, 0xA4, 0x42]);
+        assert_eq!(&packet[8..20], &transaction_id);
+    }
+
+    #[test]
+    fn test_parse_xor_mapped_address() {
+        let transaction_id = [1u8; 12];
+        let response = xor_mapped_response(
+            &transaction_id,
+            SocketAddrV4::new(Ipv4Addr::new(203, 0, 113, 5), 40000),
+        );
+
+        let addr = parse_binding_response(&response, &transaction_id).unwrap();
+        assert_eq!(addr, "203.0.113.5:40000".parse().unwrap());
+    }
+
+    #[test]
+    fn test_parse_rejects_wrong_transaction() {
+        let response = xor_mapped_response(
+            &[1u8; 12],
+            SocketAddrV4::new(Ipv4Addr::new(203, 0, 113, 5), 40000),
+        );
+
+        assert!(parse_binding_response(&response, &[2u8; 12]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_map_socket_against_mock_server() {
+        let server = mock_stun_server(SocketAddrV4::new(Ipv4Addr::new(198, 51, 100, 7), 31000)).await;
+        let client = StunClient::new(server.to_string());
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let addr = client.map_socket(&socket).await.unwrap();
+        assert_eq!(addr, "198.51.100.7:31000".parse().unwrap());
+
+        // The binding is cached for later lookups
+        assert_eq!(client.public_ip().await.unwrap(), "198.51.100.7".parse::<IpAddr>().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_map_socket_times_out() {
+        // Bound but silent server
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let client = StunClient::new(silent.local_addr().unwrap().to_string())
+            .with_timeout(Duration::from_millis(100));
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        assert!(matches!(client.map_socket(&socket).await, Err(SipError::Timeout(_))));
+    }
+}
@@ -0,0 +1,253 @@
+//! Multiple SIP trunks with failover
+//!
+//! Outbound calls go to trunks in an order picked by `TrunkStrategy`. If a
+//! trunk is unreachable, unregistered, times out or answers with a 5xx, the
+//! next trunk is tried. Other rejections (busy, declined, not found) are
+//! about the callee, not the trunk, and are returned as-is.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use super::rtp::CallQualityMetrics;
+use super::user_agent::SipUserAgent;
+use super::SipError;
+
+/// How long a trunk has to start ringing before we try the next one
+pub const TRUNK_SETUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Order in which trunks are tried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrunkStrategy {
+    /// Always start with the first configured trunk
+    #[default]
+    Priority,
+    /// Start each call on the trunk after the one used last
+    RoundRobin,
+}
+
+impl TrunkStrategy {
+    /// Read SIP_TRUNK_STRATEGY (priority or round_robin)
+    pub fn from_env() -> Self {
+        match std::env::var("SIP_TRUNK_STRATEGY")
+            .unwrap_or_default()
+            .to_lowercase()
+            .as_str()
+        {
+            "round_robin" | "round-robin" => TrunkStrategy::RoundRobin,
+            _ => TrunkStrategy::Priority,
+        }
+    }
+}
+
+/// Trunk indices to try, in order
+pub fn dial_order(strategy: TrunkStrategy, trunks: usize, start: usize) -> Vec<usize> {
+    match strategy {
+        TrunkStrategy::Priority => (0..trunks).collect(),
+        TrunkStrategy::RoundRobin => (0..trunks).map(|i| (start + i) % trunks).collect(),
+    }
+}
+
+/// Run `attempt` on each trunk in `order` until one succeeds or fails for a
+/// reason another trunk can't fix. Returns the index of the trunk used.
+pub async fn with_failover<T, F, Fut>(order: &[usize], mut attempt: F) -> Result<(usize, T), SipError>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<T, SipError>>,
+{
+    let mut last_error = None;
+
+    for &index in order {
+        match attempt(index).await {
+            Ok(value) => return Ok((index, value)),
+            Err(e) if e.is_trunk_failure() => {
+                tracing::warn!("SIP trunk {} failed, trying next: {}", index, e);
+                last_error = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| SipError::InvalidState("No SIP trunks configured".to_string())))
+}
+
+/// The configured SIP trunks
+pub struct SipTrunks {
+    agents: Vec<Arc<RwLock<SipUserAgent>>>,
+    strategy: TrunkStrategy,
+    next: AtomicUsize,
+}
+
+impl SipTrunks {
+    pub fn new(agents: Vec<Arc<RwLock<SipUserAgent>>>, strategy: TrunkStrategy) -> Self {
+        Self {
+            agents,
+            strategy,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// User agents in configured (priority) order
+    pub fn agents(&self) -> &[Arc<RwLock<SipUserAgent>>] {
+        &self.agents
+    }
+
+    pub fn strategy(&self) -> TrunkStrategy {
+        self.strategy
+    }
+
+    /// Whether at least one trunk is registered
+    pub async fn any_registered(&self) -> bool {
+        for agent in &self.agents {
+            if agent.read().await.is_registered().await {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Dial on the first trunk that accepts the call. Returns the trunk index and call id.
+    pub async fn dial(&self, to: &str) -> Result<(usize, String), SipError> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        let order = dial_order(self.strategy, self.agents.len(), start);
+        let agents = &self.agents;
+
+        with_failover(&order, |index| async move {
+            agents[index].read().await.dial_and_confirm(to, TRUNK_SETUP_TIMEOUT).await
+        })
+        .await
+    }
+
+    /// RTCP quality metrics for a call on whichever trunk carries it
+    pub async fn call_quality(&self, call_id: &str) -> Result<Option<CallQualityMetrics>, SipError> {
+        for agent in &self.agents {
+            if let Some(call) = agent.read().await.get_call(call_id).await {
+                return Ok(call.read().await.quality().await);
+            }
+        }
+        Err(SipError::CallNotFound(call_id.to_string()))
+    }
+
+    /// Put a call on hold on whichever trunk carries it
+    pub async fn hold(&self, call_id: &str) -> Result<(), SipError> {
+        for agent in &self.agents {
+            let agent = agent.read().await;
+            if agent.get_call(call_id).await.is_some() {
+                return agent.hold(call_id).await;
+            }
+        }
+        Err(SipError::CallNotFound(call_id.to_string()))
+    }
+
+    /// Take a call off hold on whichever trunk carries it
+    pub async fn unhold(&self, call_id: &str) -> Result<(), SipError> {
+        for agent in &self.agents {
+            let agent = agent.read().await;
+            if agent.get_call(call_id).await.is_some() {
+                return agent.unhold(call_id).await;
+            }
+        }
+        Err(SipError::CallNotFound(call_id.to_string()))
+    }
+
+    /// Hang up a call on whichever trunk carries it
+    pub async fn hangup(&self, call_id: &str) -> Result<(), SipError> {
+        for agent in &self.agents {
+            let agent = agent.read().await;
+            if agent.get_call(call_id).await.is_some() {
+                return agent.hangup(call_id).await;
+            }
+        }
+        Err(SipError::CallNotFound(call_id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::config::{SipConfig, SipTransport};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_dial_order() {
+        assert_eq!(dial_order(TrunkStrategy::Priority, 3, 5), vec![0, 1, 2]);
+        assert_eq!(dial_order(TrunkStrategy::RoundRobin, 3, 0), vec![0, 1, 2]);
+        assert_eq!(dial_order(TrunkStrategy::RoundRobin, 3, 4), vec![1, 2, 0]);
+        assert!(dial_order(TrunkStrategy::RoundRobin, 0, 4).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_second_trunk_on_5xx() {
+        let tried = Mutex::new(Vec::new());
+
+        let result = with_failover(&[0, 1, 2], |index| {
+            tried.lock().unwrap().push(index);
+            async move {
+                match index {
+                    0 => Err(SipError::Rejected(503)),
+                    _ => Ok(format!("call-on-{}", index)),
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(result, (1, "call-on-1".to_string()));
+        assert_eq!(*tried.lock().unwrap(), vec![0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_failover_on_timeout_and_gives_up_after_last_trunk() {
+        let result: Result<(usize, ()), _> = with_failover(&[0, 1], |index| async move {
+            match index {
+                0 => Err(SipError::Timeout("no answer".to_string())),
+                _ => Err(SipError::Rejected(502)),
+            }
+        })
+        .await;
+
+        assert!(matches!(result, Err(SipError::Rejected(502))));
+    }
+
+    #[tokio::test]
+    async fn test_callee_rejection_does_not_fail_over() {
+        let tried = Mutex::new(Vec::new());
+
+        let result: Result<(usize, ()), _> = with_failover(&[0, 1], |index| {
+            tried.lock().unwrap().push(index);
+            async move { Err(SipError::Rejected(486)) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(SipError::Rejected(486))));
+        assert_eq!(*tried.lock().unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn test_parse_trunks_inherits_shared_settings() {
+        let base = SipConfig {
+            username: "shared-user".to_string(),
+            password: "secret".to_string(),
+            caller_id: "+15551234567".to_string(),
+            ..SipConfig::default()
+        };
+        let json = r#"[
+            {"host": "primary.example.com"},
+            {"host": "backup.example.com", "port": 5080, "caller_id": "+15557654321", "transport": "TCP"}
+        ]"#;
+
+        let trunks = SipConfig::parse_trunks(json, &base).unwrap();
+
+        assert_eq!(trunks.len(), 2);
+        assert_eq!(trunks[0].trunk_host, "primary.example.com");
+        assert_eq!(trunks[0].domain, "primary.example.com");
+        assert_eq!(trunks[0].trunk_port, 5060);
+        assert_eq!(trunks[0].caller_id, "+15551234567");
+        assert_eq!(trunks[1].trunk_port, 5080);
+        assert_eq!(trunks[1].caller_id, "+15557654321");
+        assert_eq!(trunks[1].username, "shared-user");
+        assert_eq!(trunks[1].transport, SipTransport::Tcp);
+    }
+}
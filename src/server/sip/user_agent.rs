@@ -13,7 +13,8 @@ use uuid::Uuid;
 use ftth_rsipstack::{
     dialog::{
         authenticate::Credential,
-        dialog::DialogState,
+        client_dialog::ClientInviteDialog,
+        dialog::{DialogState, DialogStateSender},
         dialog_layer::DialogLayer,
         invitation::InviteOption,
         registration::Registration,
@@ -25,8 +26,12 @@ use ftth_rsipstack::{
 use tokio::sync::mpsc::unbounded_channel;
 
 use super::config::{SipCodec, SipConfig};
-use super::call::{CallEvent, CallState, SipCall};
-use super::rtp::{AudioFrame, RtpPortAllocator, RtpSession};
+use super::digest;
+use super::call::{CallDirection, CallEvent, CallState, SipCall};
+use super::audio::{decode_wav, resample, G711_SAMPLE_RATE};
+use super::rtp::{AudioFrame, RtpPortAllocator, RtpSession, FRAME_DURATION};
+use super::sdp::MediaDirection;
+use super::stun::StunClient;
 use super::SipError;
 
 /// SIP User Agent state
@@ -53,7 +58,7 @@ pub struct SipUserAgent {
     /// Active calls by call ID
     calls: RwLock<HashMap<String, Arc<RwLock<SipCall>>>>,
     /// RTP port allocator
-    rtp_ports: RtpPortAllocator,
+    rtp_ports: Arc<RtpPortAllocator>,
     /// Local IP address
     local_ip: RwLock<Option<String>>,
     /// Local SIP port
@@ -68,6 +73,10 @@ pub struct SipUserAgent {
     endpoint_inner: RwLock<Option<EndpointInnerRef>>,
     /// Credentials for authentication
     credential: RwLock<Option<Credential>>,
+    /// STUN client for public address discovery (when SIP_STUN_SERVER is set)
+    stun: Option<StunClient>,
+    /// 8kHz hold music, looped to held callers
+    hold_music: Option<Arc<Vec<i16>>>,
 }
 
 /// Agent-level events
@@ -95,8 +104,11 @@ impl SipUserAgent {
     pub fn new(config: SipConfig) -> (Self, mpsc::Receiver<AgentEvent>) {
         let (event_tx, event_rx) = mpsc::channel(100);
 
+        let stun = config.stun_server.clone().map(StunClient::new);
+        let hold_music = config.hold_music_wav.as_deref().and_then(load_hold_music).map(Arc::new);
+
         let agent = Self {
-            rtp_ports: RtpPortAllocator::new(config.rtp_port_start, config.rtp_port_end),
+            rtp_ports: Arc::new(RtpPortAllocator::new(config.rtp_port_start, config.rtp_port_end)),
             config,
             state: Arc::new(RwLock::new(AgentState::Disconnected)),
             calls: RwLock::new(HashMap::new()),
@@ -107,6 +119,8 @@ impl SipUserAgent {
             cancel_token: CancellationToken::new(),
             endpoint_inner: RwLock::new(None),
             credential: RwLock::new(None),
+            stun,
+            hold_music,
         };
 
         (agent, event_rx)
@@ -381,12 +395,18 @@ impl SipUserAgent {
         let (call_event_tx, mut call_event_rx) = mpsc::channel(50);
 
         // Allocate RTP port
-        let rtp_port = self.rtp_ports.allocate().await;
-        let rtp_session = RtpSession::new(rtp_port, self.config.codec).await?;
-        let rtp_local_port = rtp_session.local_port();
+        let rtp_port = self.rtp_ports.allocate()?;
+        let rtp_session = match RtpSession::new(rtp_port, self.config.codec).await {
+            Ok(session) => session.with_jitter_depth(Duration::from_millis(self.config.jitter_buffer_ms as u64)),
+            Err(e) => {
+                self.rtp_ports.release(rtp_port);
+                return Err(e);
+            }
+        };
 
-        // Create SDP offer
-        let sdp_offer = self.create_sdp_offer(&local_ip, rtp_local_port);
+        // Create SDP offer with the address the trunk can reach us on
+        let (media_ip, media_port) = self.media_address(&rtp_session, &local_ip).await;
+        let sdp_offer = self.create_sdp_offer(&media_ip, media_port);
 
         // Build SIP URIs
         let caller_uri = format!("sip:{}@{}", self.config.username, self.config.domain);
@@ -403,10 +423,10 @@ impl SipUserAgent {
                 .map_err(|e| SipError::CallFailed(format!("Invalid callee URI: {:?}", e)))?,
             content_type: Some("application/sdp".to_string()),
             destination: None,
-            offer: Some(sdp_offer.into_bytes()),
+            offer: Some(sdp_offer.clone().into_bytes()),
             contact: contact_uri.as_str().try_into()
                 .map_err(|e| SipError::CallFailed(format!("Invalid contact URI: {:?}", e)))?,
-            credential,
+            credential: credential.clone(),
             headers: None,
         };
 
@@ -424,6 +444,8 @@ impl SipUserAgent {
             call_event_tx,
         );
         call.set_rtp_session(Arc::new(rtp_session));
+        call.set_rtp_port(rtp_port);
+        call.set_local_sdp(sdp_offer.clone());
 
         // Store call
         let call = Arc::new(RwLock::new(call));
@@ -476,6 +498,11 @@ impl SipUserAgent {
                         tracing::info!("Call {} - Confirmed (200 OK)", call_id_for_states);
                         call_ref.set_state(CallState::Active).await;
                     }
+                    DialogState::Terminated(_, ref reason) if call_ref.state().await == CallState::Trying => {
+                        // Rejected before ringing, possibly by an auth challenge the INVITE
+                        // task will answer; it sets the outcome once the final response is in
+                        tracing::info!("Call {} - INVITE rejected: {:?}", call_id_for_states, reason);
+                    }
                     DialogState::Terminated(_, ref reason) => {
                         tracing::info!("Call {} - Terminated: {:?}", call_id_for_states, reason);
                         call_ref.set_state(CallState::Failed).await;
@@ -491,10 +518,11 @@ impl SipUserAgent {
         // Spawn INVITE task
         let call_clone = call.clone();
         let call_id_clone = call_id.clone();
+        let rtp_ports = self.rtp_ports.clone();
 
         tokio::spawn(async move {
             // Send INVITE - this blocks until we get a final response
-            match dialog_layer.do_invite(invite_option, state_tx).await {
+            match invite_with_auth(&dialog_layer, invite_option, state_tx, credential.as_ref()).await {
                 Ok((client_dialog, response)) => {
                     let call_ref = call_clone.read().await;
 
@@ -506,6 +534,7 @@ impl SipUserAgent {
                         if status >= 200 && status < 300 {
                             // Call answered (200 OK)
                             tracing::info!("Call {} connected!", call_id_clone);
+                            call_ref.set_dialog(client_dialog.clone());
                             // State already set to Active via state channel
 
                             // Keep dialog alive - wait for BYE or hangup
@@ -545,6 +574,7 @@ impl SipUserAgent {
                                 _ => "Unknown Error",
                             };
                             tracing::warn!("Call {} failed: {} - {}", call_id_clone, status, reason);
+                            call_ref.set_final_status(status).await;
                             call_ref.set_state(CallState::Failed).await;
                         }
                     } else {
@@ -559,6 +589,15 @@ impl SipUserAgent {
                     call_ref.set_state(CallState::Failed).await;
                 }
             }
+
+            // The call is over, whether rejected, hung up locally or ended by a remote BYE
+            let call_ref = call_clone.read().await;
+            if let Some(rtp) = call_ref.rtp_session() {
+                rtp.stop().await;
+            }
+            if let Some(port) = call_ref.take_rtp_port() {
+                rtp_ports.release(port);
+            }
         });
 
         tracing::info!(
@@ -571,6 +610,71 @@ impl SipUserAgent {
         Ok(call_id)
     }
 
+    /// Dial and wait until the trunk accepts the call
+    ///
+    /// Returns once the call is ringing or answered. A rejection, or no progress
+    /// within `setup_timeout`, is returned as an error after cleaning up the
+    /// call, so the caller can retry on another trunk.
+    pub async fn dial_and_confirm(&self, to: &str, setup_timeout: Duration) -> Result<String, SipError> {
+        let call_id = self.dial(to).await?;
+        let call = self
+            .get_call(&call_id)
+            .await
+            .ok_or_else(|| SipError::CallNotFound(call_id.clone()))?;
+        let deadline = tokio::time::Instant::now() + setup_timeout;
+
+        loop {
+            let (state, final_status) = {
+                let call = call.read().await;
+                (call.state().await, call.final_status().await)
+            };
+
+            match state {
+                CallState::Ringing | CallState::Active | CallState::Held => return Ok(call_id),
+                CallState::Failed | CallState::Ended => {
+                    self.calls.write().await.remove(&call_id);
+                    return Err(match final_status {
+                        Some(status) => SipError::Rejected(status),
+                        None => SipError::Transport("No final response to INVITE".to_string()),
+                    });
+                }
+                CallState::Trying | CallState::Terminating => {}
+            }
+
+            if tokio::time::Instant::now() >= deadline {
+                let _ = self.hangup(&call_id).await;
+                return Err(SipError::Timeout(format!("No response to INVITE for {}", to)));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Address to advertise for RTP media
+    ///
+    /// With a STUN server configured, the RTP socket's public mapping is used. If
+    /// the query fails we fall back to the cached public IP with the local port,
+    /// and finally to the local address.
+    async fn media_address(&self, rtp_session: &RtpSession, local_ip: &str) -> (String, u16) {
+        let local_port = rtp_session.local_port();
+
+        let Some(stun) = &self.stun else {
+            return (local_ip.to_string(), local_port);
+        };
+
+        match rtp_session.discover_public_addr(stun).await {
+            Ok(addr) => return (addr.ip().to_string(), addr.port()),
+            Err(e) => tracing::warn!("STUN binding for RTP port {} failed: {}", local_port, e),
+        }
+
+        match stun.public_ip().await {
+            Ok(ip) => (ip.to_string(), local_port),
+            Err(e) => {
+                tracing::warn!("STUN public IP unavailable, advertising local address: {}", e);
+                (local_ip.to_string(), local_port)
+            }
+        }
+    }
+
     /// Create SDP offer for outbound call
     fn create_sdp_offer(&self, local_ip: &str, rtp_port: u16) -> String {
         let session_id = rand::random::<u32>();
@@ -646,6 +750,9 @@ impl SipUserAgent {
         if let Some(rtp) = call.rtp_session() {
             rtp.stop().await;
         }
+        if let Some(port) = call.take_rtp_port() {
+            self.rtp_ports.release(port);
+        }
 
         call.set_state(CallState::Ended).await;
 
@@ -657,6 +764,57 @@ impl SipUserAgent {
         Ok(())
     }
 
+    /// Put a call on hold with a re-INVITE. With hold music configured the
+    /// stream goes sendonly and the music is played to the remote party;
+    /// otherwise it goes inactive.
+    pub async fn hold(&self, call_id: &str) -> Result<(), SipError> {
+        let call = self
+            .get_call(call_id)
+            .await
+            .ok_or_else(|| SipError::CallNotFound(call_id.to_string()))?;
+
+        {
+            let call = call.read().await;
+            let state = call.state().await;
+            if state != CallState::Active {
+                return Err(SipError::InvalidState(format!("Cannot hold call in state: {}", state)));
+            }
+            // Inbound calls are answered without a dialog of our own to re-INVITE on
+            if call.direction == CallDirection::Inbound {
+                return Err(SipError::InvalidState("Inbound SIP calls can't be put on hold".to_string()));
+            }
+
+            let direction = if self.hold_music.is_some() { MediaDirection::SendOnly } else { MediaDirection::Inactive };
+            call.reinvite(direction).await?;
+            call.set_state(CallState::Held).await;
+        }
+
+        if let Some(music) = self.hold_music.clone() {
+            tokio::spawn(play_hold_music(call, music));
+        }
+        tracing::info!("SIP call held: {}", call_id);
+        Ok(())
+    }
+
+    /// Take a call off hold, restoring two-way audio
+    pub async fn unhold(&self, call_id: &str) -> Result<(), SipError> {
+        let call = self
+            .get_call(call_id)
+            .await
+            .ok_or_else(|| SipError::CallNotFound(call_id.to_string()))?;
+        let call = call.read().await;
+
+        let state = call.state().await;
+        if state != CallState::Held {
+            return Err(SipError::InvalidState(format!("Cannot resume call in state: {}", state)));
+        }
+
+        call.reinvite(MediaDirection::SendRecv).await?;
+        call.set_state(CallState::Active).await;
+        tracing::info!("SIP call resumed: {}", call_id);
+        Ok(())
+    }
+
     /// Get a call by ID
     pub async fn get_call(&self, call_id: &str) -> Option<Arc<RwLock<SipCall>>> {
         self.calls.read().await.get(call_id).cloned()
@@ -734,6 +892,75 @@ impl Drop for SipUserAgent {
     }
 }
 
+/// Send an INVITE, answering one 401/407 challenge with the trunk credentials
+///
+/// Returns the dialog and final response of the last attempt, so a second
+/// challenge is reported like any other rejection.
+async fn invite_with_auth(
+    dialog_layer: &DialogLayer,
+    invite_option: InviteOption,
+    state_tx: DialogStateSender,
+    credential: Option<&Credential>,
+) -> Result<(ClientInviteDialog, Option<ftth_rsipstack::rsip::Response>), ftth_rsipstack::Error> {
+    let (dialog, response) = dialog_layer.do_invite(invite_option.clone(), state_tx.clone()).await?;
+
+    let challenge = response.as_ref().filter(|resp| digest::is_challenge(resp.status_code.code()));
+    let (Some(challenge), Some(credential)) = (challenge, credential) else {
+        return Ok((dialog, response));
+    };
+
+    let header = match digest::challenge_response(challenge, &ftth_rsipstack::rsip::Method::Invite, &invite_option.callee, credential) {
+        Ok(header) => header,
+        Err(e) => {
+            tracing::warn!("Cannot answer INVITE challenge: {}", e);
+            return Ok((dialog, response));
+        }
+    };
+
+    tracing::info!("INVITE challenged with {}, retrying with credentials", challenge.status_code.code());
+    let retry = InviteOption {
+        headers: Some(vec![header]),
+        ..invite_option
+    };
+    dialog_layer.do_invite(retry, state_tx).await
+}
+
+/// Read the hold music file as 8kHz mono PCM. A missing or unreadable file
+/// is logged and calls are held in silence instead.
+fn load_hold_music(path: &str) -> Option<Vec<i16>> {
+    let decoded = std::fs::read(path)
+        .map_err(SipError::Io)
+        .and_then(|bytes| decode_wav(&bytes));
+    match decoded {
+        Ok((samples, rate)) if !samples.is_empty() => Some(resample(&samples, rate, G711_SAMPLE_RATE)),
+        Ok(_) => {
+            tracing::warn!("Hold music {} is empty; holding in silence", path);
+            None
+        }
+        Err(e) => {
+            tracing::warn!("Failed to load hold music {}: {}; holding in silence", path, e);
+            None
+        }
+    }
+}
+
+/// Loop `music` to a held call, one frame every 20ms, until it is taken off hold
+async fn play_hold_music(call: Arc<RwLock<SipCall>>, music: Arc<Vec<i16>>) {
+    let frame_len = (G711_SAMPLE_RATE as u128 * FRAME_DURATION.as_millis() / 1000) as usize;
+    let mut ticker = tokio::time::interval(FRAME_DURATION);
+
+    for frame in music.chunks(frame_len).cycle() {
+        ticker.tick().await;
+        let call = call.read().await;
+        if call.state().await != CallState::Held {
+            break;
+        }
+        if call.send_audio(frame).await.is_err() {
+            break;
+        }
+    }
+}
+
 /// Builder for SipUserAgent
 pub struct SipUserAgentBuilder {
     config: SipConfig,
@@ -779,3 +1006,127 @@ impl Default for SipUserAgentBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::stun::tests::mock_stun_server;
+    use std::net::{Ipv4Addr, SocketAddrV4};
+
+    fn config_with_stun(stun_server: Option<String>) -> SipConfig {
+        SipConfig {
+            stun_server,
+            ..SipConfig::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sdp_offer_uses_stun_mapped_address() {
+        let server = mock_stun_server(SocketAddrV4::new(Ipv4Addr::new(203, 0, 113, 5), 40000)).await;
+        let (agent, _events) = SipUserAgent::new(config_with_stun(Some(server.to_string())));
+        let rtp_session = RtpSession::new(0, SipCodec::Pcmu).await.unwrap();
+
+        let (ip, port) = agent.media_address(&rtp_session, "10.0.0.5").await;
+        let sdp = agent.create_sdp_offer(&ip, port);
+
+        assert!(sdp.contains("c=IN IP4 203.0.113.5\r\n"));
+        assert!(sdp.contains("m=audio 40000 RTP/AVP"));
+        assert!(!sdp.contains("10.0.0.5"));
+    }
+
+    /// Copy the headers a response must echo from a request
+    fn response_to(request: &str, status: &str, extra: &str, body: &str) -> String {
+        let mut response = format!("SIP/2.0 {}\r\n", status);
+        for line in request.lines() {
+            let name = line.split(':').next().unwrap_or_default().to_ascii_lowercase();
+            match name.as_str() {
+                "via" | "from" | "call-id" | "cseq" => response.push_str(&format!("{}\r\n", line)),
+                "to" if line.contains("tag=") => response.push_str(&format!("{}\r\n", line)),
+                "to" => response.push_str(&format!("{};tag=mock\r\n", line)),
+                _ => {}
+            }
+        }
+        response.push_str(extra);
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        response
+    }
+
+    /// Mock trunk that accepts REGISTER, challenges the first INVITE with a
+    /// 407 and answers the one carrying credentials. Every INVITE is passed on.
+    async fn mock_trunk() -> (std::net::SocketAddr, mpsc::UnboundedReceiver<String>) {
+        let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let (invite_tx, invite_rx) = mpsc::unbounded_channel();
+
+        tokio::spawn(async move {
+            let sdp = "v=0\r\no=- 0 0 IN IP4 127.0.0.1\r\ns=-\r\nc=IN IP4 127.0.0.1\r\nt=0 0\r\n\
+                       m=audio 40000 RTP/AVP 0\r\na=rtpmap:0 PCMU/8000\r\n";
+            let mut buf = vec![0u8; 8192];
+            while let Ok((len, from)) = socket.recv_from(&mut buf).await {
+                let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                let contact = format!("Contact: <sip:mock@127.0.0.1:{}>\r\n", addr.port());
+
+                let response = if request.starts_with("REGISTER ") {
+                    response_to(&request, "200 OK", &format!("{}Expires: 3600\r\n", contact), "")
+                } else if request.starts_with("INVITE ") {
+                    let _ = invite_tx.send(request.clone());
+                    if request.contains("Proxy-Authorization:") {
+                        let extra = format!("{}Content-Type: application/sdp\r\n", contact);
+                        response_to(&request, "200 OK", &extra, sdp)
+                    } else {
+                        let challenge = "Proxy-Authenticate: Digest realm=\"mock\", nonce=\"abc123\"\r\n";
+                        response_to(&request, "407 Proxy Authentication Required", challenge, "")
+                    }
+                } else if request.starts_with("BYE ") {
+                    response_to(&request, "200 OK", "", "")
+                } else {
+                    continue;
+                };
+                let _ = socket.send_to(response.as_bytes(), from).await;
+            }
+        });
+
+        (addr, invite_rx)
+    }
+
+    #[tokio::test]
+    async fn test_dial_retries_proxy_challenge_with_credentials() {
+        let (trunk, mut invites) = mock_trunk().await;
+        let (agent, _events) = SipUserAgentBuilder::new()
+            .trunk("127.0.0.1", trunk.port())
+            .credentials("alice", "secret")
+            .caller_id("+15550000")
+            .build();
+        agent.register().await.unwrap();
+
+        let call_id = agent.dial_and_confirm("+15550100", Duration::from_secs(5)).await.unwrap();
+        let call = agent.get_call(&call_id).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while call.read().await.state().await != CallState::Active {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .expect("call never connected");
+
+        let first = invites.recv().await.unwrap();
+        assert!(!first.contains("Proxy-Authorization:"));
+        let second = invites.recv().await.unwrap();
+        assert!(second.contains("Proxy-Authorization: Digest"), "{}", second);
+        assert!(second.contains("username=\"alice\""));
+
+        agent.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_sdp_offer_uses_local_address_without_stun() {
+        let (agent, _events) = SipUserAgent::new(config_with_stun(None));
+        let rtp_session = RtpSession::new(0, SipCodec::Pcmu).await.unwrap();
+
+        let (ip, port) = agent.media_address(&rtp_session, "10.0.0.5").await;
+        let sdp = agent.create_sdp_offer(&ip, port);
+
+        assert!(sdp.contains("c=IN IP4 10.0.0.5\r\n"));
+        assert!(sdp.contains(&format!("m=audio {} RTP/AVP", rtp_session.local_port())));
+    }
+}
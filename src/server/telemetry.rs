@@ -0,0 +1,196 @@
+//! Prometheus metrics
+//!
+//! A global recorder is installed once in `run_server`; handlers record
+//! through the helpers below and `GET /metrics` renders the current values
+//! in the Prometheus text format. Gauges that mirror existing state (active
+//! calls, SIP registration) are refreshed when the endpoint is scraped.
+//!
+//! Scrapers authenticate with `Authorization: Bearer <METRICS_TOKEN>`; with
+//! no token configured the endpoint answers 404.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use sha2::{Digest, Sha256};
+
+use super::{db, sip, AppState};
+
+pub const CALLS_TOTAL: &str = "crm_calls_total";
+pub const ACTIVE_CALLS: &str = "crm_active_calls";
+pub const DIAL_LATENCY_SECONDS: &str = "crm_dial_latency_seconds";
+pub const WEBHOOK_DURATION_SECONDS: &str = "crm_webhook_duration_seconds";
+pub const HTTP_REQUEST_DURATION_SECONDS: &str = "crm_http_request_duration_seconds";
+pub const SIP_REGISTERED: &str = "crm_sip_registered";
+pub const AUTH_EVENTS_TOTAL: &str = "crm_auth_events_total";
+
+/// Histogram buckets in seconds, from fast API calls up to slow carrier dials
+const DURATION_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+fn builder() -> PrometheusBuilder {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Suffix("_seconds".to_string()), DURATION_BUCKETS)
+        .expect("duration buckets are not empty")
+}
+
+/// Install the global recorder and return a handle for rendering
+pub fn install_recorder() -> anyhow::Result<PrometheusHandle> {
+    Ok(builder().install_recorder()?)
+}
+
+/// Count a call reaching `status` (initiated, failed, answered, completed)
+pub fn record_call_status(status: &'static str) {
+    metrics::counter!(CALLS_TOTAL, "status" => status).increment(1);
+}
+
+/// Record how long the carrier took to accept a dial request and its outcome
+pub fn record_dial(elapsed: Duration, success: bool) {
+    let outcome = if success { "success" } else { "error" };
+    metrics::histogram!(DIAL_LATENCY_SECONDS, "outcome" => outcome).record(elapsed.as_secs_f64());
+    record_call_status(if success { "initiated" } else { "failed" });
+}
+
+/// Count an authentication event, e.g. ("login", "success") or ("login", "locked")
+pub fn record_auth(endpoint: &'static str, outcome: &'static str) {
+    metrics::counter!(AUTH_EVENTS_TOTAL, "endpoint" => endpoint, "outcome" => outcome).increment(1);
+}
+
+/// Records webhook processing time when dropped, so every return path is measured
+pub struct WebhookTimer {
+    event_type: String,
+    started: Instant,
+}
+
+impl WebhookTimer {
+    pub fn start(event_type: &str) -> Self {
+        Self {
+            event_type: event_type.to_string(),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for WebhookTimer {
+    fn drop(&mut self) {
+        metrics::histogram!(WEBHOOK_DURATION_SECONDS, "event_type" => self.event_type.clone())
+            .record(self.started.elapsed().as_secs_f64());
+    }
+}
+
+/// Middleware recording request duration by route template (not the raw path,
+/// which would create a series per lead id)
+pub async fn track_http(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let started = Instant::now();
+
+    let response = next.run(request).await;
+
+    metrics::histogram!(
+        HTTP_REQUEST_DURATION_SECONDS,
+        "method" => method,
+        "route" => route,
+        "status" => response.status().as_u16().to_string(),
+    )
+    .record(started.elapsed().as_secs_f64());
+
+    response
+}
+
+async fn refresh_gauges(state: &AppState) {
+    match db::stats::count_active_calls(&state.db).await {
+        Ok(count) => metrics::gauge!(ACTIVE_CALLS).set(count as f64),
+        Err(e) => tracing::warn!("Metrics: failed to count active calls: {}", e),
+    }
+
+    if let Some(trunks) = &state.sip_trunks {
+        for (trunk, agent) in trunks.agents().iter().enumerate() {
+            let registered = agent.read().await.state().await == sip::AgentState::Registered;
+            metrics::gauge!(SIP_REGISTERED, "trunk" => trunk.to_string()).set(if registered { 1.0 } else { 0.0 });
+        }
+    }
+}
+
+fn render(handle: &PrometheusHandle) -> Response {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        handle.render(),
+    )
+        .into_response()
+}
+
+/// Scrape token from `METRICS_TOKEN`; None leaves `/metrics` disabled
+pub fn token_from_env() -> Option<String> {
+    std::env::var("METRICS_TOKEN").ok().filter(|token| !token.is_empty())
+}
+
+/// Whether an `Authorization` header carries the scrape token. Digests are
+/// compared so the time taken doesn't reveal how much of the token matched.
+fn scrape_authorized(token: &str, authorization: Option<&str>) -> bool {
+    let Some(given) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    Sha256::digest(given.as_bytes()) == Sha256::digest(token.as_bytes())
+}
+
+/// `GET /metrics` for Prometheus to scrape
+pub async fn metrics_handler(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    let Some(token) = state.metrics_token.as_deref() else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let authorization = headers.get(header::AUTHORIZATION).and_then(|value| value.to_str().ok());
+    if !scrape_authorized(token, authorization) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    refresh_gauges(&state).await;
+    render(&state.metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scrape_requires_the_token() {
+        assert!(scrape_authorized("s3cret", Some("Bearer s3cret")));
+        assert!(!scrape_authorized("s3cret", Some("Bearer s3cre")));
+        assert!(!scrape_authorized("s3cret", Some("s3cret")));
+        assert!(!scrape_authorized("s3cret", None));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_render_after_simulated_dial() {
+        let recorder = builder().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            record_dial(Duration::from_millis(180), true);
+            record_call_status("answered");
+            drop(WebhookTimer::start("call.answered"));
+            metrics::gauge!(ACTIVE_CALLS).set(1.0);
+            metrics::gauge!(SIP_REGISTERED, "trunk" => "0").set(1.0);
+        });
+
+        let response = render(&handle);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+
+        for name in [CALLS_TOTAL, ACTIVE_CALLS, DIAL_LATENCY_SECONDS, WEBHOOK_DURATION_SECONDS, SIP_REGISTERED] {
+            assert!(body.contains(name), "missing {} in:\n{}", name, body);
+        }
+        assert!(body.contains(r#"crm_calls_total{status="initiated"} 1"#));
+        assert!(body.contains(r#"crm_dial_latency_seconds_bucket{outcome="success",le="0.25"} 1"#));
+    }
+}
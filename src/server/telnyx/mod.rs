@@ -1,9 +1,12 @@
 //! Telnyx Voice API client
 
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::models::{AmdMode, NumberInfo, RecordingSettings};
+
 #[derive(Error, Debug)]
 pub enum TelnyxError {
     #[error("HTTP error: {0}")]
@@ -32,6 +35,35 @@ impl TelnyxClient {
         }
     }
 
+    /// Send requests to another API host, e.g. a local stub in tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Cheap authenticated request used by the readiness check
+    pub async fn ping(&self) -> Result<(), TelnyxError> {
+        let response = self
+            .client
+            .get(format!("{}/balance", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(std::time::Duration::from_secs(5))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(TelnyxError::Api { message: error_text });
+        }
+
+        Ok(())
+    }
+
+    /// Whether an API key was provided
+    pub fn is_configured(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
     async fn post<T: Serialize, R: for<'de> Deserialize<'de>>(
         &self,
         path: &str,
@@ -60,15 +92,11 @@ impl TelnyxClient {
         to: &str,
         from: &str,
         webhook_url: Option<&str>,
+        amd_mode: AmdMode,
+        client_state: Option<&ClientState>,
     ) -> Result<DialResponse, TelnyxError> {
-        let request = DialRequest {
-            to,
-            from,
-            connection_id: &self.connection_id,
-            webhook_url: webhook_url.unwrap_or(""),
-            webhook_url_method: "POST",
-            answer_machine_detection: Some("detect"),
-        };
+        let mut request = DialRequest::new(to, from, &self.connection_id, webhook_url, amd_mode);
+        request.client_state = client_state.map(ClientState::encode);
 
         let response: TelnyxResponse<DialData> = self.post("/calls", &request).await?;
         Ok(DialResponse {
@@ -79,9 +107,9 @@ impl TelnyxClient {
     }
 
     /// Answer an incoming call
-    pub async fn answer(&self, call_control_id: &str) -> Result<(), TelnyxError> {
+    pub async fn answer(&self, call_control_id: &str, client_state: Option<&ClientState>) -> Result<(), TelnyxError> {
         let request = CallControlRequest {
-            client_state: None,
+            client_state: client_state.map(ClientState::encode),
             command_id: None,
         };
 
@@ -130,6 +158,64 @@ impl TelnyxClient {
         Ok(())
     }
 
+    /// Create a conference from an existing call; the call becomes its first participant.
+    /// Returns the Telnyx conference id.
+    pub async fn create_conference(&self, call_control_id: &str, name: &str) -> Result<String, TelnyxError> {
+        let request = CreateConferenceRequest {
+            call_control_id,
+            name,
+            beep_enabled: "never",
+        };
+
+        let response: TelnyxResponse<ConferenceData> = self.post("/conferences", &request).await?;
+        Ok(response.data.id)
+    }
+
+    /// Join a call to an existing conference, optionally muted (listen-only)
+    pub async fn join_conference(
+        &self,
+        call_control_id: &str,
+        conference_id: &str,
+        mute: bool,
+    ) -> Result<(), TelnyxError> {
+        let request = JoinConferenceRequest {
+            call_control_id,
+            mute,
+            supervisor_role: None,
+            whisper_call_control_ids: None,
+        };
+
+        let _: TelnyxResponse<serde_json::Value> = self
+            .post(&format!("/conferences/{}/actions/join", conference_id), &request)
+            .await?;
+        Ok(())
+    }
+
+    /// Join a supervisor's call to a conference in the given monitoring mode
+    pub async fn join_conference_as_supervisor(
+        &self,
+        call_control_id: &str,
+        conference_id: &str,
+        mode: SupervisorMode<'_>,
+    ) -> Result<(), TelnyxError> {
+        let request = JoinConferenceRequest::supervisor(call_control_id, mode);
+
+        let _: TelnyxResponse<serde_json::Value> = self
+            .post(&format!("/conferences/{}/actions/join", conference_id), &request)
+            .await?;
+        Ok(())
+    }
+
+    /// Remove a call from a conference
+    pub async fn leave_conference(&self, call_control_id: &str, conference_id: &str) -> Result<(), TelnyxError> {
+        let request = LeaveConferenceRequest { call_control_id };
+
+        let _: TelnyxResponse<serde_json::Value> = self
+            .post(&format!("/conferences/{}/actions/leave", conference_id), &request)
+            .await?;
+        Ok(())
+    }
+
     /// Speak text-to-speech on the call
     pub async fn speak(
         &self,
@@ -149,9 +235,40 @@ impl TelnyxClient {
         Ok(())
     }
 
+    /// Speak a prompt and collect a single key press. The result arrives in a
+    /// `call.gather.ended` webhook.
+    pub async fn gather_digit(
+        &self,
+        call_control_id: &str,
+        prompt: &str,
+        timeout: std::time::Duration,
+    ) -> Result<(), TelnyxError> {
+        let request = GatherUsingSpeakRequest {
+            payload: prompt,
+            voice: "female",
+            language: "en-US",
+            minimum_digits: 1,
+            maximum_digits: 1,
+            timeout_millis: timeout.as_millis() as u64,
+        };
+
+        let _: TelnyxResponse<serde_json::Value> = self
+            .post(&format!("/calls/{}/actions/gather_using_speak", call_control_id), &request)
+            .await?;
+        Ok(())
+    }
+
+    /// Greet an answered call
+    pub async fn greet(&self, call_control_id: &str, greeting: Greeting<'_>) -> Result<(), TelnyxError> {
+        match greeting {
+            Greeting::Audio(url) => self.play_audio(call_control_id, url).await,
+            Greeting::Speech(text) => self.speak(call_control_id, text, Some("female")).await,
+        }
+    }
+
     /// Play audio file on the call
     pub async fn play_audio(&self, call_control_id: &str, audio_url: &str) -> Result<(), TelnyxError> {
-        let request = PlayAudioRequest { audio_url };
+        let request = PlayAudioRequest { audio_url, loop_count: None };
 
         let _: TelnyxResponse<serde_json::Value> = self
             .post(&format!("/calls/{}/actions/playback_start", call_control_id), &request)
@@ -163,11 +280,11 @@ impl TelnyxClient {
     pub async fn start_recording(
         &self,
         call_control_id: &str,
-        channels: &str,
+        settings: RecordingSettings,
     ) -> Result<(), TelnyxError> {
         let request = RecordingRequest {
-            channels,
-            format: "mp3",
+            channels: settings.channels.telnyx_value(),
+            format: settings.format.telnyx_value(),
         };
 
         let _: TelnyxResponse<serde_json::Value> = self
@@ -176,6 +293,32 @@ impl TelnyxClient {
         Ok(())
     }
 
+    /// Pause call recording; the paused stretch is left out of the file
+    pub async fn pause_recording(&self, call_control_id: &str) -> Result<(), TelnyxError> {
+        let request = CallControlRequest {
+            client_state: None,
+            command_id: None,
+        };
+
+        let _: TelnyxResponse<serde_json::Value> = self
+            .post(&format!("/calls/{}/actions/record_pause", call_control_id), &request)
+            .await?;
+        Ok(())
+    }
+
+    /// Resume a paused call recording
+    pub async fn resume_recording(&self, call_control_id: &str) -> Result<(), TelnyxError> {
+        let request = CallControlRequest {
+            client_state: None,
+            command_id: None,
+        };
+
+        let _: TelnyxResponse<serde_json::Value> = self
+            .post(&format!("/calls/{}/actions/record_resume", call_control_id), &request)
+            .await?;
+        Ok(())
+    }
+
     /// Stop call recording
     pub async fn stop_recording(&self, call_control_id: &str) -> Result<(), TelnyxError> {
         let request = CallControlRequest {
@@ -189,6 +332,59 @@ impl TelnyxClient {
         Ok(())
     }
 
+    /// Create a telephony credential on a credential connection, returning its id
+    pub async fn create_telephony_credential(&self, connection_id: &str, name: &str) -> Result<String, TelnyxError> {
+        let request = CreateCredentialRequest { connection_id, name };
+        let response: TelnyxResponse<CredentialData> = self.post("/telephony_credentials", &request).await?;
+        Ok(response.data.id)
+    }
+
+    /// Create a login token for a telephony credential. The token stands in
+    /// for the credential's SIP password when the WebRTC SDK connects.
+    pub async fn create_login_token(&self, credential_id: &str) -> Result<String, TelnyxError> {
+        let response = self
+            .client
+            .post(format!("{}/telephony_credentials/{}/token", self.base_url, credential_id))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(TelnyxError::Api { message: error_text });
+        }
+
+        // The token comes back as plain text, not a JSON envelope
+        Ok(response.text().await?.trim().to_string())
+    }
+
+    /// Carrier, line type and caller name for a number, from Number Lookup
+    pub async fn lookup_number(&self, phone: &str) -> Result<NumberInfo, TelnyxError> {
+        let response = self
+            .client
+            .get(format!("{}/number_lookup/{}", self.base_url, phone.replace('+', "%2B")))
+            .query(&[("type", "carrier"), ("type", "caller-name")])
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(TelnyxError::Api { message: error_text });
+        }
+
+        let lookup: TelnyxResponse<NumberLookupData> = response.json().await?;
+        Ok(lookup.data.into())
+    }
+
+    /// Send an SMS. Returns the Telnyx message id.
+    pub async fn send_sms(&self, to: &str, from: &str, text: &str) -> Result<String, TelnyxError> {
+        let request = SendMessageRequest { to, from, text };
+
+        let response: TelnyxResponse<MessageData> = self.post("/messages", &request).await?;
+        Ok(response.data.id)
+    }
+
     /// Put call on hold (mute and play hold music)
     pub async fn hold(&self, call_control_id: &str, audio_url: Option<&str>) -> Result<(), TelnyxError> {
         // Mute the call
@@ -197,9 +393,12 @@ impl TelnyxClient {
             .post(&format!("/calls/{}/actions/mute", call_control_id), &mute_request)
             .await?;
 
-        // Play hold music if URL provided
+        // Play hold music if URL provided, looping until the call is taken off hold
         if let Some(url) = audio_url {
-            self.play_audio(call_control_id, url).await?;
+            let request = PlayAudioRequest::looped(url);
+            let _: TelnyxResponse<serde_json::Value> = self
+                .post(&format!("/calls/{}/actions/playback_start", call_control_id), &request)
+                .await?;
         }
 
         Ok(())
@@ -207,6 +406,11 @@ impl TelnyxClient {
 
     /// Resume call from hold
     pub async fn unhold(&self, call_control_id: &str) -> Result<(), TelnyxError> {
+        // Stop any looping hold music; there may be none playing, so ignore failures
+        let _: Result<TelnyxResponse<serde_json::Value>, _> = self
+            .post(&format!("/calls/{}/actions/playback_stop", call_control_id), &serde_json::json!({}))
+            .await;
+
         let request = MuteRequest { mute: false };
 
         let _: TelnyxResponse<serde_json::Value> = self
@@ -265,7 +469,62 @@ struct DialRequest<'a> {
     connection_id: &'a str,
     webhook_url: &'a str,
     webhook_url_method: &'a str,
-    answer_machine_detection: Option<&'a str>,
+    answering_machine_detection: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client_state: Option<String>,
+}
+
+impl<'a> DialRequest<'a> {
+    fn new(
+        to: &'a str,
+        from: &'a str,
+        connection_id: &'a str,
+        webhook_url: Option<&'a str>,
+        amd_mode: AmdMode,
+    ) -> Self {
+        Self {
+            to,
+            from,
+            connection_id,
+            webhook_url: webhook_url.unwrap_or(""),
+            webhook_url_method: "POST",
+            answering_machine_detection: amd_mode.telnyx_value(),
+            client_state: None,
+        }
+    }
+}
+
+/// Our call context, carried through Telnyx as base64 JSON in `client_state`
+/// and echoed back on every webhook for the call
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientState {
+    pub call_id: i64,
+    pub agent_id: Option<i64>,
+    pub campaign_id: Option<i64>,
+    /// Set on the second leg dialed to the agent, which is bridged to the lead's leg
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub agent_leg: bool,
+}
+
+impl ClientState {
+    pub fn encode(&self) -> String {
+        STANDARD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    /// None for anything we didn't encode, e.g. state set by another application
+    pub fn decode(value: &str) -> Option<Self> {
+        let bytes = STANDARD.decode(value.trim()).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+}
+
+/// How the call a webhook refers to is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallLookup<'a> {
+    /// Our call id, decoded from `client_state`
+    ById(i64),
+    /// Fallback when the event carries no usable `client_state`
+    ByControlId(&'a str),
 }
 
 #[derive(Serialize)]
@@ -284,6 +543,116 @@ struct BridgeRequest<'a> {
     call_control_id: &'a str,
 }
 
+#[derive(Serialize)]
+struct CreateConferenceRequest<'a> {
+    call_control_id: &'a str,
+    name: &'a str,
+    beep_enabled: &'a str,
+}
+
+#[derive(Serialize)]
+struct CreateCredentialRequest<'a> {
+    connection_id: &'a str,
+    name: &'a str,
+}
+
+/// Supervisor participation modes supported by Telnyx conferences
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SupervisorMode<'a> {
+    /// Muted, hears everyone
+    Monitor,
+    /// Audible only to the given call legs
+    Whisper(&'a str),
+    /// Audible to everyone
+    Barge,
+}
+
+#[derive(Serialize)]
+struct JoinConferenceRequest<'a> {
+    call_control_id: &'a str,
+    mute: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    supervisor_role: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    whisper_call_control_ids: Option<Vec<&'a str>>,
+}
+
+impl<'a> JoinConferenceRequest<'a> {
+    fn supervisor(call_control_id: &'a str, mode: SupervisorMode<'a>) -> Self {
+        let (mute, supervisor_role, whisper_call_control_ids) = match mode {
+            SupervisorMode::Monitor => (true, "monitor", None),
+            SupervisorMode::Whisper(target) => (false, "whisper", Some(vec![target])),
+            SupervisorMode::Barge => (false, "barge", None),
+        };
+
+        Self {
+            call_control_id,
+            mute,
+            supervisor_role: Some(supervisor_role),
+            whisper_call_control_ids,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct LeaveConferenceRequest<'a> {
+    call_control_id: &'a str,
+}
+
+#[derive(Serialize)]
+struct SendMessageRequest<'a> {
+    to: &'a str,
+    from: &'a str,
+    text: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageData {
+    id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct NumberLookupData {
+    #[serde(default)]
+    carrier: Option<LookupCarrier>,
+    #[serde(default)]
+    caller_name: Option<LookupCallerName>,
+    #[serde(default)]
+    portability: Option<LookupPortability>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupCarrier {
+    name: Option<String>,
+    #[serde(rename = "type")]
+    line_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupCallerName {
+    caller_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LookupPortability {
+    line_type: Option<String>,
+}
+
+impl From<NumberLookupData> for NumberInfo {
+    fn from(data: NumberLookupData) -> Self {
+        let non_empty = |s: Option<String>| s.filter(|s| !s.trim().is_empty());
+        let (carrier, carrier_type) = match data.carrier {
+            Some(c) => (non_empty(c.name), non_empty(c.line_type)),
+            None => (None, None),
+        };
+        NumberInfo {
+            carrier,
+            line_type: carrier_type.or_else(|| non_empty(data.portability.and_then(|p| p.line_type))),
+            caller_name: non_empty(data.caller_name.and_then(|c| c.caller_name)),
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct SpeakRequest<'a> {
     payload: &'a str,
@@ -291,15 +660,67 @@ struct SpeakRequest<'a> {
     language: &'a str,
 }
 
+#[derive(Serialize)]
+struct GatherUsingSpeakRequest<'a> {
+    payload: &'a str,
+    voice: &'a str,
+    language: &'a str,
+    minimum_digits: u32,
+    maximum_digits: u32,
+    timeout_millis: u64,
+}
+
 #[derive(Serialize)]
 struct PlayAudioRequest<'a> {
     audio_url: &'a str,
+    #[serde(rename = "loop", skip_serializing_if = "Option::is_none")]
+    loop_count: Option<&'a str>,
+}
+
+impl<'a> PlayAudioRequest<'a> {
+    /// Repeat the audio until playback is stopped
+    fn looped(audio_url: &'a str) -> Self {
+        Self {
+            audio_url,
+            loop_count: Some("infinity"),
+        }
+    }
+}
+
+/// Pick the hold music for a call: the campaign's own track wins over the global default
+pub fn resolve_hold_music<'a>(campaign_url: Option<&'a str>, default_url: Option<&'a str>) -> Option<&'a str> {
+    campaign_url
+        .filter(|url| !url.trim().is_empty())
+        .or(default_url.filter(|url| !url.trim().is_empty()))
+}
+
+/// What a caller hears when their call is answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Greeting<'a> {
+    /// Play a recorded greeting
+    Audio(&'a str),
+    /// Speak text-to-speech
+    Speech(&'a str),
+}
+
+impl<'a> Greeting<'a> {
+    /// The campaign's greeting when it sets one, then the agent's recorded
+    /// greeting, otherwise `text` spoken
+    pub fn for_call(campaign_text: Option<&'a str>, agent_audio_url: Option<&'a str>, text: &'a str) -> Self {
+        if let Some(campaign_text) = campaign_text.filter(|t| !t.trim().is_empty()) {
+            return Greeting::Speech(campaign_text);
+        }
+        match agent_audio_url.filter(|url| !url.trim().is_empty()) {
+            Some(url) => Greeting::Audio(url),
+            None => Greeting::Speech(text),
+        }
+    }
 }
 
 #[derive(Serialize)]
-struct RecordingRequest<'a> {
-    channels: &'a str,
-    format: &'a str,
+struct RecordingRequest {
+    channels: &'static str,
+    format: &'static str,
 }
 
 #[derive(Serialize)]
@@ -330,6 +751,16 @@ struct DialData {
     call_session_id: String,
 }
 
+#[derive(Deserialize)]
+struct ConferenceData {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct CredentialData {
+    id: String,
+}
+
 #[derive(Debug)]
 pub struct DialResponse {
     pub call_control_id: String,
@@ -345,6 +776,9 @@ pub struct TelnyxWebhookEvent {
 
 #[derive(Debug, Deserialize)]
 pub struct WebhookData {
+    /// Unique event id, stable across Telnyx retries
+    #[serde(default)]
+    pub id: Option<String>,
     pub event_type: String,
     pub payload: WebhookPayload,
 }
@@ -354,12 +788,49 @@ pub struct WebhookPayload {
     pub call_control_id: Option<String>,
     pub call_leg_id: Option<String>,
     pub call_session_id: Option<String>,
+    #[serde(default, deserialize_with = "phone_number_field")]
     pub from: Option<String>,
+    #[serde(default, deserialize_with = "phone_number_field")]
     pub to: Option<String>,
     pub state: Option<String>,
+    /// "incoming" or "outgoing"
+    pub direction: Option<String>,
     pub client_state: Option<String>,
     pub recording_url: Option<String>,
     pub result: Option<String>,
+    /// Message body (message.* events)
+    pub text: Option<String>,
+    /// Keys pressed (call.gather.ended)
+    pub digits: Option<String>,
+    /// How a gather ended: "valid", "invalid", "timeout", "call_hangup" or "cancelled"
+    pub status: Option<String>,
+}
+
+/// Call events carry numbers as plain strings, while message events use
+/// `{"phone_number": ...}` objects (and a list of them for `to`).
+fn phone_number_field<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct PhoneNumber {
+        phone_number: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Field {
+        Plain(String),
+        Object(PhoneNumber),
+        List(Vec<PhoneNumber>),
+    }
+
+    Ok(match Option::<Field>::deserialize(deserializer)? {
+        Some(Field::Plain(number)) => Some(number),
+        Some(Field::Object(p)) => Some(p.phone_number),
+        Some(Field::List(list)) => list.into_iter().next().map(|p| p.phone_number),
+        None => None,
+    })
 }
 
 impl TelnyxWebhookEvent {
@@ -370,4 +841,396 @@ impl TelnyxWebhookEvent {
     pub fn call_control_id(&self) -> Option<&str> {
         self.data.payload.call_control_id.as_deref()
     }
+
+    pub fn event_id(&self) -> Option<&str> {
+        self.data.id.as_deref()
+    }
+
+    pub fn client_state(&self) -> Option<ClientState> {
+        self.data.payload.client_state.as_deref().and_then(ClientState::decode)
+    }
+
+    /// Prefer the call id from `client_state`, falling back to the call control id
+    pub fn call_lookup(&self) -> Option<CallLookup<'_>> {
+        match self.client_state() {
+            Some(state) => Some(CallLookup::ById(state.call_id)),
+            None => self.call_control_id().map(CallLookup::ByControlId),
+        }
+    }
+}
+
+/// Stand-in for the Telnyx API, shared by tests that check outgoing requests
+#[cfg(test)]
+pub(crate) mod stub {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers one request with an empty Telnyx response and returns what it received
+    pub async fn stub_telnyx() -> (String, tokio::task::JoinHandle<(String, serde_json::Value)>) {
+        stub_http("application/json", r#"{"data":{}}"#).await
+    }
+
+    /// Answers one request with `reply` and returns the request line and JSON
+    /// body it received, Null for a request without one
+    pub async fn stub_http(
+        content_type: &'static str,
+        reply: &'static str,
+    ) -> (String, tokio::task::JoinHandle<(String, serde_json::Value)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut received = Vec::new();
+            let mut buf = [0u8; 4096];
+            let (head, body) = loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                received.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&received).to_string();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length = head
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap()))
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break (head.to_string(), body.to_string());
+                    }
+                }
+            };
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                content_type,
+                reply.len(),
+                reply
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+
+            let request_line = head.lines().next().unwrap_or_default().to_string();
+            // GET requests have no body; any body that is sent must be JSON
+            let body = if body.is_empty() { serde_json::Value::Null } else { serde_json::from_str(&body).unwrap() };
+            (request_line, body)
+        });
+
+        (url, handle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RecordingChannels, RecordingFormat};
+    use stub::stub_telnyx;
+
+    #[tokio::test]
+    async fn test_recorded_greeting_is_played() {
+        let (url, request) = stub_telnyx().await;
+        let client = TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+        let greeting = Greeting::for_call(None, Some("https://cdn.example.com/maria.mp3"), "Hello");
+        client.greet("v3:lead", greeting).await.unwrap();
+
+        let (request_line, body) = request.await.unwrap();
+        assert_eq!(request_line, "POST /calls/v3:lead/actions/playback_start HTTP/1.1");
+        assert_eq!(body["audio_url"], "https://cdn.example.com/maria.mp3");
+    }
+
+    #[tokio::test]
+    async fn test_recording_request_uses_campaign_settings() {
+        let (url, request) = stub_telnyx().await;
+        let client = TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+        let settings = RecordingSettings {
+            channels: RecordingChannels::Single,
+            format: RecordingFormat::Wav,
+        };
+        client.start_recording("v3:lead", settings).await.unwrap();
+
+        let (request_line, body) = request.await.unwrap();
+        assert_eq!(request_line, "POST /calls/v3:lead/actions/record_start HTTP/1.1");
+        assert_eq!(body, serde_json::json!({ "channels": "single", "format": "wav" }));
+    }
+
+    #[tokio::test]
+    async fn test_recording_defaults_to_dual_channel_mp3() {
+        let (url, request) = stub_telnyx().await;
+        let client = TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+        client.start_recording("v3:lead", RecordingSettings::default()).await.unwrap();
+
+        let (_, body) = request.await.unwrap();
+        assert_eq!(body, serde_json::json!({ "channels": "dual", "format": "mp3" }));
+    }
+
+    #[tokio::test]
+    async fn test_greeting_without_recording_is_spoken() {
+        let (url, request) = stub_telnyx().await;
+        let client = TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+        client.greet("v3:lead", Greeting::for_call(None, None, "Hello")).await.unwrap();
+
+        let (request_line, body) = request.await.unwrap();
+        assert_eq!(request_line, "POST /calls/v3:lead/actions/speak HTTP/1.1");
+        assert_eq!(body["payload"], "Hello");
+    }
+
+    #[test]
+    fn test_campaign_greeting_beats_agent_recording() {
+        let recording = Some("https://cdn.example.com/maria.mp3");
+        assert_eq!(Greeting::for_call(Some("Hi from Spring"), recording, "Hello"), Greeting::Speech("Hi from Spring"));
+        assert_eq!(Greeting::for_call(Some("  "), recording, "Hello"), Greeting::Audio("https://cdn.example.com/maria.mp3"));
+    }
+
+    #[tokio::test]
+    async fn test_create_telephony_credential() {
+        let (url, request) = stub::stub_http("application/json", r#"{"data":{"id":"cred-7","sip_username":"gencred7"}}"#).await;
+        let client = TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+
+        let id = client.create_telephony_credential("conn-webrtc", "crm-agent-7").await.unwrap();
+        assert_eq!(id, "cred-7");
+
+        let (request_line, body) = request.await.unwrap();
+        assert_eq!(request_line, "POST /telephony_credentials HTTP/1.1");
+        assert_eq!(body["connection_id"], "conn-webrtc");
+        assert_eq!(body["name"], "crm-agent-7");
+    }
+
+    #[tokio::test]
+    async fn test_lookup_number_request_and_response() {
+        let reply = r#"{"data":{
+            "phone_number":"+15551234567",
+            "carrier":{"name":"T-Mobile USA, Inc.","type":"mobile","mobile_network_code":"260"},
+            "caller_name":{"caller_name":"SMITH,JOHN","error_code":null},
+            "portability":{"line_type":"wireless","ported_status":"Y"}
+        }}"#;
+        let (url, request) = stub::stub_http("application/json", reply).await;
+        let client = TelnyxClient::new("key".into(), "conn".into()).with_base_url(url);
+
+        let info = client.lookup_number("+15551234567").await.unwrap();
+
+        assert_eq!(info.carrier.as_deref(), Some("T-Mobile USA, Inc."));
+        assert_eq!(info.line_type.as_deref(), Some("mobile"));
+        assert_eq!(info.caller_name.as_deref(), Some("SMITH,JOHN"));
+
+        let (request_line, _) = request.await.unwrap();
+        assert_eq!(
+            request_line,
+            "GET /number_lookup/%2B15551234567?type=carrier&type=caller-name HTTP/1.1"
+        );
+    }
+
+    #[test]
+    fn test_lookup_falls_back_to_portability_line_type() {
+        let data: NumberLookupData = serde_json::from_str(
+            r#"{"carrier":{"name":"","type":null},"caller_name":{"caller_name":""},"portability":{"line_type":"landline"}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            NumberInfo::from(data),
+            NumberInfo { carrier: None, line_type: Some("landline".into()), caller_name: None }
+        );
+    }
+
+    #[test]
+    fn test_create_conference_request_body() {
+        let request = CreateConferenceRequest {
+            call_control_id: "v3:lead-leg",
+            name: "call-42",
+            beep_enabled: "never",
+        };
+
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(body["call_control_id"], "v3:lead-leg");
+        assert_eq!(body["name"], "call-42");
+        assert_eq!(body["beep_enabled"], "never");
+    }
+
+    #[test]
+    fn test_join_conference_request_body() {
+        let request = JoinConferenceRequest {
+            call_control_id: "v3:supervisor-leg",
+            mute: true,
+            supervisor_role: None,
+            whisper_call_control_ids: None,
+        };
+
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(body["call_control_id"], "v3:supervisor-leg");
+        assert_eq!(body["mute"], true);
+        assert!(body.get("supervisor_role").is_none());
+    }
+
+    #[test]
+    fn test_supervisor_monitor_mode_is_muted() {
+        let request = JoinConferenceRequest::supervisor("v3:sup", SupervisorMode::Monitor);
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["mute"], true);
+        assert_eq!(body["supervisor_role"], "monitor");
+        assert!(body.get("whisper_call_control_ids").is_none());
+    }
+
+    #[test]
+    fn test_supervisor_whisper_targets_agent_only() {
+        let request = JoinConferenceRequest::supervisor("v3:sup", SupervisorMode::Whisper("v3:agent"));
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["mute"], false);
+        assert_eq!(body["supervisor_role"], "whisper");
+        assert_eq!(body["whisper_call_control_ids"], serde_json::json!(["v3:agent"]));
+    }
+
+    #[test]
+    fn test_supervisor_barge_is_audible_to_all() {
+        let request = JoinConferenceRequest::supervisor("v3:sup", SupervisorMode::Barge);
+        let body = serde_json::to_value(&request).unwrap();
+
+        assert_eq!(body["mute"], false);
+        assert_eq!(body["supervisor_role"], "barge");
+        assert!(body.get("whisper_call_control_ids").is_none());
+    }
+
+    #[test]
+    fn test_conference_response_parsing() {
+        let json = r#"{"data": {"id": "conf-123", "name": "call-42", "record_type": "conference"}}"#;
+        let response: TelnyxResponse<ConferenceData> = serde_json::from_str(json).unwrap();
+        assert_eq!(response.data.id, "conf-123");
+    }
+
+    #[test]
+    fn test_client_state_round_trip() {
+        let state = ClientState {
+            call_id: 42,
+            agent_id: Some(7),
+            campaign_id: None,
+            agent_leg: false,
+        };
+        let encoded = state.encode();
+
+        assert_eq!(
+            STANDARD.decode(&encoded).unwrap(),
+            br#"{"call_id":42,"agent_id":7,"campaign_id":null}"#
+        );
+        assert_eq!(ClientState::decode(&encoded), Some(state));
+        assert_eq!(ClientState::decode("not base64!"), None);
+        assert_eq!(ClientState::decode(&STANDARD.encode("plain text")), None);
+
+        let agent_leg = ClientState { agent_leg: true, ..state };
+        assert_eq!(ClientState::decode(&agent_leg.encode()), Some(agent_leg));
+    }
+
+    #[test]
+    fn test_dial_request_carries_client_state() {
+        let mut request = DialRequest::new("+15551234567", "+15557654321", "conn", None, AmdMode::Disabled);
+        assert!(serde_json::to_value(&request).unwrap().get("client_state").is_none());
+
+        let state = ClientState { call_id: 42, agent_id: None, campaign_id: Some(3), agent_leg: false };
+        request.client_state = Some(state.encode());
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(ClientState::decode(body["client_state"].as_str().unwrap()), Some(state));
+    }
+
+    #[test]
+    fn test_webhook_resolves_call_from_client_state() {
+        let state = ClientState { call_id: 42, agent_id: Some(7), campaign_id: Some(3), agent_leg: false }.encode();
+        let json = format!(
+            r#"{{"data": {{"event_type": "call.answered", "payload": {{"call_control_id": "v3:abc", "client_state": "{}"}}}}}}"#,
+            state
+        );
+        let event: TelnyxWebhookEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(event.call_lookup(), Some(CallLookup::ById(42)));
+
+        // Undecodable state falls back to the control id
+        let json = r#"{"data": {"event_type": "call.answered", "payload": {"call_control_id": "v3:abc", "client_state": "garbage"}}}"#;
+        let event: TelnyxWebhookEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(event.call_lookup(), Some(CallLookup::ByControlId("v3:abc")));
+    }
+
+    #[test]
+    fn test_webhook_event_id_parsing() {
+        let json = r#"{"data": {"id": "0ccc7b54-4df3-4bca-a65a-3da1ecc777f0", "event_type": "call.hangup", "payload": {"call_control_id": "v3:abc"}}}"#;
+        let event: TelnyxWebhookEvent = serde_json::from_str(json).unwrap();
+        assert_eq!(event.event_id(), Some("0ccc7b54-4df3-4bca-a65a-3da1ecc777f0"));
+        assert_eq!(event.call_control_id(), Some("v3:abc"));
+
+        let without_id = r#"{"data": {"event_type": "call.hangup", "payload": {}}}"#;
+        let event: TelnyxWebhookEvent = serde_json::from_str(without_id).unwrap();
+        assert_eq!(event.event_id(), None);
+    }
+
+    #[test]
+    fn test_hold_music_request_loops() {
+        let body = serde_json::to_value(PlayAudioRequest::looped("https://cdn.example.com/hold.mp3")).unwrap();
+        assert_eq!(body["audio_url"], "https://cdn.example.com/hold.mp3");
+        assert_eq!(body["loop"], "infinity");
+
+        let once = serde_json::to_value(PlayAudioRequest { audio_url: "https://cdn.example.com/a.mp3", loop_count: None }).unwrap();
+        assert!(once.get("loop").is_none());
+    }
+
+    #[test]
+    fn test_resolve_hold_music() {
+        let global = Some("https://cdn.example.com/default.mp3");
+        let campaign = Some("https://cdn.example.com/campaign.mp3");
+
+        assert_eq!(resolve_hold_music(None, global), global);
+        assert_eq!(resolve_hold_music(campaign, global), campaign);
+        assert_eq!(resolve_hold_music(Some(""), global), global);
+        assert_eq!(resolve_hold_music(None, None), None);
+    }
+
+    #[test]
+    fn test_send_message_request_body() {
+        let request = SendMessageRequest {
+            to: "+15551234567",
+            from: "+15557654321",
+            text: "Thanks for your time today!",
+        };
+        let body = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "to": "+15551234567",
+                "from": "+15557654321",
+                "text": "Thanks for your time today!"
+            })
+        );
+    }
+
+    #[test]
+    fn test_inbound_message_parsing() {
+        let json = r#"{"data": {"id": "evt-1", "event_type": "message.received", "payload": {
+            "id": "msg-1",
+            "from": {"phone_number": "+15551234567", "carrier": "T-Mobile"},
+            "to": [{"phone_number": "+15557654321", "status": "webhook_delivered"}],
+            "text": "Call me back please"
+        }}}"#;
+        let event: TelnyxWebhookEvent = serde_json::from_str(json).unwrap();
+
+        assert_eq!(event.event_type(), "message.received");
+        assert_eq!(event.call_control_id(), None);
+        assert_eq!(event.data.payload.from.as_deref(), Some("+15551234567"));
+        assert_eq!(event.data.payload.to.as_deref(), Some("+15557654321"));
+        assert_eq!(event.data.payload.text.as_deref(), Some("Call me back please"));
+
+        // Call events keep plain string numbers
+        let call = r#"{"data": {"event_type": "call.initiated", "payload": {"from": "+15551234567", "to": "+15557654321"}}}"#;
+        let event: TelnyxWebhookEvent = serde_json::from_str(call).unwrap();
+        assert_eq!(event.data.payload.from.as_deref(), Some("+15551234567"));
+    }
+
+    #[test]
+    fn test_dial_request_amd_mode() {
+        let cases = [
+            (AmdMode::Disabled, "disabled"),
+            (AmdMode::Detect, "detect"),
+            (AmdMode::DetectBeep, "detect_beep"),
+            (AmdMode::GreetingEnd, "greeting_end"),
+        ];
+
+        for (mode, expected) in cases {
+            let request = DialRequest::new("+15551234567", "+15557654321", "conn-1", Some("https://example.com/hook"), mode);
+            let body = serde_json::to_value(&request).unwrap();
+            assert_eq!(body["answering_machine_detection"], expected);
+            assert_eq!(body["to"], "+15551234567");
+            assert_eq!(body["webhook_url"], "https://example.com/hook");
+        }
+    }
 }
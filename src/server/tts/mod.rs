@@ -0,0 +1,294 @@
+//! Text-to-speech providers
+//!
+//! Telnyx's `speak` action only offers a handful of voices. When a
+//! higher-quality provider is configured, AI call responses are synthesized
+//! here, held briefly in memory and played with `playback_start` from a
+//! one-off URL Telnyx fetches from `/api/tts/{token}`. Without a provider
+//! calls keep using Telnyx speak.
+//!
+//! - TTS_PROVIDER: telnyx (default) or elevenlabs
+//! - ELEVENLABS_API_KEY: required for elevenlabs
+//! - ELEVENLABS_VOICE_ID: voice used when the agent's voice is a Telnyx one
+//! - ELEVENLABS_MODEL_ID: defaults to eleven_flash_v2_5
+//! - TTS_AUDIO_BASE_URL: public origin Telnyx fetches clips from; defaults
+//!   to the origin of WEBHOOK_URL
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use async_trait::async_trait;
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use reqwest::Client;
+use serde::Serialize;
+use thiserror::Error;
+use tokio::sync::RwLock;
+
+use super::AppState;
+
+/// How long a synthesized clip stays fetchable
+const CLIP_TTL: Duration = Duration::from_secs(10 * 60);
+
+const DEFAULT_ELEVENLABS_MODEL: &str = "eleven_flash_v2_5";
+/// ElevenLabs' stock "Rachel" voice
+const DEFAULT_ELEVENLABS_VOICE: &str = "21m00Tcm4TlvDq8ikWAM";
+
+#[derive(Debug, Error)]
+pub enum TtsError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("API error ({status}): {message}")]
+    Api { status: u16, message: String },
+}
+
+/// A speech synthesis service returning MP3 audio
+#[async_trait]
+pub trait TextToSpeech: Send + Sync {
+    fn name(&self) -> &'static str;
+    async fn synthesize(&self, text: &str, voice: &str, language: &str) -> Result<Vec<u8>, TtsError>;
+}
+
+/// ElevenLabs text-to-speech
+pub struct ElevenLabsTts {
+    client: Client,
+    api_key: String,
+    model_id: String,
+    base_url: String,
+}
+
+#[derive(Serialize)]
+struct ElevenLabsRequest<'a> {
+    text: &'a str,
+    model_id: &'a str,
+    language_code: &'a str,
+}
+
+impl ElevenLabsTts {
+    pub fn new(api_key: String, model_id: String) -> Self {
+        Self {
+            client: Client::new(),
+            api_key,
+            model_id,
+            base_url: "https://api.elevenlabs.io".to_string(),
+        }
+    }
+
+    /// Send requests to another API host, e.g. a local stub in tests
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+}
+
+#[async_trait]
+impl TextToSpeech for ElevenLabsTts {
+    fn name(&self) -> &'static str {
+        "elevenlabs"
+    }
+
+    async fn synthesize(&self, text: &str, voice: &str, language: &str) -> Result<Vec<u8>, TtsError> {
+        // ElevenLabs takes the bare ISO 639-1 code ("en" rather than "en-US")
+        let language_code = language.split(['-', '_']).next().unwrap_or(language);
+        let request = ElevenLabsRequest {
+            text,
+            model_id: &self.model_id,
+            language_code,
+        };
+
+        let response = self
+            .client
+            .post(format!("{}/v1/text-to-speech/{}", self.base_url, voice))
+            .query(&[("output_format", "mp3_22050_32")])
+            .header("xi-api-key", &self.api_key)
+            .header("Accept", "audio/mpeg")
+            .json(&request)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(TtsError::Api { status: status.as_u16(), message });
+        }
+
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// Which provider to use, read from the environment
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TtsConfig {
+    pub provider: String,
+    pub elevenlabs_api_key: Option<String>,
+    pub elevenlabs_voice_id: Option<String>,
+    pub elevenlabs_model_id: Option<String>,
+    pub audio_base_url: Option<String>,
+}
+
+impl TtsConfig {
+    pub fn from_env() -> Self {
+        let var = |name: &str| std::env::var(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+
+        Self {
+            provider: var("TTS_PROVIDER").unwrap_or_default().to_lowercase(),
+            elevenlabs_api_key: var("ELEVENLABS_API_KEY"),
+            elevenlabs_voice_id: var("ELEVENLABS_VOICE_ID"),
+            elevenlabs_model_id: var("ELEVENLABS_MODEL_ID"),
+            audio_base_url: var("TTS_AUDIO_BASE_URL").or_else(|| var("WEBHOOK_URL").and_then(|url| origin_of(&url))),
+        }
+    }
+
+    /// Build the configured player, or None to keep using Telnyx speak
+    pub fn player(&self) -> Option<TtsPlayer> {
+        let provider: Arc<dyn TextToSpeech> = match self.provider.as_str() {
+            "" | "telnyx" => return None,
+            "elevenlabs" => {
+                let Some(api_key) = self.elevenlabs_api_key.clone() else {
+                    tracing::warn!("TTS_PROVIDER is elevenlabs but ELEVENLABS_API_KEY is not set, using Telnyx speak");
+                    return None;
+                };
+                let model_id = self.elevenlabs_model_id.clone().unwrap_or_else(|| DEFAULT_ELEVENLABS_MODEL.to_string());
+                Arc::new(ElevenLabsTts::new(api_key, model_id))
+            }
+            other => {
+                tracing::warn!("Unknown TTS_PROVIDER '{}', using Telnyx speak", other);
+                return None;
+            }
+        };
+
+        let Some(audio_base_url) = self.audio_base_url.clone() else {
+            tracing::warn!("No public URL for TTS audio (set TTS_AUDIO_BASE_URL or WEBHOOK_URL), using Telnyx speak");
+            return None;
+        };
+
+        let default_voice = self.elevenlabs_voice_id.clone().unwrap_or_else(|| DEFAULT_ELEVENLABS_VOICE.to_string());
+        Some(TtsPlayer::new(provider, audio_base_url, default_voice))
+    }
+}
+
+/// Scheme and host of a URL, without its path
+fn origin_of(url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(url).ok()?;
+    let origin = url.origin();
+    origin.is_tuple().then(|| origin.ascii_serialization())
+}
+
+/// Whether a voice name is one of Telnyx's own rather than a provider voice id
+fn is_telnyx_voice(voice: &str) -> bool {
+    matches!(voice, "female" | "male") || voice.contains('.')
+}
+
+/// Synthesized clips waiting for Telnyx to fetch them
+#[derive(Default)]
+pub struct ClipStore {
+    clips: RwLock<HashMap<String, (Instant, Vec<u8>)>>,
+}
+
+impl ClipStore {
+    /// Store a clip and return the token it is served under
+    pub async fn insert(&self, audio: Vec<u8>) -> String {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        let mut clips = self.clips.write().await;
+        clips.retain(|_, (stored_at, _)| stored_at.elapsed() < CLIP_TTL);
+        clips.insert(token.clone(), (Instant::now(), audio));
+        token
+    }
+
+    pub async fn get(&self, token: &str) -> Option<Vec<u8>> {
+        let clips = self.clips.read().await;
+        clips
+            .get(token)
+            .filter(|(stored_at, _)| stored_at.elapsed() < CLIP_TTL)
+            .map(|(_, audio)| audio.clone())
+    }
+}
+
+/// Synthesizes speech with a provider and hands out URLs for the audio
+pub struct TtsPlayer {
+    provider: Arc<dyn TextToSpeech>,
+    clips: ClipStore,
+    audio_base_url: String,
+    default_voice: String,
+}
+
+impl TtsPlayer {
+    pub fn new(provider: Arc<dyn TextToSpeech>, audio_base_url: String, default_voice: String) -> Self {
+        Self {
+            provider,
+            clips: ClipStore::default(),
+            audio_base_url: audio_base_url.trim_end_matches('/').to_string(),
+            default_voice,
+        }
+    }
+
+    pub fn provider_name(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// Synthesize `text` and return the URL Telnyx should play
+    pub async fn prepare(&self, text: &str, voice: &str, language: &str) -> Result<String, TtsError> {
+        let voice = if is_telnyx_voice(voice) { &self.default_voice } else { voice };
+        let audio = self.provider.synthesize(text, voice, language).await?;
+        let token = self.clips.insert(audio).await;
+        Ok(format!("{}/api/tts/{}", self.audio_base_url, token))
+    }
+
+    pub async fn clip(&self, token: &str) -> Option<Vec<u8>> {
+        self.clips.get(token).await
+    }
+}
+
+/// Serve a synthesized clip. Unauthenticated: Telnyx fetches it, and the
+/// token is random and short-lived.
+pub async fn serve_clip(State(state): State<Arc<AppState>>, Path(token): Path<String>) -> Response {
+    match state.ai_handler.tts_clip(&token).await {
+        Some(audio) => ([(header::CONTENT_TYPE, "audio/mpeg")], audio).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(provider: &str) -> TtsConfig {
+        TtsConfig {
+            provider: provider.to_string(),
+            elevenlabs_api_key: Some("xi-key".to_string()),
+            audio_base_url: Some("https://crm.example.com".to_string()),
+            ..TtsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_provider_selection() {
+        assert!(config("").player().is_none());
+        assert!(config("telnyx").player().is_none());
+        assert!(config("polly").player().is_none());
+        assert_eq!(config("elevenlabs").player().unwrap().provider_name(), "elevenlabs");
+
+        // Missing key or public URL falls back to Telnyx speak
+        assert!(TtsConfig { elevenlabs_api_key: None, ..config("elevenlabs") }.player().is_none());
+        assert!(TtsConfig { audio_base_url: None, ..config("elevenlabs") }.player().is_none());
+    }
+
+    #[test]
+    fn test_audio_base_url_from_webhook_url() {
+        assert_eq!(
+            origin_of("https://crm.example.com/api/webhooks/telnyx").as_deref(),
+            Some("https://crm.example.com")
+        );
+        assert_eq!(origin_of("http://localhost:3000/hook").as_deref(), Some("http://localhost:3000"));
+        assert_eq!(origin_of("not a url"), None);
+    }
+
+    #[test]
+    fn test_telnyx_voices_use_provider_default() {
+        assert!(is_telnyx_voice("female"));
+        assert!(is_telnyx_voice("Polly.Joanna"));
+        assert!(!is_telnyx_voice("21m00Tcm4TlvDq8ikWAM"));
+    }
+}
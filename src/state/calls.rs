@@ -1,5 +1,5 @@
 use dioxus::prelude::*;
-use crate::models::{Call, CallStatus, Lead};
+use crate::models::{Call, CallStatus, Lead, ScreenPop};
 
 /// Global call state
 pub static CALL_STATE: GlobalSignal<CallState> = Signal::global(CallState::default);
@@ -16,6 +16,10 @@ pub struct CallState {
     pub call_duration: u32,
     pub is_muted: bool,
     pub is_on_hold: bool,
+    /// SIP call followed through `/api/sip/events`
+    pub sip_call_id: Option<String>,
+    /// Who is calling, for an inbound call routed to this agent
+    pub screen_pop: Option<ScreenPop>,
 }
 
 impl CallState {
@@ -50,6 +54,8 @@ pub fn end_call() {
     state.call_duration = 0;
     state.is_muted = false;
     state.is_on_hold = false;
+    state.sip_call_id = None;
+    state.screen_pop = None;
 }
 
 pub fn toggle_mute() {
@@ -82,6 +88,57 @@ pub fn set_answered() {
     state.call_duration = 0;
 }
 
+/// An inbound call was routed to this agent: ring with the caller's details
+#[cfg(target_arch = "wasm32")]
+pub fn show_screen_pop(call_id: i64, from: String, screen_pop: ScreenPop) {
+    let mut state = CALL_STATE.write();
+    state.current_call_id = Some(call_id);
+    state.current_lead = screen_pop.lead.clone();
+    state.dialed_number = Some(from);
+    state.is_dialing = false;
+    state.is_ringing = true;
+    state.is_answered = false;
+    state.screen_pop = Some(screen_pop);
+}
+
+/// Apply a SIP call state pushed by the server. Only the first SIP call seen
+/// is followed until it ends, so events for other calls are ignored.
+#[cfg(target_arch = "wasm32")]
+pub fn apply_sip_call_state(call_id: &str, sip_state: &str) {
+    let mut state = CALL_STATE.write();
+    match state.sip_call_id.as_deref() {
+        Some(current) if current != call_id => return,
+        Some(_) => {}
+        None => {
+            if matches!(sip_state, "ended" | "failed") {
+                return;
+            }
+            state.sip_call_id = Some(call_id.to_string());
+        }
+    }
+
+    match sip_state {
+        "trying" => state.is_dialing = true,
+        "ringing" => {
+            state.is_dialing = false;
+            state.is_ringing = true;
+        }
+        "active" if !state.is_answered => {
+            state.is_dialing = false;
+            state.is_ringing = false;
+            state.is_answered = true;
+            state.call_duration = 0;
+        }
+        "held" => state.is_on_hold = true,
+        "active" => state.is_on_hold = false,
+        "ended" | "failed" => {
+            drop(state);
+            end_call();
+        }
+        _ => {}
+    }
+}
+
 #[cfg(target_arch = "wasm32")]
 pub fn increment_duration() {
     CALL_STATE.write().call_duration += 1;
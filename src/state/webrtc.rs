@@ -1,16 +1,117 @@
 //! WebRTC state management
+//!
+//! Only `WebRTCDialer` reads this, and the app mounts `SipDialer` instead, so
+//! outside the wasm setters and tests it is unused for now.
+#![allow(dead_code)]
 
 use dioxus::prelude::*;
 
 /// Global WebRTC state
 pub static WEBRTC_STATE: GlobalSignal<WebRTCState> = Signal::global(WebRTCState::default);
 
+/// WebRTC call state as reported by the Telnyx SDK
+#[derive(Debug, Clone, PartialEq)]
+pub enum WebRTCCallState {
+    New,
+    Trying,
+    Ringing,
+    Active,
+    Held,
+    Hangup,
+    Destroy,
+    Unknown(String),
+}
+
+impl From<&str> for WebRTCCallState {
+    fn from(s: &str) -> Self {
+        match s {
+            "new" => WebRTCCallState::New,
+            "trying" | "requesting" => WebRTCCallState::Trying,
+            "ringing" | "early" => WebRTCCallState::Ringing,
+            "active" => WebRTCCallState::Active,
+            "held" => WebRTCCallState::Held,
+            "hangup" => WebRTCCallState::Hangup,
+            "destroy" | "purge" => WebRTCCallState::Destroy,
+            _ => WebRTCCallState::Unknown(s.to_string()),
+        }
+    }
+}
+
+impl WebRTCCallState {
+    pub fn display_name(&self) -> &str {
+        match self {
+            WebRTCCallState::New => "Initiating...",
+            WebRTCCallState::Trying => "Dialing...",
+            WebRTCCallState::Ringing => "Ringing...",
+            WebRTCCallState::Active => "Connected",
+            WebRTCCallState::Held => "On Hold",
+            WebRTCCallState::Hangup => "Call Ended",
+            WebRTCCallState::Destroy => "Call Ended",
+            WebRTCCallState::Unknown(_) => "Unknown",
+        }
+    }
+
+    pub fn is_ringing(&self) -> bool {
+        matches!(self, WebRTCCallState::New | WebRTCCallState::Trying | WebRTCCallState::Ringing)
+    }
+
+    pub fn is_ended(&self) -> bool {
+        matches!(self, WebRTCCallState::Hangup | WebRTCCallState::Destroy)
+    }
+
+    /// Whether a call in this state may move to `next`
+    ///
+    /// The SDK can deliver updates late or twice (our own "trying" races its
+    /// "new"), so anything that would move a call backwards is rejected.
+    pub fn can_transition_to(&self, next: &WebRTCCallState) -> bool {
+        use WebRTCCallState::*;
+
+        match (self, next) {
+            (_, Unknown(_)) | (Unknown(_), _) => false,
+            (Hangup | Destroy, _) => false,
+            (_, Hangup | Destroy) => true,
+            (New, Trying | Ringing | Active) => true,
+            (Trying, Ringing | Active) => true,
+            (Ringing, Active) => true,
+            (Active, Held) | (Held, Active) => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Clone, Default)]
 pub struct WebRTCState {
     pub is_connecting: bool,
     pub is_connected: bool,
-    pub call_state: Option<String>,
+    pub call_state: Option<WebRTCCallState>,
     pub is_in_call: bool,
+    pub is_muted: bool,
+}
+
+impl WebRTCState {
+    /// Apply a call state update, returning false when it was ignored
+    ///
+    /// A call starts at new, trying or ringing (inbound). Hangup and destroy
+    /// end it and clear the call so the next update can start a fresh one.
+    pub fn apply_call_state(&mut self, next: WebRTCCallState) -> bool {
+        let allowed = match &self.call_state {
+            None => next.is_ringing(),
+            Some(current) => current.can_transition_to(&next),
+        };
+        if !allowed {
+            return false;
+        }
+
+        if next.is_ended() {
+            self.call_state = None;
+            self.is_in_call = false;
+            self.is_muted = false;
+        } else {
+            self.call_state = Some(next);
+            self.is_in_call = true;
+        }
+        true
+    }
 }
 
 // Functions only used in wasm32 builds
@@ -34,11 +135,19 @@ pub fn set_webrtc_error(_error: String) {
     state.is_connected = false;
 }
 
+/// Apply a call state reported by the SDK, returning false when it was ignored
 #[cfg(target_arch = "wasm32")]
-pub fn set_webrtc_call_state(call_state: String) {
-    let mut state = WEBRTC_STATE.write();
-    state.call_state = Some(call_state.clone());
-    state.is_in_call = matches!(call_state.as_str(), "active" | "ringing" | "trying" | "new" | "held");
+pub fn set_webrtc_call_state(call_state: &str) -> bool {
+    let applied = WEBRTC_STATE.write().apply_call_state(WebRTCCallState::from(call_state));
+    if !applied {
+        tracing::debug!("Ignoring out-of-order WebRTC call state: {}", call_state);
+    }
+    applied
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn set_webrtc_muted(muted: bool) {
+    WEBRTC_STATE.write().is_muted = muted;
 }
 
 #[cfg(target_arch = "wasm32")]
@@ -46,4 +155,76 @@ pub fn clear_webrtc_call() {
     let mut state = WEBRTC_STATE.write();
     state.call_state = None;
     state.is_in_call = false;
+    state.is_muted = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use WebRTCCallState::*;
+
+    fn apply_all(state: &mut WebRTCState, updates: &[&str]) -> Vec<bool> {
+        updates.iter().map(|s| state.apply_call_state(WebRTCCallState::from(*s))).collect()
+    }
+
+    #[test]
+    fn test_outbound_call_lifecycle() {
+        let mut state = WebRTCState::default();
+
+        assert_eq!(apply_all(&mut state, &["new", "trying", "ringing", "active"]), vec![true; 4]);
+        assert_eq!(state.call_state, Some(Active));
+        assert!(state.is_in_call);
+
+        assert!(state.apply_call_state(Held));
+        assert!(state.apply_call_state(Active));
+
+        state.is_muted = true;
+        assert!(state.apply_call_state(Hangup));
+        assert_eq!(state.call_state, None);
+        assert!(!state.is_in_call);
+        assert!(!state.is_muted);
+
+        // The SDK follows hangup with destroy; there is no call left to end
+        assert!(!state.apply_call_state(Destroy));
+    }
+
+    #[test]
+    fn test_inbound_call_starts_ringing() {
+        let mut state = WebRTCState::default();
+        assert!(state.apply_call_state(Ringing));
+        assert!(state.apply_call_state(Active));
+        assert!(state.apply_call_state(Destroy));
+        assert!(!state.is_in_call);
+    }
+
+    #[test]
+    fn test_late_updates_do_not_move_call_backwards() {
+        let mut state = WebRTCState::default();
+
+        // Dialer marks the call as trying before the SDK reports "new"
+        assert_eq!(apply_all(&mut state, &["trying", "new", "requesting"]), vec![true, false, false]);
+        assert_eq!(state.call_state, Some(Trying));
+
+        assert!(state.apply_call_state(Active));
+        assert!(!state.apply_call_state(Ringing));
+        assert!(!state.apply_call_state(Unknown("recovering".to_string())));
+        assert_eq!(state.call_state, Some(Active));
+    }
+
+    #[test]
+    fn test_idle_ignores_non_starting_states() {
+        let mut state = WebRTCState::default();
+        assert_eq!(apply_all(&mut state, &["active", "held", "hangup", "bogus"]), vec![false; 4]);
+        assert_eq!(state.call_state, None);
+        assert!(!state.is_in_call);
+    }
+
+    #[test]
+    fn test_hold_only_from_active() {
+        assert!(Active.can_transition_to(&Held));
+        assert!(Held.can_transition_to(&Active));
+        assert!(!Ringing.can_transition_to(&Held));
+        assert!(!Hangup.can_transition_to(&Active));
+        assert!(Ringing.can_transition_to(&Hangup));
+    }
 }